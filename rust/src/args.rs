@@ -1,6 +1,7 @@
 //! Command-line argument parsing for pg_dbms_job.
 
 use crate::constants::PROGRAM;
+use crate::model::JobFilter;
 use std::env;
 
 #[derive(Default)]
@@ -18,16 +19,88 @@ pub struct Args {
     pub abort: bool,
     /// Send SIGHUP to reload configuration.
     pub reload: bool,
+    /// Send SIGUSR1 to collect due jobs right away.
+    pub poll_now: bool,
+    /// Send SIGUSR2 to dump the buffered DEBUG messages to the log.
+    pub dump_debug: bool,
     /// Run a single loop without daemonizing.
     pub single: bool,
     /// Show version and exit.
     pub version: bool,
+    /// Check that the local daemon is alive and exit.
+    pub healthcheck: bool,
+    /// Validate the configuration file and exit.
+    pub check: bool,
+    /// With `--check`, also connect to the database.
+    pub connect: bool,
+    /// Print the effective settings with their source and exit.
+    pub show_config: bool,
+    /// Print the projected firing times of this job and exit.
+    pub simulate: Option<i64>,
+    /// Interval expression to simulate instead of the job's own.
+    pub simulate_interval: Option<String>,
+    /// First execution date of the simulation.
+    pub simulate_from: Option<String>,
+    /// Last date of the simulation.
+    pub simulate_until: Option<String>,
+    /// Maximum number of simulated runs.
+    pub simulate_runs: Option<usize>,
+    /// Print the scheduled job definitions as JSON or YAML and exit.
+    pub dump_jobs: bool,
+    /// Load the scheduled job definitions of this JSON or YAML file and exit.
+    pub load_jobs: Option<String>,
+    /// With `--dump-jobs` and `--load-jobs`, `json` or `yaml`.
+    pub format: Option<String>,
+    /// With `--load-jobs`, remove the jobs the file does not list.
+    pub prune: bool,
+    /// With `--load-jobs`, only print the changes.
+    pub diff: bool,
+    /// Create the `dbms_job` schema and exit.
+    pub install_schema: bool,
+    /// Bring the `dbms_job` schema to this version and exit.
+    pub upgrade_schema: bool,
+    /// Job queue schema of the commands above, the first of `schemas` when
+    /// not given.
+    pub schema: Option<String>,
+    /// `--owner` values, comma-separated owner lists.
+    pub owners: Vec<String>,
+    /// `--job` values, comma-separated job id lists.
+    pub jobs: Vec<String>,
+}
+
+impl Args {
+    /// Jobs a `--single` run is restricted to.
+    ///
+    /// An invalid job id is an error rather than ignored: dropping it could
+    /// turn a scoped run into one claiming every due job.
+    pub fn job_filter(&self) -> Result<JobFilter, String> {
+        if !self.single && (!self.owners.is_empty() || !self.jobs.is_empty()) {
+            return Err("--owner and --job can only be used with --single".to_string());
+        }
+        let owners = split_list(&self.owners).map(str::to_string).collect();
+        let jobs = split_list(&self.jobs)
+            .map(|id| {
+                id.parse::<i64>()
+                    .map_err(|_| format!("invalid job id \"{id}\" given to --job"))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(JobFilter { owners, jobs })
+    }
+}
+
+/// Elements of comma-separated option values, trimmed, empty ones dropped.
+fn split_list(values: &[String]) -> impl Iterator<Item = &str> {
+    values
+        .iter()
+        .flat_map(|v| v.split(','))
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
 }
 
 /// Print usage text for the binary.
 pub fn usage(config_file: &str) {
     println!(
-        "usage: {PROGRAM} [options]\n\noptions:\n\n  -c, --config  file  configuration file. Default: {config_file}\n  -d, --debug         run in debug mode.\n  -k, --kill          stop current running daemon gracefully waiting\n                      for all job completion.\n  -m, --immediate     stop running daemon and jobs immediatly.\n  -r, --reload        reload configuration file and jobs definition.\n  -s, --single        do not detach and run in single loop mode and exit.\n"
+        "usage: {PROGRAM} [options]\n\noptions:\n\n  -c, --config  file  configuration file. Default: {config_file}\n  -d, --debug         run in debug mode.\n  -k, --kill          stop current running daemon gracefully waiting\n                      for all job completion.\n  -m, --immediate     stop running daemon and jobs immediatly.\n  -r, --reload        reload configuration file and jobs definition.\n  -s, --single        do not detach and run in single loop mode and exit.\n\n  --poll-now          make the running daemon collect due jobs right away.\n  --dump-debug        make the running daemon log its buffered DEBUG messages.\n  --healthcheck       exit 0 if the daemon is alive and its heartbeat is fresh.\n  --check             validate the configuration file, exit 1 on any problem.\n  --connect           with --check, also connect and check the schema.\n  --show-config       print the effective settings and where they come from.\n\n  --simulate job      print the next execution dates of a job and exit.\n  --interval expr     with --simulate, use this interval instead of the job's.\n  --from timestamp    with --simulate, first execution date. Default: next_date.\n  --until timestamp   with --simulate, stop after this date.\n  --runs count        with --simulate, number of dates to print. Default: 10.\n\n  --dump-jobs         print the scheduled job definitions and exit.\n  --load-jobs file    add, change and remove scheduled jobs to match a file\n                      written by --dump-jobs.\n  --format json|yaml  with --dump-jobs or --load-jobs, file format. Default:\n                      yaml for a .yaml or .yml file, json otherwise.\n  --prune             with --load-jobs, remove the jobs the file does not list.\n  --diff              with --load-jobs, print the changes without applying them.\n\n  --install-schema    create the dbms_job schema of this version and exit.\n  --upgrade-schema    upgrade the dbms_job schema to this version and exit.\n  --schema name       job queue schema of the commands above. Default: the\n                      first one of the schemas setting.\n\n  --owner user[,...]  with --single, only run the jobs of these owners.\n  --job id[,...]      with --single, only run these jobs.\n"
     );
 }
 
@@ -54,8 +127,29 @@ fn parse_args_from(argv: &[String]) -> Args {
             "-k" | "--kill" => args.kill = true,
             "-m" | "--immediate" => args.abort = true,
             "-r" | "--reload" => args.reload = true,
+            "--poll-now" => args.poll_now = true,
+            "--dump-debug" => args.dump_debug = true,
             "-s" | "--single" => args.single = true,
             "-v" | "--version" => args.version = true,
+            "--healthcheck" => args.healthcheck = true,
+            "--check" => args.check = true,
+            "--connect" => args.connect = true,
+            "--show-config" => args.show_config = true,
+            "--simulate" => args.simulate = iter.next().and_then(|v| v.parse().ok()),
+            "--interval" => args.simulate_interval = iter.next().cloned(),
+            "--from" => args.simulate_from = iter.next().cloned(),
+            "--until" => args.simulate_until = iter.next().cloned(),
+            "--runs" => args.simulate_runs = iter.next().and_then(|v| v.parse().ok()),
+            "--dump-jobs" => args.dump_jobs = true,
+            "--load-jobs" => args.load_jobs = iter.next().cloned(),
+            "--format" => args.format = iter.next().cloned(),
+            "--install-schema" => args.install_schema = true,
+            "--upgrade-schema" => args.upgrade_schema = true,
+            "--schema" => args.schema = iter.next().cloned(),
+            "--prune" => args.prune = true,
+            "--diff" => args.diff = true,
+            "--owner" => args.owners.extend(iter.next().cloned()),
+            "--job" => args.jobs.extend(iter.next().cloned()),
             _ => {}
         }
     }
@@ -65,6 +159,11 @@ fn parse_args_from(argv: &[String]) -> Args {
 #[cfg(test)]
 mod tests {
     use super::parse_args_from;
+    use crate::model::JobFilter;
+
+    fn argv(args: &[&str]) -> Vec<String> {
+        args.iter().map(|s| s.to_string()).collect()
+    }
 
     #[test]
     fn parse_args_from_sets_flags() {
@@ -127,6 +226,12 @@ mod tests {
         let argv = vec!["--reload".to_string()];
         assert!(parse_args_from(&argv).reload);
 
+        let argv = vec!["--poll-now".to_string()];
+        assert!(parse_args_from(&argv).poll_now);
+
+        let argv = vec!["--dump-debug".to_string()];
+        assert!(parse_args_from(&argv).dump_debug);
+
         let argv = vec!["-v".to_string()];
         assert!(parse_args_from(&argv).version);
 
@@ -138,6 +243,89 @@ mod tests {
 
         let argv = vec!["--help".to_string()];
         assert!(parse_args_from(&argv).help);
+
+        let argv = vec!["--healthcheck".to_string()];
+        assert!(parse_args_from(&argv).healthcheck);
+
+        let argv = vec!["--check".to_string(), "--connect".to_string()];
+        let args = parse_args_from(&argv);
+        assert!(args.check && args.connect && !args.healthcheck);
+
+        let argv = vec!["--show-config".to_string()];
+        assert!(parse_args_from(&argv).show_config);
+    }
+
+    #[test]
+    fn parse_args_job_file_options() {
+        let args = parse_args_from(&argv(&["--load-jobs", "jobs.json", "--prune", "--diff"]));
+        assert_eq!(args.load_jobs.as_deref(), Some("jobs.json"));
+        assert_eq!(args.format, None);
+        assert!(args.prune && args.diff && !args.dump_jobs);
+        let args = parse_args_from(&argv(&["--dump-jobs", "--format", "yaml"]));
+        assert!(args.dump_jobs);
+        assert_eq!(args.format.as_deref(), Some("yaml"));
+        assert!(parse_args_from(&argv(&["--install-schema"])).install_schema);
+        assert!(parse_args_from(&argv(&["--upgrade-schema"])).upgrade_schema);
+        let args = parse_args_from(&argv(&["--install-schema", "--schema", "tenant_a"]));
+        assert_eq!(args.schema.as_deref(), Some("tenant_a"));
+    }
+
+    #[test]
+    fn parse_args_simulate_options() {
+        let argv: Vec<String> = [
+            "--simulate",
+            "42",
+            "--interval",
+            "now() + interval '1 day'",
+            "--from",
+            "2026-01-01 00:00",
+            "--until",
+            "2026-02-01",
+            "--runs",
+            "5",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+        let args = parse_args_from(&argv);
+        assert_eq!(args.simulate, Some(42));
+        assert_eq!(
+            args.simulate_interval.as_deref(),
+            Some("now() + interval '1 day'")
+        );
+        assert_eq!(args.simulate_from.as_deref(), Some("2026-01-01 00:00"));
+        assert_eq!(args.simulate_until.as_deref(), Some("2026-02-01"));
+        assert_eq!(args.simulate_runs, Some(5));
+    }
+
+    #[test]
+    fn parse_args_job_filter() {
+        let args = parse_args_from(&argv(&[
+            "--single", "--owner", "app_user", "--job", "42, 43", "--job", "44",
+        ]));
+        assert_eq!(
+            args.job_filter(),
+            Ok(JobFilter {
+                owners: vec!["app_user".to_string()],
+                jobs: vec![42, 43, 44],
+            })
+        );
+        assert_eq!(
+            parse_args_from(&argv(&["--single"])).job_filter(),
+            Ok(JobFilter::default())
+        );
+    }
+
+    #[test]
+    fn parse_args_job_filter_errors() {
+        let err = parse_args_from(&argv(&["--single", "--job", "42,x"]))
+            .job_filter()
+            .unwrap_err();
+        assert!(err.contains("\"x\""));
+        let err = parse_args_from(&argv(&["--owner", "app_user"]))
+            .job_filter()
+            .unwrap_err();
+        assert!(err.contains("--single"));
     }
 
     #[test]
@@ -0,0 +1,312 @@
+//! `--check`: validation of the configuration for deployment pipelines.
+//!
+//! The configuration file is read as the daemon reads it, includes and
+//! environment overrides included, but the lines it would log are collected
+//! instead of written: each ERROR or WARNING, an invalid value or an unknown
+//! setting, is a problem. The directories of the log and pid files must
+//! exist and be writable, the password command must succeed, the password
+//! file must be readable and closed to other users, and a `dsn` must parse.
+//! Each `[database <name>]` section is checked the same way, its problems
+//! tagged with its name. With `--connect`, the database must also accept a
+//! connection and have a compatible job queue schema, each one of
+//! `schemas`, within `HEALTHCHECK_TIMEOUT_SECS`.
+
+use crate::config::{database_sections, read_config};
+use crate::constants::HEALTHCHECK_TIMEOUT_SECS;
+use crate::db::{
+    connect_tool, describe_target, rds_iam_target, read_passwd_file, run_passwd_command,
+    tls_connector,
+};
+use crate::logging::capture_log;
+use crate::model::{AuthMethod, Config, DbInfo, LogDestination, SslMode};
+use crate::rds_iam;
+use crate::schema::check_schema;
+use crate::{default_config, default_dbinfo};
+use chrono::Local;
+use nix::unistd::{AccessFlags, access};
+use std::fs;
+use std::path::Path;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+/// Read `config_file` into `config` and `dbinfo`, returning its problems
+/// as `LEVEL: message` lines, none when it is valid.
+pub fn check_config(config_file: &str, config: &mut Config, dbinfo: &mut DbInfo) -> Vec<String> {
+    if let Err(err) = fs::read_to_string(config_file) {
+        return vec![format!(
+            "ERROR: can not read the configuration file {config_file}, {err}"
+        )];
+    }
+    let mut problems = read_problems(config_file, config, dbinfo);
+    if config.log_destination == LogDestination::File && !config.logfile.is_empty() {
+        let logfile = Local::now().format(&config.logfile).to_string();
+        problems.extend(check_directory("logfile", &logfile));
+    }
+    problems.extend(check_directory("pidfile", &config.pidfile));
+    problems.extend(connection_problems(config, dbinfo));
+    let (sections, _) = capture_log(|| database_sections(config_file, config));
+    for name in sections {
+        let (mut db_config, mut db_dbinfo) = (default_config(), default_dbinfo());
+        db_config.database_section = name.clone();
+        let db_problems = read_problems(config_file, &mut db_config, &mut db_dbinfo)
+            .into_iter()
+            .chain(connection_problems(&db_config, &db_dbinfo));
+        problems.extend(db_problems.map(|problem| {
+            let (level, msg) = problem.split_once(": ").unwrap_or(("ERROR", &problem));
+            format!("{level}: [database {name}] {msg}")
+        }));
+    }
+    problems
+}
+
+/// The ERROR and WARNING lines logged reading `config_file`, as
+/// `LEVEL: message`.
+fn read_problems(config_file: &str, config: &mut Config, dbinfo: &mut DbInfo) -> Vec<String> {
+    let ((), lines) = capture_log(|| read_config(config_file, config, dbinfo, true));
+    lines
+        .into_iter()
+        .filter(|(level, _)| matches!(level.as_str(), "WARNING" | "ERROR" | "FATAL"))
+        .map(|(level, msg)| format!("{level}: {msg}"))
+        .collect()
+}
+
+/// Problems of the connection settings: a failing password command, an
+/// unusable password file, `dsn`, TLS certificate or RDS IAM setup.
+fn connection_problems(config: &Config, dbinfo: &DbInfo) -> Vec<String> {
+    let mut problems = Vec::new();
+    if dbinfo.auth_method == AuthMethod::RdsIam {
+        if let Err(err) = rds_iam_target(dbinfo).and_then(|target| rds_iam::auth_token(&target)) {
+            problems.push(format!("ERROR: {err}"));
+        }
+        if dbinfo.host.contains(',') || !config.ssh_host.is_empty() {
+            problems.push(
+                "ERROR: auth_method rds_iam needs the single host of the database, not a list of hosts or ssh_host"
+                    .to_string(),
+            );
+        }
+        if dbinfo.sslmode == SslMode::Disable {
+            problems.push(
+                "ERROR: auth_method rds_iam can not be used with sslmode disable".to_string(),
+            );
+        }
+    } else if !dbinfo.passwd_command.is_empty() {
+        if let Err(err) = run_passwd_command(&dbinfo.passwd_command) {
+            problems.push(format!("ERROR: {err}"));
+        }
+    } else if !dbinfo.passwd_file.is_empty() {
+        if let Err(err) = read_passwd_file(&dbinfo.passwd_file) {
+            problems.push(format!("ERROR: {err}"));
+        }
+    }
+    if !dbinfo.dsn.is_empty() {
+        if let Err(err) = dbinfo.dsn.parse::<postgres::Config>() {
+            problems.push(format!("ERROR: invalid dsn, {err}"));
+        }
+        if !config.ssh_host.is_empty() {
+            problems.push("ERROR: ssh_host can not be used with dsn".to_string());
+        }
+    }
+    if dbinfo.host.contains(',') && !config.ssh_host.is_empty() {
+        problems.push("ERROR: ssh_host can not be used with a list of hosts".to_string());
+    }
+    if let Err(err) = tls_connector(dbinfo) {
+        problems.push(format!("ERROR: {err}"));
+    }
+    if dbinfo.sslmode == SslMode::VerifyFull && !config.ssh_host.is_empty() {
+        problems.push(
+            "ERROR: sslmode verify-full can not be used with ssh_host, use verify-ca".to_string(),
+        );
+    }
+    problems
+}
+
+/// Problem with the directory of the file `path` of `setting`: it must
+/// exist and be writable for the daemon to create the file.
+fn check_directory(setting: &str, path: &str) -> Option<String> {
+    let dir = match Path::new(path).parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    if !dir.is_dir() {
+        return Some(format!(
+            "ERROR: directory {} of {setting} {path} does not exist",
+            dir.display()
+        ));
+    }
+    access(dir, AccessFlags::W_OK).err().map(|err| {
+        format!(
+            "ERROR: directory {} of {setting} {path} is not writable, {err}",
+            dir.display()
+        )
+    })
+}
+
+/// Connect to the database and check its job queue schema `schema`.
+/// Returns a one-line status, the reason of the failure as the error.
+pub fn check_database(dbinfo: &DbInfo, schema: &str) -> Result<String, String> {
+    let (tx, rx) = mpsc::channel();
+    let conn = dbinfo.clone();
+    let name = schema.to_string();
+    // On timeout the thread is abandoned; the process exits right after.
+    thread::spawn(move || {
+        let _ = tx.send(
+            connect_tool(&conn, "pg_dbms_job:check")
+                .and_then(|mut client| check_schema(&mut client, &name)),
+        );
+    });
+    let version = rx
+        .recv_timeout(Duration::from_secs(HEALTHCHECK_TIMEOUT_SECS))
+        .map_err(|_| {
+            format!("no answer from the database within {HEALTHCHECK_TIMEOUT_SECS} seconds")
+        })??;
+    let schema = match version {
+        Some(version) => format!("{schema} schema version {version}"),
+        None => format!("{schema} schema"),
+    };
+    Ok(format!(
+        "connected to {}, {schema} is compatible",
+        describe_target(dbinfo)
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_config, check_directory};
+    use crate::{default_config, default_dbinfo};
+    use std::env;
+    use std::fs;
+
+    fn temp_path(name: &str) -> String {
+        env::temp_dir()
+            .join(format!("pg_dbms_job_check_{}_{name}", std::process::id()))
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    #[test]
+    fn valid_configuration_has_no_problem() {
+        let path = temp_path("valid.conf");
+        let pidfile = temp_path("valid.pid");
+        fs::write(
+            &path,
+            format!("pidfile={pidfile}\nlogfile=\nnap_time=2\nhost=db\n"),
+        )
+        .unwrap();
+        let (mut config, mut dbinfo) = (default_config(), default_dbinfo());
+        assert!(check_config(&path, &mut config, &mut dbinfo).is_empty());
+        assert_eq!(dbinfo.host, "db");
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn tls_files_must_be_readable_and_verify_full_excludes_ssh_host() {
+        let path = temp_path("tls.conf");
+        let pidfile = temp_path("tls.pid");
+        fs::write(
+            &path,
+            format!(
+                "pidfile={pidfile}\nlogfile=\nsslmode=verify-full\nssh_host=bastion\n\
+                 sslrootcert=/nonexistent/root.crt\n"
+            ),
+        )
+        .unwrap();
+        let (mut config, mut dbinfo) = (default_config(), default_dbinfo());
+        let problems = check_config(&path, &mut config, &mut dbinfo);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(problems.len(), 2, "{problems:?}");
+        assert!(problems[0].starts_with("ERROR: can not read sslrootcert /nonexistent/root.crt"));
+        assert_eq!(
+            problems[1],
+            "ERROR: sslmode verify-full can not be used with ssh_host, use verify-ca"
+        );
+    }
+
+    #[test]
+    fn dsn_must_parse_and_excludes_ssh_host() {
+        let path = temp_path("dsn.conf");
+        let pidfile = temp_path("dsn.pid");
+        fs::write(
+            &path,
+            format!(
+                "pidfile={pidfile}\nlogfile=\ndsn=postgresql://u@db:5432/app?sslmode=require\n"
+            ),
+        )
+        .unwrap();
+        let (mut config, mut dbinfo) = (default_config(), default_dbinfo());
+        assert!(check_config(&path, &mut config, &mut dbinfo).is_empty());
+
+        fs::write(
+            &path,
+            format!(
+                "pidfile={pidfile}\nlogfile=\ndsn=postgresql://u@db:port/app\nssh_host=bastion\n"
+            ),
+        )
+        .unwrap();
+        let problems = check_config(&path, &mut config, &mut dbinfo);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(problems.len(), 2, "{problems:?}");
+        assert!(problems[0].starts_with("ERROR: invalid dsn"));
+        assert_eq!(problems[1], "ERROR: ssh_host can not be used with dsn");
+    }
+
+    #[test]
+    fn database_sections_are_checked_too() {
+        let path = temp_path("sections.conf");
+        let pidfile = temp_path("sections.pid");
+        fs::write(
+            &path,
+            format!(
+                "pidfile={pidfile}\nlogfile=\n[database sales]\ndatabase=sales\n\
+                 [database hr]\ndsn=postgresql://u@db:port/hr\npool_sise=2\n[other]\nx=1\n"
+            ),
+        )
+        .unwrap();
+        let (mut config, mut dbinfo) = (default_config(), default_dbinfo());
+        let problems = check_config(&path, &mut config, &mut dbinfo);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(problems.len(), 3, "{problems:?}");
+        assert_eq!(
+            problems[0],
+            "WARNING: Unknown section [other] in configuration file, ignored"
+        );
+        assert_eq!(
+            problems[1],
+            "WARNING: [database hr] Unknown setting pool_sise in configuration file, ignored"
+        );
+        assert!(problems[2].starts_with("ERROR: [database hr] invalid dsn"));
+    }
+
+    #[test]
+    fn invalid_values_unknown_settings_and_directories_are_reported() {
+        let path = temp_path("invalid.conf");
+        fs::write(
+            &path,
+            "pidfile=/nonexistent/pg_dbms_job/run.pid\nlogfile=\nnap_time=soon\nnap_tme=2\n",
+        )
+        .unwrap();
+        let (mut config, mut dbinfo) = (default_config(), default_dbinfo());
+        let problems = check_config(&path, &mut config, &mut dbinfo);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(problems.len(), 3, "{problems:?}");
+        assert!(problems[0].starts_with("ERROR: Invalid nap_time value soon"));
+        assert_eq!(
+            problems[1],
+            "WARNING: Unknown setting nap_tme in configuration file, ignored"
+        );
+        assert_eq!(
+            problems[2],
+            "ERROR: directory /nonexistent/pg_dbms_job of pidfile \
+             /nonexistent/pg_dbms_job/run.pid does not exist"
+        );
+
+        let problems = check_config(&path, &mut config, &mut dbinfo);
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].starts_with("ERROR: can not read the configuration file"));
+    }
+
+    #[test]
+    fn relative_paths_are_checked_in_the_current_directory() {
+        assert_eq!(check_directory("pidfile", "run.pid"), None);
+    }
+}
@@ -1,10 +1,23 @@
 //! Configuration file parsing and application.
 
+use crate::constants::{
+    DATABASE_SECTION, ENV_PREFIX, JOB_CLASS_PREFIX, LIBPQ_ENV, MAX_INCLUDE_DEPTH, SCHEMA_NAME_MAX,
+    SEMAPHORE_PREFIX,
+};
 use crate::dlog;
-use crate::logging::dprint;
-use crate::model::{Config, DbInfo, JobRunDetails};
+use crate::gelf::Endpoint;
+use crate::logging::{dprint, group_id, user_id};
+use crate::model::{
+    AuthMethod, ClassPriority, Config, DbInfo, DstPolicy, ExecutionMode, IoClass, JobRunDetails,
+    LogDestination, LogMinMessages, LogRotationAge, LogRotationCompress, SslMode,
+    TargetSessionAttrs,
+};
+use crate::syslog::facility_code;
 use crate::util::die;
+use std::collections::BTreeMap;
+use std::env;
 use std::fs;
+use std::path::{Path, PathBuf};
 
 /// Read and apply configuration from a file path.
 ///
@@ -26,253 +39,1901 @@ pub fn read_config(config_file: &str, config: &mut Config, dbinfo: &mut DbInfo,
         }
     }
     let content = content.unwrap();
+    let file_settings = expand_includes(Path::new(config_file), &content, config, "", 0);
+    if config.database_section.is_empty() {
+        for header in section_headers(&file_settings) {
+            if !header.starts_with(DATABASE_SECTION) {
+                dlog!(
+                    config,
+                    "WARNING",
+                    "Unknown section [{header}] in configuration file, ignored"
+                );
+            }
+        }
+    }
+    let file_settings = select_section(file_settings, &config.database_section);
+    let (settings, overrides) = merge_env_settings(file_settings, env::vars(), config, dbinfo);
+    let known: Vec<&str> = config_settings(config, dbinfo)
+        .into_iter()
+        .map(|(name, _)| name)
+        .filter(|name| slot_prefix(name).is_none())
+        .collect();
 
-    // Load logfile first so subsequent logs go to the configured location.
-    for line in content.lines() {
-        if let Some((var, val)) = parse_config_line(line)
-            && var == "logfile"
-            && config.logfile != val
-        {
-            config.logfile = val;
-            dlog!(
-                config,
-                "LOG",
-                "Setting logfile from configuration file to {}",
-                config.logfile
-            );
+    // With strict_config, nothing of a file with an unknown setting is
+    // applied.
+    let strict = settings
+        .iter()
+        .rev()
+        .find(|(var, _)| var == "strict_config")
+        .map_or(config.strict_config, |(_, val)| {
+            val.parse::<i32>().unwrap_or(0) != 0
+        });
+    let unknown: Vec<&str> = settings
+        .iter()
+        .map(|(var, _)| var.as_str())
+        .filter(|var| {
+            !known.contains(var)
+                && !SLOT_SETTINGS
+                    .iter()
+                    .any(|(_, prefix)| var.starts_with(prefix))
+        })
+        .collect();
+    if strict && !unknown.is_empty() {
+        let msg = format!(
+            "Unknown setting {} in configuration file with strict_config",
+            unknown.join(", ")
+        );
+        if !nodie {
+            dlog!(config, "FATAL", "{msg}");
+            die(&format!("FATAL: {msg}"));
+        }
+        dlog!(config, "ERROR", "{msg}, configuration not applied");
+        return;
+    }
+
+    // Load logfile first so subsequent logs go to the configured location,
+    // created with the configured access.
+    for (var, val) in &settings {
+        match var.as_str() {
+            "logfile" if config.logfile != *val => {
+                config.logfile = val.clone();
+                dlog!(
+                    config,
+                    "LOG",
+                    "Setting logfile from configuration file to {}",
+                    config.logfile
+                );
+            }
+            "logfile_mode" => match u32::from_str_radix(val, 8) {
+                Ok(v) if v <= 0o7777 => {
+                    if config.logfile_mode != v {
+                        config.logfile_mode = v;
+                        dlog!(
+                            config,
+                            "LOG",
+                            "Setting logfile_mode from configuration file to {:04o}",
+                            config.logfile_mode
+                        );
+                    }
+                }
+                _ => {
+                    dlog!(
+                        config,
+                        "ERROR",
+                        "Invalid logfile_mode value {} in configuration file, must be an octal mode such as 0640 (0 leaves it to the umask). Ignoring. Actual value remains {:04o}",
+                        val,
+                        config.logfile_mode
+                    );
+                }
+            },
+            "logfile_owner" if config.logfile_owner != *val => {
+                if val.is_empty() || user_id(val).is_some() {
+                    config.logfile_owner = val.clone();
+                    dlog!(
+                        config,
+                        "LOG",
+                        "Setting logfile_owner from configuration file to {}",
+                        config.logfile_owner
+                    );
+                } else {
+                    dlog!(
+                        config,
+                        "ERROR",
+                        "Invalid logfile_owner value {} in configuration file, no such user. Ignoring. Actual value remains {}",
+                        val,
+                        config.logfile_owner
+                    );
+                }
+            }
+            "logfile_group" if config.logfile_group != *val => {
+                if val.is_empty() || group_id(val).is_some() {
+                    config.logfile_group = val.clone();
+                    dlog!(
+                        config,
+                        "LOG",
+                        "Setting logfile_group from configuration file to {}",
+                        config.logfile_group
+                    );
+                } else {
+                    dlog!(
+                        config,
+                        "ERROR",
+                        "Invalid logfile_group value {} in configuration file, no such group. Ignoring. Actual value remains {}",
+                        val,
+                        config.logfile_group
+                    );
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Only at startup: the environment does not change on reload.
+    if !nodie {
+        for name in &overrides {
+            dlog!(config, "LOG", "Using environment variable {name}");
         }
     }
 
+    // Rebuilt from scratch so that a semaphore or job class removed from the
+    // file is dropped on reload.
+    let mut semaphores = BTreeMap::new();
+    let mut job_classes = BTreeMap::new();
+    let mut job_class_priorities = BTreeMap::new();
+
     // Apply remaining settings and database connection information.
-    for line in content.lines() {
-        if let Some((var, val)) = parse_config_line(line) {
-            match var.as_str() {
-                "pidfile" if config.pidfile != val => {
-                    config.pidfile = val;
+    for (var, val) in settings {
+        match var.as_str() {
+            "pidfile" if config.pidfile != val => {
+                config.pidfile = val;
+                dlog!(
+                    config,
+                    "LOG",
+                    "Setting pidfile from configuration file to {}",
+                    config.pidfile
+                );
+            }
+            "debug_ring_size" => match val.parse::<usize>() {
+                Ok(v) => {
+                    if config.debug_ring_size != v {
+                        config.debug_ring_size = v;
+                        dlog!(
+                            config,
+                            "LOG",
+                            "Setting debug_ring_size from configuration file to {}",
+                            config.debug_ring_size
+                        );
+                    }
+                }
+                Err(_) => {
+                    dlog!(
+                        config,
+                        "ERROR",
+                        "Invalid debug_ring_size value {} in configuration file, must be a non-negative integer. Ignoring. Actual value remains {}",
+                        val,
+                        config.debug_ring_size
+                    );
+                }
+            },
+            "log_min_messages" => match LogMinMessages::parse(&val) {
+                Some(v) => {
+                    if config.log_min_messages != v {
+                        config.log_min_messages = v;
+                        dlog!(
+                            config,
+                            "LOG",
+                            "Setting log_min_messages from configuration file to {}",
+                            config.log_min_messages.as_str()
+                        );
+                    }
+                }
+                None => {
+                    dlog!(
+                        config,
+                        "ERROR",
+                        "Invalid log_min_messages value {} in configuration file, must be one of debug|log|warning|error. Ignoring. Actual value remains {}",
+                        val,
+                        config.log_min_messages.as_str()
+                    );
+                }
+            },
+            "debug" => {
+                let debug_val = val.parse::<i32>().unwrap_or(0) != 0;
+                if config.debug != debug_val {
+                    config.debug = debug_val;
                     dlog!(
                         config,
                         "LOG",
-                        "Setting pidfile from configuration file to {}",
-                        config.pidfile
+                        "Setting debug from configuration file to {}",
+                        config.debug as i32
                     );
                 }
-                "debug" => {
-                    let debug_val = val.parse::<i32>().unwrap_or(0) != 0;
-                    if config.debug != debug_val {
-                        config.debug = debug_val;
+            }
+            "job_queue_interval" => {
+                apply_positive_float(config, "job_queue_interval", &val, |c| {
+                    &mut c.job_queue_interval
+                });
+            }
+            "job_queue_processes" => {
+                if let Ok(v) = val.parse::<isize>() {
+                    // Number of processes must be positive
+                    if v > 0 {
+                        config.job_queue_processes =
+                            v.try_into().unwrap_or(config.job_queue_processes);
+                        dlog!(
+                            config,
+                            "LOG",
+                            "Setting job_queue_processes from configuration file to {}",
+                            config.job_queue_processes
+                        );
+                    } else {
+                        dlog!(
+                            config,
+                            "ERROR",
+                            "Invalid job_queue_processes value {} in configuration file, must be positive. Ignoring. Actual value remains {}",
+                            val,
+                            config.job_queue_processes
+                        );
+                    }
+                }
+            }
+            "pool_size" => {
+                if let Ok(v) = val.parse::<isize>() {
+                    if v > 0 {
+                        config.pool_size = v.try_into().unwrap_or(config.pool_size);
+                        dlog!(
+                            config,
+                            "LOG",
+                            "Setting pool_size from configuration file to {}",
+                            config.pool_size
+                        );
+                    } else {
+                        dlog!(
+                            config,
+                            "ERROR",
+                            "Invalid pool_size value {} in configuration file, must be positive. Ignoring. Actual value remains {}",
+                            val,
+                            config.pool_size
+                        );
+                    }
+                }
+            }
+            "max_notifications_per_cycle" => {
+                if let Ok(v) = val.parse::<isize>() {
+                    if v > 0 {
+                        config.max_notifications_per_cycle =
+                            v.try_into().unwrap_or(config.max_notifications_per_cycle);
                         dlog!(
                             config,
                             "LOG",
-                            "Setting debug from configuration file to {}",
-                            config.debug as i32
+                            "Setting max_notifications_per_cycle from configuration file to {}",
+                            config.max_notifications_per_cycle
+                        );
+                    } else {
+                        dlog!(
+                            config,
+                            "ERROR",
+                            "Invalid max_notifications_per_cycle value {} in configuration file, must be positive. Ignoring. Actual value remains {}",
+                            val,
+                            config.max_notifications_per_cycle
                         );
                     }
                 }
-                "job_queue_interval" => {
-                    apply_positive_float(config, "job_queue_interval", &val, |c| {
-                        &mut c.job_queue_interval
-                    });
-                }
-                "job_queue_processes" => {
-                    if let Ok(v) = val.parse::<isize>() {
-                        // Number of processes must be positive
-                        if v > 0 {
-                            config.job_queue_processes =
-                                v.try_into().unwrap_or(config.job_queue_processes);
-                            dlog!(
-                                config,
-                                "LOG",
-                                "Setting job_queue_processes from configuration file to {}",
-                                config.job_queue_processes
-                            );
-                        } else {
-                            dlog!(
-                                config,
-                                "ERROR",
-                                "Invalid job_queue_processes value {} in configuration file, must be positive. Ignoring. Actual value remains {}",
-                                val,
-                                config.job_queue_processes
-                            );
-                        }
+            }
+            "hard_max_run_overruns" => {
+                if let Ok(v) = val.parse::<isize>() {
+                    if v > 0 {
+                        config.hard_max_run_overruns =
+                            v.try_into().unwrap_or(config.hard_max_run_overruns);
+                        dlog!(
+                            config,
+                            "LOG",
+                            "Setting hard_max_run_overruns from configuration file to {}",
+                            config.hard_max_run_overruns
+                        );
+                    } else {
+                        dlog!(
+                            config,
+                            "ERROR",
+                            "Invalid hard_max_run_overruns value {} in configuration file, must be positive. Ignoring. Actual value remains {}",
+                            val,
+                            config.hard_max_run_overruns
+                        );
                     }
                 }
-                "pool_size" => {
-                    if let Ok(v) = val.parse::<isize>() {
-                        if v > 0 {
-                            config.pool_size = v.try_into().unwrap_or(config.pool_size);
-                            dlog!(
-                                config,
-                                "LOG",
-                                "Setting pool_size from configuration file to {}",
-                                config.pool_size
-                            );
-                        } else {
-                            dlog!(
-                                config,
-                                "ERROR",
-                                "Invalid pool_size value {} in configuration file, must be positive. Ignoring. Actual value remains {}",
-                                val,
-                                config.pool_size
-                            );
-                        }
+            }
+            "nap_time" => {
+                apply_positive_float(config, "nap_time", &val, |c| &mut c.nap_time);
+            }
+            "schedule_refresh_interval" => {
+                apply_positive_float(config, "schedule_refresh_interval", &val, |c| {
+                    &mut c.schedule_refresh_interval
+                });
+            }
+            "startup_delay" => {
+                apply_positive_float(config, "startup_delay", &val, |c| &mut c.startup_delay);
+            }
+            "error_delay" => {
+                apply_positive_float(config, "error_delay", &val, |c| &mut c.error_delay);
+            }
+            "host" => {
+                dbinfo.host = val;
+                dlog!(
+                    config,
+                    "LOG",
+                    "Setting host from configuration file to {}",
+                    dbinfo.host
+                );
+            }
+            "database" => {
+                dbinfo.database = val;
+                dlog!(
+                    config,
+                    "LOG",
+                    "Setting database from configuration file to {}",
+                    dbinfo.database
+                );
+            }
+            "user" => {
+                dbinfo.user = val;
+                dlog!(
+                    config,
+                    "LOG",
+                    "Setting user from configuration file to {}",
+                    dbinfo.user
+                );
+            }
+            "passwd" => {
+                dbinfo.passwd = val;
+                dprint(
+                    config,
+                    "LOG",
+                    "Setting passwd from configuration file to ****",
+                );
+            }
+            "passwd_file" if dbinfo.passwd_file != val => {
+                dbinfo.passwd_file = val;
+                dlog!(
+                    config,
+                    "LOG",
+                    "Setting passwd_file from configuration file to {}",
+                    dbinfo.passwd_file
+                );
+            }
+            "passwd_command" if dbinfo.passwd_command != val => {
+                dbinfo.passwd_command = val;
+                dlog!(
+                    config,
+                    "LOG",
+                    "Setting passwd_command from configuration file to {}",
+                    dbinfo.passwd_command
+                );
+            }
+            "auth_method" => match AuthMethod::parse(&val) {
+                Some(v) => {
+                    if dbinfo.auth_method != v {
+                        dbinfo.auth_method = v;
+                        dlog!(
+                            config,
+                            "LOG",
+                            "Setting auth_method from configuration file to {}",
+                            dbinfo.auth_method.as_str()
+                        );
                     }
                 }
-                "nap_time" => {
-                    apply_positive_float(config, "nap_time", &val, |c| &mut c.nap_time);
+                None => {
+                    dlog!(
+                        config,
+                        "ERROR",
+                        "Invalid auth_method value {} in configuration file, must be one of password|rds_iam. Ignoring. Actual value remains {}",
+                        val,
+                        dbinfo.auth_method.as_str()
+                    );
+                }
+            },
+            "aws_region" if dbinfo.aws_region != val => {
+                dbinfo.aws_region = val;
+                dlog!(
+                    config,
+                    "LOG",
+                    "Setting aws_region from configuration file to {}",
+                    dbinfo.aws_region
+                );
+            }
+            "dsn" => {
+                dbinfo.dsn = val;
+                dprint(config, "LOG", "Setting dsn from configuration file to ****");
+            }
+            "port" => {
+                if let Ok(v) = val.parse::<u16>() {
+                    if v > 0 {
+                        dbinfo.port = v;
+                        dlog!(
+                            config,
+                            "LOG",
+                            "Setting port from configuration file to {}",
+                            dbinfo.port
+                        );
+                    } else {
+                        dlog!(
+                            config,
+                            "ERROR",
+                            "Invalid port value {} in configuration file, must be a positive integer. Ignoring. Actual value remains {}",
+                            val,
+                            dbinfo.port
+                        );
+                    }
                 }
-                "startup_delay" => {
-                    apply_positive_float(config, "startup_delay", &val, |c| &mut c.startup_delay);
+            }
+            "connect_timeout" => match val.parse::<u32>() {
+                Ok(v) => {
+                    if dbinfo.connect_timeout != v {
+                        dbinfo.connect_timeout = v;
+                        dlog!(
+                            config,
+                            "LOG",
+                            "Setting connect_timeout from configuration file to {}",
+                            dbinfo.connect_timeout
+                        );
+                    }
                 }
-                "error_delay" => {
-                    apply_positive_float(config, "error_delay", &val, |c| &mut c.error_delay);
+                Err(_) => {
+                    dlog!(
+                        config,
+                        "ERROR",
+                        "Invalid connect_timeout value {} in configuration file, must be a non-negative integer (0 waits forever). Ignoring. Actual value remains {}",
+                        val,
+                        dbinfo.connect_timeout
+                    );
                 }
-                "host" => {
-                    dbinfo.host = val;
+            },
+            "keepalives" => {
+                let keepalives = val.parse::<i32>().unwrap_or(0) != 0;
+                if dbinfo.keepalives != keepalives {
+                    dbinfo.keepalives = keepalives;
                     dlog!(
                         config,
                         "LOG",
-                        "Setting host from configuration file to {}",
-                        dbinfo.host
+                        "Setting keepalives from configuration file to {}",
+                        dbinfo.keepalives as i32
                     );
                 }
-                "database" => {
-                    dbinfo.database = val;
+            }
+            "keepalives_idle" => match val.parse::<u32>() {
+                Ok(v) => {
+                    if dbinfo.keepalives_idle != v {
+                        dbinfo.keepalives_idle = v;
+                        dlog!(
+                            config,
+                            "LOG",
+                            "Setting keepalives_idle from configuration file to {}",
+                            dbinfo.keepalives_idle
+                        );
+                    }
+                }
+                Err(_) => {
                     dlog!(
                         config,
-                        "LOG",
-                        "Setting database from configuration file to {}",
-                        dbinfo.database
+                        "ERROR",
+                        "Invalid keepalives_idle value {} in configuration file, must be a non-negative integer (0 for the default). Ignoring. Actual value remains {}",
+                        val,
+                        dbinfo.keepalives_idle
                     );
                 }
-                "user" => {
-                    dbinfo.user = val;
+            },
+            "keepalives_interval" => match val.parse::<u32>() {
+                Ok(v) => {
+                    if dbinfo.keepalives_interval != v {
+                        dbinfo.keepalives_interval = v;
+                        dlog!(
+                            config,
+                            "LOG",
+                            "Setting keepalives_interval from configuration file to {}",
+                            dbinfo.keepalives_interval
+                        );
+                    }
+                }
+                Err(_) => {
                     dlog!(
                         config,
-                        "LOG",
-                        "Setting user from configuration file to {}",
-                        dbinfo.user
+                        "ERROR",
+                        "Invalid keepalives_interval value {} in configuration file, must be a non-negative integer (0 for the default). Ignoring. Actual value remains {}",
+                        val,
+                        dbinfo.keepalives_interval
                     );
                 }
-                "passwd" => {
-                    dbinfo.passwd = val;
-                    dprint(
+            },
+            "keepalives_count" => match val.parse::<u32>() {
+                Ok(v) => {
+                    if dbinfo.keepalives_count != v {
+                        dbinfo.keepalives_count = v;
+                        dlog!(
+                            config,
+                            "LOG",
+                            "Setting keepalives_count from configuration file to {}",
+                            dbinfo.keepalives_count
+                        );
+                    }
+                }
+                Err(_) => {
+                    dlog!(
                         config,
-                        "LOG",
-                        "Setting passwd from configuration file to ****",
+                        "ERROR",
+                        "Invalid keepalives_count value {} in configuration file, must be a non-negative integer (0 for the default). Ignoring. Actual value remains {}",
+                        val,
+                        dbinfo.keepalives_count
                     );
                 }
-                "port" => {
-                    if let Ok(v) = val.parse::<u16>() {
-                        if v > 0 {
-                            dbinfo.port = v;
-                            dlog!(
-                                config,
-                                "LOG",
-                                "Setting port from configuration file to {}",
-                                dbinfo.port
-                            );
-                        } else {
-                            dlog!(
-                                config,
-                                "ERROR",
-                                "Invalid port value {} in configuration file, must be a positive integer. Ignoring. Actual value remains {}",
-                                val,
-                                dbinfo.port
-                            );
-                        }
+            },
+            "timezone" if dbinfo.timezone != val => {
+                dbinfo.timezone = val;
+                dlog!(
+                    config,
+                    "LOG",
+                    "Setting timezone from configuration file to {}",
+                    dbinfo.timezone
+                );
+            }
+            "sslmode" => match SslMode::parse(&val) {
+                Some(v) => {
+                    if dbinfo.sslmode != v {
+                        dbinfo.sslmode = v;
+                        dlog!(
+                            config,
+                            "LOG",
+                            "Setting sslmode from configuration file to {}",
+                            dbinfo.sslmode.as_str()
+                        );
                     }
                 }
-                "log_truncate_on_rotation" => {
-                    config.log_truncate_on_rotation = val.parse::<i32>().unwrap_or(0) != 0;
-                }
-                "stats_interval" => match val.parse::<u64>() {
-                    Ok(v) => {
-                        if config.stats_interval != v {
-                            config.stats_interval = v;
-                            dlog!(
-                                config,
-                                "LOG",
-                                "Setting stats_interval from configuration file to {}",
-                                config.stats_interval
-                            );
-                        }
+                None => {
+                    dlog!(
+                        config,
+                        "ERROR",
+                        "Invalid sslmode value {} in configuration file, must be one of disable|prefer|require|verify-ca|verify-full. Ignoring. Actual value remains {}",
+                        val,
+                        dbinfo.sslmode.as_str()
+                    );
+                }
+            },
+            "sslrootcert" if dbinfo.sslrootcert != val => {
+                dbinfo.sslrootcert = val;
+                dlog!(
+                    config,
+                    "LOG",
+                    "Setting sslrootcert from configuration file to {}",
+                    dbinfo.sslrootcert
+                );
+            }
+            "sslcert" if dbinfo.sslcert != val => {
+                dbinfo.sslcert = val;
+                dlog!(
+                    config,
+                    "LOG",
+                    "Setting sslcert from configuration file to {}",
+                    dbinfo.sslcert
+                );
+            }
+            "sslkey" if dbinfo.sslkey != val => {
+                dbinfo.sslkey = val;
+                dlog!(
+                    config,
+                    "LOG",
+                    "Setting sslkey from configuration file to {}",
+                    dbinfo.sslkey
+                );
+            }
+            "target_session_attrs" => match TargetSessionAttrs::parse(&val) {
+                Some(v) => {
+                    if dbinfo.target_session_attrs != v {
+                        dbinfo.target_session_attrs = v;
+                        dlog!(
+                            config,
+                            "LOG",
+                            "Setting target_session_attrs from configuration file to {}",
+                            dbinfo.target_session_attrs.as_str()
+                        );
                     }
-                    Err(_) => {
+                }
+                None => {
+                    dlog!(
+                        config,
+                        "ERROR",
+                        "Invalid target_session_attrs value {} in configuration file, must be one of any|read-write. Ignoring. Actual value remains {}",
+                        val,
+                        dbinfo.target_session_attrs.as_str()
+                    );
+                }
+            },
+            "job_session_settings" => match parse_session_settings(&val) {
+                Ok(v) => {
+                    if dbinfo.job_session_settings != v {
+                        dbinfo.job_session_settings = v;
                         dlog!(
                             config,
-                            "ERROR",
-                            "Invalid stats_interval value {} in configuration file, must be a non-negative integer. Ignoring. Actual value remains {}",
-                            val,
-                            config.stats_interval
+                            "LOG",
+                            "Setting job_session_settings from configuration file to {}",
+                            session_settings_str(&dbinfo.job_session_settings)
                         );
                     }
-                },
-                "job_run_details" => match JobRunDetails::parse(&val) {
-                    Some(v) => {
-                        if config.job_run_details != v {
-                            config.job_run_details = v;
-                            dlog!(
-                                config,
-                                "LOG",
-                                "Setting job_run_details from configuration file to {}",
-                                config.job_run_details.as_str()
-                            );
-                        }
+                }
+                Err(err) => {
+                    dlog!(
+                        config,
+                        "ERROR",
+                        "Invalid job_session_settings value {} in configuration file, {}. Ignoring. Actual value remains {}",
+                        val,
+                        err,
+                        session_settings_str(&dbinfo.job_session_settings)
+                    );
+                }
+            },
+            "log_truncate_on_rotation" => {
+                config.log_truncate_on_rotation = val.parse::<i32>().unwrap_or(0) != 0;
+            }
+            "log_compress" => {
+                config.log_compress = val.parse::<i32>().unwrap_or(0) != 0;
+            }
+            "log_retention_days" => match val.parse::<u64>() {
+                Ok(v) => {
+                    if config.log_retention_days != v {
+                        config.log_retention_days = v;
+                        dlog!(
+                            config,
+                            "LOG",
+                            "Setting log_retention_days from configuration file to {}",
+                            config.log_retention_days
+                        );
                     }
-                    None => {
+                }
+                Err(_) => {
+                    dlog!(
+                        config,
+                        "ERROR",
+                        "Invalid log_retention_days value {} in configuration file, must be a non-negative integer. Ignoring. Actual value remains {}",
+                        val,
+                        config.log_retention_days
+                    );
+                }
+            },
+            "log_rotation_size" => match parse_size(&val) {
+                Some(v) => {
+                    if config.log_rotation_size != v {
+                        config.log_rotation_size = v;
                         dlog!(
                             config,
-                            "ERROR",
-                            "Invalid job_run_details value {} in configuration file, must be one of all|errors|none. Ignoring. Actual value remains {}",
-                            val,
-                            config.job_run_details.as_str()
+                            "LOG",
+                            "Setting log_rotation_size from configuration file to {}",
+                            config.log_rotation_size
                         );
                     }
-                },
-                "stale_job_timeout" => match val.parse::<f64>() {
-                    Ok(v) if v.is_finite() && v >= 0.0 => {
-                        if config.stale_job_timeout != v {
-                            config.stale_job_timeout = v;
-                            dlog!(
-                                config,
-                                "LOG",
-                                "Setting stale_job_timeout from configuration file to {}",
-                                config.stale_job_timeout
-                            );
-                        }
+                }
+                None => {
+                    dlog!(
+                        config,
+                        "ERROR",
+                        "Invalid log_rotation_size value {} in configuration file, must be a size in bytes, optionally followed by kB, MB or GB (0 disables). Ignoring. Actual value remains {}",
+                        val,
+                        config.log_rotation_size
+                    );
+                }
+            },
+            "log_destination" => match LogDestination::parse(&val) {
+                Some(v) => {
+                    if config.log_destination != v {
+                        config.log_destination = v;
+                        dlog!(
+                            config,
+                            "LOG",
+                            "Setting log_destination from configuration file to {}",
+                            config.log_destination.as_str()
+                        );
                     }
-                    _ => {
+                }
+                None => {
+                    dlog!(
+                        config,
+                        "ERROR",
+                        "Invalid log_destination value {} in configuration file, must be one of file|syslog|journald. Ignoring. Actual value remains {}",
+                        val,
+                        config.log_destination.as_str()
+                    );
+                }
+            },
+            "syslog_facility" => match facility_code(&val) {
+                Some(_) => {
+                    let v = val.trim().to_ascii_lowercase();
+                    if config.syslog_facility != v {
+                        config.syslog_facility = v;
                         dlog!(
                             config,
-                            "ERROR",
-                            "Invalid stale_job_timeout value {} in configuration file, must be a non-negative number (0 disables). Ignoring. Actual value remains {}",
-                            val,
-                            config.stale_job_timeout
+                            "LOG",
+                            "Setting syslog_facility from configuration file to {}",
+                            config.syslog_facility
                         );
                     }
-                },
-                _ => {}
+                }
+                None => {
+                    dlog!(
+                        config,
+                        "ERROR",
+                        "Invalid syslog_facility value {} in configuration file, must be one of local0..local7|daemon|user. Ignoring. Actual value remains {}",
+                        val,
+                        config.syslog_facility
+                    );
+                }
+            },
+            "syslog_ident" if !val.is_empty() && config.syslog_ident != val => {
+                config.syslog_ident = val;
+                dlog!(
+                    config,
+                    "LOG",
+                    "Setting syslog_ident from configuration file to {}",
+                    config.syslog_ident
+                );
             }
-        }
-    }
-}
-
-/// Parse a configuration value as a finite, strictly positive `f64` and store
-/// it via `field`. On invalid input the existing field value is preserved and
-/// an error line is logged; on success a confirmation line is logged.
-///
-/// Pulled out so the four time-interval settings (job_queue_interval,
-/// nap_time, startup_delay, error_delay) share one validation path.
-fn apply_positive_float(
-    config: &mut Config,
+            "log_gelf_endpoint" => {
+                if !val.is_empty() && Endpoint::parse(&val).is_none() {
+                    dlog!(
+                        config,
+                        "ERROR",
+                        "Invalid log_gelf_endpoint value {} in configuration file, must be udp://host[:port] or tcp://host[:port]. Ignoring. Actual value remains {}",
+                        val,
+                        config.log_gelf_endpoint
+                    );
+                } else if config.log_gelf_endpoint != val {
+                    config.log_gelf_endpoint = val;
+                    dlog!(
+                        config,
+                        "LOG",
+                        "Setting log_gelf_endpoint from configuration file to {}",
+                        config.log_gelf_endpoint
+                    );
+                }
+            }
+            "log_rotation_age" => match LogRotationAge::parse(&val) {
+                Some(v) => {
+                    if config.log_rotation_age != v {
+                        config.log_rotation_age = v;
+                        dlog!(
+                            config,
+                            "LOG",
+                            "Setting log_rotation_age from configuration file to {}",
+                            config.log_rotation_age.as_str()
+                        );
+                    }
+                }
+                None => {
+                    dlog!(
+                        config,
+                        "ERROR",
+                        "Invalid log_rotation_age value {} in configuration file, must be one of none|hourly|daily. Ignoring. Actual value remains {}",
+                        val,
+                        config.log_rotation_age.as_str()
+                    );
+                }
+            },
+            "log_rotation_compress" => match LogRotationCompress::parse(&val) {
+                Some(v) => {
+                    if config.log_rotation_compress != v {
+                        config.log_rotation_compress = v;
+                        dlog!(
+                            config,
+                            "LOG",
+                            "Setting log_rotation_compress from configuration file to {}",
+                            config.log_rotation_compress.as_str()
+                        );
+                    }
+                }
+                None => {
+                    dlog!(
+                        config,
+                        "ERROR",
+                        "Invalid log_rotation_compress value {} in configuration file, must be one of none|gzip|zstd. Ignoring. Actual value remains {}",
+                        val,
+                        config.log_rotation_compress.as_str()
+                    );
+                }
+            },
+            "log_rotation_count" => match val.parse::<u32>() {
+                Ok(v) => {
+                    if config.log_rotation_count != v {
+                        config.log_rotation_count = v;
+                        dlog!(
+                            config,
+                            "LOG",
+                            "Setting log_rotation_count from configuration file to {}",
+                            config.log_rotation_count
+                        );
+                    }
+                }
+                Err(_) => {
+                    dlog!(
+                        config,
+                        "ERROR",
+                        "Invalid log_rotation_count value {} in configuration file, must be a non-negative integer. Ignoring. Actual value remains {}",
+                        val,
+                        config.log_rotation_count
+                    );
+                }
+            },
+            "run_details_retention_months" => match val.parse::<u64>() {
+                Ok(v) => {
+                    if config.run_details_retention_months != v {
+                        config.run_details_retention_months = v;
+                        dlog!(
+                            config,
+                            "LOG",
+                            "Setting run_details_retention_months from configuration file to {}",
+                            config.run_details_retention_months
+                        );
+                    }
+                }
+                Err(_) => {
+                    dlog!(
+                        config,
+                        "ERROR",
+                        "Invalid run_details_retention_months value {} in configuration file, must be a non-negative integer. Ignoring. Actual value remains {}",
+                        val,
+                        config.run_details_retention_months
+                    );
+                }
+            },
+            "run_details_maintenance_interval" => match val.parse::<f64>() {
+                Ok(v) if v.is_finite() && v >= 0.0 => {
+                    if config.run_details_maintenance_interval != v {
+                        config.run_details_maintenance_interval = v;
+                        dlog!(
+                            config,
+                            "LOG",
+                            "Setting run_details_maintenance_interval from configuration file to {}",
+                            config.run_details_maintenance_interval
+                        );
+                    }
+                }
+                _ => {
+                    dlog!(
+                        config,
+                        "ERROR",
+                        "Invalid run_details_maintenance_interval value {} in configuration file, must be a non-negative number (0 disables). Ignoring. Actual value remains {}",
+                        val,
+                        config.run_details_maintenance_interval
+                    );
+                }
+            },
+            "stats_interval" => match val.parse::<u64>() {
+                Ok(v) => {
+                    if config.stats_interval != v {
+                        config.stats_interval = v;
+                        dlog!(
+                            config,
+                            "LOG",
+                            "Setting stats_interval from configuration file to {}",
+                            config.stats_interval
+                        );
+                    }
+                }
+                Err(_) => {
+                    dlog!(
+                        config,
+                        "ERROR",
+                        "Invalid stats_interval value {} in configuration file, must be a non-negative integer. Ignoring. Actual value remains {}",
+                        val,
+                        config.stats_interval
+                    );
+                }
+            },
+            "max_job_size" => match val.parse::<u64>() {
+                Ok(v) => {
+                    if config.max_job_size != v {
+                        config.max_job_size = v;
+                        dlog!(
+                            config,
+                            "LOG",
+                            "Setting max_job_size from configuration file to {}",
+                            config.max_job_size
+                        );
+                    }
+                }
+                Err(_) => {
+                    dlog!(
+                        config,
+                        "ERROR",
+                        "Invalid max_job_size value {} in configuration file, must be a non-negative integer. Ignoring. Actual value remains {}",
+                        val,
+                        config.max_job_size
+                    );
+                }
+            },
+            "connection_reserve" => match val.parse::<u64>() {
+                Ok(v) => {
+                    if config.connection_reserve != v {
+                        config.connection_reserve = v;
+                        dlog!(
+                            config,
+                            "LOG",
+                            "Setting connection_reserve from configuration file to {}",
+                            config.connection_reserve
+                        );
+                    }
+                }
+                Err(_) => {
+                    dlog!(
+                        config,
+                        "ERROR",
+                        "Invalid connection_reserve value {} in configuration file, must be a non-negative integer. Ignoring. Actual value remains {}",
+                        val,
+                        config.connection_reserve
+                    );
+                }
+            },
+            "job_run_details" => match JobRunDetails::parse(&val) {
+                Some(v) => {
+                    if config.job_run_details != v {
+                        config.job_run_details = v;
+                        dlog!(
+                            config,
+                            "LOG",
+                            "Setting job_run_details from configuration file to {}",
+                            config.job_run_details.as_str()
+                        );
+                    }
+                }
+                None => {
+                    dlog!(
+                        config,
+                        "ERROR",
+                        "Invalid job_run_details value {} in configuration file, must be one of all|errors|none. Ignoring. Actual value remains {}",
+                        val,
+                        config.job_run_details.as_str()
+                    );
+                }
+            },
+            "log_job_notices" => {
+                let log_job_notices = val.parse::<i32>().unwrap_or(0) != 0;
+                if config.log_job_notices != log_job_notices {
+                    config.log_job_notices = log_job_notices;
+                    dlog!(
+                        config,
+                        "LOG",
+                        "Setting log_job_notices from configuration file to {}",
+                        config.log_job_notices as i32
+                    );
+                }
+            }
+            "auto_reload" => {
+                let auto_reload = val.parse::<i32>().unwrap_or(0) != 0;
+                if config.auto_reload != auto_reload {
+                    config.auto_reload = auto_reload;
+                    dlog!(
+                        config,
+                        "LOG",
+                        "Setting auto_reload from configuration file to {}",
+                        config.auto_reload as i32
+                    );
+                }
+            }
+            "strict_config" => {
+                let strict_config = val.parse::<i32>().unwrap_or(0) != 0;
+                if config.strict_config != strict_config {
+                    config.strict_config = strict_config;
+                    dlog!(
+                        config,
+                        "LOG",
+                        "Setting strict_config from configuration file to {}",
+                        config.strict_config as i32
+                    );
+                }
+            }
+            "scheduler_event_log" => {
+                let scheduler_event_log = val.parse::<i32>().unwrap_or(0) != 0;
+                if config.scheduler_event_log != scheduler_event_log {
+                    config.scheduler_event_log = scheduler_event_log;
+                    dlog!(
+                        config,
+                        "LOG",
+                        "Setting scheduler_event_log from configuration file to {}",
+                        config.scheduler_event_log as i32
+                    );
+                }
+            }
+            "job_nice" => match val.parse::<i32>() {
+                Ok(v) if (0..=19).contains(&v) => {
+                    if config.job_nice != v {
+                        config.job_nice = v;
+                        dlog!(
+                            config,
+                            "LOG",
+                            "Setting job_nice from configuration file to {}",
+                            config.job_nice
+                        );
+                    }
+                }
+                _ => {
+                    dlog!(
+                        config,
+                        "ERROR",
+                        "Invalid job_nice value {} in configuration file, must be an integer between 0 and 19. Ignoring. Actual value remains {}",
+                        val,
+                        config.job_nice
+                    );
+                }
+            },
+            "job_io_class" => match IoClass::parse(&val) {
+                Some(v) => {
+                    if config.job_io_class != v {
+                        config.job_io_class = v;
+                        dlog!(
+                            config,
+                            "LOG",
+                            "Setting job_io_class from configuration file to {}",
+                            config.job_io_class.as_str()
+                        );
+                    }
+                }
+                None => {
+                    dlog!(
+                        config,
+                        "ERROR",
+                        "Invalid job_io_class value {} in configuration file, must be one of none|best-effort|idle. Ignoring. Actual value remains {}",
+                        val,
+                        config.job_io_class.as_str()
+                    );
+                }
+            },
+            "job_execution_mode" => match ExecutionMode::parse(&val) {
+                Some(v) => {
+                    if config.job_execution_mode != v {
+                        config.job_execution_mode = v;
+                        dlog!(
+                            config,
+                            "LOG",
+                            "Setting job_execution_mode from configuration file to {}",
+                            config.job_execution_mode.as_str()
+                        );
+                    }
+                }
+                None => {
+                    dlog!(
+                        config,
+                        "ERROR",
+                        "Invalid job_execution_mode value {} in configuration file, must be one of set_role|security_definer. Ignoring. Actual value remains {}",
+                        val,
+                        config.job_execution_mode.as_str()
+                    );
+                }
+            },
+            "dst_policy" => match DstPolicy::parse(&val) {
+                Some(v) => {
+                    if config.dst_policy != v {
+                        config.dst_policy = v;
+                        dlog!(
+                            config,
+                            "LOG",
+                            "Setting dst_policy from configuration file to {}",
+                            config.dst_policy.as_str()
+                        );
+                    }
+                }
+                None => {
+                    dlog!(
+                        config,
+                        "ERROR",
+                        "Invalid dst_policy value {} in configuration file, must be one of server|shift|skip. Ignoring. Actual value remains {}",
+                        val,
+                        config.dst_policy.as_str()
+                    );
+                }
+            },
+            "job_io_priority" => match val.parse::<i32>() {
+                Ok(v) if (0..=7).contains(&v) => {
+                    if config.job_io_priority != v {
+                        config.job_io_priority = v;
+                        dlog!(
+                            config,
+                            "LOG",
+                            "Setting job_io_priority from configuration file to {}",
+                            config.job_io_priority
+                        );
+                    }
+                }
+                _ => {
+                    dlog!(
+                        config,
+                        "ERROR",
+                        "Invalid job_io_priority value {} in configuration file, must be an integer between 0 and 7. Ignoring. Actual value remains {}",
+                        val,
+                        config.job_io_priority
+                    );
+                }
+            },
+            "job_cgroup" if config.job_cgroup != val => {
+                config.job_cgroup = val;
+                dlog!(
+                    config,
+                    "LOG",
+                    "Setting job_cgroup from configuration file to {}",
+                    config.job_cgroup
+                );
+            }
+            "executable_job_owners" if config.executable_job_owners != val => {
+                config.executable_job_owners = val;
+                dlog!(
+                    config,
+                    "LOG",
+                    "Setting executable_job_owners from configuration file to {}",
+                    config.executable_job_owners
+                );
+            }
+            "executable_os_users" if config.executable_os_users != val => {
+                config.executable_os_users = val;
+                dlog!(
+                    config,
+                    "LOG",
+                    "Setting executable_os_users from configuration file to {}",
+                    config.executable_os_users
+                );
+            }
+            "schemas" if config.schemas != val => match schema_list(&val) {
+                Ok(_) => {
+                    config.schemas = val;
+                    dlog!(
+                        config,
+                        "LOG",
+                        "Setting schemas from configuration file to {}",
+                        config.schemas
+                    );
+                }
+                Err(err) => {
+                    dlog!(
+                        config,
+                        "ERROR",
+                        "Invalid schemas value {} in configuration file, {}. Ignoring. Actual value remains {}",
+                        val,
+                        err,
+                        config.schemas
+                    );
+                }
+            },
+            "ssh_host" if config.ssh_host != val => {
+                config.ssh_host = val;
+                dlog!(
+                    config,
+                    "LOG",
+                    "Setting ssh_host from configuration file to {}",
+                    config.ssh_host
+                );
+            }
+            "ssh_port" => match val.parse::<u16>() {
+                Ok(v) if v > 0 => {
+                    if config.ssh_port != v {
+                        config.ssh_port = v;
+                        dlog!(
+                            config,
+                            "LOG",
+                            "Setting ssh_port from configuration file to {}",
+                            config.ssh_port
+                        );
+                    }
+                }
+                _ => {
+                    dlog!(
+                        config,
+                        "ERROR",
+                        "Invalid ssh_port value {} in configuration file, must be a positive integer. Ignoring. Actual value remains {}",
+                        val,
+                        config.ssh_port
+                    );
+                }
+            },
+            "ssh_user" if config.ssh_user != val => {
+                config.ssh_user = val;
+                dlog!(
+                    config,
+                    "LOG",
+                    "Setting ssh_user from configuration file to {}",
+                    config.ssh_user
+                );
+            }
+            "ssh_key" if config.ssh_key != val => {
+                config.ssh_key = val;
+                dlog!(
+                    config,
+                    "LOG",
+                    "Setting ssh_key from configuration file to {}",
+                    config.ssh_key
+                );
+            }
+            "stale_job_timeout" => match val.parse::<f64>() {
+                Ok(v) if v.is_finite() && v >= 0.0 => {
+                    if config.stale_job_timeout != v {
+                        config.stale_job_timeout = v;
+                        dlog!(
+                            config,
+                            "LOG",
+                            "Setting stale_job_timeout from configuration file to {}",
+                            config.stale_job_timeout
+                        );
+                    }
+                }
+                _ => {
+                    dlog!(
+                        config,
+                        "ERROR",
+                        "Invalid stale_job_timeout value {} in configuration file, must be a non-negative number (0 disables). Ignoring. Actual value remains {}",
+                        val,
+                        config.stale_job_timeout
+                    );
+                }
+            },
+            "worker_hang_timeout" => match val.parse::<f64>() {
+                Ok(v) if v.is_finite() && v >= 0.0 => {
+                    if config.worker_hang_timeout != v {
+                        config.worker_hang_timeout = v;
+                        dlog!(
+                            config,
+                            "LOG",
+                            "Setting worker_hang_timeout from configuration file to {}",
+                            config.worker_hang_timeout
+                        );
+                    }
+                }
+                _ => {
+                    dlog!(
+                        config,
+                        "ERROR",
+                        "Invalid worker_hang_timeout value {} in configuration file, must be a non-negative number (0 disables). Ignoring. Actual value remains {}",
+                        val,
+                        config.worker_hang_timeout
+                    );
+                }
+            },
+            "hard_max_run_duration" => match val.parse::<f64>() {
+                Ok(v) if v.is_finite() && v >= 0.0 => {
+                    if config.hard_max_run_duration != v {
+                        config.hard_max_run_duration = v;
+                        dlog!(
+                            config,
+                            "LOG",
+                            "Setting hard_max_run_duration from configuration file to {}",
+                            config.hard_max_run_duration
+                        );
+                    }
+                }
+                _ => {
+                    dlog!(
+                        config,
+                        "ERROR",
+                        "Invalid hard_max_run_duration value {} in configuration file, must be a non-negative number (0 disables). Ignoring. Actual value remains {}",
+                        val,
+                        config.hard_max_run_duration
+                    );
+                }
+            },
+            "job_run_timeout" => match val.parse::<f64>() {
+                Ok(v) if v.is_finite() && v >= 0.0 => {
+                    if config.job_run_timeout != v {
+                        config.job_run_timeout = v;
+                        dlog!(
+                            config,
+                            "LOG",
+                            "Setting job_run_timeout from configuration file to {}",
+                            config.job_run_timeout
+                        );
+                    }
+                }
+                _ => {
+                    dlog!(
+                        config,
+                        "ERROR",
+                        "Invalid job_run_timeout value {} in configuration file, must be a non-negative number (0 disables). Ignoring. Actual value remains {}",
+                        val,
+                        config.job_run_timeout
+                    );
+                }
+            },
+            "max_failures" => match val.parse::<u32>() {
+                Ok(v) => {
+                    if config.max_failures != v {
+                        config.max_failures = v;
+                        dlog!(
+                            config,
+                            "LOG",
+                            "Setting max_failures from configuration file to {}",
+                            config.max_failures
+                        );
+                    }
+                }
+                Err(_) => {
+                    dlog!(
+                        config,
+                        "ERROR",
+                        "Invalid max_failures value {} in configuration file, must be a non-negative integer (0 disables). Ignoring. Actual value remains {}",
+                        val,
+                        config.max_failures
+                    );
+                }
+            },
+            "max_nap_time" => match val.parse::<f64>() {
+                Ok(v) if v.is_finite() && v >= 0.0 => {
+                    if config.max_nap_time != v {
+                        config.max_nap_time = v;
+                        dlog!(
+                            config,
+                            "LOG",
+                            "Setting max_nap_time from configuration file to {}",
+                            config.max_nap_time
+                        );
+                    }
+                }
+                _ => {
+                    dlog!(
+                        config,
+                        "ERROR",
+                        "Invalid max_nap_time value {} in configuration file, must be a non-negative number (0 disables). Ignoring. Actual value remains {}",
+                        val,
+                        config.max_nap_time
+                    );
+                }
+            },
+            "reconnect_max_delay" => match val.parse::<f64>() {
+                Ok(v) if v.is_finite() && v >= 0.0 => {
+                    if config.reconnect_max_delay != v {
+                        config.reconnect_max_delay = v;
+                        dlog!(
+                            config,
+                            "LOG",
+                            "Setting reconnect_max_delay from configuration file to {}",
+                            config.reconnect_max_delay
+                        );
+                    }
+                }
+                _ => {
+                    dlog!(
+                        config,
+                        "ERROR",
+                        "Invalid reconnect_max_delay value {} in configuration file, must be a non-negative number. Ignoring. Actual value remains {}",
+                        val,
+                        config.reconnect_max_delay
+                    );
+                }
+            },
+            "standby_check_interval" => match val.parse::<f64>() {
+                Ok(v) if v.is_finite() && v >= 0.0 => {
+                    if config.standby_check_interval != v {
+                        config.standby_check_interval = v;
+                        dlog!(
+                            config,
+                            "LOG",
+                            "Setting standby_check_interval from configuration file to {}",
+                            config.standby_check_interval
+                        );
+                    }
+                }
+                _ => {
+                    dlog!(
+                        config,
+                        "ERROR",
+                        "Invalid standby_check_interval value {} in configuration file, must be a non-negative number. Ignoring. Actual value remains {}",
+                        val,
+                        config.standby_check_interval
+                    );
+                }
+            },
+            "connection_check_interval" => match val.parse::<f64>() {
+                Ok(v) if v.is_finite() && v >= 0.0 => {
+                    if config.connection_check_interval != v {
+                        config.connection_check_interval = v;
+                        dlog!(
+                            config,
+                            "LOG",
+                            "Setting connection_check_interval from configuration file to {}",
+                            config.connection_check_interval
+                        );
+                    }
+                }
+                _ => {
+                    dlog!(
+                        config,
+                        "ERROR",
+                        "Invalid connection_check_interval value {} in configuration file, must be a non-negative number. Ignoring. Actual value remains {}",
+                        val,
+                        config.connection_check_interval
+                    );
+                }
+            },
+            "multiple_schedulers" => {
+                let multiple_schedulers = val.parse::<i32>().unwrap_or(0) != 0;
+                if config.multiple_schedulers != multiple_schedulers {
+                    config.multiple_schedulers = multiple_schedulers;
+                    dlog!(
+                        config,
+                        "LOG",
+                        "Setting multiple_schedulers from configuration file to {}",
+                        config.multiple_schedulers as i32
+                    );
+                }
+            }
+            "reconnect_max_attempts" => match val.parse::<u32>() {
+                Ok(v) => {
+                    if config.reconnect_max_attempts != v {
+                        config.reconnect_max_attempts = v;
+                        dlog!(
+                            config,
+                            "LOG",
+                            "Setting reconnect_max_attempts from configuration file to {}",
+                            config.reconnect_max_attempts
+                        );
+                    }
+                }
+                Err(_) => {
+                    dlog!(
+                        config,
+                        "ERROR",
+                        "Invalid reconnect_max_attempts value {} in configuration file, must be a non-negative integer (0 retries forever). Ignoring. Actual value remains {}",
+                        val,
+                        config.reconnect_max_attempts
+                    );
+                }
+            },
+            "notify_debounce" => match val.parse::<f64>() {
+                Ok(v) if v.is_finite() && v >= 0.0 => {
+                    if config.notify_debounce != v {
+                        config.notify_debounce = v;
+                        dlog!(
+                            config,
+                            "LOG",
+                            "Setting notify_debounce from configuration file to {}",
+                            config.notify_debounce
+                        );
+                    }
+                }
+                _ => {
+                    dlog!(
+                        config,
+                        "ERROR",
+                        "Invalid notify_debounce value {} in configuration file, must be a non-negative number (0 disables). Ignoring. Actual value remains {}",
+                        val,
+                        config.notify_debounce
+                    );
+                }
+            },
+            name if name.starts_with(SEMAPHORE_PREFIX) => {
+                parse_slots(
+                    config,
+                    name,
+                    &val,
+                    SEMAPHORE_PREFIX,
+                    &config.semaphores,
+                    &mut semaphores,
+                );
+            }
+            name if name.starts_with(JOB_CLASS_PREFIX) && class_priority_line(name).is_some() => {
+                parse_class_priority(config, name, &val, &mut job_class_priorities);
+            }
+            name if name.starts_with(JOB_CLASS_PREFIX) => {
+                parse_slots(
+                    config,
+                    name,
+                    &val,
+                    JOB_CLASS_PREFIX,
+                    &config.job_classes,
+                    &mut job_classes,
+                );
+            }
+            name if !known.contains(&name) => {
+                dlog!(
+                    config,
+                    "WARNING",
+                    "Unknown setting {name} in configuration file, ignored"
+                );
+            }
+            _ => {}
+        }
+    }
+
+    if config.semaphores != semaphores {
+        config.semaphores = semaphores;
+        dlog!(
+            config,
+            "LOG",
+            "Setting semaphores from configuration file to {}",
+            slot_list(&config.semaphores)
+        );
+    }
+    if config.job_classes != job_classes {
+        config.job_classes = job_classes;
+        dlog!(
+            config,
+            "LOG",
+            "Setting job_classes from configuration file to {}",
+            slot_list(&config.job_classes)
+        );
+    }
+    if config.job_class_priorities != job_class_priorities {
+        config.job_class_priorities = job_class_priorities;
+        dlog!(
+            config,
+            "LOG",
+            "Setting job_class_priorities from configuration file to {}",
+            class_priority_list(&config.job_class_priorities)
+        );
+    }
+}
+
+/// Settings made of one `<prefix><name> = <slots>` line per name, with
+/// their prefix.
+const SLOT_SETTINGS: &[(&str, &str)] = &[
+    ("semaphores", SEMAPHORE_PREFIX),
+    ("job_classes", JOB_CLASS_PREFIX),
+    ("job_class_priorities", JOB_CLASS_PREFIX),
+];
+
+/// Prefix of the lines of `setting` when it is one of [`SLOT_SETTINGS`].
+fn slot_prefix(setting: &str) -> Option<&'static str> {
+    SLOT_SETTINGS
+        .iter()
+        .find(|(name, _)| *name == setting)
+        .map(|(_, prefix)| *prefix)
+}
+
+/// Add the `<prefix><name> = <slots>` line `var` to `slots`. An invalid
+/// count keeps the value of `current`, if any.
+fn parse_slots(
+    config: &Config,
+    var: &str,
+    val: &str,
+    prefix: &str,
+    current: &BTreeMap<String, u32>,
+    slots: &mut BTreeMap<String, u32>,
+) {
+    let name = &var[prefix.len()..];
+    match val.parse::<u32>() {
+        Ok(count) if count > 0 && !name.is_empty() => {
+            slots.insert(name.to_string(), count);
+        }
+        _ => {
+            let current = current.get(name).copied();
+            dlog!(
+                config,
+                "ERROR",
+                "Invalid {} value {} in configuration file, must be a positive integer. Ignoring. Actual value remains {}",
+                var,
+                val,
+                current.map_or("unset".to_string(), |v| v.to_string())
+            );
+            if let Some(count) = current {
+                slots.insert(name.to_string(), count);
+            }
+        }
+    }
+}
+
+/// Priorities a `job_class.<name>.<priority>` line sets: the class name and
+/// the priority, `nice`, `io_class` or `io_priority`.
+fn class_priority_line(var: &str) -> Option<(&str, &str)> {
+    let (class, priority) = var.strip_prefix(JOB_CLASS_PREFIX)?.rsplit_once('.')?;
+    matches!(priority, "nice" | "io_class" | "io_priority").then_some((class, priority))
+}
+
+/// Add the `job_class.<name>.<priority>` line `var` to `priorities`. An
+/// invalid value keeps the one of the configuration in effect, if any.
+fn parse_class_priority(
+    config: &Config,
+    var: &str,
+    val: &str,
+    priorities: &mut BTreeMap<String, ClassPriority>,
+) {
+    let Some((class, priority)) = class_priority_line(var).filter(|(class, _)| !class.is_empty())
+    else {
+        dlog!(
+            config,
+            "ERROR",
+            "Invalid setting {} in configuration file, a job class priority needs the name of its class. Ignoring.",
+            var
+        );
+        return;
+    };
+    let current = config
+        .job_class_priorities
+        .get(class)
+        .copied()
+        .unwrap_or_default();
+    let entry = priorities.entry(class.to_string()).or_default();
+    let (valid, expected, actual) = match priority {
+        "nice" => {
+            let value = val.parse::<i32>().ok().filter(|v| (0..=19).contains(v));
+            entry.nice = value.or(current.nice);
+            (
+                value.is_some(),
+                "an integer between 0 and 19",
+                current.nice.map(|v| v.to_string()),
+            )
+        }
+        "io_class" => {
+            let value = IoClass::parse(val);
+            entry.io_class = value.or(current.io_class);
+            (
+                value.is_some(),
+                "one of none|best-effort|idle",
+                current.io_class.map(|v| v.as_str().to_string()),
+            )
+        }
+        _ => {
+            let value = val.parse::<i32>().ok().filter(|v| (0..=7).contains(v));
+            entry.io_priority = value.or(current.io_priority);
+            (
+                value.is_some(),
+                "an integer between 0 and 7",
+                current.io_priority.map(|v| v.to_string()),
+            )
+        }
+    };
+    if *entry == ClassPriority::default() {
+        priorities.remove(class);
+    }
+    if !valid {
+        dlog!(
+            config,
+            "ERROR",
+            "Invalid {} value {} in configuration file, must be {}. Ignoring. Actual value remains {}",
+            var,
+            val,
+            expected,
+            actual.unwrap_or_else(|| "unset".to_string())
+        );
+    }
+}
+
+/// Job class priorities as `name.priority=value` pairs separated by commas,
+/// `none` when there is none.
+pub fn class_priority_list(priorities: &BTreeMap<String, ClassPriority>) -> String {
+    let list: Vec<String> = priorities
+        .iter()
+        .flat_map(|(name, priority)| {
+            [
+                priority.nice.map(|v| format!("{name}.nice={v}")),
+                priority
+                    .io_class
+                    .map(|v| format!("{name}.io_class={}", v.as_str())),
+                priority
+                    .io_priority
+                    .map(|v| format!("{name}.io_priority={v}")),
+            ]
+        })
+        .flatten()
+        .collect();
+    if list.is_empty() {
+        return "none".to_string();
+    }
+    list.join(",")
+}
+
+/// Semaphores or job classes as `name=slots` pairs separated by commas, `none` when there
+/// is none.
+pub fn slot_list(semaphores: &BTreeMap<String, u32>) -> String {
+    if semaphores.is_empty() {
+        return "none".to_string();
+    }
+    semaphores
+        .iter()
+        .map(|(name, slots)| format!("{name}={slots}"))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Settings whose value is never written out, only the fact that it changed.
+pub const SECRET_SETTINGS: &[&str] = &["passwd", "dsn"];
+
+/// Every setting of the configuration file with its current value, in the
+/// order of the sample configuration file. Values use the file's syntax.
+pub fn config_settings(config: &Config, dbinfo: &DbInfo) -> Vec<(&'static str, String)> {
+    vec![
+        ("debug", u8::from(config.debug).to_string()),
+        (
+            "log_min_messages",
+            config.log_min_messages.as_str().to_string(),
+        ),
+        ("debug_ring_size", config.debug_ring_size.to_string()),
+        ("pidfile", config.pidfile.clone()),
+        ("schemas", config.schemas.clone()),
+        ("auto_reload", u8::from(config.auto_reload).to_string()),
+        ("strict_config", u8::from(config.strict_config).to_string()),
+        ("logfile", config.logfile.clone()),
+        ("logfile_mode", format!("{:04o}", config.logfile_mode)),
+        ("logfile_owner", config.logfile_owner.clone()),
+        ("logfile_group", config.logfile_group.clone()),
+        (
+            "log_truncate_on_rotation",
+            u8::from(config.log_truncate_on_rotation).to_string(),
+        ),
+        ("log_retention_days", config.log_retention_days.to_string()),
+        ("log_compress", u8::from(config.log_compress).to_string()),
+        ("log_rotation_size", config.log_rotation_size.to_string()),
+        (
+            "log_destination",
+            config.log_destination.as_str().to_string(),
+        ),
+        ("syslog_facility", config.syslog_facility.clone()),
+        ("syslog_ident", config.syslog_ident.clone()),
+        ("log_gelf_endpoint", config.log_gelf_endpoint.clone()),
+        (
+            "log_rotation_age",
+            config.log_rotation_age.as_str().to_string(),
+        ),
+        ("log_rotation_count", config.log_rotation_count.to_string()),
+        (
+            "log_rotation_compress",
+            config.log_rotation_compress.as_str().to_string(),
+        ),
+        ("job_queue_interval", config.job_queue_interval.to_string()),
+        (
+            "job_queue_processes",
+            config.job_queue_processes.to_string(),
+        ),
+        ("pool_size", config.pool_size.to_string()),
+        ("nap_time", config.nap_time.to_string()),
+        ("startup_delay", config.startup_delay.to_string()),
+        ("error_delay", config.error_delay.to_string()),
+        ("stats_interval", config.stats_interval.to_string()),
+        (
+            "job_run_details",
+            config.job_run_details.as_str().to_string(),
+        ),
+        (
+            "log_job_notices",
+            u8::from(config.log_job_notices).to_string(),
+        ),
+        (
+            "scheduler_event_log",
+            u8::from(config.scheduler_event_log).to_string(),
+        ),
+        (
+            "run_details_maintenance_interval",
+            config.run_details_maintenance_interval.to_string(),
+        ),
+        (
+            "run_details_retention_months",
+            config.run_details_retention_months.to_string(),
+        ),
+        ("stale_job_timeout", config.stale_job_timeout.to_string()),
+        (
+            "worker_hang_timeout",
+            config.worker_hang_timeout.to_string(),
+        ),
+        ("notify_debounce", config.notify_debounce.to_string()),
+        ("max_nap_time", config.max_nap_time.to_string()),
+        (
+            "reconnect_max_delay",
+            config.reconnect_max_delay.to_string(),
+        ),
+        (
+            "reconnect_max_attempts",
+            config.reconnect_max_attempts.to_string(),
+        ),
+        (
+            "standby_check_interval",
+            config.standby_check_interval.to_string(),
+        ),
+        (
+            "connection_check_interval",
+            config.connection_check_interval.to_string(),
+        ),
+        (
+            "multiple_schedulers",
+            u8::from(config.multiple_schedulers).to_string(),
+        ),
+        (
+            "schedule_refresh_interval",
+            config.schedule_refresh_interval.to_string(),
+        ),
+        (
+            "max_notifications_per_cycle",
+            config.max_notifications_per_cycle.to_string(),
+        ),
+        ("max_job_size", config.max_job_size.to_string()),
+        (
+            "hard_max_run_duration",
+            config.hard_max_run_duration.to_string(),
+        ),
+        (
+            "hard_max_run_overruns",
+            config.hard_max_run_overruns.to_string(),
+        ),
+        ("job_run_timeout", config.job_run_timeout.to_string()),
+        ("max_failures", config.max_failures.to_string()),
+        ("job_nice", config.job_nice.to_string()),
+        ("job_io_class", config.job_io_class.as_str().to_string()),
+        (
+            "job_execution_mode",
+            config.job_execution_mode.as_str().to_string(),
+        ),
+        ("dst_policy", config.dst_policy.as_str().to_string()),
+        ("job_io_priority", config.job_io_priority.to_string()),
+        ("job_cgroup", config.job_cgroup.clone()),
+        (
+            "executable_job_owners",
+            config.executable_job_owners.clone(),
+        ),
+        ("executable_os_users", config.executable_os_users.clone()),
+        ("connection_reserve", config.connection_reserve.to_string()),
+        ("semaphores", slot_list(&config.semaphores)),
+        ("job_classes", slot_list(&config.job_classes)),
+        (
+            "job_class_priorities",
+            class_priority_list(&config.job_class_priorities),
+        ),
+        ("ssh_host", config.ssh_host.clone()),
+        ("ssh_port", config.ssh_port.to_string()),
+        ("ssh_user", config.ssh_user.clone()),
+        ("ssh_key", config.ssh_key.clone()),
+        ("host", dbinfo.host.clone()),
+        ("port", dbinfo.port.to_string()),
+        ("database", dbinfo.database.clone()),
+        ("user", dbinfo.user.clone()),
+        ("passwd", dbinfo.passwd.clone()),
+        ("passwd_file", dbinfo.passwd_file.clone()),
+        ("passwd_command", dbinfo.passwd_command.clone()),
+        ("auth_method", dbinfo.auth_method.as_str().to_string()),
+        ("aws_region", dbinfo.aws_region.clone()),
+        ("dsn", dbinfo.dsn.clone()),
+        ("connect_timeout", dbinfo.connect_timeout.to_string()),
+        ("keepalives", u8::from(dbinfo.keepalives).to_string()),
+        ("keepalives_idle", dbinfo.keepalives_idle.to_string()),
+        (
+            "keepalives_interval",
+            dbinfo.keepalives_interval.to_string(),
+        ),
+        ("keepalives_count", dbinfo.keepalives_count.to_string()),
+        ("timezone", dbinfo.timezone.clone()),
+        ("sslmode", dbinfo.sslmode.as_str().to_string()),
+        ("sslrootcert", dbinfo.sslrootcert.clone()),
+        ("sslcert", dbinfo.sslcert.clone()),
+        ("sslkey", dbinfo.sslkey.clone()),
+        (
+            "target_session_attrs",
+            dbinfo.target_session_attrs.as_str().to_string(),
+        ),
+        (
+            "job_session_settings",
+            session_settings_str(&dbinfo.job_session_settings),
+        ),
+    ]
+}
+
+/// Settings whose value differs between two [`config_settings`] snapshots,
+/// as `(name, old, new)`. Secret values are masked.
+pub fn changed_settings(
+    old: &[(&'static str, String)],
+    new: &[(&'static str, String)],
+) -> Vec<(&'static str, String, String)> {
+    old.iter()
+        .zip(new)
+        .filter(|((_, before), (_, after))| before != after)
+        .map(|((name, before), (_, after))| {
+            if SECRET_SETTINGS.contains(name) {
+                (*name, "********".to_string(), "********".to_string())
+            } else {
+                (*name, before.clone(), after.clone())
+            }
+        })
+        .collect()
+}
+
+/// Parse a configuration value as a finite, strictly positive `f64` and store
+/// it via `field`. On invalid input the existing field value is preserved and
+/// an error line is logged; on success a confirmation line is logged.
+///
+/// Pulled out so the four time-interval settings (job_queue_interval,
+/// nap_time, startup_delay, error_delay) share one validation path.
+fn apply_positive_float(
+    config: &mut Config,
     name: &str,
     raw: &str,
     field: impl FnOnce(&mut Config) -> &mut f64,
 ) {
-    let parsed = match raw.parse::<f64>() {
-        Ok(v) => v,
-        Err(_) => return,
-    };
+    // An unparseable value is reported like a negative one.
+    let parsed = raw.parse::<f64>().unwrap_or(f64::NAN);
     if parsed > 0.0 && parsed.is_finite() {
         *field(config) = parsed;
         dlog!(
@@ -282,241 +1943,1591 @@ fn apply_positive_float(
             name,
             parsed
         );
-    } else {
-        let current = *field(config);
-        dlog!(
-            config,
-            "ERROR",
-            "Invalid {} value {} in configuration file, must be positive and finite. Ignoring. Actual value remains {}",
-            name,
-            raw,
-            current
+    } else {
+        let current = *field(config);
+        dlog!(
+            config,
+            "ERROR",
+            "Invalid {} value {} in configuration file, must be positive and finite. Ignoring. Actual value remains {}",
+            name,
+            raw,
+            current
+        );
+    }
+}
+
+/// Parse a size in bytes, optionally followed by a `kB`, `MB` or `GB` unit
+/// (multiples of 1024, case-insensitive), e.g. `100MB`.
+fn parse_size(raw: &str) -> Option<u64> {
+    let raw = raw.trim();
+    let digits = raw.find(|c: char| !c.is_ascii_digit()).unwrap_or(raw.len());
+    let value = raw[..digits].parse::<u64>().ok()?;
+    let unit: u64 = match raw[digits..].trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "kb" => 1 << 10,
+        "mb" => 1 << 20,
+        "gb" => 1 << 30,
+        _ => return None,
+    };
+    value.checked_mul(unit)
+}
+
+/// Parse a comma-separated list of `name=value` settings, e.g.
+/// `work_mem=256MB, statement_timeout=0`. A value can not hold a comma.
+/// The driver only speaks UTF8, so no other `client_encoding` is accepted.
+fn parse_session_settings(raw: &str) -> Result<Vec<(String, String)>, String> {
+    let mut settings = Vec::new();
+    for item in raw
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+    {
+        let Some((name, value)) = item.split_once('=') else {
+            return Err(format!("{item} is not name=value"));
+        };
+        let (name, value) = (name.trim(), value.trim());
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+        {
+            return Err(format!("invalid setting name {name:?}"));
+        }
+        if value.is_empty() {
+            return Err(format!("no value for {name}"));
+        }
+        if name.eq_ignore_ascii_case("client_encoding")
+            && !matches!(value.to_ascii_uppercase().as_str(), "UTF8" | "UTF-8")
+        {
+            return Err(format!("client_encoding must be UTF8, not {value}"));
+        }
+        settings.push((name.to_string(), value.to_string()));
+    }
+    Ok(settings)
+}
+
+/// `job_session_settings` as written in the configuration file.
+fn session_settings_str(settings: &[(String, String)]) -> String {
+    settings
+        .iter()
+        .map(|(name, value)| format!("{name}={value}"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Job queue schemas of a `schemas` value, in order, once each. A name is
+/// a lowercase identifier short enough for the names of its notification
+/// channels, see [`SCHEMA_NAME_MAX`].
+pub fn schema_list(schemas: &str) -> Result<Vec<String>, String> {
+    let mut names: Vec<String> = Vec::new();
+    for name in schemas.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+        let valid = name.len() <= SCHEMA_NAME_MAX
+            && name.starts_with(|c: char| c.is_ascii_lowercase() || c == '_')
+            && name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+        if !valid {
+            return Err(format!(
+                "schema name \"{name}\" must be a lowercase identifier of at most {SCHEMA_NAME_MAX} characters"
+            ));
+        }
+        if !names.iter().any(|n| n == name) {
+            names.push(name.to_string());
+        }
+    }
+    if names.is_empty() {
+        return Err("no schema is listed".to_string());
+    }
+    Ok(names)
+}
+
+/// Names of the `[database <name>]` sections of `config_file`, in order
+/// of appearance, empty when it has none or can not be read.
+pub fn database_sections(config_file: &str, config: &Config) -> Vec<String> {
+    let Ok(content) = fs::read_to_string(config_file) else {
+        return Vec::new();
+    };
+    let settings = expand_includes(Path::new(config_file), &content, config, "", 0);
+    let mut names: Vec<String> = Vec::new();
+    for header in section_headers(&settings) {
+        if let Some(name) = header.strip_prefix(DATABASE_SECTION) {
+            if !names.iter().any(|n| n == name) {
+                names.push(name.to_string());
+            }
+        }
+    }
+    names
+}
+
+/// Headers of the sections settings of `settings` belong to, in order of
+/// appearance, once each.
+fn section_headers(settings: &[(String, String)]) -> Vec<&str> {
+    let mut headers: Vec<&str> = Vec::new();
+    for (var, _) in settings {
+        if let Some((header, _)) = var.strip_prefix('[').and_then(|v| v.split_once(']')) {
+            if !headers.contains(&header) {
+                headers.push(header);
+            }
+        }
+    }
+    headers
+}
+
+/// Settings outside of any section followed by those of the section
+/// `[database <section>]`, the settings of other sections dropped. With an
+/// empty `section`, only the settings outside of sections.
+fn select_section(settings: Vec<(String, String)>, section: &str) -> Vec<(String, String)> {
+    let header = format!("[{DATABASE_SECTION}{section}]");
+    let (global, sectioned): (Vec<_>, Vec<_>) = settings
+        .into_iter()
+        .partition(|(var, _)| !var.starts_with('['));
+    let selected = sectioned
+        .into_iter()
+        .filter_map(|(var, val)| Some((var.strip_prefix(&header)?.to_string(), val)));
+    global.into_iter().chain(selected).collect()
+}
+
+/// Settings of the configuration file `path` of content `content`, in
+/// order, with each `include` line replaced by the settings of the files
+/// it names. A relative path is relative to the directory of `path`, and
+/// the file name may contain `*` and `?` wildcards, the matching files
+/// being read in name order. Files that can not be read are reported and
+/// skipped. The settings following a `[header]` line, or included in the
+/// section `section`, are prefixed with their `[header]`.
+fn expand_includes(
+    path: &Path,
+    content: &str,
+    config: &Config,
+    section: &str,
+    depth: usize,
+) -> Vec<(String, String)> {
+    let mut settings = Vec::new();
+    let mut section = section.to_string();
+    for line in content.lines() {
+        if let Some(header) = parse_section_line(line) {
+            section = header;
+            continue;
+        }
+        let Some((var, val)) = parse_config_line(line) else {
+            continue;
+        };
+        let val = match expand_env_vars(&val, |name| env::var(name).ok()) {
+            Ok(val) => val,
+            Err(name) => {
+                dlog!(
+                    config,
+                    "ERROR",
+                    "Undefined environment variable {name} in the value of {var} in {}, setting ignored",
+                    path.display()
+                );
+                continue;
+            }
+        };
+        if var != "include" {
+            if section.is_empty() {
+                settings.push((var, val));
+            } else {
+                settings.push((format!("[{section}]{var}"), val));
+            }
+            continue;
+        }
+        if depth >= MAX_INCLUDE_DEPTH {
+            dlog!(
+                config,
+                "ERROR",
+                "configuration files nested too deeply, include {} of {} ignored",
+                val,
+                path.display()
+            );
+            continue;
+        }
+        let pattern = path.parent().unwrap_or(Path::new("")).join(&val);
+        for file in included_files(&pattern, config) {
+            dlog!(
+                config,
+                "DEBUG",
+                "including configuration file {}",
+                file.display()
+            );
+            match fs::read_to_string(&file) {
+                Ok(included) => {
+                    settings.extend(expand_includes(
+                        &file,
+                        &included,
+                        config,
+                        &section,
+                        depth + 1,
+                    ));
+                }
+                Err(err) => {
+                    dlog!(
+                        config,
+                        "ERROR",
+                        "can not read configuration file {} included by {}, {err}",
+                        file.display(),
+                        path.display()
+                    );
+                }
+            }
+        }
+    }
+    settings
+}
+
+/// Files named by an `include` path, whose file name may contain
+/// wildcards: the matching files in name order, skipping hidden ones
+/// unless the pattern starts with a dot.
+fn included_files(pattern: &Path, config: &Config) -> Vec<PathBuf> {
+    let name = pattern
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    if !name.contains(['*', '?']) {
+        return vec![pattern.to_path_buf()];
+    }
+    let dir = match pattern.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) => {
+            dlog!(
+                config,
+                "ERROR",
+                "can not list included configuration files {}, {err}",
+                pattern.display()
+            );
+            return Vec::new();
+        }
+    };
+    let mut files: Vec<PathBuf> = entries
+        .flatten()
+        .filter(|entry| entry.path().is_file())
+        .filter(|entry| {
+            let file = entry.file_name().to_string_lossy().into_owned();
+            (name.starts_with('.') || !file.starts_with('.')) && wildcard_match(&name, &file)
+        })
+        .map(|entry| entry.path())
+        .collect();
+    files.sort();
+    files
+}
+
+/// Whether `name` matches `pattern`, where `*` stands for any characters
+/// and `?` for one.
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Position after the last `*` and the name position it matched up to.
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p + 1, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((after, matched)) => {
+                    p = after;
+                    n = matched + 1;
+                    star = Some((after, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Settings of the configuration files, those set by a
+/// `PG_DBMS_JOB_<NAME>` variable of `vars` replaced by its value, the
+/// connection settings still unset taken from the libpq variables of
+/// [`LIBPQ_ENV`], and the names of the variables used. Only the settings of
+/// [`config_settings`] can be set this way, not the `semaphore.<name>` and
+/// `job_class.<name>` ones.
+fn merge_env_settings(
+    file_settings: Vec<(String, String)>,
+    vars: impl Iterator<Item = (String, String)>,
+    config: &Config,
+    dbinfo: &DbInfo,
+) -> (Vec<(String, String)>, Vec<String>) {
+    let known = config_settings(config, dbinfo);
+    let vars: Vec<(String, String)> = vars.collect();
+    let mut env_settings = Vec::new();
+    let mut names = Vec::new();
+    for (name, val) in &vars {
+        let Some(var) = name.strip_prefix(ENV_PREFIX).map(str::to_lowercase) else {
+            continue;
+        };
+        if slot_prefix(&var).is_none() && known.iter().any(|(setting, _)| *setting == var) {
+            env_settings.push((var, val.trim().to_string()));
+            names.push(name.clone());
+        }
+    }
+    let mut settings: Vec<(String, String)> = file_settings
+        .into_iter()
+        .filter(|(var, _)| !env_settings.iter().any(|(env_var, _)| env_var == var))
+        .collect();
+    settings.extend(env_settings);
+    for (setting, name) in LIBPQ_ENV {
+        if settings.iter().any(|(var, _)| var == setting) {
+            continue;
+        }
+        if let Some((_, val)) = vars
+            .iter()
+            .find(|(var, val)| var == name && !val.is_empty())
+        {
+            settings.push((setting.to_string(), val.clone()));
+            names.push(name.to_string());
+        }
+    }
+    names.sort();
+    (settings, names)
+}
+
+/// Where each setting of [`config_settings`] comes from once `config_file`
+/// has been read: `env <NAME>` when a variable of `vars` sets it, `file`
+/// when the configuration files do, `default` otherwise.
+pub fn setting_sources(
+    config_file: &str,
+    config: &Config,
+    dbinfo: &DbInfo,
+    vars: impl Iterator<Item = (String, String)>,
+) -> Vec<(&'static str, String)> {
+    let file_settings = fs::read_to_string(config_file)
+        .map(|content| expand_includes(Path::new(config_file), &content, config, "", 0))
+        .unwrap_or_default();
+    let file_settings = select_section(file_settings, &config.database_section);
+    let vars: Vec<(String, String)> = vars.collect();
+    let env_names: Vec<(String, String)> = vars
+        .iter()
+        .filter_map(|(name, _)| {
+            let var = name.strip_prefix(ENV_PREFIX)?.to_lowercase();
+            Some((var, name.clone()))
+        })
+        .collect();
+    let libpq_name = |setting: &str| {
+        let (_, name) = LIBPQ_ENV.iter().find(|(var, _)| *var == setting)?;
+        vars.iter()
+            .any(|(var, val)| var == name && !val.is_empty())
+            .then_some(*name)
+    };
+    config_settings(config, dbinfo)
+        .into_iter()
+        .map(|(setting, _)| {
+            let env = env_names
+                .iter()
+                .find(|(var, _)| var == setting && slot_prefix(setting).is_none());
+            let in_file = file_settings.iter().any(|(var, _)| {
+                var == setting || slot_prefix(setting).is_some_and(|prefix| var.starts_with(prefix))
+            });
+            let source = match env {
+                Some((_, name)) => format!("env {name}"),
+                None if in_file => "file".to_string(),
+                None => match libpq_name(setting) {
+                    Some(name) => format!("env {name}"),
+                    None => "default".to_string(),
+                },
+            };
+            (setting, source)
+        })
+        .collect()
+}
+
+/// Header of a section line, `[database db1]` giving `database db1`, its
+/// words separated by single spaces.
+fn parse_section_line(line: &str) -> Option<String> {
+    let l = line.split('#').next().unwrap_or_default().trim();
+    let header = l.strip_prefix('[')?.strip_suffix(']')?;
+    Some(header.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+/// Replace each `${NAME}` of a configuration value by the value `lookup`
+/// gives for `NAME`, failing with the first name it has none for. A `$`
+/// not followed by a complete `${...}` is kept as is.
+fn expand_env_vars(val: &str, lookup: impl Fn(&str) -> Option<String>) -> Result<String, String> {
+    let mut expanded = String::new();
+    let mut rest = val;
+    while let Some(start) = rest.find("${") {
+        let Some(len) = rest[start + 2..].find('}') else {
+            break;
+        };
+        let name = &rest[start + 2..start + 2 + len];
+        expanded.push_str(&rest[..start]);
+        expanded.push_str(&lookup(name).ok_or_else(|| name.to_string())?);
+        rest = &rest[start + 3 + len..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// Parse a single configuration line into `key=value` components.
+fn parse_config_line(line: &str) -> Option<(String, String)> {
+    let mut l = line.replace('\r', "");
+    if let Some(idx) = l.find('#') {
+        l = l[..idx].to_string();
+    }
+    let l = l.trim();
+    if l.is_empty() {
+        return None;
+    }
+    let parts: Vec<&str> = l.splitn(2, '=').collect();
+    if parts.len() != 2 {
+        return None;
+    }
+    let var = parts[0].trim().to_lowercase();
+    let val = parts[1].trim().to_string();
+    Some((var, val))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        apply_positive_float, changed_settings, class_priority_list, config_settings,
+        database_sections, expand_env_vars, merge_env_settings, parse_config_line, parse_size,
+        read_config, schema_list, setting_sources, slot_list, wildcard_match,
+    };
+    use crate::constants::{DEFAULT_SCHEMA, SCHEMA_NAME_MAX};
+    use crate::default_dbinfo;
+    use crate::logging::capture_log;
+    use crate::model::{
+        Config, DbInfo, DstPolicy, ExecutionMode, IoClass, LogMinMessages, LogRotationAge,
+        LogRotationCompress,
+    };
+    use std::collections::BTreeMap;
+    use std::fs;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn temp_path(prefix: &str) -> std::path::PathBuf {
+        // SystemTime::now().as_nanos() collides ~95% of the time on macOS
+        // for back-to-back calls; pair it with a process-wide counter so
+        // every call really is unique.
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+        std::env::temp_dir().join(format!("{prefix}_{now}_{n}"))
+    }
+
+    /// Build a Config seeded with sentinel values that make it easy to detect
+    /// which field a test wrote to.
+    fn float_test_config() -> Config {
+        Config {
+            debug: false,
+            pidfile: "/tmp/pg_dbms_job.pid".to_string(),
+            logfile: String::new(),
+            logfile_mode: 0,
+            logfile_owner: String::new(),
+            logfile_group: String::new(),
+            log_truncate_on_rotation: false,
+            job_queue_interval: 7.0,
+            job_queue_processes: 1024,
+            pool_size: 100,
+            nap_time: 11.0,
+            startup_delay: 13.0,
+            error_delay: 17.0,
+            stats_interval: 0,
+            job_run_details: crate::model::JobRunDetails::All,
+            stale_job_timeout: 3600.0,
+            notify_debounce: 0.0,
+            schedule_refresh_interval: 60.0,
+            max_notifications_per_cycle: 10000,
+            max_job_size: 0,
+            hard_max_run_duration: 0.0,
+            hard_max_run_overruns: 3,
+            job_nice: 0,
+            job_io_class: crate::model::IoClass::None,
+            job_io_priority: 4,
+            connection_reserve: 0,
+            job_cgroup: String::new(),
+            executable_job_owners: String::new(),
+            executable_os_users: String::new(),
+            log_retention_days: 0,
+            log_compress: false,
+            max_nap_time: 0.0,
+            reconnect_max_delay: 60.0,
+            reconnect_max_attempts: 0,
+            standby_check_interval: 0.0,
+            job_run_timeout: 0.0,
+            max_failures: 0,
+            connection_check_interval: 0.0,
+            multiple_schedulers: false,
+            semaphores: BTreeMap::new(),
+            job_classes: BTreeMap::new(),
+            job_class_priorities: BTreeMap::new(),
+            run_details_maintenance_interval: 0.0,
+            run_details_retention_months: 3,
+            worker_hang_timeout: 600.0,
+            job_execution_mode: crate::model::ExecutionMode::SetRole,
+            dst_policy: crate::model::DstPolicy::Server,
+            ssh_host: String::new(),
+            ssh_port: 22,
+            ssh_user: String::new(),
+            ssh_key: String::new(),
+            log_rotation_size: 0,
+            log_rotation_count: 5,
+            log_rotation_age: crate::model::LogRotationAge::None,
+            log_destination: crate::model::LogDestination::File,
+            syslog_facility: "local0".to_string(),
+            syslog_ident: "pg_dbms_job".to_string(),
+            log_min_messages: crate::model::LogMinMessages::Log,
+            log_job_notices: false,
+            log_rotation_compress: crate::model::LogRotationCompress::None,
+            scheduler_event_log: false,
+            debug_ring_size: 0,
+            log_gelf_endpoint: String::new(),
+            auto_reload: false,
+            strict_config: false,
+            database_section: String::new(),
+            schemas: DEFAULT_SCHEMA.to_string(),
+            schema: DEFAULT_SCHEMA.to_string(),
+        }
+    }
+
+    #[test]
+    fn parse_config_line_basic() {
+        let parsed = parse_config_line("host = localhost");
+        assert_eq!(parsed, Some(("host".to_string(), "localhost".to_string())));
+    }
+
+    #[test]
+    fn parse_config_line_ignores_comments() {
+        assert_eq!(parse_config_line("# just a comment"), None);
+        let parsed = parse_config_line("logfile=/tmp/test.log # rotate");
+        assert_eq!(
+            parsed,
+            Some(("logfile".to_string(), "/tmp/test.log".to_string()))
+        );
+    }
+
+    #[test]
+    fn parse_config_line_empty() {
+        assert_eq!(parse_config_line(""), None);
+        assert_eq!(parse_config_line("   "), None);
+        assert_eq!(parse_config_line("  \t  "), None);
+    }
+
+    #[test]
+    fn parse_config_line_no_equals() {
+        assert_eq!(parse_config_line("no_equals_here"), None);
+    }
+
+    #[test]
+    fn parse_config_line_strips_carriage_return() {
+        let parsed = parse_config_line("host = myhost\r");
+        assert_eq!(parsed, Some(("host".to_string(), "myhost".to_string())));
+    }
+
+    #[test]
+    fn parse_config_line_value_with_equals() {
+        let parsed = parse_config_line("passwd = a=b=c");
+        assert_eq!(parsed, Some(("passwd".to_string(), "a=b=c".to_string())));
+    }
+
+    #[test]
+    fn parse_config_line_case_insensitive_key() {
+        let parsed = parse_config_line("HOST = myhost");
+        assert_eq!(parsed, Some(("host".to_string(), "myhost".to_string())));
+    }
+
+    #[test]
+    fn read_config_updates_values() {
+        let mut config = Config {
+            debug: false,
+            pidfile: "/tmp/pg_dbms_job.pid".to_string(),
+            logfile: "".to_string(),
+            logfile_mode: 0,
+            logfile_owner: String::new(),
+            logfile_group: String::new(),
+            log_truncate_on_rotation: false,
+            job_queue_interval: 0.1,
+            job_queue_processes: 1024,
+            pool_size: 100,
+            nap_time: 0.1,
+            startup_delay: 3.0,
+            error_delay: 0.5,
+            stats_interval: 0,
+            job_run_details: crate::model::JobRunDetails::All,
+            stale_job_timeout: 3600.0,
+            notify_debounce: 0.0,
+            schedule_refresh_interval: 60.0,
+            max_notifications_per_cycle: 10000,
+            max_job_size: 0,
+            hard_max_run_duration: 0.0,
+            hard_max_run_overruns: 3,
+            job_nice: 0,
+            job_io_class: crate::model::IoClass::None,
+            job_io_priority: 4,
+            connection_reserve: 0,
+            job_cgroup: String::new(),
+            executable_job_owners: String::new(),
+            executable_os_users: String::new(),
+            log_retention_days: 0,
+            log_compress: false,
+            max_nap_time: 0.0,
+            reconnect_max_delay: 60.0,
+            reconnect_max_attempts: 0,
+            standby_check_interval: 0.0,
+            job_run_timeout: 0.0,
+            max_failures: 0,
+            connection_check_interval: 0.0,
+            multiple_schedulers: false,
+            semaphores: BTreeMap::new(),
+            job_classes: BTreeMap::new(),
+            job_class_priorities: BTreeMap::new(),
+            run_details_maintenance_interval: 0.0,
+            run_details_retention_months: 3,
+            worker_hang_timeout: 600.0,
+            job_execution_mode: crate::model::ExecutionMode::SetRole,
+            dst_policy: crate::model::DstPolicy::Server,
+            ssh_host: String::new(),
+            ssh_port: 22,
+            ssh_user: String::new(),
+            ssh_key: String::new(),
+            log_rotation_size: 0,
+            log_rotation_count: 5,
+            log_rotation_age: crate::model::LogRotationAge::None,
+            log_destination: crate::model::LogDestination::File,
+            syslog_facility: "local0".to_string(),
+            syslog_ident: "pg_dbms_job".to_string(),
+            log_min_messages: crate::model::LogMinMessages::Log,
+            log_job_notices: false,
+            log_rotation_compress: crate::model::LogRotationCompress::None,
+            scheduler_event_log: false,
+            debug_ring_size: 0,
+            log_gelf_endpoint: String::new(),
+            auto_reload: false,
+            strict_config: false,
+            database_section: String::new(),
+            schemas: DEFAULT_SCHEMA.to_string(),
+            schema: DEFAULT_SCHEMA.to_string(),
+        };
+        let mut dbinfo = default_dbinfo();
+
+        let path = temp_path("pg_dbms_job_test.conf");
+        let content = r#"
+pidfile=/tmp/pg_dbms_job_test.pid
+debug=1
+job_queue_interval=7.5
+job_queue_processes=50
+nap_time=0.2
+host=127.0.0.1
+database=testdb
+user=tester
+passwd=secret
+port=5433
+log_truncate_on_rotation=1
+"#;
+        fs::write(&path, content).expect("write temp config");
+
+        read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, false);
+
+        assert_eq!(config.pidfile, "/tmp/pg_dbms_job_test.pid");
+        assert!(config.debug);
+        assert_eq!(config.job_queue_interval, 7.5);
+        assert_eq!(config.job_queue_processes, 50);
+        assert_eq!(config.nap_time, 0.2);
+        assert!(config.log_truncate_on_rotation);
+        assert_eq!(dbinfo.host, "127.0.0.1");
+        assert_eq!(dbinfo.database, "testdb");
+        assert_eq!(dbinfo.user, "tester");
+        assert_eq!(dbinfo.passwd, "secret");
+        assert_eq!(dbinfo.port, 5433);
+
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn read_config_parses_job_run_details_and_stale_job_timeout() {
+        let mut config = float_test_config();
+        let mut dbinfo = default_dbinfo();
+        let path = temp_path("pg_dbms_job_reap.conf");
+        fs::write(&path, "job_run_details=errors\nstale_job_timeout=120\n")
+            .expect("write temp config");
+
+        read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, false);
+
+        assert_eq!(config.job_run_details, crate::model::JobRunDetails::Errors);
+        assert_eq!(config.stale_job_timeout, 120.0);
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn read_config_rejects_negative_stale_job_timeout() {
+        let mut config = float_test_config();
+        let original = config.stale_job_timeout;
+        let mut dbinfo = default_dbinfo();
+        let path = temp_path("pg_dbms_job_reap_bad.conf");
+        // Negative and non-numeric are rejected; the field keeps its value.
+        fs::write(&path, "stale_job_timeout=-5\n").expect("write temp config");
+
+        read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, false);
+
+        assert_eq!(config.stale_job_timeout, original);
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn read_config_allows_zero_stale_job_timeout_to_disable() {
+        let mut config = float_test_config();
+        let mut dbinfo = default_dbinfo();
+        let path = temp_path("pg_dbms_job_reap_zero.conf");
+        fs::write(&path, "stale_job_timeout=0\n").expect("write temp config");
+
+        read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, false);
+
+        assert_eq!(config.stale_job_timeout, 0.0);
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn read_config_parses_notify_debounce_and_rejects_negative() {
+        let mut config = float_test_config();
+        let mut dbinfo = default_dbinfo();
+        let path = temp_path("pg_dbms_job_debounce.conf");
+        fs::write(&path, "notify_debounce=0.25\n").expect("write temp config");
+        read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, false);
+        assert_eq!(config.notify_debounce, 0.25);
+
+        fs::write(&path, "notify_debounce=-1\n").expect("write temp config");
+        read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, false);
+        assert_eq!(config.notify_debounce, 0.25);
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn changed_settings_lists_changes_and_masks_password() {
+        let config = float_test_config();
+        let dbinfo = DbInfo {
+            host: "db1".to_string(),
+            database: "app".to_string(),
+            user: "sched".to_string(),
+            passwd: "old secret".to_string(),
+            ..default_dbinfo()
+        };
+        let before = config_settings(&config, &dbinfo);
+        assert!(changed_settings(&before, &before).is_empty());
+
+        let mut config2 = config.clone();
+        config2.nap_time = 0.5;
+        config2.job_io_class = IoClass::Idle;
+        let mut dbinfo2 = dbinfo.clone();
+        dbinfo2.passwd = "new secret".to_string();
+        let after = config_settings(&config2, &dbinfo2);
+        assert_eq!(
+            changed_settings(&before, &after),
+            vec![
+                ("nap_time", "11".to_string(), "0.5".to_string()),
+                ("job_io_class", "none".to_string(), "idle".to_string()),
+                ("passwd", "********".to_string(), "********".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn read_config_parses_max_nap_time_and_rejects_negative() {
+        let mut config = float_test_config();
+        let mut dbinfo = default_dbinfo();
+        let path = temp_path("pg_dbms_job_max_nap.conf");
+        fs::write(&path, "max_nap_time=5\n").expect("write temp config");
+        read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, false);
+        assert_eq!(config.max_nap_time, 5.0);
+
+        fs::write(&path, "max_nap_time=-1\n").expect("write temp config");
+        read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, false);
+        assert_eq!(config.max_nap_time, 5.0);
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn read_config_parses_worker_hang_timeout() {
+        let mut config = float_test_config();
+        let mut dbinfo = default_dbinfo();
+        let path = temp_path("pg_dbms_job_hang.conf");
+        fs::write(&path, "worker_hang_timeout=0\n").expect("write temp config");
+        read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, false);
+        assert_eq!(config.worker_hang_timeout, 0.0);
+
+        fs::write(&path, "worker_hang_timeout=nan\n").expect("write temp config");
+        read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, false);
+        assert_eq!(config.worker_hang_timeout, 0.0);
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn read_config_parses_run_details_maintenance() {
+        let mut config = float_test_config();
+        let mut dbinfo = default_dbinfo();
+        let path = temp_path("pg_dbms_job_run_details_maintenance.conf");
+        fs::write(
+            &path,
+            "run_details_maintenance_interval=86400\nrun_details_retention_months=6\n",
+        )
+        .expect("write temp config");
+        read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, false);
+        assert_eq!(config.run_details_maintenance_interval, 86400.0);
+        assert_eq!(config.run_details_retention_months, 6);
+
+        fs::write(
+            &path,
+            "run_details_maintenance_interval=-1\nrun_details_retention_months=-1\n",
+        )
+        .expect("write temp config");
+        read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, false);
+        assert_eq!(config.run_details_maintenance_interval, 86400.0);
+        assert_eq!(config.run_details_retention_months, 6);
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn read_config_parses_semaphores_and_job_classes_and_drops_removed_ones() {
+        let mut config = float_test_config();
+        let mut dbinfo = default_dbinfo();
+        let path = temp_path("pg_dbms_job_semaphore.conf");
+        fs::write(
+            &path,
+            "semaphore.etl=3\nSemaphore.Warehouse = 2\njob_class.etl=8\njob_class.monitoring=2\n",
+        )
+        .expect("write temp config");
+        read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, false);
+        assert_eq!(slot_list(&config.semaphores), "etl=3,warehouse=2");
+        assert_eq!(slot_list(&config.job_classes), "etl=8,monitoring=2");
+
+        // An invalid count keeps the previous one, a removed line drops it.
+        fs::write(&path, "semaphore.etl=0\nsemaphore.=4\njob_class.etl=-1\n")
+            .expect("write temp config");
+        read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, false);
+        assert_eq!(slot_list(&config.semaphores), "etl=3");
+        assert_eq!(slot_list(&config.job_classes), "etl=8");
+
+        fs::write(&path, "debug=0\n").expect("write temp config");
+        read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, false);
+        assert_eq!(config.semaphores, BTreeMap::new());
+        assert_eq!(slot_list(&config.semaphores), "none");
+        assert_eq!(slot_list(&config.job_classes), "none");
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn read_config_parses_job_class_priorities() {
+        let mut config = float_test_config();
+        let mut dbinfo = default_dbinfo();
+        let path = temp_path("pg_dbms_job_class_priority.conf");
+        fs::write(
+            &path,
+            "job_class.etl=8
+job_class.etl.nice=15
+job_class.etl.io_class=idle
+             job_class.report.io_priority=6
+",
+        )
+        .expect("write temp config");
+        read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, false);
+        assert_eq!(slot_list(&config.job_classes), "etl=8");
+        assert_eq!(
+            class_priority_list(&config.job_class_priorities),
+            "etl.nice=15,etl.io_class=idle,report.io_priority=6"
+        );
+
+        // An invalid value keeps the previous one, a removed line drops it.
+        fs::write(
+            &path,
+            "job_class.etl.nice=20
+job_class.report.io_class=realtime
+job_class..nice=3
+",
+        )
+        .expect("write temp config");
+        let (_, lines) =
+            capture_log(|| read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, false));
+        let errors: Vec<&str> = lines
+            .iter()
+            .filter(|(level, _)| level == "ERROR")
+            .map(|(_, msg)| msg.as_str())
+            .collect();
+        assert_eq!(errors.len(), 3, "{errors:?}");
+        assert!(errors[0].starts_with("Invalid job_class.etl.nice value 20"));
+        assert!(errors[2].contains("a job class priority needs the name of its class"));
+        assert_eq!(
+            class_priority_list(&config.job_class_priorities),
+            "etl.nice=15"
         );
+        assert_eq!(slot_list(&config.job_classes), "none");
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn read_config_parses_max_notifications_per_cycle() {
+        let mut config = float_test_config();
+        let mut dbinfo = default_dbinfo();
+        let path = temp_path("pg_dbms_job_notify_cap.conf");
+        fs::write(&path, "max_notifications_per_cycle=500\n").expect("write temp config");
+        read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, false);
+        assert_eq!(config.max_notifications_per_cycle, 500);
+
+        fs::write(&path, "max_notifications_per_cycle=0\n").expect("write temp config");
+        read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, false);
+        assert_eq!(config.max_notifications_per_cycle, 500);
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn read_config_parses_max_job_size() {
+        let mut config = float_test_config();
+        let mut dbinfo = default_dbinfo();
+        let path = temp_path("pg_dbms_job_max_job_size.conf");
+        fs::write(&path, "max_job_size=1048576\n").expect("write temp config");
+        read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, false);
+        assert_eq!(config.max_job_size, 1048576);
+
+        fs::write(&path, "max_job_size=-1\n").expect("write temp config");
+        read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, false);
+        assert_eq!(config.max_job_size, 1048576);
+
+        fs::write(&path, "max_job_size=0\n").expect("write temp config");
+        read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, false);
+        assert_eq!(config.max_job_size, 0);
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn read_config_parses_job_session_settings() {
+        let mut config = float_test_config();
+        let mut dbinfo = default_dbinfo();
+        let path = temp_path("pg_dbms_job_job_session_settings.conf");
+        fs::write(
+            &path,
+            "job_session_settings = work_mem=256MB, client_encoding=UTF8, idle_in_transaction_session_timeout=5min\n",
+        )
+        .expect("write temp config");
+        read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, false);
+        let expected = vec![
+            ("work_mem".to_string(), "256MB".to_string()),
+            ("client_encoding".to_string(), "UTF8".to_string()),
+            (
+                "idle_in_transaction_session_timeout".to_string(),
+                "5min".to_string(),
+            ),
+        ];
+        assert_eq!(dbinfo.job_session_settings, expected);
+
+        for invalid in [
+            "work_mem",
+            "work_mem=",
+            "work mem=1MB",
+            "=1",
+            "client_encoding=LATIN1",
+        ] {
+            fs::write(&path, format!("job_session_settings={invalid}\n"))
+                .expect("write temp config");
+            read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, false);
+            assert_eq!(dbinfo.job_session_settings, expected, "{invalid}");
+        }
+
+        fs::write(&path, "job_session_settings=\n").expect("write temp config");
+        read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, false);
+        assert!(dbinfo.job_session_settings.is_empty());
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn read_config_parses_log_retention() {
+        let mut config = float_test_config();
+        let mut dbinfo = default_dbinfo();
+        let path = temp_path("pg_dbms_job_log_retention.conf");
+        fs::write(&path, "log_retention_days=14\nlog_compress=1\n").expect("write temp config");
+        read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, false);
+        assert_eq!(config.log_retention_days, 14);
+        assert!(config.log_compress);
+
+        fs::write(&path, "log_retention_days=-1\nlog_compress=0\n").expect("write temp config");
+        read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, false);
+        assert_eq!(config.log_retention_days, 14);
+        assert!(!config.log_compress);
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn read_config_parses_connection_reserve() {
+        let mut config = float_test_config();
+        let mut dbinfo = default_dbinfo();
+        let path = temp_path("pg_dbms_job_connection_reserve.conf");
+        fs::write(&path, "connection_reserve=10\n").expect("write temp config");
+        read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, false);
+        assert_eq!(config.connection_reserve, 10);
+
+        fs::write(&path, "connection_reserve=ten\n").expect("write temp config");
+        read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, false);
+        assert_eq!(config.connection_reserve, 10);
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn read_config_parses_hard_max_run_policy() {
+        let mut config = float_test_config();
+        let mut dbinfo = default_dbinfo();
+        let path = temp_path("pg_dbms_job_hard_max.conf");
+        fs::write(
+            &path,
+            "hard_max_run_duration=900\nhard_max_run_overruns=5\njob_run_timeout=3600\nmax_failures=16\n",
+        )
+        .expect("write temp config");
+        read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, false);
+        assert_eq!(config.hard_max_run_duration, 900.0);
+        assert_eq!(config.hard_max_run_overruns, 5);
+        assert_eq!(config.job_run_timeout, 3600.0);
+        assert_eq!(config.max_failures, 16);
+
+        fs::write(
+            &path,
+            "hard_max_run_duration=-1\nhard_max_run_overruns=0\njob_run_timeout=-1\nmax_failures=-1\n",
+        )
+        .expect("write temp config");
+        read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, false);
+        assert_eq!(config.hard_max_run_duration, 900.0);
+        assert_eq!(config.hard_max_run_overruns, 5);
+        assert_eq!(config.job_run_timeout, 3600.0);
+        assert_eq!(config.max_failures, 16);
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn read_config_parses_job_priority() {
+        let mut config = float_test_config();
+        let mut dbinfo = default_dbinfo();
+        let path = temp_path("pg_dbms_job_priority.conf");
+        fs::write(
+            &path,
+            "job_nice=10\njob_io_class=Best-Effort\njob_io_priority=6\n",
+        )
+        .expect("write temp config");
+        read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, false);
+        assert_eq!(config.job_nice, 10);
+        assert_eq!(config.job_io_class, IoClass::BestEffort);
+        assert_eq!(config.job_io_priority, 6);
+
+        fs::write(
+            &path,
+            "job_nice=20\njob_io_class=realtime\njob_io_priority=8\n",
+        )
+        .expect("write temp config");
+        read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, false);
+        assert_eq!(config.job_nice, 10);
+        assert_eq!(config.job_io_class, IoClass::BestEffort);
+        assert_eq!(config.job_io_priority, 6);
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn read_config_parses_job_execution_mode() {
+        let mut config = float_test_config();
+        let mut dbinfo = default_dbinfo();
+        let path = temp_path("pg_dbms_job_execution_mode.conf");
+        fs::write(&path, "job_execution_mode=Security_Definer\n").expect("write temp config");
+        read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, false);
+        assert_eq!(config.job_execution_mode, ExecutionMode::SecurityDefiner);
+
+        fs::write(&path, "job_execution_mode=sudo\n").expect("write temp config");
+        read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, false);
+        assert_eq!(config.job_execution_mode, ExecutionMode::SecurityDefiner);
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn read_config_parses_dst_policy() {
+        let mut config = float_test_config();
+        let mut dbinfo = default_dbinfo();
+        let path = temp_path("pg_dbms_job_dst_policy.conf");
+        fs::write(&path, "dst_policy=skip\n").expect("write temp config");
+        read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, false);
+        assert_eq!(config.dst_policy, DstPolicy::Skip);
+
+        fs::write(&path, "dst_policy=twice\n").expect("write temp config");
+        read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, false);
+        assert_eq!(config.dst_policy, DstPolicy::Skip);
+        let _ = fs::remove_file(path);
     }
-}
 
-/// Parse a single configuration line into `key=value` components.
-fn parse_config_line(line: &str) -> Option<(String, String)> {
-    let mut l = line.replace('\r', "");
-    if let Some(idx) = l.find('#') {
-        l = l[..idx].to_string();
+    #[test]
+    fn read_config_parses_job_cgroup() {
+        let mut config = float_test_config();
+        let mut dbinfo = default_dbinfo();
+        let path = temp_path("pg_dbms_job_cgroup.conf");
+        fs::write(&path, "job_cgroup=pg_dbms_job.slice/jobs\n").expect("write temp config");
+        read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, false);
+        assert_eq!(config.job_cgroup, "pg_dbms_job.slice/jobs");
+        let _ = fs::remove_file(path);
     }
-    let l = l.trim();
-    if l.is_empty() {
-        return None;
+
+    #[test]
+    fn parse_size_units() {
+        assert_eq!(parse_size("512"), Some(512));
+        assert_eq!(parse_size("10kB"), Some(10 * 1024));
+        assert_eq!(parse_size("100MB"), Some(100 * 1024 * 1024));
+        assert_eq!(parse_size("1 gb"), Some(1 << 30));
+        assert_eq!(parse_size("0"), Some(0));
+        assert_eq!(parse_size("MB"), None);
+        assert_eq!(parse_size("10TB"), None);
+        assert_eq!(parse_size("-1"), None);
     }
-    let parts: Vec<&str> = l.splitn(2, '=').collect();
-    if parts.len() != 2 {
-        return None;
+
+    #[test]
+    fn read_config_parses_log_rotation() {
+        let mut config = float_test_config();
+        let mut dbinfo = default_dbinfo();
+        let path = temp_path("pg_dbms_job_log_rotation.conf");
+        fs::write(&path, "log_rotation_size=100MB\nlog_rotation_count=10\n")
+            .expect("write temp config");
+        read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, false);
+        assert_eq!(config.log_rotation_size, 100 * 1024 * 1024);
+        assert_eq!(config.log_rotation_count, 10);
+
+        fs::write(&path, "log_rotation_size=big\n").expect("write temp config");
+        read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, false);
+        assert_eq!(config.log_rotation_size, 100 * 1024 * 1024);
+
+        fs::write(&path, "log_rotation_age=daily\n").expect("write temp config");
+        read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, false);
+        assert_eq!(config.log_rotation_age, LogRotationAge::Daily);
+        fs::write(&path, "log_rotation_age=weekly\n").expect("write temp config");
+        read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, false);
+        assert_eq!(config.log_rotation_age, LogRotationAge::Daily);
+
+        fs::write(&path, "log_rotation_compress=ZSTD\n").expect("write temp config");
+        read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, false);
+        assert_eq!(config.log_rotation_compress, LogRotationCompress::Zstd);
+        fs::write(&path, "log_rotation_compress=xz\n").expect("write temp config");
+        read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, false);
+        assert_eq!(config.log_rotation_compress, LogRotationCompress::Zstd);
+        let _ = fs::remove_file(path);
     }
-    let var = parts[0].trim().to_lowercase();
-    let val = parts[1].trim().to_string();
-    Some((var, val))
-}
 
-#[cfg(test)]
-mod tests {
-    use super::{apply_positive_float, parse_config_line, read_config};
-    use crate::model::{Config, DbInfo};
-    use std::fs;
-    use std::sync::atomic::{AtomicU64, Ordering};
-    use std::time::{SystemTime, UNIX_EPOCH};
+    #[test]
+    fn read_config_parses_log_min_messages() {
+        let mut config = float_test_config();
+        let mut dbinfo = default_dbinfo();
+        let path = temp_path("pg_dbms_job_log_min_messages.conf");
+        fs::write(&path, "log_min_messages=warning\n").expect("write temp config");
+        read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, false);
+        assert_eq!(config.log_min_messages, LogMinMessages::Warning);
 
-    fn temp_path(prefix: &str) -> std::path::PathBuf {
-        // SystemTime::now().as_nanos() collides ~95% of the time on macOS
-        // for back-to-back calls; pair it with a process-wide counter so
-        // every call really is unique.
-        static COUNTER: AtomicU64 = AtomicU64::new(0);
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_nanos();
-        let n = COUNTER.fetch_add(1, Ordering::Relaxed);
-        std::env::temp_dir().join(format!("{prefix}_{now}_{n}"))
+        fs::write(&path, "log_min_messages=notice\n").expect("write temp config");
+        read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, false);
+        assert_eq!(config.log_min_messages, LogMinMessages::Warning);
+        let _ = fs::remove_file(path);
     }
 
-    /// Build a Config seeded with sentinel values that make it easy to detect
-    /// which field a test wrote to.
-    fn float_test_config() -> Config {
-        Config {
-            debug: false,
-            pidfile: "/tmp/pg_dbms_job.pid".to_string(),
-            logfile: String::new(),
-            log_truncate_on_rotation: false,
-            job_queue_interval: 7.0,
-            job_queue_processes: 1024,
-            pool_size: 100,
-            nap_time: 11.0,
-            startup_delay: 13.0,
-            error_delay: 17.0,
-            stats_interval: 0,
-            job_run_details: crate::model::JobRunDetails::All,
-            stale_job_timeout: 3600.0,
-        }
+    #[test]
+    fn wildcards() {
+        assert!(wildcard_match("*.conf", "10-tuning.conf"));
+        assert!(wildcard_match("*.conf", ".conf"));
+        assert!(!wildcard_match("*.conf", "tuning.conf.bak"));
+        assert!(wildcard_match("??-*.c*f", "10-a.conf"));
+        assert!(!wildcard_match("??-*", "1-a"));
+        assert!(wildcard_match("a*b*c", "aXbYbZc"));
+        assert!(wildcard_match("*", ""));
+        assert!(!wildcard_match("?", ""));
     }
 
     #[test]
-    fn parse_config_line_basic() {
-        let parsed = parse_config_line("host = localhost");
-        assert_eq!(parsed, Some(("host".to_string(), "localhost".to_string())));
+    fn read_config_expands_includes() {
+        let mut config = float_test_config();
+        let mut dbinfo = default_dbinfo();
+        let dir = temp_path("pg_dbms_job_include");
+        fs::create_dir_all(dir.join("conf.d")).expect("create conf.d");
+        fs::write(
+            dir.join("main.conf"),
+            "host=main\nport=5433\ninclude = conf.d/*.conf\nuser=main\ninclude=missing.conf\n",
+        )
+        .expect("write main config");
+        fs::write(dir.join("conf.d/20-b.conf"), "port=5435\npasswd=secret\n").unwrap();
+        fs::write(dir.join("conf.d/10-a.conf"), "host=a\nport=5434\nuser=a\n").unwrap();
+        fs::write(dir.join("conf.d/.hidden.conf"), "database=hidden\n").unwrap();
+        fs::write(dir.join("conf.d/30-c.conf.bak"), "database=bak\n").unwrap();
+        // An include cycle stops at the depth limit.
+        fs::write(dir.join("conf.d/40-loop.conf"), "include=40-loop.conf\n").unwrap();
+        let main = dir.join("main.conf");
+        read_config(main.to_str().unwrap(), &mut config, &mut dbinfo, false);
+        assert_eq!(dbinfo.host, "a");
+        assert_eq!(dbinfo.port, 5435);
+        assert_eq!(dbinfo.passwd, "secret");
+        // Set after the include.
+        assert_eq!(dbinfo.user, "main");
+        assert_eq!(dbinfo.database, "");
+        let _ = fs::remove_dir_all(dir);
     }
 
     #[test]
-    fn parse_config_line_ignores_comments() {
-        assert_eq!(parse_config_line("# just a comment"), None);
-        let parsed = parse_config_line("logfile=/tmp/test.log # rotate");
+    fn environment_variables_override_settings() {
+        let config = float_test_config();
+        let dbinfo = default_dbinfo();
+        let vars = [
+            ("PG_DBMS_JOB_PASSWD", " s3cr#t "),
+            ("PG_DBMS_JOB_JOB_QUEUE_PROCESSES", "8"),
+            ("PG_DBMS_JOB_SEMAPHORES", "etl=1"),
+            ("PG_DBMS_JOB_NOT_A_SETTING", "1"),
+            ("PGPASSWORD", "other"),
+        ]
+        .map(|(name, val)| (name.to_string(), val.to_string()));
+        let file_settings = ["host=db1", "passwd=from_file", "semaphore.etl=2"]
+            .into_iter()
+            .filter_map(parse_config_line)
+            .collect();
+        let (settings, names) =
+            merge_env_settings(file_settings, vars.into_iter(), &config, &dbinfo);
+        let pair = |var: &str, val: &str| (var.to_string(), val.to_string());
         assert_eq!(
-            parsed,
-            Some(("logfile".to_string(), "/tmp/test.log".to_string()))
+            settings,
+            [
+                pair("host", "db1"),
+                pair("semaphore.etl", "2"),
+                pair("passwd", "s3cr#t"),
+                pair("job_queue_processes", "8"),
+            ]
+        );
+        assert_eq!(
+            names,
+            ["PG_DBMS_JOB_JOB_QUEUE_PROCESSES", "PG_DBMS_JOB_PASSWD"]
         );
     }
 
     #[test]
-    fn parse_config_line_empty() {
-        assert_eq!(parse_config_line(""), None);
-        assert_eq!(parse_config_line("   "), None);
-        assert_eq!(parse_config_line("  \t  "), None);
+    fn libpq_variables_fill_the_unset_connection_settings() {
+        let config = float_test_config();
+        let dbinfo = default_dbinfo();
+        let vars = [
+            ("PGHOST", "db2"),
+            ("PGPORT", "5433"),
+            ("PGUSER", "from_libpq"),
+            ("PG_DBMS_JOB_USER", "from_prefix"),
+            ("PGDATABASE", ""),
+            ("PGPASSWORD", "pw"),
+        ]
+        .map(|(name, val)| (name.to_string(), val.to_string()));
+        let file_settings = ["host=db1"]
+            .into_iter()
+            .filter_map(parse_config_line)
+            .collect();
+        let (settings, names) =
+            merge_env_settings(file_settings, vars.clone().into_iter(), &config, &dbinfo);
+        let pair = |var: &str, val: &str| (var.to_string(), val.to_string());
+        assert_eq!(
+            settings,
+            [
+                pair("host", "db1"),
+                pair("user", "from_prefix"),
+                pair("port", "5433"),
+                pair("passwd", "pw"),
+            ]
+        );
+        assert_eq!(names, ["PGPASSWORD", "PGPORT", "PG_DBMS_JOB_USER"]);
+
+        let path = temp_path("pg_dbms_job_libpq.conf");
+        fs::write(&path, "host=db1\n").expect("write");
+        let sources = setting_sources(path.to_str().unwrap(), &config, &dbinfo, vars.into_iter());
+        let _ = fs::remove_file(path);
+        let source = |name: &str| {
+            sources
+                .iter()
+                .find(|(setting, _)| *setting == name)
+                .map(|(_, source)| source.as_str())
+                .unwrap()
+        };
+        assert_eq!(source("host"), "file");
+        assert_eq!(source("port"), "env PGPORT");
+        assert_eq!(source("user"), "env PG_DBMS_JOB_USER");
+        assert_eq!(source("database"), "default");
     }
 
     #[test]
-    fn parse_config_line_no_equals() {
-        assert_eq!(parse_config_line("no_equals_here"), None);
+    fn database_sections_override_the_global_settings() {
+        let mut config = float_test_config();
+        let mut dbinfo = default_dbinfo();
+        let dir = temp_path("pg_dbms_job_sections");
+        fs::create_dir_all(&dir).unwrap();
+        let main = dir.join("main.conf");
+        fs::write(
+            &main,
+            "host=db\njob_queue_processes=4\n\
+             [database sales]  # the sales database\ndatabase=sales\njob_queue_processes=8\n\
+             [ database   hr ]\ninclude=hr.conf\n",
+        )
+        .unwrap();
+        fs::write(dir.join("hr.conf"), "database=hr\nhost=db2\n").unwrap();
+        let main = main.to_str().unwrap();
+
+        assert_eq!(database_sections(main, &config), ["sales", "hr"]);
+        read_config(main, &mut config, &mut dbinfo, false);
+        assert_eq!((dbinfo.host.as_str(), dbinfo.database.as_str()), ("db", ""));
+        assert_eq!(config.job_queue_processes, 4);
+
+        config.database_section = "sales".to_string();
+        read_config(main, &mut config, &mut dbinfo, true);
+        assert_eq!(
+            (dbinfo.host.as_str(), dbinfo.database.as_str()),
+            ("db", "sales")
+        );
+        assert_eq!(config.job_queue_processes, 8);
+
+        config.database_section = "hr".to_string();
+        read_config(main, &mut config, &mut dbinfo, true);
+        assert_eq!(
+            (dbinfo.host.as_str(), dbinfo.database.as_str()),
+            ("db2", "hr")
+        );
+        assert_eq!(config.job_queue_processes, 4);
+        fs::remove_dir_all(&dir).unwrap();
     }
 
     #[test]
-    fn parse_config_line_strips_carriage_return() {
-        let parsed = parse_config_line("host = myhost\r");
-        assert_eq!(parsed, Some(("host".to_string(), "myhost".to_string())));
+    fn schema_lists_are_validated_and_deduplicated() {
+        assert_eq!(
+            schema_list(" dbms_job, tenant_a,,tenant_b ,tenant_a").unwrap(),
+            ["dbms_job", "tenant_a", "tenant_b"]
+        );
+        assert_eq!(schema_list("_t2").unwrap(), ["_t2"]);
+        assert_eq!(schema_list(" , ").unwrap_err(), "no schema is listed");
+        for name in ["Tenant", "2tenant", "ten-ant", "\"tenant\""] {
+            assert!(schema_list(name).unwrap_err().contains(name), "{name}");
+        }
+        assert!(schema_list(&"t".repeat(SCHEMA_NAME_MAX)).is_ok());
+        assert!(schema_list(&"t".repeat(SCHEMA_NAME_MAX + 1)).is_err());
     }
 
     #[test]
-    fn parse_config_line_value_with_equals() {
-        let parsed = parse_config_line("passwd = a=b=c");
-        assert_eq!(parsed, Some(("passwd".to_string(), "a=b=c".to_string())));
+    fn setting_sources_tell_file_env_and_default_apart() {
+        let config = float_test_config();
+        let dbinfo = default_dbinfo();
+        let path = temp_path("pg_dbms_job_sources.conf");
+        fs::write(&path, "host=db1\npasswd=from_file\nsemaphore.etl=2\n").expect("write");
+        let vars = [
+            ("PG_DBMS_JOB_PASSWD", "s3cret"),
+            ("PG_DBMS_JOB_SEMAPHORES", "etl=1"),
+        ]
+        .map(|(name, val)| (name.to_string(), val.to_string()));
+        let sources = setting_sources(path.to_str().unwrap(), &config, &dbinfo, vars.into_iter());
+        let _ = fs::remove_file(path);
+        let source = |name: &str| {
+            sources
+                .iter()
+                .find(|(setting, _)| *setting == name)
+                .map(|(_, source)| source.as_str())
+                .unwrap()
+        };
+        assert_eq!(source("host"), "file");
+        assert_eq!(source("passwd"), "env PG_DBMS_JOB_PASSWD");
+        assert_eq!(source("semaphores"), "file");
+        assert_eq!(source("nap_time"), "default");
+        assert_eq!(sources.len(), config_settings(&config, &dbinfo).len());
     }
 
     #[test]
-    fn parse_config_line_case_insensitive_key() {
-        let parsed = parse_config_line("HOST = myhost");
-        assert_eq!(parsed, Some(("host".to_string(), "myhost".to_string())));
+    fn read_config_parses_debug_ring_size() {
+        let mut config = float_test_config();
+        let mut dbinfo = default_dbinfo();
+        let path = temp_path("pg_dbms_job_debug_ring_size.conf");
+        fs::write(&path, "debug_ring_size=1000\n").expect("write temp config");
+        read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, false);
+        assert_eq!(config.debug_ring_size, 1000);
+        assert!(config.buffers("DEBUG") && !config.buffers("LOG"));
+
+        fs::write(&path, "debug_ring_size=-1\n").expect("write temp config");
+        read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, false);
+        assert_eq!(config.debug_ring_size, 1000);
+        let _ = fs::remove_file(path);
     }
 
     #[test]
-    fn read_config_updates_values() {
-        let mut config = Config {
-            debug: false,
-            pidfile: "/tmp/pg_dbms_job.pid".to_string(),
-            logfile: "".to_string(),
-            log_truncate_on_rotation: false,
-            job_queue_interval: 0.1,
-            job_queue_processes: 1024,
-            pool_size: 100,
-            nap_time: 0.1,
-            startup_delay: 3.0,
-            error_delay: 0.5,
-            stats_interval: 0,
-            job_run_details: crate::model::JobRunDetails::All,
-            stale_job_timeout: 3600.0,
+    fn read_config_parses_auto_reload() {
+        let mut config = float_test_config();
+        let mut dbinfo = default_dbinfo();
+        let path = temp_path("pg_dbms_job_auto_reload.conf");
+        fs::write(&path, "auto_reload=1\n").expect("write temp config");
+        read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, false);
+        assert!(config.auto_reload);
+
+        fs::write(&path, "auto_reload=0\n").expect("write temp config");
+        read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, true);
+        assert!(!config.auto_reload);
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn expand_env_vars_replaces_variables() {
+        let lookup = |name: &str| match name {
+            "HOSTNAME" => Some("db1".to_string()),
+            "EMPTY" => Some(String::new()),
+            _ => None,
         };
+        assert_eq!(
+            expand_env_vars("/var/log/${HOSTNAME}/${HOSTNAME}.log", lookup),
+            Ok("/var/log/db1/db1.log".to_string())
+        );
+        assert_eq!(expand_env_vars("a${EMPTY}b", lookup), Ok("ab".to_string()));
+        assert_eq!(
+            expand_env_vars("pa$$word ${", lookup),
+            Ok("pa$$word ${".to_string())
+        );
+        assert_eq!(
+            expand_env_vars("${HOSTNAME}-${DB_PASSWORD}", lookup),
+            Err("DB_PASSWORD".to_string())
+        );
+    }
+
+    #[test]
+    fn read_config_expands_environment_variables() {
+        let mut config = float_test_config();
         let mut dbinfo = DbInfo {
-            host: "".to_string(),
-            database: "".to_string(),
-            user: "".to_string(),
-            passwd: "".to_string(),
-            port: 5432,
+            passwd: "before".to_string(),
+            ..default_dbinfo()
         };
+        let home = std::env::var("HOME").unwrap_or_default();
+        let path = temp_path("pg_dbms_job_env_vars.conf");
+        fs::write(
+            &path,
+            "database=db_${HOME}\npasswd=${PG_DBMS_JOB_TEST_UNDEFINED_VAR}\n",
+        )
+        .expect("write temp config");
+        let (_, lines) =
+            capture_log(|| read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, true));
+        assert_eq!(dbinfo.database, format!("db_{home}"));
+        assert_eq!(dbinfo.passwd, "before");
+        assert!(lines.iter().any(|(level, msg)| level == "ERROR"
+            && msg.starts_with(
+                "Undefined environment variable PG_DBMS_JOB_TEST_UNDEFINED_VAR in the value of passwd"
+            )));
+        let _ = fs::remove_file(path);
+    }
 
-        let path = temp_path("pg_dbms_job_test.conf");
-        let content = r#"
-pidfile=/tmp/pg_dbms_job_test.pid
-debug=1
-job_queue_interval=7.5
-job_queue_processes=50
-nap_time=0.2
-host=127.0.0.1
-database=testdb
-user=tester
-passwd=secret
-port=5433
-log_truncate_on_rotation=1
-"#;
-        fs::write(&path, content).expect("write temp config");
-
-        read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, false);
+    #[test]
+    fn read_config_parses_logfile_access() {
+        let mut config = float_test_config();
+        let mut dbinfo = default_dbinfo();
+        let path = temp_path("pg_dbms_job_logfile_access.conf");
+        fs::write(
+            &path,
+            "logfile_mode=0640\nlogfile_owner=root\nlogfile_group=0\n",
+        )
+        .expect("write temp config");
+        read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, true);
+        assert_eq!(config.logfile_mode, 0o640);
+        assert_eq!(config.logfile_owner, "root");
+        assert_eq!(config.logfile_group, "0");
 
-        assert_eq!(config.pidfile, "/tmp/pg_dbms_job_test.pid");
-        assert!(config.debug);
-        assert_eq!(config.job_queue_interval, 7.5);
-        assert_eq!(config.job_queue_processes, 50);
-        assert_eq!(config.nap_time, 0.2);
-        assert!(config.log_truncate_on_rotation);
-        assert_eq!(dbinfo.host, "127.0.0.1");
-        assert_eq!(dbinfo.database, "testdb");
-        assert_eq!(dbinfo.user, "tester");
-        assert_eq!(dbinfo.passwd, "secret");
-        assert_eq!(dbinfo.port, 5433);
+        fs::write(
+            &path,
+            "logfile_mode=0999\nlogfile_owner=pg_dbms_job_no_such_user\n",
+        )
+        .expect("write temp config");
+        read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, true);
+        assert_eq!(config.logfile_mode, 0o640);
+        assert_eq!(config.logfile_owner, "root");
+        let _ = fs::remove_file(path);
+    }
 
+    #[test]
+    fn read_config_parses_connect_timeout_and_keepalives() {
+        let mut config = float_test_config();
+        let mut dbinfo = default_dbinfo();
+        let path = temp_path("pg_dbms_job_keepalives.conf");
+        fs::write(
+            &path,
+            "connect_timeout=0\nkeepalives=0\nkeepalives_idle=30\n\
+             keepalives_interval=-1\nkeepalives_count=3\n",
+        )
+        .expect("write temp config");
+        read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, true);
+        assert_eq!(dbinfo.connect_timeout, 0);
+        assert!(!dbinfo.keepalives);
+        assert_eq!(dbinfo.keepalives_idle, 30);
+        assert_eq!(dbinfo.keepalives_interval, 10);
+        assert_eq!(dbinfo.keepalives_count, 3);
         let _ = fs::remove_file(path);
     }
 
     #[test]
-    fn read_config_parses_job_run_details_and_stale_job_timeout() {
+    fn strict_config_refuses_a_file_with_an_unknown_setting() {
         let mut config = float_test_config();
-        let mut dbinfo = DbInfo {
-            host: String::new(),
-            database: String::new(),
-            user: String::new(),
-            passwd: String::new(),
-            port: 5432,
-        };
-        let path = temp_path("pg_dbms_job_reap.conf");
-        fs::write(&path, "job_run_details=errors\nstale_job_timeout=120\n")
-            .expect("write temp config");
+        let mut dbinfo = default_dbinfo();
+        let path = temp_path("pg_dbms_job_strict_config.conf");
+        fs::write(&path, "strict_config=1\npool_size=7\n").expect("write temp config");
+        read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, true);
+        assert!(config.strict_config);
+        assert_eq!(config.pool_size, 7);
 
-        read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, false);
+        fs::write(
+            &path,
+            "strict_config=1\npool_size=9\njob_queue_procesess=8\n",
+        )
+        .expect("write temp config");
+        let (_, lines) =
+            capture_log(|| read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, true));
+        assert_eq!(config.pool_size, 7);
+        assert_eq!(
+            lines,
+            vec![(
+                "ERROR".to_string(),
+                "Unknown setting job_queue_procesess in configuration file with \
+                 strict_config, configuration not applied"
+                    .to_string()
+            )]
+        );
 
-        assert_eq!(config.job_run_details, crate::model::JobRunDetails::Errors);
-        assert_eq!(config.stale_job_timeout, 120.0);
+        fs::write(&path, "pool_size=9\njob_queue_procesess=8\n").expect("write temp config");
+        config.strict_config = false;
+        read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, true);
+        assert_eq!(config.pool_size, 9);
         let _ = fs::remove_file(path);
     }
 
     #[test]
-    fn read_config_rejects_negative_stale_job_timeout() {
+    fn read_config_parses_log_gelf_endpoint() {
         let mut config = float_test_config();
-        let original = config.stale_job_timeout;
-        let mut dbinfo = DbInfo {
-            host: String::new(),
-            database: String::new(),
-            user: String::new(),
-            passwd: String::new(),
-            port: 5432,
-        };
-        let path = temp_path("pg_dbms_job_reap_bad.conf");
-        // Negative and non-numeric are rejected; the field keeps its value.
-        fs::write(&path, "stale_job_timeout=-5\n").expect("write temp config");
+        let mut dbinfo = default_dbinfo();
+        let path = temp_path("pg_dbms_job_log_gelf_endpoint.conf");
+        fs::write(&path, "log_gelf_endpoint=tcp://graylog:12201\n").expect("write temp config");
+        read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, false);
+        assert_eq!(config.log_gelf_endpoint, "tcp://graylog:12201");
 
+        fs::write(&path, "log_gelf_endpoint=graylog:12201\n").expect("write temp config");
         read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, false);
+        assert_eq!(config.log_gelf_endpoint, "tcp://graylog:12201");
 
-        assert_eq!(config.stale_job_timeout, original);
+        fs::write(&path, "log_gelf_endpoint=\n").expect("write temp config");
+        read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, false);
+        assert_eq!(config.log_gelf_endpoint, "");
         let _ = fs::remove_file(path);
     }
 
     #[test]
-    fn read_config_allows_zero_stale_job_timeout_to_disable() {
+    fn read_config_parses_ssh_tunnel() {
         let mut config = float_test_config();
-        let mut dbinfo = DbInfo {
-            host: String::new(),
-            database: String::new(),
-            user: String::new(),
-            passwd: String::new(),
-            port: 5432,
-        };
-        let path = temp_path("pg_dbms_job_reap_zero.conf");
-        fs::write(&path, "stale_job_timeout=0\n").expect("write temp config");
-
+        let mut dbinfo = default_dbinfo();
+        let path = temp_path("pg_dbms_job_ssh_tunnel.conf");
+        fs::write(
+            &path,
+            "ssh_host=bastion.example.com\nssh_port=2222\nssh_user=tunnel\nssh_key=/etc/pg_dbms_job/id_ed25519\n",
+        )
+        .expect("write temp config");
         read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, false);
+        assert_eq!(config.ssh_host, "bastion.example.com");
+        assert_eq!(config.ssh_port, 2222);
+        assert_eq!(config.ssh_user, "tunnel");
+        assert_eq!(config.ssh_key, "/etc/pg_dbms_job/id_ed25519");
 
-        assert_eq!(config.stale_job_timeout, 0.0);
+        fs::write(&path, "ssh_port=0\nssh_host=\n").expect("write temp config");
+        read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, false);
+        assert_eq!(config.ssh_port, 2222);
+        assert!(config.ssh_host.is_empty());
         let _ = fs::remove_file(path);
     }
 
@@ -526,6 +3537,9 @@ log_truncate_on_rotation=1
             debug: false,
             pidfile: "/tmp/pg_dbms_job.pid".to_string(),
             logfile: String::new(),
+            logfile_mode: 0,
+            logfile_owner: String::new(),
+            logfile_group: String::new(),
             log_truncate_on_rotation: false,
             job_queue_interval: 0.1,
             job_queue_processes: 1024,
@@ -536,14 +3550,60 @@ log_truncate_on_rotation=1
             stats_interval: 0,
             job_run_details: crate::model::JobRunDetails::All,
             stale_job_timeout: 3600.0,
+            notify_debounce: 0.0,
+            schedule_refresh_interval: 60.0,
+            max_notifications_per_cycle: 10000,
+            max_job_size: 0,
+            hard_max_run_duration: 0.0,
+            hard_max_run_overruns: 3,
+            job_nice: 0,
+            job_io_class: crate::model::IoClass::None,
+            job_io_priority: 4,
+            connection_reserve: 0,
+            job_cgroup: String::new(),
+            executable_job_owners: String::new(),
+            executable_os_users: String::new(),
+            log_retention_days: 0,
+            log_compress: false,
+            max_nap_time: 0.0,
+            reconnect_max_delay: 60.0,
+            reconnect_max_attempts: 0,
+            standby_check_interval: 0.0,
+            job_run_timeout: 0.0,
+            max_failures: 0,
+            connection_check_interval: 0.0,
+            multiple_schedulers: false,
+            semaphores: BTreeMap::new(),
+            job_classes: BTreeMap::new(),
+            job_class_priorities: BTreeMap::new(),
+            run_details_maintenance_interval: 0.0,
+            run_details_retention_months: 3,
+            worker_hang_timeout: 600.0,
+            job_execution_mode: crate::model::ExecutionMode::SetRole,
+            dst_policy: crate::model::DstPolicy::Server,
+            ssh_host: String::new(),
+            ssh_port: 22,
+            ssh_user: String::new(),
+            ssh_key: String::new(),
+            log_rotation_size: 0,
+            log_rotation_count: 5,
+            log_rotation_age: crate::model::LogRotationAge::None,
+            log_destination: crate::model::LogDestination::File,
+            syslog_facility: "local0".to_string(),
+            syslog_ident: "pg_dbms_job".to_string(),
+            log_min_messages: crate::model::LogMinMessages::Log,
+            log_job_notices: false,
+            log_rotation_compress: crate::model::LogRotationCompress::None,
+            scheduler_event_log: false,
+            debug_ring_size: 0,
+            log_gelf_endpoint: String::new(),
+            auto_reload: false,
+            strict_config: false,
+            database_section: String::new(),
+            schemas: DEFAULT_SCHEMA.to_string(),
+            schema: DEFAULT_SCHEMA.to_string(),
         };
-        let mut dbinfo = DbInfo {
-            host: String::new(),
-            database: String::new(),
-            user: String::new(),
-            passwd: String::new(),
-            port: 5432,
-        };
+        let mut dbinfo = default_dbinfo();
 
         // Should not panic when nodie=true
         read_config("/nonexistent/path.conf", &mut config, &mut dbinfo, true);
@@ -558,6 +3618,9 @@ log_truncate_on_rotation=1
             debug: false,
             pidfile: "/tmp/pg_dbms_job.pid".to_string(),
             logfile: String::new(),
+            logfile_mode: 0,
+            logfile_owner: String::new(),
+            logfile_group: String::new(),
             log_truncate_on_rotation: false,
             job_queue_interval: 0.1,
             job_queue_processes: 1024,
@@ -568,14 +3631,60 @@ log_truncate_on_rotation=1
             stats_interval: 0,
             job_run_details: crate::model::JobRunDetails::All,
             stale_job_timeout: 3600.0,
+            notify_debounce: 0.0,
+            schedule_refresh_interval: 60.0,
+            max_notifications_per_cycle: 10000,
+            max_job_size: 0,
+            hard_max_run_duration: 0.0,
+            hard_max_run_overruns: 3,
+            job_nice: 0,
+            job_io_class: crate::model::IoClass::None,
+            job_io_priority: 4,
+            connection_reserve: 0,
+            job_cgroup: String::new(),
+            executable_job_owners: String::new(),
+            executable_os_users: String::new(),
+            log_retention_days: 0,
+            log_compress: false,
+            max_nap_time: 0.0,
+            reconnect_max_delay: 60.0,
+            reconnect_max_attempts: 0,
+            standby_check_interval: 0.0,
+            job_run_timeout: 0.0,
+            max_failures: 0,
+            connection_check_interval: 0.0,
+            multiple_schedulers: false,
+            semaphores: BTreeMap::new(),
+            job_classes: BTreeMap::new(),
+            job_class_priorities: BTreeMap::new(),
+            run_details_maintenance_interval: 0.0,
+            run_details_retention_months: 3,
+            worker_hang_timeout: 600.0,
+            job_execution_mode: crate::model::ExecutionMode::SetRole,
+            dst_policy: crate::model::DstPolicy::Server,
+            ssh_host: String::new(),
+            ssh_port: 22,
+            ssh_user: String::new(),
+            ssh_key: String::new(),
+            log_rotation_size: 0,
+            log_rotation_count: 5,
+            log_rotation_age: crate::model::LogRotationAge::None,
+            log_destination: crate::model::LogDestination::File,
+            syslog_facility: "local0".to_string(),
+            syslog_ident: "pg_dbms_job".to_string(),
+            log_min_messages: crate::model::LogMinMessages::Log,
+            log_job_notices: false,
+            log_rotation_compress: crate::model::LogRotationCompress::None,
+            scheduler_event_log: false,
+            debug_ring_size: 0,
+            log_gelf_endpoint: String::new(),
+            auto_reload: false,
+            strict_config: false,
+            database_section: String::new(),
+            schemas: DEFAULT_SCHEMA.to_string(),
+            schema: DEFAULT_SCHEMA.to_string(),
         };
-        let mut dbinfo = DbInfo {
-            host: String::new(),
-            database: String::new(),
-            user: String::new(),
-            passwd: String::new(),
-            port: 5432,
-        };
+        let mut dbinfo = default_dbinfo();
 
         let path = temp_path("pg_dbms_job_invalid.conf");
         let content = r#"
@@ -607,6 +3716,9 @@ port=notanumber
             debug: false,
             pidfile: "/tmp/pg_dbms_job.pid".to_string(),
             logfile: String::new(),
+            logfile_mode: 0,
+            logfile_owner: String::new(),
+            logfile_group: String::new(),
             log_truncate_on_rotation: false,
             job_queue_interval: 0.1,
             job_queue_processes: 1024,
@@ -617,23 +3729,71 @@ port=notanumber
             stats_interval: 0,
             job_run_details: crate::model::JobRunDetails::All,
             stale_job_timeout: 3600.0,
+            notify_debounce: 0.0,
+            schedule_refresh_interval: 60.0,
+            max_notifications_per_cycle: 10000,
+            max_job_size: 0,
+            hard_max_run_duration: 0.0,
+            hard_max_run_overruns: 3,
+            job_nice: 0,
+            job_io_class: crate::model::IoClass::None,
+            job_io_priority: 4,
+            connection_reserve: 0,
+            job_cgroup: String::new(),
+            executable_job_owners: String::new(),
+            executable_os_users: String::new(),
+            log_retention_days: 0,
+            log_compress: false,
+            max_nap_time: 0.0,
+            reconnect_max_delay: 60.0,
+            reconnect_max_attempts: 0,
+            standby_check_interval: 0.0,
+            job_run_timeout: 0.0,
+            max_failures: 0,
+            connection_check_interval: 0.0,
+            multiple_schedulers: false,
+            semaphores: BTreeMap::new(),
+            job_classes: BTreeMap::new(),
+            job_class_priorities: BTreeMap::new(),
+            run_details_maintenance_interval: 0.0,
+            run_details_retention_months: 3,
+            worker_hang_timeout: 600.0,
+            job_execution_mode: crate::model::ExecutionMode::SetRole,
+            dst_policy: crate::model::DstPolicy::Server,
+            ssh_host: String::new(),
+            ssh_port: 22,
+            ssh_user: String::new(),
+            ssh_key: String::new(),
+            log_rotation_size: 0,
+            log_rotation_count: 5,
+            log_rotation_age: crate::model::LogRotationAge::None,
+            log_destination: crate::model::LogDestination::File,
+            syslog_facility: "local0".to_string(),
+            syslog_ident: "pg_dbms_job".to_string(),
+            log_min_messages: crate::model::LogMinMessages::Log,
+            log_job_notices: false,
+            log_rotation_compress: crate::model::LogRotationCompress::None,
+            scheduler_event_log: false,
+            debug_ring_size: 0,
+            log_gelf_endpoint: String::new(),
+            auto_reload: false,
+            strict_config: false,
+            database_section: String::new(),
+            schemas: DEFAULT_SCHEMA.to_string(),
+            schema: DEFAULT_SCHEMA.to_string(),
         };
-        let mut dbinfo = DbInfo {
-            host: String::new(),
-            database: String::new(),
-            user: String::new(),
-            passwd: String::new(),
-            port: 5432,
-        };
+        let mut dbinfo = default_dbinfo();
 
         let path = temp_path("pg_dbms_job_delays.conf");
-        let content = "startup_delay=5.5\nerror_delay=2.0\n";
+        let content = "startup_delay=5.5\nerror_delay=2.0\nstandby_check_interval=5\nstandby_check_interval=-1\nconnection_check_interval=2.5\nconnection_check_interval=x\n";
         fs::write(&path, content).expect("write temp config");
 
         read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, false);
 
         assert_eq!(config.startup_delay, 5.5);
         assert_eq!(config.error_delay, 2.0);
+        assert_eq!(config.standby_check_interval, 5.0);
+        assert_eq!(config.connection_check_interval, 2.5);
 
         let _ = fs::remove_file(path);
     }
@@ -666,6 +3826,9 @@ port=notanumber
             debug: false,
             pidfile: "/tmp/pg_dbms_job.pid".to_string(),
             logfile: String::new(),
+            logfile_mode: 0,
+            logfile_owner: String::new(),
+            logfile_group: String::new(),
             log_truncate_on_rotation: false,
             job_queue_interval: 0.1,
             job_queue_processes: 1024,
@@ -676,14 +3839,60 @@ port=notanumber
             stats_interval: 0,
             job_run_details: crate::model::JobRunDetails::All,
             stale_job_timeout: 3600.0,
+            notify_debounce: 0.0,
+            schedule_refresh_interval: 60.0,
+            max_notifications_per_cycle: 10000,
+            max_job_size: 0,
+            hard_max_run_duration: 0.0,
+            hard_max_run_overruns: 3,
+            job_nice: 0,
+            job_io_class: crate::model::IoClass::None,
+            job_io_priority: 4,
+            connection_reserve: 0,
+            job_cgroup: String::new(),
+            executable_job_owners: String::new(),
+            executable_os_users: String::new(),
+            log_retention_days: 0,
+            log_compress: false,
+            max_nap_time: 0.0,
+            reconnect_max_delay: 60.0,
+            reconnect_max_attempts: 0,
+            standby_check_interval: 0.0,
+            job_run_timeout: 0.0,
+            max_failures: 0,
+            connection_check_interval: 0.0,
+            multiple_schedulers: false,
+            semaphores: BTreeMap::new(),
+            job_classes: BTreeMap::new(),
+            job_class_priorities: BTreeMap::new(),
+            run_details_maintenance_interval: 0.0,
+            run_details_retention_months: 3,
+            worker_hang_timeout: 600.0,
+            job_execution_mode: crate::model::ExecutionMode::SetRole,
+            dst_policy: crate::model::DstPolicy::Server,
+            ssh_host: String::new(),
+            ssh_port: 22,
+            ssh_user: String::new(),
+            ssh_key: String::new(),
+            log_rotation_size: 0,
+            log_rotation_count: 5,
+            log_rotation_age: crate::model::LogRotationAge::None,
+            log_destination: crate::model::LogDestination::File,
+            syslog_facility: "local0".to_string(),
+            syslog_ident: "pg_dbms_job".to_string(),
+            log_min_messages: crate::model::LogMinMessages::Log,
+            log_job_notices: false,
+            log_rotation_compress: crate::model::LogRotationCompress::None,
+            scheduler_event_log: false,
+            debug_ring_size: 0,
+            log_gelf_endpoint: String::new(),
+            auto_reload: false,
+            strict_config: false,
+            database_section: String::new(),
+            schemas: DEFAULT_SCHEMA.to_string(),
+            schema: DEFAULT_SCHEMA.to_string(),
         };
-        let mut dbinfo = DbInfo {
-            host: String::new(),
-            database: String::new(),
-            user: String::new(),
-            passwd: String::new(),
-            port: 5432,
-        };
+        let mut dbinfo = default_dbinfo();
 
         let path = temp_path("pg_dbms_job_logfile.conf");
         fs::write(&path, "logfile=/tmp/test_scheduler.log\n").expect("write");
@@ -698,6 +3907,9 @@ port=notanumber
             debug: false,
             pidfile: "/tmp/pg_dbms_job.pid".to_string(),
             logfile: String::new(),
+            logfile_mode: 0,
+            logfile_owner: String::new(),
+            logfile_group: String::new(),
             log_truncate_on_rotation: false,
             job_queue_interval: 5.0,
             job_queue_processes: 10,
@@ -708,14 +3920,60 @@ port=notanumber
             stats_interval: 0,
             job_run_details: crate::model::JobRunDetails::All,
             stale_job_timeout: 3600.0,
+            notify_debounce: 0.0,
+            schedule_refresh_interval: 60.0,
+            max_notifications_per_cycle: 10000,
+            max_job_size: 0,
+            hard_max_run_duration: 0.0,
+            hard_max_run_overruns: 3,
+            job_nice: 0,
+            job_io_class: crate::model::IoClass::None,
+            job_io_priority: 4,
+            connection_reserve: 0,
+            job_cgroup: String::new(),
+            executable_job_owners: String::new(),
+            executable_os_users: String::new(),
+            log_retention_days: 0,
+            log_compress: false,
+            max_nap_time: 0.0,
+            reconnect_max_delay: 60.0,
+            reconnect_max_attempts: 0,
+            standby_check_interval: 0.0,
+            job_run_timeout: 0.0,
+            max_failures: 0,
+            connection_check_interval: 0.0,
+            multiple_schedulers: false,
+            semaphores: BTreeMap::new(),
+            job_classes: BTreeMap::new(),
+            job_class_priorities: BTreeMap::new(),
+            run_details_maintenance_interval: 0.0,
+            run_details_retention_months: 3,
+            worker_hang_timeout: 600.0,
+            job_execution_mode: crate::model::ExecutionMode::SetRole,
+            dst_policy: crate::model::DstPolicy::Server,
+            ssh_host: String::new(),
+            ssh_port: 22,
+            ssh_user: String::new(),
+            ssh_key: String::new(),
+            log_rotation_size: 0,
+            log_rotation_count: 5,
+            log_rotation_age: crate::model::LogRotationAge::None,
+            log_destination: crate::model::LogDestination::File,
+            syslog_facility: "local0".to_string(),
+            syslog_ident: "pg_dbms_job".to_string(),
+            log_min_messages: crate::model::LogMinMessages::Log,
+            log_job_notices: false,
+            log_rotation_compress: crate::model::LogRotationCompress::None,
+            scheduler_event_log: false,
+            debug_ring_size: 0,
+            log_gelf_endpoint: String::new(),
+            auto_reload: false,
+            strict_config: false,
+            database_section: String::new(),
+            schemas: DEFAULT_SCHEMA.to_string(),
+            schema: DEFAULT_SCHEMA.to_string(),
         };
-        let mut dbinfo = DbInfo {
-            host: String::new(),
-            database: String::new(),
-            user: String::new(),
-            passwd: String::new(),
-            port: 5432,
-        };
+        let mut dbinfo = default_dbinfo();
 
         let path = temp_path("pg_dbms_job_zero.conf");
         let content = "job_queue_interval=0\nnap_time=0\nstartup_delay=0\nerror_delay=0\njob_queue_processes=0\n";
@@ -736,6 +3994,9 @@ port=notanumber
             debug: false,
             pidfile: "/tmp/pg_dbms_job.pid".to_string(),
             logfile: String::new(),
+            logfile_mode: 0,
+            logfile_owner: String::new(),
+            logfile_group: String::new(),
             log_truncate_on_rotation: false,
             job_queue_interval: 5.0,
             job_queue_processes: 10,
@@ -746,14 +4007,60 @@ port=notanumber
             stats_interval: 0,
             job_run_details: crate::model::JobRunDetails::All,
             stale_job_timeout: 3600.0,
+            notify_debounce: 0.0,
+            schedule_refresh_interval: 60.0,
+            max_notifications_per_cycle: 10000,
+            max_job_size: 0,
+            hard_max_run_duration: 0.0,
+            hard_max_run_overruns: 3,
+            job_nice: 0,
+            job_io_class: crate::model::IoClass::None,
+            job_io_priority: 4,
+            connection_reserve: 0,
+            job_cgroup: String::new(),
+            executable_job_owners: String::new(),
+            executable_os_users: String::new(),
+            log_retention_days: 0,
+            log_compress: false,
+            max_nap_time: 0.0,
+            reconnect_max_delay: 60.0,
+            reconnect_max_attempts: 0,
+            standby_check_interval: 0.0,
+            job_run_timeout: 0.0,
+            max_failures: 0,
+            connection_check_interval: 0.0,
+            multiple_schedulers: false,
+            semaphores: BTreeMap::new(),
+            job_classes: BTreeMap::new(),
+            job_class_priorities: BTreeMap::new(),
+            run_details_maintenance_interval: 0.0,
+            run_details_retention_months: 3,
+            worker_hang_timeout: 600.0,
+            job_execution_mode: crate::model::ExecutionMode::SetRole,
+            dst_policy: crate::model::DstPolicy::Server,
+            ssh_host: String::new(),
+            ssh_port: 22,
+            ssh_user: String::new(),
+            ssh_key: String::new(),
+            log_rotation_size: 0,
+            log_rotation_count: 5,
+            log_rotation_age: crate::model::LogRotationAge::None,
+            log_destination: crate::model::LogDestination::File,
+            syslog_facility: "local0".to_string(),
+            syslog_ident: "pg_dbms_job".to_string(),
+            log_min_messages: crate::model::LogMinMessages::Log,
+            log_job_notices: false,
+            log_rotation_compress: crate::model::LogRotationCompress::None,
+            scheduler_event_log: false,
+            debug_ring_size: 0,
+            log_gelf_endpoint: String::new(),
+            auto_reload: false,
+            strict_config: false,
+            database_section: String::new(),
+            schemas: DEFAULT_SCHEMA.to_string(),
+            schema: DEFAULT_SCHEMA.to_string(),
         };
-        let mut dbinfo = DbInfo {
-            host: String::new(),
-            database: String::new(),
-            user: String::new(),
-            passwd: String::new(),
-            port: 5432,
-        };
+        let mut dbinfo = default_dbinfo();
 
         let path = temp_path("pg_dbms_job_inf.conf");
         let content = "job_queue_interval=inf\nnap_time=inf\nstartup_delay=inf\nerror_delay=inf\n";
@@ -772,6 +4079,9 @@ port=notanumber
             debug: false,
             pidfile: "/tmp/pg_dbms_job.pid".to_string(),
             logfile: String::new(),
+            logfile_mode: 0,
+            logfile_owner: String::new(),
+            logfile_group: String::new(),
             log_truncate_on_rotation: false,
             job_queue_interval: 5.0,
             job_queue_processes: 10,
@@ -782,14 +4092,60 @@ port=notanumber
             stats_interval: 0,
             job_run_details: crate::model::JobRunDetails::All,
             stale_job_timeout: 3600.0,
+            notify_debounce: 0.0,
+            schedule_refresh_interval: 60.0,
+            max_notifications_per_cycle: 10000,
+            max_job_size: 0,
+            hard_max_run_duration: 0.0,
+            hard_max_run_overruns: 3,
+            job_nice: 0,
+            job_io_class: crate::model::IoClass::None,
+            job_io_priority: 4,
+            connection_reserve: 0,
+            job_cgroup: String::new(),
+            executable_job_owners: String::new(),
+            executable_os_users: String::new(),
+            log_retention_days: 0,
+            log_compress: false,
+            max_nap_time: 0.0,
+            reconnect_max_delay: 60.0,
+            reconnect_max_attempts: 0,
+            standby_check_interval: 0.0,
+            job_run_timeout: 0.0,
+            max_failures: 0,
+            connection_check_interval: 0.0,
+            multiple_schedulers: false,
+            semaphores: BTreeMap::new(),
+            job_classes: BTreeMap::new(),
+            job_class_priorities: BTreeMap::new(),
+            run_details_maintenance_interval: 0.0,
+            run_details_retention_months: 3,
+            worker_hang_timeout: 600.0,
+            job_execution_mode: crate::model::ExecutionMode::SetRole,
+            dst_policy: crate::model::DstPolicy::Server,
+            ssh_host: String::new(),
+            ssh_port: 22,
+            ssh_user: String::new(),
+            ssh_key: String::new(),
+            log_rotation_size: 0,
+            log_rotation_count: 5,
+            log_rotation_age: crate::model::LogRotationAge::None,
+            log_destination: crate::model::LogDestination::File,
+            syslog_facility: "local0".to_string(),
+            syslog_ident: "pg_dbms_job".to_string(),
+            log_min_messages: crate::model::LogMinMessages::Log,
+            log_job_notices: false,
+            log_rotation_compress: crate::model::LogRotationCompress::None,
+            scheduler_event_log: false,
+            debug_ring_size: 0,
+            log_gelf_endpoint: String::new(),
+            auto_reload: false,
+            strict_config: false,
+            database_section: String::new(),
+            schemas: DEFAULT_SCHEMA.to_string(),
+            schema: DEFAULT_SCHEMA.to_string(),
         };
-        let mut dbinfo = DbInfo {
-            host: String::new(),
-            database: String::new(),
-            user: String::new(),
-            passwd: String::new(),
-            port: 5432,
-        };
+        let mut dbinfo = default_dbinfo();
 
         // Set pidfile to same value — should remain unchanged
         let path = temp_path("pg_dbms_job_noop.conf");
@@ -805,6 +4161,9 @@ port=notanumber
             debug: false,
             pidfile: "/tmp/pg_dbms_job.pid".to_string(),
             logfile: String::new(),
+            logfile_mode: 0,
+            logfile_owner: String::new(),
+            logfile_group: String::new(),
             log_truncate_on_rotation: false,
             job_queue_interval: 0.1,
             job_queue_processes: 1024,
@@ -815,14 +4174,60 @@ port=notanumber
             stats_interval: 0,
             job_run_details: crate::model::JobRunDetails::All,
             stale_job_timeout: 3600.0,
+            notify_debounce: 0.0,
+            schedule_refresh_interval: 60.0,
+            max_notifications_per_cycle: 10000,
+            max_job_size: 0,
+            hard_max_run_duration: 0.0,
+            hard_max_run_overruns: 3,
+            job_nice: 0,
+            job_io_class: crate::model::IoClass::None,
+            job_io_priority: 4,
+            connection_reserve: 0,
+            job_cgroup: String::new(),
+            executable_job_owners: String::new(),
+            executable_os_users: String::new(),
+            log_retention_days: 0,
+            log_compress: false,
+            max_nap_time: 0.0,
+            reconnect_max_delay: 60.0,
+            reconnect_max_attempts: 0,
+            standby_check_interval: 0.0,
+            job_run_timeout: 0.0,
+            max_failures: 0,
+            connection_check_interval: 0.0,
+            multiple_schedulers: false,
+            semaphores: BTreeMap::new(),
+            job_classes: BTreeMap::new(),
+            job_class_priorities: BTreeMap::new(),
+            run_details_maintenance_interval: 0.0,
+            run_details_retention_months: 3,
+            worker_hang_timeout: 600.0,
+            job_execution_mode: crate::model::ExecutionMode::SetRole,
+            dst_policy: crate::model::DstPolicy::Server,
+            ssh_host: String::new(),
+            ssh_port: 22,
+            ssh_user: String::new(),
+            ssh_key: String::new(),
+            log_rotation_size: 0,
+            log_rotation_count: 5,
+            log_rotation_age: crate::model::LogRotationAge::None,
+            log_destination: crate::model::LogDestination::File,
+            syslog_facility: "local0".to_string(),
+            syslog_ident: "pg_dbms_job".to_string(),
+            log_min_messages: crate::model::LogMinMessages::Log,
+            log_job_notices: false,
+            log_rotation_compress: crate::model::LogRotationCompress::None,
+            scheduler_event_log: false,
+            debug_ring_size: 0,
+            log_gelf_endpoint: String::new(),
+            auto_reload: false,
+            strict_config: false,
+            database_section: String::new(),
+            schemas: DEFAULT_SCHEMA.to_string(),
+            schema: DEFAULT_SCHEMA.to_string(),
         };
-        let mut dbinfo = DbInfo {
-            host: String::new(),
-            database: String::new(),
-            user: String::new(),
-            passwd: String::new(),
-            port: 5432,
-        };
+        let mut dbinfo = default_dbinfo();
 
         let path = temp_path("pg_dbms_job_dbg.conf");
         fs::write(&path, "debug=1\n").expect("write");
@@ -843,6 +4248,9 @@ port=notanumber
             debug: false,
             pidfile: "/tmp/pg_dbms_job.pid".to_string(),
             logfile: String::new(),
+            logfile_mode: 0,
+            logfile_owner: String::new(),
+            logfile_group: String::new(),
             log_truncate_on_rotation: false,
             job_queue_interval: 0.1,
             job_queue_processes: 1024,
@@ -853,14 +4261,60 @@ port=notanumber
             stats_interval: 0,
             job_run_details: crate::model::JobRunDetails::All,
             stale_job_timeout: 3600.0,
+            notify_debounce: 0.0,
+            schedule_refresh_interval: 60.0,
+            max_notifications_per_cycle: 10000,
+            max_job_size: 0,
+            hard_max_run_duration: 0.0,
+            hard_max_run_overruns: 3,
+            job_nice: 0,
+            job_io_class: crate::model::IoClass::None,
+            job_io_priority: 4,
+            connection_reserve: 0,
+            job_cgroup: String::new(),
+            executable_job_owners: String::new(),
+            executable_os_users: String::new(),
+            log_retention_days: 0,
+            log_compress: false,
+            max_nap_time: 0.0,
+            reconnect_max_delay: 60.0,
+            reconnect_max_attempts: 0,
+            standby_check_interval: 0.0,
+            job_run_timeout: 0.0,
+            max_failures: 0,
+            connection_check_interval: 0.0,
+            multiple_schedulers: false,
+            semaphores: BTreeMap::new(),
+            job_classes: BTreeMap::new(),
+            job_class_priorities: BTreeMap::new(),
+            run_details_maintenance_interval: 0.0,
+            run_details_retention_months: 3,
+            worker_hang_timeout: 600.0,
+            job_execution_mode: crate::model::ExecutionMode::SetRole,
+            dst_policy: crate::model::DstPolicy::Server,
+            ssh_host: String::new(),
+            ssh_port: 22,
+            ssh_user: String::new(),
+            ssh_key: String::new(),
+            log_rotation_size: 0,
+            log_rotation_count: 5,
+            log_rotation_age: crate::model::LogRotationAge::None,
+            log_destination: crate::model::LogDestination::File,
+            syslog_facility: "local0".to_string(),
+            syslog_ident: "pg_dbms_job".to_string(),
+            log_min_messages: crate::model::LogMinMessages::Log,
+            log_job_notices: false,
+            log_rotation_compress: crate::model::LogRotationCompress::None,
+            scheduler_event_log: false,
+            debug_ring_size: 0,
+            log_gelf_endpoint: String::new(),
+            auto_reload: false,
+            strict_config: false,
+            database_section: String::new(),
+            schemas: DEFAULT_SCHEMA.to_string(),
+            schema: DEFAULT_SCHEMA.to_string(),
         };
-        let mut dbinfo = DbInfo {
-            host: String::new(),
-            database: String::new(),
-            user: String::new(),
-            passwd: String::new(),
-            port: 5432,
-        };
+        let mut dbinfo = default_dbinfo();
 
         let path = temp_path("pg_dbms_job_pool.conf");
         fs::write(&path, "pool_size=25\n").expect("write");
@@ -875,6 +4329,9 @@ port=notanumber
             debug: false,
             pidfile: "/tmp/pg_dbms_job.pid".to_string(),
             logfile: String::new(),
+            logfile_mode: 0,
+            logfile_owner: String::new(),
+            logfile_group: String::new(),
             log_truncate_on_rotation: false,
             job_queue_interval: 0.1,
             job_queue_processes: 1024,
@@ -885,14 +4342,60 @@ port=notanumber
             stats_interval: 0,
             job_run_details: crate::model::JobRunDetails::All,
             stale_job_timeout: 3600.0,
+            notify_debounce: 0.0,
+            schedule_refresh_interval: 60.0,
+            max_notifications_per_cycle: 10000,
+            max_job_size: 0,
+            hard_max_run_duration: 0.0,
+            hard_max_run_overruns: 3,
+            job_nice: 0,
+            job_io_class: crate::model::IoClass::None,
+            job_io_priority: 4,
+            connection_reserve: 0,
+            job_cgroup: String::new(),
+            executable_job_owners: String::new(),
+            executable_os_users: String::new(),
+            log_retention_days: 0,
+            log_compress: false,
+            max_nap_time: 0.0,
+            reconnect_max_delay: 60.0,
+            reconnect_max_attempts: 0,
+            standby_check_interval: 0.0,
+            job_run_timeout: 0.0,
+            max_failures: 0,
+            connection_check_interval: 0.0,
+            multiple_schedulers: false,
+            semaphores: BTreeMap::new(),
+            job_classes: BTreeMap::new(),
+            job_class_priorities: BTreeMap::new(),
+            run_details_maintenance_interval: 0.0,
+            run_details_retention_months: 3,
+            worker_hang_timeout: 600.0,
+            job_execution_mode: crate::model::ExecutionMode::SetRole,
+            dst_policy: crate::model::DstPolicy::Server,
+            ssh_host: String::new(),
+            ssh_port: 22,
+            ssh_user: String::new(),
+            ssh_key: String::new(),
+            log_rotation_size: 0,
+            log_rotation_count: 5,
+            log_rotation_age: crate::model::LogRotationAge::None,
+            log_destination: crate::model::LogDestination::File,
+            syslog_facility: "local0".to_string(),
+            syslog_ident: "pg_dbms_job".to_string(),
+            log_min_messages: crate::model::LogMinMessages::Log,
+            log_job_notices: false,
+            log_rotation_compress: crate::model::LogRotationCompress::None,
+            scheduler_event_log: false,
+            debug_ring_size: 0,
+            log_gelf_endpoint: String::new(),
+            auto_reload: false,
+            strict_config: false,
+            database_section: String::new(),
+            schemas: DEFAULT_SCHEMA.to_string(),
+            schema: DEFAULT_SCHEMA.to_string(),
         };
-        let mut dbinfo = DbInfo {
-            host: String::new(),
-            database: String::new(),
-            user: String::new(),
-            passwd: String::new(),
-            port: 5432,
-        };
+        let mut dbinfo = default_dbinfo();
 
         let path = temp_path("pg_dbms_job_pool_invalid.conf");
         let content = "pool_size=0\npool_size=-10\npool_size=notanumber\n";
@@ -909,6 +4412,9 @@ port=notanumber
             debug: false,
             pidfile: "/tmp/pg_dbms_job.pid".to_string(),
             logfile: String::new(),
+            logfile_mode: 0,
+            logfile_owner: String::new(),
+            logfile_group: String::new(),
             log_truncate_on_rotation: false,
             job_queue_interval: 0.1,
             job_queue_processes: 1024,
@@ -919,14 +4425,60 @@ port=notanumber
             stats_interval: 0,
             job_run_details: crate::model::JobRunDetails::All,
             stale_job_timeout: 3600.0,
+            notify_debounce: 0.0,
+            schedule_refresh_interval: 60.0,
+            max_notifications_per_cycle: 10000,
+            max_job_size: 0,
+            hard_max_run_duration: 0.0,
+            hard_max_run_overruns: 3,
+            job_nice: 0,
+            job_io_class: crate::model::IoClass::None,
+            job_io_priority: 4,
+            connection_reserve: 0,
+            job_cgroup: String::new(),
+            executable_job_owners: String::new(),
+            executable_os_users: String::new(),
+            log_retention_days: 0,
+            log_compress: false,
+            max_nap_time: 0.0,
+            reconnect_max_delay: 60.0,
+            reconnect_max_attempts: 0,
+            standby_check_interval: 0.0,
+            job_run_timeout: 0.0,
+            max_failures: 0,
+            connection_check_interval: 0.0,
+            multiple_schedulers: false,
+            semaphores: BTreeMap::new(),
+            job_classes: BTreeMap::new(),
+            job_class_priorities: BTreeMap::new(),
+            run_details_maintenance_interval: 0.0,
+            run_details_retention_months: 3,
+            worker_hang_timeout: 600.0,
+            job_execution_mode: crate::model::ExecutionMode::SetRole,
+            dst_policy: crate::model::DstPolicy::Server,
+            ssh_host: String::new(),
+            ssh_port: 22,
+            ssh_user: String::new(),
+            ssh_key: String::new(),
+            log_rotation_size: 0,
+            log_rotation_count: 5,
+            log_rotation_age: crate::model::LogRotationAge::None,
+            log_destination: crate::model::LogDestination::File,
+            syslog_facility: "local0".to_string(),
+            syslog_ident: "pg_dbms_job".to_string(),
+            log_min_messages: crate::model::LogMinMessages::Log,
+            log_job_notices: false,
+            log_rotation_compress: crate::model::LogRotationCompress::None,
+            scheduler_event_log: false,
+            debug_ring_size: 0,
+            log_gelf_endpoint: String::new(),
+            auto_reload: false,
+            strict_config: false,
+            database_section: String::new(),
+            schemas: DEFAULT_SCHEMA.to_string(),
+            schema: DEFAULT_SCHEMA.to_string(),
         };
-        let mut dbinfo = DbInfo {
-            host: String::new(),
-            database: String::new(),
-            user: String::new(),
-            passwd: String::new(),
-            port: 5432,
-        };
+        let mut dbinfo = default_dbinfo();
 
         let path = temp_path("pg_dbms_job_stats.conf");
         fs::write(&path, "stats_interval=60\n").expect("write");
@@ -947,6 +4499,9 @@ port=notanumber
             debug: false,
             pidfile: "/tmp/pg_dbms_job.pid".to_string(),
             logfile: String::new(),
+            logfile_mode: 0,
+            logfile_owner: String::new(),
+            logfile_group: String::new(),
             log_truncate_on_rotation: false,
             job_queue_interval: 0.1,
             job_queue_processes: 1024,
@@ -957,14 +4512,60 @@ port=notanumber
             stats_interval: 45,
             job_run_details: crate::model::JobRunDetails::All,
             stale_job_timeout: 3600.0,
+            notify_debounce: 0.0,
+            schedule_refresh_interval: 60.0,
+            max_notifications_per_cycle: 10000,
+            max_job_size: 0,
+            hard_max_run_duration: 0.0,
+            hard_max_run_overruns: 3,
+            job_nice: 0,
+            job_io_class: crate::model::IoClass::None,
+            job_io_priority: 4,
+            connection_reserve: 0,
+            job_cgroup: String::new(),
+            executable_job_owners: String::new(),
+            executable_os_users: String::new(),
+            log_retention_days: 0,
+            log_compress: false,
+            max_nap_time: 0.0,
+            reconnect_max_delay: 60.0,
+            reconnect_max_attempts: 0,
+            standby_check_interval: 0.0,
+            job_run_timeout: 0.0,
+            max_failures: 0,
+            connection_check_interval: 0.0,
+            multiple_schedulers: false,
+            semaphores: BTreeMap::new(),
+            job_classes: BTreeMap::new(),
+            job_class_priorities: BTreeMap::new(),
+            run_details_maintenance_interval: 0.0,
+            run_details_retention_months: 3,
+            worker_hang_timeout: 600.0,
+            job_execution_mode: crate::model::ExecutionMode::SetRole,
+            dst_policy: crate::model::DstPolicy::Server,
+            ssh_host: String::new(),
+            ssh_port: 22,
+            ssh_user: String::new(),
+            ssh_key: String::new(),
+            log_rotation_size: 0,
+            log_rotation_count: 5,
+            log_rotation_age: crate::model::LogRotationAge::None,
+            log_destination: crate::model::LogDestination::File,
+            syslog_facility: "local0".to_string(),
+            syslog_ident: "pg_dbms_job".to_string(),
+            log_min_messages: crate::model::LogMinMessages::Log,
+            log_job_notices: false,
+            log_rotation_compress: crate::model::LogRotationCompress::None,
+            scheduler_event_log: false,
+            debug_ring_size: 0,
+            log_gelf_endpoint: String::new(),
+            auto_reload: false,
+            strict_config: false,
+            database_section: String::new(),
+            schemas: DEFAULT_SCHEMA.to_string(),
+            schema: DEFAULT_SCHEMA.to_string(),
         };
-        let mut dbinfo = DbInfo {
-            host: String::new(),
-            database: String::new(),
-            user: String::new(),
-            passwd: String::new(),
-            port: 5432,
-        };
+        let mut dbinfo = default_dbinfo();
 
         // Negative numbers and non-numeric tokens both fail u64 parsing, so
         // the previously-applied value (45) must survive.
@@ -981,6 +4582,9 @@ port=notanumber
             debug: false,
             pidfile: "/tmp/pg_dbms_job.pid".to_string(),
             logfile: String::new(),
+            logfile_mode: 0,
+            logfile_owner: String::new(),
+            logfile_group: String::new(),
             log_truncate_on_rotation: false,
             job_queue_interval: 0.1,
             job_queue_processes: 1024,
@@ -991,24 +4595,75 @@ port=notanumber
             stats_interval: 0,
             job_run_details: crate::model::JobRunDetails::All,
             stale_job_timeout: 3600.0,
+            notify_debounce: 0.0,
+            schedule_refresh_interval: 60.0,
+            max_notifications_per_cycle: 10000,
+            max_job_size: 0,
+            hard_max_run_duration: 0.0,
+            hard_max_run_overruns: 3,
+            job_nice: 0,
+            job_io_class: crate::model::IoClass::None,
+            job_io_priority: 4,
+            connection_reserve: 0,
+            job_cgroup: String::new(),
+            executable_job_owners: String::new(),
+            executable_os_users: String::new(),
+            log_retention_days: 0,
+            log_compress: false,
+            max_nap_time: 0.0,
+            reconnect_max_delay: 60.0,
+            reconnect_max_attempts: 0,
+            standby_check_interval: 0.0,
+            job_run_timeout: 0.0,
+            max_failures: 0,
+            connection_check_interval: 0.0,
+            multiple_schedulers: false,
+            semaphores: BTreeMap::new(),
+            job_classes: BTreeMap::new(),
+            job_class_priorities: BTreeMap::new(),
+            run_details_maintenance_interval: 0.0,
+            run_details_retention_months: 3,
+            worker_hang_timeout: 600.0,
+            job_execution_mode: crate::model::ExecutionMode::SetRole,
+            dst_policy: crate::model::DstPolicy::Server,
+            ssh_host: String::new(),
+            ssh_port: 22,
+            ssh_user: String::new(),
+            ssh_key: String::new(),
+            log_rotation_size: 0,
+            log_rotation_count: 5,
+            log_rotation_age: crate::model::LogRotationAge::None,
+            log_destination: crate::model::LogDestination::File,
+            syslog_facility: "local0".to_string(),
+            syslog_ident: "pg_dbms_job".to_string(),
+            log_min_messages: crate::model::LogMinMessages::Log,
+            log_job_notices: false,
+            log_rotation_compress: crate::model::LogRotationCompress::None,
+            scheduler_event_log: false,
+            debug_ring_size: 0,
+            log_gelf_endpoint: String::new(),
+            auto_reload: false,
+            strict_config: false,
+            database_section: String::new(),
+            schemas: DEFAULT_SCHEMA.to_string(),
+            schema: DEFAULT_SCHEMA.to_string(),
         };
-        let mut dbinfo = DbInfo {
-            host: String::new(),
-            database: String::new(),
-            user: String::new(),
-            passwd: String::new(),
-            port: 5432,
-        };
+        let mut dbinfo = default_dbinfo();
 
         let path = temp_path("pg_dbms_job_dbinfo.conf");
-        let content =
-            "host=db.example.com\ndatabase=production\nuser=scheduler\npasswd=s3cret\nport=5433\n";
+        let content = "host=db.example.com\ndatabase=production\nuser=scheduler\npasswd=s3cret\nport=5433\npasswd_file=/etc/pg_dbms_job/passwd\npasswd_command=/usr/bin/get-secret pg_dbms_job\ndsn = postgresql://scheduler@db.example.com:5433/production?sslmode=require\n";
         fs::write(&path, content).expect("write");
         read_config(path.to_str().unwrap(), &mut config, &mut dbinfo, false);
         assert_eq!(dbinfo.host, "db.example.com");
         assert_eq!(dbinfo.database, "production");
         assert_eq!(dbinfo.user, "scheduler");
         assert_eq!(dbinfo.passwd, "s3cret");
+        assert_eq!(dbinfo.passwd_file, "/etc/pg_dbms_job/passwd");
+        assert_eq!(dbinfo.passwd_command, "/usr/bin/get-secret pg_dbms_job");
+        assert_eq!(
+            dbinfo.dsn,
+            "postgresql://scheduler@db.example.com:5433/production?sslmode=require"
+        );
         assert_eq!(dbinfo.port, 5433);
         let _ = fs::remove_file(path);
     }
@@ -9,9 +9,61 @@ pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 /// configurable `stale_job_timeout`; this is only the check cadence, capped so
 /// it is never coarser than the timeout itself.
 pub const REAP_INTERVAL_SECS: f64 = 60.0;
+/// How often (seconds) the main loop looks for hung workers, capped by
+/// `worker_hang_timeout`.
+pub const HANG_CHECK_INTERVAL_SECS: f64 = 30.0;
+/// How often (seconds) the main loop looks for runs past their
+/// `max_run_duration`.
+pub const RUN_DEADLINE_CHECK_INTERVAL_SECS: f64 = 1.0;
+/// How often (seconds) the main loop refuses the queued jobs over
+/// `max_job_size`. The claim queries skip them meanwhile.
+pub const OVERSIZED_JOB_CHECK_INTERVAL_SECS: f64 = 60.0;
+/// Time a run canceled for exceeding its `max_run_duration` has to end
+/// before its backend is terminated.
+pub const RUN_CANCEL_GRACE: Duration = Duration::from_secs(10);
 /// Program name used in usage text and messaging, sourced from `Cargo.toml`.
 pub const PROGRAM: &str = env!("CARGO_PKG_NAME");
 
+/// Optional capabilities of this scheduler build, recorded in
+/// `dbms_job.scheduler_registry` so DBAs can tell what each daemon supports.
+pub const FEATURES: &[&str] = &[
+    "job_result",
+    "job_chaining",
+    "schedule_cache",
+    "continue_on_error",
+    "resource_key",
+    "semaphore",
+    "max_run_duration",
+    "job_notices",
+    "priority",
+    "job_classes",
+    "job_dependencies",
+    "executable_jobs",
+    "schemas",
+];
+
+/// Custom setting a job body can assign (`set_config('pg_dbms_job.result',
+/// ..., true)`) to publish a result value, e.g. the number of rows it
+/// processed. Read back before COMMIT and stored in the `result` column of
+/// `all_scheduler_job_run_details`.
+pub const RESULT_SETTING: &str = "pg_dbms_job.result";
+
+/// Custom setting holding the id of the run during a job, the `run_id` of
+/// its run details and of the `[run ...]` tag of its log lines, so a job
+/// body can quote it in its own messages.
+pub const RUN_ID_SETTING: &str = "pg_dbms_job.run_id";
+
+/// Prefix of the transaction-local settings the exception handler of a job
+/// body fills with the stacked diagnostics of its error
+/// (`pg_dbms_job.error_sqlstate`, `_message`, `_detail`, `_hint` and
+/// `_context`), read back by the scheduler once the body returns.
+pub const ERROR_SETTING_PREFIX: &str = "pg_dbms_job.error_";
+
+/// Messages raised by one job run kept for its run details; the ones past
+/// it are counted but not stored, so a NOTICE in a loop can not exhaust the
+/// worker's memory.
+pub const JOB_NOTICES_MAX: usize = 100;
+
 /// Stack size (bytes) for each per-job worker thread. Workers only issue SQL
 /// over a pooled connection and format short strings — the heavy PL/pgSQL work
 /// happens inside the PostgreSQL backend, not here — so the 2 MiB default stack
@@ -26,6 +78,96 @@ pub const WORKER_STACK_SIZE: usize = 512 * 1024;
 /// backpressure to producers instead of growing memory without limit.
 pub const LOG_CHANNEL_CAPACITY: usize = 16384;
 
+/// Local syslog sockets tried in turn by `log_destination = syslog`.
+pub const SYSLOG_SOCKETS: &[&str] = &["/dev/log", "/var/run/syslog"];
+/// Socket of the journal native protocol, for `log_destination = journald`.
+pub const JOURNALD_SOCKETS: &[&str] = &["/run/systemd/journal/socket"];
+/// Delay before shipping log lines again to an unreachable GELF endpoint.
+pub const GELF_RETRY_INTERVAL: Duration = Duration::from_secs(10);
+/// Timeout of the connection to a GELF endpoint over TCP and of each write,
+/// bounding how long an unresponsive endpoint holds up the log writer.
+pub const GELF_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Period (seconds) of the clean-up of old strftime-dated log files
+/// (`log_retention_days`, `log_compress`).
+pub const LOG_EXPIRE_INTERVAL_SECS: u64 = 3600;
+
+/// Period (seconds) of the scheduler's heartbeat in
+/// `dbms_job.scheduler_registry`.
+pub const REGISTRY_HEARTBEAT_SECS: u64 = 10;
+/// Age (seconds) after which a registry heartbeat is stale: its scheduler is
+/// taken as gone and another one may start on the database.
+pub const REGISTRY_STALE_SECS: f64 = 60.0;
+/// Key of the session advisory lock a scheduler holds on its main
+/// connection for as long as it serves the database.
+pub const INSTANCE_LOCK_KEY: i64 = 0x7067_5f64_626d_736c;
+/// How long the main connection has to answer its check, and to acknowledge
+/// what is sent on it, before it is taken as dead.
+pub const CONNECTION_CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long a scheduler waits for the instance lock to be released by the
+/// terminated backend of its own previous main connection.
+pub const INSTANCE_LOCK_WAIT: Duration = Duration::from_secs(5);
+
+/// Minimum interval (seconds) between two `queue_saturated` events of
+/// `dbms_job.scheduler_event_log` while the worker pool stays saturated.
+pub const SATURATION_EVENT_INTERVAL_SECS: f64 = 60.0;
+
+/// Prefix of the environment variables overriding a setting of the
+/// configuration file, e.g. `PG_DBMS_JOB_PASSWD`.
+pub const ENV_PREFIX: &str = "PG_DBMS_JOB_";
+
+/// libpq environment variables giving a connection setting the
+/// configuration file and the `PG_DBMS_JOB_` variables leave unset.
+pub const LIBPQ_ENV: &[(&str, &str)] = &[
+    ("host", "PGHOST"),
+    ("port", "PGPORT"),
+    ("user", "PGUSER"),
+    ("database", "PGDATABASE"),
+    ("passwd", "PGPASSWORD"),
+];
+
+/// Depth of nested `include` directives of the configuration file beyond
+/// which they are ignored, which also stops an include cycle.
+pub const MAX_INCLUDE_DEPTH: usize = 10;
+
+/// Start of the header of a section of the configuration file holding the
+/// settings of one of the databases served, `[database <name>]`.
+pub const DATABASE_SECTION: &str = "database ";
+
+/// Schema of the job queue created by the extension, the one served when
+/// `schemas` is not set.
+pub const DEFAULT_SCHEMA: &str = "dbms_job";
+/// Suffix of the channel of the changes to the scheduled jobs of a job
+/// queue schema, `dbms_job_scheduled_notify` for the default one.
+pub const SCHEDULED_CHANNEL_SUFFIX: &str = "_scheduled_notify";
+/// Suffix of the channel of the asynchronous jobs of a job queue schema,
+/// `dbms_job_async_notify` for the default one.
+pub const ASYNC_CHANNEL_SUFFIX: &str = "_async_notify";
+/// Longest name of a job queue schema, so that its channel names fit in
+/// the 63 bytes of an identifier.
+pub const SCHEMA_NAME_MAX: usize = 63 - SCHEDULED_CHANNEL_SUFFIX.len();
+
+/// Prefix of the configuration keys defining a semaphore,
+/// `semaphore.<name> = <slots>`.
+pub const SEMAPHORE_PREFIX: &str = "semaphore.";
+/// Prefix of the configuration keys defining the process limit of a job
+/// class, `job_class.<name> = <processes>`.
+pub const JOB_CLASS_PREFIX: &str = "job_class.";
+
+/// Time limit (seconds) for `ssh` to open the tunnel to the database.
+pub const SSH_TUNNEL_TIMEOUT_SECS: f64 = 15.0;
+
+/// Quiet time (seconds) of the directory of the configuration file after
+/// which an `auto_reload` change is applied, and how often it is checked.
+pub const AUTO_RELOAD_SETTLE_SECS: f64 = 1.0;
+
+/// Time limit (seconds) of a `passwd_command` run, after which it is
+/// killed and the connection attempt fails.
+pub const PASSWD_COMMAND_TIMEOUT_SECS: f64 = 10.0;
+
+/// Time limit (seconds) of a `--healthcheck` probe, connection included.
+pub const HEALTHCHECK_TIMEOUT_SECS: u64 = 10;
+
 /// Maximum time (seconds) a worker waits to check out a pooled connection
 /// before giving up. With the worker count capped at the pool size a checkout
 /// should almost never block, so this only bounds the worst case (a stalled
@@ -44,11 +186,18 @@ pub const WORKER_SLOT_POLL_INTERVAL: Duration = Duration::from_millis(10);
 #[cfg(test)]
 mod tests {
     use super::{
-        LOG_CHANNEL_CAPACITY, POOL_CONNECTION_TIMEOUT_SECS, PROGRAM, VERSION,
-        WORKER_SLOT_POLL_INTERVAL, WORKER_STACK_SIZE,
+        LOG_CHANNEL_CAPACITY, POOL_CONNECTION_TIMEOUT_SECS, PROGRAM, REGISTRY_HEARTBEAT_SECS,
+        REGISTRY_STALE_SECS, VERSION, WORKER_SLOT_POLL_INTERVAL, WORKER_STACK_SIZE,
     };
     use std::time::Duration;
 
+    #[test]
+    fn registry_heartbeat_goes_stale_only_after_missed_beats() {
+        // A busy main loop can delay a beat; one late beat must not let a
+        // second scheduler in.
+        const { assert!(REGISTRY_STALE_SECS >= 3.0 * REGISTRY_HEARTBEAT_SECS as f64) };
+    }
+
     #[test]
     fn worker_stack_size_is_sane() {
         // Small enough to keep a burst of in-flight workers from ballooning
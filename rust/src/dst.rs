@@ -0,0 +1,251 @@
+//! Daylight saving time policy of the schedule evaluation.
+//!
+//! `dbms_job.get_next_date()` evaluates a job's interval with the server's
+//! `timestamptz` arithmetic, so what happens around a DST change depends on
+//! how the expression is written: a date in the skipped hour silently lands
+//! an hour later, and an hourly job runs twice at the same wall-clock time
+//! in the repeated hour. With a `dst_policy` other than `server`, the
+//! interval of a job whose next date crosses a change of UTC offset is
+//! evaluated again on the wall clock: at the run date, in a fixed-offset
+//! time zone equal to the offset of that date. The wall-clock date obtained
+//! is then placed in the session time zone by the policy: in the repeated
+//! hour at its first occurrence only, in the skipped hour shifted by the
+//! length of the gap (`shift`) or not at all (`skip`), the interval being
+//! evaluated again from there.
+
+use crate::db::in_schema;
+use crate::dlog;
+use crate::model::{Config, DstPolicy};
+use crate::simulate::{from_epoch, next_date_expr};
+use chrono::{DateTime, Utc};
+use postgres::Client;
+
+/// Dates in the skipped hour skipped in a row before falling back to
+/// `shift`, in case every date the interval yields is in the gap.
+const MAX_DST_SKIPS: usize = 8;
+
+/// A wall-clock date placed in the session time zone.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum WallDate {
+    /// The date exists, at this instant or, in the repeated hour, first at
+    /// this instant.
+    Valid(DateTime<Utc>),
+    /// The date is in the skipped hour; the server maps it to this instant,
+    /// shifted by the length of the gap.
+    Skipped(DateTime<Utc>),
+}
+
+/// POSIX time zone with the fixed UTC offset `offset` (seconds east of
+/// UTC), e.g. `<+0100>-01:00:00`. POSIX offsets count west of UTC.
+fn fixed_zone(offset: i32) -> String {
+    let sign = if offset < 0 { '-' } else { '+' };
+    let posix_sign = if offset < 0 { '+' } else { '-' };
+    let abs = offset.unsigned_abs();
+    let (h, m, s) = (abs / 3600, abs / 60 % 60, abs % 60);
+    format!("<{sign}{h:02}{m:02}>{posix_sign}{h:02}:{m:02}:{s:02}")
+}
+
+/// Whether the session time zone has another UTC offset at `b` than at `a`.
+pub fn crosses_offset_change(
+    client: &mut Client,
+    a: DateTime<Utc>,
+    b: DateTime<Utc>,
+) -> Result<bool, String> {
+    client
+        .query_one(
+            "SELECT extract(timezone FROM to_timestamp($1::float8)) \
+                 <> extract(timezone FROM to_timestamp($2::float8))",
+            &[&epoch(a), &epoch(b)],
+        )
+        .map(|row| row.get(0))
+        .map_err(|e| e.to_string())
+}
+
+/// Epoch seconds of `date`, as passed to `to_timestamp()`.
+fn epoch(date: DateTime<Utc>) -> f64 {
+    date.timestamp_micros() as f64 / 1_000_000.0
+}
+
+/// Next date of `interval` evaluated at `at` under `policy`. `None` when
+/// the interval yields no date.
+pub fn wall_clock_next_date(
+    client: &mut Client,
+    schema: &str,
+    interval: &str,
+    at: DateTime<Utc>,
+    policy: DstPolicy,
+) -> Result<Option<DateTime<Utc>>, String> {
+    let mut at = at;
+    let mut skips = 0;
+    loop {
+        let Some(wall) = evaluate_on_wall_clock(client, schema, interval, at)? else {
+            return Ok(None);
+        };
+        match place_wall_date(client, &wall, at)? {
+            WallDate::Valid(date) => return Ok(Some(date)),
+            WallDate::Skipped(date)
+                if policy == DstPolicy::Skip && date > at && skips < MAX_DST_SKIPS =>
+            {
+                skips += 1;
+                at = date;
+            }
+            WallDate::Skipped(date) => return Ok(Some(date)),
+        }
+    }
+}
+
+/// Wall-clock date, as a `timestamp` literal, of `interval` evaluated at
+/// `at` in a time zone of the fixed UTC offset of `at`.
+fn evaluate_on_wall_clock(
+    client: &mut Client,
+    schema: &str,
+    interval: &str,
+    at: DateTime<Utc>,
+) -> Result<Option<String>, String> {
+    let expr = next_date_expr(schema, interval, at);
+    let mut tx = client.transaction().map_err(|e| e.to_string())?;
+    let offset: f64 = tx
+        .query_one(
+            "SELECT extract(timezone FROM to_timestamp($1::float8))::float8",
+            &[&epoch(at)],
+        )
+        .map_err(|e| e.to_string())?
+        .get(0);
+    tx.execute(
+        "SELECT set_config('TimeZone', $1, true)",
+        &[&fixed_zone(offset as i32)],
+    )
+    .map_err(|e| e.to_string())?;
+    let wall: Option<String> = tx
+        .query_one(
+            &format!("SELECT (({expr})::timestamptz)::timestamp::text"),
+            &[],
+        )
+        .map_err(|e| format!("can not evaluate interval \"{interval}\": {e}"))?
+        .get(0);
+    // Rolled back on drop, restoring the session time zone.
+    Ok(wall)
+}
+
+/// Place the wall-clock date `wall`, computed at `at`, in the session time
+/// zone.
+fn place_wall_date(client: &mut Client, wall: &str, at: DateTime<Utc>) -> Result<WallDate, String> {
+    let row = client
+        .query_one(
+            "WITH c(i) AS (VALUES ($1::text::timestamp AT TIME ZONE current_setting('TimeZone')), \
+                ($1::text::timestamp AT TIME ZONE \
+                 (extract(timezone FROM to_timestamp($2::float8)) * interval '1 second'))) \
+             SELECT extract(epoch FROM min(i) FILTER (WHERE i AT TIME ZONE current_setting('TimeZone') = $1::text::timestamp))::float8, \
+                    extract(epoch FROM $1::text::timestamp AT TIME ZONE current_setting('TimeZone'))::float8 \
+             FROM c",
+            &[&wall, &epoch(at)],
+        )
+        .map_err(|e| e.to_string())?;
+    let valid: Option<f64> = row.get(0);
+    let mapped: f64 = row.get(1);
+    match valid.and_then(from_epoch) {
+        Some(date) => Ok(WallDate::Valid(date)),
+        None => from_epoch(mapped)
+            .map(WallDate::Skipped)
+            .ok_or_else(|| format!("date {wall} is out of range")),
+    }
+}
+
+/// Apply `dst_policy` to the scheduled jobs just claimed, `jobids`: those
+/// whose new `next_date` has another UTC offset than their run date get it
+/// computed again on the wall clock.
+pub fn apply_dst_policy(client: &mut Client, config: &Config, jobids: &[i64]) {
+    if config.dst_policy == DstPolicy::Server || jobids.is_empty() {
+        return;
+    }
+    let rows = match client.query(
+        &*in_schema(
+            &config.schema,
+            "SELECT job, interval, extract(epoch FROM this_date)::float8, extract(epoch FROM next_date)::float8 \
+             FROM dbms_job.all_scheduled_jobs \
+             WHERE job = ANY($1) AND this_date IS NOT NULL \
+               AND extract(timezone FROM next_date) <> extract(timezone FROM this_date)",
+        ),
+        &[&jobids],
+    ) {
+        Ok(rows) => rows,
+        Err(err) => {
+            dlog!(
+                config,
+                "ERROR",
+                "can not look for jobs crossing a DST change, {}",
+                err
+            );
+            return;
+        }
+    };
+    for row in rows {
+        let jobid: i64 = row.get(0);
+        let interval: String = row.get(1);
+        let (Some(at), Some(next)) = (from_epoch(row.get(2)), from_epoch(row.get(3))) else {
+            continue;
+        };
+        let date =
+            match wall_clock_next_date(client, &config.schema, &interval, at, config.dst_policy) {
+                Ok(Some(date)) => date,
+                Ok(None) => continue,
+                Err(err) => {
+                    dlog!(
+                        config,
+                        "ERROR",
+                        "can not apply dst_policy to job {}, keeping its next date, {}",
+                        jobid,
+                        err
+                    );
+                    continue;
+                }
+            };
+        if date == next {
+            dlog!(
+                config,
+                "DEBUG",
+                "job {} next date unchanged by dst_policy",
+                jobid
+            );
+            continue;
+        }
+        match client.execute(
+            &*in_schema(
+                &config.schema,
+                "UPDATE dbms_job.all_scheduled_jobs SET next_date = to_timestamp($2::float8) \
+             WHERE job = $1 AND this_date IS NOT NULL",
+            ),
+            &[&jobid, &epoch(date)],
+        ) {
+            Ok(_) => dlog!(
+                config,
+                "LOG",
+                "job {} crosses a DST change, next date moved from {} to {} by dst_policy={}",
+                jobid,
+                next.to_rfc3339(),
+                date.to_rfc3339(),
+                config.dst_policy.as_str()
+            ),
+            Err(err) => dlog!(
+                config,
+                "ERROR",
+                "can not apply dst_policy to job {}, {}",
+                jobid,
+                err
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fixed_zone;
+
+    #[test]
+    fn fixed_zone_counts_posix_offsets_west() {
+        assert_eq!(fixed_zone(3600), "<+0100>-01:00:00");
+        assert_eq!(fixed_zone(-18000), "<-0500>+05:00:00");
+        assert_eq!(fixed_zone(19800), "<+0530>-05:30:00");
+        assert_eq!(fixed_zone(0), "<+0000>-00:00:00");
+    }
+}
@@ -0,0 +1,164 @@
+//! Daemon events mirrored into `dbms_job.scheduler_event_log`.
+//!
+//! With `scheduler_event_log` set, the start and stop of the daemon, the
+//! configuration reloads, the losses of the main database connection and
+//! the saturation of the worker pool are also recorded in the database, for
+//! DBAs without access to the scheduler host. Events are queued with their
+//! date and written by the main loop once connected, so a lost connection is
+//! recorded as soon as it is back.
+
+use crate::constants::SATURATION_EVENT_INTERVAL_SECS;
+use crate::db::in_schema;
+use crate::dlog;
+use crate::model::Config;
+use crate::util::host_name;
+use postgres::Client;
+use std::collections::VecDeque;
+use std::process;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// Events kept while disconnected; the oldest are dropped beyond.
+const PENDING_EVENTS_MAX: usize = 100;
+
+/// Kind of a daemon event, the `event` column of the table.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SchedulerEvent {
+    Start,
+    Stop,
+    Reload,
+    ConnectionLost,
+    QueueSaturated,
+}
+
+impl SchedulerEvent {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SchedulerEvent::Start => "start",
+            SchedulerEvent::Stop => "stop",
+            SchedulerEvent::Reload => "reload",
+            SchedulerEvent::ConnectionLost => "connection_lost",
+            SchedulerEvent::QueueSaturated => "queue_saturated",
+        }
+    }
+}
+
+/// Events not yet written to the database.
+#[derive(Default)]
+pub struct EventLog {
+    pending: VecDeque<(SystemTime, SchedulerEvent, String)>,
+    last_saturation: Option<Instant>,
+}
+
+impl EventLog {
+    /// Queue an event, dated now.
+    pub fn record(&mut self, event: SchedulerEvent, detail: String) {
+        if self.pending.len() >= PENDING_EVENTS_MAX {
+            self.pending.pop_front();
+        }
+        self.pending.push_back((SystemTime::now(), event, detail));
+    }
+
+    /// Queue a saturation of the worker pool, at most once every
+    /// [`SATURATION_EVENT_INTERVAL_SECS`] while it lasts.
+    pub fn saturated(&mut self, max_workers: usize, now: Instant) {
+        if self
+            .last_saturation
+            .is_some_and(|t| now.duration_since(t).as_secs_f64() < SATURATION_EVENT_INTERVAL_SECS)
+        {
+            return;
+        }
+        self.last_saturation = Some(now);
+        self.record(
+            SchedulerEvent::QueueSaturated,
+            format!("worker pool saturated at {max_workers} concurrent jobs"),
+        );
+    }
+
+    /// Write the queued events, or drop them when `scheduler_event_log` is
+    /// off. They are kept for the next call when the connection is closed,
+    /// dropped on any other error.
+    pub fn flush(&mut self, client: &mut Client, config: &Config) {
+        if !config.scheduler_event_log {
+            self.pending.clear();
+            return;
+        }
+        if self.pending.is_empty() {
+            return;
+        }
+        let mut dates = Vec::with_capacity(self.pending.len());
+        let mut events = Vec::with_capacity(self.pending.len());
+        let mut details = Vec::with_capacity(self.pending.len());
+        for (at, event, detail) in &self.pending {
+            dates.push(
+                at.duration_since(UNIX_EPOCH)
+                    .map_or(0.0, |d| d.as_secs_f64()),
+            );
+            events.push(event.as_str());
+            details.push(detail.as_str());
+        }
+        match client.execute(
+            &*in_schema(
+                &config.schema,
+                "INSERT INTO dbms_job.scheduler_event_log (event_date, host, pid, event, detail) \
+             SELECT to_timestamp(e.at), $1, $2, e.event, e.detail \
+             FROM unnest($3::float8[], $4::text[], $5::text[]) AS e(at, event, detail)",
+            ),
+            &[
+                &host_name(),
+                &(process::id() as i32),
+                &dates,
+                &events,
+                &details,
+            ],
+        ) {
+            Ok(_) => self.pending.clear(),
+            Err(_) if client.is_closed() => {}
+            Err(err) => {
+                dlog!(
+                    config,
+                    "ERROR",
+                    "can not record {} events in dbms_job.scheduler_event_log, {err}",
+                    self.pending.len()
+                );
+                self.pending.clear();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EventLog, PENDING_EVENTS_MAX, SchedulerEvent};
+    use crate::constants::SATURATION_EVENT_INTERVAL_SECS;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn pending_events_are_capped_dropping_the_oldest() {
+        let mut events = EventLog::default();
+        for i in 0..PENDING_EVENTS_MAX + 2 {
+            events.record(SchedulerEvent::Reload, i.to_string());
+        }
+        assert_eq!(events.pending.len(), PENDING_EVENTS_MAX);
+        assert_eq!(events.pending.front().unwrap().2, "2");
+        assert_eq!(events.pending.front().unwrap().1.as_str(), "reload");
+    }
+
+    #[test]
+    fn saturation_is_recorded_once_per_interval() {
+        let mut events = EventLog::default();
+        let t0 = Instant::now();
+        events.saturated(8, t0);
+        events.saturated(8, t0 + Duration::from_secs(1));
+        assert_eq!(events.pending.len(), 1);
+        assert_eq!(
+            events.pending[0].2,
+            "worker pool saturated at 8 concurrent jobs"
+        );
+        events.saturated(
+            8,
+            t0 + Duration::from_secs_f64(SATURATION_EVENT_INTERVAL_SECS + 1.0),
+        );
+        assert_eq!(events.pending.len(), 2);
+        assert_eq!(events.pending[1].1, SchedulerEvent::QueueSaturated);
+    }
+}
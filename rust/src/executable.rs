@@ -0,0 +1,398 @@
+//! Operating system commands run by `EXECUTABLE` jobs.
+//!
+//! The `what` of an executable job is a command line the scheduler runs
+//! with `/bin/sh -c` on its own host, e.g. the `pg_dump` or `rsync` step of
+//! a chain of SQL jobs. The job connection keeps the transaction of the run
+//! open meanwhile, so resource keys, semaphores and the bookkeeping of the
+//! run work as for any other job. The standard output of the command is the
+//! result of the run, its standard error lines are kept with the messages
+//! of the run and its exit code is the error code of the run details.
+//!
+//! A command runs with the rights of the scheduler's operating system user,
+//! or of the job's `os_user` when the scheduler runs as root, so only the
+//! roles listed in `executable_job_owners` can have one run, and only as
+//! the users listed in `executable_os_users`, never as root: a scheduler
+//! running as root refuses the commands without `os_user`.
+
+use crate::constants::{ENV_PREFIX, LIBPQ_ENV};
+use crate::model::{Config, Job};
+use nix::sys::signal::{Signal, killpg};
+use nix::unistd::{Pid, Uid, User};
+use std::env;
+use std::io::Read;
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Shell running the command line of a job.
+const SHELL: &str = "/bin/sh";
+
+/// Bytes kept of each of the standard output and error of a command, the
+/// rest is read and dropped.
+const OUTPUT_MAX: usize = 64 * 1024;
+
+/// Interval at which a running command is checked for its end.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Time left after a command ended to processes it left in the background
+/// with its output open, before the output read so far is taken.
+const OUTPUT_GRACE: Duration = Duration::from_secs(1);
+
+/// How a command ended.
+#[derive(Debug, PartialEq)]
+pub enum CommandEnd {
+    /// The command exited with this code.
+    Exited(i32),
+    /// The command was killed by this signal.
+    Signaled(i32),
+    /// The scheduler killed the command past the time limit of the run.
+    TimedOut,
+}
+
+/// A command that ran to its end.
+#[derive(Debug)]
+pub struct CommandRun {
+    pub end: CommandEnd,
+    /// Standard output, without its last newline.
+    pub stdout: String,
+    /// Lines of the standard error.
+    pub stderr: Vec<String>,
+}
+
+impl CommandRun {
+    /// Exit code of the command, `None` when it was killed.
+    pub fn exit_code(&self) -> Option<i32> {
+        match self.end {
+            CommandEnd::Exited(code) => Some(code),
+            _ => None,
+        }
+    }
+
+    /// Error of a command that did not exit with code 0. `limit` is the
+    /// time limit of the run and the name of its setting.
+    pub fn failure(&self, limit: Option<(&str, Duration)>) -> Option<String> {
+        match self.end {
+            CommandEnd::Exited(0) => None,
+            CommandEnd::Exited(code) => Some(format!("command exited with code {code}")),
+            CommandEnd::Signaled(signal) => Some(format!("command killed by signal {signal}")),
+            CommandEnd::TimedOut => Some(match limit {
+                Some((setting, limit)) => format!(
+                    "run exceeded {} of {} seconds: command killed",
+                    setting,
+                    limit.as_secs_f64()
+                ),
+                None => "command killed".to_string(),
+            }),
+        }
+    }
+}
+
+/// Whether `executable_job_owners` lists the owner of a job.
+pub fn owner_allowed(config: &Config, owner: Option<&str>) -> bool {
+    owner.is_some_and(|owner| is_listed(&config.executable_job_owners, owner))
+}
+
+/// Whether `executable_os_users` lists the `os_user` of a job.
+pub fn os_user_allowed(config: &Config, os_user: &str) -> bool {
+    is_listed(&config.executable_os_users, os_user)
+}
+
+/// Whether a comma-separated list of roles holds `role`.
+fn is_listed(list: &str, role: &str) -> bool {
+    list.split(',').any(|listed| listed.trim() == role)
+}
+
+/// Run the command of an executable job to its end, killing it with its
+/// process group once past `limit`. `beat` is called while it runs.
+///
+/// The command gets the job number and the run id in `PG_DBMS_JOB_ID` and
+/// `PG_DBMS_JOB_RUN_ID`, and none of the `PG_DBMS_JOB_` and libpq variables
+/// of the scheduler's environment. With an `os_user`, it runs as that user,
+/// with its primary group, from its home directory or `/` when it has none.
+///
+/// An error is returned when the command can not be started, or would run
+/// as root, see [`command_user`].
+pub fn run_command(
+    job: &Job,
+    run_id: &str,
+    limit: Option<Duration>,
+    beat: impl Fn(),
+) -> Result<CommandRun, String> {
+    let mut command = Command::new(SHELL);
+    // The variables the scheduler takes its settings from, its password
+    // among them, are not passed on.
+    for (name, _) in env::vars_os() {
+        if name.to_str().is_some_and(|name| {
+            name.starts_with(ENV_PREFIX) || LIBPQ_ENV.iter().any(|(_, var)| *var == name)
+        }) {
+            command.env_remove(name);
+        }
+    }
+    command
+        .arg("-c")
+        .arg(&job.what)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .env("PG_DBMS_JOB_ID", job.job.to_string())
+        .env("PG_DBMS_JOB_RUN_ID", run_id)
+        // Its own group, so that the processes it starts are killed with it.
+        .process_group(0);
+    if let Some(user) = command_user(job.os_user.as_deref(), Uid::effective().is_root())? {
+        let name = &user.name;
+        // Service users often have no home directory.
+        let dir = if user.dir.is_dir() {
+            user.dir.as_path()
+        } else {
+            Path::new("/")
+        };
+        command
+            .uid(user.uid.as_raw())
+            .gid(user.gid.as_raw())
+            .current_dir(dir)
+            .env("HOME", &user.dir)
+            .env("USER", name)
+            .env("LOGNAME", name);
+    }
+    let mut child = command
+        .spawn()
+        .map_err(|e| format!("command could not be started, {e}"))?;
+    let stdout = capture(child.stdout.take());
+    let stderr = capture(child.stderr.take());
+
+    let started = Instant::now();
+    let end = loop {
+        beat();
+        match child.try_wait() {
+            Ok(Some(status)) => {
+                break match (status.code(), status.signal()) {
+                    (Some(code), _) => CommandEnd::Exited(code),
+                    (None, signal) => CommandEnd::Signaled(signal.unwrap_or(0)),
+                };
+            }
+            Ok(None) => {}
+            Err(err) => {
+                kill_group(&mut child);
+                return Err(format!("can not wait for the command, {err}"));
+            }
+        }
+        if limit.is_some_and(|limit| started.elapsed() >= limit) {
+            kill_group(&mut child);
+            break CommandEnd::TimedOut;
+        }
+        thread::sleep(POLL_INTERVAL);
+    };
+
+    let stdout = stdout.take();
+    Ok(CommandRun {
+        end,
+        stdout: stdout.strip_suffix('\n').unwrap_or(&stdout).to_string(),
+        stderr: stderr
+            .take()
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(str::to_string)
+            .collect(),
+    })
+}
+
+/// User a command runs as, `None` for the scheduler's own user. The
+/// `os_user` of the job must not have uid 0, and a job without one is
+/// refused when the scheduler runs as root.
+fn command_user(os_user: Option<&str>, as_root: bool) -> Result<Option<User>, String> {
+    let Some(name) = os_user else {
+        return if as_root {
+            Err("the scheduler runs as root and the job has no os_user, \
+                 commands never run as root"
+                .to_string())
+        } else {
+            Ok(None)
+        };
+    };
+    let user = User::from_name(name)
+        .map_err(|e| format!("can not look up os_user {name}, {e}"))?
+        .ok_or_else(|| format!("os_user {name} does not exist"))?;
+    if user.uid.is_root() {
+        return Err(format!(
+            "os_user {name} has uid 0, commands never run as root"
+        ));
+    }
+    Ok(Some(user))
+}
+
+/// Kill a command and the processes of its group, and reap it.
+fn kill_group(child: &mut Child) {
+    if let Ok(pid) = i32::try_from(child.id()) {
+        let _ = killpg(Pid::from_raw(pid), Signal::SIGKILL);
+    }
+    let _ = child.wait();
+}
+
+/// Output of a command read by a thread of its own, so that a command
+/// filling a pipe never blocks.
+struct Capture {
+    kept: Arc<Mutex<Vec<u8>>>,
+    reader: Option<JoinHandle<()>>,
+}
+
+fn capture(pipe: Option<impl Read + Send + 'static>) -> Capture {
+    let kept = Arc::new(Mutex::new(Vec::new()));
+    let reader = pipe.map(|mut pipe| {
+        let kept = Arc::clone(&kept);
+        thread::spawn(move || {
+            let mut buf = [0u8; 8192];
+            loop {
+                match pipe.read(&mut buf) {
+                    Ok(0) => break,
+                    Ok(n) => {
+                        let mut kept = kept.lock().unwrap_or_else(|e| e.into_inner());
+                        let room = OUTPUT_MAX.saturating_sub(kept.len());
+                        kept.extend_from_slice(&buf[..n.min(room)]);
+                    }
+                    Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {}
+                    Err(_) => break,
+                }
+            }
+        })
+    });
+    Capture { kept, reader }
+}
+
+impl Capture {
+    /// The output read, once the pipe is closed or [`OUTPUT_GRACE`] passed.
+    fn take(self) -> String {
+        if let Some(reader) = self.reader {
+            let deadline = Instant::now() + OUTPUT_GRACE;
+            while !reader.is_finished() && Instant::now() < deadline {
+                thread::sleep(Duration::from_millis(10));
+            }
+        }
+        let kept = self.kept.lock().unwrap_or_else(|e| e.into_inner());
+        String::from_utf8_lossy(&kept).into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CommandEnd, OUTPUT_MAX, command_user, is_listed, run_command};
+    use crate::model::Job;
+    use nix::unistd::Uid;
+    use std::cell::Cell;
+    use std::time::{Duration, Instant};
+
+    fn command_job(what: &str) -> Job {
+        Job {
+            job: 7,
+            what: what.to_string(),
+            log_user: Some("backup".to_string()),
+            schema_user: None,
+            on_success: None,
+            on_failure: None,
+            continue_on_error: false,
+            resource_key: None,
+            max_run_duration: None,
+            priority: 3,
+            source_job: 7,
+            executable: true,
+            // Commands without os_user are refused to a root scheduler.
+            os_user: Uid::effective().is_root().then(|| "nobody".to_string()),
+            job_class: None,
+        }
+    }
+
+    #[test]
+    fn output_and_exit_code_of_a_command_are_captured() {
+        let beats = Cell::new(0);
+        let run = run_command(
+            &command_job("echo \"$PG_DBMS_JOB_ID $PG_DBMS_JOB_RUN_ID\"; echo oops >&2; exit 3"),
+            "run-1",
+            None,
+            || beats.set(beats.get() + 1),
+        )
+        .expect("command runs");
+        assert_eq!(run.end, CommandEnd::Exited(3));
+        assert_eq!(run.exit_code(), Some(3));
+        assert_eq!(run.stdout, "7 run-1");
+        assert_eq!(run.stderr, vec!["oops".to_string()]);
+        assert_eq!(
+            run.failure(None).as_deref(),
+            Some("command exited with code 3")
+        );
+        assert!(beats.get() > 0);
+
+        let run = run_command(&command_job("true"), "run-2", None, || {}).expect("command runs");
+        assert_eq!(run.failure(None), None);
+    }
+
+    #[test]
+    fn output_of_a_command_is_capped() {
+        let run = run_command(
+            &command_job("head -c 200000 /dev/zero | tr '\\0' x"),
+            "run-1",
+            None,
+            || {},
+        )
+        .expect("command runs");
+        assert_eq!(run.end, CommandEnd::Exited(0));
+        assert_eq!(run.stdout.len(), OUTPUT_MAX);
+    }
+
+    #[test]
+    fn a_command_past_its_limit_is_killed_with_its_group() {
+        let started = Instant::now();
+        let run = run_command(
+            &command_job("sleep 30 & sleep 30; echo done"),
+            "run-1",
+            Some(Duration::from_millis(300)),
+            || {},
+        )
+        .expect("command runs");
+        assert_eq!(run.end, CommandEnd::TimedOut);
+        assert_eq!(run.exit_code(), None);
+        // The background sleep holding the output open was killed too.
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(
+            run.failure(Some(("max_run_duration", Duration::from_secs(60))))
+                .as_deref(),
+            Some("run exceeded max_run_duration of 60 seconds: command killed")
+        );
+    }
+
+    #[test]
+    fn an_unknown_os_user_is_refused() {
+        let mut job = command_job("true");
+        job.os_user = Some("pg_dbms_job_no_such_user".to_string());
+        let err = run_command(&job, "run-1", None, || {}).unwrap_err();
+        assert!(err.contains("does not exist"), "{err}");
+    }
+
+    #[test]
+    fn commands_never_run_as_root() {
+        let mut job = command_job("true");
+        job.os_user = Some("root".to_string());
+        let err = run_command(&job, "run-1", None, || {}).unwrap_err();
+        assert!(err.contains("uid 0"), "{err}");
+    }
+
+    #[test]
+    fn a_root_scheduler_refuses_commands_without_os_user() {
+        let err = command_user(None, true).unwrap_err();
+        assert!(err.contains("no os_user"), "{err}");
+        assert!(command_user(None, false).unwrap().is_none());
+        assert_eq!(
+            command_user(Some("nobody"), true).unwrap().unwrap().name,
+            "nobody"
+        );
+    }
+
+    #[test]
+    fn only_listed_owners_run_commands() {
+        assert!(!is_listed("", "backup"));
+        assert!(is_listed("etl, backup", "backup"));
+        assert!(!is_listed("etl, backup", "Backup"));
+        assert!(!is_listed("etl, backup", "app"));
+    }
+}
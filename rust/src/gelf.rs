@@ -0,0 +1,412 @@
+//! GELF output of the log writer.
+//!
+//! With `log_gelf_endpoint` set, each log line is also shipped to a Graylog
+//! (or any GELF) input, for hosts without a persistent filesystem. The
+//! message is the GELF 1.1 JSON object with the level of the line as
+//! `_log_level`, the pid as `_pid` and, for a line logged while a worker
+//! runs a job, `_job_id`, `_run_id` and `_duration`. Over TCP messages are
+//! framed by a null byte; over UDP a message larger than a datagram is sent
+//! in GELF chunks. An unreachable endpoint is retried every
+//! [`GELF_RETRY_INTERVAL`], the lines shipped meanwhile being dropped.
+
+use crate::constants::{GELF_RETRY_INTERVAL, GELF_TIMEOUT};
+use crate::util::host_name;
+use chrono::{DateTime, Local};
+use std::fmt;
+use std::io::Write;
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs, UdpSocket};
+use std::sync::OnceLock;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// Port of an endpoint given without one, the usual GELF input port.
+pub const DEFAULT_PORT: u16 = 12201;
+/// Size of a UDP datagram, chunk header included.
+const CHUNK_SIZE: usize = 8192;
+/// Size of the header of a chunk: magic bytes, message id, sequence number
+/// and count.
+const CHUNK_HEADER_SIZE: usize = 12;
+/// Chunks of a message at most; larger messages are dropped by Graylog.
+const MAX_CHUNKS: usize = 128;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Transport {
+    Udp,
+    Tcp,
+}
+
+/// Address of a GELF input, `udp://host[:port]` or `tcp://host[:port]`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Endpoint {
+    pub transport: Transport,
+    pub host: String,
+    pub port: u16,
+}
+
+impl Endpoint {
+    /// Parse a `log_gelf_endpoint` value, `None` when invalid. An IPv6
+    /// address is written in brackets.
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        let (transport, rest) = if let Some(rest) = value.strip_prefix("udp://") {
+            (Transport::Udp, rest)
+        } else if let Some(rest) = value.strip_prefix("tcp://") {
+            (Transport::Tcp, rest)
+        } else {
+            return None;
+        };
+        let (host, port) = if let Some(bracketed) = rest.strip_prefix('[') {
+            let (host, after) = bracketed.split_once(']')?;
+            match after {
+                "" => (host, None),
+                _ => (host, Some(after.strip_prefix(':')?)),
+            }
+        } else {
+            match rest.split_once(':') {
+                Some((host, port)) => (host, Some(port)),
+                None => (rest, None),
+            }
+        };
+        if host.is_empty() || host.contains(['/', '[', ']']) {
+            return None;
+        }
+        let port = match port {
+            Some(port) => port.parse().ok().filter(|&p| p != 0)?,
+            None => DEFAULT_PORT,
+        };
+        Some(Endpoint {
+            transport,
+            host: host.to_string(),
+            port,
+        })
+    }
+}
+
+impl fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let scheme = match self.transport {
+            Transport::Udp => "udp",
+            Transport::Tcp => "tcp",
+        };
+        if self.host.contains(':') {
+            write!(f, "{scheme}://[{}]:{}", self.host, self.port)
+        } else {
+            write!(f, "{scheme}://{}:{}", self.host, self.port)
+        }
+    }
+}
+
+/// Host name sent in every message, looked up once.
+pub fn host() -> &'static str {
+    static HOST: OnceLock<String> = OnceLock::new();
+    HOST.get_or_init(host_name)
+}
+
+/// Job of a line logged during a run: its id, the run id and the seconds
+/// since the run started.
+pub type JobFields<'a> = (i64, &'a str, f64);
+
+/// GELF message of one log line.
+pub fn message(
+    host: &str,
+    level: &str,
+    msg: &str,
+    at: DateTime<Local>,
+    pid: u32,
+    job: Option<JobFields<'_>>,
+) -> String {
+    let mut json = format!(
+        "{{\"version\":\"1.1\",\"host\":{},\"short_message\":{},\"timestamp\":{}.{:03},\"level\":{},\"_log_level\":{},\"_pid\":{pid}",
+        json_string(host),
+        json_string(msg),
+        at.timestamp(),
+        at.timestamp_subsec_millis(),
+        crate::syslog::severity(level),
+        json_string(level),
+    );
+    if let Some((jobid, run_id, duration)) = job {
+        json.push_str(&format!(
+            ",\"_job_id\":{jobid},\"_run_id\":{},\"_duration\":{duration:.3}",
+            json_string(run_id)
+        ));
+    }
+    json.push('}');
+    json
+}
+
+/// `s` as a JSON string literal.
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if u32::from(c) < 0x20 => out.push_str(&format!("\\u{:04x}", u32::from(c))),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// UDP datagrams of a message: the message itself when it fits, GELF
+/// chunks otherwise. `None` when it needs more than [`MAX_CHUNKS`].
+pub fn datagrams(payload: &[u8], id: u64) -> Option<Vec<Vec<u8>>> {
+    if payload.len() <= CHUNK_SIZE {
+        return Some(vec![payload.to_vec()]);
+    }
+    let pieces: Vec<&[u8]> = payload.chunks(CHUNK_SIZE - CHUNK_HEADER_SIZE).collect();
+    if pieces.len() > MAX_CHUNKS {
+        return None;
+    }
+    let count = pieces.len() as u8;
+    Some(
+        pieces
+            .into_iter()
+            .enumerate()
+            .map(|(seq, piece)| {
+                let mut chunk = Vec::with_capacity(CHUNK_HEADER_SIZE + piece.len());
+                chunk.extend_from_slice(&[0x1e, 0x0f]);
+                chunk.extend_from_slice(&id.to_be_bytes());
+                chunk.push(seq as u8);
+                chunk.push(count);
+                chunk.extend_from_slice(piece);
+                chunk
+            })
+            .collect(),
+    )
+}
+
+enum Connection {
+    Udp(UdpSocket),
+    Tcp(TcpStream),
+}
+
+/// Connection of the log writer to a GELF endpoint, opened lazily and
+/// again after an error, at most once every [`GELF_RETRY_INTERVAL`].
+pub struct GelfSink {
+    /// The `log_gelf_endpoint` value it was created for.
+    spec: String,
+    endpoint: Endpoint,
+    connection: Option<Connection>,
+    /// Set while the endpoint is unreachable: when to try again.
+    retry_at: Option<Instant>,
+    /// Messages dropped while the endpoint is unreachable.
+    dropped: u64,
+    /// Id of the next chunked message.
+    next_id: u64,
+}
+
+impl GelfSink {
+    /// Sink of a `log_gelf_endpoint` value, `None` when invalid.
+    pub fn new(spec: &str) -> Option<Self> {
+        Some(GelfSink {
+            spec: spec.to_string(),
+            endpoint: Endpoint::parse(spec)?,
+            connection: None,
+            retry_at: None,
+            dropped: 0,
+            next_id: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_nanos() as u64),
+        })
+    }
+
+    pub fn spec(&self) -> &str {
+        &self.spec
+    }
+
+    /// Ship a message. Returns the line to log when the endpoint has just
+    /// become unreachable, or reachable again.
+    pub fn send(&mut self, payload: &[u8]) -> Option<String> {
+        if self.connection.is_none() {
+            if self.retry_at.is_some_and(|t| Instant::now() < t) {
+                self.dropped += 1;
+                return None;
+            }
+            match self.connect() {
+                Ok(connection) => self.connection = Some(connection),
+                Err(err) => return self.failed(&err),
+            }
+        }
+        let sent = match self.connection.as_mut()? {
+            Connection::Udp(socket) => {
+                self.next_id = self.next_id.wrapping_add(1);
+                match datagrams(payload, self.next_id) {
+                    Some(datagrams) => datagrams
+                        .iter()
+                        .try_for_each(|d| socket.send(d).map(|_| ()))
+                        .map_err(|e| e.to_string()),
+                    // Too large for GELF over UDP: dropped, not an outage.
+                    None => Ok(()),
+                }
+            }
+            Connection::Tcp(stream) => stream
+                .write_all(payload)
+                .and_then(|()| stream.write_all(b"\0"))
+                .map_err(|e| e.to_string()),
+        };
+        match sent {
+            Ok(()) => self.retry_at.take().map(|_| {
+                format!(
+                    "shipping log lines to GELF endpoint {} again, {} were dropped",
+                    self.endpoint,
+                    std::mem::take(&mut self.dropped)
+                )
+            }),
+            Err(err) => {
+                self.connection = None;
+                self.failed(&err)
+            }
+        }
+    }
+
+    /// Record a failure, returning the line to log for the first one of an
+    /// outage.
+    fn failed(&mut self, err: &str) -> Option<String> {
+        self.dropped += 1;
+        let first = self.retry_at.is_none();
+        self.retry_at = Some(Instant::now() + GELF_RETRY_INTERVAL);
+        first.then(|| {
+            format!(
+                "can not ship log lines to GELF endpoint {}, {err}; retrying every {} seconds",
+                self.endpoint,
+                GELF_RETRY_INTERVAL.as_secs()
+            )
+        })
+    }
+
+    fn connect(&self) -> Result<Connection, String> {
+        let addr: SocketAddr = (self.endpoint.host.as_str(), self.endpoint.port)
+            .to_socket_addrs()
+            .map_err(|e| e.to_string())?
+            .next()
+            .ok_or_else(|| format!("{} has no address", self.endpoint.host))?;
+        match self.endpoint.transport {
+            Transport::Udp => {
+                let local = if addr.is_ipv4() {
+                    "0.0.0.0:0"
+                } else {
+                    "[::]:0"
+                };
+                let socket = UdpSocket::bind(local).map_err(|e| e.to_string())?;
+                socket.connect(addr).map_err(|e| e.to_string())?;
+                Ok(Connection::Udp(socket))
+            }
+            Transport::Tcp => {
+                let stream =
+                    TcpStream::connect_timeout(&addr, GELF_TIMEOUT).map_err(|e| e.to_string())?;
+                stream
+                    .set_write_timeout(Some(GELF_TIMEOUT))
+                    .map_err(|e| e.to_string())?;
+                Ok(Connection::Tcp(stream))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DEFAULT_PORT, Endpoint, GelfSink, Transport, datagrams, message};
+    use chrono::{Local, TimeZone};
+    use std::io::Read;
+    use std::net::{TcpListener, UdpSocket};
+
+    #[test]
+    fn endpoints() {
+        assert_eq!(
+            Endpoint::parse("udp://graylog.example.com:12202"),
+            Some(Endpoint {
+                transport: Transport::Udp,
+                host: "graylog.example.com".to_string(),
+                port: 12202,
+            })
+        );
+        let tcp = Endpoint::parse(" tcp://[::1] ").unwrap();
+        assert_eq!((tcp.transport, tcp.host.as_str()), (Transport::Tcp, "::1"));
+        assert_eq!(tcp.port, DEFAULT_PORT);
+        assert_eq!(tcp.to_string(), "tcp://[::1]:12201");
+        assert_eq!(Endpoint::parse("graylog:12201"), None);
+        assert_eq!(Endpoint::parse("http://graylog:12201"), None);
+        assert_eq!(Endpoint::parse("udp://graylog:port"), None);
+        assert_eq!(Endpoint::parse("udp://:12201"), None);
+        assert_eq!(Endpoint::parse("tcp://::1:12201"), None);
+    }
+
+    #[test]
+    fn message_format() {
+        let at = Local.with_ymd_and_hms(2026, 3, 5, 9, 7, 2).unwrap();
+        assert_eq!(
+            message("db1", "LOG", "scheduler \"started\"", at, 42, None),
+            format!(
+                "{{\"version\":\"1.1\",\"host\":\"db1\",\"short_message\":\"scheduler \\\"started\\\"\",\"timestamp\":{}.000,\"level\":6,\"_log_level\":\"LOG\",\"_pid\":42}}",
+                at.timestamp()
+            )
+        );
+        let job = message("db1", "ERROR", "a\nb\u{1}", at, 42, Some((7, "run", 1.5)));
+        assert!(job.contains("\"short_message\":\"a\\nb\\u0001\""));
+        assert!(job.contains("\"level\":3,"));
+        assert!(job.ends_with(",\"_job_id\":7,\"_run_id\":\"run\",\"_duration\":1.500}"));
+    }
+
+    #[test]
+    fn large_messages_are_chunked() {
+        assert_eq!(datagrams(b"{}", 1).unwrap(), vec![b"{}".to_vec()]);
+        let payload = vec![b'x'; 20000];
+        let chunks = datagrams(&payload, 0x0102).unwrap();
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(
+            &chunks[1][..12],
+            &[0x1e, 0x0f, 0, 0, 0, 0, 0, 0, 1, 2, 1, 3]
+        );
+        assert!(chunks.iter().all(|c| c.len() <= 8192));
+        let total: usize = chunks.iter().map(|c| c.len() - 12).sum();
+        assert_eq!(total, payload.len());
+        assert!(datagrams(&vec![b'x'; 8180 * 128 + 1], 1).is_none());
+    }
+
+    #[test]
+    fn ships_over_udp_and_tcp() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let port = receiver.local_addr().unwrap().port();
+        let mut sink = GelfSink::new(&format!("udp://127.0.0.1:{port}")).unwrap();
+        assert_eq!(sink.send(b"{\"a\":1}"), None);
+        let mut buf = [0u8; 64];
+        let n = receiver.recv(&mut buf).unwrap();
+        assert_eq!(&buf[..n], b"{\"a\":1}");
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let mut sink = GelfSink::new(&format!("tcp://127.0.0.1:{port}")).unwrap();
+        assert_eq!(sink.send(b"{\"a\":1}"), None);
+        assert_eq!(sink.send(b"{\"b\":2}"), None);
+        drop(sink);
+        let mut received = Vec::new();
+        listener
+            .accept()
+            .unwrap()
+            .0
+            .read_to_end(&mut received)
+            .unwrap();
+        assert_eq!(received, b"{\"a\":1}\0{\"b\":2}\0");
+    }
+
+    #[test]
+    fn unreachable_endpoint_is_reported_once() {
+        // Nothing listens on a port just released.
+        let port = {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            listener.local_addr().unwrap().port()
+        };
+        let mut sink = GelfSink::new(&format!("tcp://127.0.0.1:{port}")).unwrap();
+        let first = sink.send(b"{}").expect("outage reported");
+        assert!(first.starts_with(&format!(
+            "can not ship log lines to GELF endpoint tcp://127.0.0.1:{port}, "
+        )));
+        assert_eq!(sink.send(b"{}"), None);
+        assert_eq!(sink.dropped, 2);
+    }
+}
@@ -0,0 +1,106 @@
+//! `--healthcheck`: liveness probe of the local daemon for container
+//! runtimes.
+//!
+//! The daemon is healthy when the process named in its pid file is alive and
+//! its row of `dbms_job.scheduler_registry` has a fresh heartbeat: the
+//! heartbeat is sent from the main loop, so a hung loop or a lost database
+//! connection shows up as a stale one. The probe gives up after
+//! `HEALTHCHECK_TIMEOUT_SECS` so that a database that does not answer fails
+//! the check instead of hanging it.
+
+use crate::constants::{HEALTHCHECK_TIMEOUT_SECS, REGISTRY_STALE_SECS};
+use crate::db::{connect_tool, in_schema};
+use crate::model::DbInfo;
+use crate::process::read_pid_from_file;
+use crate::util::host_name;
+use nix::sys::signal::kill;
+use nix::unistd::Pid;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+/// Check the daemon whose pid file is `pidfile` through the registry of the
+/// job queue schema `schema`. Returns a one-line status, the reason it is
+/// unhealthy as the error.
+pub fn run_healthcheck(pidfile: &str, dbinfo: &DbInfo, schema: &str) -> Result<String, String> {
+    let pid = read_pid_from_file(pidfile)
+        .ok_or_else(|| format!("can not read a pid from {pidfile}, is the daemon running?"))?;
+    kill(Pid::from_raw(pid), None).map_err(|e| format!("process {pid} is not running, {e}"))?;
+
+    let (tx, rx) = mpsc::channel();
+    let dbinfo = dbinfo.clone();
+    let schema = schema.to_string();
+    // On timeout the thread is abandoned; the process exits right after.
+    thread::spawn(move || {
+        let _ = tx.send(heartbeat_age(&dbinfo, &schema, pid));
+    });
+    let age = rx
+        .recv_timeout(Duration::from_secs(HEALTHCHECK_TIMEOUT_SECS))
+        .map_err(|_| {
+            format!("no answer from the database within {HEALTHCHECK_TIMEOUT_SECS} seconds")
+        })??;
+    heartbeat_verdict(pid, age, REGISTRY_STALE_SECS)
+}
+
+/// Age in seconds of the registry heartbeat of the daemon `pid` on this
+/// host: `None` when it has no row, `Some(None)` when it has cleared it.
+fn heartbeat_age(dbinfo: &DbInfo, schema: &str, pid: i32) -> Result<Option<Option<f64>>, String> {
+    let mut client = connect_tool(dbinfo, "pg_dbms_job:healthcheck")?;
+    let row = client
+        .query_opt(
+            &*in_schema(
+                schema,
+                "SELECT extract(epoch FROM current_timestamp - heartbeat)::float8 \
+             FROM dbms_job.scheduler_registry WHERE host = $1 AND pid = $2",
+            ),
+            &[&host_name(), &pid],
+        )
+        .map_err(|e| e.to_string())?;
+    Ok(row.map(|row| row.get(0)))
+}
+
+/// Judge the heartbeat of the daemon `pid`.
+fn heartbeat_verdict(pid: i32, age: Option<Option<f64>>, stale: f64) -> Result<String, String> {
+    match age {
+        None => Err(format!(
+            "scheduler {pid} is not registered in dbms_job.scheduler_registry"
+        )),
+        Some(None) => Err(format!("scheduler {pid} has stopped its heartbeat")),
+        Some(Some(age)) if age > stale => Err(format!(
+            "heartbeat of scheduler {pid} is {age:.0} seconds old"
+        )),
+        Some(Some(age)) => Ok(format!(
+            "scheduler {pid} is alive, last heartbeat {:.0} seconds ago",
+            age.max(0.0)
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::heartbeat_verdict;
+
+    #[test]
+    fn fresh_heartbeat_is_healthy() {
+        assert_eq!(
+            heartbeat_verdict(42, Some(Some(3.2)), 60.0).unwrap(),
+            "scheduler 42 is alive, last heartbeat 3 seconds ago"
+        );
+    }
+
+    #[test]
+    fn stale_missing_or_cleared_heartbeat_is_unhealthy() {
+        let err = heartbeat_verdict(42, Some(Some(75.0)), 60.0).unwrap_err();
+        assert_eq!(err, "heartbeat of scheduler 42 is 75 seconds old");
+        assert!(
+            heartbeat_verdict(42, Some(None), 60.0)
+                .unwrap_err()
+                .contains("stopped")
+        );
+        assert!(
+            heartbeat_verdict(42, None, 60.0)
+                .unwrap_err()
+                .contains("not registered")
+        );
+    }
+}
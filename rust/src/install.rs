@@ -0,0 +1,260 @@
+//! `--install-schema` and `--upgrade-schema`: the `dbms_job` schema shipped
+//! in the binary.
+//!
+//! Where the extension files are installed on the server, the schema is
+//! created and updated as the `pg_dbms_job` extension. Elsewhere, such as on
+//! managed services that only allow their own extensions, the extension
+//! script embedded in the binary is run as is in a `dbms_job` schema, and
+//! the embedded migrations bring it from the version returned by
+//! `dbms_job.schema_version()` to the one of the binary.
+//!
+//! Another job queue schema of the database, given by `--schema`, is always
+//! installed from the embedded script, with its objects and notification
+//! channels renamed after it: an extension can only be created once.
+
+use crate::constants::{DEFAULT_SCHEMA, VERSION};
+use crate::db::{async_channel, connect_tool, in_schema, scheduled_channel};
+use crate::model::DbInfo;
+use postgres::{Client, Transaction};
+
+/// Name of the extension.
+const EXTENSION: &str = "pg_dbms_job";
+
+/// Creation script of the objects of this version, as run by
+/// `CREATE EXTENSION`.
+const INSTALL_SCRIPT: &str = include_str!(concat!(
+    "../../sql/pg_dbms_job--",
+    env!("CARGO_PKG_VERSION"),
+    ".sql"
+));
+
+/// Migrations of a schema installed without the extension: the update
+/// script from a version to the next, in order. 3.1.0 is the first version
+/// that can be installed so; each later `updates/` script is added here.
+const MIGRATIONS: &[(&str, &str, &str)] = &[];
+
+/// How the `dbms_job` schema of a database is installed.
+#[derive(Debug, PartialEq)]
+enum Installed {
+    /// No `dbms_job` schema.
+    Nothing,
+    /// The extension, at this version.
+    Extension(String),
+    /// The objects of this version, created by `--install-schema`.
+    Schema(String),
+    /// A `dbms_job` schema without `schema_version()`.
+    Unknown,
+}
+
+/// Create the job queue schema `schema` of this version.
+pub fn install_schema(dbinfo: &DbInfo, schema: &str) -> Result<(), String> {
+    let mut client = connect_tool(dbinfo, "pg_dbms_job:install-schema")?;
+    match installed(&mut client, schema)? {
+        Installed::Nothing => {}
+        Installed::Extension(version) | Installed::Schema(version) => {
+            return Err(format!(
+                "the {schema} schema is already installed at version {version}, use --upgrade-schema"
+            ));
+        }
+        Installed::Unknown => {
+            return Err(format!(
+                "a {schema} schema of an unknown version already exists"
+            ));
+        }
+    }
+    let mut tx = client.transaction().map_err(|e| e.to_string())?;
+    if schema == DEFAULT_SCHEMA && extension_available(&mut tx, VERSION)? {
+        tx.batch_execute(&format!("CREATE EXTENSION {EXTENSION} VERSION '{VERSION}'"))
+            .map_err(|e| sql_error("CREATE EXTENSION", &e))?;
+        tx.commit().map_err(|e| e.to_string())?;
+        println!("installed the {EXTENSION} extension version {VERSION}");
+    } else {
+        tx.batch_execute(&format!("CREATE SCHEMA \"{schema}\""))
+            .map_err(|e| sql_error("CREATE SCHEMA", &e))?;
+        tx.batch_execute(&schema_script(schema, INSTALL_SCRIPT))
+            .map_err(|e| sql_error("the installation script", &e))?;
+        tx.commit().map_err(|e| e.to_string())?;
+        println!("installed the {schema} schema version {VERSION}");
+    }
+    Ok(())
+}
+
+/// Bring the job queue schema `schema` to this version.
+pub fn upgrade_schema(dbinfo: &DbInfo, schema: &str) -> Result<(), String> {
+    let mut client = connect_tool(dbinfo, "pg_dbms_job:upgrade-schema")?;
+    match installed(&mut client, schema)? {
+        Installed::Nothing => Err(format!("no {schema} schema, use --install-schema")),
+        Installed::Unknown => Err(format!(
+            "the {schema} schema has no schema_version(), it can not be upgraded"
+        )),
+        Installed::Extension(version) if version == VERSION => {
+            println!("the {EXTENSION} extension is up to date at version {VERSION}");
+            Ok(())
+        }
+        Installed::Extension(version) => {
+            let mut tx = client.transaction().map_err(|e| e.to_string())?;
+            if !extension_available(&mut tx, VERSION)? {
+                return Err(format!(
+                    "the {EXTENSION} extension is installed at version {version}: install the extension files of version {VERSION} on the server to upgrade it"
+                ));
+            }
+            tx.batch_execute(&format!(
+                "ALTER EXTENSION {EXTENSION} UPDATE TO '{VERSION}'"
+            ))
+            .map_err(|e| sql_error("ALTER EXTENSION", &e))?;
+            tx.commit().map_err(|e| e.to_string())?;
+            println!("upgraded the {EXTENSION} extension from version {version} to {VERSION}");
+            Ok(())
+        }
+        Installed::Schema(version) => {
+            let steps = migration_path(MIGRATIONS, &version, VERSION)?;
+            if steps.is_empty() {
+                println!("the {schema} schema is up to date at version {VERSION}");
+                return Ok(());
+            }
+            let mut tx = client.transaction().map_err(|e| e.to_string())?;
+            for (from, to, script) in steps {
+                tx.batch_execute(&schema_script(schema, script))
+                    .map_err(|e| sql_error(&format!("the migration from {from} to {to}"), &e))?;
+                println!("migrated the {schema} schema from version {from} to {to}");
+            }
+            tx.commit().map_err(|e| e.to_string())?;
+            Ok(())
+        }
+    }
+}
+
+/// What is installed in the job queue schema `schema` of the database.
+fn installed(client: &mut Client, schema: &str) -> Result<Installed, String> {
+    let row = client
+        .query_one(
+            &*in_schema(
+                schema,
+                "SELECT (SELECT extversion FROM pg_extension WHERE extname = $1), \
+                 to_regnamespace(quote_ident($2)) IS NOT NULL, \
+                 to_regprocedure('dbms_job.schema_version()') IS NOT NULL",
+            ),
+            &[&EXTENSION, &schema],
+        )
+        .map_err(|e| e.to_string())?;
+    if let Some(version) = row
+        .get::<_, Option<String>>(0)
+        .filter(|_| schema == DEFAULT_SCHEMA)
+    {
+        return Ok(Installed::Extension(version));
+    }
+    if !row.get::<_, bool>(1) {
+        return Ok(Installed::Nothing);
+    }
+    if !row.get::<_, bool>(2) {
+        return Ok(Installed::Unknown);
+    }
+    let version: String = client
+        .query_one(&*in_schema(schema, "SELECT dbms_job.schema_version()"), &[])
+        .map_err(|e| e.to_string())?
+        .get(0);
+    Ok(Installed::Schema(version))
+}
+
+/// `script`, written for the `dbms_job` schema, for the job queue schema
+/// `schema`: its objects and notification channels are renamed after it.
+fn schema_script(schema: &str, script: &str) -> String {
+    if schema == DEFAULT_SCHEMA {
+        return script.to_string();
+    }
+    in_schema(schema, script)
+        .replace(
+            &format!("'{}'", scheduled_channel(DEFAULT_SCHEMA)),
+            &format!("'{}'", scheduled_channel(schema)),
+        )
+        .replace(
+            &format!("'{}'", async_channel(DEFAULT_SCHEMA)),
+            &format!("'{}'", async_channel(schema)),
+        )
+        .replace(&format!("'{DEFAULT_SCHEMA}'"), &format!("'{schema}'"))
+}
+
+/// Whether the server has the extension files of `version`.
+fn extension_available(tx: &mut Transaction<'_>, version: &str) -> Result<bool, String> {
+    tx.query_one(
+        "SELECT EXISTS (SELECT 1 FROM pg_available_extension_versions WHERE name = $1 AND version = $2)",
+        &[&EXTENSION, &version],
+    )
+    .map(|row| row.get(0))
+    .map_err(|e| e.to_string())
+}
+
+/// The migrations leading from version `from` to `to`, empty when `from`
+/// is `to`.
+fn migration_path<'a>(
+    migrations: &'a [(&'a str, &'a str, &'a str)],
+    from: &str,
+    to: &str,
+) -> Result<Vec<(&'a str, &'a str, &'a str)>, String> {
+    let mut steps = Vec::new();
+    let mut version = from;
+    while version != to {
+        match migrations.iter().find(|(start, _, _)| *start == version) {
+            Some(&step) => {
+                steps.push(step);
+                version = step.1;
+            }
+            None => {
+                return Err(format!(
+                    "no migration of the dbms_job schema from version {version} to {to}"
+                ));
+            }
+        }
+    }
+    Ok(steps)
+}
+
+/// Message of a failed statement, with the error reported by the server.
+fn sql_error(what: &str, err: &postgres::Error) -> String {
+    match err.as_db_error() {
+        Some(db) => format!("{what} failed: {}", db.message()),
+        None => format!("{what} failed: {err}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{INSTALL_SCRIPT, migration_path, schema_script};
+
+    #[test]
+    fn the_embedded_script_is_the_one_of_this_version() {
+        assert!(INSTALL_SCRIPT.contains("CREATE TABLE dbms_job.all_scheduled_jobs"));
+        assert!(INSTALL_SCRIPT.contains(&format!("SELECT '{}'::text", env!("CARGO_PKG_VERSION"))));
+    }
+
+    #[test]
+    fn the_script_of_another_schema_names_its_objects_and_channels() {
+        assert_eq!(schema_script("dbms_job", INSTALL_SCRIPT), INSTALL_SCRIPT);
+        let script = schema_script("tenant_a", INSTALL_SCRIPT);
+        assert!(script.contains("CREATE TABLE \"tenant_a\".all_scheduled_jobs"));
+        assert!(script.contains("pg_notify('tenant_a_scheduled_notify'"));
+        assert!(script.contains("pg_notify('tenant_a_async_notify'"));
+        assert!(script.contains("n.nspname = 'tenant_a'"));
+        // Only the pg_dbms_job.* settings keep the name.
+        let script = script.replace("pg_dbms_job.", "");
+        assert!(!script.contains("dbms_job."));
+        assert!(!script.contains("'dbms_job"));
+    }
+
+    #[test]
+    fn migrations_are_chained() {
+        let migrations = [
+            ("3.1.0", "3.2.0", "a"),
+            ("3.2.0", "3.2.1", "b"),
+            ("3.2.1", "3.3.0", "c"),
+        ];
+        assert_eq!(migration_path(&migrations, "3.3.0", "3.3.0"), Ok(vec![]));
+        let steps = migration_path(&migrations, "3.2.0", "3.3.0").unwrap();
+        assert_eq!(
+            steps.iter().map(|s| s.2).collect::<Vec<_>>(),
+            vec!["b", "c"]
+        );
+        assert!(migration_path(&migrations, "3.0.2", "3.3.0").is_err());
+        assert!(migration_path(&migrations, "3.1.0", "3.2.5").is_err());
+    }
+}
@@ -0,0 +1,545 @@
+//! `--dump-jobs` and `--load-jobs`: scheduled job definitions as a JSON or
+//! YAML file.
+//!
+//! The definitions of the scheduled jobs are dumped as a JSON array, or a
+//! YAML sequence, that can be kept under version control, and a file of
+//! that format is loaded back by adding the jobs it lists, updating those
+//! that differ and, with `--prune`, removing those it does not list.
+//! `--diff` only prints what a load would change. JSON is built and parsed
+//! by the server (`jsonb_agg`, `jsonb_to_recordset`), so both sides of the
+//! comparison are rendered as text by the same casts; YAML is only
+//! translated from and to that JSON.
+//!
+//! Jobs are identified by their `job` number. `next_date` is run state: it
+//! is used for the jobs added, never compared nor updated.
+
+use crate::db::{connect_tool, in_schema};
+use crate::model::DbInfo;
+use postgres::Client;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+
+/// Fields of a job definition, as `(JSON key, column, type)`, in the order
+/// of the `fields` of a [`JobDefinition`].
+const FIELDS: &[(&str, &str, &str)] = &[
+    ("owner", "log_user", "name"),
+    ("schema_user", "schema_user", "text"),
+    ("what", "what", "text"),
+    ("interval", "interval", "text"),
+    ("broken", "broken", "boolean"),
+    ("on_success", "on_success", "bigint"),
+    ("on_failure", "on_failure", "bigint"),
+    ("continue_on_error", "continue_on_error", "boolean"),
+    ("retry_delay", "retry_delay", "interval"),
+    ("resource_key", "resource_key", "text"),
+    ("max_run_duration", "max_run_duration", "interval"),
+    ("max_failures", "max_failures", "integer"),
+    ("priority", "priority", "integer"),
+    ("job_class", "job_class", "text"),
+    ("job_type", "job_type", "text"),
+    ("os_user", "os_user", "text"),
+];
+
+/// Fields a job of the file can not leave out.
+const REQUIRED_FIELDS: &[&str] = &["owner", "what"];
+
+/// Fields whose absence from the file means the default of their column.
+const DEFAULTS: &[(&str, &str)] = &[
+    ("broken", "false"),
+    ("continue_on_error", "false"),
+    ("priority", "3"),
+    ("job_type", "'PLSQL_BLOCK'"),
+];
+
+/// Value of a field of the file, with its default when it is left out.
+fn field_value(key: &str) -> String {
+    match DEFAULTS.iter().find(|(field, _)| *field == key) {
+        Some((_, default)) => format!("coalesce(f.{key}, {default})"),
+        None => format!("f.{key}"),
+    }
+}
+
+/// Format of a job definitions file.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum JobFileFormat {
+    Json,
+    Yaml,
+}
+
+impl JobFileFormat {
+    /// The format named by `--format`, else the one of the extension of the
+    /// file: `.yaml` and `.yml` are YAML, anything else JSON.
+    pub fn new(name: Option<&str>, path: Option<&str>) -> Result<Self, String> {
+        match name.map(str::to_lowercase).as_deref() {
+            Some("json") => Ok(JobFileFormat::Json),
+            Some("yaml" | "yml") => Ok(JobFileFormat::Yaml),
+            Some(other) => Err(format!(
+                "invalid format \"{other}\" given to --format, expected json or yaml"
+            )),
+            None => match path
+                .and_then(|p| Path::new(p).extension())
+                .and_then(|e| e.to_str())
+                .map(str::to_lowercase)
+                .as_deref()
+            {
+                Some("yaml" | "yml") => Ok(JobFileFormat::Yaml),
+                _ => Ok(JobFileFormat::Json),
+            },
+        }
+    }
+
+    /// The JSON array of the server as written in this format.
+    fn render(self, json: &str) -> Result<String, String> {
+        match self {
+            JobFileFormat::Json => Ok(format!("{json}\n")),
+            JobFileFormat::Yaml => {
+                let jobs: Value = serde_json::from_str(json)
+                    .map_err(|e| format!("invalid JSON from the server, {e}"))?;
+                serde_yaml::to_string(&ordered_definitions(jobs))
+                    .map_err(|e| format!("can not write YAML, {e}"))
+            }
+        }
+    }
+
+    /// The content of a file of this format as the JSON the server parses.
+    fn to_json(self, text: &str) -> Result<String, String> {
+        match self {
+            JobFileFormat::Json => Ok(text.to_string()),
+            JobFileFormat::Yaml => serde_yaml::from_str::<Value>(text)
+                .map(|jobs| jobs.to_string())
+                .map_err(|e| format!("invalid job definitions file, {e}")),
+        }
+    }
+}
+
+/// The definitions as YAML, with the keys of each job in the order of
+/// [`FIELDS`] between `job` and `next_date` rather than the order of jsonb.
+fn ordered_definitions(jobs: Value) -> serde_yaml::Value {
+    let Value::Array(jobs) = jobs else {
+        return serde_yaml::to_value(jobs).unwrap_or_default();
+    };
+    let keys: Vec<&str> = std::iter::once("job")
+        .chain(FIELDS.iter().map(|(key, _, _)| *key))
+        .chain(std::iter::once("next_date"))
+        .collect();
+    serde_yaml::Value::Sequence(
+        jobs.into_iter()
+            .map(|mut job| {
+                let mut mapping = serde_yaml::Mapping::new();
+                for key in &keys {
+                    if let Some(value) = job.get_mut(*key).map(Value::take) {
+                        mapping.insert(
+                            (*key).into(),
+                            serde_yaml::to_value(value).unwrap_or_default(),
+                        );
+                    }
+                }
+                serde_yaml::Value::Mapping(mapping)
+            })
+            .collect(),
+    )
+}
+
+/// A scheduled job definition, each field of [`FIELDS`] rendered as text.
+#[derive(Clone, Debug, PartialEq)]
+pub struct JobDefinition {
+    pub job: i64,
+    pub fields: Vec<Option<String>>,
+}
+
+/// Change made to the scheduled jobs by a load.
+#[derive(Clone, Debug, PartialEq)]
+pub enum JobChange {
+    /// A job of the file absent from the database.
+    Add(i64),
+    /// A job whose listed fields differ.
+    Change(i64, Vec<&'static str>),
+    /// A job absent from the file, with `--prune`.
+    Remove(i64),
+}
+
+impl JobChange {
+    fn job(&self) -> i64 {
+        match self {
+            JobChange::Add(job) | JobChange::Change(job, _) | JobChange::Remove(job) => *job,
+        }
+    }
+
+    /// One line of the `--diff` output.
+    pub fn describe(&self) -> String {
+        match self {
+            JobChange::Add(job) => format!("add job {job}"),
+            JobChange::Change(job, fields) => format!("change job {job}: {}", fields.join(", ")),
+            JobChange::Remove(job) => format!("remove job {job}"),
+        }
+    }
+}
+
+/// Print the definitions of every scheduled job of the job queue schema
+/// `schema` as a JSON array or a YAML sequence.
+pub fn dump_jobs(dbinfo: &DbInfo, schema: &str, format: JobFileFormat) -> Result<(), String> {
+    let mut client = connect_tool(dbinfo, "pg_dbms_job:dump-jobs")?;
+    let row = client
+        .query_one(&*in_schema(schema, &dump_query()), &[])
+        .map_err(|e| sql_error(&e))?;
+    print!("{}", format.render(&row.get::<_, String>(0))?);
+    Ok(())
+}
+
+/// Load the job definitions of the file `path` into the job queue schema
+/// `schema`. With `prune`, the jobs it does not list are removed; with
+/// `diff_only`, nothing is changed. Every change is printed, then applied
+/// in a single transaction.
+pub fn load_jobs(
+    dbinfo: &DbInfo,
+    schema: &str,
+    path: &str,
+    format: JobFileFormat,
+    prune: bool,
+    diff_only: bool,
+) -> Result<(), String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("can not read {path}: {e}"))?;
+    let json = format.to_json(&text)?;
+    let mut client = connect_tool(dbinfo, "pg_dbms_job:load-jobs")?;
+    let wanted = file_definitions(&mut client, &json)?;
+    let current = database_definitions(&mut client, schema)?;
+    let changes = diff_jobs(&wanted, &current, prune);
+
+    for change in &changes {
+        println!("{}", change.describe());
+    }
+    if changes.is_empty() {
+        println!("job definitions are up to date");
+        return Ok(());
+    }
+    if diff_only {
+        return Ok(());
+    }
+    apply_changes(&mut client, schema, &json, &changes)?;
+    let count = |f: fn(&JobChange) -> bool| changes.iter().filter(|c| f(c)).count();
+    println!(
+        "{} added, {} changed, {} removed",
+        count(|c| matches!(c, JobChange::Add(_))),
+        count(|c| matches!(c, JobChange::Change(..))),
+        count(|c| matches!(c, JobChange::Remove(_)))
+    );
+    Ok(())
+}
+
+/// Query building the pretty-printed JSON array of the scheduled jobs.
+fn dump_query() -> String {
+    let fields: Vec<String> = FIELDS
+        .iter()
+        .map(|(key, column, _)| format!("'{key}', j.{column}"))
+        .collect();
+    format!(
+        "SELECT jsonb_pretty(coalesce(jsonb_agg(jsonb_build_object('job', j.job, {}, 'next_date', j.next_date) ORDER BY j.job), '[]')) \
+         FROM dbms_job.all_scheduled_jobs j",
+        fields.join(", ")
+    )
+}
+
+/// `jsonb_to_recordset` of the file, passed as `$1`, with its column list.
+fn file_recordset() -> String {
+    let columns: Vec<String> = FIELDS
+        .iter()
+        .map(|(key, _, kind)| format!("{key} {kind}"))
+        .collect();
+    format!(
+        "jsonb_to_recordset($1::text::jsonb) AS f(job bigint, {}, next_date timestamp with time zone)",
+        columns.join(", ")
+    )
+}
+
+/// Definitions listed by the file, as JSON, checked for missing fields and
+/// duplicate jobs.
+fn file_definitions(client: &mut Client, json: &str) -> Result<Vec<JobDefinition>, String> {
+    let fields: Vec<String> = FIELDS
+        .iter()
+        .map(|(key, _, _)| format!("{}::text", field_value(key)))
+        .collect();
+    let query = format!(
+        "SELECT f.job, {} FROM {} ORDER BY f.job",
+        fields.join(", "),
+        file_recordset()
+    );
+    let rows = client
+        .query(&query, &[&json])
+        .map_err(|e| format!("invalid job definitions file, {}", sql_error(&e)))?;
+    let definitions: Vec<JobDefinition> = rows.iter().map(definition_from_row).collect();
+    check_definitions(&definitions)?;
+    Ok(definitions)
+}
+
+/// Definitions of the scheduled jobs of the database.
+fn database_definitions(client: &mut Client, schema: &str) -> Result<Vec<JobDefinition>, String> {
+    let fields: Vec<String> = FIELDS
+        .iter()
+        .map(|(_, column, _)| format!("j.{column}::text"))
+        .collect();
+    let query = format!(
+        "SELECT j.job, {} FROM dbms_job.all_scheduled_jobs j ORDER BY j.job",
+        fields.join(", ")
+    );
+    let rows = client
+        .query(&*in_schema(schema, &query), &[])
+        .map_err(|e| sql_error(&e))?;
+    Ok(rows.iter().map(definition_from_row).collect())
+}
+
+/// Message of a statement error, the server's own when there is one.
+fn sql_error(err: &postgres::Error) -> String {
+    err.as_db_error()
+        .map_or_else(|| err.to_string(), |db| db.message().to_string())
+}
+
+fn definition_from_row(row: &postgres::Row) -> JobDefinition {
+    JobDefinition {
+        job: row.get::<_, Option<i64>>(0).unwrap_or_default(),
+        fields: (1..=FIELDS.len()).map(|i| row.get(i)).collect(),
+    }
+}
+
+/// Refuse a file whose jobs lack their number or a required field, or list
+/// the same job twice.
+fn check_definitions(definitions: &[JobDefinition]) -> Result<(), String> {
+    let mut seen = BTreeSet::new();
+    for (i, def) in definitions.iter().enumerate() {
+        if def.job == 0 {
+            return Err(format!("job definition {} has no job number", i + 1));
+        }
+        if !seen.insert(def.job) {
+            return Err(format!("job {} is defined more than once", def.job));
+        }
+        for ((key, _, _), value) in FIELDS.iter().zip(&def.fields) {
+            if value.is_none() && REQUIRED_FIELDS.contains(key) {
+                return Err(format!("job {} has no {key}", def.job));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Changes turning the `current` definitions into the `wanted` ones, by job
+/// number.
+pub fn diff_jobs(
+    wanted: &[JobDefinition],
+    current: &[JobDefinition],
+    prune: bool,
+) -> Vec<JobChange> {
+    let current_by_job: BTreeMap<i64, &JobDefinition> =
+        current.iter().map(|def| (def.job, def)).collect();
+    let wanted_jobs: BTreeSet<i64> = wanted.iter().map(|def| def.job).collect();
+
+    let mut changes: Vec<JobChange> = wanted
+        .iter()
+        .filter_map(|def| match current_by_job.get(&def.job) {
+            None => Some(JobChange::Add(def.job)),
+            Some(cur) => {
+                let fields: Vec<&'static str> = FIELDS
+                    .iter()
+                    .zip(def.fields.iter().zip(&cur.fields))
+                    .filter(|(_, (new, old))| new != old)
+                    .map(|((key, _, _), _)| *key)
+                    .collect();
+                (!fields.is_empty()).then_some(JobChange::Change(def.job, fields))
+            }
+        })
+        .collect();
+    if prune {
+        changes.extend(
+            current
+                .iter()
+                .filter(|def| !wanted_jobs.contains(&def.job))
+                .map(|def| JobChange::Remove(def.job)),
+        );
+    }
+    changes.sort_by_key(JobChange::job);
+    changes
+}
+
+/// Apply `changes` from the file `json` in one transaction. The job
+/// sequence is moved past the jobs added, so that later submissions do not
+/// collide with them.
+fn apply_changes(
+    client: &mut Client,
+    schema: &str,
+    json: &str,
+    changes: &[JobChange],
+) -> Result<(), String> {
+    let jobs_of = |f: fn(&JobChange) -> bool| -> Vec<i64> {
+        changes
+            .iter()
+            .filter(|c| f(c))
+            .map(JobChange::job)
+            .collect()
+    };
+    let added = jobs_of(|c| matches!(c, JobChange::Add(_)));
+    let changed = jobs_of(|c| matches!(c, JobChange::Change(..)));
+    let removed = jobs_of(|c| matches!(c, JobChange::Remove(_)));
+
+    let columns: Vec<&str> = FIELDS.iter().map(|(_, column, _)| *column).collect();
+    let values: Vec<String> = FIELDS.iter().map(|(key, _, _)| field_value(key)).collect();
+    let insert = format!(
+        "INSERT INTO dbms_job.all_scheduled_jobs (job, priv_user, {}, next_date) \
+         SELECT f.job, f.owner, {}, coalesce(f.next_date, current_timestamp) FROM {} \
+         WHERE f.job = ANY($2)",
+        columns.join(", "),
+        values.join(", "),
+        file_recordset()
+    );
+    let assignments: Vec<String> = columns
+        .iter()
+        .zip(&values)
+        .map(|(column, value)| format!("{column} = {value}"))
+        .collect();
+    let update = format!(
+        "UPDATE dbms_job.all_scheduled_jobs j SET priv_user = f.owner, {} FROM {} \
+         WHERE j.job = f.job AND f.job = ANY($2)",
+        assignments.join(", "),
+        file_recordset()
+    );
+
+    let mut tx = client.transaction().map_err(|e| sql_error(&e))?;
+    if !added.is_empty() {
+        tx.execute(&*in_schema(schema, &insert), &[&json, &added])
+            .map_err(|e| format!("can not add jobs, {}", sql_error(&e)))?;
+        tx.execute(
+            &*in_schema(
+                schema,
+                "SELECT setval('dbms_job.jobseq', max(job)) FROM dbms_job.all_scheduled_jobs \
+                 HAVING max(job) > (SELECT last_value FROM dbms_job.jobseq)",
+            ),
+            &[],
+        )
+        .map_err(|e| format!("can not advance the job sequence, {}", sql_error(&e)))?;
+    }
+    if !changed.is_empty() {
+        tx.execute(&*in_schema(schema, &update), &[&json, &changed])
+            .map_err(|e| format!("can not change jobs, {}", sql_error(&e)))?;
+    }
+    if !removed.is_empty() {
+        tx.execute(
+            &*in_schema(
+                schema,
+                "DELETE FROM dbms_job.all_scheduled_jobs WHERE job = ANY($1)",
+            ),
+            &[&removed],
+        )
+        .map_err(|e| format!("can not remove jobs, {}", sql_error(&e)))?;
+    }
+    tx.commit().map_err(|e| sql_error(&e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        FIELDS, JobChange, JobDefinition, JobFileFormat, check_definitions, diff_jobs, dump_query,
+    };
+
+    fn definition(job: i64, what: &str) -> JobDefinition {
+        let mut fields = vec![None; FIELDS.len()];
+        fields[0] = Some("app".to_string());
+        fields[2] = Some(what.to_string());
+        JobDefinition { job, fields }
+    }
+
+    #[test]
+    fn diff_adds_changes_and_prunes_jobs() {
+        let mut changed = definition(2, "SELECT 2;");
+        changed.fields[3] = Some("sysdate + 1".to_string());
+        let wanted = vec![
+            definition(1, "SELECT 1;"),
+            changed,
+            definition(4, "SELECT 4;"),
+        ];
+        let current = vec![
+            definition(1, "SELECT 1;"),
+            definition(2, "SELECT 0;"),
+            definition(3, "SELECT 3;"),
+        ];
+        assert_eq!(
+            diff_jobs(&wanted, &current, false),
+            vec![
+                JobChange::Change(2, vec!["what", "interval"]),
+                JobChange::Add(4)
+            ]
+        );
+        assert_eq!(
+            diff_jobs(&wanted, &current, true),
+            vec![
+                JobChange::Change(2, vec!["what", "interval"]),
+                JobChange::Remove(3),
+                JobChange::Add(4)
+            ]
+        );
+        assert!(diff_jobs(&current, &current, true).is_empty());
+    }
+
+    #[test]
+    fn changes_describe_themselves() {
+        assert_eq!(JobChange::Add(4).describe(), "add job 4");
+        assert_eq!(
+            JobChange::Change(2, vec!["what", "broken"]).describe(),
+            "change job 2: what, broken"
+        );
+        assert_eq!(JobChange::Remove(3).describe(), "remove job 3");
+    }
+
+    #[test]
+    fn definitions_need_a_number_an_owner_and_a_body() {
+        assert!(check_definitions(&[definition(1, "SELECT 1;")]).is_ok());
+        let err = check_definitions(&[definition(0, "SELECT 1;")]).unwrap_err();
+        assert_eq!(err, "job definition 1 has no job number");
+        let err = check_definitions(&[definition(1, "x"), definition(1, "y")]).unwrap_err();
+        assert_eq!(err, "job 1 is defined more than once");
+        let mut ownerless = definition(5, "SELECT 1;");
+        ownerless.fields[0] = None;
+        assert_eq!(
+            check_definitions(&[ownerless]).unwrap_err(),
+            "job 5 has no owner"
+        );
+    }
+
+    #[test]
+    fn dump_lists_every_field() {
+        let query = dump_query();
+        for (key, column, _) in FIELDS {
+            assert!(query.contains(&format!("'{key}', j.{column}")));
+        }
+        assert!(query.contains("'next_date', j.next_date"));
+    }
+
+    #[test]
+    fn format_from_option_or_extension() {
+        let format = |name, path| JobFileFormat::new(name, path).unwrap();
+        assert_eq!(format(None, None), JobFileFormat::Json);
+        assert_eq!(format(None, Some("jobs.json")), JobFileFormat::Json);
+        assert_eq!(format(None, Some("deploy/jobs.YML")), JobFileFormat::Yaml);
+        assert_eq!(format(None, Some("jobs.yaml")), JobFileFormat::Yaml);
+        assert_eq!(format(Some("json"), Some("jobs.yaml")), JobFileFormat::Json);
+        assert_eq!(format(Some("YAML"), None), JobFileFormat::Yaml);
+        assert_eq!(
+            JobFileFormat::new(Some("toml"), None).unwrap_err(),
+            "invalid format \"toml\" given to --format, expected json or yaml"
+        );
+    }
+
+    #[test]
+    fn yaml_round_trips_through_json() {
+        let json = r#"[{"what": "DELETE FROM a;\nVACUUM a;", "job": 7, "owner": "app", "broken": false, "on_success": null, "next_date": "2026-10-16T10:00:00+00:00"}]"#;
+        let yaml = JobFileFormat::Yaml.render(json).unwrap();
+        assert!(yaml.starts_with("- job: 7\n  owner: app\n  what: |-\n"));
+        assert!(yaml.contains("\n  on_success: null\n"));
+        assert!(yaml.ends_with("next_date: 2026-10-16T10:00:00+00:00\n"));
+        let back: serde_json::Value =
+            serde_json::from_str(&JobFileFormat::Yaml.to_json(&yaml).unwrap()).unwrap();
+        assert_eq!(
+            back,
+            serde_json::from_str::<serde_json::Value>(json).unwrap()
+        );
+        assert!(JobFileFormat::Yaml.to_json("- job: [1").is_err());
+    }
+}
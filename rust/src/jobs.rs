@@ -1,41 +1,197 @@
 //! Job discovery and execution logic.
 
-use crate::constants::WORKER_STACK_SIZE;
-use crate::db::{JobPool, get_job_connection, reset_job_connection};
+use crate::constants::{
+    DEFAULT_SCHEMA, ERROR_SETTING_PREFIX, RESULT_SETTING, RUN_CANCEL_GRACE, RUN_ID_SETTING,
+    WORKER_STACK_SIZE,
+};
+use crate::db::{JobPool, get_job_connection, in_schema, take_job_notices};
 use crate::dlog;
-use crate::logging::dprint;
-use crate::model::{Config, Job, JobKind, JobRunDetails, JobStats, JobStatsGuard};
+use crate::dst::apply_dst_policy;
+use crate::executable::{CommandEnd, CommandRun, os_user_allowed, owner_allowed, run_command};
+use crate::logging::{dprint, job_log_context};
+use crate::model::{
+    Config, ExecutionMode, Job, JobFilter, JobKind, JobRunDetails, JobStats, JobStatsGuard,
+    RunStatus,
+};
+use crate::priority::apply_job_priority;
+use crate::run_details::{RunDetails, RunDetailsQueue, store_run_details};
+use crate::util::{host_name, new_run_id};
 use chrono::Local;
-use postgres::Client;
-use std::collections::HashMap;
-use std::process;
-use std::sync::Arc;
-use std::thread::JoinHandle;
-use std::time::Instant;
-
-/// Collect scheduled jobs that are ready to run.
+use postgres::types::ToSql;
+use postgres::{Client, Row};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Condition of the claim queries restricting them to a [`JobFilter`]:
+/// `$1` is the owner list and `$2` the job id list, empty for no restriction.
+const JOB_FILTER_CONDITION: &str = "(cardinality($1::text[]) = 0 OR log_user::text = ANY($1)) \
+     AND (cardinality($2::bigint[]) = 0 OR job = ANY($2))";
+
+/// Columns of a claimed job returned by the claim queries, read by
+/// [`job_from_row`].
+const CLAIM_COLUMNS: &str = "job, what, log_user, schema_user, on_success, on_failure, \
+     continue_on_error, resource_key, \
+     extract(epoch FROM max_run_duration)::float8 AS max_run_duration, priority, \
+     job_class, job_type, os_user";
+
+/// Columns of a job copied from `all_scheduled_jobs` into `all_async_jobs`
+/// by a chain or a dependency.
+const ASYNC_COPY_COLUMNS: &str = "log_user, schema_user, what, on_success, on_failure, \
+     continue_on_error, resource_key, max_run_duration, priority, job_class, job_type, os_user";
+
+/// Query claiming the rows of `table` matching `due`, by `order`, up to the
+/// `LIMIT` `$3`: `set` marks them running. Rows locked by another scheduler
+/// are skipped, and so are the bodies over `max_job_size` when it is not 0,
+/// left to [`refuse_oversized_jobs`].
+///
+/// With `limit_params`, the number of the first of the four parameters of
+/// [`ClaimLimits::params`], a job class takes no more rows than its limit
+/// minus its jobs already running, in both queues, and so does the
+/// `resource_key` of a semaphore with its slots.
+fn claim_query(
+    table: &str,
+    set: &str,
+    due: &str,
+    order: &str,
+    max_job_size: u64,
+    limit_params: Option<usize>,
+) -> String {
+    let mut due = format!("this_date IS NULL AND {due} AND {JOB_FILTER_CONDITION}");
+    if max_job_size > 0 {
+        due.push_str(&format!(" AND octet_length(what) <= {max_job_size}"));
+    }
+    // An asynchronous copy of a scheduled job completes the dependencies on
+    // the job copied.
+    let source_job = if table == "all_async_jobs" {
+        "coalesce(source_job, job)"
+    } else {
+        "job"
+    };
+    let returning = format!("{CLAIM_COLUMNS}, {source_job} AS source_job");
+    let Some(names) = limit_params else {
+        return format!(
+            "UPDATE dbms_job.{table} SET {set} WHERE this_date IS NULL AND job IN \
+             (SELECT job FROM dbms_job.{table} WHERE {due} ORDER BY {order} LIMIT $3 \
+             FOR UPDATE SKIP LOCKED) RETURNING {returning}"
+        );
+    };
+    let (slots, keys, key_slots) = (names + 1, names + 2, names + 3);
+    format!(
+        "WITH classes AS (SELECT c.name, c.slots \
+             - (SELECT count(*) FROM dbms_job.all_scheduled_jobs r WHERE r.this_date IS NOT NULL AND r.job_class = c.name) \
+             - (SELECT count(*) FROM dbms_job.all_async_jobs r WHERE r.this_date IS NOT NULL AND r.job_class = c.name) AS free \
+             FROM unnest(${names}::text[], ${slots}::bigint[]) AS c(name, slots)), \
+         semaphores AS (SELECT s.name, s.slots \
+             - (SELECT count(*) FROM dbms_job.all_scheduled_jobs r WHERE r.this_date IS NOT NULL AND r.resource_key = s.name) \
+             - (SELECT count(*) FROM dbms_job.all_async_jobs r WHERE r.this_date IS NOT NULL AND r.resource_key = s.name) AS free \
+             FROM unnest(${keys}::text[], ${key_slots}::bigint[]) AS s(name, slots)), \
+         due AS (SELECT job, job_class, resource_key, \
+             row_number() OVER (PARTITION BY job_class ORDER BY {order}) AS class_rank, \
+             row_number() OVER (PARTITION BY resource_key ORDER BY {order}) AS key_rank \
+             FROM dbms_job.{table} WHERE {due}) \
+         UPDATE dbms_job.{table} SET {set} WHERE this_date IS NULL AND job IN \
+         (SELECT j.job FROM dbms_job.{table} j JOIN due USING (job) \
+         LEFT JOIN classes c ON c.name = due.job_class \
+         LEFT JOIN semaphores s ON s.name = due.resource_key \
+         WHERE (c.name IS NULL OR due.class_rank <= c.free) \
+         AND (s.name IS NULL OR due.key_rank <= s.free) ORDER BY {order} LIMIT $3 \
+         FOR UPDATE OF j SKIP LOCKED) RETURNING {returning}"
+    )
+}
+
+/// Names and limits of the job classes and of the semaphores, the limit
+/// parameters of a [`claim_query`].
+struct ClaimLimits {
+    class_names: Vec<String>,
+    class_slots: Vec<i64>,
+    semaphore_names: Vec<String>,
+    semaphore_slots: Vec<i64>,
+}
+
+impl ClaimLimits {
+    fn new(config: &Config) -> Self {
+        let (class_names, class_slots) = named_limits(&config.job_classes);
+        let (semaphore_names, semaphore_slots) = named_limits(&config.semaphores);
+        Self {
+            class_names,
+            class_slots,
+            semaphore_names,
+            semaphore_slots,
+        }
+    }
+
+    /// Whether there is neither class nor semaphore to enforce.
+    fn is_empty(&self) -> bool {
+        self.class_names.is_empty() && self.semaphore_names.is_empty()
+    }
+
+    /// The class names and limits, then the semaphore names and slots.
+    fn params(&self) -> [&(dyn ToSql + Sync); 4] {
+        [
+            &self.class_names,
+            &self.class_slots,
+            &self.semaphore_names,
+            &self.semaphore_slots,
+        ]
+    }
+}
+
+/// The names and limits of `job_class.<name>` or `semaphore.<name>`
+/// settings, as two arrays.
+fn named_limits(settings: &BTreeMap<String, u32>) -> (Vec<String>, Vec<i64>) {
+    settings
+        .iter()
+        .map(|(name, slots)| (name.clone(), i64::from(*slots)))
+        .unzip()
+}
+
+/// Convert a number of free worker slots into the `LIMIT` of a claim query.
+fn claim_limit(slots: usize) -> i64 {
+    i64::try_from(slots).unwrap_or(i64::MAX)
+}
+
+/// Collect scheduled jobs that are ready to run, at most `limit` of them,
+/// by priority then longest overdue first. Rows locked by another scheduler claiming them at
+/// the same time are skipped, so that each job goes to a single one.
 ///
 /// Clears and refills `jobs` in place to reuse the existing allocation.
+/// Returns true when the limit was reached: more jobs may be due and are
+/// left in the queue, with their `next_date` untouched, for a later cycle.
 pub fn get_scheduled_jobs(
     client: &mut Client,
     config: &Config,
+    filter: &JobFilter,
+    limit: usize,
     config_invalidated: &mut bool,
     jobs: &mut HashMap<i64, Job>,
-) {
+) -> bool {
     dprint(config, "DEBUG", "Get scheduled jobs to run");
     jobs.clear();
-    let query = "UPDATE dbms_job.all_scheduled_jobs SET this_date = current_timestamp, next_date = dbms_job.get_next_date(interval), instance = instance+1 WHERE interval IS NOT NULL AND NOT broken AND this_date IS NULL AND next_date <= current_timestamp RETURNING job, what, log_user, schema_user";
-    match client.query(query, &[]) {
+    let limits = ClaimLimits::new(config);
+    let query = claim_query(
+        "all_scheduled_jobs",
+        "this_date = current_timestamp, next_date = dbms_job.get_next_date(interval), instance = instance+1",
+        "interval IS NOT NULL AND NOT broken AND next_date <= current_timestamp",
+        "priority, next_date, job",
+        config.max_job_size,
+        (!limits.is_empty()).then_some(4),
+    );
+    let remaining = claim_limit(limit);
+    let mut params: Vec<&(dyn ToSql + Sync)> = vec![&filter.owners, &filter.jobs, &remaining];
+    if !limits.is_empty() {
+        params.extend(limits.params());
+    }
+    match client.query(&*in_schema(&config.schema, &query), &params) {
         Ok(rows) => {
             for row in rows {
-                let job = Job {
-                    job: row.get::<_, i64>("job"),
-                    what: row.get::<_, String>("what"),
-                    log_user: row.get::<_, Option<String>>("log_user"),
-                    schema_user: row.get::<_, Option<String>>("schema_user"),
-                };
+                let job = job_from_row(&row);
                 jobs.insert(job.job, job);
             }
+            let jobids: Vec<i64> = jobs.keys().copied().collect();
+            apply_dst_policy(client, config, &jobids);
         }
         Err(err) => {
             dlog!(config, "ERROR", "can't execute statement, {err}");
@@ -48,49 +204,505 @@ pub fn get_scheduled_jobs(
         "Found {} scheduled jobs to run",
         jobs.len()
     );
+    jobs.len() >= limit
 }
 
-/// Collect asynchronous jobs queued for execution.
+/// Refuse queued jobs whose body exceeds `max_job_size` bytes. The claim
+/// queries skip them until then, so that an oversized body is never
+/// fetched, logged or run.
 ///
-/// Clears and refills `jobs` in place to reuse the existing allocation.
-pub fn get_async_jobs(client: &mut Client, config: &Config, jobs: &mut HashMap<i64, Job>) {
-    jobs.clear();
-    let query = "UPDATE dbms_job.all_async_jobs SET this_date = current_timestamp WHERE this_date IS NULL RETURNING job, what, log_user, schema_user";
-    if let Ok(rows) = client.query(query, &[]) {
+/// Scheduled jobs are marked broken with the reason in `last_error`; they run
+/// again once their body is fixed and `dbms_job.broken()` clears the flag.
+/// Asynchronous jobs are removed from the queue. Each refusal is logged and
+/// recorded as a failed run in the run details.
+pub fn refuse_oversized_jobs(client: &mut Client, config: &Config) {
+    if config.max_job_size == 0 {
+        return;
+    }
+    let limit = i64::try_from(config.max_job_size).unwrap_or(i64::MAX);
+    let queries = [
+        &*in_schema(
+            &config.schema,
+            "UPDATE dbms_job.all_scheduled_jobs SET broken = true, \
+         last_error = format('job body of %s bytes exceeds max_job_size (%s bytes)', octet_length(what), $1::bigint), \
+         last_sqlstate = NULL, last_failure_date = current_timestamp, last_status = 'BROKEN' \
+         WHERE NOT broken AND this_date IS NULL AND octet_length(what) > $1::bigint \
+         RETURNING job, log_user::text, last_error",
+        ),
+        &*in_schema(
+            &config.schema,
+            "DELETE FROM dbms_job.all_async_jobs \
+         WHERE this_date IS NULL AND octet_length(what) > $1::bigint \
+         RETURNING job, log_user::text, \
+         format('job body of %s bytes exceeds max_job_size (%s bytes)', octet_length(what), $1::bigint)",
+        ),
+    ];
+    for (index, query) in queries.into_iter().enumerate() {
+        let rows = match client.query(query, &[&limit]) {
+            Ok(rows) => rows,
+            Err(err) => {
+                dlog!(config, "ERROR", "can not refuse oversized jobs, {err}");
+                continue;
+            }
+        };
         for row in rows {
-            let job = Job {
-                job: row.get::<_, i64>("job"),
-                what: row.get::<_, String>("what"),
-                log_user: row.get::<_, Option<String>>("log_user"),
-                schema_user: row.get::<_, Option<String>>("schema_user"),
+            let jobid: i64 = row.get(0);
+            let owner: Option<String> = row.get(1);
+            let reason: String = row.get(2);
+            dlog!(config, "ERROR", "job {} refused: {}", jobid, reason);
+            // The first query breaks scheduled jobs, the second drops
+            // asynchronous ones.
+            let status = if index == 0 {
+                notify_job_broken(client, config, jobid, owner.as_deref(), &reason);
+                RunStatus::Broken
+            } else {
+                RunStatus::Skipped
             };
-            jobs.insert(job.job, job);
+            if config.job_run_details != JobRunDetails::None {
+                let start_t = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+                store_run_details(
+                    client,
+                    config,
+                    &JobExecutionDetails {
+                        owner: owner.as_deref().unwrap_or(""),
+                        jobid,
+                        start_date: &start_t,
+                        duration_secs: 0,
+                        status,
+                        err_text: &reason,
+                        sqlstate: "",
+                        diagnostics: None,
+                        result: None,
+                        notices: &[],
+                        exit_code: None,
+                        run_id: None,
+                        backend_pid: None,
+                    }
+                    .to_run_details(&config.schema),
+                );
+            }
         }
-    } else {
-        dprint(config, "ERROR", "can't execute statement");
     }
+}
+
+/// Suffix of the channel notified of every job of a job queue schema the
+/// scheduler marks broken, `dbms_job_broken` for the default one; each
+/// owner also gets its own channel, this one followed by the role name.
+pub const BROKEN_CHANNEL_SUFFIX: &str = "_broken";
+
+/// Tell listeners that the scheduler has marked `jobid` broken.
+///
+/// The JSON payload (`job`, `owner`, `reason`) is sent on the
+/// `<schema>_broken` channel of the job queue schema, see
+/// [`BROKEN_CHANNEL_SUFFIX`], and on the owner's `<schema>_broken_<owner>`
+/// channel, so that an application can `LISTEN` for the breakage of its own
+/// jobs.
+fn notify_job_broken(
+    client: &mut Client,
+    config: &Config,
+    jobid: i64,
+    owner: Option<&str>,
+    reason: &str,
+) {
+    let channel = format!("{}{BROKEN_CHANNEL_SUFFIX}", config.schema);
+    let mut channels = vec![channel.clone()];
+    if let Some(owner) = owner.filter(|o| !o.is_empty()) {
+        channels.push(owner_channel(&channel, owner));
+    }
+    // NOTIFY payloads are limited to 8000 bytes.
+    if let Err(err) = client.execute(
+        "SELECT pg_notify(channel, json_build_object('job', $2::bigint, 'owner', $3::text, \
+         'reason', left($4, 1000))::text) FROM unnest($1::text[]) AS channel",
+        &[&channels, &jobid, &owner, &reason],
+    ) {
+        dlog!(
+            config,
+            "ERROR",
+            "can not notify that job {} is broken, {}",
+            jobid,
+            err
+        );
+    }
+}
+
+/// Per-owner broken-job channel, cut to the 63 bytes of a PostgreSQL
+/// identifier on a character boundary.
+fn owner_channel(broken_channel: &str, owner: &str) -> String {
+    let mut channel = format!("{broken_channel}_{owner}");
+    let mut end = channel.len().min(63);
+    while !channel.is_char_boundary(end) {
+        end -= 1;
+    }
+    channel.truncate(end);
+    channel
+}
+
+/// Most skipped jobs listed by [`skipped_broken_message`].
+const MAX_LISTED_BROKEN_JOBS: usize = 20;
 
-    let query = "UPDATE dbms_job.all_scheduled_jobs SET this_date = current_timestamp WHERE this_date IS NULL AND interval IS NULL AND next_date <= current_timestamp RETURNING job, what, log_user, schema_user";
-    if let Ok(rows) = client.query(query, &[]) {
+/// Due scheduled jobs that the claim queries leave alone because they are
+/// marked broken, as `(job, failures)` ordered by job id.
+pub fn broken_due_jobs(
+    client: &mut Client,
+    schema: &str,
+) -> Result<Vec<(i64, i64)>, postgres::Error> {
+    let rows = client.query(
+        &*in_schema(
+            schema,
+            "SELECT job, coalesce(failures, 0) FROM dbms_job.all_scheduled_jobs \
+         WHERE broken AND this_date IS NULL AND next_date <= current_timestamp ORDER BY job",
+        ),
+        &[],
+    )?;
+    Ok(rows.iter().map(|row| (row.get(0), row.get(1))).collect())
+}
+
+/// Log the due jobs skipped because they are broken at `level` and return how
+/// many there are, or `None` when the lookup failed.
+pub fn log_broken_jobs(client: &mut Client, config: &Config, level: &str) -> Option<usize> {
+    match broken_due_jobs(client, &config.schema) {
+        Ok(jobs) => {
+            if !jobs.is_empty() {
+                dprint(config, level, &skipped_broken_message(&jobs));
+            }
+            Some(jobs.len())
+        }
+        Err(err) => {
+            dlog!(config, "ERROR", "can not look up broken jobs, {err}");
+            None
+        }
+    }
+}
+
+/// Describe due jobs skipped because they are broken, e.g.
+/// `skipping 2 due jobs marked broken: job 3 (16 failures), job 7 (0 failures)`.
+fn skipped_broken_message(jobs: &[(i64, i64)]) -> String {
+    let mut listed = jobs
+        .iter()
+        .take(MAX_LISTED_BROKEN_JOBS)
+        .map(|(job, failures)| format!("job {job} ({failures} failures)"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    if jobs.len() > MAX_LISTED_BROKEN_JOBS {
+        listed.push_str(&format!(
+            " and {} more",
+            jobs.len() - MAX_LISTED_BROKEN_JOBS
+        ));
+    }
+    format!(
+        "skipping {} due job{} marked broken: {}",
+        jobs.len(),
+        if jobs.len() == 1 { "" } else { "s" },
+        listed
+    )
+}
+
+/// Read the `dbms_job.maintenance_mode` switch and log when it flips.
+///
+/// `paused` holds the state seen by the previous call and is updated in
+/// place; the new state is returned. When the switch can not be read the
+/// previous state is kept.
+pub fn check_maintenance_mode(client: &mut Client, config: &Config, paused: &mut bool) -> bool {
+    let row = match client.query_opt(
+        &*in_schema(
+            &config.schema,
+            "SELECT enabled, reason FROM dbms_job.maintenance_mode",
+        ),
+        &[],
+    ) {
+        Ok(row) => row,
+        Err(err) => {
+            dlog!(
+                config,
+                "ERROR",
+                "can not read dbms_job.maintenance_mode, {err}"
+            );
+            return *paused;
+        }
+    };
+    let (enabled, reason) = row
+        .map(|r| (r.get::<_, bool>(0), r.get::<_, Option<String>>(1)))
+        .unwrap_or((false, None));
+    if enabled != *paused {
+        dprint(
+            config,
+            "LOG",
+            &maintenance_message(enabled, reason.as_deref()),
+        );
+        *paused = enabled;
+    }
+    enabled
+}
+
+/// Log line for a change of the maintenance switch.
+fn maintenance_message(enabled: bool, reason: Option<&str>) -> String {
+    match (enabled, reason.map(str::trim).filter(|r| !r.is_empty())) {
+        (true, Some(reason)) => {
+            format!("maintenance mode enabled, job dispatch paused: {reason}")
+        }
+        (true, None) => "maintenance mode enabled, job dispatch paused".to_string(),
+        (false, _) => "maintenance mode disabled, resuming job dispatch".to_string(),
+    }
+}
+
+/// Collect asynchronous jobs queued for execution, at most `limit` of them
+/// by priority then in queue order. With `notified`, the ids of the
+/// notification payloads, only those jobs are claimed and the queue is not
+/// scanned. As for scheduled
+/// jobs, rows locked by another scheduler are skipped.
+///
+/// Clears and refills `jobs` in place to reuse the existing allocation.
+/// Returns true when the limit was reached and more jobs may be waiting.
+pub fn get_async_jobs(
+    client: &mut Client,
+    config: &Config,
+    filter: &JobFilter,
+    notified: Option<&[i64]>,
+    limit: usize,
+    jobs: &mut HashMap<i64, Job>,
+) -> bool {
+    jobs.clear();
+    let limits = ClaimLimits::new(config);
+    let limited = !limits.is_empty();
+    let query = claim_query(
+        "all_async_jobs",
+        "this_date = current_timestamp",
+        "($4::bigint[] IS NULL OR job = ANY($4))",
+        "priority, job",
+        config.max_job_size,
+        limited.then_some(5),
+    );
+    let remaining = claim_limit(limit);
+    let mut params: Vec<&(dyn ToSql + Sync)> =
+        vec![&filter.owners, &filter.jobs, &remaining, &notified];
+    if limited {
+        params.extend(limits.params());
+    }
+    if let Ok(rows) = client.query(&*in_schema(&config.schema, &query), &params) {
         for row in rows {
-            let job = Job {
-                job: row.get::<_, i64>("job"),
-                what: row.get::<_, String>("what"),
-                log_user: row.get::<_, Option<String>>("log_user"),
-                schema_user: row.get::<_, Option<String>>("schema_user"),
-            };
+            let job = job_from_row(&row);
             jobs.insert(job.job, job);
         }
     } else {
         dprint(config, "ERROR", "can't execute statement");
     }
 
+    if jobs.len() >= limit {
+        return true;
+    }
+
+    // One-shot scheduled jobs are not notified on this channel, they wait
+    // for the next scan of the queue.
+    if notified.is_none() {
+        let query = claim_query(
+            "all_scheduled_jobs",
+            "this_date = current_timestamp",
+            "interval IS NULL AND NOT broken AND next_date <= current_timestamp",
+            "priority, next_date, job",
+            config.max_job_size,
+            limited.then_some(4),
+        );
+        let remaining = claim_limit(limit - jobs.len());
+        let mut params: Vec<&(dyn ToSql + Sync)> = vec![&filter.owners, &filter.jobs, &remaining];
+        if limited {
+            params.extend(limits.params());
+        }
+        if let Ok(rows) = client.query(&*in_schema(&config.schema, &query), &params) {
+            for row in rows {
+                let job = job_from_row(&row);
+                jobs.insert(job.job, job);
+            }
+        } else {
+            dprint(config, "ERROR", "can't execute statement");
+        }
+    }
+
     dlog!(
         config,
         "DEBUG",
         "Found {} asynchronous jobs to run",
         jobs.len()
     );
+    jobs.len() >= limit
+}
+
+/// Build a [`Job`] from a row returned by one of the claiming queries.
+fn job_from_row(row: &Row) -> Job {
+    Job {
+        job: row.get::<_, i64>("job"),
+        what: row.get::<_, String>("what"),
+        log_user: row.get::<_, Option<String>>("log_user"),
+        schema_user: row.get::<_, Option<String>>("schema_user"),
+        on_success: row.get::<_, Option<i64>>("on_success"),
+        on_failure: row.get::<_, Option<i64>>("on_failure"),
+        continue_on_error: row.get::<_, bool>("continue_on_error"),
+        resource_key: row.get::<_, Option<String>>("resource_key"),
+        // A limit of zero or less is no limit.
+        max_run_duration: row
+            .get::<_, Option<f64>>("max_run_duration")
+            .filter(|secs| *secs > 0.0)
+            .map(Duration::from_secs_f64),
+        priority: row.get::<_, i32>("priority"),
+        source_job: row.get::<_, i64>("source_job"),
+        executable: row.get::<_, String>("job_type") == "EXECUTABLE",
+        os_user: row.get::<_, Option<String>>("os_user"),
+        job_class: row.get::<_, Option<String>>("job_class"),
+    }
+}
+
+/// Enqueue the job chained to a completed run as an asynchronous job.
+///
+/// The definition (body, owner, search_path and its own chain targets) is
+/// copied from `all_scheduled_jobs`, so a chain can continue past the first
+/// step. This runs under the finished job's role, which means Row Level
+/// Security limits chaining to jobs the same owner can see.
+fn enqueue_chained_job(client: &mut Client, config: &Config, jobid: i64, target: i64) {
+    match client.execute(
+        &*in_schema(
+            &config.schema,
+            &format!(
+                "INSERT INTO dbms_job.all_async_jobs ({ASYNC_COPY_COLUMNS}, source_job) \
+             SELECT {ASYNC_COPY_COLUMNS}, job FROM dbms_job.all_scheduled_jobs WHERE job = $1"
+            ),
+        ),
+        &[&target],
+    ) {
+        Ok(0) => dlog!(
+            config,
+            "WARNING",
+            "job {} chains to job {} which is not a job in the job queue, nothing enqueued",
+            jobid,
+            target
+        ),
+        Ok(_) => dlog!(
+            config,
+            "LOG",
+            "job {} completed, enqueued chained job {}",
+            jobid,
+            target
+        ),
+        Err(err) => dlog!(
+            config,
+            "ERROR",
+            "failed to enqueue job {} chained to job {}: {}",
+            target,
+            jobid,
+            err
+        ),
+    }
+}
+
+/// Record a completed run of `prerequisite` in the dependencies waiting for
+/// it, and enqueue as asynchronous jobs the dependents now ready: all their
+/// `SUCCEEDED` prerequisites have succeeded, or one of their `FAILED` ones
+/// has failed. Like chaining, this runs under the finished job's role.
+fn enqueue_dependents(client: &mut Client, config: &Config, prerequisite: i64, failed: bool) {
+    let condition = if failed { "FAILED" } else { "SUCCEEDED" };
+    // Most jobs have no dependent: skip the transaction.
+    match client.query_one(
+        &*in_schema(
+            &config.schema,
+            "SELECT EXISTS (SELECT FROM dbms_job.all_job_dependencies \
+         WHERE prerequisite = $1 AND condition = $2)",
+        ),
+        &[&prerequisite, &condition],
+    ) {
+        Ok(row) if row.get::<_, bool>(0) => {}
+        Ok(_) => return,
+        Err(err) => {
+            dlog!(
+                config,
+                "ERROR",
+                "failed to look up the jobs depending on job {}: {}",
+                prerequisite,
+                err
+            );
+            return;
+        }
+    }
+    match satisfy_dependencies(client, &config.schema, prerequisite, condition) {
+        Ok(dependents) => {
+            for (dependent, enqueued) in dependents {
+                if enqueued {
+                    dlog!(
+                        config,
+                        "LOG",
+                        "job {} completed, enqueued dependent job {}",
+                        prerequisite,
+                        dependent
+                    );
+                } else {
+                    dlog!(
+                        config,
+                        "WARNING",
+                        "job {} depends on job {} but is not a job in the job queue, nothing enqueued",
+                        dependent,
+                        prerequisite
+                    );
+                }
+            }
+        }
+        Err(err) => dlog!(
+            config,
+            "ERROR",
+            "failed to enqueue the jobs depending on job {}: {}",
+            prerequisite,
+            err
+        ),
+    }
+}
+
+/// Mark the dependencies on `prerequisite` with `condition` satisfied, then
+/// reset and copy into `all_async_jobs` the dependents they make ready.
+/// Returns each ready dependent, with whether it was found to be enqueued.
+///
+/// The dependency rows of the dependents are locked first: prerequisites of
+/// a job completing at the same time then see each other's runs, and the
+/// job is enqueued once.
+fn satisfy_dependencies(
+    client: &mut Client,
+    schema: &str,
+    prerequisite: i64,
+    condition: &str,
+) -> Result<Vec<(i64, bool)>, postgres::Error> {
+    let mut tx = client.transaction()?;
+    tx.execute(
+        &*in_schema(schema, "SELECT FROM dbms_job.all_job_dependencies WHERE job IN \
+         (SELECT job FROM dbms_job.all_job_dependencies WHERE prerequisite = $1 AND condition = $2) \
+         ORDER BY job, prerequisite, condition FOR UPDATE"),
+        &[&prerequisite, &condition],
+    )?;
+    tx.execute(
+        &*in_schema(
+            schema,
+            "UPDATE dbms_job.all_job_dependencies SET satisfied_date = current_timestamp \
+         WHERE prerequisite = $1 AND condition = $2",
+        ),
+        &[&prerequisite, &condition],
+    )?;
+    let rows = tx.query(
+        &*in_schema(
+            schema,
+            &format!(
+                "WITH ready AS (UPDATE dbms_job.all_job_dependencies SET satisfied_date = NULL \
+             WHERE job IN (SELECT d.job FROM dbms_job.all_job_dependencies d \
+             WHERE d.prerequisite = $1 AND d.condition = $2 AND (d.condition = 'FAILED' \
+             OR NOT EXISTS (SELECT FROM dbms_job.all_job_dependencies p WHERE p.job = d.job \
+             AND p.condition = 'SUCCEEDED' AND p.satisfied_date IS NULL))) RETURNING job), \
+             enqueued AS (INSERT INTO dbms_job.all_async_jobs ({ASYNC_COPY_COLUMNS}, source_job) \
+             SELECT {ASYNC_COPY_COLUMNS}, job FROM dbms_job.all_scheduled_jobs \
+             WHERE job IN (SELECT job FROM ready) RETURNING source_job) \
+             SELECT DISTINCT r.job, e.source_job IS NOT NULL AS enqueued \
+             FROM ready r LEFT JOIN enqueued e ON e.source_job = r.job ORDER BY r.job"
+            ),
+        ),
+        &[&prerequisite, &condition],
+    )?;
+    tx.commit()?;
+    Ok(rows
+        .iter()
+        .map(|row| (row.get::<_, i64>(0), row.get::<_, bool>(1)))
+        .collect())
 }
 
 /// Remove a job from the async queue (or fallback to scheduled).
@@ -102,19 +714,43 @@ pub fn delete_job(client: &mut Client, config: &Config, jobid: i64) {
     );
     let row = client
         .query_opt(
-            "DELETE FROM dbms_job.all_async_jobs WHERE job = $1 RETURNING job",
+            &*in_schema(
+                &config.schema,
+                "DELETE FROM dbms_job.all_async_jobs WHERE job = $1 RETURNING job",
+            ),
             &[&jobid],
         )
         .ok()
         .flatten();
     if row.is_none() {
         let _ = client.execute(
-            "DELETE FROM dbms_job.all_scheduled_jobs WHERE job = $1",
+            &*in_schema(
+                &config.schema,
+                "DELETE FROM dbms_job.all_scheduled_jobs WHERE job = $1",
+            ),
             &[&jobid],
         );
     }
 }
 
+/// Start of the `application_name` of the job connections running the jobs
+/// of `kind` of the job queue schema `schema`, followed by the job id. The
+/// ids of the jobs of two schemas overlap: the name of a schema other than
+/// `dbms_job` is part of it.
+fn job_application_prefix(schema: &str, kind: &str) -> String {
+    if schema == DEFAULT_SCHEMA {
+        format!("pg_dbms_job:{kind}:")
+    } else {
+        format!("pg_dbms_job:{schema}:{kind}:")
+    }
+}
+
+/// `application_name` of the job connection running `job`, see
+/// [`job_application_prefix`].
+fn job_application_name(schema: &str, kind: &str, job: i64) -> String {
+    format!("{}{job}", job_application_prefix(schema, kind))
+}
+
 /// Re-queue jobs left flagged running by workers that never finished.
 ///
 /// A worker that returns before clearing its row — most commonly because it
@@ -133,8 +769,8 @@ pub fn delete_job(client: &mut Client, config: &Config, jobid: i64) {
 ///
 /// The leaked rows have not executed their body (they fail during setup, before
 /// the DO block), so clearing the marker re-queues them for another attempt.
-/// Scheduled rows additionally bump `failures`, mirroring the normal
-/// failure-path bookkeeping.
+/// Scheduled rows additionally bump `failures` and set `last_error`,
+/// mirroring the normal failure-path bookkeeping.
 pub fn reap_stale_jobs(client: &mut Client, config: &Config) {
     let timeout = config.stale_job_timeout;
     if timeout <= 0.0 {
@@ -142,12 +778,18 @@ pub fn reap_stale_jobs(client: &mut Client, config: &Config) {
     }
 
     match client.execute(
-        "UPDATE dbms_job.all_async_jobs AS j SET this_date = NULL \
+        &*in_schema(
+            &config.schema,
+            "UPDATE dbms_job.all_async_jobs AS j SET this_date = NULL \
          WHERE j.this_date IS NOT NULL \
            AND j.this_date < current_timestamp - make_interval(secs => $1) \
            AND NOT EXISTS (SELECT 1 FROM pg_catalog.pg_stat_activity a \
-                           WHERE a.application_name = 'pg_dbms_job:async:' || j.job)",
-        &[&timeout],
+                           WHERE a.application_name = $2 || j.job)",
+        ),
+        &[
+            &timeout,
+            &job_application_prefix(&config.schema, JobKind::Async.label()),
+        ],
     ) {
         Ok(n) if n > 0 => dlog!(config, "LOG", "reaped {} stale asynchronous job(s)", n),
         Ok(_) => {}
@@ -159,51 +801,482 @@ pub fn reap_stale_jobs(client: &mut Client, config: &Config) {
         ),
     }
 
-    match client.execute(
-        "UPDATE dbms_job.all_scheduled_jobs AS j SET this_date = NULL, failures = failures + 1 \
-         WHERE j.this_date IS NOT NULL \
-           AND j.this_date < current_timestamp - make_interval(secs => $1) \
-           AND NOT EXISTS (SELECT 1 FROM pg_catalog.pg_stat_activity a \
-                           WHERE a.application_name = 'pg_dbms_job:scheduled:' || j.job)",
-        &[&timeout],
-    ) {
-        Ok(n) if n > 0 => dlog!(config, "LOG", "reaped {} stale scheduled job(s)", n),
-        Ok(_) => {}
-        Err(err) => dlog!(
-            config,
-            "ERROR",
-            "failed to reap stale scheduled jobs: {}",
-            err
-        ),
-    }
+    let max_failures = i32::try_from(config.max_failures).unwrap_or(i32::MAX);
+    match client.query(
+        &*in_schema(&config.schema, &format!(
+            "UPDATE dbms_job.all_scheduled_jobs AS j SET this_date = NULL, failures = coalesce(failures, 0) + 1, \
+             run_count = run_count + 1, failure_count = failure_count + 1, \
+             last_error = 'run abandoned by its worker, re-queued after stale_job_timeout', \
+             last_sqlstate = NULL, last_failure_date = current_timestamp, last_status = 'FAILED', \
+             broken = broken OR {} \
+             WHERE j.this_date IS NOT NULL \
+               AND j.this_date < current_timestamp - make_interval(secs => $1) \
+               AND NOT EXISTS (SELECT 1 FROM pg_catalog.pg_stat_activity a \
+                               WHERE a.application_name = $3 || j.job) \
+             RETURNING job, log_user::text, failures, \
+                 broken AND failures >= coalesce(max_failures, $2) AND coalesce(max_failures, $2) > 0",
+            max_failures_reached("$2")
+        )),
+        &[
+            &timeout,
+            &max_failures,
+            &job_application_prefix(&config.schema, JobKind::Scheduled.label()),
+        ],
+    ) {
+        Ok(rows) => {
+            if !rows.is_empty() {
+                dlog!(config, "LOG", "reaped {} stale scheduled job(s)", rows.len());
+            }
+            for row in rows.iter().filter(|row| row.get::<_, bool>(3)) {
+                let jobid: i64 = row.get(0);
+                let reason = max_failures_reason(row.get(2));
+                dlog!(config, "ERROR", "job {} {}", jobid, reason);
+                notify_job_broken(client, config, jobid, row.get(1), &reason);
+            }
+        }
+        Err(err) => dlog!(
+            config,
+            "ERROR",
+            "failed to reap stale scheduled jobs: {}",
+            err
+        ),
+    }
+}
+
+/// Jobs claimed by the main loop that a worker could not start, for lack of
+/// a database connection, handed back to be re-queued.
+///
+/// The worker has no connection to clear the row's `this_date` itself, so
+/// the main loop does it with [`requeue_jobs`] once `error_delay` has passed,
+/// instead of leaving the row to the stale-job reaper.
+#[derive(Default)]
+pub struct RequeueQueue {
+    pending: Mutex<Vec<(JobKind, i64, Instant)>>,
+}
+
+impl RequeueQueue {
+    /// Hand a claimed job back.
+    pub fn push(&self, kind: JobKind, job: i64) {
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.push((kind, job, Instant::now()));
+        }
+    }
+
+    /// Remove and return the jobs handed back at least `delay` before `now`.
+    fn take_due(&self, now: Instant, delay: Duration) -> Vec<(JobKind, i64)> {
+        let Ok(mut pending) = self.pending.lock() else {
+            return Vec::new();
+        };
+        let (due, waiting): (Vec<_>, Vec<_>) = pending
+            .drain(..)
+            .partition(|(_, _, at)| now.saturating_duration_since(*at) >= delay);
+        *pending = waiting;
+        due.into_iter().map(|(kind, job, _)| (kind, job)).collect()
+    }
+}
+
+/// Heartbeats of the running workers, keyed by worker id.
+///
+/// A worker beats before each step of its run. While it waits on the
+/// database its beat stalls, which is normal as long as its backend is
+/// executing; a stalled beat while the backend is idle, or gone, means the
+/// worker is hung on the client side. See [`check_hung_workers`].
+#[derive(Default)]
+pub struct WorkerBeats {
+    beats: Mutex<HashMap<u64, WorkerBeat>>,
+}
+
+/// Last beat of a worker.
+#[derive(Clone, Copy)]
+struct WorkerBeat {
+    kind: JobKind,
+    job: i64,
+    step: &'static str,
+    at: Instant,
+}
+
+impl WorkerBeats {
+    fn beat(&self, worker: u64, beat: WorkerBeat) {
+        if let Ok(mut beats) = self.beats.lock() {
+            beats.insert(worker, beat);
+        }
+    }
+
+    fn forget(&self, worker: u64) {
+        if let Ok(mut beats) = self.beats.lock() {
+            beats.remove(&worker);
+        }
+    }
+
+    /// Workers whose last beat is at least `age` old at `now`.
+    fn stalled(&self, now: Instant, age: Duration) -> Vec<(u64, WorkerBeat)> {
+        let Ok(beats) = self.beats.lock() else {
+            return Vec::new();
+        };
+        let mut stalled: Vec<_> = beats
+            .iter()
+            .filter(|(_, beat)| now.saturating_duration_since(beat.at) >= age)
+            .map(|(worker, beat)| (*worker, *beat))
+            .collect();
+        stalled.sort_by_key(|(worker, _)| *worker);
+        stalled
+    }
+}
+
+/// Heartbeat of one worker, forgotten when the worker ends.
+struct Heartbeat<'a> {
+    beats: &'a WorkerBeats,
+    worker: u64,
+    kind: JobKind,
+    job: i64,
+}
+
+impl Heartbeat<'_> {
+    fn beat(&self, step: &'static str) {
+        self.beats.beat(
+            self.worker,
+            WorkerBeat {
+                kind: self.kind,
+                job: self.job,
+                step,
+                at: Instant::now(),
+            },
+        );
+    }
+}
+
+impl Drop for Heartbeat<'_> {
+    fn drop(&mut self) {
+        self.beats.forget(self.worker);
+    }
+}
+
+/// Find the workers hung on the client side: no beat for
+/// `worker_hang_timeout` seconds while their backend is not executing.
+///
+/// The backend of a hung worker is terminated, which ends the worker's
+/// wait with an error and fails the run. A worker whose backend is already
+/// gone can not be woken up: it is given up on and its id returned, so that
+/// it stops holding a worker slot; the stale-job reaper re-queues its job.
+pub fn check_hung_workers(client: &mut Client, config: &Config, beats: &WorkerBeats) -> Vec<u64> {
+    let timeout = Duration::from_secs_f64(config.worker_hang_timeout);
+    let stalled = beats.stalled(Instant::now(), timeout);
+    if stalled.is_empty() {
+        return Vec::new();
+    }
+    let names: Vec<String> = stalled
+        .iter()
+        .map(|(_, beat)| job_application_name(&config.schema, beat.kind.label(), beat.job))
+        .collect();
+    let backends: HashMap<String, (i32, Option<String>)> = match client.query(
+        "SELECT application_name, pid, state FROM pg_catalog.pg_stat_activity \
+         WHERE application_name = ANY($1)",
+        &[&names],
+    ) {
+        Ok(rows) => rows
+            .iter()
+            .map(|row| (row.get(0), (row.get(1), row.get(2))))
+            .collect(),
+        Err(err) => {
+            dlog!(config, "ERROR", "can not check for hung workers, {}", err);
+            return Vec::new();
+        }
+    };
+
+    let mut abandoned = Vec::new();
+    for ((worker, beat), name) in stalled.iter().zip(&names) {
+        let silent = beat.at.elapsed().as_secs_f64();
+        match backends.get(name) {
+            // A long statement: the beat only stalls while it runs.
+            Some((_, Some(state))) if state == "active" => {}
+            Some((pid, state)) => {
+                dlog!(
+                    config,
+                    "WARNING",
+                    "worker of {} job {} hung at step \"{}\": no progress for {:.0} seconds while its backend {} is {}, terminating the backend",
+                    beat.kind.label(),
+                    beat.job,
+                    beat.step,
+                    silent,
+                    pid,
+                    state.as_deref().unwrap_or("unknown")
+                );
+                if let Err(err) = client.execute("SELECT pg_terminate_backend($1)", &[pid]) {
+                    dlog!(
+                        config,
+                        "ERROR",
+                        "can not terminate backend {}, {}",
+                        pid,
+                        err
+                    );
+                }
+            }
+            None => {
+                dlog!(
+                    config,
+                    "WARNING",
+                    "worker of {} job {} hung at step \"{}\": no progress for {:.0} seconds and its backend is gone, giving up on it",
+                    beat.kind.label(),
+                    beat.job,
+                    beat.step,
+                    silent
+                );
+                beats.forget(*worker);
+                abandoned.push(*worker);
+            }
+        }
+    }
+    abandoned
+}
+
+/// Runs with a time limit, the `max_run_duration` of their job or
+/// `job_run_timeout`, registered by their worker when the body starts and
+/// checked by the main loop. See [`check_run_deadlines`].
+#[derive(Default)]
+pub struct RunDeadlines {
+    runs: Mutex<HashMap<u64, RunDeadline>>,
+}
+
+/// A run with a time limit.
+#[derive(Clone, Copy)]
+struct RunDeadline {
+    kind: JobKind,
+    job: i64,
+    backend_pid: i32,
+    /// Setting the limit comes from, as logged.
+    setting: &'static str,
+    limit: Duration,
+    started: Instant,
+    /// When the run was canceled for exceeding `limit`.
+    cancelled: Option<Instant>,
+    terminated: bool,
+}
+
+/// What the main loop does to a run past its time limit.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum DeadlineAction {
+    Cancel,
+    Terminate,
+}
+
+impl RunDeadline {
+    /// The action due at `now`: cancel once past the limit, terminate
+    /// [`RUN_CANCEL_GRACE`] after a cancel that did not end the run.
+    fn due_action(&self, now: Instant) -> Option<DeadlineAction> {
+        match self.cancelled {
+            _ if self.terminated => None,
+            Some(at) if now.saturating_duration_since(at) >= RUN_CANCEL_GRACE => {
+                Some(DeadlineAction::Terminate)
+            }
+            Some(_) => None,
+            None if now.saturating_duration_since(self.started) >= self.limit => {
+                Some(DeadlineAction::Cancel)
+            }
+            None => None,
+        }
+    }
+}
+
+impl RunDeadlines {
+    fn start(&self, worker: u64, deadline: RunDeadline) {
+        if let Ok(mut runs) = self.runs.lock() {
+            runs.insert(worker, deadline);
+        }
+    }
+
+    /// Forget the run of a worker. Returns it when it was canceled for
+    /// exceeding its time limit.
+    fn finish(&self, worker: u64) -> Option<RunDeadline> {
+        self.runs
+            .lock()
+            .ok()
+            .and_then(|mut runs| runs.remove(&worker))
+            .filter(|run| run.cancelled.is_some())
+    }
+
+    /// The runs with an action due at `now`, marked as done.
+    fn take_due(&self, now: Instant) -> Vec<(RunDeadline, DeadlineAction)> {
+        let Ok(mut runs) = self.runs.lock() else {
+            return Vec::new();
+        };
+        let mut due = Vec::new();
+        for run in runs.values_mut() {
+            let Some(action) = run.due_action(now) else {
+                continue;
+            };
+            match action {
+                DeadlineAction::Cancel => run.cancelled = Some(now),
+                DeadlineAction::Terminate => run.terminated = true,
+            }
+            due.push((*run, action));
+        }
+        due.sort_by_key(|(run, _)| run.job);
+        due
+    }
+}
+
+/// Stop the runs past their time limit, the `max_run_duration` of their job
+/// or `job_run_timeout`: the statement of the run is canceled with `pg_cancel_backend()`, and the backend terminated
+/// with `pg_terminate_backend()` when the run has not ended
+/// [`RUN_CANCEL_GRACE`] later. The worker records the run as `TIMED_OUT`.
+///
+/// The backend is only signaled while it still has the application_name
+/// of the job, so that a pid the worker has already given back is left
+/// alone.
+pub fn check_run_deadlines(client: &mut Client, config: &Config, deadlines: &RunDeadlines) {
+    for (run, action) in deadlines.take_due(Instant::now()) {
+        let (function, verb) = match action {
+            DeadlineAction::Cancel => ("pg_cancel_backend", "canceling its statement"),
+            DeadlineAction::Terminate => (
+                "pg_terminate_backend",
+                "still running, terminating its backend",
+            ),
+        };
+        dlog!(
+            config,
+            "WARNING",
+            "{} job {} exceeded its {} of {} seconds, {}",
+            run.kind.label(),
+            run.job,
+            run.setting,
+            run.limit.as_secs_f64(),
+            verb
+        );
+        let app_name = job_application_name(&config.schema, run.kind.label(), run.job);
+        if let Err(err) = client.execute(
+            &format!(
+                "SELECT {function}(pid) FROM pg_catalog.pg_stat_activity \
+                 WHERE pid = $1 AND application_name = $2"
+            ),
+            &[&run.backend_pid, &app_name],
+        ) {
+            dlog!(
+                config,
+                "ERROR",
+                "can not stop backend {} of job {}, {}",
+                run.backend_pid,
+                run.job,
+                err
+            );
+        }
+    }
+}
+
+/// Time limit of a run: the `max_run_duration` of its job or
+/// `job_run_timeout`, whichever is shorter, with the name of its setting.
+fn run_limit(
+    max_run_duration: Option<Duration>,
+    job_run_timeout: f64,
+) -> Option<(&'static str, Duration)> {
+    let timeout = (job_run_timeout > 0.0).then(|| Duration::from_secs_f64(job_run_timeout));
+    match (max_run_duration, timeout) {
+        (Some(own), Some(global)) if global < own => Some(("job_run_timeout", global)),
+        (Some(own), _) => Some(("max_run_duration", own)),
+        (None, Some(global)) => Some(("job_run_timeout", global)),
+        (None, None) => None,
+    }
+}
+
+/// Make the jobs handed back by workers eligible again, `error_delay` after
+/// their worker failed. A recurring scheduled job already had its
+/// `next_date` moved forward by the claim, so it is pulled back to now to be
+/// retried right away rather than at its next period.
+pub fn requeue_jobs(client: &mut Client, config: &Config, requeue: &RequeueQueue) {
+    let delay = Duration::from_secs_f64(config.error_delay);
+    for (kind, job) in requeue.take_due(Instant::now(), delay) {
+        let query = match kind {
+            JobKind::Async => {
+                "UPDATE dbms_job.all_async_jobs SET this_date = NULL WHERE job = $1 AND this_date IS NOT NULL"
+            }
+            JobKind::Scheduled => {
+                "UPDATE dbms_job.all_scheduled_jobs SET this_date = NULL, \
+                 next_date = least(next_date, current_timestamp) \
+                 WHERE job = $1 AND this_date IS NOT NULL"
+            }
+        };
+        match client.execute(&*in_schema(&config.schema, query), &[&job]) {
+            Ok(_) => dlog!(
+                config,
+                "LOG",
+                "re-queued {} job {} after its worker failed to start",
+                kind.label(),
+                job
+            ),
+            Err(err) => {
+                dlog!(
+                    config,
+                    "ERROR",
+                    "can not re-queue {} job {}, {}",
+                    kind.label(),
+                    job,
+                    err
+                );
+                requeue.push(kind, job);
+            }
+        }
+    }
+}
+
+/// State shared by the main loop with the workers.
+#[derive(Default)]
+pub struct WorkerShared {
+    /// Started and finished runs.
+    pub stats: JobStats,
+    /// Jobs handed back by workers that could not start them.
+    pub requeue: RequeueQueue,
+    /// Heartbeats of the running workers.
+    pub beats: WorkerBeats,
+    /// Run details of the finished runs, written by the main loop.
+    pub run_details: RunDetailsQueue,
+    /// Runs with a time limit.
+    pub deadlines: RunDeadlines,
 }
 
+/// Key of the next worker in the running-workers map.
+static NEXT_WORKER_ID: AtomicU64 = AtomicU64::new(1);
+
 /// Spawn a worker thread to execute a job.
 pub fn spawn_job(
     kind: JobKind,
     job: Job,
     pool: &Arc<JobPool>,
     config: &Arc<Config>,
-    stats: &Arc<JobStats>,
+    shared: &Arc<WorkerShared>,
     running_workers: &mut HashMap<u64, JoinHandle<()>>,
-    next_worker_id: &mut u64,
 ) {
-    let worker_id = *next_worker_id;
-    *next_worker_id = next_worker_id.wrapping_add(1);
+    let worker_id = NEXT_WORKER_ID.fetch_add(1, Ordering::Relaxed);
+    // Ties together the log lines and the run details of this execution.
+    let run_id = new_run_id();
 
+    let jobid = job.job;
     let pool_clone = Arc::clone(pool);
     let config_clone = Arc::clone(config);
-    let stats_clone = Arc::clone(stats);
+    let shared_clone = Arc::clone(shared);
 
     // Workers only drive SQL over a pooled connection, so a small stack is
     // plenty; the default 2 MiB per thread is what made a burst of in-flight
     // jobs balloon RSS. See `WORKER_STACK_SIZE`.
-    let spawn_result = std::thread::Builder::new()
+    let spawn_result = thread::Builder::new()
         .name(format!("job-{}", job.job))
         .stack_size(WORKER_STACK_SIZE)
         .spawn(move || {
+            // Forgotten on drop, after a panic too.
+            let heartbeat = Heartbeat {
+                beats: &shared_clone.beats,
+                worker: worker_id,
+                kind,
+                job: jobid,
+            };
             let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
-                execute_job(kind, job, &pool_clone, &config_clone, &stats_clone);
+                execute_job(
+                    kind,
+                    job,
+                    &run_id,
+                    &pool_clone,
+                    &config_clone,
+                    &shared_clone,
+                    &heartbeat,
+                );
             }));
         });
 
@@ -212,10 +1285,11 @@ pub fn spawn_job(
             running_workers.insert(worker_id, handle);
         }
         Err(err) => {
-            // The dispatch UPDATE already set this_date on the row; leaving it
-            // set means the stale-job reaper re-queues it later, so a transient
-            // thread-spawn failure (e.g. resource exhaustion) doesn't lose work.
+            // The dispatch UPDATE already set this_date on the row; hand the
+            // job back so that a transient thread-spawn failure (e.g.
+            // resource exhaustion) doesn't lose work.
             dlog!(config, "ERROR", "failed to spawn worker thread: {err}");
+            shared.requeue.push(kind, jobid);
         }
     }
 }
@@ -226,10 +1300,26 @@ pub fn spawn_job(
 /// three things: the application_name and log labels, the post-commit /
 /// post-rollback bookkeeping for scheduled rows, and whether the row is
 /// removed from the async queue afterwards.
-fn execute_job(kind: JobKind, job: Job, pool: &Arc<JobPool>, config: &Config, stats: &JobStats) {
+fn execute_job(
+    kind: JobKind,
+    job: Job,
+    run_id: &str,
+    pool: &Arc<JobPool>,
+    config: &Config,
+    shared: &WorkerShared,
+    heartbeat: &Heartbeat<'_>,
+) {
+    let WorkerShared {
+        stats,
+        requeue,
+        run_details,
+        deadlines,
+        ..
+    } = shared;
     // Bump started now, finished on Drop — survives every early return below
     // and any panic, so the periodic stats LOG line stays balanced.
     let _stats_guard = JobStatsGuard::new(stats);
+    let _log_context = job_log_context(job.job, run_id);
     let kind_label = kind.label();
     let start_t = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
     dlog!(config, "DEBUG", "executing {} job {}", kind_label, job.job);
@@ -241,26 +1331,52 @@ fn execute_job(kind: JobKind, job: Job, pool: &Arc<JobPool>, config: &Config, st
         job.job
     );
 
-    let app_name = format!("pg_dbms_job:{}:{}", kind_label, job.job);
+    let app_name = job_application_name(&config.schema, kind_label, job.job);
+    heartbeat.beat("connect");
     let mut client = match get_job_connection(pool, &app_name) {
         Ok(c) => c,
         Err(err) => {
-            dlog!(config, "ERROR", "{}", err);
+            dlog!(
+                config,
+                "ERROR",
+                "can not get a connection for {} job {}, it will be retried in {} seconds, reason: {}",
+                kind_label,
+                job.job,
+                config.error_delay,
+                err
+            );
+            requeue.push(kind, job.job);
             return;
         }
     };
 
     dlog!(config, "DEBUG", "connected to database for job {}", job.job);
+    // Only keep what this run raises.
+    take_job_notices();
+    heartbeat.beat("session setup");
+    apply_job_priority(&mut client, config, &job);
 
-    if let Some(log_user) = &job.log_user {
-        let quoted = quote_ident(log_user);
-        dlog!(config, "DEBUG", "SET ROLE {quoted}");
-        if let Err(err) = client.batch_execute(&format!("SET ROLE {quoted}")) {
-            dlog!(config, "ERROR", "can not change role, reason: {err}");
-            return;
+    // Owner whose SECURITY DEFINER function runs the body, instead of a
+    // SET ROLE of the whole session.
+    let definer = match config.job_execution_mode {
+        ExecutionMode::SecurityDefiner => job.log_user.as_deref(),
+        ExecutionMode::SetRole => None,
+    };
+    match (&job.log_user, definer) {
+        (Some(_), Some(_)) => dprint(
+            config,
+            "DEBUG",
+            "the job runs in a SECURITY DEFINER function of its owner",
+        ),
+        (Some(log_user), None) => {
+            dlog!(config, "DEBUG", "SET ROLE {}", quote_ident(log_user));
+            // The name is a parameter, never part of the statement text.
+            if let Err(err) = client.execute("SELECT set_config('role', $1, false)", &[log_user]) {
+                dlog!(config, "ERROR", "can not change role, reason: {err}");
+                return;
+            }
         }
-    } else {
-        dprint(config, "DEBUG", "log_user is not set, using default role");
+        (None, _) => dprint(config, "DEBUG", "log_user is not set, using default role"),
     }
 
     dprint(config, "DEBUG", "BEGIN");
@@ -276,7 +1392,11 @@ fn execute_job(kind: JobKind, job: Job, pool: &Arc<JobPool>, config: &Config, st
     if let Some(schema_user) = &job.schema_user {
         let quoted_path = quote_search_path(schema_user);
         dlog!(config, "DEBUG", "SET LOCAL search_path TO {quoted_path}");
-        if let Err(err) = client.batch_execute(&format!("SET LOCAL search_path TO {quoted_path}")) {
+        // Also accepts a list without any schema, which SET can not express.
+        if let Err(err) = client.execute(
+            "SELECT set_config('search_path', $1, true)",
+            &[&quoted_path],
+        ) {
             dlog!(
                 config,
                 "ERROR",
@@ -292,86 +1412,271 @@ fn execute_job(kind: JobKind, job: Job, pool: &Arc<JobPool>, config: &Config, st
         );
     }
 
-    let mut status_text = String::new();
+    // Start every run with an empty result so a value published by the
+    // previous job on this pooled session can never leak into this one.
+    // The run id is a UUID, safe to inline.
+    if let Err(err) = client.batch_execute(&format!(
+        "SET LOCAL {RESULT_SETTING} TO ''; SET LOCAL {RUN_ID_SETTING} TO '{run_id}'"
+    )) {
+        dlog!(
+            config,
+            "ERROR",
+            "can not reset the job result, reason: {err}"
+        );
+        return;
+    }
+
+    let mut status = RunStatus::Succeeded;
     let mut err_text = String::new();
     let mut sqlstate = String::new();
+    let mut diagnostics: Option<ErrorDiagnostics> = None;
+    let mut result: Option<String> = None;
+    // Set when this run made the scheduler mark the job broken.
+    let mut broken_reason: Option<String> = None;
 
-    let t0 = Instant::now();
-    let code = build_do_block(job.job, &job.what);
-    dprint(config, "DEBUG", "code to execute:");
-    dprint(config, "DEBUG", &code);
-
-    let exec_result = client.batch_execute(&code);
+    // Waiting for another job on the same resource is not run time. The
+    // slots of a semaphore are counted when claiming the job, one of more
+    // than one slot is not locked.
+    heartbeat.beat("resource lock");
+    let lock_result = match &job.resource_key {
+        Some(key) if config.semaphores.get(key).is_none_or(|&slots| slots <= 1) => {
+            lock_resource(&mut client, config, job.job, key)
+        }
+        _ => Ok(()),
+    };
 
-    if let Err(err) = exec_result {
-        err_text = err.to_string();
-        sqlstate = err.code().map(|c| c.code().to_string()).unwrap_or_default();
-        status_text = "ERROR".to_string();
-        dlog!(
-            config,
-            "ERROR",
-            "job {} failure, reason: {}",
-            job.job,
-            err_text
+    let t0 = Instant::now();
+    heartbeat.beat("execute");
+    // The backend the run is started on, the one terminated on timeout.
+    let backend_pid = client.backend_pid();
+    let limit = run_limit(job.max_run_duration, config.job_run_timeout);
+    // A command is killed by its worker, its backend only waits meanwhile.
+    if let (Ok(()), Some((setting, limit)), false) = (&lock_result, limit, job.executable) {
+        deadlines.start(
+            heartbeat.worker,
+            RunDeadline {
+                kind,
+                job: job.job,
+                backend_pid,
+                setting,
+                limit,
+                started: t0,
+                cancelled: None,
+                terminated: false,
+            },
         );
-        dprint(config, "DEBUG", "ROLLBACK");
-        if let Err(err) = client.batch_execute("ROLLBACK") {
+    }
+    let mut command: Option<CommandRun> = None;
+    let exec_result = if let Err(err) = lock_result {
+        Err(err)
+    } else if job.executable {
+        run_executable(&mut client, config, &job, run_id, limit, heartbeat).and_then(|run| {
+            let failure = run.failure(limit);
+            command = Some(run);
+            failure.map_or(Ok(Vec::new()), |text| Err(RunError::from_text(text, "")))
+        })
+    } else if job.continue_on_error {
+        execute_statements(&mut client, config, job.job, &job.what, definer)
+    } else {
+        dprint(config, "DEBUG", "code to execute:");
+        run_body(&mut client, config, job.job, &job.what, definer).map(|()| Vec::new())
+    };
+
+    heartbeat.beat("end of run");
+    let timed_out = deadlines.finish(heartbeat.worker);
+    match exec_result {
+        Err(error) => {
+            err_text = error.text;
+            sqlstate = error.sqlstate;
+            status = failure_status(&sqlstate, error.diagnostics.message.as_deref());
+            diagnostics = Some(*error.diagnostics);
+            if command
+                .as_ref()
+                .is_some_and(|run| run.end == CommandEnd::TimedOut)
+            {
+                status = RunStatus::TimedOut;
+            }
+            if let Some(deadline) = timed_out {
+                status = RunStatus::TimedOut;
+                err_text = format!(
+                    "run exceeded {} of {} seconds: {}",
+                    deadline.setting,
+                    deadline.limit.as_secs_f64(),
+                    err_text
+                );
+                // The backend was terminated (admin_shutdown): the run is
+                // recorded from another session.
+                if sqlstate == "57P01" || client.is_closed() {
+                    match get_job_connection(pool, &app_name) {
+                        Ok(c) => client = c,
+                        Err(err) => dlog!(
+                            config,
+                            "ERROR",
+                            "can not get a connection to record the timeout of job {}, reason: {}",
+                            job.job,
+                            err
+                        ),
+                    }
+                }
+            }
             dlog!(
                 config,
                 "ERROR",
-                "can not rollback a transaction, reason: {err}"
+                "job {} failure, reason: {}",
+                job.job,
+                err_text
             );
-        } else if matches!(kind, JobKind::Scheduled) {
-            // The DO-block failed inside a transaction we own, so the
-            // scheduled row's `this_date` is still set from the dispatch
-            // UPDATE. Clear it and bump `failures` so the row is eligible
-            // for the next attempt.
-            if let Err(err) = client.execute(
-                "UPDATE dbms_job.all_scheduled_jobs SET this_date = NULL, failures = failures+1 WHERE job = $1",
-                &[&job.job],
-            ) {
+            dprint(config, "DEBUG", "ROLLBACK");
+            if let Err(err) = client.batch_execute("ROLLBACK") {
                 dlog!(
                     config,
                     "ERROR",
-                    "failed to record failure for scheduled job {}: {}",
+                    "can not rollback a transaction, reason: {err}"
+                );
+            } else if matches!(kind, JobKind::Scheduled) {
+                // The DO-block failed inside a transaction we own, so the
+                // scheduled row's `this_date` is still set from the dispatch
+                // UPDATE. Clear it and bump `failures` so the row is eligible
+                // for the next attempt; keep the error on the row itself.
+                broken_reason = record_scheduled_failure(
+                    &mut client,
+                    config,
                     job.job,
-                    err
+                    status,
+                    &err_text,
+                    &sqlstate,
                 );
             }
         }
-    } else {
-        dprint(config, "DEBUG", "COMMIT");
-        if let Err(err) = client.batch_execute("COMMIT") {
-            dlog!(
-                config,
-                "ERROR",
-                "can not commit a transaction, reason: {err}"
-            );
-        } else if matches!(kind, JobKind::Scheduled) {
-            let duration_secs = t0.elapsed().as_secs() as i64;
-            if let Err(err) = client.execute(
-                "UPDATE dbms_job.all_scheduled_jobs SET this_date = NULL, last_date = current_timestamp, total_time = ($1 || ' seconds')::interval, failures = 0, instance = instance+1 WHERE job = $2",
-                &[&duration_secs.to_string(), &job.job],
-            ) {
+        Ok(failed_statements) => {
+            if !failed_statements.is_empty() {
+                // Continue-on-error job: the other statements are committed,
+                // the run counts as a failure.
+                status = RunStatus::Partial;
+                err_text = describe_statement_failures(&failed_statements);
+                sqlstate = failed_statements[0].error.sqlstate.clone();
+                diagnostics = Some(*failed_statements[0].error.diagnostics.clone());
                 dlog!(
                     config,
                     "ERROR",
-                    "failed to record success for scheduled job {}: {}",
+                    "job {} partial failure, reason: {}",
                     job.job,
-                    err
+                    err_text
+                );
+            }
+            if !job.executable {
+                result = read_job_result(&mut client, config, job.job);
+            }
+            dprint(config, "DEBUG", "COMMIT");
+            if let Err(err) = client.batch_execute("COMMIT") {
+                dlog!(
+                    config,
+                    "ERROR",
+                    "can not commit a transaction, reason: {err}"
+                );
+            } else if matches!(kind, JobKind::Scheduled) && !failed_statements.is_empty() {
+                broken_reason = record_scheduled_failure(
+                    &mut client,
+                    config,
+                    job.job,
+                    status,
+                    &err_text,
+                    &sqlstate,
                 );
+            } else if matches!(kind, JobKind::Scheduled) {
+                let duration_secs = t0.elapsed().as_secs() as i64;
+                if let Err(err) = client.execute(
+                    &*in_schema(
+                        &config.schema,
+                        "UPDATE dbms_job.all_scheduled_jobs SET this_date = NULL, last_date = current_timestamp, total_time = ($1 || ' seconds')::interval, failures = 0, instance = instance+1, run_count = run_count+1, last_success_date = current_timestamp, last_status = 'SUCCEEDED' WHERE job = $2",
+                    ),
+                    &[&duration_secs.to_string(), &job.job],
+                ) {
+                    dlog!(
+                        config,
+                        "ERROR",
+                        "failed to record success for scheduled job {}: {}",
+                        job.job,
+                        err
+                    );
+                }
             }
         }
     }
 
+    let mut notices = take_job_notices();
+    if let Some(run) = &command {
+        if !run.stdout.is_empty() {
+            result = Some(run.stdout.clone());
+        }
+        notices.extend(run.stderr.iter().map(|line| format!("STDERR: {line}")));
+    }
+    if config.log_job_notices {
+        for notice in &notices {
+            let level = if notice.starts_with("WARNING:") {
+                "WARNING"
+            } else {
+                "LOG"
+            };
+            dlog!(config, level, "job {} {}", job.job, notice);
+        }
+    }
+
     if matches!(kind, JobKind::Async) {
         dprint(config, "DEBUG", "delete job");
         delete_job(&mut client, config, job.job);
     }
 
-    let duration_secs = t0.elapsed().as_secs() as i64;
-    // `status_text` is "ERROR" only when the job failed; empty on success.
-    let failed = !status_text.is_empty();
+    let elapsed = t0.elapsed();
+    let duration_secs = elapsed.as_secs() as i64;
+    let failed = status != RunStatus::Succeeded;
+    stats.record_run(kind, failed);
+
+    if broken_reason.is_none() && matches!(kind, JobKind::Scheduled) {
+        broken_reason = check_overrun(&mut client, config, job.job, elapsed);
+        if let Some(reason) = &broken_reason {
+            dlog!(config, "WARNING", "job {} {}", job.job, reason);
+        }
+    }
+    if let Some(reason) = broken_reason {
+        notify_job_broken(
+            &mut client,
+            config,
+            job.job,
+            job.log_user.as_deref(),
+            &reason,
+        );
+        if config.job_run_details != JobRunDetails::None {
+            run_details.push(
+                JobExecutionDetails {
+                    owner: job.log_user.as_deref().unwrap_or(""),
+                    jobid: job.job,
+                    start_date: &start_t,
+                    duration_secs,
+                    status: RunStatus::Broken,
+                    err_text: &reason,
+                    sqlstate: "",
+                    diagnostics: None,
+                    result: None,
+                    notices: &[],
+                    exit_code: None,
+                    run_id: Some(run_id),
+                    backend_pid: Some(backend_pid),
+                }
+                .to_run_details(&config.schema),
+            );
+        }
+    }
+
+    let chained = if failed {
+        job.on_failure
+    } else {
+        job.on_success
+    };
+    if let Some(target) = chained {
+        enqueue_chained_job(&mut client, config, job.job, target);
+    }
+    enqueue_dependents(&mut client, config, job.source_job, failed);
     let record_details = match config.job_run_details {
         JobRunDetails::All => true,
         JobRunDetails::Errors => failed,
@@ -383,17 +1688,23 @@ fn execute_job(kind: JobKind, job: Job, pool: &Arc<JobPool>, config: &Config, st
             jobid: job.job,
             start_date: &start_t,
             duration_secs,
-            status_text: &status_text,
+            status,
             err_text: &err_text,
             sqlstate: &sqlstate,
+            diagnostics: diagnostics.as_ref(),
+            result: result.as_deref(),
+            notices: &notices,
+            exit_code: command.as_ref().and_then(CommandRun::exit_code),
+            run_id: Some(run_id),
+            backend_pid: Some(backend_pid),
         };
         dlog!(
             config,
             "DEBUG",
-            "storing job execution details: {:?}",
+            "queueing job execution details: {:?}",
             details
         );
-        store_job_execution_details(&mut client, config, details);
+        run_details.push(details.to_run_details(&config.schema));
     } else {
         dlog!(
             config,
@@ -404,8 +1715,6 @@ fn execute_job(kind: JobKind, job: Job, pool: &Arc<JobPool>, config: &Config, st
         );
     }
 
-    reset_job_connection(&mut client);
-
     dlog!(
         config,
         "DEBUG",
@@ -416,26 +1725,652 @@ fn execute_job(kind: JobKind, job: Job, pool: &Arc<JobPool>, config: &Config, st
     );
 }
 
-/// Escape a PostgreSQL identifier with double-quote quoting.
-fn quote_ident(ident: &str) -> String {
-    format!("\"{}\"", ident.replace('"', "\"\""))
+/// Record a failed run on the scheduled job row: clear the running marker,
+/// bump `failures` and the run counters, and keep the error and the run status. A job with a `retry_delay` has its
+/// `next_date` brought forward to that delay from now, unless its next
+/// scheduled run comes sooner.
+///
+/// A job whose `failures` reach its `max_failures`, or the scheduler's
+/// when it has none, is marked broken: the reason is returned.
+fn record_scheduled_failure(
+    client: &mut Client,
+    config: &Config,
+    jobid: i64,
+    status: RunStatus,
+    err_text: &str,
+    sqlstate: &str,
+) -> Option<String> {
+    let max_failures = i32::try_from(config.max_failures).unwrap_or(i32::MAX);
+    match client.query_opt(
+        &*in_schema(&config.schema, &format!(
+            "UPDATE dbms_job.all_scheduled_jobs SET this_date = NULL, failures = coalesce(failures, 0)+1, \
+             run_count = run_count+1, failure_count = failure_count+1, last_error = $2, last_sqlstate = NULLIF($3, ''), last_failure_date = current_timestamp, \
+             last_status = $4, \
+             broken = broken OR {}, \
+             next_date = CASE WHEN retry_delay IS NULL THEN next_date \
+                 ELSE least(next_date, current_timestamp + retry_delay) END \
+             WHERE job = $1 \
+             RETURNING broken, failures, coalesce(max_failures, $5)",
+            max_failures_reached("$5")
+        )),
+        &[&jobid, &err_text, &sqlstate, &status.as_str(), &max_failures],
+    ) {
+        Ok(Some(row)) => {
+            let failures: i64 = row.get(1);
+            let limit: i32 = row.get(2);
+            (row.get::<_, bool>(0) && limit > 0 && failures >= i64::from(limit)).then(|| {
+                let reason = max_failures_reason(failures);
+                dlog!(config, "ERROR", "job {} {}", jobid, reason);
+                reason
+            })
+        }
+        Ok(None) => None,
+        Err(err) => {
+            dlog!(
+                config,
+                "ERROR",
+                "failed to record failure for scheduled job {}: {}",
+                jobid,
+                err
+            );
+            None
+        }
+    }
+}
+
+/// Condition of a scheduled job row whose next failed run reaches its
+/// `max_failures`, or the scheduler's, the `param` placeholder, when NULL.
+fn max_failures_reached(param: &str) -> String {
+    format!(
+        "(coalesce(max_failures, {param}) > 0 \
+         AND coalesce(failures, 0) + 1 >= coalesce(max_failures, {param}))"
+    )
+}
+
+/// Explanation recorded when a job is marked broken for failing too often.
+fn max_failures_reason(failures: i64) -> String {
+    format!("marked broken after {failures} consecutive failed runs, reaching max_failures")
+}
+
+/// Status of a run that raised an error, from its SQLSTATE and message.
+///
+/// `query_canceled` is raised both by `statement_timeout` and by
+/// `pg_cancel_backend()`; only the message tells them apart, so a server
+/// with a translated `lc_messages` reports a timed out statement as
+/// cancelled.
+fn failure_status(sqlstate: &str, message: Option<&str>) -> RunStatus {
+    let message = message.unwrap_or("");
+    match sqlstate {
+        // statement_timeout, lock_timeout and
+        // idle_in_transaction_session_timeout.
+        "57014" | "55P03" if message.contains("timeout") => RunStatus::Timeout,
+        "25P03" => RunStatus::Timeout,
+        // pg_cancel_backend(), pg_terminate_backend().
+        "57014" | "57P01" => RunStatus::Cancelled,
+        _ => RunStatus::Failed,
+    }
+}
+
+/// Error of a failed statement.
+struct RunError {
+    /// Full error text, as logged and stored in `last_error`.
+    text: String,
+    /// SQLSTATE, empty when the error did not come from the server.
+    sqlstate: String,
+    /// Boxed to keep the `Result`s carrying a `RunError` small.
+    diagnostics: Box<ErrorDiagnostics>,
+}
+
+impl RunError {
+    /// The text carries the DETAIL and HINT of a server error and, when the
+    /// error was raised inside the job body, its CONTEXT: the PL/pgSQL line
+    /// and the chain of function calls that failed.
+    fn from_error(err: &postgres::Error) -> Self {
+        let diagnostics = ErrorDiagnostics::from_error(err);
+        RunError {
+            text: match err.as_db_error() {
+                Some(db) => diagnostics.text(db.severity()),
+                None => err.to_string(),
+            },
+            sqlstate: err.code().map(|c| c.code().to_string()).unwrap_or_default(),
+            diagnostics: Box::new(diagnostics),
+        }
+    }
+
+    /// An error trapped by the exception handler of the job body, see
+    /// [`build_do_block`], laid out as the server would have sent it.
+    fn from_diagnostics(sqlstate: String, diagnostics: ErrorDiagnostics) -> Self {
+        RunError {
+            text: diagnostics.text("ERROR"),
+            sqlstate,
+            diagnostics: Box::new(diagnostics),
+        }
+    }
+
+    /// An error raised by the scheduler itself rather than the server.
+    fn from_text(text: String, sqlstate: &str) -> Self {
+        RunError {
+            diagnostics: Box::new(ErrorDiagnostics {
+                message: Some(text.clone()),
+                ..ErrorDiagnostics::default()
+            }),
+            text,
+            sqlstate: sqlstate.to_string(),
+        }
+    }
+}
+
+/// Fields of a server error stored in their own run-details columns, so
+/// that failures can be grouped by message.
+#[derive(Clone, Debug, Default)]
+struct ErrorDiagnostics {
+    message: Option<String>,
+    detail: Option<String>,
+    hint: Option<String>,
+    context: Option<String>,
+}
+
+impl ErrorDiagnostics {
+    fn from_error(err: &postgres::Error) -> Self {
+        match err.as_db_error() {
+            Some(db) => ErrorDiagnostics {
+                message: Some(db.message().to_string()),
+                detail: db.detail().map(str::to_string),
+                hint: db.hint().map(str::to_string),
+                context: db.where_().map(str::to_string),
+            },
+            None => ErrorDiagnostics {
+                message: Some(err.to_string()),
+                ..ErrorDiagnostics::default()
+            },
+        }
+    }
+
+    /// Text of a server error in `psql` layout: the message, then its
+    /// DETAIL, HINT and CONTEXT lines.
+    fn text(&self, severity: &str) -> String {
+        let mut text = format!(
+            "db error: {severity}: {}",
+            self.message.as_deref().unwrap_or_default()
+        );
+        if let Some(detail) = &self.detail {
+            text.push_str(&format!("\nDETAIL: {detail}"));
+        }
+        if let Some(hint) = &self.hint {
+            text.push_str(&format!("\nHINT: {hint}"));
+        }
+        match &self.context {
+            Some(context) => with_error_context(text, context),
+            None => text,
+        }
+    }
+}
+
+/// Append the CONTEXT of a server error to its message, in `psql` layout.
+fn with_error_context(message: String, context: &str) -> String {
+    format!("{message}\nCONTEXT: {context}")
+}
+
+/// A statement of a continue-on-error job that failed.
+struct StatementFailure {
+    /// Position of the statement in the job body, starting at 1.
+    index: usize,
+    error: RunError,
+}
+
+/// Savepoint taken before each statement of a continue-on-error job.
+const STATEMENT_SAVEPOINT: &str = "pg_dbms_job_statement";
+
+/// Run the statements of a continue-on-error job one by one, each in its own
+/// DO block under a savepoint, so that a failing statement is rolled back
+/// alone and the following ones still run.
+///
+/// Returns the statements that failed. When every statement failed, or the
+/// savepoint handling itself fails, the run is an error as a whole.
+fn execute_statements(
+    client: &mut Client,
+    config: &Config,
+    jobid: i64,
+    what: &str,
+    definer: Option<&str>,
+) -> Result<Vec<StatementFailure>, RunError> {
+    let statements = split_statements(what);
+    let mut failures = Vec::new();
+    for (i, statement) in statements.iter().enumerate() {
+        if let Some(command) = outside_transaction_command(statement) {
+            let text = format!("{command} can not run inside a transaction block");
+            dlog!(
+                config,
+                "WARNING",
+                "job {} statement {} refused, continuing with the next one, reason: {}",
+                jobid,
+                i + 1,
+                text
+            );
+            failures.push(StatementFailure {
+                index: i + 1,
+                error: RunError {
+                    text,
+                    // active_sql_transaction, as raised by the server.
+                    sqlstate: "25001".to_string(),
+                    diagnostics: Box::default(),
+                },
+            });
+            continue;
+        }
+        dlog!(config, "DEBUG", "statement {} to execute:", i + 1);
+        client
+            .batch_execute(&format!("SAVEPOINT {STATEMENT_SAVEPOINT}"))
+            .map_err(|err| RunError::from_error(&err))?;
+        // The terminator goes on its own line so that a statement ending
+        // with a `--` comment does not comment it out.
+        match run_body(client, config, jobid, &format!("{statement}\n;"), definer) {
+            Ok(()) => client
+                .batch_execute(&format!("RELEASE SAVEPOINT {STATEMENT_SAVEPOINT}"))
+                .map_err(|err| RunError::from_error(&err))?,
+            Err(error) => {
+                dlog!(
+                    config,
+                    "WARNING",
+                    "job {} statement {} failed, continuing with the next one, reason: {}",
+                    jobid,
+                    i + 1,
+                    error.text
+                );
+                client
+                    .batch_execute(&format!("ROLLBACK TO SAVEPOINT {STATEMENT_SAVEPOINT}"))
+                    .map_err(|err| RunError::from_error(&err))?;
+                failures.push(StatementFailure {
+                    index: i + 1,
+                    error,
+                });
+            }
+        }
+    }
+    if !failures.is_empty() && failures.len() == statements.len() {
+        let text = describe_statement_failures(&failures);
+        let first = failures.swap_remove(0);
+        return Err(RunError {
+            text,
+            ..first.error
+        });
+    }
+    Ok(failures)
+}
+
+/// Error text of a continue-on-error run, e.g.
+/// `statement 2: relation "t" does not exist; statement 4: ...`.
+fn describe_statement_failures(failures: &[StatementFailure]) -> String {
+    failures
+        .iter()
+        .map(|f| format!("statement {}: {}", f.index, f.error.text))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Split a job body into its top-level statements for continue-on-error
+/// execution. Semicolons inside quoted strings and identifiers, dollar-quoted
+/// strings, comments and PL/pgSQL blocks (`DECLARE`/`BEGIN ... END`, `IF`,
+/// `LOOP`, `CASE`) do not split; empty statements are dropped.
+fn split_statements(body: &str) -> Vec<String> {
+    let chars: Vec<char> = body.chars().collect();
+    let len = chars.len();
+    let mut statements = Vec::new();
+    let mut start = 0;
+    let mut i = 0;
+    // Open blocks, `true` for a DECLARE section waiting for its BEGIN.
+    let mut blocks: Vec<bool> = Vec::new();
+    // Last word or `;` seen, to tell where a statement starts.
+    let mut prev = String::from(";");
+    while i < len {
+        match chars[i] {
+            quote @ ('\'' | '"') => {
+                // E'...' strings take backslash escapes.
+                let escapes = quote == '\'' && prev == "e" && matches!(chars[i - 1], 'e' | 'E');
+                i += 1;
+                while i < len {
+                    // A backslash escape, or a doubled quote.
+                    if (escapes && chars[i] == '\\')
+                        || (chars[i] == quote && chars.get(i + 1) == Some(&quote))
+                    {
+                        i += 2;
+                    } else if chars[i] == quote {
+                        break;
+                    } else {
+                        i += 1;
+                    }
+                }
+                i += 1;
+                prev.clear();
+            }
+            '-' if chars.get(i + 1) == Some(&'-') => {
+                while i < len && chars[i] != '\n' {
+                    i += 1;
+                }
+            }
+            '/' if chars.get(i + 1) == Some(&'*') => {
+                i += 2;
+                while i < len && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                    i += 1;
+                }
+                i += 2;
+            }
+            '$' => match dollar_tag(&chars[i..]) {
+                Some(tag_len) => {
+                    let tag = &chars[i..i + tag_len];
+                    i += tag_len;
+                    while i < len && !chars[i..].starts_with(tag) {
+                        i += 1;
+                    }
+                    i += tag_len;
+                    prev.clear();
+                }
+                None => i += 1,
+            },
+            c if c.is_alphabetic() || c == '_' => {
+                let word_start = i;
+                while i < len && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '$')
+                {
+                    i += 1;
+                }
+                let word = chars[word_start..i]
+                    .iter()
+                    .collect::<String>()
+                    .to_lowercase();
+                let statement_start = matches!(
+                    prev.as_str(),
+                    ";" | "then" | "else" | "loop" | "begin" | "declare"
+                );
+                match word.as_str() {
+                    // The IF, LOOP or CASE of END IF, END LOOP, END CASE.
+                    "if" | "loop" | "case" if prev == "end" => {}
+                    "end" => {
+                        blocks.pop();
+                    }
+                    "declare" if statement_start => blocks.push(true),
+                    "begin" => match blocks.last_mut() {
+                        Some(declare @ true) => *declare = false,
+                        _ => blocks.push(false),
+                    },
+                    // IF also appears in IF EXISTS clauses of SQL commands.
+                    "if" if statement_start => blocks.push(false),
+                    "loop" | "case" => blocks.push(false),
+                    _ => {}
+                }
+                prev = word;
+            }
+            ';' => {
+                if blocks.is_empty() {
+                    statements.push(chars[start..i].iter().collect::<String>());
+                    start = i + 1;
+                }
+                i += 1;
+                prev = ";".to_string();
+            }
+            c => {
+                if !c.is_whitespace() {
+                    prev.clear();
+                }
+                i += 1;
+            }
+        }
+    }
+    statements.push(chars[start.min(len)..].iter().collect::<String>());
+    statements
+        .into_iter()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+/// Commands a continue-on-error statement can not run, as they can not run
+/// inside a transaction block, named after the first words of `statement`.
+fn outside_transaction_command(statement: &str) -> Option<String> {
+    let mut rest = statement.trim_start();
+    loop {
+        if let Some(comment) = rest.strip_prefix("--") {
+            rest = comment.split_once('\n').map_or("", |(_, r)| r).trim_start();
+        } else if let Some(comment) = rest.strip_prefix("/*") {
+            rest = comment.split_once("*/").map_or("", |(_, r)| r).trim_start();
+        } else {
+            break;
+        }
+    }
+    let words: Vec<String> = rest
+        .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|w| !w.is_empty())
+        .take(5)
+        .map(str::to_lowercase)
+        .collect();
+    let word = |n: usize| words.get(n).map_or("", String::as_str);
+    let command = match (word(0), word(1)) {
+        ("vacuum" | "commit" | "rollback", _) => word(0).to_string(),
+        ("start", "transaction") | ("alter", "system") => format!("{} {}", word(0), word(1)),
+        ("create" | "drop", "database" | "tablespace" | "subscription") => {
+            format!("{} {}", word(0), word(1))
+        }
+        ("create" | "drop" | "reindex", _) if words.iter().any(|w| w == "concurrently") => {
+            format!("{} ... concurrently", word(0))
+        }
+        _ => return None,
+    };
+    Some(command.to_uppercase())
+}
+
+/// Length of the dollar-quote opening tag (`$$` or `$tag$`) at the start of
+/// `chars`, if any. `$1`-style parameters are not tags.
+fn dollar_tag(chars: &[char]) -> Option<usize> {
+    if chars.get(1).is_some_and(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let mut j = 1;
+    while j < chars.len() && (chars[j].is_alphanumeric() || chars[j] == '_') {
+        j += 1;
+    }
+    (chars.get(j) == Some(&'$')).then_some(j + 1)
+}
+
+/// Apply the `hard_max_run_duration` policy to a finished scheduled run.
+///
+/// A run longer than the limit increments the job's `consecutive_overruns`,
+/// any other run resets it. When the count reaches `hard_max_run_overruns`
+/// the job is marked broken and the explanation, also stored in
+/// `last_error`, is returned.
+fn check_overrun(
+    client: &mut Client,
+    config: &Config,
+    jobid: i64,
+    elapsed: Duration,
+) -> Option<String> {
+    if config.hard_max_run_duration <= 0.0 {
+        return None;
+    }
+    let overran = elapsed.as_secs_f64() > config.hard_max_run_duration;
+    let limit = i32::try_from(config.hard_max_run_overruns).unwrap_or(i32::MAX);
+    let reason = overrun_reason(config.hard_max_run_overruns, config.hard_max_run_duration);
+    // Only the transition to broken is left to notify the scheduler: the
+    // counter update itself bumps `instance` like the other internal updates.
+    match client.query_opt(
+        &*in_schema(&config.schema, "UPDATE dbms_job.all_scheduled_jobs SET \
+         consecutive_overruns = CASE WHEN $2 THEN consecutive_overruns + 1 ELSE 0 END, \
+         broken = $2 AND consecutive_overruns + 1 >= $3, \
+         last_error = CASE WHEN $2 AND consecutive_overruns + 1 >= $3 THEN $4 ELSE last_error END, \
+         last_sqlstate = CASE WHEN $2 AND consecutive_overruns + 1 >= $3 THEN NULL ELSE last_sqlstate END, \
+         last_failure_date = CASE WHEN $2 AND consecutive_overruns + 1 >= $3 \
+             THEN current_timestamp ELSE last_failure_date END, \
+         last_status = CASE WHEN $2 AND consecutive_overruns + 1 >= $3 THEN 'BROKEN' ELSE last_status END, \
+         instance = CASE WHEN $2 AND consecutive_overruns + 1 >= $3 THEN instance ELSE instance + 1 END \
+         WHERE job = $1 AND NOT broken AND ($2 OR consecutive_overruns > 0) \
+         RETURNING broken, consecutive_overruns"),
+        &[&jobid, &overran, &limit, &reason],
+    ) {
+        Ok(Some(row)) => {
+            let broken: bool = row.get(0);
+            let overruns: i32 = row.get(1);
+            if overran && !broken {
+                dlog!(
+                    config,
+                    "WARNING",
+                    "job {} ran {:.0} seconds, longer than hard_max_run_duration ({} of {} consecutive overruns)",
+                    jobid,
+                    elapsed.as_secs_f64(),
+                    overruns,
+                    limit
+                );
+            }
+            broken.then_some(reason)
+        }
+        Ok(None) => None,
+        Err(err) => {
+            dlog!(
+                config,
+                "ERROR",
+                "failed to record run duration of scheduled job {}: {}",
+                jobid,
+                err
+            );
+            None
+        }
+    }
+}
+
+/// Explanation recorded when a job is marked broken for overrunning.
+fn overrun_reason(overruns: usize, max_duration: f64) -> String {
+    format!(
+        "marked broken after {overruns} consecutive runs longer than hard_max_run_duration ({max_duration} seconds)"
+    )
+}
+
+/// Escape a PostgreSQL identifier with double-quote quoting.
+fn quote_ident(ident: &str) -> String {
+    format!("\"{}\"", ident.replace('"', "\"\""))
+}
+
+/// Quote a comma-separated list of schema names for use with SET search_path.
+///
+/// Elements starting with a double quote are read as quoted identifiers, the
+/// way `current_setting('search_path')` (the `schema_user` default) renders
+/// them: they may contain commas and `""`-escaped quotes. Other elements are
+/// taken verbatim as the schema name. Every element is then re-quoted on its
+/// own, so placeholders like "$user" keep their meaning and nothing in the
+/// value can escape the identifier list.
+fn quote_search_path(raw: &str) -> String {
+    split_search_path(raw)
+        .iter()
+        .map(|s| quote_ident(s))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Split a `search_path` value into schema names, resolving quoted elements.
+/// Empty elements are dropped.
+fn split_search_path(raw: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut chars = raw.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let mut name = String::new();
+        let quoted = chars.next_if_eq(&'"').is_some();
+        if quoted {
+            loop {
+                match chars.next() {
+                    Some('"') if chars.peek() == Some(&'"') => {
+                        chars.next();
+                        name.push('"');
+                    }
+                    Some('"') | None => break,
+                    Some(c) => name.push(c),
+                }
+            }
+            while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        }
+        // Anything else up to the separator belongs to the name as written.
+        while let Some(c) = chars.next_if(|c| *c != ',') {
+            name.push(c);
+        }
+        chars.next();
+        let name = if quoted {
+            name
+        } else {
+            name.trim_end().to_string()
+        };
+        if !name.is_empty() {
+            names.push(name);
+        }
+        if chars.peek().is_none() {
+            return names;
+        }
+    }
+}
+
+/// Take the transaction-level advisory lock of a job's `resource_key`.
+///
+/// The lock is keyed on a hash of the key, so jobs sharing it never run at
+/// the same time, whichever scheduler runs them; it is released by the
+/// COMMIT or ROLLBACK ending the run. A busy key is logged before waiting.
+fn lock_resource(
+    client: &mut Client,
+    config: &Config,
+    jobid: i64,
+    key: &str,
+) -> Result<(), RunError> {
+    let lock_key = if config.schema == DEFAULT_SCHEMA {
+        key.to_string()
+    } else {
+        format!("{}.{key}", config.schema)
+    };
+    let locked: bool = client
+        .query_one(
+            "SELECT pg_try_advisory_xact_lock(hashtextextended($1, 0))",
+            &[&lock_key],
+        )
+        .map_err(|err| RunError::from_error(&err))?
+        .get(0);
+    if locked {
+        return Ok(());
+    }
+    dlog!(
+        config,
+        "LOG",
+        "job {} waiting for resource_key \"{}\" held by another job",
+        jobid,
+        key
+    );
+    client
+        .execute(
+            "SELECT pg_advisory_xact_lock(hashtextextended($1, 0))",
+            &[&lock_key],
+        )
+        .map(|_| ())
+        .map_err(|err| RunError::from_error(&err))
+}
+
+/// Read the value the job published through [`RESULT_SETTING`], if any.
+///
+/// Must run inside the job's transaction, before COMMIT, so values set with
+/// `set_config(..., true)` (transaction-local) are still visible. An empty
+/// string means the job did not publish anything.
+fn read_job_result(client: &mut Client, config: &Config, jobid: i64) -> Option<String> {
+    match client.query_one("SELECT current_setting($1, true)", &[&RESULT_SETTING]) {
+        Ok(row) => published_result(row.get::<_, Option<String>>(0)),
+        Err(err) => {
+            dlog!(
+                config,
+                "ERROR",
+                "failed to read result of job {}: {}",
+                jobid,
+                err
+            );
+            None
+        }
+    }
 }
 
-/// Quote a comma-separated list of schema names for use with SET search_path.
-/// Segments already wrapped in double quotes are passed through unchanged so
-/// that reserved placeholders like "$user" keep their special meaning.
-fn quote_search_path(raw: &str) -> String {
-    raw.split(',')
-        .map(|s| {
-            let s = s.trim();
-            if s.len() >= 2 && s.starts_with('"') && s.ends_with('"') {
-                s.to_string()
-            } else {
-                quote_ident(s)
-            }
-        })
-        .collect::<Vec<_>>()
-        .join(", ")
+/// Result of a run from the value of [`RESULT_SETTING`]: `None` when the
+/// setting is unknown to the session or was reset to an empty string at the
+/// start of the run.
+fn published_result(setting: Option<String>) -> Option<String> {
+    setting.filter(|value| !value.is_empty())
 }
 
 /// Data captured for job execution history.
@@ -445,82 +2380,444 @@ struct JobExecutionDetails<'a> {
     jobid: i64,
     start_date: &'a str,
     duration_secs: i64,
-    status_text: &'a str,
+    status: RunStatus,
     err_text: &'a str,
     sqlstate: &'a str,
+    diagnostics: Option<&'a ErrorDiagnostics>,
+    result: Option<&'a str>,
+    /// Messages raised by the job body, see [`take_job_notices`].
+    notices: &'a [String],
+    /// Exit code of the command of an executable job.
+    exit_code: Option<i32>,
+    /// Id of the run, `None` for a job refused before running.
+    run_id: Option<&'a str>,
+    /// Pid of the backend that ran the job, `None` when refused.
+    backend_pid: Option<i32>,
 }
 
-/// Store job execution details in the database.
-fn store_job_execution_details(
-    client: &mut Client,
-    config: &Config,
-    details: JobExecutionDetails<'_>,
-) {
-    let query = r#"
-    INSERT INTO dbms_job.all_scheduler_job_run_details
-        (owner, job_name, status, error, req_start_date, actual_start_date, run_duration, slave_pid, additional_info)
-    VALUES
-        ($1, $2, $3, $4::bigint, NULL,
-         to_timestamp($5, 'YYYY-MM-DD HH24:MI:SS'),
-         $6,
-         $7, $8)
-    "#;
-
-    let error_code: Option<i64> = details.sqlstate.parse::<i64>().ok();
-    let additional_info = if details.sqlstate.is_empty() {
-        details.err_text.to_string()
-    } else if details.err_text.is_empty() {
-        format!("sqlstate={}", details.sqlstate)
+impl JobExecutionDetails<'_> {
+    /// The run-details row of the run of a job of the job queue schema
+    /// `schema`.
+    fn to_run_details(&self, schema: &str) -> RunDetails {
+        let diagnostics = self.diagnostics.cloned().unwrap_or_default();
+        RunDetails {
+            owner: self.owner.to_string(),
+            jobid: self.jobid,
+            status: self.status.details_status(),
+            error_code: self
+                .exit_code
+                .map(i64::from)
+                .or_else(|| self.sqlstate.parse::<i64>().ok()),
+            start_date: self.start_date.to_string(),
+            duration_secs: self.duration_secs,
+            backend_pid: self.backend_pid,
+            additional_info: additional_info(self.sqlstate, self.err_text, self.notices),
+            result: self.result.map(str::to_string),
+            error_message: diagnostics.message,
+            error_detail: diagnostics.detail,
+            error_hint: diagnostics.hint,
+            error_context: diagnostics.context,
+            run_id: self.run_id.map(str::to_string),
+            scheduler_host: host_name(),
+            job_schema: schema.to_string(),
+        }
+    }
+}
+
+/// `additional_info` of a run: its error, then the messages its body
+/// raised, one per line.
+fn additional_info(sqlstate: &str, err_text: &str, notices: &[String]) -> String {
+    let mut info = if sqlstate.is_empty() {
+        err_text.to_string()
+    } else if err_text.is_empty() {
+        format!("sqlstate={sqlstate}")
     } else {
-        format!("sqlstate={}, {}", details.sqlstate, details.err_text)
+        format!("sqlstate={sqlstate}, {err_text}")
     };
-
-    if let Err(err) = client.execute(
-        query,
-        &[
-            &details.owner,
-            &details.jobid.to_string(),
-            &details.status_text,
-            &error_code, // parameter 3 / $4
-            &details.start_date,
-            &details.duration_secs, // bigint
-            &(process::id() as i32),
-            &additional_info,
-        ],
-    ) {
-        if let Some(db) = err.as_db_error() {
-            dlog!(
-                config,
-                "ERROR",
-                "failed to store job execution details for job {}: code={} message={} detail={:?} hint={:?}",
-                details.jobid,
-                db.code().code(),
-                db.message(),
-                db.detail(),
-                db.hint()
-            );
-        } else {
-            dlog!(
-                config,
-                "ERROR",
-                "failed to store job execution details for job {}: {}",
-                details.jobid,
-                err
-            );
+    for notice in notices {
+        if !info.is_empty() {
+            info.push('\n');
         }
+        info.push_str(notice);
     }
+    info
 }
 
 /// Build a DO block wrapper for the job body.
+///
+/// The body runs in an inner block whose exception handler rolls back what
+/// it did and copies the stacked diagnostics of its error (SQLSTATE,
+/// message, detail, hint and the context down to the failing line) into
+/// the `pg_dbms_job.error_*` settings, local to the transaction of the
+/// run, see [`read_trapped_error`]. `WHEN OTHERS` does not trap
+/// `query_canceled`, so timeouts and cancellations still reach the
+/// scheduler as server errors.
 fn build_do_block(jobid: i64, what: &str) -> String {
     format!(
-        "DO $pg_dbms_job$\nDECLARE\n\tjob bigint := {jobid};\n\tnext_date timestamp with time zone := current_timestamp;\n\tbroken boolean := false;\nBEGIN\n\t{what}\nEND;\n$pg_dbms_job$;"
+        "DO $pg_dbms_job$\n{}\n$pg_dbms_job$;",
+        plpgsql_block(jobid, what)
+    )
+}
+
+/// PL/pgSQL block of the job body, declaring the `job`, `next_date` and
+/// `broken` variables of the Oracle DBMS_JOB job environment, with the
+/// exception handler described in [`build_do_block`].
+fn plpgsql_block(jobid: i64, what: &str) -> String {
+    let p = ERROR_SETTING_PREFIX;
+    format!(
+        "DECLARE\n\tjob bigint := {jobid};\n\tnext_date timestamp with time zone := current_timestamp;\n\tbroken boolean := false;\nBEGIN\n\tBEGIN\n\t{what}\n\tEXCEPTION WHEN OTHERS THEN\n\t\tDECLARE\n\t\t\te_detail text;\n\t\t\te_hint text;\n\t\t\te_context text;\n\t\tBEGIN\n\t\t\tGET STACKED DIAGNOSTICS e_detail = PG_EXCEPTION_DETAIL, e_hint = PG_EXCEPTION_HINT, e_context = PG_EXCEPTION_CONTEXT;\n\t\t\tPERFORM set_config('{p}sqlstate', SQLSTATE, true), set_config('{p}message', SQLERRM, true), set_config('{p}detail', e_detail, true), set_config('{p}hint', e_hint, true), set_config('{p}context', e_context, true);\n\t\tEND;\n\tEND;\nEND;"
+    )
+}
+
+/// Temporary function running the job body in `security_definer` mode.
+const DEFINER_FUNCTION: &str = "pg_temp.pg_dbms_job_run()";
+
+/// Build the statements running the job body through a SECURITY DEFINER
+/// function created by `owner` in the session's temporary schema.
+///
+/// The scheduler only switches to the owner to create the function, then
+/// calls it under its own role. The server refuses `SET ROLE`, `RESET ROLE`
+/// and `SET SESSION AUTHORIZATION` inside a SECURITY DEFINER function, so
+/// the body can not take back the scheduler's privileges the way it can
+/// from the DO block run after a `SET ROLE`. The function is created in the
+/// job's transaction and goes away with its commit or rollback.
+fn build_definer_call(jobid: i64, what: &str, owner: &str) -> String {
+    format!(
+        "SET LOCAL ROLE {};\nCREATE OR REPLACE FUNCTION {DEFINER_FUNCTION} RETURNS void LANGUAGE plpgsql SECURITY DEFINER AS $pg_dbms_job$\n{}\n$pg_dbms_job$;\nRESET ROLE;\nSELECT {DEFINER_FUNCTION};\nDROP FUNCTION {DEFINER_FUNCTION};",
+        quote_ident(owner),
+        plpgsql_block(jobid, what)
     )
 }
 
+/// Run the command of an executable job, see [`crate::executable`], once
+/// its owner is found in `executable_job_owners` and its `os_user`, if
+/// any, in `executable_os_users`.
+///
+/// The transaction of the run stays open while the command runs, so the
+/// server's `idle_in_transaction_session_timeout` is lifted for it.
+fn run_executable(
+    client: &mut Client,
+    config: &Config,
+    job: &Job,
+    run_id: &str,
+    limit: Option<(&'static str, Duration)>,
+    heartbeat: &Heartbeat<'_>,
+) -> Result<CommandRun, RunError> {
+    if !owner_allowed(config, job.log_user.as_deref()) {
+        return Err(RunError::from_text(
+            format!(
+                "EXECUTABLE jobs of role {} are not allowed, see executable_job_owners",
+                job.log_user.as_deref().unwrap_or("(none)")
+            ),
+            "42501",
+        ));
+    }
+    if let Some(os_user) = job.os_user.as_deref()
+        && !os_user_allowed(config, os_user)
+    {
+        return Err(RunError::from_text(
+            format!("EXECUTABLE jobs can not run as os_user {os_user}, see executable_os_users"),
+            "42501",
+        ));
+    }
+    client
+        .batch_execute("SET LOCAL idle_in_transaction_session_timeout TO 0")
+        .map_err(|err| RunError::from_error(&err))?;
+    dlog!(config, "DEBUG", "command to execute: {}", job.what);
+    run_command(job, run_id, limit.map(|(_, limit)| limit), || {
+        heartbeat.beat("execute")
+    })
+    .map_err(|text| RunError::from_text(text, ""))
+}
+
+/// Run a job body, or a statement of a continue-on-error job, in a DO
+/// block or, given a `definer`, through a SECURITY DEFINER function of that
+/// role.
+fn run_body(
+    client: &mut Client,
+    config: &Config,
+    jobid: i64,
+    what: &str,
+    definer: Option<&str>,
+) -> Result<(), RunError> {
+    let code = match definer {
+        Some(owner) => build_definer_call(jobid, what, owner),
+        None => build_do_block(jobid, what),
+    };
+    dprint(config, "DEBUG", &code);
+    client
+        .batch_execute(&code)
+        .map_err(|err| RunError::from_error(&err))?;
+    match read_trapped_error(client)? {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
+
+/// Read the error the exception handler of the job body stored in the
+/// `pg_dbms_job.error_*` settings, if any, see [`build_do_block`].
+///
+/// The settings are local to the transaction, or to the savepoint of a
+/// continue-on-error statement, so they go away with the rollback of the
+/// failed run or statement.
+fn read_trapped_error(client: &mut Client) -> Result<Option<RunError>, RunError> {
+    let row = client
+        .query_one(
+            "SELECT nullif(current_setting($1 || 'sqlstate', true), ''), \
+                    nullif(current_setting($1 || 'message', true), ''), \
+                    nullif(current_setting($1 || 'detail', true), ''), \
+                    nullif(current_setting($1 || 'hint', true), ''), \
+                    nullif(current_setting($1 || 'context', true), '')",
+            &[&ERROR_SETTING_PREFIX],
+        )
+        .map_err(|err| RunError::from_error(&err))?;
+    Ok(row.get::<_, Option<String>>(0).map(|sqlstate| {
+        RunError::from_diagnostics(
+            sqlstate,
+            ErrorDiagnostics {
+                message: row.get(1),
+                detail: row.get(2),
+                hint: row.get(3),
+                context: row.get(4),
+            },
+        )
+    }))
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{build_do_block, quote_ident, quote_search_path};
+    use super::{
+        DeadlineAction, ErrorDiagnostics, Heartbeat, JobExecutionDetails, MAX_LISTED_BROKEN_JOBS,
+        RUN_CANCEL_GRACE, RequeueQueue, RunDeadline, RunDeadlines, RunError, StatementFailure,
+        WorkerBeats, additional_info, build_definer_call, build_do_block, claim_limit, claim_query,
+        describe_statement_failures, failure_status, maintenance_message, max_failures_reached,
+        max_failures_reason, outside_transaction_command, overrun_reason, owner_channel,
+        published_result, quote_ident, quote_search_path, run_limit, skipped_broken_message,
+        split_statements, with_error_context,
+    };
+    use crate::model::{JobKind, RunStatus};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn additional_info_appends_job_notices() {
+        assert_eq!(additional_info("", "", &[]), "");
+        assert_eq!(
+            additional_info("23505", "ERROR: duplicate key", &[]),
+            "sqlstate=23505, ERROR: duplicate key"
+        );
+        let notices = vec![
+            "NOTICE: purged 12 rows".to_string(),
+            "WARNING: nothing to vacuum".to_string(),
+        ];
+        assert_eq!(
+            additional_info("", "", &notices),
+            "NOTICE: purged 12 rows\nWARNING: nothing to vacuum"
+        );
+        assert_eq!(
+            additional_info("P0001", "", &notices[..1]),
+            "sqlstate=P0001\nNOTICE: purged 12 rows"
+        );
+    }
+
+    #[test]
+    fn the_published_result_is_stored_with_the_run() {
+        // Unknown to the session, or reset at the start of the run.
+        assert_eq!(published_result(None), None);
+        assert_eq!(published_result(Some(String::new())), None);
+
+        let result = published_result(Some("42".to_string()));
+        let details = JobExecutionDetails {
+            owner: "app",
+            jobid: 7,
+            start_date: "2026-03-05 09:07:02",
+            duration_secs: 1,
+            status: RunStatus::Succeeded,
+            err_text: "",
+            sqlstate: "",
+            diagnostics: None,
+            result: result.as_deref(),
+            notices: &[],
+            exit_code: None,
+            run_id: Some("run-7"),
+            backend_pid: Some(4242),
+        };
+        assert_eq!(
+            details.to_run_details("dbms_job").result.as_deref(),
+            Some("42")
+        );
+    }
+
+    #[test]
+    fn worker_beats_report_stalled_workers_until_they_end() {
+        let beats = WorkerBeats::default();
+        let start = Instant::now();
+        {
+            let heartbeat = Heartbeat {
+                beats: &beats,
+                worker: 7,
+                kind: JobKind::Scheduled,
+                job: 42,
+            };
+            heartbeat.beat("execute");
+            assert!(beats.stalled(start, Duration::from_secs(60)).is_empty());
+            let later = Instant::now() + Duration::from_secs(60);
+            let stalled = beats.stalled(later, Duration::from_secs(60));
+            assert_eq!(stalled.len(), 1);
+            assert_eq!(stalled[0].0, 7);
+            assert_eq!((stalled[0].1.job, stalled[0].1.step), (42, "execute"));
+        }
+        // The end of the worker forgets its beat.
+        let later = Instant::now() + Duration::from_secs(60);
+        assert!(beats.stalled(later, Duration::from_secs(60)).is_empty());
+    }
+
+    #[test]
+    fn runs_past_their_max_run_duration_are_canceled_then_terminated() {
+        let deadlines = RunDeadlines::default();
+        let start = Instant::now();
+        let run = RunDeadline {
+            kind: JobKind::Async,
+            job: 42,
+            backend_pid: 1234,
+            setting: "max_run_duration",
+            limit: Duration::from_secs(60),
+            started: start,
+            cancelled: None,
+            terminated: false,
+        };
+        deadlines.start(7, run);
+        deadlines.start(8, RunDeadline { job: 43, ..run });
+        assert!(deadlines.take_due(start).is_empty());
+
+        let late = start + Duration::from_secs(60);
+        let due = deadlines.take_due(late);
+        assert_eq!(
+            due.iter().map(|(r, a)| (r.job, *a)).collect::<Vec<_>>(),
+            vec![(42, DeadlineAction::Cancel), (43, DeadlineAction::Cancel)]
+        );
+        // Canceled once, terminated after the grace, then left alone.
+        assert!(deadlines.take_due(late).is_empty());
+        // Run 43 ended with the cancel.
+        assert!(deadlines.finish(8).is_some());
+        let due = deadlines.take_due(late + RUN_CANCEL_GRACE);
+        assert_eq!(due.len(), 1);
+        assert_eq!((due[0].0.job, due[0].1), (42, DeadlineAction::Terminate));
+        assert!(deadlines.take_due(late + RUN_CANCEL_GRACE * 2).is_empty());
+        assert!(deadlines.finish(7).is_some());
+
+        // A run ending in time was not stopped.
+        deadlines.start(9, run);
+        assert!(deadlines.finish(9).is_none());
+        assert!(deadlines.finish(9).is_none());
+    }
+
+    #[test]
+    fn run_limit_is_the_shorter_of_the_job_and_global_ones() {
+        let minute = Duration::from_secs(60);
+        assert_eq!(run_limit(None, 0.0), None);
+        assert_eq!(
+            run_limit(Some(minute), 0.0),
+            Some(("max_run_duration", minute))
+        );
+        assert_eq!(
+            run_limit(None, 3600.0),
+            Some(("job_run_timeout", Duration::from_secs(3600)))
+        );
+        assert_eq!(
+            run_limit(Some(minute), 3600.0),
+            Some(("max_run_duration", minute))
+        );
+        assert_eq!(
+            run_limit(Some(minute), 30.0),
+            Some(("job_run_timeout", Duration::from_secs(30)))
+        );
+    }
+
+    #[test]
+    fn failure_status_tells_timeouts_and_cancellations_apart() {
+        let timeout = Some("canceling statement due to statement timeout");
+        assert_eq!(failure_status("57014", timeout), RunStatus::Timeout);
+        let lock = Some("canceling statement due to lock timeout");
+        assert_eq!(failure_status("55P03", lock), RunStatus::Timeout);
+        assert_eq!(failure_status("25P03", None), RunStatus::Timeout);
+        let cancel = Some("canceling statement due to user request");
+        assert_eq!(failure_status("57014", cancel), RunStatus::Cancelled);
+        assert_eq!(failure_status("57P01", None), RunStatus::Cancelled);
+        // A row lock not obtained with NOWAIT is a plain failure.
+        let nowait = Some("could not obtain lock on row in relation \"t\"");
+        assert_eq!(failure_status("55P03", nowait), RunStatus::Failed);
+        assert_eq!(
+            failure_status("22012", Some("division by zero")),
+            RunStatus::Failed
+        );
+        assert_eq!(failure_status("", None), RunStatus::Failed);
+    }
+
+    #[test]
+    fn claim_query_caps_classes_and_semaphores_only_when_there_are_some() {
+        let plain = claim_query(
+            "all_async_jobs",
+            "this_date = now()",
+            "true",
+            "priority, job",
+            0,
+            None,
+        );
+        assert!(plain.starts_with("UPDATE dbms_job.all_async_jobs SET this_date = now()"));
+        assert!(plain.contains("ORDER BY priority, job LIMIT $3 FOR UPDATE SKIP LOCKED"));
+        assert!(!plain.contains("classes"));
+        assert!(!plain.contains("semaphores"));
+        assert!(!plain.contains("octet_length"));
+
+        let capped = claim_query(
+            "all_async_jobs",
+            "this_date = now()",
+            "true",
+            "priority, job",
+            1024,
+            Some(5),
+        );
+        assert!(capped.contains("unnest($5::text[], $6::bigint[])"));
+        assert!(capped.contains("PARTITION BY job_class ORDER BY priority, job"));
+        assert!(capped.contains("WHERE (c.name IS NULL OR due.class_rank <= c.free)"));
+        // A semaphore caps the running jobs of its resource key the same way.
+        assert!(capped.contains("unnest($7::text[], $8::bigint[])"));
+        assert!(capped.contains("PARTITION BY resource_key ORDER BY priority, job"));
+        assert!(capped.contains("AND (s.name IS NULL OR due.key_rank <= s.free)"));
+        assert!(capped.contains("FOR UPDATE OF j SKIP LOCKED"));
+        // Oversized bodies wait for their refusal.
+        assert!(capped.contains("AND octet_length(what) <= 1024) UPDATE"));
+    }
+
+    #[test]
+    fn claimed_copies_complete_the_dependencies_on_their_source() {
+        let async_claim = claim_query(
+            "all_async_jobs",
+            "this_date = now()",
+            "true",
+            "job",
+            0,
+            None,
+        );
+        assert!(async_claim.ends_with("coalesce(source_job, job) AS source_job"));
+        let scheduled_claim = claim_query(
+            "all_scheduled_jobs",
+            "this_date = now()",
+            "true",
+            "job",
+            0,
+            Some(4),
+        );
+        assert!(scheduled_claim.ends_with(", job AS source_job"));
+    }
+
+    #[test]
+    fn claim_limit_saturates() {
+        assert_eq!(claim_limit(0), 0);
+        assert_eq!(claim_limit(8), 8);
+        // --single claims without limit.
+        assert_eq!(claim_limit(usize::MAX), i64::MAX);
+    }
 
     #[test]
     fn build_do_block_includes_job_and_code() {
@@ -542,6 +2839,54 @@ mod tests {
         assert!(block.contains("broken boolean := false"));
     }
 
+    #[test]
+    fn build_do_block_traps_the_error_into_settings() {
+        let block = build_do_block(1, "PERFORM 1/0;");
+        let body = block.find("PERFORM 1/0;").unwrap();
+        let handler = block.find("EXCEPTION WHEN OTHERS THEN").unwrap();
+        assert!(block[..body].ends_with("BEGIN\n\tBEGIN\n\t"));
+        assert!(body < handler);
+        assert!(block[handler..].contains("GET STACKED DIAGNOSTICS"));
+        for field in ["sqlstate", "message", "detail", "hint", "context"] {
+            assert!(block[handler..].contains(&format!("set_config('pg_dbms_job.error_{field}'")));
+        }
+        // Never re-raised: the settings would be rolled back with the error.
+        assert!(!block[handler..].contains("RAISE"));
+    }
+
+    #[test]
+    fn trapped_errors_read_like_server_errors() {
+        let error = RunError::from_diagnostics(
+            "22012".to_string(),
+            ErrorDiagnostics {
+                message: Some("division by zero".to_string()),
+                detail: None,
+                hint: Some("check the divisor".to_string()),
+                context: Some("PL/pgSQL function inline_code_block line 7 at PERFORM".to_string()),
+            },
+        );
+        assert_eq!(error.sqlstate, "22012");
+        assert_eq!(
+            error.text,
+            "db error: ERROR: division by zero\nHINT: check the divisor\nCONTEXT: PL/pgSQL function inline_code_block line 7 at PERFORM"
+        );
+        assert_eq!(error.diagnostics.detail, None);
+    }
+
+    #[test]
+    fn build_definer_call_creates_the_function_as_the_owner() {
+        let call = build_definer_call(42, "RAISE NOTICE 'hello';", "app\"user");
+        assert!(call.starts_with("SET LOCAL ROLE \"app\"\"user\";\n"));
+        assert!(call.contains("FUNCTION pg_temp.pg_dbms_job_run() RETURNS void"));
+        assert!(call.contains("SECURITY DEFINER"));
+        assert!(call.contains("job bigint := 42"));
+        assert!(call.contains("RAISE NOTICE 'hello';"));
+        // Called and dropped once the scheduler is back to its own role.
+        let reset = call.find("RESET ROLE;").unwrap();
+        assert!(call[reset..].contains("SELECT pg_temp.pg_dbms_job_run();"));
+        assert!(call.ends_with("DROP FUNCTION pg_temp.pg_dbms_job_run();"));
+    }
+
     #[test]
     fn build_do_block_negative_jobid() {
         let block = build_do_block(-1, "SELECT 1;");
@@ -625,10 +2970,59 @@ mod tests {
         );
     }
 
+    #[test]
+    fn quote_search_path_three_elements() {
+        assert_eq!(
+            quote_search_path("app, extensions, public"),
+            "\"app\", \"extensions\", \"public\""
+        );
+    }
+
+    #[test]
+    fn quote_search_path_quoted_name_with_comma_and_quote() {
+        assert_eq!(
+            quote_search_path("\"a,b\", \"x\"\"y\""),
+            "\"a,b\", \"x\"\"y\""
+        );
+    }
+
+    #[test]
+    fn quote_search_path_requotes_quoted_injection_attempt() {
+        // Starts and ends with a double quote but is not a single identifier:
+        // it must not be passed through to SET verbatim.
+        let result = quote_search_path("\"a\"; DROP TABLE users; \"b\"");
+        assert_eq!(result, "\"a; DROP TABLE users; \"\"b\"\"\"");
+    }
+
+    #[test]
+    fn build_definer_call_quotes_a_hostile_owner() {
+        let call = build_definer_call(1, "NULL;", "foo; DROP TABLE x; '\"--");
+        assert!(call.starts_with("SET LOCAL ROLE \"foo; DROP TABLE x; '\"\"--\";\n"));
+    }
+
+    #[test]
+    fn quote_search_path_keeps_hostile_names_in_identifiers() {
+        assert_eq!(
+            quote_search_path("foo; DROP TABLE x, $user"),
+            "\"foo; DROP TABLE x\", \"$user\""
+        );
+        assert_eq!(
+            quote_search_path("\"x\"\" TO public; RESET ALL; --\""),
+            "\"x\"\" TO public; RESET ALL; --\""
+        );
+        assert_eq!(quote_search_path("it's"), "\"it's\"");
+    }
+
+    #[test]
+    fn quote_search_path_skips_empty_elements() {
+        assert_eq!(quote_search_path("app,, public,"), "\"app\", \"public\"");
+        assert_eq!(quote_search_path(""), "");
+    }
+
     #[test]
     fn build_do_block_empty_what() {
         let block = build_do_block(1, "");
-        assert!(block.contains("BEGIN\n\t\nEND;"));
+        assert!(block.contains("\tBEGIN\n\t\n\tEXCEPTION"));
     }
 
     #[test]
@@ -671,4 +3065,239 @@ mod tests {
         let result = quote_ident("a\nb");
         assert_eq!(result, "\"a\nb\"");
     }
+
+    #[test]
+    fn owner_channel_fits_an_identifier() {
+        assert_eq!(
+            owner_channel("dbms_job_broken", "app_user"),
+            "dbms_job_broken_app_user"
+        );
+        let long = "é".repeat(40);
+        let channel = owner_channel("dbms_job_broken", &long);
+        assert!(channel.len() <= 63);
+        assert!(channel.starts_with("dbms_job_broken_é"));
+    }
+
+    #[test]
+    fn skipped_broken_message_lists_jobs_and_failures() {
+        assert_eq!(
+            skipped_broken_message(&[(3, 16), (7, 0)]),
+            "skipping 2 due jobs marked broken: job 3 (16 failures), job 7 (0 failures)"
+        );
+        assert_eq!(
+            skipped_broken_message(&[(5, 1)]),
+            "skipping 1 due job marked broken: job 5 (1 failures)"
+        );
+    }
+
+    #[test]
+    fn skipped_broken_message_truncates_long_lists() {
+        let jobs: Vec<(i64, i64)> = (1..=25).map(|j| (j, 0)).collect();
+        let msg = skipped_broken_message(&jobs);
+        assert!(msg.starts_with("skipping 25 due jobs marked broken: job 1 (0 failures)"));
+        assert!(msg.contains(&format!("job {MAX_LISTED_BROKEN_JOBS} (0 failures)")));
+        assert!(!msg.contains("job 21 "));
+        assert!(msg.ends_with(" and 5 more"));
+    }
+
+    #[test]
+    fn with_error_context_appends_context_line() {
+        assert_eq!(
+            with_error_context(
+                "db error: ERROR: division by zero".to_string(),
+                "PL/pgSQL function inline_code_block line 6 at assignment"
+            ),
+            "db error: ERROR: division by zero\nCONTEXT: PL/pgSQL function inline_code_block line 6 at assignment"
+        );
+    }
+
+    #[test]
+    fn maintenance_message_includes_reason() {
+        assert_eq!(
+            maintenance_message(true, Some("pg_upgrade")),
+            "maintenance mode enabled, job dispatch paused: pg_upgrade"
+        );
+        assert_eq!(
+            maintenance_message(true, Some("  ")),
+            "maintenance mode enabled, job dispatch paused"
+        );
+        assert_eq!(
+            maintenance_message(false, Some("pg_upgrade")),
+            "maintenance mode disabled, resuming job dispatch"
+        );
+    }
+
+    #[test]
+    fn max_failures_default_to_the_scheduler_setting() {
+        assert_eq!(
+            max_failures_reached("$5"),
+            "(coalesce(max_failures, $5) > 0 AND coalesce(failures, 0) + 1 >= coalesce(max_failures, $5))"
+        );
+        assert_eq!(
+            max_failures_reason(16),
+            "marked broken after 16 consecutive failed runs, reaching max_failures"
+        );
+    }
+
+    #[test]
+    fn overrun_reason_names_policy() {
+        assert_eq!(
+            overrun_reason(3, 900.0),
+            "marked broken after 3 consecutive runs longer than hard_max_run_duration (900 seconds)"
+        );
+        assert!(overrun_reason(1, 1.5).contains("(1.5 seconds)"));
+    }
+
+    #[test]
+    fn split_statements_on_top_level_semicolons() {
+        assert_eq!(
+            split_statements("DELETE FROM a;\n  ANALYZE b ;\n\nDELETE FROM c"),
+            vec!["DELETE FROM a", "ANALYZE b", "DELETE FROM c"]
+        );
+        assert!(split_statements(" ; ;\n").is_empty());
+    }
+
+    #[test]
+    fn split_statements_ignores_quoted_and_commented_semicolons() {
+        let body = "INSERT INTO t VALUES ('a;b', 'it''s;');\n\
+                    SELECT \"odd;name\" FROM t; -- trailing; comment\n\
+                    /* block; comment */ SELECT 1";
+        assert_eq!(
+            split_statements(body),
+            vec![
+                "INSERT INTO t VALUES ('a;b', 'it''s;')",
+                "SELECT \"odd;name\" FROM t",
+                "-- trailing; comment\n/* block; comment */ SELECT 1",
+            ]
+        );
+    }
+
+    #[test]
+    fn split_statements_keeps_escape_strings() {
+        let body = "INSERT INTO t VALUES (E'a\\';b', e'c\\\\'); SELECT E'it''s;'";
+        assert_eq!(
+            split_statements(body),
+            vec![
+                "INSERT INTO t VALUES (E'a\\';b', e'c\\\\')",
+                "SELECT E'it''s;'",
+            ]
+        );
+    }
+
+    #[test]
+    fn split_statements_keeps_plpgsql_blocks() {
+        let body = "IF NOT EXISTS (SELECT 1 FROM a) THEN\n\
+                        INSERT INTO a VALUES (1);\n\
+                    ELSIF found THEN\n\
+                        RAISE NOTICE 'found';\n\
+                    END IF;\n\
+                    FOR r IN SELECT id FROM b LOOP\n\
+                        DELETE FROM c WHERE id = r.id;\n\
+                        EXIT WHEN r.id > 10;\n\
+                    END LOOP;\n\
+                    DROP TABLE IF EXISTS d;\n\
+                    DECLARE n int; BEGIN\n\
+                        SELECT CASE WHEN x THEN 1 ELSE 2 END INTO n FROM e;\n\
+                        CASE n WHEN 1 THEN PERFORM f(); ELSE NULL; END CASE;\n\
+                    EXCEPTION WHEN others THEN\n\
+                        NULL;\n\
+                    END;\n\
+                    <<outer>> LOOP EXIT outer; END LOOP outer;\n\
+                    UPDATE g SET done = true";
+        let statements = split_statements(body);
+        assert_eq!(statements.len(), 6, "{statements:#?}");
+        assert!(statements[0].starts_with("IF NOT EXISTS") && statements[0].ends_with("END IF"));
+        assert!(statements[1].starts_with("FOR r") && statements[1].ends_with("END LOOP"));
+        assert_eq!(statements[2], "DROP TABLE IF EXISTS d");
+        assert!(statements[3].starts_with("DECLARE n int;") && statements[3].ends_with("END"));
+        assert_eq!(statements[4], "<<outer>> LOOP EXIT outer; END LOOP outer");
+        assert_eq!(statements[5], "UPDATE g SET done = true");
+    }
+
+    #[test]
+    fn statements_outside_a_transaction_block_are_named() {
+        for (statement, command) in [
+            ("VACUUM (ANALYZE) b", "VACUUM"),
+            ("-- nightly\nvacuum b", "VACUUM"),
+            ("CREATE DATABASE x", "CREATE DATABASE"),
+            (
+                "CREATE UNIQUE INDEX CONCURRENTLY i ON t (a)",
+                "CREATE ... CONCURRENTLY",
+            ),
+            (
+                "REINDEX (VERBOSE) TABLE CONCURRENTLY t",
+                "REINDEX ... CONCURRENTLY",
+            ),
+            ("ALTER SYSTEM SET work_mem = '8MB'", "ALTER SYSTEM"),
+            ("COMMIT", "COMMIT"),
+        ] {
+            assert_eq!(
+                outside_transaction_command(statement).as_deref(),
+                Some(command)
+            );
+        }
+        for statement in [
+            "ANALYZE b",
+            "CREATE INDEX i ON t (a)",
+            "DELETE FROM vacuum_log",
+        ] {
+            assert_eq!(outside_transaction_command(statement), None, "{statement}");
+        }
+    }
+
+    #[test]
+    fn split_statements_keeps_dollar_quoted_bodies() {
+        let body = "EXECUTE $q$DELETE FROM a; DELETE FROM b$q$; PERFORM f($$x;y$$, $1)";
+        assert_eq!(
+            split_statements(body),
+            vec![
+                "EXECUTE $q$DELETE FROM a; DELETE FROM b$q$",
+                "PERFORM f($$x;y$$, $1)",
+            ]
+        );
+    }
+
+    #[test]
+    fn describe_statement_failures_lists_each_statement() {
+        let failures = vec![
+            StatementFailure {
+                index: 2,
+                error: RunError {
+                    text: "lock timeout".to_string(),
+                    sqlstate: "55P03".to_string(),
+                    diagnostics: Box::default(),
+                },
+            },
+            StatementFailure {
+                index: 4,
+                error: RunError {
+                    text: "relation \"t\" does not exist".to_string(),
+                    sqlstate: "42P01".to_string(),
+                    diagnostics: Box::default(),
+                },
+            },
+        ];
+        assert_eq!(
+            describe_statement_failures(&failures),
+            "statement 2: lock timeout; statement 4: relation \"t\" does not exist"
+        );
+    }
+
+    #[test]
+    fn requeue_queue_releases_jobs_after_delay() {
+        use crate::model::JobKind;
+        use std::time::{Duration, Instant};
+
+        let queue = RequeueQueue::default();
+        queue.push(JobKind::Async, 7);
+        queue.push(JobKind::Scheduled, 8);
+        let delay = Duration::from_millis(500);
+
+        assert!(queue.take_due(Instant::now(), delay).is_empty());
+        let due = queue.take_due(Instant::now() + delay, delay);
+        assert_eq!(due.len(), 2);
+        assert!(matches!(due[0], (JobKind::Async, 7)));
+        assert!(matches!(due[1], (JobKind::Scheduled, 8)));
+        assert!(queue.take_due(Instant::now() + delay, delay).is_empty());
+    }
 }
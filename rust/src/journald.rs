@@ -0,0 +1,49 @@
+//! journald output of the log writer.
+//!
+//! With `log_destination = journald` each log line is sent to the journal
+//! as one entry of the native protocol: a datagram of `FIELD=value` lines
+//! on the journald socket. Besides `MESSAGE`, `PRIORITY` and
+//! `SYSLOG_IDENTIFIER`, the entry carries the level of the line as `LEVEL`
+//! and, for a line logged while a worker runs a job, the job as `JOBID`, the
+//! id of the run as `RUN_ID` and the seconds since it started as
+//! `DURATION`, so that `journalctl JOBID=42` lists everything about a job.
+
+/// Encode a journal entry. A value containing a newline is written in the
+/// binary form of the protocol: the name, a newline, the length of the
+/// value as a little-endian `u64`, then the value.
+pub fn entry(fields: &[(&str, &str)]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    for (name, value) in fields {
+        buf.extend_from_slice(name.as_bytes());
+        if value.contains('\n') {
+            buf.push(b'\n');
+            buf.extend_from_slice(&(value.len() as u64).to_le_bytes());
+        } else {
+            buf.push(b'=');
+        }
+        buf.extend_from_slice(value.as_bytes());
+        buf.push(b'\n');
+    }
+    buf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::entry;
+
+    #[test]
+    fn entry_encodes_fields() {
+        assert_eq!(
+            entry(&[("MESSAGE", "ERROR: boom"), ("JOBID", "42")]),
+            b"MESSAGE=ERROR: boom\nJOBID=42\n"
+        );
+    }
+
+    #[test]
+    fn entry_encodes_multiline_values_in_binary() {
+        let mut expected = b"MESSAGE\n".to_vec();
+        expected.extend_from_slice(&3u64.to_le_bytes());
+        expected.extend_from_slice(b"a\nb\nLEVEL=LOG\n");
+        assert_eq!(entry(&[("MESSAGE", "a\nb"), ("LEVEL", "LOG")]), expected);
+    }
+}
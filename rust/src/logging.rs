@@ -5,17 +5,26 @@
 //! per batch.  This avoids per-line open/close syscalls and eliminates
 //! interleaved output from concurrent worker threads.
 
-use crate::constants::LOG_CHANNEL_CAPACITY;
-use crate::model::Config;
-use chrono::Local;
+use crate::constants::{DEFAULT_SCHEMA, JOURNALD_SOCKETS, LOG_CHANNEL_CAPACITY, SYSLOG_SOCKETS};
+use crate::gelf::{self, GelfSink};
+use crate::journald;
+use crate::model::{Config, LogDestination, LogRotationAge, LogRotationCompress};
+use crate::syslog::{self, DatagramSocket};
+use chrono::{DateTime, Datelike, Local, Timelike};
+use nix::unistd::{Group, User};
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::VecDeque;
 use std::fs::{self, OpenOptions};
 use std::io::{BufWriter, Write};
-use std::os::unix::fs::MetadataExt;
-use std::path::Path;
-use std::process;
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::process::{self, Command};
 use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc;
-use std::time::Duration;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant, SystemTime};
 
 /// Command sent from any thread to the dedicated log writer.
 enum LogCmd {
@@ -24,10 +33,21 @@ enum LogCmd {
         line: String,
         fname: String,
         truncate_on_rotation: bool,
+        rotation: Rotation,
+        access: Access,
     },
     /// Flush all pending writes and send an ack (does not stop the thread).
-    #[cfg(test)]
     Flush(mpsc::Sender<()>),
+    /// A log line for the syslog socket, or the journald one with
+    /// `journal`, with the line written to stderr when it can not be
+    /// delivered.
+    Datagram {
+        journal: bool,
+        datagram: Vec<u8>,
+        line: String,
+    },
+    /// A GELF message for the `log_gelf_endpoint` of the line.
+    Gelf { endpoint: String, payload: Vec<u8> },
     /// Drop the persistent file handle so the next write re-opens the path.
     /// Used after external log rotation (e.g. logrotate + SIGHUP).
     Reopen,
@@ -35,6 +55,220 @@ enum LogCmd {
     Shutdown(mpsc::Sender<()>),
 }
 
+/// Rotation settings of the log file, sent along with each line so a
+/// reload applies to the next write.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Rotation {
+    /// Size (bytes) above which the file is rotated, `0` never.
+    size: u64,
+    /// Period after which the file is rotated.
+    age: LogRotationAge,
+    /// Rotated files kept, `<file>.1` being the most recent.
+    count: u32,
+    /// Compression of the rotated files.
+    compress: LogRotationCompress,
+}
+
+impl Rotation {
+    fn of(config: &Config) -> Self {
+        Rotation {
+            size: config.log_rotation_size,
+            age: config.log_rotation_age,
+            count: config.log_rotation_count,
+            compress: config.log_rotation_compress,
+        }
+    }
+}
+
+/// Mode and ownership given to a log file the writer thread creates.
+struct Access {
+    /// Permission bits, `0` leaving them to the umask.
+    mode: u32,
+    /// User name or id, empty to keep the daemon's.
+    owner: String,
+    /// Group name or id, empty to keep the daemon's.
+    group: String,
+}
+
+impl Access {
+    fn of(config: &Config) -> Self {
+        Access {
+            mode: config.logfile_mode,
+            owner: config.logfile_owner.clone(),
+            group: config.logfile_group.clone(),
+        }
+    }
+
+    /// Give the newly created `file` this mode and ownership.
+    fn apply(&self, file: &fs::File) -> Result<(), String> {
+        if self.mode != 0 {
+            file.set_permissions(fs::Permissions::from_mode(self.mode))
+                .map_err(|err| format!("can not set the mode of the log file, {err}"))?;
+        }
+        if self.owner.is_empty() && self.group.is_empty() {
+            return Ok(());
+        }
+        let uid = match self.owner.as_str() {
+            "" => None,
+            owner => Some(user_id(owner).ok_or(format!("unknown log file owner {owner}"))?),
+        };
+        let gid = match self.group.as_str() {
+            "" => None,
+            group => Some(group_id(group).ok_or(format!("unknown log file group {group}"))?),
+        };
+        std::os::unix::fs::fchown(file, uid, gid)
+            .map_err(|err| format!("can not change the owner of the log file, {err}"))
+    }
+}
+
+/// The id of the user named or numbered `name`.
+pub fn user_id(name: &str) -> Option<u32> {
+    if let Ok(id) = name.parse::<u32>() {
+        return Some(id);
+    }
+    User::from_name(name)
+        .ok()
+        .flatten()
+        .map(|user| user.uid.as_raw())
+}
+
+/// The id of the group named or numbered `name`.
+pub fn group_id(name: &str) -> Option<u32> {
+    if let Ok(id) = name.parse::<u32>() {
+        return Some(id);
+    }
+    Group::from_name(name)
+        .ok()
+        .flatten()
+        .map(|group| group.gid.as_raw())
+}
+
+/// Suffixes of a rotated file, plain or compressed.
+const ROTATED_SUFFIXES: [&str; 3] = ["", ".gz", ".zst"];
+
+/// Rotation period of `age` that `at` falls in, `None` without time-based
+/// rotation. Periods follow the local wall clock.
+fn rotation_period(age: LogRotationAge, at: DateTime<Local>) -> Option<i64> {
+    let day = i64::from(at.num_days_from_ce());
+    match age {
+        LogRotationAge::None => None,
+        LogRotationAge::Hourly => Some(day * 24 + i64::from(at.hour())),
+        LogRotationAge::Daily => Some(day),
+    }
+}
+
+/// Path of the `n`th rotated file of `fname`.
+fn numbered(fname: &str, n: u32) -> String {
+    format!("{fname}.{n}")
+}
+
+/// Rename `fname` to `fname.1`, shifting the older rotated files, compressed
+/// or not, up to `fname.<count>` and removing those beyond, left by a larger
+/// count.
+fn rotate_numbered(fname: &str, count: u32) {
+    if count == 0 {
+        let _ = fs::remove_file(fname);
+    } else {
+        for suffix in ROTATED_SUFFIXES {
+            let _ = fs::remove_file(numbered(fname, count) + suffix);
+            for n in (1..count).rev() {
+                let _ = fs::rename(numbered(fname, n) + suffix, numbered(fname, n + 1) + suffix);
+            }
+        }
+        let _ = fs::rename(fname, numbered(fname, 1));
+    }
+    let mut n = count + 1;
+    while ROTATED_SUFFIXES
+        .iter()
+        .filter(|suffix| fs::remove_file(numbered(fname, n) + suffix).is_ok())
+        .count()
+        > 0
+    {
+        n += 1;
+    }
+}
+
+/// Compress a rotated log file in a background thread, which returns the
+/// error to log if any. Both tools keep the modification time, so
+/// `log_retention_days` applies to the compressed files as well.
+fn compress_rotated(
+    path: PathBuf,
+    compress: LogRotationCompress,
+) -> Option<JoinHandle<Result<(), String>>> {
+    let (program, args): (&str, &[&str]) = match compress {
+        LogRotationCompress::None => return None,
+        LogRotationCompress::Gzip => ("gzip", &["-f"]),
+        LogRotationCompress::Zstd => ("zstd", &["-q", "-f", "--rm"]),
+    };
+    std::thread::Builder::new()
+        .name("log-compress".into())
+        .spawn(move || {
+            match Command::new(program)
+                .args(args)
+                .arg("--")
+                .arg(&path)
+                .status()
+            {
+                Ok(status) if status.success() => Ok(()),
+                Ok(status) => Err(format!(
+                    "can not compress log file {}, {program} {status}",
+                    path.display()
+                )),
+                Err(err) => Err(format!(
+                    "can not compress log file {}, {program}: {err}",
+                    path.display()
+                )),
+            }
+        })
+        .ok()
+}
+
+/// Wait for the compression of the previous rotated file, writing its
+/// error to the log if it failed.
+fn finish_compression(
+    compressing: &mut Option<JoinHandle<Result<(), String>>>,
+    writer: &mut Option<BufWriter<std::fs::File>>,
+) {
+    if let Some(Err(msg)) = compressing.take().and_then(|handle| handle.join().ok()) {
+        write_own_line(writer, "ERROR", &msg);
+    }
+}
+
+/// Write a line of the log writer itself to the open log file, or stderr.
+fn write_own_line(writer: &mut Option<BufWriter<std::fs::File>>, level: &str, msg: &str) {
+    let line = format_line(level, msg, Local::now());
+    match writer {
+        Some(w) => {
+            let _ = w.write_all(line.as_bytes());
+        }
+        None => eprint!("{line}"),
+    }
+}
+
+/// The file of the directory of `fname` that is the inode `(dev, ino)`,
+/// i.e. where the log file was renamed by an external rotation, unless it
+/// is compressed already.
+fn find_renamed(fname: &str, (dev, ino): (u64, u64)) -> Option<PathBuf> {
+    let dir = match Path::new(fname).parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .find(|entry| {
+            entry
+                .metadata()
+                .is_ok_and(|m| m.is_file() && m.dev() == dev && m.ino() == ino)
+        })
+        .map(|entry| entry.path())
+        .filter(|path| {
+            !path
+                .extension()
+                .is_some_and(|ext| ext == "gz" || ext == "zst")
+        })
+}
+
 /// Sender + the pid of the process that spawned the writer thread.
 /// After `fork()` only the calling thread survives in the child, so a sender
 /// inherited across fork points at a dead receiver. We detect this by
@@ -124,6 +358,18 @@ fn log_writer_thread(rx: mpsc::Receiver<LogCmd>) {
     // `current_fname` can't carry this: it goes back to "" whenever `logfile`
     // is unset, so it would mis-classify the next real open as a "first" one.
     let mut opened_real_logfile = false;
+    // Bytes in the open file, for size-based rotation. Counted rather than
+    // stat'ed on each line; re-checked against the file before rotating.
+    let mut written: u64 = 0;
+    // When the open file was opened, for time-based rotation.
+    let mut started: DateTime<Local> = Local::now();
+    // Compression of the last rotated file, and the one asked by the
+    // latest line, for the files rotated by an external tool.
+    let mut compressing: Option<JoinHandle<Result<(), String>>> = None;
+    let mut compress = LogRotationCompress::None;
+    let mut syslog = DatagramSocket::new(SYSLOG_SOCKETS);
+    let mut journal = DatagramSocket::new(JOURNALD_SOCKETS);
+    let mut gelf: Option<GelfSink> = None;
 
     loop {
         // Block until the first message arrives.
@@ -157,11 +403,17 @@ fn log_writer_thread(rx: mpsc::Receiver<LogCmd>) {
                 }
                 writer = None;
                 open_id = None;
+                // A renamed file is compressed like one we rotated.
+                if compress != LogRotationCompress::None
+                    && let Some(renamed) = find_renamed(&current_fname, (dev, ino))
+                {
+                    finish_compression(&mut compressing, &mut writer);
+                    compressing = compress_rotated(renamed, compress);
+                }
             }
         }
 
         let mut shutdown_ack: Option<mpsc::Sender<()>> = None;
-        #[cfg(test)]
         let mut flush_acks: Vec<mpsc::Sender<()>> = Vec::new();
 
         for cmd in batch {
@@ -170,7 +422,10 @@ fn log_writer_thread(rx: mpsc::Receiver<LogCmd>) {
                     line,
                     fname,
                     truncate_on_rotation,
+                    rotation,
+                    access,
                 } => {
+                    compress = rotation.compress;
                     // Handle file rotation / truncation.
                     if fname != current_fname {
                         // Flush the old writer before switching files.
@@ -197,8 +452,50 @@ fn log_writer_thread(rx: mpsc::Receiver<LogCmd>) {
                         continue;
                     }
 
-                    // Open the file lazily (persistent handle).
+                    let now = Local::now();
+                    let due = |written: u64, started: DateTime<Local>| {
+                        written > 0
+                            && ((rotation.size > 0 && written + line.len() as u64 > rotation.size)
+                                || rotation_period(rotation.age, now)
+                                    != rotation_period(rotation.age, started))
+                    };
+                    if due(written, started)
+                        && let Some(ref mut w) = writer
+                    {
+                        let _ = w.flush();
+                        // The file may have been truncated behind our back
+                        // (`copytruncate`).
+                        written = w.get_ref().metadata().map_or(written, |m| m.len());
+                        if due(written, started) {
+                            finish_compression(&mut compressing, &mut writer);
+                            writer = None;
+                            open_id = None;
+                            rotate_numbered(&current_fname, rotation.count);
+                            if rotation.count > 0 {
+                                compressing = compress_rotated(
+                                    numbered(&current_fname, 1).into(),
+                                    rotation.compress,
+                                );
+                            }
+                        }
+                    }
+
+                    // Open the file lazily (persistent handle), rotating
+                    // first a file left over from a previous period or run.
                     if writer.is_none() {
+                        if let Ok(meta) = fs::metadata(&current_fname)
+                            && due(meta.len(), meta.modified().map_or(now, DateTime::from))
+                        {
+                            finish_compression(&mut compressing, &mut writer);
+                            rotate_numbered(&current_fname, rotation.count);
+                            if rotation.count > 0 {
+                                compressing = compress_rotated(
+                                    numbered(&current_fname, 1).into(),
+                                    rotation.compress,
+                                );
+                            }
+                        }
+                        let created = !Path::new(&current_fname).exists();
                         match OpenOptions::new()
                             .append(true)
                             .create(true)
@@ -206,8 +503,19 @@ fn log_writer_thread(rx: mpsc::Receiver<LogCmd>) {
                         {
                             Ok(f) => {
                                 opened_real_logfile = true;
-                                open_id = f.metadata().ok().map(|m| (m.dev(), m.ino()));
+                                let denied = if created {
+                                    access.apply(&f).err()
+                                } else {
+                                    None
+                                };
+                                let meta = f.metadata().ok();
+                                open_id = meta.as_ref().map(|m| (m.dev(), m.ino()));
+                                written = meta.map_or(0, |m| m.len());
+                                started = now;
                                 writer = Some(BufWriter::new(f));
+                                if let Some(msg) = denied {
+                                    write_own_line(&mut writer, "WARNING", &msg);
+                                }
                             }
                             Err(_) => {
                                 eprintln!("ERROR: can't write to log file {current_fname}");
@@ -219,12 +527,36 @@ fn log_writer_thread(rx: mpsc::Receiver<LogCmd>) {
 
                     if let Some(ref mut w) = writer {
                         let _ = w.write_all(line.as_bytes());
+                        written += line.len() as u64;
                     }
                 }
-                #[cfg(test)]
                 LogCmd::Flush(ack) => {
                     flush_acks.push(ack);
                 }
+                LogCmd::Datagram {
+                    journal: to_journal,
+                    datagram,
+                    line,
+                } => {
+                    let socket = if to_journal {
+                        &mut journal
+                    } else {
+                        &mut syslog
+                    };
+                    if !socket.send(&datagram) {
+                        eprint!("{line}");
+                    }
+                }
+                LogCmd::Gelf { endpoint, payload } => {
+                    if gelf.as_ref().is_none_or(|sink| sink.spec() != endpoint) {
+                        gelf = GelfSink::new(&endpoint);
+                    }
+                    if let Some(sink) = &mut gelf
+                        && let Some(msg) = sink.send(&payload)
+                    {
+                        write_own_line(&mut writer, "WARNING", &msg);
+                    }
+                }
                 LogCmd::Reopen => {
                     if let Some(ref mut w) = writer {
                         let _ = w.flush();
@@ -244,7 +576,6 @@ fn log_writer_thread(rx: mpsc::Receiver<LogCmd>) {
         }
 
         // Acknowledge all flush requests.
-        #[cfg(test)]
         for ack in flush_acks {
             let _ = ack.send(());
         }
@@ -255,7 +586,8 @@ fn log_writer_thread(rx: mpsc::Receiver<LogCmd>) {
         }
     }
 
-    // Final flush on exit.
+    // Final flush on exit, after the last compression.
+    finish_compression(&mut compressing, &mut writer);
     if let Some(ref mut w) = writer {
         let _ = w.flush();
     }
@@ -292,28 +624,374 @@ pub fn shutdown_logger() {
     }
 }
 
+/// Set while a clean-up of dated log files runs, so runs never overlap.
+static EXPIRING: AtomicBool = AtomicBool::new(false);
+
+/// Age after which a dated log file is compressed with `log_compress`.
+const COMPRESS_AFTER: Duration = Duration::from_secs(86400);
+
+/// Remove, and with `log_compress` gzip, old log files of a strftime-dated
+/// `logfile`, and remove old rotated `<logfile>.N` files, in a background
+/// thread.
+///
+/// The files are those of the log directory whose name matches the pattern
+/// (each `%` escape matching any text), with or without a `.gz` suffix, and
+/// their age is their modification time. The file currently written is never
+/// touched. Compression runs the system `gzip`, which keeps the
+/// modification time, so retention applies to compressed files as well.
+/// Rotated files are compressed when rotated, with `log_rotation_compress`.
+pub fn expire_log_files(config: &Config) {
+    let dated = config.logfile.contains('%');
+    if config.logfile.is_empty()
+        || !(config.log_retention_days > 0 || (dated && config.log_compress))
+    {
+        return;
+    }
+    if EXPIRING.swap(true, Ordering::AcqRel) {
+        return;
+    }
+    let config = config.clone();
+    let spawned = std::thread::Builder::new()
+        .name("log-expire".into())
+        .spawn(move || {
+            expire_dated_files(&config, SystemTime::now());
+            EXPIRING.store(false, Ordering::Release);
+        });
+    if spawned.is_err() {
+        EXPIRING.store(false, Ordering::Release);
+    }
+}
+
+fn expire_dated_files(config: &Config, now: SystemTime) {
+    let dated = config.logfile.contains('%');
+    let pattern = Path::new(&config.logfile);
+    let (Some(dir), Some(name)) = (pattern.parent(), pattern.file_name()) else {
+        return;
+    };
+    // Only the file name may be dated: a dated directory would need a walk.
+    if dir.to_string_lossy().contains('%') {
+        return;
+    }
+    let dir = if dir.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        dir
+    };
+    let pieces = dated_name_pieces(&name.to_string_lossy());
+    let current = Local::now().format(&config.logfile).to_string();
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let retention = Duration::from_secs(config.log_retention_days.saturating_mul(86400));
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let fname = entry.file_name().to_string_lossy().into_owned();
+        let rotated = rotated_base(&fname);
+        if path.to_string_lossy() == current
+            || !(dated || rotated.is_some())
+            || !matches_dated_name(&pieces, rotated.unwrap_or(&fname))
+        {
+            continue;
+        }
+        let Some(age) = entry
+            .metadata()
+            .ok()
+            .filter(|m| m.is_file())
+            .and_then(|m| m.modified().ok())
+            .and_then(|mtime| now.duration_since(mtime).ok())
+        else {
+            continue;
+        };
+        if config.log_retention_days > 0 && age > retention {
+            match fs::remove_file(&path) {
+                Ok(()) => dprint(
+                    config,
+                    "LOG",
+                    &format!(
+                        "removed log file {} older than log_retention_days",
+                        path.display()
+                    ),
+                ),
+                Err(err) => dprint(
+                    config,
+                    "ERROR",
+                    &format!("can not remove log file {}, {err}", path.display()),
+                ),
+            }
+        } else if config.log_compress
+            && dated
+            && rotated.is_none()
+            && age > COMPRESS_AFTER
+            && !fname.ends_with(".gz")
+        {
+            match Command::new("gzip").arg("-f").arg("--").arg(&path).status() {
+                Ok(status) if status.success() => {}
+                Ok(status) => dprint(
+                    config,
+                    "ERROR",
+                    &format!(
+                        "can not compress log file {}, gzip {status}",
+                        path.display()
+                    ),
+                ),
+                Err(err) => dprint(
+                    config,
+                    "ERROR",
+                    &format!("can not compress log file {}, gzip: {err}", path.display()),
+                ),
+            }
+        }
+    }
+}
+
+/// Name of the log file a rotated `<name>.N` file comes from, `None` when
+/// `name` is not a rotated file.
+fn rotated_base(name: &str) -> Option<&str> {
+    let name = name
+        .strip_suffix(".gz")
+        .or_else(|| name.strip_suffix(".zst"))
+        .unwrap_or(name);
+    let (base, n) = name.rsplit_once('.')?;
+    (!n.is_empty() && n.bytes().all(|b| b.is_ascii_digit())).then_some(base)
+}
+
+/// Split a strftime file name pattern into its literal pieces; `None`
+/// stands for an escape, which matches any text. `%%` is a literal `%`.
+fn dated_name_pieces(pattern: &str) -> Vec<Option<String>> {
+    let mut pieces = Vec::new();
+    let mut literal = String::new();
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            literal.push(c);
+            continue;
+        }
+        if chars.peek() == Some(&'%') {
+            chars.next();
+            literal.push('%');
+            continue;
+        }
+        // Padding modifiers (`%-d`, `%_H`, `%0e`) belong to the escape.
+        while matches!(chars.peek(), Some('-' | '_' | '0')) {
+            chars.next();
+        }
+        chars.next();
+        if !literal.is_empty() {
+            pieces.push(Some(std::mem::take(&mut literal)));
+        }
+        if pieces.last() != Some(&None) {
+            pieces.push(None);
+        }
+    }
+    if !literal.is_empty() {
+        pieces.push(Some(literal));
+    }
+    pieces
+}
+
+/// Whether a file name is one of the dated log files, compressed or not.
+fn matches_dated_name(pieces: &[Option<String>], name: &str) -> bool {
+    let name = name.strip_suffix(".gz").unwrap_or(name);
+    let mut rest = name;
+    let mut anchored = true;
+    for (i, piece) in pieces.iter().enumerate() {
+        match piece {
+            None => anchored = false,
+            Some(lit) if anchored => match rest.strip_prefix(lit.as_str()) {
+                Some(r) => rest = r,
+                None => return false,
+            },
+            // The last piece must end the name, the others are taken at
+            // their first occurrence.
+            Some(lit) if i == pieces.len() - 1 => return rest.ends_with(lit.as_str()),
+            Some(lit) => match rest.find(lit.as_str()) {
+                Some(pos) => {
+                    rest = &rest[pos + lit.len()..];
+                    anchored = true;
+                }
+                None => return false,
+            },
+        }
+    }
+    // A trailing escape matches whatever is left, otherwise nothing may be.
+    !anchored || rest.is_empty()
+}
+
 /// Block until all previously sent log messages have been written and flushed.
-#[cfg(test)]
+///
+/// Called before `fork()`: the writer thread of the parent does not survive
+/// in the child, and the parent exits right away, so without it the lines
+/// still queued would be lost, or written by the exiting parent while the
+/// child's own writer appends to the same file.
 pub fn flush_logger() {
+    let sender = {
+        let guard = match LOG_STATE.lock() {
+            Ok(g) => g,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        guard
+            .as_ref()
+            .filter(|state| state.pid == process::id())
+            .map(|state| state.tx.clone())
+    };
+    let Some(tx) = sender else {
+        return;
+    };
     let (ack_tx, ack_rx) = mpsc::channel();
-    let sent = with_sender(|tx| tx.send(LogCmd::Flush(ack_tx)).is_ok()).unwrap_or(false);
-    if sent {
+    if tx.send(LogCmd::Flush(ack_tx)).is_ok() {
         let _ = ack_rx.recv_timeout(Duration::from_secs(5));
     }
 }
 
+/// Job run by the current thread, see [`job_log_context`].
+struct JobContext {
+    jobid: i64,
+    run_id: String,
+    started: Instant,
+}
+
+thread_local! {
+    static JOB_CONTEXT: RefCell<Option<JobContext>> = const { RefCell::new(None) };
+}
+
+/// Tags the log lines of the current thread with a job until dropped.
+pub struct JobLogContext(());
+
+/// Tag the log lines of the current thread with the run `run_id` of
+/// `jobid`: the messages start with `[run <run_id>]`, and journald entries
+/// get the `JOBID`, `RUN_ID` and `DURATION` fields.
+pub fn job_log_context(jobid: i64, run_id: &str) -> JobLogContext {
+    JOB_CONTEXT.set(Some(JobContext {
+        jobid,
+        run_id: run_id.to_string(),
+        started: Instant::now(),
+    }));
+    JobLogContext(())
+}
+
+impl Drop for JobLogContext {
+    fn drop(&mut self) {
+        JOB_CONTEXT.set(None);
+    }
+}
+
+/// `msg` prefixed with the database section and the job queue schema of
+/// `config`, when not the default one, and the run of the current thread,
+/// if any.
+fn tagged<'a>(config: &Config, msg: &'a str) -> Cow<'a, str> {
+    let msg = JOB_CONTEXT.with_borrow(|context| match context {
+        Some(context) => Cow::Owned(format!("[run {}] {msg}", context.run_id)),
+        None => Cow::Borrowed(msg),
+    });
+    let msg = if config.schema == DEFAULT_SCHEMA {
+        msg
+    } else {
+        Cow::Owned(format!("[schema {}] {msg}", config.schema))
+    };
+    if config.database_section.is_empty() {
+        msg
+    } else {
+        Cow::Owned(format!("[database {}] {msg}", config.database_section))
+    }
+}
+
+/// DEBUG lines not written, with their date, kept for [`dump_debug_ring`]
+/// when `debug_ring_size` is set.
+static DEBUG_RING: Mutex<VecDeque<(DateTime<Local>, String)>> = Mutex::new(VecDeque::new());
+
+/// Keep a DEBUG line in the ring buffer, dropping the oldest beyond `size`.
+fn buffer_debug(size: usize, msg: &str) {
+    let mut ring = DEBUG_RING.lock().unwrap_or_else(|e| e.into_inner());
+    while ring.len() >= size {
+        ring.pop_front();
+    }
+    ring.push_back((Local::now(), msg.to_string()));
+}
+
+/// Write the DEBUG lines kept in the ring buffer to the log, oldest first
+/// and with their own date, then empty it. Used on SIGUSR2 to look at what
+/// preceded an incident without running in debug mode.
+pub fn dump_debug_ring(config: &Config) {
+    let lines = std::mem::take(&mut *DEBUG_RING.lock().unwrap_or_else(|e| e.into_inner()));
+    emit(
+        config,
+        "LOG",
+        &format!("dumping {} buffered DEBUG messages", lines.len()),
+        Local::now(),
+    );
+    for (at, msg) in &lines {
+        emit(config, "DEBUG", msg, *at);
+    }
+    emit(
+        config,
+        "LOG",
+        "end of the buffered DEBUG messages",
+        Local::now(),
+    );
+}
+
+thread_local! {
+    /// Lines logged by the current thread during [`capture_log`].
+    static CAPTURED: RefCell<Option<Vec<(String, String)>>> = const { RefCell::new(None) };
+}
+
+/// Run `f`, collecting the lines it logs from this thread, whatever their
+/// level, as `(level, message)` instead of writing them.
+pub fn capture_log<T>(f: impl FnOnce() -> T) -> (T, Vec<(String, String)>) {
+    CAPTURED.set(Some(Vec::new()));
+    let result = f();
+    (result, CAPTURED.take().unwrap_or_default())
+}
+
+/// Whether a line of `level` is written, buffered or captured, i.e. worth
+/// formatting.
+pub fn wanted(config: &Config, level: &str) -> bool {
+    config.logs(level) || config.buffers(level) || CAPTURED.with_borrow(Option::is_some)
+}
+
 /// Write a log line based on config and severity level.
 ///
 /// The line is fully formatted in the caller's thread (no allocation under
 /// a lock) and then sent to the dedicated writer thread via a channel.
 pub fn dprint(config: &Config, level: &str, msg: &str) {
-    if level.eq_ignore_ascii_case("DEBUG") && !config.debug {
+    let captured = CAPTURED.with_borrow_mut(|captured| {
+        captured
+            .as_mut()
+            .map(|lines| lines.push((level.to_string(), msg.to_string())))
+            .is_some()
+    });
+    if captured {
+        return;
+    }
+    if !config.logs(level) {
+        if config.buffers(level) {
+            buffer_debug(config.debug_ring_size, &tagged(config, msg));
+        }
         return;
     }
+    emit(config, level, &tagged(config, msg), Local::now());
+}
 
-    // Pre-format the complete line outside any lock.
-    let t = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
-    let line = format!("{t} [{}]: {level}: {msg}\n", process::id());
+/// Whether the lines written to stderr are in the console format, see
+/// [`enable_console_colors`].
+static CONSOLE_COLORS: AtomicBool = AtomicBool::new(false);
+
+/// Write the lines going to stderr in a short, colorized form instead of
+/// the log file format: for `--single` runs on a terminal.
+pub fn enable_console_colors() {
+    CONSOLE_COLORS.store(true, Ordering::Relaxed);
+}
+
+/// Send a log line dated `at` to the writer thread, whatever its level.
+fn emit(config: &Config, level: &str, msg: &str, at: DateTime<Local>) {
+    // Pre-format the complete line outside any lock. It only goes to stderr
+    // unless a log file is written.
+    let to_stderr = config.log_destination != LogDestination::File || config.logfile.is_empty();
+    let line = if to_stderr && CONSOLE_COLORS.load(Ordering::Relaxed) {
+        format_console_line(level, msg, at)
+    } else {
+        format_line(level, msg, at)
+    };
 
     let fname = if config.logfile.contains('%') {
         Local::now().format(&config.logfile).to_string()
@@ -321,15 +999,70 @@ pub fn dprint(config: &Config, level: &str, msg: &str) {
         config.logfile.clone()
     };
 
-    let dispatched = with_sender(|tx| {
-        tx.send(LogCmd::Line {
+    let cmd = match config.log_destination {
+        LogDestination::File => LogCmd::Line {
             line: line.clone(),
             fname,
             truncate_on_rotation: config.log_truncate_on_rotation,
-        })
-        .is_ok()
-    })
-    .unwrap_or(false);
+            rotation: Rotation::of(config),
+            access: Access::of(config),
+        },
+        LogDestination::Syslog => LogCmd::Datagram {
+            journal: false,
+            datagram: syslog::datagram(
+                syslog::facility_code(&config.syslog_facility).unwrap_or(syslog::DEFAULT_FACILITY),
+                syslog::severity(level),
+                &config.syslog_ident,
+                process::id(),
+                &format!("{level}: {msg}"),
+                at,
+            )
+            .into_bytes(),
+            line: line.clone(),
+        },
+        LogDestination::Journald => {
+            let message = format!("{level}: {msg}");
+            let priority = syslog::severity(level).to_string();
+            let mut fields = vec![
+                ("MESSAGE", message.as_str()),
+                ("PRIORITY", priority.as_str()),
+                ("SYSLOG_IDENTIFIER", config.syslog_ident.as_str()),
+                ("LEVEL", level),
+            ];
+            let job = job_fields().map(|(jobid, run_id, duration)| {
+                (jobid.to_string(), run_id, format!("{duration:.3}"))
+            });
+            if let Some((jobid, run_id, duration)) = &job {
+                fields.push(("JOBID", jobid));
+                fields.push(("RUN_ID", run_id));
+                fields.push(("DURATION", duration));
+            }
+            LogCmd::Datagram {
+                journal: true,
+                datagram: journald::entry(&fields),
+                line: line.clone(),
+            }
+        }
+    };
+    let gelf = (!config.log_gelf_endpoint.is_empty()).then(|| {
+        let job = job_fields();
+        LogCmd::Gelf {
+            endpoint: config.log_gelf_endpoint.clone(),
+            payload: gelf::message(
+                gelf::host(),
+                level,
+                msg,
+                at,
+                process::id(),
+                job.as_ref()
+                    .map(|(jobid, run_id, duration)| (*jobid, run_id.as_str(), *duration)),
+            )
+            .into_bytes(),
+        }
+    });
+    let dispatched =
+        with_sender(|tx| tx.send(cmd).is_ok() && gelf.is_none_or(|gelf| tx.send(gelf).is_ok()))
+            .unwrap_or(false);
     if !dispatched {
         // Writer thread missing or its channel is closed — make sure the line
         // still surfaces somewhere instead of silently disappearing.
@@ -337,8 +1070,47 @@ pub fn dprint(config: &Config, level: &str, msg: &str) {
     }
 }
 
-/// Convenience macro that defers `format!` so DEBUG messages skip the
-/// allocation entirely when debug logging is disabled.
+/// Job, run id and seconds since the run started of the current thread, if
+/// it runs a job.
+fn job_fields() -> Option<(i64, String, f64)> {
+    JOB_CONTEXT.with_borrow(|context| {
+        context.as_ref().map(|context| {
+            (
+                context.jobid,
+                context.run_id.clone(),
+                context.started.elapsed().as_secs_f64(),
+            )
+        })
+    })
+}
+
+/// A log line as written to the log file.
+fn format_line(level: &str, msg: &str, at: DateTime<Local>) -> String {
+    let t = at.format("%Y-%m-%d %H:%M:%S");
+    format!("{t} [{}]: {level}: {msg}\n", process::id())
+}
+
+/// A log line as written to a terminal: the time, the level in color and
+/// the message, dimmed for DEBUG.
+fn format_console_line(level: &str, msg: &str, at: DateTime<Local>) -> String {
+    let t = at.format("%H:%M:%S");
+    let color = match level {
+        "DEBUG" => "2",
+        "WARNING" => "33",
+        "ERROR" => "31",
+        "FATAL" | "PANIC" => "1;31",
+        _ => "36",
+    };
+    if level == "DEBUG" {
+        format!("{t} \x1b[{color}m{level:<7} {msg}\x1b[0m\n")
+    } else {
+        format!("{t} \x1b[{color}m{level:<7}\x1b[0m {msg}\n")
+    }
+}
+
+/// Convenience macro that defers `format!` so messages filtered out by
+/// `debug` and `log_min_messages`, and neither kept in the DEBUG ring
+/// buffer nor captured, skip the allocation entirely.
 ///
 /// ```ignore
 /// dlog!(config, "DEBUG", "connecting to job {}", job.job);
@@ -346,21 +1118,26 @@ pub fn dprint(config: &Config, level: &str, msg: &str) {
 /// ```
 #[macro_export]
 macro_rules! dlog {
-    ($config:expr, "DEBUG", $($arg:tt)+) => {
-        if $config.debug {
-            $crate::logging::dprint($config, "DEBUG", &format!($($arg)+))
-        }
-    };
     ($config:expr, $level:expr, $($arg:tt)+) => {
-        $crate::logging::dprint($config, $level, &format!($($arg)+))
+        if $crate::logging::wanted($config, $level) {
+            $crate::logging::dprint($config, $level, &format!($($arg)+))
+        }
     };
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{dprint, flush_logger, reopen_logger};
-    use crate::model::Config;
+    use super::{
+        dated_name_pieces, dprint, dump_debug_ring, expire_dated_files, find_renamed, flush_logger,
+        format_console_line, group_id, job_log_context, matches_dated_name, reopen_logger,
+        rotate_numbered, rotated_base, user_id,
+    };
+    use crate::constants::DEFAULT_SCHEMA;
+    use crate::model::{Config, LogMinMessages, LogRotationAge, LogRotationCompress};
+    use std::collections::BTreeMap;
     use std::fs;
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+    use std::path::Path;
     use std::sync::atomic::{AtomicU64, Ordering};
     use std::time::{SystemTime, UNIX_EPOCH};
 
@@ -382,6 +1159,9 @@ mod tests {
             debug,
             pidfile: "/tmp/pg_dbms_job.pid".to_string(),
             logfile: path.to_string_lossy().to_string(),
+            logfile_mode: 0,
+            logfile_owner: String::new(),
+            logfile_group: String::new(),
             log_truncate_on_rotation: false,
             job_queue_interval: 5.0,
             job_queue_processes: 1000,
@@ -392,6 +1172,58 @@ mod tests {
             stats_interval: 0,
             job_run_details: crate::model::JobRunDetails::All,
             stale_job_timeout: 3600.0,
+            notify_debounce: 0.0,
+            schedule_refresh_interval: 60.0,
+            max_notifications_per_cycle: 10000,
+            max_job_size: 0,
+            hard_max_run_duration: 0.0,
+            hard_max_run_overruns: 3,
+            job_nice: 0,
+            job_io_class: crate::model::IoClass::None,
+            job_io_priority: 4,
+            connection_reserve: 0,
+            job_cgroup: String::new(),
+            executable_job_owners: String::new(),
+            executable_os_users: String::new(),
+            log_retention_days: 0,
+            log_compress: false,
+            max_nap_time: 0.0,
+            reconnect_max_delay: 60.0,
+            reconnect_max_attempts: 0,
+            standby_check_interval: 0.0,
+            job_run_timeout: 0.0,
+            max_failures: 0,
+            connection_check_interval: 0.0,
+            multiple_schedulers: false,
+            semaphores: BTreeMap::new(),
+            job_classes: BTreeMap::new(),
+            job_class_priorities: BTreeMap::new(),
+            run_details_maintenance_interval: 0.0,
+            run_details_retention_months: 3,
+            worker_hang_timeout: 600.0,
+            job_execution_mode: crate::model::ExecutionMode::SetRole,
+            dst_policy: crate::model::DstPolicy::Server,
+            ssh_host: String::new(),
+            ssh_port: 22,
+            ssh_user: String::new(),
+            ssh_key: String::new(),
+            log_rotation_size: 0,
+            log_rotation_count: 5,
+            log_rotation_age: crate::model::LogRotationAge::None,
+            log_destination: crate::model::LogDestination::File,
+            syslog_facility: "local0".to_string(),
+            syslog_ident: "pg_dbms_job".to_string(),
+            log_min_messages: crate::model::LogMinMessages::Log,
+            log_job_notices: false,
+            log_rotation_compress: crate::model::LogRotationCompress::None,
+            scheduler_event_log: false,
+            debug_ring_size: 0,
+            log_gelf_endpoint: String::new(),
+            auto_reload: false,
+            strict_config: false,
+            database_section: String::new(),
+            schemas: DEFAULT_SCHEMA.to_string(),
+            schema: DEFAULT_SCHEMA.to_string(),
         }
     }
 
@@ -406,6 +1238,37 @@ mod tests {
         let _ = fs::remove_file(path);
     }
 
+    #[test]
+    fn created_logfile_gets_the_configured_mode_and_group() {
+        let path = temp_log_path();
+        let mut config = test_config(&path, false);
+        config.logfile_mode = 0o604;
+        config.logfile_group = nix::unistd::getgid().to_string();
+        dprint(&config, "LOG", "created");
+        flush_logger();
+        let meta = fs::metadata(&path).expect("stat log file");
+        assert_eq!(meta.permissions().mode() & 0o7777, 0o604);
+        assert_eq!(meta.gid(), nix::unistd::getgid().as_raw());
+
+        // A file that already exists is left as is.
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+        reopen_logger();
+        dprint(&config, "LOG", "reopened");
+        flush_logger();
+        let meta = fs::metadata(&path).expect("stat log file");
+        assert_eq!(meta.permissions().mode() & 0o7777, 0o600);
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn user_and_group_ids_resolve_names_and_numbers() {
+        assert_eq!(user_id("root"), Some(0));
+        assert_eq!(user_id("1234"), Some(1234));
+        assert_eq!(user_id("pg_dbms_job_no_such_user"), None);
+        assert_eq!(group_id("0"), Some(0));
+        assert_eq!(group_id("pg_dbms_job_no_such_group"), None);
+    }
+
     #[test]
     fn bounded_channel_drains_high_volume_without_loss() {
         // Send well past LOG_CHANNEL_CAPACITY so the bounded sync_channel is
@@ -450,6 +1313,147 @@ mod tests {
         let _ = fs::remove_file(path);
     }
 
+    #[test]
+    fn dprint_honors_log_min_messages() {
+        let path = temp_log_path();
+        let mut config = test_config(&path, false);
+        config.log_min_messages = LogMinMessages::Warning;
+        dprint(&config, "DEBUG", "debug line");
+        dprint(&config, "LOG", "log line");
+        dprint(&config, "WARNING", "warning line");
+        dprint(&config, "FATAL", "fatal line");
+        config.log_min_messages = LogMinMessages::Error;
+        config.debug = true;
+        dprint(&config, "WARNING", "second warning line");
+        dprint(&config, "DEBUG", "debug line with debug");
+        flush_logger();
+        let content = fs::read_to_string(&path).expect("read log file");
+        assert!(!content.contains("debug line\n") && !content.contains("log line"));
+        assert!(content.contains("warning line") && content.contains("fatal line"));
+        assert!(!content.contains("second warning line"));
+        // `debug` still writes DEBUG lines.
+        assert!(content.contains("debug line with debug"));
+
+        config.debug = false;
+        config.log_min_messages = LogMinMessages::Debug;
+        assert!(config.logs("DEBUG") && config.logs("LOG"));
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn debug_ring_keeps_the_last_unwritten_debug_lines() {
+        let path = temp_log_path();
+        let mut config = test_config(&path, false);
+        config.debug_ring_size = 3;
+        for i in 0..5 {
+            crate::dlog!(&config, "DEBUG", "ring line {i}");
+        }
+        dprint(&config, "LOG", "written line");
+        flush_logger();
+        let content = fs::read_to_string(&path).expect("read log file");
+        assert!(!content.contains("ring line"));
+
+        dump_debug_ring(&config);
+        dump_debug_ring(&config);
+        flush_logger();
+        let content = fs::read_to_string(&path).expect("read log file");
+        assert!(content.contains("LOG: dumping 3 buffered DEBUG messages"));
+        assert!(!content.contains("ring line 1"));
+        let first = content
+            .find("DEBUG: ring line 2")
+            .expect("oldest kept line");
+        assert!(content.find("DEBUG: ring line 4").unwrap() > first);
+        assert!(content.contains("LOG: end of the buffered DEBUG messages"));
+        // The buffer is emptied by a dump.
+        assert!(content.contains("LOG: dumping 0 buffered DEBUG messages"));
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn lines_of_a_job_run_carry_its_run_id() {
+        let path = temp_log_path();
+        let config = test_config(&path, false);
+        {
+            let _context = job_log_context(42, "0b5e2a52-1f0e-4c4b-9a43-5d5f6e0c7a11");
+            dprint(&config, "ERROR", "job 42 failure");
+        }
+        dprint(&config, "LOG", "outside of a run");
+        flush_logger();
+        let content = fs::read_to_string(&path).expect("read log file");
+        assert!(
+            content.contains("ERROR: [run 0b5e2a52-1f0e-4c4b-9a43-5d5f6e0c7a11] job 42 failure\n")
+        );
+        assert!(content.contains("LOG: outside of a run\n"));
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn lines_of_another_schema_carry_its_database_and_schema() {
+        let path = temp_log_path();
+        let mut config = test_config(&path, false);
+        config.database_section = "sales".to_string();
+        config.schema = "tenant_a".to_string();
+        {
+            let _context = job_log_context(3, "run-3");
+            dprint(&config, "ERROR", "job 3 failure");
+        }
+        config.schema = DEFAULT_SCHEMA.to_string();
+        dprint(&config, "LOG", "default schema");
+        flush_logger();
+        let content = fs::read_to_string(&path).expect("read log file");
+        assert!(
+            content
+                .contains("ERROR: [database sales] [schema tenant_a] [run run-3] job 3 failure\n")
+        );
+        assert!(content.contains("LOG: [database sales] default schema\n"));
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn lines_are_also_shipped_to_the_gelf_endpoint() {
+        let receiver = std::net::UdpSocket::bind("127.0.0.1:0").expect("bind receiver");
+        receiver
+            .set_read_timeout(Some(std::time::Duration::from_secs(5)))
+            .unwrap();
+        let path = temp_log_path();
+        let mut config = test_config(&path, false);
+        config.log_gelf_endpoint = format!("udp://{}", receiver.local_addr().unwrap());
+        {
+            let _context = job_log_context(7, "run-7");
+            dprint(&config, "WARNING", "shipped line");
+        }
+        flush_logger();
+        let mut buf = [0u8; 1024];
+        let n = receiver.recv(&mut buf).expect("GELF message");
+        let message = String::from_utf8_lossy(&buf[..n]);
+        assert!(message.starts_with("{\"version\":\"1.1\","));
+        assert!(message.contains("\"short_message\":\"[run run-7] shipped line\""));
+        assert!(message.contains("\"level\":4,\"_log_level\":\"WARNING\""));
+        assert!(message.contains("\"_job_id\":7,\"_run_id\":\"run-7\""));
+        // The log file is still written.
+        let content = fs::read_to_string(&path).expect("read log file");
+        assert!(content.contains("WARNING: [run run-7] shipped line"));
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn console_lines_are_short_and_colorized() {
+        use chrono::TimeZone;
+        let at = chrono::Local.with_ymd_and_hms(2026, 3, 5, 9, 7, 2).unwrap();
+        assert_eq!(
+            format_console_line("ERROR", "job 42 failure", at),
+            "09:07:02 \x1b[31mERROR  \x1b[0m job 42 failure\n"
+        );
+        assert_eq!(
+            format_console_line("LOG", "Entering main loop.", at),
+            "09:07:02 \x1b[36mLOG    \x1b[0m Entering main loop.\n"
+        );
+        assert_eq!(
+            format_console_line("DEBUG", "BEGIN", at),
+            "09:07:02 \x1b[2mDEBUG   BEGIN\x1b[0m\n"
+        );
+    }
+
     #[test]
     fn dprint_log_format_contains_level_and_pid() {
         let path = temp_log_path();
@@ -469,6 +1473,9 @@ mod tests {
             debug: true,
             pidfile: "/tmp/pg_dbms_job.pid".to_string(),
             logfile: String::new(),
+            logfile_mode: 0,
+            logfile_owner: String::new(),
+            logfile_group: String::new(),
             log_truncate_on_rotation: false,
             job_queue_interval: 5.0,
             job_queue_processes: 1000,
@@ -479,6 +1486,58 @@ mod tests {
             stats_interval: 0,
             job_run_details: crate::model::JobRunDetails::All,
             stale_job_timeout: 3600.0,
+            notify_debounce: 0.0,
+            schedule_refresh_interval: 60.0,
+            max_notifications_per_cycle: 10000,
+            max_job_size: 0,
+            hard_max_run_duration: 0.0,
+            hard_max_run_overruns: 3,
+            job_nice: 0,
+            job_io_class: crate::model::IoClass::None,
+            job_io_priority: 4,
+            connection_reserve: 0,
+            job_cgroup: String::new(),
+            executable_job_owners: String::new(),
+            executable_os_users: String::new(),
+            log_retention_days: 0,
+            log_compress: false,
+            max_nap_time: 0.0,
+            reconnect_max_delay: 60.0,
+            reconnect_max_attempts: 0,
+            standby_check_interval: 0.0,
+            job_run_timeout: 0.0,
+            max_failures: 0,
+            connection_check_interval: 0.0,
+            multiple_schedulers: false,
+            semaphores: BTreeMap::new(),
+            job_classes: BTreeMap::new(),
+            job_class_priorities: BTreeMap::new(),
+            run_details_maintenance_interval: 0.0,
+            run_details_retention_months: 3,
+            worker_hang_timeout: 600.0,
+            job_execution_mode: crate::model::ExecutionMode::SetRole,
+            dst_policy: crate::model::DstPolicy::Server,
+            ssh_host: String::new(),
+            ssh_port: 22,
+            ssh_user: String::new(),
+            ssh_key: String::new(),
+            log_rotation_size: 0,
+            log_rotation_count: 5,
+            log_rotation_age: crate::model::LogRotationAge::None,
+            log_destination: crate::model::LogDestination::File,
+            syslog_facility: "local0".to_string(),
+            syslog_ident: "pg_dbms_job".to_string(),
+            log_min_messages: crate::model::LogMinMessages::Log,
+            log_job_notices: false,
+            log_rotation_compress: crate::model::LogRotationCompress::None,
+            scheduler_event_log: false,
+            debug_ring_size: 0,
+            log_gelf_endpoint: String::new(),
+            auto_reload: false,
+            strict_config: false,
+            database_section: String::new(),
+            schemas: DEFAULT_SCHEMA.to_string(),
+            schema: DEFAULT_SCHEMA.to_string(),
         };
         // Should print to stderr without crashing
         dprint(&config, "LOG", "stderr fallback");
@@ -600,6 +1659,9 @@ mod tests {
             debug: false,
             pidfile: "/tmp/pg_dbms_job.pid".to_string(),
             logfile: template.to_string_lossy().to_string(),
+            logfile_mode: 0,
+            logfile_owner: String::new(),
+            logfile_group: String::new(),
             log_truncate_on_rotation: false,
             job_queue_interval: 5.0,
             job_queue_processes: 1000,
@@ -610,6 +1672,58 @@ mod tests {
             stats_interval: 0,
             job_run_details: crate::model::JobRunDetails::All,
             stale_job_timeout: 3600.0,
+            notify_debounce: 0.0,
+            schedule_refresh_interval: 60.0,
+            max_notifications_per_cycle: 10000,
+            max_job_size: 0,
+            hard_max_run_duration: 0.0,
+            hard_max_run_overruns: 3,
+            job_nice: 0,
+            job_io_class: crate::model::IoClass::None,
+            job_io_priority: 4,
+            connection_reserve: 0,
+            job_cgroup: String::new(),
+            executable_job_owners: String::new(),
+            executable_os_users: String::new(),
+            log_retention_days: 0,
+            log_compress: false,
+            max_nap_time: 0.0,
+            reconnect_max_delay: 60.0,
+            reconnect_max_attempts: 0,
+            standby_check_interval: 0.0,
+            job_run_timeout: 0.0,
+            max_failures: 0,
+            connection_check_interval: 0.0,
+            multiple_schedulers: false,
+            semaphores: BTreeMap::new(),
+            job_classes: BTreeMap::new(),
+            job_class_priorities: BTreeMap::new(),
+            run_details_maintenance_interval: 0.0,
+            run_details_retention_months: 3,
+            worker_hang_timeout: 600.0,
+            job_execution_mode: crate::model::ExecutionMode::SetRole,
+            dst_policy: crate::model::DstPolicy::Server,
+            ssh_host: String::new(),
+            ssh_port: 22,
+            ssh_user: String::new(),
+            ssh_key: String::new(),
+            log_rotation_size: 0,
+            log_rotation_count: 5,
+            log_rotation_age: crate::model::LogRotationAge::None,
+            log_destination: crate::model::LogDestination::File,
+            syslog_facility: "local0".to_string(),
+            syslog_ident: "pg_dbms_job".to_string(),
+            log_min_messages: crate::model::LogMinMessages::Log,
+            log_job_notices: false,
+            log_rotation_compress: crate::model::LogRotationCompress::None,
+            scheduler_event_log: false,
+            debug_ring_size: 0,
+            log_gelf_endpoint: String::new(),
+            auto_reload: false,
+            strict_config: false,
+            database_section: String::new(),
+            schemas: DEFAULT_SCHEMA.to_string(),
+            schema: DEFAULT_SCHEMA.to_string(),
         };
         dprint(&cfg, "LOG", "date formatted");
         flush_logger();
@@ -864,6 +1978,9 @@ mod tests {
             debug: false,
             pidfile: "/tmp/pg_dbms_job.pid".to_string(),
             logfile: String::new(),
+            logfile_mode: 0,
+            logfile_owner: String::new(),
+            logfile_group: String::new(),
             log_truncate_on_rotation: false,
             job_queue_interval: 5.0,
             job_queue_processes: 1000,
@@ -874,6 +1991,58 @@ mod tests {
             stats_interval: 0,
             job_run_details: crate::model::JobRunDetails::All,
             stale_job_timeout: 3600.0,
+            notify_debounce: 0.0,
+            schedule_refresh_interval: 60.0,
+            max_notifications_per_cycle: 10000,
+            max_job_size: 0,
+            hard_max_run_duration: 0.0,
+            hard_max_run_overruns: 3,
+            job_nice: 0,
+            job_io_class: crate::model::IoClass::None,
+            job_io_priority: 4,
+            connection_reserve: 0,
+            job_cgroup: String::new(),
+            executable_job_owners: String::new(),
+            executable_os_users: String::new(),
+            log_retention_days: 0,
+            log_compress: false,
+            max_nap_time: 0.0,
+            reconnect_max_delay: 60.0,
+            reconnect_max_attempts: 0,
+            standby_check_interval: 0.0,
+            job_run_timeout: 0.0,
+            max_failures: 0,
+            connection_check_interval: 0.0,
+            multiple_schedulers: false,
+            semaphores: BTreeMap::new(),
+            job_classes: BTreeMap::new(),
+            job_class_priorities: BTreeMap::new(),
+            run_details_maintenance_interval: 0.0,
+            run_details_retention_months: 3,
+            worker_hang_timeout: 600.0,
+            job_execution_mode: crate::model::ExecutionMode::SetRole,
+            dst_policy: crate::model::DstPolicy::Server,
+            ssh_host: String::new(),
+            ssh_port: 22,
+            ssh_user: String::new(),
+            ssh_key: String::new(),
+            log_rotation_size: 0,
+            log_rotation_count: 5,
+            log_rotation_age: crate::model::LogRotationAge::None,
+            log_destination: crate::model::LogDestination::File,
+            syslog_facility: "local0".to_string(),
+            syslog_ident: "pg_dbms_job".to_string(),
+            log_min_messages: crate::model::LogMinMessages::Log,
+            log_job_notices: false,
+            log_rotation_compress: crate::model::LogRotationCompress::None,
+            scheduler_event_log: false,
+            debug_ring_size: 0,
+            log_gelf_endpoint: String::new(),
+            auto_reload: false,
+            strict_config: false,
+            database_section: String::new(),
+            schemas: DEFAULT_SCHEMA.to_string(),
+            schema: DEFAULT_SCHEMA.to_string(),
         };
         dprint(&cfg, "LOG", "stderr fallback before reopen");
         reopen_logger();
@@ -926,4 +2095,242 @@ mod tests {
         );
         let _ = fs::remove_file(path);
     }
+
+    #[test]
+    fn dated_name_matches_rotated_files() {
+        let pieces = dated_name_pieces("pg_dbms_job-%Y-%m-%d.log");
+        assert!(matches_dated_name(&pieces, "pg_dbms_job-2026-10-16.log"));
+        assert!(matches_dated_name(&pieces, "pg_dbms_job-2026-10-16.log.gz"));
+        assert!(!matches_dated_name(&pieces, "pg_dbms_job-2026-10-16.log.1"));
+        assert!(!matches_dated_name(&pieces, "other-2026-10-16.log"));
+
+        let pieces = dated_name_pieces("job_%a");
+        assert!(matches_dated_name(&pieces, "job_Mon"));
+        assert!(!matches_dated_name(&pieces, "jobs_Mon"));
+
+        // `%%` is a literal percent sign, padding modifiers are escapes.
+        let pieces = dated_name_pieces("100%%-%-d.log");
+        assert_eq!(
+            pieces,
+            vec![Some("100%-".to_string()), None, Some(".log".to_string())]
+        );
+    }
+
+    #[test]
+    fn expire_removes_and_compresses_old_dated_files() {
+        use std::time::{Duration, SystemTime};
+
+        let dir = temp_log_path().with_extension("d");
+        fs::create_dir_all(&dir).expect("create log dir");
+        let mut config = test_config(&dir.join("pg_dbms_job-%Y%m%d.log"), false);
+        config.log_retention_days = 7;
+        config.log_compress = true;
+        let now = SystemTime::now();
+        let aged = |name: &str, days: u64| {
+            let path = dir.join(name);
+            let file = fs::File::create(&path).expect("create log file");
+            file.set_modified(now - Duration::from_secs(days * 86400 + 60))
+                .expect("set mtime");
+            path
+        };
+        let expired = aged("pg_dbms_job-20260901.log", 30);
+        let expired_gz = aged("pg_dbms_job-20260902.log.gz", 30);
+        let old = aged("pg_dbms_job-20261010.log", 3);
+        let recent = aged("pg_dbms_job-20261016.log", 0);
+        let unrelated = aged("other-20260901.log", 30);
+
+        expire_dated_files(&config, now);
+        assert!(!expired.exists());
+        assert!(!expired_gz.exists());
+        assert!(!old.exists());
+        assert!(dir.join("pg_dbms_job-20261010.log.gz").exists());
+        assert!(recent.exists());
+        assert!(unrelated.exists());
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn rotates_by_size_keeping_count_files() {
+        let dir = temp_log_path().with_extension("d");
+        fs::create_dir_all(&dir).expect("create log dir");
+        let path = dir.join("pg_dbms_job.log");
+        let fname = path.to_string_lossy().to_string();
+        // Left by a larger log_rotation_count.
+        fs::write(format!("{fname}.3"), "stale\n").expect("seed old log");
+        let mut config = test_config(&path, false);
+        config.log_rotation_size = 200;
+        config.log_rotation_count = 2;
+        // Lines are about 50 bytes, depending on the width of the pid: three
+        // or four per file.
+        for i in 0..30 {
+            dprint(&config, "LOG", &format!("rotation line {i:02}"));
+        }
+        flush_logger();
+
+        let current = fs::read_to_string(&path).expect("read log file");
+        let first = fs::read_to_string(format!("{fname}.1")).expect("read .1");
+        let second = fs::read_to_string(format!("{fname}.2")).expect("read .2");
+        assert!(!Path::new(&format!("{fname}.3")).exists());
+        for content in [&current, &first, &second] {
+            assert!(content.len() <= 200, "content={content:?}");
+        }
+        assert!(current.contains("rotation line 29"));
+        // `.1` holds the lines just before those of the current file.
+        let number = |line: &str| line.rsplit(' ').next().unwrap().to_string();
+        let last_rotated = number(first.lines().last().expect("lines in .1"));
+        let first_current = number(current.lines().next().expect("lines in log"));
+        assert_eq!(
+            last_rotated.parse::<u32>().unwrap() + 1,
+            first_current.parse::<u32>().unwrap()
+        );
+        // The oldest lines were rotated out.
+        assert!(!second.contains("rotation line 00"));
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn rotation_shifts_compressed_files() {
+        let dir = temp_log_path().with_extension("d");
+        fs::create_dir_all(&dir).expect("create log dir");
+        let fname = dir.join("pg_dbms_job.log").to_string_lossy().to_string();
+        for (name, content) in [
+            ("", "current"),
+            (".1.gz", "one"),
+            (".2.zst", "two"),
+            (".3", "three"),
+        ] {
+            fs::write(format!("{fname}{name}"), content).expect("seed log");
+        }
+        rotate_numbered(&fname, 3);
+        let read = |name: &str| fs::read_to_string(format!("{fname}{name}")).ok();
+        assert_eq!(read(".1").as_deref(), Some("current"));
+        assert_eq!(read(".2.gz").as_deref(), Some("one"));
+        assert_eq!(read(".3.zst").as_deref(), Some("two"));
+        assert_eq!(read(""), None);
+        assert_eq!(read(".4"), None);
+        assert_eq!(read(".1.gz"), None);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn rotated_files_are_compressed_in_background() {
+        use std::process::Command;
+        use std::time::{Duration, Instant};
+
+        let dir = temp_log_path().with_extension("d");
+        fs::create_dir_all(&dir).expect("create log dir");
+        let path = dir.join("pg_dbms_job.log");
+        let fname = path.to_string_lossy().to_string();
+        let mut config = test_config(&path, false);
+        config.log_rotation_size = 200;
+        config.log_rotation_count = 3;
+        config.log_rotation_compress = LogRotationCompress::Gzip;
+        for i in 0..10 {
+            dprint(&config, "LOG", &format!("compressed line {i:02}"));
+        }
+        flush_logger();
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while Path::new(&format!("{fname}.1")).exists() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        assert!(!Path::new(&format!("{fname}.1")).exists());
+        assert!(Path::new(&format!("{fname}.2.gz")).exists());
+        let output = Command::new("gzip")
+            .args(["-dc", &format!("{fname}.1.gz")])
+            .output()
+            .expect("run gzip");
+        let rotated = String::from_utf8_lossy(&output.stdout);
+        assert!(rotated.contains("compressed line"), "rotated={rotated:?}");
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn finds_the_file_renamed_by_an_external_rotation() {
+        use std::os::unix::fs::MetadataExt;
+
+        let dir = temp_log_path().with_extension("d");
+        fs::create_dir_all(&dir).expect("create log dir");
+        let path = dir.join("pg_dbms_job.log");
+        fs::write(&path, "line\n").expect("seed log");
+        let meta = fs::metadata(&path).expect("stat log");
+        let id = (meta.dev(), meta.ino());
+        let renamed = dir.join("pg_dbms_job.log-20261016");
+        fs::rename(&path, &renamed).expect("rename log");
+        fs::write(&path, "").expect("create new log");
+        let fname = path.to_string_lossy().to_string();
+        assert_eq!(find_renamed(&fname, id), Some(renamed.clone()));
+
+        fs::rename(&renamed, dir.join("pg_dbms_job.log-20261016.gz")).expect("rename log");
+        assert_eq!(find_renamed(&fname, id), None);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn rotates_a_file_of_a_previous_day() {
+        use std::time::{Duration, SystemTime};
+
+        let dir = temp_log_path().with_extension("d");
+        fs::create_dir_all(&dir).expect("create log dir");
+        let path = dir.join("pg_dbms_job.log");
+        let fname = path.to_string_lossy().to_string();
+        let file = fs::File::create(&path).expect("create log file");
+        fs::write(&path, "yesterday\n").expect("seed old log");
+        file.set_modified(SystemTime::now() - Duration::from_secs(2 * 86400))
+            .expect("set mtime");
+        let mut config = test_config(&path, false);
+        config.log_rotation_age = LogRotationAge::Daily;
+        dprint(&config, "LOG", "today");
+        dprint(&config, "LOG", "still today");
+        flush_logger();
+
+        let current = fs::read_to_string(&path).expect("read log file");
+        let rotated = fs::read_to_string(format!("{fname}.1")).expect("read .1");
+        assert_eq!(rotated, "yesterday\n");
+        assert!(current.contains("today") && current.contains("still today"));
+        assert!(!Path::new(&format!("{fname}.2")).exists());
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn expire_removes_old_rotated_files() {
+        use std::time::{Duration, SystemTime};
+
+        assert_eq!(rotated_base("pg_dbms_job.log.12"), Some("pg_dbms_job.log"));
+        assert_eq!(
+            rotated_base("pg_dbms_job.log.3.gz"),
+            Some("pg_dbms_job.log")
+        );
+        assert_eq!(
+            rotated_base("pg_dbms_job.log.4.zst"),
+            Some("pg_dbms_job.log")
+        );
+        assert_eq!(rotated_base("pg_dbms_job.log"), None);
+
+        let dir = temp_log_path().with_extension("d");
+        fs::create_dir_all(&dir).expect("create log dir");
+        let mut config = test_config(&dir.join("pg_dbms_job.log"), false);
+        config.log_retention_days = 7;
+        config.log_compress = true;
+        let now = SystemTime::now();
+        let aged = |name: &str, days: u64| {
+            let path = dir.join(name);
+            let file = fs::File::create(&path).expect("create log file");
+            file.set_modified(now - Duration::from_secs(days * 86400 + 60))
+                .expect("set mtime");
+            path
+        };
+        let current = aged("pg_dbms_job.log", 30);
+        let expired = aged("pg_dbms_job.log.2", 30);
+        let kept = aged("pg_dbms_job.log.1", 3);
+        let unrelated = aged("other.log.1", 30);
+
+        expire_dated_files(&config, now);
+        assert!(current.exists());
+        assert!(!expired.exists());
+        // Rotated files are not compressed.
+        assert!(kept.exists());
+        assert!(unrelated.exists());
+        let _ = fs::remove_dir_all(dir);
+    }
 }
@@ -1,39 +1,89 @@
 //! pg_dbms_job scheduler entry point.
 
 mod args;
+mod check;
 mod config;
 mod constants;
 mod db;
+mod dst;
+mod events;
+mod executable;
+mod gelf;
+mod healthcheck;
+mod install;
+mod jobfile;
 mod jobs;
+mod journald;
 mod logging;
+mod maintenance;
 mod model;
+mod priority;
 mod process;
+mod rds_iam;
+mod run_details;
+mod schedule;
+mod schema;
+mod show;
+mod simulate;
+mod syslog;
+mod tunnel;
 mod util;
+mod watch;
 
-use crate::args::{parse_args, usage};
-use crate::config::read_config;
-use crate::constants::{REAP_INTERVAL_SECS, VERSION, WORKER_SLOT_POLL_INTERVAL};
+use crate::args::{Args, parse_args, usage};
+use crate::check::{check_config, check_database};
+use crate::config::{
+    changed_settings, config_settings, database_sections, read_config, schema_list,
+};
+use crate::constants::{
+    DEFAULT_SCHEMA, PROGRAM, REGISTRY_HEARTBEAT_SECS, VERSION, WORKER_SLOT_POLL_INTERVAL,
+};
 use crate::db::JobPool;
-use crate::db::{ConnectError, connect_db, create_job_pool};
-use crate::jobs::{get_async_jobs, get_scheduled_jobs, reap_stale_jobs, spawn_job};
-use crate::logging::{dprint, reopen_logger, shutdown_logger};
-use crate::model::{Config, DbInfo, Job, JobKind, JobRunDetails, JobStats};
+use crate::db::{
+    ConnectError, async_channel, check_connection, check_connection_headroom, close_db, connect_db,
+    create_job_pool, describe_target, record_config_audit, scheduled_channel, send_heartbeat,
+};
+use crate::events::{EventLog, SchedulerEvent};
+use crate::healthcheck::run_healthcheck;
+use crate::install::{install_schema, upgrade_schema};
+use crate::jobfile::{JobFileFormat, dump_jobs, load_jobs};
+use crate::jobs::{
+    WorkerShared, check_hung_workers, check_maintenance_mode, check_run_deadlines, get_async_jobs,
+    get_scheduled_jobs, log_broken_jobs, reap_stale_jobs, refuse_oversized_jobs, requeue_jobs,
+    spawn_job,
+};
+use crate::logging::{
+    dprint, dump_debug_ring, enable_console_colors, expire_log_files, reopen_logger,
+    shutdown_logger,
+};
+use crate::maintenance::{Housekeeping, Task, maintain_run_details};
+use crate::model::{
+    AuthMethod, Config, ConfigAudit, DbInfo, IoClass, Job, JobFilter, JobKind, JobRunDetails,
+    JobStats, QueueDepth, SslMode, TargetSessionAttrs,
+};
 use crate::process::{
     daemonize, reap_children, release_pidfile, signal_handling, wait_all_children, write_pidfile,
 };
-use crate::util::die;
+use crate::schedule::{ScheduleCache, ScheduleEvent};
+use crate::show::show_config;
+use crate::simulate::{DEFAULT_RUNS, SimulateOptions, run_simulation};
+use crate::tunnel::Tunnel;
+use crate::util::{die, random_fraction};
+use crate::watch::ConfigWatcher;
 use fallible_iterator::FallibleIterator;
 use nix::sys::signal::Signal;
 use postgres::{Client, Notification};
-use signal_hook::consts::signal::{SIGHUP, SIGINT, SIGTERM};
+use signal_hook::consts::signal::{SIGHUP, SIGINT, SIGTERM, SIGUSR1, SIGUSR2};
 use signal_hook::flag;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::io::{self, IsTerminal};
 use std::path::Path;
-use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 use std::thread;
 use std::thread::JoinHandle;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime};
 
 fn main() {
     let mut args = parse_args();
@@ -51,15 +101,160 @@ fn main() {
         return;
     }
 
+    // A foreground run on a terminal logs to it in a readable form.
+    if args.single && io::stderr().is_terminal() && env::var_os("NO_COLOR").is_none() {
+        enable_console_colors();
+    }
+
     let mut config = default_config();
     let mut dbinfo = default_dbinfo();
 
+    if args.check {
+        let mut problems = check_config(&args.config_file, &mut config, &mut dbinfo);
+        if args.connect {
+            let mut tunnel = Tunnel::default();
+            match tunnel.ensure(&config, &dbinfo) {
+                Ok(_) => {
+                    for schema in schema_list(&config.schemas).unwrap_or_default() {
+                        match check_database(&tunnel.route(&dbinfo), &schema) {
+                            Ok(status) => println!("OK: {status}"),
+                            Err(err) => problems.push(format!("ERROR: {err}")),
+                        }
+                    }
+                }
+                Err(err) => problems.push(format!("ERROR: {err}")),
+            }
+        }
+        for problem in &problems {
+            println!("{problem}");
+        }
+        if !problems.is_empty() {
+            die(&format!(
+                "ERROR: {} problem(s) in configuration file {}",
+                problems.len(),
+                args.config_file
+            ));
+        }
+        println!("OK: configuration file {} is valid", args.config_file);
+        return;
+    }
+
+    if args.show_config {
+        if let Err(err) = std::fs::read_to_string(&args.config_file) {
+            die(&format!(
+                "FATAL: can not read the configuration file {}, {err}",
+                args.config_file
+            ));
+        }
+        let (settings, problems) = show_config(
+            &args.config_file,
+            &mut config,
+            &mut dbinfo,
+            args.debug_override,
+        );
+        for problem in &problems {
+            eprintln!("{problem}");
+        }
+        for setting in &settings {
+            println!("{setting}");
+        }
+        return;
+    }
+
     read_config(&args.config_file, &mut config, &mut dbinfo, false);
 
     if let Some(debug) = args.debug_override {
         config.debug = debug;
     }
 
+    // Tool commands reach the database through their own tunnel, if any.
+    let mut tool_tunnel = Tunnel::default();
+    if args.healthcheck
+        || args.simulate.is_some()
+        || args.dump_jobs
+        || args.load_jobs.is_some()
+        || args.install_schema
+        || args.upgrade_schema
+    {
+        if let Err(err) = tool_tunnel.ensure(&config, &dbinfo) {
+            die(&format!("ERROR: {err}"));
+        }
+    }
+    let tool_dbinfo = tool_tunnel.route(&dbinfo);
+    let tool_schema = match &args.schema {
+        Some(name) => match schema_list(name) {
+            Ok(mut names) if names.len() == 1 => names.remove(0),
+            Ok(_) => die("ERROR: --schema takes a single schema name"),
+            Err(err) => die(&format!("ERROR: invalid --schema, {err}")),
+        },
+        None => schema_list(&config.schemas)
+            .ok()
+            .and_then(|names| names.into_iter().next())
+            .unwrap_or_else(|| DEFAULT_SCHEMA.to_string()),
+    };
+
+    if args.healthcheck {
+        match run_healthcheck(&config.pidfile, &tool_dbinfo, &tool_schema) {
+            Ok(status) => println!("OK: {status}"),
+            Err(err) => die(&format!("ERROR: {err}")),
+        }
+        return;
+    }
+
+    if let Some(job) = args.simulate {
+        let opts = SimulateOptions {
+            schema: &tool_schema,
+            job,
+            interval: args.simulate_interval.as_deref(),
+            from: args.simulate_from.as_deref(),
+            until: args.simulate_until.as_deref(),
+            runs: args.simulate_runs.unwrap_or(DEFAULT_RUNS),
+            dst_policy: config.dst_policy,
+        };
+        if let Err(err) = run_simulation(&tool_dbinfo, &opts) {
+            die(&format!("ERROR: {err}"));
+        }
+        return;
+    }
+
+    if args.dump_jobs {
+        let result = JobFileFormat::new(args.format.as_deref(), None)
+            .and_then(|format| dump_jobs(&tool_dbinfo, &tool_schema, format));
+        if let Err(err) = result {
+            die(&format!("ERROR: {err}"));
+        }
+        return;
+    }
+
+    if let Some(path) = &args.load_jobs {
+        let result = JobFileFormat::new(args.format.as_deref(), Some(path)).and_then(|format| {
+            load_jobs(
+                &tool_dbinfo,
+                &tool_schema,
+                path,
+                format,
+                args.prune,
+                args.diff,
+            )
+        });
+        if let Err(err) = result {
+            die(&format!("ERROR: {err}"));
+        }
+        return;
+    }
+
+    if args.install_schema || args.upgrade_schema {
+        let result = if args.install_schema {
+            install_schema(&tool_dbinfo, &tool_schema)
+        } else {
+            upgrade_schema(&tool_dbinfo, &tool_schema)
+        };
+        if let Err(err) = result {
+            die(&format!("ERROR: {err}"));
+        }
+        return;
+    }
+
     if args.kill {
         signal_handling(&config.pidfile, Signal::SIGTERM);
         return;
@@ -69,8 +264,18 @@ fn main() {
     } else if args.reload {
         signal_handling(&config.pidfile, Signal::SIGHUP);
         return;
+    } else if args.poll_now {
+        signal_handling(&config.pidfile, Signal::SIGUSR1);
+        return;
+    } else if args.dump_debug {
+        signal_handling(&config.pidfile, Signal::SIGUSR2);
+        return;
     }
 
+    let job_filter = args
+        .job_filter()
+        .unwrap_or_else(|err| die(&format!("ERROR: {err}")));
+
     if !args.single {
         daemonize(&config);
     }
@@ -80,294 +285,767 @@ fn main() {
     }
 
     let terminate_flag = Arc::new(AtomicBool::new(false));
-    let reload_flag = Arc::new(AtomicBool::new(false));
+    let dump_flag = Arc::new(AtomicBool::new(false));
 
     flag::register(SIGINT, Arc::clone(&terminate_flag)).expect("register SIGINT");
     flag::register(SIGTERM, Arc::clone(&terminate_flag)).expect("register SIGTERM");
-    flag::register(SIGHUP, Arc::clone(&reload_flag)).expect("register SIGHUP");
+    flag::register(SIGUSR2, Arc::clone(&dump_flag)).expect("register SIGUSR2");
 
     dprint(&config, "LOG", "Entering main loop.");
+    let watcher = Mutex::new(ConfigWatcher::default());
+    let sections = database_sections(&args.config_file, &config);
+    let databases = if sections.is_empty() {
+        vec![(config.clone(), dbinfo)]
+    } else {
+        sections
+            .into_iter()
+            .map(|name| {
+                let mut db_config = default_config();
+                db_config.database_section = name;
+                let mut db_dbinfo = default_dbinfo();
+                read_config(&args.config_file, &mut db_config, &mut db_dbinfo, true);
+                if let Some(debug) = args.debug_override {
+                    db_config.debug = debug;
+                }
+                (db_config, db_dbinfo)
+            })
+            .collect()
+    };
+    // One scheduler loop per database and job queue schema.
+    let mut loops: Vec<(Config, DbInfo)> = Vec::new();
+    for (db_config, db_dbinfo) in databases {
+        let schemas =
+            schema_list(&db_config.schemas).unwrap_or_else(|_| vec![DEFAULT_SCHEMA.to_string()]);
+        for schema in schemas {
+            let schema_config = Config {
+                schema,
+                ..db_config.clone()
+            };
+            loops.push((schema_config, db_dbinfo.clone()));
+        }
+    }
+    let alone = loops.len() == 1;
+    let pidfile = if alone {
+        let (loop_config, loop_dbinfo) = loops.remove(0);
+        let scheduler = Scheduler {
+            args: &args,
+            job_filter: &job_filter,
+            terminate_flag: &terminate_flag,
+            dump_flag: &dump_flag,
+            watcher: &watcher,
+            owns_pidfile: true,
+            alone,
+        };
+        scheduler.run(loop_config, loop_dbinfo)
+    } else {
+        // Each loop has its own connections and job_queue_processes; the
+        // first one looks after the pid file.
+        thread::scope(|scope| {
+            let loops: Vec<_> = loops
+                .into_iter()
+                .enumerate()
+                .map(|(i, (loop_config, loop_dbinfo))| {
+                    let scheduler = Scheduler {
+                        args: &args,
+                        job_filter: &job_filter,
+                        terminate_flag: &terminate_flag,
+                        dump_flag: &dump_flag,
+                        watcher: &watcher,
+                        owns_pidfile: i == 0,
+                        alone,
+                    };
+                    scope.spawn(move || scheduler.run(loop_config, loop_dbinfo))
+                })
+                .collect();
+            let pidfiles: Vec<String> = loops
+                .into_iter()
+                .map(|handle| handle.join().unwrap_or_default())
+                .collect();
+            pidfiles.into_iter().next().unwrap_or_default()
+        })
+    };
+    let config = if pidfile.is_empty() {
+        config
+    } else {
+        Config { pidfile, ..config }
+    };
+    release_pidfile();
+    if Path::new(&config.pidfile).exists()
+        && let Err(err) = std::fs::remove_file(&config.pidfile)
+    {
+        dlog!(
+            &config,
+            "ERROR",
+            "Unable to remove pid file {}, {}",
+            config.pidfile,
+            err
+        );
+    }
 
-    let mut config = Arc::new(config);
-    let mut dbh: Option<Client> = None;
-    let mut job_pool: Option<Arc<JobPool>> = None;
-    let mut running_workers: HashMap<u64, JoinHandle<()>> = HashMap::new();
-    let mut next_worker_id: u64 = 1;
-    let mut scheduled_jobs: HashMap<i64, Job> = HashMap::new();
-    let mut async_jobs: HashMap<i64, Job> = HashMap::new();
-    let mut previous_async_exec = Instant::now();
-    let mut previous_scheduled_exec = Instant::now();
-    let mut previous_reap = Instant::now();
-    let job_stats = Arc::new(JobStats::default());
-    let mut last_stats_at = Instant::now();
-    let mut last_saturation_log: Option<Instant> = None;
-    let mut startup = true;
-    let mut config_invalidated = false;
-    let mut in_recovery_logged = false;
-
-    while !terminate_flag.load(Ordering::Relaxed) {
-        reap_children(&mut running_workers);
-
-        if config.stats_interval > 0 && last_stats_at.elapsed().as_secs() >= config.stats_interval {
-            let elapsed = last_stats_at.elapsed().as_secs();
-            let (started, finished) = job_stats.drain();
-            dlog!(
-                &config,
-                "LOG",
-                "stats: jobs started={}, finished={} in last {} seconds",
-                started,
-                finished,
-                elapsed
-            );
-            last_stats_at = Instant::now();
-        }
-
-        if reload_flag.swap(false, Ordering::Relaxed) {
-            // Drop the persistent log file handle *before* writing anything.
-            // After logrotate-style rotation (rename pg_dbms_job.log →
-            // pg_dbms_job.log.1, create a fresh pg_dbms_job.log) our open fd
-            // still points at the renamed-aside inode, so any line emitted now
-            // — including the "Received reload" line below and whatever
-            // read_config() logs — would land in the old file. Reopening first
-            // makes the next write re-open the configured path, i.e. the new
-            // file, which is also what `lsof` will then show.
-            reopen_logger();
-            dprint(&config, "LOG", "Received reload signal HUP.");
-            let mut cfg = Config::clone(&config);
-            let old_pidfile = cfg.pidfile.clone();
-            read_config(&args.config_file, &mut cfg, &mut dbinfo, true);
-            if old_pidfile != cfg.pidfile {
-                if let Err(err) = std::fs::rename(&old_pidfile, &cfg.pidfile) {
-                    cfg.pidfile = old_pidfile.clone();
-                    config = Arc::new(cfg);
+    dprint(&config, "LOG", "pg_dbms_job scheduler stopped.");
+    shutdown_logger();
+}
+
+/// What the scheduler loops of a daemon share: one loop serves each job
+/// queue schema of `schemas` in the database of the settings outside
+/// sections, or in the one of each `[database <name>]` section.
+struct Scheduler<'a> {
+    args: &'a Args,
+    job_filter: &'a JobFilter,
+    terminate_flag: &'a AtomicBool,
+    dump_flag: &'a AtomicBool,
+    watcher: &'a Mutex<ConfigWatcher>,
+    /// Whether this loop renames the pid file when `pidfile` changes.
+    owns_pidfile: bool,
+    /// Whether this loop is the only one: when it can not serve its
+    /// database the daemon stops, instead of leaving the others running.
+    alone: bool,
+}
+
+impl Scheduler<'_> {
+    /// Poll and run the jobs of the database of `config` and `dbinfo`
+    /// until asked to stop, returning the path of the pid file.
+    fn run(self, config: Config, mut dbinfo: DbInfo) -> String {
+        let Scheduler {
+            args,
+            job_filter,
+            terminate_flag,
+            dump_flag,
+            watcher,
+            owns_pidfile,
+            alone,
+        } = self;
+        let reload_flag = Arc::new(AtomicBool::new(false));
+        let poll_flag = Arc::new(AtomicBool::new(false));
+        flag::register(SIGHUP, Arc::clone(&reload_flag)).expect("register SIGHUP");
+        flag::register(SIGUSR1, Arc::clone(&poll_flag)).expect("register SIGUSR1");
+
+        // Written to dbms_job.scheduler_event_log once connected.
+        let mut events = EventLog::default();
+        events.record(
+            SchedulerEvent::Start,
+            format!("{PROGRAM} {VERSION} started"),
+        );
+
+        let started_at = SystemTime::now();
+        let uptime_start = Instant::now();
+        let mut connections: u64 = 0;
+        let mut max_concurrent: usize = 0;
+        let mut config = Arc::new(config);
+        let mut dbh: Option<Client> = None;
+        // Connection to the database in recovery waiting for its promotion.
+        let mut standby: Option<Client> = None;
+        let mut job_pool: Option<Arc<JobPool>> = None;
+        let mut running_workers: HashMap<u64, JoinHandle<()>> = HashMap::new();
+        let mut scheduled_jobs: HashMap<i64, Job> = HashMap::new();
+        let mut async_jobs: HashMap<i64, Job> = HashMap::new();
+        let mut previous_async_exec = Instant::now();
+        let mut previous_scheduled_exec = Instant::now();
+        let shared = Arc::new(WorkerShared::default());
+        let mut last_stats_at = Instant::now();
+        let mut last_saturation_log: Option<Instant> = None;
+        let mut startup = true;
+        let mut config_invalidated = false;
+        let mut in_recovery_logged = false;
+        let mut schedule_cache = ScheduleCache::default();
+        let mut maintenance_paused = false;
+        let mut headroom_low = false;
+        let mut housekeeping = Housekeeping::new(Instant::now());
+        let mut queue_depth: Option<QueueDepth> = None;
+        let mut tunnel = Tunnel::default();
+        watcher
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .ensure(&config, &args.config_file);
+        // Claims were cut short by the free worker slots: more jobs may be due.
+        let mut async_backlog = false;
+        let mut scheduled_backlog = false;
+        // A reload or SIGUSR1 asked for due jobs to be collected right away.
+        let mut poll_now = false;
+        // Current notification wait, raised up to max_nap_time while idle.
+        let mut idle_nap = config.nap_time;
+        let mut pending_audit: Option<ConfigAudit> = None;
+        let mut reconnect = Reconnect::default();
+        let mut last_connection_check = Instant::now();
+        // The main connection was found dead, to be reopened.
+        let mut connection_lost = false;
+
+        while !terminate_flag.load(Ordering::Relaxed) {
+            reap_children(&mut running_workers);
+
+            // A main connection that no longer answers is dropped before
+            // anything waits on it, and reopened below.
+            if let Some(client) = dbh.as_mut()
+                && (client.is_closed()
+                    || (config.connection_check_interval > 0.0
+                        && last_connection_check.elapsed().as_secs_f64()
+                            >= config.connection_check_interval))
+            {
+                last_connection_check = Instant::now();
+                if let Err(err) = check_connection(client) {
                     dlog!(
                         &config,
-                        "ERROR",
-                        "can't change path to pid keeping old one {}, {}",
-                        old_pidfile,
+                        "WARNING",
+                        "lost the connection to the database, {}",
                         err
                     );
-                } else {
-                    config = Arc::new(cfg);
-                    dlog!(
-                        &config,
-                        "LOG",
-                        "path to pid file has changed, rename {} into {}",
-                        old_pidfile,
-                        config.pidfile
-                    );
+                    // Dropping a client waits for the answers to its pending
+                    // queries: done aside, so that a hung server does not
+                    // hold the loop.
+                    let dead = dbh.take();
+                    thread::spawn(move || drop(dead));
+                    connection_lost = true;
                 }
-            } else {
-                config = Arc::new(cfg);
             }
-            config_invalidated = true;
-        }
 
-        if config_invalidated {
-            let _ = dbh.take();
-            job_pool = None;
-        }
+            if config.stats_interval > 0
+                && last_stats_at.elapsed().as_secs() >= config.stats_interval
+            {
+                let elapsed = last_stats_at.elapsed().as_secs();
+                let (started, finished) = shared.stats.drain();
+                // Broken jobs are silently passed over by the claim query; list
+                // the due ones so "my job is not running" has a visible answer.
+                let broken = dbh
+                    .as_mut()
+                    .and_then(|client| log_broken_jobs(client, &config, "LOG"))
+                    .map(|n| format!(", broken due jobs={n}"))
+                    .unwrap_or_default();
+                dlog!(
+                    &config,
+                    "LOG",
+                    "stats: jobs started={}, finished={} in last {} seconds{}{}",
+                    started,
+                    finished,
+                    elapsed,
+                    pending_summary(queue_depth),
+                    broken
+                );
+                last_stats_at = Instant::now();
+            }
 
-        if dbh.is_none() {
-            match connect_db(&dbinfo, &config) {
-                Ok(client) => {
-                    if in_recovery_logged {
-                        dprint(&config, "LOG", "database has exited recovery mode");
-                        in_recovery_logged = false;
-                    }
-                    dbh = Some(client);
-                }
-                Err(ConnectError::InRecovery) => {
-                    if !in_recovery_logged {
-                        dprint(
+            for worker in run_housekeeping(
+                &mut housekeeping,
+                &mut dbh,
+                &config,
+                &shared,
+                &mut queue_depth,
+            ) {
+                running_workers.remove(&worker);
+            }
+
+            if reload_flag.swap(false, Ordering::Relaxed) {
+                // Drop the persistent log file handle *before* writing anything.
+                // After logrotate-style rotation (rename pg_dbms_job.log →
+                // pg_dbms_job.log.1, create a fresh pg_dbms_job.log) our open fd
+                // still points at the renamed-aside inode, so any line emitted now
+                // — including the "Received reload" line below and whatever
+                // read_config() logs — would land in the old file. Reopening first
+                // makes the next write re-open the configured path, i.e. the new
+                // file, which is also what `lsof` will then show.
+                reopen_logger();
+                // Apply new retention settings on the next iteration.
+                housekeeping.reset(Task::OversizedJobs);
+                housekeeping.reset(Task::LogRetention);
+                housekeeping.reset(Task::HistoryPurge);
+                dprint(&config, "LOG", "Received reload signal HUP.");
+                let before = config_settings(&config, &dbinfo);
+                let mut cfg = Config::clone(&config);
+                let old_pidfile = cfg.pidfile.clone();
+                read_config(&args.config_file, &mut cfg, &mut dbinfo, true);
+                if owns_pidfile && old_pidfile != cfg.pidfile {
+                    if let Err(err) = std::fs::rename(&old_pidfile, &cfg.pidfile) {
+                        cfg.pidfile = old_pidfile.clone();
+                        config = Arc::new(cfg);
+                        dlog!(
                             &config,
-                            "WARNING",
-                            "database is in recovery, retrying later",
+                            "ERROR",
+                            "can't change path to pid keeping old one {}, {}",
+                            old_pidfile,
+                            err
+                        );
+                    } else {
+                        config = Arc::new(cfg);
+                        dlog!(
+                            &config,
+                            "LOG",
+                            "path to pid file has changed, rename {} into {}",
+                            old_pidfile,
+                            config.pidfile
                         );
-                        in_recovery_logged = true;
                     }
-                    thread::sleep(Duration::from_secs_f64(config.startup_delay));
-                    startup = true;
-                    config_invalidated = true;
-                    continue;
+                } else {
+                    config = Arc::new(cfg);
+                }
+                idle_nap = config.nap_time;
+                watcher
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .ensure(&config, &args.config_file);
+                let changes = changed_settings(&before, &config_settings(&config, &dbinfo));
+                events.record(
+                    SchedulerEvent::Reload,
+                    reload_detail(&args.config_file, &changes),
+                );
+                // Recorded once reconnected, possibly to another database.
+                pending_audit = Some(ConfigAudit {
+                    config_file: args.config_file.clone(),
+                    content: std::fs::read(&args.config_file).ok(),
+                    changes,
+                });
+                config_invalidated = true;
+                poll_now = true;
+            }
+
+            if poll_flag.swap(false, Ordering::Relaxed) {
+                dprint(&config, "LOG", "Received poll signal USR1.");
+                poll_now = true;
+            }
+
+            if dump_flag.swap(false, Ordering::Relaxed) {
+                dump_debug_ring(&config);
+            }
+
+            match tunnel.ensure(&config, &dbinfo) {
+                Ok(false) => {}
+                Ok(true) => {
+                    // The route to the database changed: reconnect through it.
+                    let _ = dbh.take();
+                    standby = None;
+                    job_pool = None;
                 }
                 Err(err) => {
                     dlog!(&config, "ERROR", "{}", err);
-                    thread::sleep(Duration::from_secs_f64(config.startup_delay));
+                    reconnect.wait(&config);
                     startup = true;
                     config_invalidated = true;
                     continue;
                 }
             }
-        }
 
-        if job_pool.is_none() {
-            let effective_pool_size = config.pool_size.min(config.job_queue_processes) as u32;
-            match create_job_pool(&dbinfo, effective_pool_size) {
-                Ok(pool) => {
-                    dlog!(
-                        &config,
-                        "LOG",
-                        "Connection pool created with max size {}",
-                        effective_pool_size
+            // A closed main connection is reopened right away.
+            if config_invalidated || connection_lost || dbh.as_ref().is_some_and(Client::is_closed)
+            {
+                if dbh.take().is_some_and(|client| client.is_closed()) {
+                    connection_lost = true;
+                }
+                if connection_lost {
+                    connection_lost = false;
+                    events.record(
+                        SchedulerEvent::ConnectionLost,
+                        format!("connection to {} lost", describe_target(&dbinfo)),
                     );
-                    job_pool = Some(Arc::new(pool));
+                    // Notifications sent while nothing listened are lost:
+                    // the queue is read in full once reconnected.
+                    poll_now = true;
                 }
-                Err(err) => {
-                    dlog!(&config, "ERROR", "Failed to create connection pool: {err}");
-                    thread::sleep(Duration::from_secs_f64(config.startup_delay));
-                    startup = true;
-                    config_invalidated = true;
-                    continue;
+                standby = None;
+                job_pool = None;
+            }
+
+            if dbh.is_none() {
+                match connect_db(&tunnel.route(&dbinfo), &config, started_at, &mut standby) {
+                    Ok(mut client) => {
+                        connections += 1;
+                        // Registration has just sent the heartbeat.
+                        housekeeping.mark(Task::Heartbeat, Instant::now());
+                        if let Some(audit) = pending_audit.take() {
+                            record_config_audit(&mut client, &config, &audit);
+                        }
+                        if in_recovery_logged {
+                            dprint(&config, "LOG", "database has exited recovery mode");
+                            in_recovery_logged = false;
+                        }
+                        dbh = Some(client);
+                        // Changes made while we were not listening were missed.
+                        schedule_cache.invalidate();
+                    }
+                    Err(err @ ConnectError::IncompatibleSchema(_)) => {
+                        // Retrying can not help: every job query would fail.
+                        if alone {
+                            dlog!(&config, "FATAL", "{}", err);
+                            die(&format!("FATAL: {err}"));
+                        }
+                        // The other loops keep running.
+                        dlog!(
+                            &config,
+                            "FATAL",
+                            "{}, stopping the scheduler of this job queue",
+                            err
+                        );
+                        break;
+                    }
+                    Err(err @ ConnectError::AlreadyRunning(_)) => {
+                        if alone {
+                            dlog!(&config, "FATAL", "{}! Aborting.", err);
+                            die(&format!("FATAL: {err}! Aborting."));
+                        }
+                        dlog!(
+                            &config,
+                            "FATAL",
+                            "{}! Stopping the scheduler of this job queue.",
+                            err
+                        );
+                        break;
+                    }
+                    Err(ConnectError::InRecovery) if standby.is_some() => {
+                        if !in_recovery_logged {
+                            dprint(
+                                &config,
+                                "WARNING",
+                                "database is in recovery, waiting for its promotion",
+                            );
+                            in_recovery_logged = true;
+                        }
+                        thread::sleep(Duration::from_secs_f64(config.standby_check_interval));
+                        startup = true;
+                        continue;
+                    }
+                    Err(ConnectError::InRecovery) => {
+                        if !in_recovery_logged {
+                            dprint(
+                                &config,
+                                "WARNING",
+                                "database is in recovery, retrying later",
+                            );
+                            in_recovery_logged = true;
+                        }
+                        reconnect.wait(&config);
+                        startup = true;
+                        config_invalidated = true;
+                        continue;
+                    }
+                    Err(err) => {
+                        dlog!(&config, "ERROR", "{}", err);
+                        reconnect.wait(&config);
+                        startup = true;
+                        config_invalidated = true;
+                        continue;
+                    }
                 }
             }
-        }
 
-        let mut async_count = 0usize;
-        let mut scheduled_count = 0usize;
+            if job_pool.is_none() {
+                let effective_pool_size = config.pool_size.min(config.job_queue_processes) as u32;
+                match create_job_pool(&tunnel.route(&dbinfo), effective_pool_size) {
+                    Ok(pool) => {
+                        dlog!(
+                            &config,
+                            "LOG",
+                            "Connection pool created with max size {}",
+                            effective_pool_size
+                        );
+                        job_pool = Some(Arc::new(pool));
+                    }
+                    Err(err) => {
+                        dlog!(&config, "ERROR", "Failed to create connection pool: {err}");
+                        reconnect.wait(&config);
+                        startup = true;
+                        config_invalidated = true;
+                        continue;
+                    }
+                }
+            }
 
-        if let Some(client) = dbh.as_mut() {
-            config_invalidated = false;
-            let mut notifications = client.notifications();
-            collect_notifications(
-                &mut notifications,
-                &config,
-                Duration::from_secs_f64(config.nap_time),
-                &mut async_count,
-                &mut scheduled_count,
-            );
-        } else {
-            thread::sleep(Duration::from_secs_f64(config.startup_delay));
-            startup = true;
-            config_invalidated = true;
-            continue;
-        }
+            if let Some(client) = dbh.as_mut() {
+                events.flush(client, &config);
+            }
 
-        if async_count == 0
-            && !startup
-            && previous_async_exec.elapsed().as_secs_f64() >= config.job_queue_interval
-        {
-            dprint(
-                &config,
-                "DEBUG",
-                "job_queue_interval reached, forcing collect of asynchronous jobs",
-            );
-            async_count = 1;
-        }
-        if scheduled_count == 0
-            && !startup
-            && previous_scheduled_exec.elapsed().as_secs_f64() >= config.job_queue_interval
-        {
-            dprint(
-                &config,
-                "DEBUG",
-                "job_queue_interval reached, forcing collect of scheduled jobs",
-            );
-            scheduled_count = 1;
-        }
+            let mut tally = NotificationTally::default();
 
-        if async_count > 0 || startup {
             if let Some(client) = dbh.as_mut() {
-                get_async_jobs(client, &config, &mut async_jobs);
+                config_invalidated = false;
+                collect_notifications(
+                    &mut client.notifications(),
+                    &config,
+                    cycle_nap(
+                        idle_nap,
+                        schedule_cache.time_until_due(
+                            Instant::now(),
+                            Duration::from_secs_f64(config.schedule_refresh_interval),
+                        ),
+                        &config,
+                    ),
+                    Duration::from_secs_f64(config.notify_debounce),
+                    &mut tally,
+                );
+                // Broken during the wait: reopened at once rather than after
+                // the claims have failed on it.
+                if client.is_closed() {
+                    continue;
+                }
+                if tally.unknown_scheduled {
+                    schedule_cache.invalidate();
+                }
+                for event in &tally.schedule_events {
+                    schedule_cache.apply(client, &config, *event);
+                }
+            } else {
+                reconnect.wait(&config);
+                startup = true;
+                config_invalidated = true;
+                continue;
             }
-            previous_async_exec = Instant::now();
-        }
 
-        if scheduled_count > 0 || startup {
-            if let Some(client) = dbh.as_mut() {
-                get_scheduled_jobs(
-                    client,
+            let mut async_count = tally.async_count;
+            let schedule_refresh = Duration::from_secs_f64(config.schedule_refresh_interval);
+
+            if async_count == 0
+                && !startup
+                && previous_async_exec.elapsed().as_secs_f64() >= config.job_queue_interval
+            {
+                dprint(
                     &config,
-                    &mut config_invalidated,
-                    &mut scheduled_jobs,
+                    "DEBUG",
+                    "job_queue_interval reached, forcing collect of asynchronous jobs",
                 );
+                async_count = 1;
             }
-            previous_scheduled_exec = Instant::now();
-            if config_invalidated {
-                thread::sleep(Duration::from_secs_f64(config.startup_delay));
-                startup = true;
+            let mut collect_async = async_count > 0 || startup || async_backlog || poll_now;
+            // When every notification named its jobs, only those are claimed;
+            // the queue is still read in full every job_queue_interval, for
+            // the jobs whose notification was missed.
+            let notified_async = if tally.async_count > 0
+                && !tally.unknown_async
+                && !startup
+                && !async_backlog
+                && !poll_now
+                && previous_async_exec.elapsed().as_secs_f64() < config.job_queue_interval
+            {
+                let mut jobs = std::mem::take(&mut tally.async_jobs);
+                jobs.sort_unstable();
+                jobs.dedup();
+                Some(jobs)
+            } else {
+                None
+            };
+            // Scheduled jobs are only claimed when the schedule cache says one is
+            // due: right away after a change notification, otherwise at most every
+            // job_queue_interval.
+            let mut collect_scheduled = startup
+                || scheduled_backlog
+                || poll_now
+                || ((!tally.schedule_events.is_empty()
+                    || tally.unknown_scheduled
+                    || previous_scheduled_exec.elapsed().as_secs_f64()
+                        >= config.job_queue_interval)
+                    && schedule_cache.is_due(Instant::now(), schedule_refresh));
+            poll_now = false;
+
+            // Only claim as many jobs as there are free worker slots: a claimed
+            // job has its next_date advanced and its this_date held while it
+            // waits. Due jobs beyond that stay in the queue and are claimed in a
+            // later cycle. A --single run claims everything once.
+            let max_workers = effective_max_workers(&config);
+            let mut free_slots = if args.single {
+                usize::MAX
+            } else {
+                max_workers.saturating_sub(running_workers.len())
+            };
+            if free_slots == 0 {
+                async_backlog |= collect_async;
+                scheduled_backlog |= collect_scheduled;
+                collect_async = false;
+                collect_scheduled = false;
+            }
+
+            // While dbms_job.maintenance_mode is set nothing is claimed. The
+            // collect timers are left running so the first check after the
+            // switch is cleared claims everything that became due meanwhile.
+            if (collect_async || collect_scheduled)
+                && let Some(client) = dbh.as_mut()
+                && check_maintenance_mode(client, &config, &mut maintenance_paused)
+            {
                 continue;
             }
-        }
+            // Same when the server is short of connection slots: claiming jobs
+            // now would take the last ones, locking out even superusers.
+            if (collect_async || collect_scheduled)
+                && let Some(client) = dbh.as_mut()
+            {
+                let pool_idle = job_pool
+                    .as_ref()
+                    .map_or(0, |pool| pool.state().idle_connections);
+                if check_connection_headroom(client, &config, pool_idle, &mut headroom_low) {
+                    continue;
+                }
+            }
+
+            if collect_async {
+                if let Some(client) = dbh.as_mut() {
+                    async_backlog = get_async_jobs(
+                        client,
+                        &config,
+                        job_filter,
+                        notified_async.as_deref(),
+                        free_slots,
+                        &mut async_jobs,
+                    );
+                    free_slots = free_slots.saturating_sub(async_jobs.len());
+                }
+                if notified_async.is_none() {
+                    previous_async_exec = Instant::now();
+                }
+            }
+
+            if collect_scheduled {
+                dprint(
+                    &config,
+                    "DEBUG",
+                    "schedule cache has due jobs, collecting scheduled jobs",
+                );
+                if free_slots == 0 {
+                    scheduled_backlog = true;
+                } else if let Some(client) = dbh.as_mut() {
+                    scheduled_backlog = get_scheduled_jobs(
+                        client,
+                        &config,
+                        job_filter,
+                        free_slots,
+                        &mut config_invalidated,
+                        &mut scheduled_jobs,
+                    );
+                    if config.logs("DEBUG") && !config_invalidated {
+                        log_broken_jobs(client, &config, "DEBUG");
+                    }
+                    // Claimed jobs got a new next_date.
+                    if !config_invalidated
+                        && (!scheduled_jobs.is_empty()
+                            || schedule_cache.needs_reload(Instant::now(), schedule_refresh))
+                    {
+                        schedule_cache.reload(client, &config);
+                    }
+                }
+                previous_scheduled_exec = Instant::now();
+                if config_invalidated {
+                    reconnect.wait(&config);
+                    startup = true;
+                    continue;
+                }
+            }
 
-        config_invalidated = false;
-        startup = false;
+            config_invalidated = false;
+            startup = false;
+            reconnect.reset();
+
+            // Back off the notification wait while nothing happens, and snap
+            // back to nap_time on the first sign of activity.
+            let active = tally.async_count > 0
+                || !tally.schedule_events.is_empty()
+                || tally.unknown_scheduled
+                || !async_jobs.is_empty()
+                || !scheduled_jobs.is_empty()
+                || async_backlog
+                || scheduled_backlog;
+            idle_nap = if active {
+                config.nap_time
+            } else {
+                backoff_nap(idle_nap, &config)
+            };
 
-        // Periodically re-queue jobs abandoned by workers that never cleared
-        // their dispatch marker (e.g. a worker that could not obtain a pooled
-        // connection, or a crashed worker/daemon). Without this such rows stay
-        // flagged running forever and silently disappear from the queue. The
-        // check cadence is capped so it is never coarser than the eligibility
-        // age itself.
-        if config.stale_job_timeout > 0.0
-            && previous_reap.elapsed().as_secs_f64()
-                >= REAP_INTERVAL_SECS.min(config.stale_job_timeout)
-        {
+            // Jobs whose worker could not get a connection are handed back and
+            // made eligible again after error_delay.
             if let Some(client) = dbh.as_mut() {
-                reap_stale_jobs(client, &config);
+                requeue_jobs(client, &config, &shared.requeue);
             }
-            previous_reap = Instant::now();
-        }
 
-        let max_workers = effective_max_workers(&config);
+            // Highest priority first, so that when the worker slots run out
+            // the critical jobs are not the ones kept waiting.
+            for (kind, job) in dispatch_order(&mut scheduled_jobs, &mut async_jobs) {
+                await_worker_slot(
+                    &mut running_workers,
+                    max_workers,
+                    &config,
+                    &mut last_saturation_log,
+                    &mut events,
+                    &mut || {
+                        run_housekeeping(
+                            &mut housekeeping,
+                            &mut dbh,
+                            &config,
+                            &shared,
+                            &mut queue_depth,
+                        )
+                    },
+                );
+                spawn_job(
+                    kind,
+                    job,
+                    job_pool.as_ref().unwrap(),
+                    &config,
+                    &shared,
+                    &mut running_workers,
+                );
+                max_concurrent = max_concurrent.max(running_workers.len());
+            }
 
-        for (_, job) in scheduled_jobs.drain() {
-            await_worker_slot(
-                &mut running_workers,
-                max_workers,
-                &config,
-                &mut last_saturation_log,
-            );
-            spawn_job(
-                JobKind::Scheduled,
-                job,
-                job_pool.as_ref().unwrap(),
-                &config,
-                &job_stats,
-                &mut running_workers,
-                &mut next_worker_id,
-            );
+            if args.single {
+                break;
+            }
         }
 
-        for (_, job) in async_jobs.drain() {
-            await_worker_slot(
-                &mut running_workers,
-                max_workers,
-                &config,
-                &mut last_saturation_log,
-            );
-            spawn_job(
-                JobKind::Async,
-                job,
-                job_pool.as_ref().unwrap(),
+        wait_all_children(&mut running_workers);
+        // Workers are done with their pooled connections: close them, then the
+        // LISTEN connection.
+        drop(job_pool);
+        let summary = shutdown_summary(
+            uptime_start.elapsed(),
+            &shared.stats,
+            connections.saturating_sub(1),
+            max_concurrent,
+        );
+        if let Some(mut client) = dbh.take() {
+            shared.run_details.flush(&mut client, &config);
+            events.record(SchedulerEvent::Stop, summary.clone());
+            events.flush(&mut client, &config);
+            close_db(client, &config);
+        }
+        let unwritten = shared.run_details.len();
+        if unwritten > 0 {
+            dlog!(
                 &config,
-                &job_stats,
-                &mut running_workers,
-                &mut next_worker_id,
+                "ERROR",
+                "{} job run details could not be written before exiting",
+                unwritten
             );
         }
-
-        if args.single {
-            break;
-        }
+        dprint(&config, "LOG", &summary);
+        config.pidfile.clone()
     }
+}
 
-    wait_all_children(&mut running_workers);
-    release_pidfile();
-    if Path::new(&config.pidfile).exists()
-        && let Err(err) = std::fs::remove_file(&config.pidfile)
-    {
-        dlog!(
-            &config,
-            "ERROR",
-            "Unable to remove pid file {}, {}",
-            config.pidfile,
-            err
-        );
-    }
+/// Claimed jobs in the order they are started: by priority, scheduled jobs
+/// before asynchronous ones of the same priority, then by job number. Both
+/// maps are drained.
+fn dispatch_order(
+    scheduled: &mut HashMap<i64, Job>,
+    async_jobs: &mut HashMap<i64, Job>,
+) -> Vec<(JobKind, Job)> {
+    let mut jobs: Vec<(JobKind, Job)> = scheduled
+        .drain()
+        .map(|(_, job)| (JobKind::Scheduled, job))
+        .chain(async_jobs.drain().map(|(_, job)| (JobKind::Async, job)))
+        .collect();
+    jobs.sort_by_key(|(kind, job)| (job.priority, matches!(kind, JobKind::Async), job.job));
+    jobs
+}
 
-    dprint(&config, "LOG", "pg_dbms_job scheduler stopped.");
-    shutdown_logger();
+/// Final statistics line logged on clean shutdown, for capacity reviews and
+/// to confirm a drain completed.
+fn shutdown_summary(
+    uptime: Duration,
+    stats: &JobStats,
+    reconnects: u64,
+    max_concurrent: usize,
+) -> String {
+    format!(
+        "shutdown summary: uptime {}s, jobs executed: scheduled={}, async={}, failures={}, reconnects={}, max concurrent jobs={}",
+        uptime.as_secs(),
+        stats.scheduled_runs.load(Ordering::Relaxed),
+        stats.async_runs.load(Ordering::Relaxed),
+        stats.failed_runs.load(Ordering::Relaxed),
+        reconnects,
+        max_concurrent
+    )
 }
 
 /// The minimal view of a backend notification the main loop needs: enough to
@@ -417,13 +1095,30 @@ impl NotificationSource for postgres::Notifications<'_> {
     }
 }
 
-/// Count a received notification against the async or scheduled tally so the
-/// main loop knows which job tables to scan this cycle.
+/// What the notifications received during one dispatch cycle asked for.
+#[derive(Default)]
+struct NotificationTally {
+    /// Number of notifications on the async channel.
+    async_count: usize,
+    /// Ids of the asynchronous jobs named by those notifications.
+    async_jobs: Vec<i64>,
+    /// Set when an async-channel payload named no job (a large batch, or an
+    /// older extension version): the whole queue must be read.
+    unknown_async: bool,
+    /// Decoded changes to the scheduled jobs table, in arrival order.
+    schedule_events: Vec<ScheduleEvent>,
+    /// Set when a scheduled-channel payload could not be decoded (e.g. sent
+    /// by an older extension version): the whole schedule must be reloaded.
+    unknown_scheduled: bool,
+}
+
+/// Count a received notification against the async tally or record the
+/// scheduled-table change it describes, so the main loop knows which job
+/// tables to scan this cycle.
 fn tally_notification<N: NotificationLike>(
     config: &Config,
     notification: &N,
-    async_count: &mut usize,
-    scheduled_count: &mut usize,
+    tally: &mut NotificationTally,
 ) {
     dlog!(
         config,
@@ -433,54 +1128,135 @@ fn tally_notification<N: NotificationLike>(
         notification.process_id(),
         notification.payload()
     );
-    if notification.channel() == "dbms_job_async_notify" {
-        *async_count += 1;
-    } else if notification.channel() == "dbms_job_scheduled_notify" {
-        *scheduled_count += 1;
+    if notification.channel() == async_channel(&config.schema) {
+        tally.async_count += 1;
+        match parse_async_payload(notification.payload()) {
+            Some(jobs) => tally.async_jobs.extend(jobs),
+            None => tally.unknown_async = true,
+        }
+    } else if notification.channel() == scheduled_channel(&config.schema) {
+        match ScheduleEvent::parse(notification.payload()) {
+            Some(event) => tally.schedule_events.push(event),
+            None => tally.unknown_scheduled = true,
+        }
+    }
+}
+
+/// Decode a `dbms_job_async_notify` payload, `{"jobs" : [1,2]}`, into the
+/// ids of the submitted jobs. `None` for an empty or unknown payload.
+fn parse_async_payload(payload: &str) -> Option<Vec<i64>> {
+    let body = payload.trim().strip_prefix('{')?.strip_suffix('}')?;
+    let (key, value) = body.split_once(':')?;
+    if key.trim().trim_matches('"') != "jobs" {
+        return None;
     }
+    let list = value.trim().strip_prefix('[')?.strip_suffix(']')?;
+    list.split(',')
+        .map(|job| job.trim().parse::<i64>().ok())
+        .collect::<Option<Vec<i64>>>()
+        .filter(|jobs| !jobs.is_empty())
 }
 
-/// Collect the notifications driving this dispatch cycle, tallying them per
-/// channel into `async_count` / `scheduled_count`.
+/// Log a failed wait for notifications, with the message of the server,
+/// e.g. the reason it closed the connection.
+fn log_notification_error(config: &Config, err: &postgres::Error) {
+    let message = err
+        .as_db_error()
+        .map_or_else(|| err.to_string(), |db| db.message().to_string());
+    dlog!(config, "ERROR", "notification error: {}", message);
+}
+
+/// Collect the notifications driving this dispatch cycle into `tally`.
 ///
 /// Blocks up to `nap_time` for the *first* notification so the idle loop stays
 /// cheap, then drains any others that are already buffered WITHOUT blocking a
 /// second `nap_time`. This is the crux of the dispatch latency: the postgres
 /// `TimeoutIter` resets its delay on every notification and only returns `None`
 /// after a full `nap_time` of silence, so reusing it to drain would tax every
-/// dispatch with one extra `nap_time` of latency. `wait_first` is therefore
-/// invoked at most once per cycle.
+/// dispatch with one extra `nap_time` of latency.
+///
+/// With a non-zero `debounce`, the cycle keeps listening until `debounce` has
+/// elapsed since the first notification, so a burst (e.g. a bulk submit of
+/// thousands of jobs, one NOTIFY each) collapses into a single collection
+/// pass instead of one claim cycle per network read. The window is measured
+/// from the first notification and never extended, so the added latency is
+/// bounded by `debounce`. With `debounce` at zero `wait_first` is invoked at
+/// most once per cycle.
+///
+/// At most `max_notifications_per_cycle` notifications are consumed per call.
+/// Under a notification storm the drain would otherwise keep reading for as
+/// long as the backend keeps sending, and no job would be dispatched in the
+/// meantime; the remainder stays buffered in the connection and is picked up,
+/// without blocking, by the next cycle.
 fn collect_notifications<S: NotificationSource>(
     source: &mut S,
     config: &Config,
     nap_time: Duration,
-    async_count: &mut usize,
-    scheduled_count: &mut usize,
+    debounce: Duration,
+    tally: &mut NotificationTally,
 ) {
     match source.wait_first(nap_time) {
-        Ok(Some(notification)) => {
-            tally_notification(config, &notification, async_count, scheduled_count)
-        }
+        Ok(Some(notification)) => tally_notification(config, &notification, tally),
         // Nothing arrived this cycle: do not poll again, leave the tallies at 0.
         Ok(None) => return,
         Err(err) => {
-            dlog!(config, "ERROR", "notification error: {err}");
+            log_notification_error(config, &err);
             return;
         }
     }
 
-    loop {
-        match source.next_buffered() {
+    let deadline = Instant::now() + debounce;
+    let limit = config.max_notifications_per_cycle.max(1);
+    let mut received = 1usize;
+    'window: loop {
+        loop {
+            if received >= limit {
+                dlog!(
+                    config,
+                    "DEBUG",
+                    "notification limit of {} reached, remaining notifications are left for the next cycle",
+                    limit
+                );
+                break 'window;
+            }
+            match source.next_buffered() {
+                Ok(Some(notification)) => {
+                    tally_notification(config, &notification, tally);
+                    received += 1;
+                }
+                Ok(None) => break,
+                Err(err) => {
+                    log_notification_error(config, &err);
+                    break 'window;
+                }
+            }
+        }
+
+        let now = Instant::now();
+        if now >= deadline {
+            break;
+        }
+        match source.wait_first(deadline - now) {
             Ok(Some(notification)) => {
-                tally_notification(config, &notification, async_count, scheduled_count)
+                tally_notification(config, &notification, tally);
+                received += 1;
             }
             Ok(None) => break,
             Err(err) => {
-                dlog!(config, "ERROR", "notification error: {err}");
+                log_notification_error(config, &err);
                 break;
             }
         }
     }
+
+    if received > 1 {
+        dlog!(
+            config,
+            "DEBUG",
+            "coalesced {} notifications into one collection pass",
+            received
+        );
+    }
 }
 
 /// The maximum number of concurrent worker threads to keep in flight.
@@ -513,11 +1289,16 @@ fn effective_max_workers(config: &Config) -> usize {
 ///     `error_delay` seconds (shared across both dispatch loops via
 ///     `last_saturation_log`) instead of once per poll — otherwise a sustained
 ///     backlog would flood the log.
+///   * `while_waiting` runs on every poll, so that the registry heartbeat
+///     keeps going while long jobs hold every slot. It returns the workers
+///     given up on as hung, which stop holding a slot.
 fn await_worker_slot(
     running_workers: &mut HashMap<u64, JoinHandle<()>>,
     max_workers: usize,
     config: &Config,
     last_saturation_log: &mut Option<Instant>,
+    events: &mut EventLog,
+    while_waiting: &mut dyn FnMut() -> Vec<u64>,
 ) {
     reap_children(running_workers);
     while running_workers.len() >= max_workers {
@@ -532,19 +1313,187 @@ fn await_worker_slot(
                 max_workers
             );
             *last_saturation_log = Some(now);
+            events.saturated(max_workers, now);
+        }
+        for worker in while_waiting() {
+            running_workers.remove(&worker);
         }
         thread::sleep(WORKER_SLOT_POLL_INTERVAL);
         reap_children(running_workers);
     }
 }
 
+/// Next idle notification wait: doubled, up to `max_nap_time`. Never below
+/// `nap_time`, so a back-off disabled by `max_nap_time` keeps it fixed.
+fn backoff_nap(current: f64, config: &Config) -> f64 {
+    if config.max_nap_time <= config.nap_time {
+        return config.nap_time;
+    }
+    (current * 2.0).clamp(config.nap_time, config.max_nap_time)
+}
+
+/// Failed attempts in a row to reach the database, spacing the next ones.
+#[derive(Default)]
+struct Reconnect {
+    failures: u32,
+}
+
+impl Reconnect {
+    /// Wait before the next attempt, exiting instead once
+    /// `reconnect_max_attempts` have failed.
+    fn wait(&mut self, config: &Config) {
+        self.failures = self.failures.saturating_add(1);
+        if config.reconnect_max_attempts > 0 && self.failures >= config.reconnect_max_attempts {
+            dlog!(
+                config,
+                "FATAL",
+                "giving up after {} failed attempts to reach the database",
+                self.failures
+            );
+            die(&format!(
+                "FATAL: giving up after {} failed attempts to reach the database",
+                self.failures
+            ));
+        }
+        // Between half and all of the delay, so that schedulers cut off
+        // together do not come back together.
+        let delay = reconnect_delay(self.failures, config) * (0.5 + random_fraction() / 2.0);
+        dlog!(
+            config,
+            "DEBUG",
+            "attempt {} to reach the database failed, retrying in {:.1} seconds",
+            self.failures,
+            delay
+        );
+        thread::sleep(Duration::from_secs_f64(delay));
+    }
+
+    fn reset(&mut self) {
+        self.failures = 0;
+    }
+}
+
+/// Wait after the `failures`-th failed attempt in a row to reach the
+/// database: `startup_delay` doubled at each failure up to
+/// `reconnect_max_delay`, never below `startup_delay`.
+fn reconnect_delay(failures: u32, config: &Config) -> f64 {
+    let doubled = config.startup_delay * 2f64.powi(failures.saturating_sub(1).min(62) as i32);
+    doubled
+        .min(config.reconnect_max_delay)
+        .max(config.startup_delay)
+}
+
+/// Notification wait of this cycle: the idle wait, cut short so that the
+/// loop is awake when the next cached scheduled job is due and in time for
+/// the registry heartbeat, but never below `nap_time`.
+fn cycle_nap(idle_nap: f64, until_due: Duration, config: &Config) -> Duration {
+    let nap = idle_nap
+        .min(until_due.as_secs_f64())
+        .min(REGISTRY_HEARTBEAT_SECS as f64)
+        .max(config.nap_time);
+    Duration::from_secs_f64(nap)
+}
+
+/// Run the housekeeping tasks that are due, then write the run details
+/// queued by the workers. The heartbeat keeps the queue depth it reports,
+/// cleared when it could not be counted rather than left stale. Tasks
+/// needing the database are skipped while disconnected.
+/// Returns the workers given up on as hung.
+fn run_housekeeping(
+    housekeeping: &mut Housekeeping,
+    dbh: &mut Option<Client>,
+    config: &Config,
+    shared: &WorkerShared,
+    queue_depth: &mut Option<QueueDepth>,
+) -> Vec<u64> {
+    let now = Instant::now();
+    let mut abandoned = Vec::new();
+    for task in Task::ALL {
+        if !housekeeping.is_due(task, config, now) {
+            continue;
+        }
+        match task {
+            Task::Heartbeat => {
+                *queue_depth = dbh
+                    .as_mut()
+                    .and_then(|client| send_heartbeat(client, config));
+            }
+            // Re-queue jobs abandoned by workers that never cleared their
+            // dispatch marker (e.g. a worker that failed during session
+            // setup, or a crashed worker/daemon). Without this such rows
+            // stay flagged running forever and silently disappear from the
+            // queue.
+            Task::StaleSweep => {
+                if let Some(client) = dbh.as_mut() {
+                    reap_stale_jobs(client, config);
+                }
+            }
+            Task::HungWorkers => {
+                if let Some(client) = dbh.as_mut() {
+                    abandoned = check_hung_workers(client, config, &shared.beats);
+                }
+            }
+            Task::RunDeadlines => {
+                if let Some(client) = dbh.as_mut() {
+                    check_run_deadlines(client, config, &shared.deadlines);
+                }
+            }
+            Task::OversizedJobs => match dbh.as_mut() {
+                Some(client) => refuse_oversized_jobs(client, config),
+                None => continue,
+            },
+            Task::LogRetention => expire_log_files(config),
+            Task::HistoryPurge => match dbh.as_mut() {
+                Some(client) => maintain_run_details(client, config),
+                // Retried once connected.
+                None => continue,
+            },
+        }
+        housekeeping.mark(task, now);
+    }
+    if let Some(client) = dbh.as_mut() {
+        shared.run_details.flush(client, config);
+    }
+    abandoned
+}
+
+/// `detail` of a reload event: the file and the names of the changed
+/// settings.
+fn reload_detail(config_file: &str, changes: &[(&str, String, String)]) -> String {
+    if changes.is_empty() {
+        return format!("configuration reloaded from {config_file}, no change");
+    }
+    let names: Vec<&str> = changes.iter().map(|(name, _, _)| *name).collect();
+    format!(
+        "configuration reloaded from {config_file}, changed: {}",
+        names.join(", ")
+    )
+}
+
+/// Queue depth part of the stats line, empty until it has been counted.
+fn pending_summary(depth: Option<QueueDepth>) -> String {
+    depth
+        .map(|d| {
+            format!(
+                ", pending scheduled jobs={}, pending async jobs={}",
+                d.scheduled, d.asynchronous
+            )
+        })
+        .unwrap_or_default()
+}
+
 /// Default scheduler configuration values.
 fn default_config() -> Config {
     Config {
         debug: false,
         pidfile: "/tmp/pg_dbms_job.pid".to_string(),
         logfile: String::new(),
+        logfile_mode: 0,
+        logfile_owner: String::new(),
+        logfile_group: String::new(),
         log_truncate_on_rotation: false,
+        log_retention_days: 0,
+        log_compress: false,
         job_queue_interval: 0.1,
         job_queue_processes: 1024,
         pool_size: 100,
@@ -554,6 +1503,56 @@ fn default_config() -> Config {
         stats_interval: 15,
         job_run_details: JobRunDetails::All,
         stale_job_timeout: 3600.0,
+        notify_debounce: 0.0,
+        schedule_refresh_interval: 60.0,
+        max_notifications_per_cycle: 10000,
+        max_job_size: 0,
+        hard_max_run_duration: 0.0,
+        hard_max_run_overruns: 3,
+        job_nice: 0,
+        job_io_class: IoClass::None,
+        job_io_priority: 4,
+        connection_reserve: 0,
+        job_cgroup: String::new(),
+        executable_job_owners: String::new(),
+        executable_os_users: String::new(),
+        max_nap_time: 0.0,
+        reconnect_max_delay: 60.0,
+        reconnect_max_attempts: 0,
+        standby_check_interval: 0.0,
+        job_run_timeout: 0.0,
+        max_failures: 0,
+        connection_check_interval: 5.0,
+        multiple_schedulers: false,
+        semaphores: BTreeMap::new(),
+        job_classes: BTreeMap::new(),
+        job_class_priorities: BTreeMap::new(),
+        run_details_maintenance_interval: 0.0,
+        run_details_retention_months: 3,
+        worker_hang_timeout: 600.0,
+        job_execution_mode: crate::model::ExecutionMode::SetRole,
+        dst_policy: crate::model::DstPolicy::Server,
+        ssh_host: String::new(),
+        ssh_port: 22,
+        ssh_user: String::new(),
+        ssh_key: String::new(),
+        log_rotation_size: 0,
+        log_rotation_count: 5,
+        log_rotation_age: crate::model::LogRotationAge::None,
+        log_destination: crate::model::LogDestination::File,
+        syslog_facility: "local0".to_string(),
+        syslog_ident: "pg_dbms_job".to_string(),
+        log_min_messages: crate::model::LogMinMessages::Log,
+        log_job_notices: false,
+        log_rotation_compress: crate::model::LogRotationCompress::None,
+        scheduler_event_log: false,
+        debug_ring_size: 0,
+        log_gelf_endpoint: String::new(),
+        auto_reload: false,
+        strict_config: false,
+        database_section: String::new(),
+        schemas: DEFAULT_SCHEMA.to_string(),
+        schema: DEFAULT_SCHEMA.to_string(),
     }
 }
 
@@ -564,6 +1563,23 @@ fn default_dbinfo() -> DbInfo {
         database: String::new(),
         user: String::new(),
         passwd: String::new(),
+        passwd_file: String::new(),
+        passwd_command: String::new(),
+        auth_method: AuthMethod::Password,
+        aws_region: String::new(),
+        dsn: String::new(),
+        connect_timeout: 10,
+        keepalives: true,
+        keepalives_idle: 60,
+        keepalives_interval: 10,
+        keepalives_count: 6,
+        timezone: String::new(),
+        sslmode: SslMode::Prefer,
+        sslrootcert: String::new(),
+        sslcert: String::new(),
+        sslkey: String::new(),
+        target_session_attrs: TargetSessionAttrs::Any,
+        job_session_settings: Vec::new(),
         port: 5432,
     }
 }
@@ -571,23 +1587,70 @@ fn default_dbinfo() -> DbInfo {
 #[cfg(test)]
 mod tests {
     use super::{
-        NotificationLike, NotificationSource, await_worker_slot, collect_notifications,
-        default_config, default_dbinfo, effective_max_workers,
+        NotificationLike, NotificationSource, NotificationTally, await_worker_slot, backoff_nap,
+        collect_notifications, cycle_nap, default_config, default_dbinfo, dispatch_order,
+        effective_max_workers, parse_async_payload, pending_summary, reconnect_delay,
+        reload_detail, shutdown_summary,
     };
+    use crate::constants::REGISTRY_HEARTBEAT_SECS;
+    use crate::events::EventLog;
+    use crate::model::{Job, JobKind, QueueDepth};
+    use crate::schedule::ScheduleEvent;
     use std::collections::{HashMap, VecDeque};
     use std::sync::{Arc, Barrier};
     use std::thread;
     use std::time::{Duration, Instant};
 
+    #[test]
+    fn claimed_jobs_start_by_priority() {
+        let job = |job, priority| {
+            (
+                job,
+                Job {
+                    job,
+                    priority,
+                    ..Default::default()
+                },
+            )
+        };
+        let mut scheduled = HashMap::from([job(1, 3), job(2, 5), job(3, 1)]);
+        let mut async_jobs = HashMap::from([job(4, 3), job(5, 1), job(6, 2)]);
+        let order: Vec<(bool, i64)> = dispatch_order(&mut scheduled, &mut async_jobs)
+            .into_iter()
+            .map(|(kind, job)| (matches!(kind, JobKind::Async), job.job))
+            .collect();
+        assert_eq!(
+            order,
+            vec![
+                (false, 3),
+                (true, 5),
+                (true, 6),
+                (false, 1),
+                (true, 4),
+                (false, 2)
+            ]
+        );
+        assert!(scheduled.is_empty() && async_jobs.is_empty());
+    }
+
     /// A notification stub carrying only the channel name the tally logic reads.
     struct FakeNotification {
         channel: String,
+        payload: String,
     }
 
     impl FakeNotification {
         fn new(channel: &str) -> Self {
             FakeNotification {
                 channel: channel.to_string(),
+                payload: String::new(),
+            }
+        }
+
+        fn with_payload(channel: &str, payload: &str) -> Self {
+            FakeNotification {
+                channel: channel.to_string(),
+                payload: payload.to_string(),
             }
         }
     }
@@ -600,7 +1663,7 @@ mod tests {
             0
         }
         fn payload(&self) -> &str {
-            ""
+            &self.payload
         }
     }
 
@@ -613,6 +1676,9 @@ mod tests {
         wait_first_calls: usize,
         next_buffered_calls: usize,
         wait_first_sleep: Option<Duration>,
+        /// Notifications returned, one per call, by `wait_first` once `first`
+        /// has been consumed: they model a burst still arriving on the wire.
+        later: VecDeque<FakeNotification>,
     }
 
     impl FakeSource {
@@ -620,6 +1686,7 @@ mod tests {
             FakeSource {
                 first,
                 buffered: buffered.into(),
+                later: VecDeque::new(),
                 wait_first_calls: 0,
                 next_buffered_calls: 0,
                 wait_first_sleep: None,
@@ -637,7 +1704,7 @@ mod tests {
             if let Some(d) = self.wait_first_sleep.take() {
                 std::thread::sleep(d);
             }
-            Ok(self.first.take())
+            Ok(self.first.take().or_else(|| self.later.pop_front()))
         }
         fn next_buffered(&mut self) -> Result<Option<FakeNotification>, postgres::Error> {
             self.next_buffered_calls += 1;
@@ -658,22 +1725,22 @@ mod tests {
                 FakeNotification::new("dbms_job_async_notify"),
             ],
         );
-        let (mut async_count, mut scheduled_count) = (0usize, 0usize);
+        let mut tally = NotificationTally::default();
 
         collect_notifications(
             &mut source,
             &config,
             Duration::from_millis(100),
-            &mut async_count,
-            &mut scheduled_count,
+            Duration::ZERO,
+            &mut tally,
         );
 
         assert_eq!(
             source.wait_first_calls, 1,
             "must block at most once per cycle"
         );
-        assert_eq!(async_count, 2);
-        assert_eq!(scheduled_count, 1);
+        assert_eq!(tally.async_count, 2);
+        assert!(tally.unknown_scheduled);
     }
 
     // When nothing arrives within nap_time we must not poll again — the cycle
@@ -682,20 +1749,21 @@ mod tests {
     fn collect_notifications_idle_does_not_drain() {
         let config = default_config();
         let mut source = FakeSource::new(None, vec![]);
-        let (mut async_count, mut scheduled_count) = (0usize, 0usize);
+        let mut tally = NotificationTally::default();
 
         collect_notifications(
             &mut source,
             &config,
             Duration::from_millis(100),
-            &mut async_count,
-            &mut scheduled_count,
+            Duration::ZERO,
+            &mut tally,
         );
 
         assert_eq!(source.wait_first_calls, 1);
         assert_eq!(source.next_buffered_calls, 0, "idle cycle must not drain");
-        assert_eq!(async_count, 0);
-        assert_eq!(scheduled_count, 0);
+        assert_eq!(tally.async_count, 0);
+        assert!(!tally.unknown_scheduled);
+        assert!(tally.schedule_events.is_empty());
     }
 
     // Latency guard: simulate the worst case where the first notification only
@@ -711,16 +1779,10 @@ mod tests {
             vec![FakeNotification::new("dbms_job_async_notify")],
         );
         source.wait_first_sleep = Some(nap_time);
-        let (mut async_count, mut scheduled_count) = (0usize, 0usize);
+        let mut tally = NotificationTally::default();
 
         let start = Instant::now();
-        collect_notifications(
-            &mut source,
-            &config,
-            nap_time,
-            &mut async_count,
-            &mut scheduled_count,
-        );
+        collect_notifications(&mut source, &config, nap_time, Duration::ZERO, &mut tally);
         let elapsed = start.elapsed();
 
         assert!(
@@ -728,7 +1790,197 @@ mod tests {
             "dispatch cycle took {elapsed:?}, expected well under two nap_times ({:?})",
             nap_time * 2
         );
-        assert_eq!(async_count, 2);
+        assert_eq!(tally.async_count, 2);
+    }
+
+    // A burst that keeps arriving after the first drain is folded into the same
+    // collection pass while the debounce window is open.
+    #[test]
+    fn collect_notifications_debounce_coalesces_burst() {
+        let config = default_config();
+        let mut source = FakeSource::new(
+            Some(FakeNotification::new("dbms_job_async_notify")),
+            vec![FakeNotification::new("dbms_job_async_notify")],
+        );
+        source.later = vec![
+            FakeNotification::new("dbms_job_async_notify"),
+            FakeNotification::new("dbms_job_scheduled_notify"),
+        ]
+        .into();
+        let mut tally = NotificationTally::default();
+
+        collect_notifications(
+            &mut source,
+            &config,
+            Duration::from_millis(100),
+            Duration::from_millis(200),
+            &mut tally,
+        );
+
+        assert_eq!(tally.async_count, 3);
+        assert!(tally.unknown_scheduled);
+        assert!(source.later.is_empty());
+    }
+
+    // Without debounce, notifications that were not yet buffered are left for
+    // the next cycle.
+    #[test]
+    fn collect_notifications_without_debounce_leaves_late_arrivals() {
+        let config = default_config();
+        let mut source =
+            FakeSource::new(Some(FakeNotification::new("dbms_job_async_notify")), vec![]);
+        source.later = vec![FakeNotification::new("dbms_job_async_notify")].into();
+        let mut tally = NotificationTally::default();
+
+        collect_notifications(
+            &mut source,
+            &config,
+            Duration::from_millis(100),
+            Duration::ZERO,
+            &mut tally,
+        );
+
+        assert_eq!(source.wait_first_calls, 1);
+        assert_eq!(tally.async_count, 1);
+        assert_eq!(source.later.len(), 1);
+    }
+
+    // Structured payloads on the scheduled channel are decoded into cache
+    // events, in arrival order, and do not force a full reload.
+    #[test]
+    fn collect_notifications_decodes_schedule_events() {
+        let config = default_config();
+        let mut source = FakeSource::new(
+            Some(FakeNotification::with_payload(
+                "dbms_job_scheduled_notify",
+                r#"{"op" : "submit", "job" : 5}"#,
+            )),
+            vec![FakeNotification::with_payload(
+                "dbms_job_scheduled_notify",
+                r#"{"op" : "remove", "job" : 4}"#,
+            )],
+        );
+        let mut tally = NotificationTally::default();
+
+        collect_notifications(
+            &mut source,
+            &config,
+            Duration::from_millis(100),
+            Duration::ZERO,
+            &mut tally,
+        );
+
+        assert_eq!(
+            tally.schedule_events,
+            vec![ScheduleEvent::Submit(5), ScheduleEvent::Remove(4)]
+        );
+        assert!(!tally.unknown_scheduled);
+        assert_eq!(tally.async_count, 0);
+    }
+
+    // Async payloads naming their jobs are collected; one without ids, sent
+    // for a large batch or by an older extension, asks for a full scan.
+    #[test]
+    fn collect_notifications_gathers_notified_async_jobs() {
+        let config = default_config();
+        let mut source = FakeSource::new(
+            Some(FakeNotification::with_payload(
+                "dbms_job_async_notify",
+                r#"{"jobs" : [7,8]}"#,
+            )),
+            vec![FakeNotification::with_payload(
+                "dbms_job_async_notify",
+                r#"{"jobs" : [9]}"#,
+            )],
+        );
+        let mut tally = NotificationTally::default();
+        collect_notifications(
+            &mut source,
+            &config,
+            Duration::from_millis(100),
+            Duration::ZERO,
+            &mut tally,
+        );
+        assert_eq!(tally.async_jobs, vec![7, 8, 9]);
+        assert!(!tally.unknown_async);
+
+        let mut source = FakeSource::new(
+            Some(FakeNotification::with_payload(
+                "dbms_job_async_notify",
+                r#"{"jobs" : [7]}"#,
+            )),
+            vec![FakeNotification::with_payload(
+                "dbms_job_async_notify",
+                "New asynchronous job received",
+            )],
+        );
+        let mut tally = NotificationTally::default();
+        collect_notifications(
+            &mut source,
+            &config,
+            Duration::from_millis(100),
+            Duration::ZERO,
+            &mut tally,
+        );
+        assert_eq!(tally.async_count, 2);
+        assert!(tally.unknown_async);
+    }
+
+    #[test]
+    fn async_payload_lists_the_submitted_jobs() {
+        assert_eq!(parse_async_payload(r#"{"jobs" : [3]}"#), Some(vec![3]));
+        assert_eq!(
+            parse_async_payload(r#"{"jobs":[10, 11,12]}"#),
+            Some(vec![10, 11, 12])
+        );
+        assert_eq!(parse_async_payload(""), None);
+        assert_eq!(parse_async_payload(r#"{"jobs" : []}"#), None);
+        assert_eq!(parse_async_payload(r#"{"jobs" : null}"#), None);
+        assert_eq!(parse_async_payload(r#"{"op" : [3]}"#), None);
+        assert_eq!(parse_async_payload(r#"{"jobs" : [3,x]}"#), None);
+    }
+
+    // The drain stops at the per-cycle cap and leaves the rest buffered for the
+    // next iteration, which then starts without blocking.
+    #[test]
+    fn collect_notifications_stops_at_cycle_limit() {
+        let mut config = default_config();
+        config.max_notifications_per_cycle = 3;
+        let mut source = FakeSource::new(
+            Some(FakeNotification::new("dbms_job_async_notify")),
+            (0..5)
+                .map(|_| FakeNotification::new("dbms_job_async_notify"))
+                .collect(),
+        );
+        let mut tally = NotificationTally::default();
+
+        collect_notifications(
+            &mut source,
+            &config,
+            Duration::from_millis(100),
+            Duration::from_millis(200),
+            &mut tally,
+        );
+
+        assert_eq!(tally.async_count, 3);
+        assert_eq!(source.buffered.len(), 3, "remainder must stay buffered");
+        assert_eq!(
+            source.wait_first_calls, 1,
+            "the cap also ends the debounce window"
+        );
+    }
+
+    #[test]
+    fn shutdown_summary_reports_totals() {
+        use crate::model::{JobKind, JobStats};
+        let stats = JobStats::default();
+        stats.record_run(JobKind::Scheduled, false);
+        stats.record_run(JobKind::Async, true);
+        let line = shutdown_summary(Duration::from_secs(3725), &stats, 2, 7);
+        assert_eq!(
+            line,
+            "shutdown summary: uptime 3725s, jobs executed: scheduled=1, async=1, failures=1, reconnects=2, max concurrent jobs=7"
+        );
     }
 
     #[test]
@@ -814,7 +2066,14 @@ mod tests {
         let mut last = None;
         // No workers running and a cap of 4: must not block and must not emit a
         // saturation notice.
-        await_worker_slot(&mut running, 4, &config, &mut last);
+        await_worker_slot(
+            &mut running,
+            4,
+            &config,
+            &mut last,
+            &mut EventLog::default(),
+            &mut Vec::new,
+        );
         assert!(running.is_empty());
         assert!(last.is_none(), "must not log saturation below the cap");
     }
@@ -828,7 +2087,14 @@ mod tests {
         let mut last = None;
         // The up-front reap clears the finished worker so the cap is no longer
         // reached: it returns without ever entering the wait/log path.
-        await_worker_slot(&mut running, 1, &config, &mut last);
+        await_worker_slot(
+            &mut running,
+            1,
+            &config,
+            &mut last,
+            &mut EventLog::default(),
+            &mut Vec::new,
+        );
         assert!(running.is_empty(), "finished worker must be reaped");
         assert!(last.is_none(), "no wait happened, so no saturation log");
     }
@@ -854,7 +2120,14 @@ mod tests {
         let mut last = None;
         // Cap of 1 with a busy worker: the helper polls until the worker is
         // released and reaped, then returns.
-        await_worker_slot(&mut running, 1, &config, &mut last);
+        await_worker_slot(
+            &mut running,
+            1,
+            &config,
+            &mut last,
+            &mut EventLog::default(),
+            &mut Vec::new,
+        );
         releaser.join().unwrap();
         assert!(running.is_empty(), "released worker must be reaped");
         assert!(
@@ -872,6 +2145,84 @@ mod tests {
         assert_eq!(effective_max_workers(&config), 1);
     }
 
+    #[test]
+    fn reload_detail_names_changed_settings() {
+        assert_eq!(
+            reload_detail("/etc/pg_dbms_job.conf", &[]),
+            "configuration reloaded from /etc/pg_dbms_job.conf, no change"
+        );
+        let changes = [
+            ("nap_time", "0.1".to_string(), "1".to_string()),
+            ("pool_size", "100".to_string(), "10".to_string()),
+        ];
+        assert_eq!(
+            reload_detail("/etc/pg_dbms_job.conf", &changes),
+            "configuration reloaded from /etc/pg_dbms_job.conf, changed: nap_time, pool_size"
+        );
+    }
+
+    #[test]
+    fn pending_summary_reports_counted_depth_only() {
+        assert_eq!(pending_summary(None), "");
+        assert_eq!(
+            pending_summary(Some(QueueDepth {
+                scheduled: 3,
+                asynchronous: 120,
+            })),
+            ", pending scheduled jobs=3, pending async jobs=120"
+        );
+    }
+
+    #[test]
+    fn backoff_nap_doubles_up_to_ceiling() {
+        let mut config = default_config();
+        config.nap_time = 0.1;
+        // Disabled by default: the wait stays at nap_time.
+        assert_eq!(backoff_nap(0.1, &config), 0.1);
+        config.max_nap_time = 0.5;
+        assert_eq!(backoff_nap(0.1, &config), 0.2);
+        assert_eq!(backoff_nap(0.4, &config), 0.5);
+        assert_eq!(backoff_nap(0.5, &config), 0.5);
+        // A ceiling lowered by a reload clamps the current wait.
+        config.max_nap_time = 0.3;
+        assert_eq!(backoff_nap(0.5, &config), 0.3);
+    }
+
+    #[test]
+    fn reconnect_delay_doubles_up_to_ceiling() {
+        let mut config = default_config();
+        config.startup_delay = 3.0;
+        assert_eq!(reconnect_delay(1, &config), 3.0);
+        assert_eq!(reconnect_delay(2, &config), 6.0);
+        assert_eq!(reconnect_delay(5, &config), 48.0);
+        assert_eq!(reconnect_delay(6, &config), 60.0);
+        assert_eq!(reconnect_delay(u32::MAX, &config), 60.0);
+        // A ceiling below startup_delay keeps the wait fixed.
+        config.reconnect_max_delay = 0.0;
+        assert_eq!(reconnect_delay(4, &config), 3.0);
+    }
+
+    #[test]
+    fn cycle_nap_wakes_for_due_jobs_and_heartbeat() {
+        let mut config = default_config();
+        config.nap_time = 0.1;
+        config.max_nap_time = 60.0;
+        let far = Duration::from_secs(3600);
+        assert_eq!(
+            cycle_nap(60.0, far, &config),
+            Duration::from_secs(REGISTRY_HEARTBEAT_SECS)
+        );
+        assert_eq!(
+            cycle_nap(60.0, Duration::from_secs(2), &config),
+            Duration::from_secs(2)
+        );
+        assert_eq!(
+            cycle_nap(60.0, Duration::ZERO, &config),
+            Duration::from_secs_f64(0.1)
+        );
+        assert_eq!(cycle_nap(0.8, far, &config), Duration::from_secs_f64(0.8));
+    }
+
     #[test]
     fn default_config_delays_positive_and_finite() {
         let config = default_config();
@@ -0,0 +1,275 @@
+//! Internal housekeeping of the scheduler.
+//!
+//! The daemon needs some upkeep of its own: the registry heartbeat, the
+//! sweep of jobs left flagged running by a lost worker, the recycling of
+//! hung workers, the stop of runs past their `max_run_duration`, the
+//! refusal of jobs over `max_job_size`, the retention of dated log files and the rotation of the run-details
+//! partitions. Each is
+//! a [`Task`] run from the main loop on its own period, derived from the
+//! configuration, so that none of it has to be submitted as a `dbms_job`
+//! job.
+
+use crate::constants::{
+    HANG_CHECK_INTERVAL_SECS, LOG_EXPIRE_INTERVAL_SECS, OVERSIZED_JOB_CHECK_INTERVAL_SECS,
+    REAP_INTERVAL_SECS, REGISTRY_HEARTBEAT_SECS, RUN_DEADLINE_CHECK_INTERVAL_SECS,
+};
+use crate::db::in_schema;
+use crate::dlog;
+use crate::logging::dprint;
+use crate::model::Config;
+use postgres::Client;
+use std::time::{Duration, Instant};
+
+/// A housekeeping task of the scheduler.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Task {
+    /// Registry heartbeat and queue depth.
+    Heartbeat,
+    /// Re-queue jobs whose worker never cleared their dispatch marker.
+    StaleSweep,
+    /// Recycle workers hung on the client side.
+    HungWorkers,
+    /// Stop the runs past their job's `max_run_duration`.
+    RunDeadlines,
+    /// Refuse the queued jobs whose body exceeds `max_job_size`.
+    OversizedJobs,
+    /// Remove or compress old strftime-dated and rotated log files.
+    LogRetention,
+    /// Create upcoming run-details partitions and drop expired ones.
+    HistoryPurge,
+}
+
+impl Task {
+    /// Every task, in the order they run when due together.
+    pub const ALL: [Task; 7] = [
+        Task::Heartbeat,
+        Task::StaleSweep,
+        Task::HungWorkers,
+        Task::RunDeadlines,
+        Task::OversizedJobs,
+        Task::LogRetention,
+        Task::HistoryPurge,
+    ];
+
+    /// Period of the task under `config`, `None` when it is disabled.
+    pub fn period(self, config: &Config) -> Option<Duration> {
+        match self {
+            Task::Heartbeat => Some(Duration::from_secs(REGISTRY_HEARTBEAT_SECS)),
+            // Never coarser than the eligibility age itself.
+            Task::StaleSweep => (config.stale_job_timeout > 0.0)
+                .then(|| Duration::from_secs_f64(REAP_INTERVAL_SECS.min(config.stale_job_timeout))),
+            Task::HungWorkers => (config.worker_hang_timeout > 0.0).then(|| {
+                Duration::from_secs_f64(HANG_CHECK_INTERVAL_SECS.min(config.worker_hang_timeout))
+            }),
+            Task::RunDeadlines => Some(Duration::from_secs_f64(RUN_DEADLINE_CHECK_INTERVAL_SECS)),
+            Task::OversizedJobs => (config.max_job_size > 0)
+                .then(|| Duration::from_secs_f64(OVERSIZED_JOB_CHECK_INTERVAL_SECS)),
+            Task::LogRetention => Some(Duration::from_secs(LOG_EXPIRE_INTERVAL_SECS)),
+            Task::HistoryPurge => (config.run_details_maintenance_interval > 0.0)
+                .then(|| Duration::from_secs_f64(config.run_details_maintenance_interval)),
+        }
+    }
+
+    /// Whether the task first runs at startup rather than one period later.
+    /// The heartbeat is sent by the registration and a sweep right away
+    /// could race the jobs claimed just before a restart.
+    fn runs_at_startup(self) -> bool {
+        matches!(
+            self,
+            Task::OversizedJobs | Task::LogRetention | Task::HistoryPurge
+        )
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Last run of each housekeeping task.
+pub struct Housekeeping {
+    last_run: [Option<Instant>; Task::ALL.len()],
+}
+
+impl Housekeeping {
+    /// Schedule of a scheduler started at `now`.
+    pub fn new(now: Instant) -> Self {
+        Self {
+            last_run: Task::ALL.map(|task| (!task.runs_at_startup()).then_some(now)),
+        }
+    }
+
+    /// Whether `task` is enabled and its period has elapsed at `now`.
+    pub fn is_due(&self, task: Task, config: &Config, now: Instant) -> bool {
+        match (task.period(config), self.last_run[task.index()]) {
+            (None, _) => false,
+            (Some(_), None) => true,
+            (Some(period), Some(last)) => now.saturating_duration_since(last) >= period,
+        }
+    }
+
+    /// Record that `task` ran at `now`.
+    pub fn mark(&mut self, task: Task, now: Instant) {
+        self.last_run[task.index()] = Some(now);
+    }
+
+    /// Run `task` at the next check, e.g. after a reload changed its
+    /// settings.
+    pub fn reset(&mut self, task: Task) {
+        self.last_run[task.index()] = None;
+    }
+}
+
+/// Create the run-details partitions of the coming month and drop those
+/// older than `run_details_retention_months`, through
+/// `dbms_job.maintain_run_details_partitions()`.
+pub fn maintain_run_details(client: &mut Client, config: &Config) {
+    let retention = i32::try_from(config.run_details_retention_months).unwrap_or(i32::MAX);
+    match client.query_one(
+        &*in_schema(
+            &config.schema,
+            "SELECT dbms_job.maintain_run_details_partitions(months_ahead => 1, retention_months => $1)",
+        ),
+        &[&retention],
+    ) {
+        Ok(_) => dprint(
+            config,
+            "DEBUG",
+            "run details partitions maintained",
+        ),
+        Err(err) => dlog!(
+            config,
+            "ERROR",
+            "can not maintain the run details partitions, {}",
+            err
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Housekeeping, Task};
+    use crate::constants::DEFAULT_SCHEMA;
+    use crate::model::Config;
+    use std::collections::BTreeMap;
+    use std::time::{Duration, Instant};
+
+    fn config() -> Config {
+        let mut config = Config {
+            debug: false,
+            pidfile: "/tmp/pg_dbms_job.pid".to_string(),
+            logfile: String::new(),
+            logfile_mode: 0,
+            logfile_owner: String::new(),
+            logfile_group: String::new(),
+            log_truncate_on_rotation: false,
+            job_queue_interval: 0.1,
+            job_queue_processes: 1024,
+            pool_size: 100,
+            nap_time: 0.1,
+            startup_delay: 3.0,
+            error_delay: 0.5,
+            stats_interval: 0,
+            job_run_details: crate::model::JobRunDetails::All,
+            stale_job_timeout: 3600.0,
+            notify_debounce: 0.0,
+            schedule_refresh_interval: 60.0,
+            max_notifications_per_cycle: 10000,
+            max_job_size: 0,
+            hard_max_run_duration: 0.0,
+            hard_max_run_overruns: 3,
+            job_nice: 0,
+            job_io_class: crate::model::IoClass::None,
+            job_io_priority: 4,
+            connection_reserve: 0,
+            job_cgroup: String::new(),
+            executable_job_owners: String::new(),
+            executable_os_users: String::new(),
+            log_retention_days: 0,
+            log_compress: false,
+            max_nap_time: 0.0,
+            reconnect_max_delay: 60.0,
+            reconnect_max_attempts: 0,
+            standby_check_interval: 0.0,
+            job_run_timeout: 0.0,
+            max_failures: 0,
+            connection_check_interval: 0.0,
+            multiple_schedulers: false,
+            semaphores: BTreeMap::new(),
+            job_classes: BTreeMap::new(),
+            job_class_priorities: BTreeMap::new(),
+            run_details_maintenance_interval: 0.0,
+            run_details_retention_months: 3,
+            worker_hang_timeout: 600.0,
+            job_execution_mode: crate::model::ExecutionMode::SetRole,
+            dst_policy: crate::model::DstPolicy::Server,
+            ssh_host: String::new(),
+            ssh_port: 22,
+            ssh_user: String::new(),
+            ssh_key: String::new(),
+            log_rotation_size: 0,
+            log_rotation_count: 5,
+            log_rotation_age: crate::model::LogRotationAge::None,
+            log_destination: crate::model::LogDestination::File,
+            syslog_facility: "local0".to_string(),
+            syslog_ident: "pg_dbms_job".to_string(),
+            log_min_messages: crate::model::LogMinMessages::Log,
+            log_job_notices: false,
+            log_rotation_compress: crate::model::LogRotationCompress::None,
+            scheduler_event_log: false,
+            debug_ring_size: 0,
+            log_gelf_endpoint: String::new(),
+            auto_reload: false,
+            strict_config: false,
+            database_section: String::new(),
+            schemas: DEFAULT_SCHEMA.to_string(),
+            schema: DEFAULT_SCHEMA.to_string(),
+        };
+        config.stale_job_timeout = 30.0;
+        config
+    }
+
+    #[test]
+    fn startup_runs_log_retention_while_other_tasks_wait() {
+        let config = config();
+        let now = Instant::now();
+        let housekeeping = Housekeeping::new(now);
+        assert!(!housekeeping.is_due(Task::Heartbeat, &config, now));
+        assert!(!housekeeping.is_due(Task::StaleSweep, &config, now));
+        assert!(!housekeeping.is_due(Task::HungWorkers, &config, now));
+        assert!(!housekeeping.is_due(Task::RunDeadlines, &config, now));
+        assert!(housekeeping.is_due(Task::LogRetention, &config, now));
+        // Disabled by default.
+        assert!(!housekeeping.is_due(Task::OversizedJobs, &config, now));
+        assert!(!housekeeping.is_due(Task::HistoryPurge, &config, now));
+    }
+
+    #[test]
+    fn tasks_come_due_after_their_period() {
+        let mut config = config();
+        config.run_details_maintenance_interval = 3600.0;
+        let now = Instant::now();
+        let mut housekeeping = Housekeeping::new(now);
+        assert!(housekeeping.is_due(Task::HistoryPurge, &config, now));
+        housekeeping.mark(Task::HistoryPurge, now);
+        assert!(!housekeeping.is_due(Task::HistoryPurge, &config, now));
+
+        // The sweep period is capped by stale_job_timeout.
+        let later = now + Duration::from_secs(30);
+        assert!(housekeeping.is_due(Task::StaleSweep, &config, later));
+        assert!(!housekeeping.is_due(Task::HistoryPurge, &config, later));
+        config.stale_job_timeout = 0.0;
+        assert!(!housekeeping.is_due(Task::StaleSweep, &config, later));
+        assert!(housekeeping.is_due(Task::HungWorkers, &config, later));
+        config.worker_hang_timeout = 0.0;
+        assert!(!housekeeping.is_due(Task::HungWorkers, &config, later));
+
+        housekeeping.reset(Task::HistoryPurge);
+        assert!(housekeeping.is_due(Task::HistoryPurge, &config, later));
+
+        config.max_job_size = 1024;
+        assert!(housekeeping.is_due(Task::OversizedJobs, &config, now));
+        housekeeping.mark(Task::OversizedJobs, now);
+        assert!(!housekeeping.is_due(Task::OversizedJobs, &config, later));
+        assert!(housekeeping.is_due(Task::OversizedJobs, &config, now + Duration::from_secs(60)));
+    }
+}
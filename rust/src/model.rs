@@ -1,18 +1,73 @@
 //! Data models shared across the scheduler.
 
+use std::collections::BTreeMap;
 use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
 
 #[derive(Clone)]
 /// Runtime configuration values for the scheduler.
 pub struct Config {
     /// Whether debug logging is enabled.
     pub debug: bool,
+    /// Least severe level of the log lines written; DEBUG lines are also
+    /// written with `debug`.
+    pub log_min_messages: LogMinMessages,
+    /// DEBUG lines kept in memory when not written, dumped to the log on
+    /// SIGUSR2; `0` keeps none.
+    pub debug_ring_size: usize,
     /// Path to the daemon pid file.
     pub pidfile: String,
+    /// Reload the configuration when its file changes, as on SIGHUP.
+    pub auto_reload: bool,
+    /// Refuse a configuration file with an unknown setting instead of
+    /// ignoring the setting.
+    pub strict_config: bool,
+    /// Name of the `[database <name>]` section of the configuration file
+    /// this scheduler loop serves; empty for the settings outside sections.
+    pub database_section: String,
+    /// Job queue schemas served in the database, separated by commas, one
+    /// scheduler loop each.
+    pub schemas: String,
+    /// Job queue schema this scheduler loop serves, one of `schemas`.
+    pub schema: String,
+    /// Where log lines are written.
+    pub log_destination: LogDestination,
     /// Path template for the log file.
     pub logfile: String,
+    /// Permission bits given to a log file the scheduler creates, `0`
+    /// leaves them to the umask.
+    pub logfile_mode: u32,
+    /// User name or id owning a log file the scheduler creates, empty for
+    /// the user of the daemon.
+    pub logfile_owner: String,
+    /// Group name or id of a log file the scheduler creates, empty for the
+    /// group of the daemon.
+    pub logfile_group: String,
     /// Whether to truncate log file on rotation.
     pub log_truncate_on_rotation: bool,
+    /// Days after which a strftime-dated log file is removed. `0` keeps
+    /// them forever.
+    pub log_retention_days: u64,
+    /// Gzip strftime-dated log files older than a day.
+    pub log_compress: bool,
+    /// Size (bytes) at which the log file is renamed to `.1`, shifting the
+    /// older ones to `.2`, `.3`, ... `0` disables size-based rotation.
+    pub log_rotation_size: u64,
+    /// Period after which the log file is rotated like on reaching
+    /// `log_rotation_size`.
+    pub log_rotation_age: LogRotationAge,
+    /// Number of rotated log files kept by size- and time-based rotation.
+    pub log_rotation_count: u32,
+    /// Compression of the rotated log files.
+    pub log_rotation_compress: LogRotationCompress,
+    /// Syslog facility of the lines with `log_destination = syslog`.
+    pub syslog_facility: String,
+    /// Program name of the lines with `log_destination = syslog` or
+    /// `journald`.
+    pub syslog_ident: String,
+    /// GELF input the log lines are also shipped to, `udp://host[:port]` or
+    /// `tcp://host[:port]`; empty ships none.
+    pub log_gelf_endpoint: String,
     /// Interval (seconds) for queue polling.
     pub job_queue_interval: f64,
     /// Max number of concurrent jobs.
@@ -31,10 +86,494 @@ pub struct Config {
     pub stats_interval: u64,
     /// Which job executions are recorded in `all_scheduler_job_run_details`.
     pub job_run_details: JobRunDetails,
+    /// Also log the NOTICE and WARNING messages raised by job bodies.
+    pub log_job_notices: bool,
+    /// Also record the daemon events in `dbms_job.scheduler_event_log`.
+    pub scheduler_event_log: bool,
+    /// Interval (seconds) between runs of the run-details partition
+    /// maintenance. `0` disables it.
+    pub run_details_maintenance_interval: f64,
+    /// Months of run details kept by the partition maintenance. `0` keeps
+    /// them all.
+    pub run_details_retention_months: u64,
     /// Age (seconds) after which a job still flagged running (`this_date` set)
     /// with no live worker backend is treated as abandoned and re-queued by
     /// the reaper. `0` disables reaping.
     pub stale_job_timeout: f64,
+    /// Seconds without progress of a worker, while its backend is not
+    /// executing, after which the worker is taken as hung. `0` disables the
+    /// check.
+    pub worker_hang_timeout: f64,
+    /// How a job body is run with the privileges of its owner.
+    pub job_execution_mode: ExecutionMode,
+    /// How next dates crossing a daylight saving time change are placed.
+    pub dst_policy: DstPolicy,
+    /// Window (seconds) during which notifications following the first one
+    /// are coalesced into the same collection pass. `0` disables debouncing.
+    pub notify_debounce: f64,
+    /// Ceiling (seconds) up to which `nap_time` is doubled while the daemon
+    /// is idle. `0`, or a value not above `nap_time`, disables the back-off.
+    pub max_nap_time: f64,
+    /// Ceiling (seconds) up to which the wait between failed attempts to
+    /// reach the database is doubled from `startup_delay`.
+    pub reconnect_max_delay: f64,
+    /// Failed attempts in a row to reach the database after which the
+    /// daemon exits, `0` retrying forever.
+    pub reconnect_max_attempts: u32,
+    /// Interval (seconds) at which a connection to a database in recovery
+    /// checks for its promotion, `0` reconnecting instead.
+    pub standby_check_interval: f64,
+    /// Interval (seconds) at which the main connection is checked to still
+    /// answer, `0` disabling the check.
+    pub connection_check_interval: f64,
+    /// Several schedulers may serve the database at the same time, each
+    /// claiming its own jobs.
+    pub multiple_schedulers: bool,
+    /// Interval (seconds) between full reloads of the in-memory schedule
+    /// cache, which is otherwise updated from change notifications.
+    pub schedule_refresh_interval: f64,
+    /// Max number of notifications consumed per main loop iteration; the
+    /// remainder is processed by the next iteration.
+    pub max_notifications_per_cycle: usize,
+    /// Max size (bytes) of a job body; larger jobs are refused at claim time.
+    /// `0` disables the limit.
+    pub max_job_size: u64,
+    /// Run duration (seconds) above which a scheduled job run counts as an
+    /// overrun. `0` disables the policy.
+    pub hard_max_run_duration: f64,
+    /// Number of consecutive overruns after which a scheduled job is marked
+    /// broken.
+    pub hard_max_run_overruns: usize,
+    /// Run time (seconds) after which any job run is canceled, on top of
+    /// the `max_run_duration` of its job. `0` disables it.
+    pub job_run_timeout: f64,
+    /// Consecutive failed runs after which a scheduled job is marked broken,
+    /// unless the job has a `max_failures` of its own. `0` disables it.
+    pub max_failures: u32,
+    /// CPU niceness (0-19) applied to the database backend running a job.
+    /// `0` leaves it unchanged.
+    pub job_nice: i32,
+    /// IO scheduling class applied to the database backend running a job.
+    pub job_io_class: IoClass,
+    /// IO priority (0-7, 0 is highest) within the `best-effort` class.
+    pub job_io_priority: i32,
+    /// cgroup (directory under `/sys/fs/cgroup`, or absolute path) the
+    /// database backend running a job is moved into. Empty disables it.
+    pub job_cgroup: String,
+    /// Comma-separated roles whose `EXECUTABLE` jobs the scheduler runs.
+    /// Empty refuses them all.
+    pub executable_job_owners: String,
+    /// Comma-separated operating system users an `EXECUTABLE` job can run
+    /// as with its `os_user`. Empty refuses them all; root is never allowed.
+    pub executable_os_users: String,
+    /// Connection slots of the server left free for others: no job is
+    /// claimed when fewer remain. `0` disables the check.
+    pub connection_reserve: u64,
+    /// Bastion host the database is reached through, by an SSH tunnel the
+    /// daemon runs itself. Empty connects directly.
+    pub ssh_host: String,
+    /// SSH port of `ssh_host`.
+    pub ssh_port: u16,
+    /// User logging into `ssh_host`. Empty lets `ssh` choose.
+    pub ssh_user: String,
+    /// Private key used to log into `ssh_host`. Empty lets `ssh` choose.
+    pub ssh_key: String,
+    /// Named semaphores (`semaphore.<name> = N`): at most N jobs whose
+    /// `resource_key` is the name run at the same time.
+    pub semaphores: BTreeMap<String, u32>,
+    /// Job classes (`job_class.<name> = N`): at most N jobs whose
+    /// `job_class` is the name run at the same time, in the whole database.
+    pub job_classes: BTreeMap<String, u32>,
+    /// Priorities of the backends running the jobs of a class
+    /// (`job_class.<name>.nice`, `.io_class`, `.io_priority`), overriding
+    /// `job_nice`, `job_io_class` and `job_io_priority`.
+    pub job_class_priorities: BTreeMap<String, ClassPriority>,
+}
+
+impl Config {
+    /// Whether a log line of `level` is written.
+    pub fn logs(&self, level: &str) -> bool {
+        let level = LogMinMessages::of_level(level);
+        level >= self.log_min_messages || (level == LogMinMessages::Debug && self.debug)
+    }
+
+    /// Whether a log line of `level` that is not written is kept in the
+    /// DEBUG ring buffer.
+    pub fn buffers(&self, level: &str) -> bool {
+        self.debug_ring_size > 0
+            && LogMinMessages::of_level(level) == LogMinMessages::Debug
+            && !self.logs(level)
+    }
+}
+
+/// Least severe level of the log lines written, PostgreSQL's
+/// `log_min_messages` reduced to the levels of the scheduler.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Default)]
+pub enum LogMinMessages {
+    Debug,
+    #[default]
+    Log,
+    Warning,
+    /// ERROR, FATAL and PANIC lines are always written.
+    Error,
+}
+
+impl LogMinMessages {
+    /// Parse a configuration value: `debug` | `log` | `warning` | `error`
+    /// (case-insensitive). Returns `None` for unrecognised input.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "debug" => Some(LogMinMessages::Debug),
+            "log" => Some(LogMinMessages::Log),
+            "warning" => Some(LogMinMessages::Warning),
+            "error" => Some(LogMinMessages::Error),
+            _ => None,
+        }
+    }
+
+    /// Canonical lowercase name, used in log lines.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LogMinMessages::Debug => "debug",
+            LogMinMessages::Log => "log",
+            LogMinMessages::Warning => "warning",
+            LogMinMessages::Error => "error",
+        }
+    }
+
+    /// Rank of the level of a log line; levels between those of the
+    /// setting (INFO, NOTICE) rank as LOG, FATAL and PANIC as ERROR.
+    fn of_level(level: &str) -> Self {
+        match level.to_ascii_uppercase().as_str() {
+            "DEBUG" => LogMinMessages::Debug,
+            "WARNING" => LogMinMessages::Warning,
+            "ERROR" | "FATAL" | "PANIC" => LogMinMessages::Error,
+            _ => LogMinMessages::Log,
+        }
+    }
+}
+
+/// Priorities of the backends running the jobs of a job class, each one
+/// taking the place of the global setting when set.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct ClassPriority {
+    /// CPU niceness (0-19), in place of `job_nice`.
+    pub nice: Option<i32>,
+    /// IO scheduling class, in place of `job_io_class`.
+    pub io_class: Option<IoClass>,
+    /// IO priority (0-7) within the `best-effort` class, in place of
+    /// `job_io_priority`.
+    pub io_priority: Option<i32>,
+}
+
+/// IO scheduling class of the backends running jobs (Linux `ioprio_set`).
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum IoClass {
+    /// Leave the backend's IO priority unchanged.
+    #[default]
+    None,
+    /// Best-effort class with the priority given by `job_io_priority`.
+    BestEffort,
+    /// Only get disk time when no other process needs it.
+    Idle,
+}
+
+impl IoClass {
+    /// Parse a configuration value: `none` | `best-effort` | `idle`
+    /// (case-insensitive). Returns `None` for unrecognised input.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "none" | "" => Some(IoClass::None),
+            "best-effort" | "besteffort" => Some(IoClass::BestEffort),
+            "idle" => Some(IoClass::Idle),
+            _ => None,
+        }
+    }
+
+    /// Canonical lowercase name, used in log lines.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            IoClass::None => "none",
+            IoClass::BestEffort => "best-effort",
+            IoClass::Idle => "idle",
+        }
+    }
+}
+
+/// How a job body is run with the privileges of its owner.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum ExecutionMode {
+    /// `SET ROLE` to the owner on the scheduler's session, then a DO block.
+    /// The body can switch back with `RESET ROLE`.
+    #[default]
+    SetRole,
+    /// The body is wrapped in a temporary SECURITY DEFINER function created
+    /// by the owner, where the server refuses any change of role.
+    SecurityDefiner,
+}
+
+impl ExecutionMode {
+    /// Parse a configuration value: `set_role` | `security_definer`
+    /// (case-insensitive). Returns `None` for unrecognised input.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "set_role" | "set-role" => Some(ExecutionMode::SetRole),
+            "security_definer" | "security-definer" => Some(ExecutionMode::SecurityDefiner),
+            _ => None,
+        }
+    }
+
+    /// Canonical lowercase name, used in log lines.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ExecutionMode::SetRole => "set_role",
+            ExecutionMode::SecurityDefiner => "security_definer",
+        }
+    }
+}
+
+/// Destination of the log lines.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum LogDestination {
+    /// The `logfile`, or stderr when it is empty.
+    #[default]
+    File,
+    /// The local syslog socket.
+    Syslog,
+    /// The systemd journal, with structured fields.
+    Journald,
+}
+
+impl LogDestination {
+    /// Parse a configuration value: `file` | `syslog` | `journald`
+    /// (case-insensitive).
+    /// Returns `None` for unrecognised input.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "file" => Some(LogDestination::File),
+            "syslog" => Some(LogDestination::Syslog),
+            "journald" => Some(LogDestination::Journald),
+            _ => None,
+        }
+    }
+
+    /// Canonical lowercase name, used in log lines.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LogDestination::File => "file",
+            LogDestination::Syslog => "syslog",
+            LogDestination::Journald => "journald",
+        }
+    }
+}
+
+/// Period of the time-based rotation of the log file.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum LogRotationAge {
+    /// No time-based rotation.
+    #[default]
+    None,
+    /// Rotate on the first write of each hour.
+    Hourly,
+    /// Rotate on the first write of each day.
+    Daily,
+}
+
+impl LogRotationAge {
+    /// Parse a configuration value: `none` (or `0`) | `hourly` | `daily`
+    /// (case-insensitive). Returns `None` for unrecognised input.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "none" | "0" => Some(LogRotationAge::None),
+            "hourly" => Some(LogRotationAge::Hourly),
+            "daily" => Some(LogRotationAge::Daily),
+            _ => None,
+        }
+    }
+
+    /// Canonical lowercase name, used in log lines.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LogRotationAge::None => "none",
+            LogRotationAge::Hourly => "hourly",
+            LogRotationAge::Daily => "daily",
+        }
+    }
+}
+
+/// Compression of a rotated log file, run in the background right after
+/// the rotation.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum LogRotationCompress {
+    /// Rotated files are kept as they are.
+    #[default]
+    None,
+    /// `gzip`, to `<file>.N.gz`.
+    Gzip,
+    /// `zstd`, to `<file>.N.zst`.
+    Zstd,
+}
+
+impl LogRotationCompress {
+    /// Parse a configuration value: `none` (or `0`) | `gzip` | `zstd`
+    /// (case-insensitive). Returns `None` for unrecognised input.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "none" | "0" => Some(LogRotationCompress::None),
+            "gzip" => Some(LogRotationCompress::Gzip),
+            "zstd" => Some(LogRotationCompress::Zstd),
+            _ => None,
+        }
+    }
+
+    /// Canonical lowercase name, used in log lines.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LogRotationCompress::None => "none",
+            LogRotationCompress::Gzip => "gzip",
+            LogRotationCompress::Zstd => "zstd",
+        }
+    }
+}
+
+/// Placement of a job's next date around a daylight saving time change.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum DstPolicy {
+    /// Keep the date computed by the server's `timestamptz` arithmetic.
+    #[default]
+    Server,
+    /// Evaluate on the wall clock; a date in the skipped hour is shifted by
+    /// the length of the gap, one in the repeated hour runs once.
+    Shift,
+    /// Evaluate on the wall clock; a date in the skipped hour is skipped,
+    /// one in the repeated hour runs once.
+    Skip,
+}
+
+impl DstPolicy {
+    /// Parse a configuration value: `server` | `shift` | `skip`
+    /// (case-insensitive). Returns `None` for unrecognised input.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "server" => Some(DstPolicy::Server),
+            "shift" => Some(DstPolicy::Shift),
+            "skip" => Some(DstPolicy::Skip),
+            _ => None,
+        }
+    }
+
+    /// Canonical lowercase name, used in log lines.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DstPolicy::Server => "server",
+            DstPolicy::Shift => "shift",
+            DstPolicy::Skip => "skip",
+        }
+    }
+}
+
+/// Use of TLS on the database connections, named after the libpq modes.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum SslMode {
+    /// Never use TLS.
+    Disable,
+    /// Use TLS when the server supports it, without verifying it.
+    #[default]
+    Prefer,
+    /// Always use TLS, without verifying the server.
+    Require,
+    /// Always use TLS and verify the server certificate chain.
+    VerifyCa,
+    /// As `VerifyCa`, also checking the host name against the certificate.
+    VerifyFull,
+}
+
+impl SslMode {
+    /// Parse a configuration value: `disable` | `prefer` | `require` |
+    /// `verify-ca` | `verify-full` (case-insensitive). Returns `None` for
+    /// unrecognised input.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "disable" => Some(SslMode::Disable),
+            "prefer" => Some(SslMode::Prefer),
+            "require" => Some(SslMode::Require),
+            "verify-ca" => Some(SslMode::VerifyCa),
+            "verify-full" => Some(SslMode::VerifyFull),
+            _ => None,
+        }
+    }
+
+    /// Canonical lowercase name, used in log lines.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SslMode::Disable => "disable",
+            SslMode::Prefer => "prefer",
+            SslMode::Require => "require",
+            SslMode::VerifyCa => "verify-ca",
+            SslMode::VerifyFull => "verify-full",
+        }
+    }
+}
+
+/// How the connections authenticate.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum AuthMethod {
+    /// The password of `passwd_command`, `passwd_file` or `passwd`.
+    #[default]
+    Password,
+    /// An Amazon RDS IAM authentication token generated at each connection.
+    RdsIam,
+}
+
+impl AuthMethod {
+    /// Parse a configuration value: `password` | `rds_iam`
+    /// (case-insensitive). Returns `None` for unrecognised input.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "password" => Some(AuthMethod::Password),
+            "rds_iam" => Some(AuthMethod::RdsIam),
+            _ => None,
+        }
+    }
+
+    /// Canonical lowercase name, used in log lines.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AuthMethod::Password => "password",
+            AuthMethod::RdsIam => "rds_iam",
+        }
+    }
+}
+
+/// Kind of server the connections accept when `host` lists several,
+/// named after the libpq `target_session_attrs` values.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum TargetSessionAttrs {
+    /// The first server that accepts the connection.
+    #[default]
+    Any,
+    /// A server accepting writes, i.e. the primary.
+    ReadWrite,
+}
+
+impl TargetSessionAttrs {
+    /// Parse a configuration value: `any` | `read-write`
+    /// (case-insensitive). Returns `None` for unrecognised input.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "any" => Some(TargetSessionAttrs::Any),
+            "read-write" => Some(TargetSessionAttrs::ReadWrite),
+            _ => None,
+        }
+    }
+
+    /// Canonical lowercase name, used in log lines.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TargetSessionAttrs::Any => "any",
+            TargetSessionAttrs::ReadWrite => "read-write",
+        }
+    }
 }
 
 /// Controls how much job-execution history is written to
@@ -49,7 +588,7 @@ pub enum JobRunDetails {
     /// Record every run. This is the historical default behaviour.
     #[default]
     All,
-    /// Record only runs that failed (status = ERROR).
+    /// Record only runs that did not succeed.
     Errors,
     /// Never record run details.
     None,
@@ -77,19 +616,83 @@ impl JobRunDetails {
     }
 }
 
+/// Outcome of a job run, written to the `status` of the run details and
+/// to `last_status` of a scheduled job.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RunStatus {
+    /// The job body completed.
+    Succeeded,
+    /// The job body raised an error.
+    Failed,
+    /// Some statements of a continue-on-error job failed.
+    Partial,
+    /// The run was canceled by a statement, lock or idle timeout.
+    Timeout,
+    /// The scheduler stopped the run for exceeding its `max_run_duration`.
+    TimedOut,
+    /// The run was canceled or its backend terminated by an operator.
+    Cancelled,
+    /// The job was removed from the queue without being run.
+    Skipped,
+    /// The scheduler marked the job broken.
+    Broken,
+}
+
+impl RunStatus {
+    /// Status name, `SUCCEEDED` for a successful run.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RunStatus::Succeeded => "SUCCEEDED",
+            RunStatus::Failed => "FAILED",
+            RunStatus::Partial => "PARTIAL",
+            RunStatus::Timeout => "TIMEOUT",
+            RunStatus::TimedOut => "TIMED_OUT",
+            RunStatus::Cancelled => "CANCELLED",
+            RunStatus::Skipped => "SKIPPED",
+            RunStatus::Broken => "BROKEN",
+        }
+    }
+
+    /// Status written to the run details, where a successful run keeps the
+    /// empty status of earlier versions.
+    pub fn details_status(self) -> &'static str {
+        match self {
+            RunStatus::Succeeded => "",
+            status => status.as_str(),
+        }
+    }
+}
+
 /// Cross-thread counters incremented by worker threads.
 ///
 /// `started` is bumped when a worker enters `execute_job`; `finished` is bumped
 /// when it leaves (including via early-return error paths and panics, courtesy
 /// of [`JobStatsGuard`]). The main loop reads the counters periodically via
 /// [`JobStats::drain`] and emits them at LOG level.
+///
+/// The `*_runs` counters are cumulative since startup and never drained; they
+/// feed the summary logged on shutdown.
 #[derive(Default)]
 pub struct JobStats {
     pub started: AtomicU64,
     pub finished: AtomicU64,
+    pub scheduled_runs: AtomicU64,
+    pub async_runs: AtomicU64,
+    pub failed_runs: AtomicU64,
 }
 
 impl JobStats {
+    /// Count a job execution that ran its body to completion or failure.
+    pub fn record_run(&self, kind: JobKind, failed: bool) {
+        match kind {
+            JobKind::Scheduled => self.scheduled_runs.fetch_add(1, Ordering::Relaxed),
+            JobKind::Async => self.async_runs.fetch_add(1, Ordering::Relaxed),
+        };
+        if failed {
+            self.failed_runs.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
     /// Atomically read and reset both counters. Returns `(started, finished)`.
     pub fn drain(&self) -> (u64, u64) {
         (
@@ -99,6 +702,25 @@ impl JobStats {
     }
 }
 
+/// A configuration reload, recorded in `dbms_job.config_audit`.
+pub struct ConfigAudit {
+    /// Path of the configuration file reloaded.
+    pub config_file: String,
+    /// Content of the file, hashed by the database; `None` if unreadable.
+    pub content: Option<Vec<u8>>,
+    /// Changed settings as `(name, old, new)`.
+    pub changes: Vec<(&'static str, String, String)>,
+}
+
+/// Jobs due but not yet claimed, counted with each registry heartbeat.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct QueueDepth {
+    /// Due scheduled jobs, recurring or one-shot, not broken.
+    pub scheduled: i64,
+    /// Queued asynchronous jobs.
+    pub asynchronous: i64,
+}
+
 /// RAII guard that bumps `started` on construction and `finished` on drop.
 ///
 /// Using Drop means every exit from `execute_job` — clean return, early error
@@ -131,11 +753,55 @@ pub struct DbInfo {
     pub user: String,
     /// Database password.
     pub passwd: String,
+    /// File holding the database password, read at each connection in
+    /// place of `passwd`.
+    pub passwd_file: String,
+    /// Command whose output is the database password, run at each
+    /// connection in place of `passwd` and `passwd_file`.
+    pub passwd_command: String,
+    /// How the connections authenticate.
+    pub auth_method: AuthMethod,
+    /// AWS region of the database for `rds_iam`, empty to take it from the
+    /// environment or the host name.
+    pub aws_region: String,
+    /// Connection URI or libpq connection string, used as is in place of
+    /// the separate settings when set.
+    pub dsn: String,
+    /// Seconds to wait for a connection to be established, 0 waits
+    /// forever.
+    pub connect_timeout: u32,
+    /// Send TCP keepalives on the connections.
+    pub keepalives: bool,
+    /// Idle seconds before the first keepalive, 0 for the default of two
+    /// hours.
+    pub keepalives_idle: u32,
+    /// Seconds between unanswered keepalives, 0 for the system default.
+    pub keepalives_interval: u32,
+    /// Unanswered keepalives after which the connection is dead, 0 for the
+    /// system default.
+    pub keepalives_count: u32,
+    /// Time zone of the database sessions, empty for the server's.
+    pub timezone: String,
+    /// Whether the connections use TLS and how the server is verified.
+    pub sslmode: SslMode,
+    /// PEM file of the certificate authorities the server certificate is
+    /// verified against, empty for the system ones.
+    pub sslrootcert: String,
+    /// PEM file of the client certificate, empty for none.
+    pub sslcert: String,
+    /// PEM file of the PKCS#8 key of `sslcert`.
+    pub sslkey: String,
+    /// Server accepted when `host` is a comma-separated list, the next one
+    /// is tried otherwise.
+    pub target_session_attrs: TargetSessionAttrs,
+    /// Settings given to the sessions of the job connections only, as
+    /// `(name, value)` pairs, e.g. `("work_mem", "256MB")`.
+    pub job_session_settings: Vec<(String, String)>,
     /// Database port.
     pub port: u16,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 /// A job definition fetched from the scheduler tables.
 pub struct Job {
     /// Job identifier.
@@ -146,6 +812,44 @@ pub struct Job {
     pub log_user: Option<String>,
     /// Optional schema owner for the job.
     pub schema_user: Option<String>,
+    /// Job enqueued asynchronously after a successful run.
+    pub on_success: Option<i64>,
+    /// Job enqueued asynchronously after a failed run.
+    pub on_failure: Option<i64>,
+    /// Run each statement of `what` under its own savepoint and carry on
+    /// after a failing one.
+    pub continue_on_error: bool,
+    /// Key of the resource the job uses; runs sharing a key are serialized
+    /// with an advisory lock.
+    pub resource_key: Option<String>,
+    /// Run time after which the scheduler cancels the run, then terminates
+    /// its backend.
+    pub max_run_duration: Option<Duration>,
+    /// From 1 (highest) to 5 (lowest): due jobs are claimed and started in
+    /// this order.
+    pub priority: i32,
+    /// Scheduled job whose dependents this run completes: the job itself,
+    /// or the one an asynchronous copy was made from by a chain or a
+    /// dependency.
+    pub source_job: i64,
+    /// `what` is an operating system command run by the scheduler (job type
+    /// `EXECUTABLE`) rather than a PL/pgSQL block.
+    pub executable: bool,
+    /// Operating system user an executable job runs as, the scheduler's own
+    /// when `None`.
+    pub os_user: Option<String>,
+    /// Job class of the job, whose limit and priorities apply to its runs.
+    pub job_class: Option<String>,
+}
+
+/// Subset of jobs a `--single` run claims (`--owner`, `--job`). An empty
+/// list does not restrict.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct JobFilter {
+    /// Owners (`log_user`) of the jobs to run.
+    pub owners: Vec<String>,
+    /// Identifiers of the jobs to run.
+    pub jobs: Vec<i64>,
 }
 
 #[derive(Copy, Clone)]
@@ -169,7 +873,23 @@ impl JobKind {
 
 #[cfg(test)]
 mod tests {
-    use super::{Config, DbInfo, Job, JobKind, JobRunDetails, JobStats, JobStatsGuard};
+    use super::{
+        Config, DbInfo, DstPolicy, ExecutionMode, Job, JobKind, JobRunDetails, JobStats,
+        JobStatsGuard, LogDestination, LogMinMessages, LogRotationAge, RunStatus, SslMode,
+        TargetSessionAttrs,
+    };
+    use crate::constants::DEFAULT_SCHEMA;
+    use crate::default_dbinfo;
+    use std::collections::BTreeMap;
+    use std::time::Duration;
+
+    #[test]
+    fn run_status_keeps_empty_details_status_for_success() {
+        assert_eq!(RunStatus::Succeeded.as_str(), "SUCCEEDED");
+        assert_eq!(RunStatus::Succeeded.details_status(), "");
+        assert_eq!(RunStatus::Timeout.details_status(), "TIMEOUT");
+        assert_eq!(RunStatus::Cancelled.as_str(), "CANCELLED");
+    }
 
     #[test]
     fn job_run_details_default_is_all() {
@@ -199,6 +919,88 @@ mod tests {
         assert_eq!(JobRunDetails::parse("1"), None);
     }
 
+    #[test]
+    fn log_min_messages_parse() {
+        assert_eq!(LogMinMessages::default(), LogMinMessages::Log);
+        assert_eq!(
+            LogMinMessages::parse(" Warning"),
+            Some(LogMinMessages::Warning)
+        );
+        assert_eq!(LogMinMessages::parse("debug"), Some(LogMinMessages::Debug));
+        assert_eq!(LogMinMessages::parse("notice"), None);
+    }
+
+    #[test]
+    fn log_destination_parse() {
+        assert_eq!(LogDestination::default(), LogDestination::File);
+        assert_eq!(
+            LogDestination::parse("SysLog"),
+            Some(LogDestination::Syslog)
+        );
+        assert_eq!(LogDestination::parse("file"), Some(LogDestination::File));
+        assert_eq!(
+            LogDestination::parse("journald"),
+            Some(LogDestination::Journald)
+        );
+        assert_eq!(LogDestination::parse("eventlog"), None);
+    }
+
+    #[test]
+    fn log_rotation_age_parse() {
+        assert_eq!(LogRotationAge::default(), LogRotationAge::None);
+        assert_eq!(LogRotationAge::parse(" Daily"), Some(LogRotationAge::Daily));
+        assert_eq!(
+            LogRotationAge::parse("hourly"),
+            Some(LogRotationAge::Hourly)
+        );
+        assert_eq!(LogRotationAge::parse("0"), Some(LogRotationAge::None));
+        assert_eq!(LogRotationAge::parse("weekly"), None);
+    }
+
+    #[test]
+    fn dst_policy_parse() {
+        assert_eq!(DstPolicy::default(), DstPolicy::Server);
+        assert_eq!(DstPolicy::parse("Skip "), Some(DstPolicy::Skip));
+        assert_eq!(DstPolicy::parse("shift"), Some(DstPolicy::Shift));
+        assert_eq!(DstPolicy::parse("once"), None);
+    }
+
+    #[test]
+    fn target_session_attrs_parse() {
+        assert_eq!(TargetSessionAttrs::default(), TargetSessionAttrs::Any);
+        assert_eq!(
+            TargetSessionAttrs::parse(" Read-Write"),
+            Some(TargetSessionAttrs::ReadWrite)
+        );
+        assert_eq!(TargetSessionAttrs::parse("primary"), None);
+        assert_eq!(TargetSessionAttrs::ReadWrite.as_str(), "read-write");
+    }
+
+    #[test]
+    fn ssl_mode_parse() {
+        assert_eq!(SslMode::default(), SslMode::Prefer);
+        assert_eq!(SslMode::parse("Verify-Full"), Some(SslMode::VerifyFull));
+        assert_eq!(SslMode::parse("verify-ca"), Some(SslMode::VerifyCa));
+        assert_eq!(SslMode::parse("disable"), Some(SslMode::Disable));
+        assert_eq!(SslMode::parse("allow"), None);
+        assert_eq!(SslMode::VerifyCa.as_str(), "verify-ca");
+    }
+
+    #[test]
+    fn execution_mode_parse() {
+        assert_eq!(ExecutionMode::default(), ExecutionMode::SetRole);
+        assert_eq!(
+            ExecutionMode::parse(" SET_ROLE"),
+            Some(ExecutionMode::SetRole)
+        );
+        assert_eq!(
+            ExecutionMode::parse("security-definer"),
+            Some(ExecutionMode::SecurityDefiner)
+        );
+        assert_eq!(ExecutionMode::parse(""), None);
+        assert_eq!(ExecutionMode::parse("definer"), None);
+    }
+
     #[test]
     fn job_run_details_as_str_roundtrips_through_parse() {
         for v in [
@@ -216,6 +1018,9 @@ mod tests {
             debug: true,
             pidfile: "/tmp/test.pid".to_string(),
             logfile: "/tmp/test.log".to_string(),
+            logfile_mode: 0,
+            logfile_owner: String::new(),
+            logfile_group: String::new(),
             log_truncate_on_rotation: true,
             job_queue_interval: 10.0,
             job_queue_processes: 2,
@@ -226,6 +1031,58 @@ mod tests {
             stats_interval: 0,
             job_run_details: crate::model::JobRunDetails::All,
             stale_job_timeout: 3600.0,
+            notify_debounce: 0.0,
+            schedule_refresh_interval: 60.0,
+            max_notifications_per_cycle: 10000,
+            max_job_size: 0,
+            hard_max_run_duration: 0.0,
+            hard_max_run_overruns: 3,
+            job_nice: 0,
+            job_io_class: crate::model::IoClass::None,
+            job_io_priority: 4,
+            connection_reserve: 0,
+            job_cgroup: String::new(),
+            executable_job_owners: String::new(),
+            executable_os_users: String::new(),
+            log_retention_days: 0,
+            log_compress: false,
+            max_nap_time: 0.0,
+            reconnect_max_delay: 60.0,
+            reconnect_max_attempts: 0,
+            standby_check_interval: 0.0,
+            job_run_timeout: 0.0,
+            max_failures: 0,
+            connection_check_interval: 0.0,
+            multiple_schedulers: false,
+            semaphores: BTreeMap::new(),
+            job_classes: BTreeMap::new(),
+            job_class_priorities: BTreeMap::new(),
+            run_details_maintenance_interval: 0.0,
+            run_details_retention_months: 3,
+            worker_hang_timeout: 600.0,
+            job_execution_mode: crate::model::ExecutionMode::SetRole,
+            dst_policy: crate::model::DstPolicy::Server,
+            ssh_host: String::new(),
+            ssh_port: 22,
+            ssh_user: String::new(),
+            ssh_key: String::new(),
+            log_rotation_size: 0,
+            log_rotation_count: 5,
+            log_rotation_age: crate::model::LogRotationAge::None,
+            log_destination: crate::model::LogDestination::File,
+            syslog_facility: "local0".to_string(),
+            syslog_ident: "pg_dbms_job".to_string(),
+            log_min_messages: crate::model::LogMinMessages::Log,
+            log_job_notices: false,
+            log_rotation_compress: crate::model::LogRotationCompress::None,
+            scheduler_event_log: false,
+            debug_ring_size: 0,
+            log_gelf_endpoint: String::new(),
+            auto_reload: false,
+            strict_config: false,
+            database_section: String::new(),
+            schemas: DEFAULT_SCHEMA.to_string(),
+            schema: DEFAULT_SCHEMA.to_string(),
         };
         assert!(config.debug);
         assert_eq!(config.pidfile, "/tmp/test.pid");
@@ -235,7 +1092,7 @@ mod tests {
             database: "db".to_string(),
             user: "user".to_string(),
             passwd: "pass".to_string(),
-            port: 5432,
+            ..default_dbinfo()
         };
         assert_eq!(dbinfo.database, "db");
 
@@ -244,6 +1101,7 @@ mod tests {
             what: "SELECT 1".to_string(),
             log_user: Some("user".to_string()),
             schema_user: None,
+            ..Default::default()
         };
         assert_eq!(job.job, 1);
         assert!(matches!(JobKind::Async, JobKind::Async));
@@ -255,6 +1113,9 @@ mod tests {
             debug: true,
             pidfile: "/tmp/test.pid".to_string(),
             logfile: "/tmp/test.log".to_string(),
+            logfile_mode: 0,
+            logfile_owner: String::new(),
+            logfile_group: String::new(),
             log_truncate_on_rotation: false,
             job_queue_interval: 5.0,
             job_queue_processes: 10,
@@ -265,6 +1126,58 @@ mod tests {
             stats_interval: 30,
             job_run_details: crate::model::JobRunDetails::All,
             stale_job_timeout: 3600.0,
+            notify_debounce: 0.0,
+            schedule_refresh_interval: 60.0,
+            max_notifications_per_cycle: 10000,
+            max_job_size: 0,
+            hard_max_run_duration: 0.0,
+            hard_max_run_overruns: 3,
+            job_nice: 0,
+            job_io_class: crate::model::IoClass::None,
+            job_io_priority: 4,
+            connection_reserve: 0,
+            job_cgroup: String::new(),
+            executable_job_owners: String::new(),
+            executable_os_users: String::new(),
+            log_retention_days: 0,
+            log_compress: false,
+            max_nap_time: 0.0,
+            reconnect_max_delay: 60.0,
+            reconnect_max_attempts: 0,
+            standby_check_interval: 0.0,
+            job_run_timeout: 0.0,
+            max_failures: 0,
+            connection_check_interval: 0.0,
+            multiple_schedulers: false,
+            semaphores: BTreeMap::new(),
+            job_classes: BTreeMap::new(),
+            job_class_priorities: BTreeMap::new(),
+            run_details_maintenance_interval: 0.0,
+            run_details_retention_months: 3,
+            worker_hang_timeout: 600.0,
+            job_execution_mode: crate::model::ExecutionMode::SetRole,
+            dst_policy: crate::model::DstPolicy::Server,
+            ssh_host: String::new(),
+            ssh_port: 22,
+            ssh_user: String::new(),
+            ssh_key: String::new(),
+            log_rotation_size: 0,
+            log_rotation_count: 5,
+            log_rotation_age: crate::model::LogRotationAge::None,
+            log_destination: crate::model::LogDestination::File,
+            syslog_facility: "local0".to_string(),
+            syslog_ident: "pg_dbms_job".to_string(),
+            log_min_messages: crate::model::LogMinMessages::Log,
+            log_job_notices: false,
+            log_rotation_compress: crate::model::LogRotationCompress::None,
+            scheduler_event_log: false,
+            debug_ring_size: 0,
+            log_gelf_endpoint: String::new(),
+            auto_reload: false,
+            strict_config: false,
+            database_section: String::new(),
+            schemas: DEFAULT_SCHEMA.to_string(),
+            schema: DEFAULT_SCHEMA.to_string(),
         };
         let cloned = config.clone();
         assert_eq!(cloned.pidfile, config.pidfile);
@@ -280,6 +1193,7 @@ mod tests {
             user: "u".to_string(),
             passwd: "p".to_string(),
             port: 5433,
+            ..default_dbinfo()
         };
         let cloned = dbinfo.clone();
         assert_eq!(cloned.host, "host");
@@ -293,12 +1207,31 @@ mod tests {
             what: "DO SOMETHING".to_string(),
             log_user: Some("admin".to_string()),
             schema_user: Some("public".to_string()),
+            on_success: Some(43),
+            on_failure: None,
+            continue_on_error: true,
+            resource_key: Some("orders".to_string()),
+            max_run_duration: Some(Duration::from_secs(600)),
+            priority: 1,
+            source_job: 42,
+            executable: true,
+            os_user: Some("backup".to_string()),
+            job_class: None,
         };
         let cloned = job.clone();
         assert_eq!(cloned.job, 42);
         assert_eq!(cloned.what, "DO SOMETHING");
         assert_eq!(cloned.log_user, Some("admin".to_string()));
         assert_eq!(cloned.schema_user, Some("public".to_string()));
+        assert_eq!(cloned.on_success, Some(43));
+        assert_eq!(cloned.on_failure, None);
+        assert!(cloned.continue_on_error);
+        assert_eq!(cloned.resource_key, Some("orders".to_string()));
+        assert_eq!(cloned.max_run_duration, Some(Duration::from_secs(600)));
+        assert_eq!(cloned.priority, 1);
+        assert_eq!(cloned.source_job, 42);
+        assert!(cloned.executable);
+        assert_eq!(cloned.os_user, Some("backup".to_string()));
     }
 
     #[test]
@@ -308,11 +1241,26 @@ mod tests {
             what: String::new(),
             log_user: None,
             schema_user: None,
+            on_success: None,
+            on_failure: None,
+            continue_on_error: false,
+            resource_key: None,
+            max_run_duration: None,
+            priority: 3,
+            source_job: 0,
+            executable: false,
+            os_user: None,
+            job_class: None,
         };
         assert_eq!(job.job, 0);
         assert!(job.what.is_empty());
         assert!(job.log_user.is_none());
         assert!(job.schema_user.is_none());
+        assert!(job.on_success.is_none());
+        assert!(job.on_failure.is_none());
+        assert!(job.resource_key.is_none());
+        assert!(job.max_run_duration.is_none());
+        assert!(job.os_user.is_none());
     }
 
     #[test]
@@ -379,6 +1327,20 @@ mod tests {
         assert_eq!(stats.drain(), (1, 1));
     }
 
+    #[test]
+    fn job_stats_record_run_is_cumulative() {
+        use std::sync::atomic::Ordering;
+        let stats = JobStats::default();
+        stats.record_run(JobKind::Scheduled, false);
+        stats.record_run(JobKind::Async, true);
+        stats.record_run(JobKind::Async, false);
+        // Draining the periodic counters leaves the totals untouched.
+        stats.drain();
+        assert_eq!(stats.scheduled_runs.load(Ordering::Relaxed), 1);
+        assert_eq!(stats.async_runs.load(Ordering::Relaxed), 2);
+        assert_eq!(stats.failed_runs.load(Ordering::Relaxed), 1);
+    }
+
     #[test]
     fn jobkind_label_is_stable_across_copies() {
         // The label is used in `application_name` strings emitted to PG and
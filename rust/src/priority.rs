@@ -0,0 +1,239 @@
+//! CPU and IO priority, and cgroup, of the database backends running jobs.
+//!
+//! Job bodies are executed by PostgreSQL backends, not by the scheduler, so
+//! lowering the scheduler's own priority would not make heavy jobs yield to
+//! the rest of the host. When the database runs on the same host, the
+//! scheduler instead applies `job_nice` and `job_io_class` to the backend of
+//! each job connection, and moves it into `job_cgroup` so that the kernel
+//! accounts and limits job CPU and memory apart from the rest of the
+//! server. This needs the right to change that process's priority: run the
+//! scheduler as the PostgreSQL system user (or with `CAP_SYS_NICE`). A
+//! non-privileged user can only lower a priority, never raise it back. The
+//! cgroup must be writable by that user, e.g. a delegated systemd slice.
+//!
+//! A job class can have priorities of its own, `job_class.<name>.nice`,
+//! `.io_class` and `.io_priority`. Job connections are pooled, so once a
+//! class has any, the priorities of every job are set, global ones
+//! included, rather than left to what the previous job of the backend had.
+//!
+//! Through `ssh_host` the server address is the local end of the tunnel
+//! and the backend pid is one of the remote host, so nothing is applied.
+
+use crate::dlog;
+use crate::model::{Config, IoClass, Job};
+use postgres::Client;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Set once the "can not apply" warning has been logged, so that a setup
+/// where priorities can never be applied does not log it for every job.
+static WARNED: AtomicBool = AtomicBool::new(false);
+
+/// `ioprio_set` target: a single process.
+#[cfg(target_os = "linux")]
+const IOPRIO_WHO_PROCESS: libc::c_int = 1;
+/// Bit position of the class in an `ioprio` value.
+const IOPRIO_CLASS_SHIFT: i32 = 13;
+const IOPRIO_CLASS_BE: i32 = 2;
+const IOPRIO_CLASS_IDLE: i32 = 3;
+
+/// Mount point of the cgroup hierarchy, base of a relative `job_cgroup`.
+const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+
+/// Apply the configured priorities, those of the class of `job` first, and
+/// cgroup to the backend of a job connection.
+///
+/// Failure only costs the priority change, so it is logged once as a
+/// WARNING and the job runs anyway.
+pub fn apply_job_priority(client: &mut Client, config: &Config, job: &Job) {
+    if config.job_nice == 0
+        && config.job_io_class == IoClass::None
+        && config.job_cgroup.is_empty()
+        && config.job_class_priorities.is_empty()
+    {
+        return;
+    }
+    if let Err(reason) = try_apply(client, config, job.job_class.as_deref())
+        && !WARNED.swap(true, Ordering::Relaxed)
+    {
+        dlog!(
+            config,
+            "WARNING",
+            "can not apply job_nice/job_io_class/job_cgroup to job backends, {}",
+            reason
+        );
+    }
+}
+
+/// CPU niceness, IO class and IO priority for a job of `class`.
+fn job_priority(config: &Config, class: Option<&str>) -> (i32, IoClass, i32) {
+    let own = class
+        .and_then(|class| config.job_class_priorities.get(class))
+        .copied()
+        .unwrap_or_default();
+    (
+        own.nice.unwrap_or(config.job_nice),
+        own.io_class.unwrap_or(config.job_io_class),
+        own.io_priority.unwrap_or(config.job_io_priority),
+    )
+}
+
+fn try_apply(client: &mut Client, config: &Config, class: Option<&str>) -> Result<(), String> {
+    if !config.ssh_host.is_empty() {
+        return Err("the database server is reached through ssh_host".to_string());
+    }
+    // A Unix socket connection has no server address.
+    let row = client
+        .query_one(
+            "SELECT pg_backend_pid(), \
+             coalesce(host(inet_server_addr()) IN ('127.0.0.1', '::1'), true)",
+            &[],
+        )
+        .map_err(|e| e.to_string())?;
+    let pid: i32 = row.get(0);
+    let local: bool = row.get(1);
+    if !local {
+        return Err("the database server is not on this host".to_string());
+    }
+    // The pid comes from the server; make sure it names a PostgreSQL
+    // process here before touching it.
+    let comm = fs::read_to_string(format!("/proc/{pid}/comm")).unwrap_or_default();
+    if comm.trim() != "postgres" {
+        return Err(format!(
+            "process {pid} is not a PostgreSQL backend of this host"
+        ));
+    }
+    // A pooled backend keeps the priorities of its previous job, which
+    // may have been of another class.
+    let reset = !config.job_class_priorities.is_empty();
+    let (nice, io_class, io_priority) = job_priority(config, class);
+    if nice > 0 || reset {
+        set_nice(pid, nice).map_err(|e| format!("setpriority: {e}"))?;
+    }
+    match ioprio_value(io_class, io_priority) {
+        Some(ioprio) => set_ioprio(pid, ioprio).map_err(|e| format!("ioprio_set: {e}"))?,
+        // No class: the IO priority follows the niceness again.
+        None if reset => set_ioprio(pid, 0).map_err(|e| format!("ioprio_set: {e}"))?,
+        None => {}
+    }
+    if !config.job_cgroup.is_empty() {
+        let procs = cgroup_procs_path(&config.job_cgroup);
+        // A pooled backend already moved stays there; writing its pid
+        // again is harmless.
+        fs::write(&procs, pid.to_string())
+            .map_err(|e| format!("can not write {}: {e}", procs.display()))?;
+    }
+    Ok(())
+}
+
+/// `cgroup.procs` file of a cgroup, given as an absolute path or relative
+/// to the cgroup mount point.
+fn cgroup_procs_path(cgroup: &str) -> PathBuf {
+    let dir = Path::new(cgroup.trim());
+    if dir.is_absolute() {
+        dir.join("cgroup.procs")
+    } else {
+        Path::new(CGROUP_ROOT).join(dir).join("cgroup.procs")
+    }
+}
+
+/// `ioprio_set` value for a class and best-effort priority, `None` when the
+/// IO priority is left alone.
+fn ioprio_value(class: IoClass, priority: i32) -> Option<i32> {
+    match class {
+        IoClass::None => None,
+        IoClass::BestEffort => Some((IOPRIO_CLASS_BE << IOPRIO_CLASS_SHIFT) | priority),
+        IoClass::Idle => Some(IOPRIO_CLASS_IDLE << IOPRIO_CLASS_SHIFT),
+    }
+}
+
+fn set_nice(pid: i32, nice: i32) -> io::Result<()> {
+    // SAFETY: setpriority only reads its integer arguments.
+    let rc = unsafe { libc::setpriority(libc::PRIO_PROCESS, pid as libc::id_t, nice) };
+    if rc == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn set_ioprio(pid: i32, ioprio: i32) -> io::Result<()> {
+    // SAFETY: ioprio_set takes three integers and no pointers.
+    let rc = unsafe { libc::syscall(libc::SYS_ioprio_set, IOPRIO_WHO_PROCESS, pid, ioprio) };
+    if rc == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_ioprio(_pid: i32, _ioprio: i32) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "IO priorities are only supported on Linux",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{cgroup_procs_path, ioprio_value, job_priority};
+    use crate::default_config;
+    use crate::model::{ClassPriority, Config, IoClass};
+    use std::path::Path;
+
+    #[test]
+    fn job_priority_prefers_the_settings_of_the_job_class() {
+        let mut config = Config {
+            job_nice: 5,
+            job_io_class: IoClass::BestEffort,
+            job_io_priority: 4,
+            ..default_config()
+        };
+        config.job_class_priorities.insert(
+            "etl".to_string(),
+            ClassPriority {
+                nice: Some(15),
+                io_class: Some(IoClass::Idle),
+                io_priority: None,
+            },
+        );
+        assert_eq!(job_priority(&config, None), (5, IoClass::BestEffort, 4));
+        assert_eq!(
+            job_priority(&config, Some("monitoring")),
+            (5, IoClass::BestEffort, 4)
+        );
+        assert_eq!(job_priority(&config, Some("etl")), (15, IoClass::Idle, 4));
+    }
+
+    #[test]
+    fn ioprio_value_encodes_class_and_priority() {
+        assert_eq!(ioprio_value(IoClass::None, 4), None);
+        assert_eq!(ioprio_value(IoClass::BestEffort, 7), Some((2 << 13) | 7));
+        assert_eq!(ioprio_value(IoClass::BestEffort, 0), Some(2 << 13));
+        // The idle class has no priority levels.
+        assert_eq!(ioprio_value(IoClass::Idle, 7), Some(3 << 13));
+    }
+
+    #[test]
+    fn cgroup_procs_path_resolves_relative_names() {
+        assert_eq!(
+            cgroup_procs_path("pg_dbms_job.slice/jobs"),
+            Path::new("/sys/fs/cgroup/pg_dbms_job.slice/jobs/cgroup.procs")
+        );
+        assert_eq!(
+            cgroup_procs_path("/sys/fs/cgroup/system.slice/jobs"),
+            Path::new("/sys/fs/cgroup/system.slice/jobs/cgroup.procs")
+        );
+    }
+
+    #[test]
+    fn io_class_parse_roundtrips() {
+        for class in [IoClass::None, IoClass::BestEffort, IoClass::Idle] {
+            assert_eq!(IoClass::parse(class.as_str()), Some(class));
+        }
+        assert_eq!(IoClass::parse(" IDLE "), Some(IoClass::Idle));
+        assert_eq!(IoClass::parse("realtime"), None);
+    }
+}
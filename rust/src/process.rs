@@ -2,7 +2,7 @@
 
 use crate::constants::PROGRAM;
 use crate::dlog;
-use crate::logging::reset_logger_after_fork;
+use crate::logging::{flush_logger, reset_logger_after_fork};
 use crate::model::Config;
 use crate::util::die;
 use fs2::FileExt;
@@ -26,6 +26,8 @@ static PIDFILE_GUARD: Mutex<Option<File>> = Mutex::new(None);
 
 /// Fork and detach the scheduler from the controlling terminal.
 pub fn daemonize(config: &Config) {
+    // Only the child keeps logging: let the parent's writer drain first.
+    flush_logger();
     match unsafe { fork() } {
         Ok(ForkResult::Parent { .. }) => process::exit(0),
         Ok(ForkResult::Child) => {}
@@ -140,7 +142,7 @@ pub fn signal_handling(pidfile: &str, sig: Signal) {
 }
 
 /// Read a pid from a file path.
-fn read_pid_from_file(path: &str) -> Option<i32> {
+pub fn read_pid_from_file(path: &str) -> Option<i32> {
     let mut buf = String::new();
     File::open(path).ok()?.read_to_string(&mut buf).ok()?;
     buf.trim().parse::<i32>().ok()
@@ -0,0 +1,297 @@
+//! Amazon RDS IAM authentication.
+//!
+//! With `auth_method=rds_iam` the password of each connection is an
+//! authentication token generated at connect time: a SigV4 presigned
+//! `connect` request for the `rds-db` service, valid 15 minutes. It is
+//! signed by the AWS SDK with the credentials of its default provider
+//! chain: the environment, the shared configuration and credentials files
+//! (profiles, SSO, assume-role), a web identity token (EKS IRSA), the ECS
+//! container credentials or the EC2 instance profile. A new token is
+//! generated for every connection, so reconnections never reuse an expired
+//! one.
+
+use crate::model::DbInfo;
+use aws_config::{BehaviorVersion, SdkConfig};
+use aws_credential_types::Credentials;
+use aws_credential_types::provider::ProvideCredentials;
+use aws_sigv4::http_request::{
+    SignableBody, SignableRequest, SignatureLocation, SigningSettings, sign,
+};
+use aws_sigv4::sign::v4;
+use std::env;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, SystemTime};
+use tokio::runtime::{Builder, Runtime};
+
+/// Seconds a token can be used to open a connection.
+const TOKEN_LIFETIME_SECS: u64 = 900;
+/// Service the tokens are signed for.
+const SERVICE: &str = "rds-db";
+/// Temporary credentials are fetched again once they expire within this
+/// time, so that a token is never signed with credentials about to expire.
+const CREDENTIALS_REFRESH_MARGIN: Duration = Duration::from_secs(300);
+
+/// The AWS SDK configuration of the daemon, loaded at the first token, and
+/// the runtime its providers run on.
+struct Sdk {
+    runtime: Runtime,
+    config: SdkConfig,
+    /// Temporary credentials of the last token, kept until they are about to
+    /// expire so that the instance metadata or STS are not asked for every
+    /// connection.
+    cached: Mutex<Option<Credentials>>,
+}
+
+static SDK: OnceLock<Result<Sdk, String>> = OnceLock::new();
+
+fn sdk() -> Result<&'static Sdk, String> {
+    SDK.get_or_init(|| {
+        let runtime = Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| format!("can not start the AWS SDK runtime, {e}"))?;
+        let config = runtime.block_on(aws_config::load_defaults(BehaviorVersion::latest()));
+        Ok(Sdk {
+            runtime,
+            config,
+            cached: Mutex::new(None),
+        })
+    })
+    .as_ref()
+    .map_err(String::clone)
+}
+
+/// Generate the authentication token of a connection to `dbinfo`.
+pub fn auth_token(dbinfo: &DbInfo) -> Result<String, String> {
+    let sdk = sdk()?;
+    let credentials = credentials(sdk)?;
+    let configured = sdk.config.region().map(ToString::to_string).or_else(|| {
+        env::var("AWS_DEFAULT_REGION")
+            .ok()
+            .filter(|v| !v.is_empty())
+    });
+    let region = region(dbinfo, configured)?;
+    token(
+        &dbinfo.host,
+        dbinfo.port,
+        &dbinfo.user,
+        &region,
+        &credentials,
+        SystemTime::now(),
+    )
+}
+
+/// Credentials of the default provider chain of the SDK.
+fn credentials(sdk: &Sdk) -> Result<Credentials, String> {
+    let mut cached = sdk.cached.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(credentials) = cached.as_ref()
+        && still_valid(credentials, SystemTime::now())
+    {
+        return Ok(credentials.clone());
+    }
+    let provider = sdk
+        .config
+        .credentials_provider()
+        .ok_or_else(|| "no AWS credentials provider configured".to_string())?;
+    let credentials = sdk
+        .runtime
+        .block_on(provider.provide_credentials())
+        .map_err(|e| format!("can not get AWS credentials, {}", error_chain(&e)))?;
+    // Credentials without expiry come from the environment or a file, read
+    // again for each token so that a rotation is seen.
+    *cached = credentials.expiry().is_some().then(|| credentials.clone());
+    Ok(credentials)
+}
+
+/// Whether temporary credentials can still sign a token at `now`.
+fn still_valid(credentials: &Credentials, now: SystemTime) -> bool {
+    credentials
+        .expiry()
+        .is_some_and(|expiry| expiry > now + CREDENTIALS_REFRESH_MARGIN)
+}
+
+/// An error of the SDK with its sources, which hold the reason, e.g. the
+/// providers tried.
+fn error_chain(err: &dyn std::error::Error) -> String {
+    let mut text = err.to_string();
+    let mut source = err.source();
+    while let Some(err) = source {
+        text.push_str(": ");
+        text.push_str(&err.to_string());
+        source = err.source();
+    }
+    text
+}
+
+/// Region of the database: `aws_region`, else the region configured for
+/// the SDK (`AWS_REGION`, the profile, the instance metadata) or
+/// `AWS_DEFAULT_REGION`, else the one in an RDS host name such as
+/// `mydb.abc123.eu-west-1.rds.amazonaws.com`.
+fn region(dbinfo: &DbInfo, configured: Option<String>) -> Result<String, String> {
+    if !dbinfo.aws_region.is_empty() {
+        return Ok(dbinfo.aws_region.clone());
+    }
+    if let Some(region) = configured {
+        return Ok(region);
+    }
+    let labels: Vec<&str> = dbinfo.host.split('.').collect();
+    match labels.iter().position(|label| *label == "rds") {
+        Some(i) if i > 0 => Ok(labels[i - 1].to_string()),
+        _ => Err(format!(
+            "can not tell the AWS region of host {}, set aws_region",
+            dbinfo.host
+        )),
+    }
+}
+
+/// The token: the URL of the `connect` action presigned by the SDK, without
+/// scheme.
+fn token(
+    host: &str,
+    port: u16,
+    user: &str,
+    region: &str,
+    credentials: &Credentials,
+    time: SystemTime,
+) -> Result<String, String> {
+    let mut settings = SigningSettings::default();
+    settings.expires_in = Some(Duration::from_secs(TOKEN_LIFETIME_SECS));
+    settings.signature_location = SignatureLocation::QueryParams;
+    let identity = credentials.clone().into();
+    let params = v4::SigningParams::builder()
+        .identity(&identity)
+        .region(region)
+        .name(SERVICE)
+        .time(time)
+        .settings(settings)
+        .build()
+        .map_err(|e| format!("can not sign the RDS IAM token, {e}"))?
+        .into();
+    let url = format!(
+        "https://{host}:{port}/?Action=connect&DBUser={}",
+        uri_encode(user)
+    );
+    let request = SignableRequest::new("GET", &url, std::iter::empty(), SignableBody::empty())
+        .map_err(|e| format!("can not sign the RDS IAM token, {e}"))?;
+    let (instructions, _) = sign(request, &params)
+        .map_err(|e| format!("can not sign the RDS IAM token, {e}"))?
+        .into_parts();
+    let mut token = url["https://".len()..].to_string();
+    for (name, value) in instructions.params() {
+        token.push_str(&format!("&{name}={}", uri_encode(value)));
+    }
+    Ok(token)
+}
+
+/// Percent-encode all but the unreserved characters, as SigV4 requires.
+fn uri_encode(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for b in s.bytes() {
+        if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.' | b'~') {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{b:02X}"));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CREDENTIALS_REFRESH_MARGIN, region, still_valid, token};
+    use crate::default_dbinfo;
+    use crate::model::{AuthMethod, DbInfo, SslMode};
+    use aws_credential_types::Credentials;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    fn dbinfo(host: &str) -> DbInfo {
+        DbInfo {
+            host: host.to_string(),
+            database: "d".to_string(),
+            user: "app_user".to_string(),
+            auth_method: AuthMethod::RdsIam,
+            sslmode: SslMode::VerifyFull,
+            ..default_dbinfo()
+        }
+    }
+
+    #[test]
+    fn tokens_are_presigned_connect_requests() {
+        // 2026-10-16 13:05:09 UTC
+        let now = UNIX_EPOCH + Duration::from_secs(1_792_155_909);
+        let credentials = Credentials::new(
+            "AKIDEXAMPLE",
+            "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+            None,
+            None,
+            "test",
+        );
+        let host = "mydb.abc123.eu-west-1.rds.amazonaws.com";
+        let signed = token(host, 5432, "app_user", "eu-west-1", &credentials, now).unwrap();
+        assert!(
+            signed.starts_with(
+                "mydb.abc123.eu-west-1.rds.amazonaws.com:5432/?Action=connect&DBUser=app_user&"
+            ),
+            "{signed}"
+        );
+        for param in [
+            "X-Amz-Algorithm=AWS4-HMAC-SHA256",
+            "X-Amz-Credential=AKIDEXAMPLE%2F20261016%2Feu-west-1%2Frds-db%2Faws4_request",
+            "X-Amz-Date=20261016T130509Z",
+            "X-Amz-Expires=900",
+            "X-Amz-SignedHeaders=host",
+        ] {
+            assert!(signed.contains(&format!("&{param}")), "{param} in {signed}");
+        }
+        let signature = signed.split("&X-Amz-Signature=").nth(1).unwrap();
+        assert_eq!(signature.len(), 64);
+        assert!(!signed.contains("X-Amz-Security-Token"));
+
+        let credentials = Credentials::new(
+            "AKIDEXAMPLE",
+            "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+            Some("FQoG/session+token=".to_string()),
+            None,
+            "test",
+        );
+        let signed = token(host, 5432, "app user@x", "eu-west-1", &credentials, now).unwrap();
+        assert!(signed.contains("DBUser=app%20user%40x&"), "{signed}");
+        assert!(
+            signed.contains("&X-Amz-Security-Token=FQoG%2Fsession%2Btoken%3D"),
+            "{signed}"
+        );
+    }
+
+    #[test]
+    fn temporary_credentials_are_renewed_before_they_expire() {
+        let now = SystemTime::now();
+        let expiring =
+            |expiry| Credentials::new("AKID", "secret", Some("t".to_string()), expiry, "test");
+        assert!(still_valid(
+            &expiring(Some(now + Duration::from_secs(3600))),
+            now
+        ));
+        assert!(!still_valid(
+            &expiring(Some(now + CREDENTIALS_REFRESH_MARGIN / 2)),
+            now
+        ));
+        // Long-term credentials are read again for each token.
+        assert!(!still_valid(&expiring(None), now));
+    }
+
+    #[test]
+    fn region_from_setting_sdk_configuration_or_host() {
+        let mut info = dbinfo("mydb.abc123.eu-west-1.rds.amazonaws.com");
+        assert_eq!(region(&info, None), Ok("eu-west-1".to_string()));
+        let configured = Some("us-east-2".to_string());
+        assert_eq!(
+            region(&info, configured.clone()),
+            Ok("us-east-2".to_string())
+        );
+        info.aws_region = "ap-south-1".to_string();
+        assert_eq!(region(&info, configured), Ok("ap-south-1".to_string()));
+
+        let info = dbinfo("db.example.com");
+        assert!(region(&info, None).is_err());
+    }
+}
@@ -0,0 +1,331 @@
+//! Writer of the job run details.
+//!
+//! A worker used to insert the row of its run into
+//! `dbms_job.all_scheduler_job_run_details` on its job connection, as its
+//! last step: when that connection had just broken, with the job, the row
+//! was lost with an ERROR line. Workers now queue their row in a
+//! [`RunDetailsQueue`] and the main loop writes the queue on its own
+//! connection, a batch per transaction. Rows that fail on a transient error,
+//! such as a lost connection, stay queued and are written once it is back.
+
+use crate::db::in_schema;
+use crate::dlog;
+use crate::logging::dprint;
+use crate::model::Config;
+use postgres::types::ToSql;
+use postgres::{Client, Error};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+/// Rows kept while they can not be written; the oldest are dropped beyond.
+const PENDING_RUN_DETAILS_MAX: usize = 10_000;
+
+/// Rows written per transaction.
+const RUN_DETAILS_BATCH: usize = 500;
+
+const INSERT_RUN_DETAILS: &str = r#"
+    INSERT INTO dbms_job.all_scheduler_job_run_details
+        (owner, job_name, status, error, req_start_date, actual_start_date, run_duration, slave_pid, additional_info, result,
+         error_message, error_detail, error_hint, error_context, run_id, scheduler_host, job_schema)
+    VALUES
+        ($1, $2::bigint::text, $3, $4::bigint, NULL,
+         to_timestamp($5, 'YYYY-MM-DD HH24:MI:SS'),
+         $6,
+         $7, $8, $9, $10, $11, $12, $13, $14::text::uuid, $15, $16)
+    "#;
+
+/// A row of `dbms_job.all_scheduler_job_run_details`.
+#[derive(Debug)]
+pub struct RunDetails {
+    pub owner: String,
+    pub jobid: i64,
+    pub status: &'static str,
+    pub error_code: Option<i64>,
+    pub start_date: String,
+    pub duration_secs: i64,
+    /// Pid of the backend that ran the job, `None` for a job refused
+    /// before running.
+    pub backend_pid: Option<i32>,
+    pub additional_info: String,
+    pub result: Option<String>,
+    pub error_message: Option<String>,
+    pub error_detail: Option<String>,
+    pub error_hint: Option<String>,
+    pub error_context: Option<String>,
+    /// Id of the run, `None` for a job refused before running.
+    pub run_id: Option<String>,
+    /// Host name of the scheduler.
+    pub scheduler_host: String,
+    /// Job queue schema of the job.
+    pub job_schema: String,
+}
+
+impl RunDetails {
+    fn params(&self) -> [&(dyn ToSql + Sync); 16] {
+        [
+            &self.owner,
+            &self.jobid,
+            &self.status,
+            &self.error_code,
+            &self.start_date,
+            &self.duration_secs,
+            &self.backend_pid,
+            &self.additional_info,
+            &self.result,
+            &self.error_message,
+            &self.error_detail,
+            &self.error_hint,
+            &self.error_context,
+            &self.run_id,
+            &self.scheduler_host,
+            &self.job_schema,
+        ]
+    }
+}
+
+/// Run details queued by the workers for the main loop to write.
+#[derive(Default)]
+pub struct RunDetailsQueue {
+    pending: Mutex<VecDeque<RunDetails>>,
+    /// Rows dropped from a full queue since the last flush.
+    dropped: AtomicUsize,
+    /// The last flush failed, its error logged.
+    failing: AtomicBool,
+}
+
+impl RunDetailsQueue {
+    /// Queue the row of a run.
+    pub fn push(&self, details: RunDetails) {
+        let Ok(mut pending) = self.pending.lock() else {
+            return;
+        };
+        if pending.len() >= PENDING_RUN_DETAILS_MAX {
+            pending.pop_front();
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+        pending.push_back(details);
+    }
+
+    /// Number of rows waiting to be written.
+    pub fn len(&self) -> usize {
+        self.pending.lock().map_or(0, |pending| pending.len())
+    }
+
+    /// Write the queued rows. A batch failing on a transient error is kept
+    /// for the next call; on any other error its rows are written one by
+    /// one, so that only the ones the table refuses are lost.
+    pub fn flush(&self, client: &mut Client, config: &Config) {
+        let dropped = self.dropped.swap(0, Ordering::Relaxed);
+        if dropped > 0 {
+            dlog!(
+                config,
+                "ERROR",
+                "{} job run details dropped, more than {} were waiting to be written",
+                dropped,
+                PENDING_RUN_DETAILS_MAX
+            );
+        }
+        let insert = in_schema(&config.schema, INSERT_RUN_DETAILS);
+        loop {
+            let batch = self.take(RUN_DETAILS_BATCH);
+            if batch.is_empty() {
+                return;
+            }
+            let err = match insert_batch(client, &insert, &batch) {
+                Ok(()) => {
+                    if self.failing.swap(false, Ordering::Relaxed) {
+                        dprint(config, "LOG", "job run details are written again");
+                    }
+                    continue;
+                }
+                Err(err) => err,
+            };
+            if is_transient(client, &err) {
+                self.keep_for_retry(config, batch, &err);
+                return;
+            }
+            let mut rows = batch.into_iter();
+            while let Some(details) = rows.next() {
+                let Err(err) = client.execute(insert.as_ref(), &details.params()) else {
+                    continue;
+                };
+                if is_transient(client, &err) {
+                    self.keep_for_retry(
+                        config,
+                        std::iter::once(details).chain(rows).collect(),
+                        &err,
+                    );
+                    return;
+                }
+                log_refused(config, &details, &err);
+            }
+        }
+    }
+
+    /// Put back rows that failed on the transient error `err`, warning of
+    /// it when the previous write did not fail.
+    fn keep_for_retry(&self, config: &Config, rows: Vec<RunDetails>, err: &Error) {
+        if !self.failing.swap(true, Ordering::Relaxed) {
+            dlog!(
+                config,
+                "WARNING",
+                "can not write {} job run details, keeping them to retry: {}",
+                rows.len() + self.len(),
+                err.as_db_error()
+                    .map_or_else(|| err.to_string(), |db| db.message().to_string())
+            );
+        }
+        self.give_back(rows);
+    }
+
+    /// Remove and return up to `max` rows, oldest first.
+    fn take(&self, max: usize) -> Vec<RunDetails> {
+        let Ok(mut pending) = self.pending.lock() else {
+            return Vec::new();
+        };
+        let count = pending.len().min(max);
+        pending.drain(..count).collect()
+    }
+
+    /// Put rows back at the front of the queue, in their order.
+    fn give_back(&self, rows: Vec<RunDetails>) {
+        if let Ok(mut pending) = self.pending.lock() {
+            for details in rows.into_iter().rev() {
+                pending.push_front(details);
+            }
+        }
+    }
+}
+
+/// Write the row of a run right away, on the caller's connection.
+pub fn store_run_details(client: &mut Client, config: &Config, details: &RunDetails) {
+    let insert = in_schema(&config.schema, INSERT_RUN_DETAILS);
+    if let Err(err) = client.execute(insert.as_ref(), &details.params()) {
+        log_refused(config, details, &err);
+    }
+}
+
+/// Write rows in a single transaction. The main loop does not wait more
+/// than a second for a lock on the table, the rows are retried instead.
+fn insert_batch(client: &mut Client, insert: &str, batch: &[RunDetails]) -> Result<(), Error> {
+    let mut tx = client.transaction()?;
+    tx.batch_execute("SET LOCAL lock_timeout TO '1s'")?;
+    let statement = tx.prepare(insert)?;
+    for details in batch {
+        tx.execute(&statement, &details.params())?;
+    }
+    tx.commit()
+}
+
+/// Whether a failed write may succeed later as is: the connection is gone,
+/// or the server reported a connection, concurrency or resource problem.
+fn is_transient(client: &Client, err: &Error) -> bool {
+    if client.is_closed() {
+        return true;
+    }
+    match err.as_db_error() {
+        Some(db) => is_transient_sqlstate(db.code().code()),
+        None => true,
+    }
+}
+
+/// SQLSTATE classes 08 (connection exception), 40 (transaction rollback),
+/// 53 (insufficient resources) and 57 (operator intervention), and
+/// lock_not_available.
+fn is_transient_sqlstate(code: &str) -> bool {
+    matches!(code.get(..2), Some("08" | "40" | "53" | "57")) || code == "55P03"
+}
+
+fn log_refused(config: &Config, details: &RunDetails, err: &Error) {
+    if let Some(db) = err.as_db_error() {
+        dlog!(
+            config,
+            "ERROR",
+            "failed to store job execution details for job {}: code={} message={} detail={:?} hint={:?}",
+            details.jobid,
+            db.code().code(),
+            db.message(),
+            db.detail(),
+            db.hint()
+        );
+    } else {
+        dlog!(
+            config,
+            "ERROR",
+            "failed to store job execution details for job {}: {}",
+            details.jobid,
+            err
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PENDING_RUN_DETAILS_MAX, RunDetails, RunDetailsQueue, is_transient_sqlstate};
+    use std::sync::atomic::Ordering;
+
+    fn details(jobid: i64) -> RunDetails {
+        RunDetails {
+            owner: "alice".to_string(),
+            jobid,
+            status: "",
+            error_code: None,
+            start_date: "2026-10-16 12:00:00".to_string(),
+            duration_secs: 1,
+            backend_pid: Some(1),
+            additional_info: String::new(),
+            result: None,
+            error_message: None,
+            error_detail: None,
+            error_hint: None,
+            error_context: None,
+            run_id: None,
+            scheduler_host: "vm".to_string(),
+            job_schema: "dbms_job".to_string(),
+        }
+    }
+
+    #[test]
+    fn rows_are_taken_in_order_and_given_back_in_front() {
+        let queue = RunDetailsQueue::default();
+        for jobid in 1..=5 {
+            queue.push(details(jobid));
+        }
+        let batch = queue.take(3);
+        assert_eq!(
+            batch.iter().map(|d| d.jobid).collect::<Vec<_>>(),
+            vec![1, 2, 3]
+        );
+        queue.give_back(batch);
+        let all = queue.take(10);
+        assert_eq!(
+            all.iter().map(|d| d.jobid).collect::<Vec<_>>(),
+            vec![1, 2, 3, 4, 5]
+        );
+        assert_eq!(queue.len(), 0);
+    }
+
+    #[test]
+    fn a_full_queue_drops_the_oldest_rows() {
+        let queue = RunDetailsQueue::default();
+        for jobid in 0..PENDING_RUN_DETAILS_MAX as i64 + 2 {
+            queue.push(details(jobid));
+        }
+        assert_eq!(queue.len(), PENDING_RUN_DETAILS_MAX);
+        assert_eq!(queue.take(1)[0].jobid, 2);
+        assert_eq!(queue.dropped.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn transient_errors_are_told_apart() {
+        assert!(is_transient_sqlstate("08006"));
+        assert!(is_transient_sqlstate("40P01"));
+        assert!(is_transient_sqlstate("53100"));
+        assert!(is_transient_sqlstate("57P01"));
+        assert!(is_transient_sqlstate("55P03"));
+        assert!(!is_transient_sqlstate("22P02"));
+        assert!(!is_transient_sqlstate("42501"));
+        assert!(!is_transient_sqlstate("23505"));
+    }
+}
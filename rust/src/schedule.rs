@@ -0,0 +1,279 @@
+//! In-memory cache of scheduled job due times.
+//!
+//! The `dbms_job_scheduled_notify` trigger sends one
+//! `{"op":"submit|change|remove","job":<id>}` payload per changed row (and
+//! `{"op":"truncate"}` on TRUNCATE). The main loop applies these events to the
+//! cache one job at a time and only runs the scheduled-jobs claim query when
+//! the cache says a job is due, instead of re-querying the whole table on every
+//! change and every `job_queue_interval`.
+
+use crate::db::in_schema;
+use crate::dlog;
+use crate::model::Config;
+use postgres::Client;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// A change to `dbms_job.all_scheduled_jobs` decoded from a notification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScheduleEvent {
+    /// A job was submitted.
+    Submit(i64),
+    /// A job definition was changed by a user (not by the scheduler itself).
+    Change(i64),
+    /// A job was removed.
+    Remove(i64),
+    /// The table was truncated.
+    Truncate,
+}
+
+impl ScheduleEvent {
+    /// Decode a `dbms_job_scheduled_notify` payload.
+    ///
+    /// Returns `None` for anything that is not a well-formed structured
+    /// payload (e.g. the opaque payloads of older extension versions); the
+    /// caller then falls back to a full reload.
+    pub fn parse(payload: &str) -> Option<Self> {
+        let body = payload.trim().strip_prefix('{')?.strip_suffix('}')?;
+        let mut op = None;
+        let mut job = None;
+        for field in body.split(',') {
+            let (key, value) = field.split_once(':')?;
+            let value = value.trim();
+            match key.trim().trim_matches('"') {
+                "op" => op = Some(value.trim_matches('"')),
+                "job" if value != "null" => job = Some(value.parse::<i64>().ok()?),
+                _ => {}
+            }
+        }
+        match (op?, job) {
+            ("submit", Some(job)) => Some(ScheduleEvent::Submit(job)),
+            ("change", Some(job)) => Some(ScheduleEvent::Change(job)),
+            ("remove", Some(job)) => Some(ScheduleEvent::Remove(job)),
+            ("truncate", _) => Some(ScheduleEvent::Truncate),
+            _ => None,
+        }
+    }
+}
+
+/// Due time of every recurring, non-broken scheduled job.
+///
+/// Times are stored as local `Instant`s computed from the database's own
+/// `next_date - current_timestamp`, so clock skew between the scheduler host
+/// and the database server does not matter.
+#[derive(Default)]
+pub struct ScheduleCache {
+    due_at: HashMap<i64, Instant>,
+    loaded_at: Option<Instant>,
+}
+
+impl ScheduleCache {
+    /// Forget everything; the next [`is_due`](Self::is_due) check returns true
+    /// so the caller claims and reloads.
+    pub fn invalidate(&mut self) {
+        self.due_at.clear();
+        self.loaded_at = None;
+    }
+
+    /// Whether the cache has to be reloaded from the table: never loaded, or
+    /// loaded more than `refresh` ago. A periodic reload heals any change the
+    /// scheduler missed, e.g. while it was disconnected.
+    pub fn needs_reload(&self, now: Instant, refresh: Duration) -> bool {
+        self.loaded_at
+            .is_none_or(|t| now.saturating_duration_since(t) >= refresh)
+    }
+
+    /// Whether the scheduled-jobs claim query should run now.
+    pub fn is_due(&self, now: Instant, refresh: Duration) -> bool {
+        self.needs_reload(now, refresh) || self.due_at.values().any(|due| *due <= now)
+    }
+
+    /// Time left before [`is_due`](Self::is_due) turns true by itself: the
+    /// earliest cached due time or the next periodic reload.
+    pub fn time_until_due(&self, now: Instant, refresh: Duration) -> Duration {
+        let Some(loaded_at) = self.loaded_at else {
+            return Duration::ZERO;
+        };
+        self.due_at
+            .values()
+            .copied()
+            .chain([loaded_at + refresh])
+            .map(|due| due.saturating_duration_since(now))
+            .min()
+            .unwrap_or(Duration::ZERO)
+    }
+
+    /// Record the due time of `job`, `due_in` seconds from now (negative when
+    /// already overdue).
+    fn set(&mut self, job: i64, due_in: f64, now: Instant) {
+        let offset = Duration::from_secs_f64(due_in.abs().min(u32::MAX as f64));
+        let due = if due_in >= 0.0 {
+            now + offset
+        } else {
+            now.checked_sub(offset).unwrap_or(now)
+        };
+        self.due_at.insert(job, due);
+    }
+
+    /// Reload the due time of every recurring, non-broken job.
+    pub fn reload(&mut self, client: &mut Client, config: &Config) {
+        match client.query(
+            &*in_schema(
+                &config.schema,
+                "SELECT job, extract(epoch FROM next_date - current_timestamp)::float8 \
+                 FROM dbms_job.all_scheduled_jobs WHERE interval IS NOT NULL AND NOT broken",
+            ),
+            &[],
+        ) {
+            Ok(rows) => {
+                let now = Instant::now();
+                self.due_at.clear();
+                for row in rows {
+                    self.set(row.get(0), row.get(1), now);
+                }
+                self.loaded_at = Some(now);
+                dlog!(
+                    config,
+                    "DEBUG",
+                    "schedule cache reloaded with {} jobs",
+                    self.due_at.len()
+                );
+            }
+            Err(err) => {
+                dlog!(config, "ERROR", "can not reload the schedule cache, {err}");
+                self.invalidate();
+            }
+        }
+    }
+
+    /// Apply a single change notification.
+    pub fn apply(&mut self, client: &mut Client, config: &Config, event: ScheduleEvent) {
+        dlog!(config, "DEBUG", "schedule cache event {:?}", event);
+        match event {
+            ScheduleEvent::Submit(job) | ScheduleEvent::Change(job) => {
+                self.refresh(client, config, job)
+            }
+            ScheduleEvent::Remove(job) => {
+                self.due_at.remove(&job);
+            }
+            ScheduleEvent::Truncate => self.invalidate(),
+        }
+    }
+
+    /// Re-read the due time of a single job, dropping it from the cache when
+    /// it no longer exists, is broken or is a one-shot job.
+    fn refresh(&mut self, client: &mut Client, config: &Config, job: i64) {
+        match client.query_opt(
+            &*in_schema(
+                &config.schema,
+                "SELECT extract(epoch FROM next_date - current_timestamp)::float8 \
+                 FROM dbms_job.all_scheduled_jobs \
+                 WHERE job = $1 AND interval IS NOT NULL AND NOT broken",
+            ),
+            &[&job],
+        ) {
+            Ok(Some(row)) => self.set(job, row.get(0), Instant::now()),
+            Ok(None) => {
+                self.due_at.remove(&job);
+            }
+            Err(err) => {
+                dlog!(
+                    config,
+                    "ERROR",
+                    "can not refresh job {} in the schedule cache, {}",
+                    job,
+                    err
+                );
+                self.invalidate();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ScheduleCache, ScheduleEvent};
+    use std::time::{Duration, Instant};
+
+    const REFRESH: Duration = Duration::from_secs(60);
+
+    #[test]
+    fn parse_json_build_object_payloads() {
+        // json_build_object() puts spaces around the separators.
+        assert_eq!(
+            ScheduleEvent::parse(r#"{"op" : "submit", "job" : 12}"#),
+            Some(ScheduleEvent::Submit(12))
+        );
+        assert_eq!(
+            ScheduleEvent::parse(r#"{"op":"change","job":7}"#),
+            Some(ScheduleEvent::Change(7))
+        );
+        assert_eq!(
+            ScheduleEvent::parse(r#"{"job" : 3, "op" : "remove"}"#),
+            Some(ScheduleEvent::Remove(3))
+        );
+        assert_eq!(
+            ScheduleEvent::parse(r#"{"op" : "truncate"}"#),
+            Some(ScheduleEvent::Truncate)
+        );
+    }
+
+    #[test]
+    fn parse_rejects_legacy_and_malformed_payloads() {
+        assert_eq!(ScheduleEvent::parse("INSERT:12"), None);
+        assert_eq!(ScheduleEvent::parse(""), None);
+        assert_eq!(ScheduleEvent::parse(r#"{"op" : "submit"}"#), None);
+        assert_eq!(
+            ScheduleEvent::parse(r#"{"op" : "submit", "job" : "x"}"#),
+            None
+        );
+        assert_eq!(ScheduleEvent::parse(r#"{"op" : "run", "job" : 1}"#), None);
+    }
+
+    #[test]
+    fn empty_cache_is_due_until_loaded() {
+        let mut cache = ScheduleCache::default();
+        let now = Instant::now();
+        assert!(cache.is_due(now, REFRESH));
+        cache.loaded_at = Some(now);
+        assert!(!cache.is_due(now, REFRESH));
+    }
+
+    #[test]
+    fn cache_is_due_when_a_job_is_overdue() {
+        let mut cache = ScheduleCache::default();
+        let now = Instant::now();
+        cache.loaded_at = Some(now);
+        cache.set(1, 3600.0, now);
+        assert!(!cache.is_due(now, REFRESH));
+        cache.set(2, -5.0, now);
+        assert!(cache.is_due(now, REFRESH));
+        cache.due_at.remove(&2);
+        assert!(!cache.is_due(now, REFRESH));
+        assert!(cache.is_due(now + Duration::from_secs(3600), REFRESH));
+    }
+
+    #[test]
+    fn time_until_due_is_bounded_by_jobs_and_reload() {
+        let mut cache = ScheduleCache::default();
+        let now = Instant::now();
+        assert_eq!(cache.time_until_due(now, REFRESH), Duration::ZERO);
+        cache.loaded_at = Some(now);
+        assert_eq!(cache.time_until_due(now, REFRESH), REFRESH);
+        cache.set(1, 10.0, now);
+        assert_eq!(cache.time_until_due(now, REFRESH), Duration::from_secs(10));
+        cache.set(2, -5.0, now);
+        assert_eq!(cache.time_until_due(now, REFRESH), Duration::ZERO);
+    }
+
+    #[test]
+    fn cache_needs_reload_after_refresh_interval() {
+        let mut cache = ScheduleCache::default();
+        let now = Instant::now();
+        cache.loaded_at = Some(now);
+        assert!(!cache.needs_reload(now + Duration::from_secs(59), REFRESH));
+        assert!(cache.needs_reload(now + REFRESH, REFRESH));
+        cache.invalidate();
+        assert!(cache.needs_reload(now, REFRESH));
+    }
+}
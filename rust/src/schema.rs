@@ -0,0 +1,355 @@
+//! Compatibility check of the `dbms_job` schema against this scheduler.
+//!
+//! The scheduler's queries name columns added by successive extension
+//! versions. Against an outdated schema every one of them fails with a bare
+//! "can't execute statement" error, forever; checking the layout once at
+//! connect time turns that into a single clear message.
+//!
+//! The version of the schema, returned by `dbms_job.schema_version()` or
+//! else recorded for the extension, is checked first: a schema older than
+//! [`MIN_SCHEMA_VERSION`] or of a later major version is refused outright.
+//! The layout check remains for schemas of unknown version, imported by
+//! hand from an older script.
+
+use crate::constants::{DEFAULT_SCHEMA, VERSION};
+use crate::db::in_schema;
+use postgres::Client;
+use std::collections::HashSet;
+
+/// Columns of the `dbms_job` tables used by this scheduler version.
+pub const REQUIRED_COLUMNS: &[(&str, &[&str])] = &[
+    (
+        "all_scheduled_jobs",
+        &[
+            "job",
+            "log_user",
+            "schema_user",
+            "last_date",
+            "this_date",
+            "next_date",
+            "total_time",
+            "broken",
+            "interval",
+            "failures",
+            "what",
+            "instance",
+            "on_success",
+            "on_failure",
+            "last_error",
+            "last_sqlstate",
+            "last_failure_date",
+            "consecutive_overruns",
+            "continue_on_error",
+            "retry_delay",
+            "resource_key",
+            "max_run_duration",
+            "max_failures",
+            "priority",
+            "job_class",
+            "job_type",
+            "os_user",
+            "run_count",
+            "failure_count",
+            "last_success_date",
+            "last_status",
+        ],
+    ),
+    (
+        "all_async_jobs",
+        &[
+            "job",
+            "log_user",
+            "schema_user",
+            "this_date",
+            "what",
+            "on_success",
+            "on_failure",
+            "continue_on_error",
+            "resource_key",
+            "max_run_duration",
+            "priority",
+            "job_class",
+            "source_job",
+            "job_type",
+            "os_user",
+        ],
+    ),
+    (
+        "all_job_dependencies",
+        &["job", "prerequisite", "condition", "satisfied_date"],
+    ),
+    (
+        "all_scheduler_job_run_details",
+        &[
+            "owner",
+            "job_name",
+            "status",
+            "error",
+            "req_start_date",
+            "actual_start_date",
+            "run_duration",
+            "slave_pid",
+            "additional_info",
+            "result",
+            "error_message",
+            "error_detail",
+            "error_hint",
+            "error_context",
+            "run_id",
+            "scheduler_host",
+            "job_schema",
+        ],
+    ),
+    (
+        "scheduler_registry",
+        &[
+            "host",
+            "pid",
+            "version",
+            "features",
+            "start_time",
+            "heartbeat",
+            "pending_scheduled",
+            "pending_async",
+        ],
+    ),
+    ("maintenance_mode", &["enabled", "reason"]),
+    (
+        "config_audit",
+        &["host", "pid", "config_file", "config_hash", "changes"],
+    ),
+    (
+        "scheduler_event_log",
+        &["event_date", "host", "pid", "event", "detail"],
+    ),
+];
+
+/// Oldest version of the `dbms_job` schema this scheduler works with.
+pub const MIN_SCHEMA_VERSION: &str = "3.1.0";
+
+/// Functions of the `dbms_job` schema called by this scheduler.
+pub const REQUIRED_FUNCTIONS: &[&str] = &["get_next_date", "cron_next_date", "calendar_next_date"];
+
+/// Verify that the job queue schema `schema` is of a version this scheduler
+/// works with and has every table, column and function it uses. On mismatch
+/// the error names the installed version or what is missing. Returns the
+/// version of the schema, when known.
+pub fn check_schema(client: &mut Client, schema: &str) -> Result<Option<String>, String> {
+    let version = schema_version(client, schema)?;
+    if let Some(version) = &version {
+        check_version(version)?;
+    }
+
+    let found: HashSet<(String, String)> = client
+        .query(
+            "SELECT c.relname::text, a.attname::text \
+             FROM pg_catalog.pg_attribute a \
+             JOIN pg_catalog.pg_class c ON c.oid = a.attrelid \
+             JOIN pg_catalog.pg_namespace n ON n.oid = c.relnamespace \
+             WHERE n.nspname = $1 AND a.attnum > 0 AND NOT a.attisdropped",
+            &[&schema],
+        )
+        .map_err(|e| e.to_string())?
+        .iter()
+        .map(|row| (row.get(0), row.get(1)))
+        .collect();
+    let functions: HashSet<String> = client
+        .query(
+            "SELECT p.proname::text FROM pg_catalog.pg_proc p \
+             JOIN pg_catalog.pg_namespace n ON n.oid = p.pronamespace \
+             WHERE n.nspname = $1",
+            &[&schema],
+        )
+        .map_err(|e| e.to_string())?
+        .iter()
+        .map(|row| row.get(0))
+        .collect();
+
+    let missing = missing_objects(schema, &found, &functions);
+    if missing.is_empty() {
+        return Ok(version);
+    }
+    Err(incompatibility_message(&missing, version.as_deref()))
+}
+
+/// Version of the job queue schema: the one returned by its
+/// `schema_version()`, else, for `dbms_job`, the one of the installed
+/// extension, `None` for a schema older than both.
+fn schema_version(client: &mut Client, schema: &str) -> Result<Option<String>, String> {
+    let row = client
+        .query_one(
+            &*in_schema(
+                schema,
+                "SELECT to_regprocedure('dbms_job.schema_version()') IS NOT NULL, \
+                 (SELECT extversion FROM pg_catalog.pg_extension WHERE extname = 'pg_dbms_job')",
+            ),
+            &[],
+        )
+        .map_err(|e| e.to_string())?;
+    if !row.get::<_, bool>(0) {
+        return Ok(row
+            .get::<_, Option<String>>(1)
+            .filter(|_| schema == DEFAULT_SCHEMA));
+    }
+    client
+        .query_one(&*in_schema(schema, "SELECT dbms_job.schema_version()"), &[])
+        .map(|row| Some(row.get(0)))
+        .map_err(|e| e.to_string())
+}
+
+/// Refuse a schema older than [`MIN_SCHEMA_VERSION`] or of a major version
+/// later than this scheduler's, whose objects may have changed in ways the
+/// layout check can not see. A version that does not parse is left to the
+/// layout check.
+fn check_version(version: &str) -> Result<(), String> {
+    let (Some(installed), Some(minimum), Some(daemon)) = (
+        parse_version(version),
+        parse_version(MIN_SCHEMA_VERSION),
+        parse_version(VERSION),
+    ) else {
+        return Ok(());
+    };
+    if installed < minimum {
+        return Err(format!(
+            "schema version {version} is older than {MIN_SCHEMA_VERSION}, the oldest this scheduler {VERSION} works with; run pg_dbms_job --upgrade-schema or ALTER EXTENSION pg_dbms_job UPDATE to upgrade it"
+        ));
+    }
+    if installed.0 > daemon.0 {
+        return Err(format!(
+            "schema version {version} is newer than this scheduler {VERSION}, upgrade the scheduler"
+        ));
+    }
+    Ok(())
+}
+
+/// `MAJOR.MINOR.PATCH` as numbers, a missing part being 0.
+fn parse_version(version: &str) -> Option<(u32, u32, u32)> {
+    let mut parts = version.trim().split('.').map(|part| part.parse().ok());
+    let major = parts.next()??;
+    let minor = parts.next().unwrap_or(Some(0))?;
+    let patch = parts.next().unwrap_or(Some(0))?;
+    if parts.next().is_some() {
+        return None;
+    }
+    Some((major, minor, patch))
+}
+
+/// List the required objects absent from the introspected schema.
+fn missing_objects(
+    schema: &str,
+    columns: &HashSet<(String, String)>,
+    functions: &HashSet<String>,
+) -> Vec<String> {
+    let mut missing = Vec::new();
+    for (table, cols) in REQUIRED_COLUMNS {
+        if !columns.iter().any(|(t, _)| t == table) {
+            missing.push(format!("table {schema}.{table}"));
+            continue;
+        }
+        for col in *cols {
+            if !columns.contains(&(table.to_string(), col.to_string())) {
+                missing.push(format!("column {schema}.{table}.{col}"));
+            }
+        }
+    }
+    for func in REQUIRED_FUNCTIONS {
+        if !functions.contains(*func) {
+            missing.push(format!("function {schema}.{func}()"));
+        }
+    }
+    missing
+}
+
+/// Build the operator-facing error for an incompatible schema.
+fn incompatibility_message(missing: &[String], extversion: Option<&str>) -> String {
+    let installed = match extversion {
+        Some(v) => format!("installed extension version is {v}"),
+        None => "the pg_dbms_job extension is not installed".to_string(),
+    };
+    format!(
+        "missing {}; {}, run pg_dbms_job --upgrade-schema or ALTER EXTENSION pg_dbms_job UPDATE to upgrade it",
+        missing.join(", "),
+        installed
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        MIN_SCHEMA_VERSION, REQUIRED_COLUMNS, REQUIRED_FUNCTIONS, check_version,
+        incompatibility_message, missing_objects, parse_version,
+    };
+    use crate::constants::VERSION;
+    use std::collections::HashSet;
+
+    fn full_schema() -> (HashSet<(String, String)>, HashSet<String>) {
+        let columns = REQUIRED_COLUMNS
+            .iter()
+            .flat_map(|(t, cols)| cols.iter().map(|c| (t.to_string(), c.to_string())))
+            .collect();
+        let functions = REQUIRED_FUNCTIONS.iter().map(|f| f.to_string()).collect();
+        (columns, functions)
+    }
+
+    #[test]
+    fn complete_schema_has_nothing_missing() {
+        let (columns, functions) = full_schema();
+        assert!(missing_objects("dbms_job", &columns, &functions).is_empty());
+    }
+
+    #[test]
+    fn reports_missing_column_and_function() {
+        let (mut columns, mut functions) = full_schema();
+        columns.remove(&("all_async_jobs".to_string(), "on_success".to_string()));
+        functions.remove("get_next_date");
+        assert_eq!(
+            missing_objects("dbms_job", &columns, &functions),
+            vec![
+                "column dbms_job.all_async_jobs.on_success".to_string(),
+                "function dbms_job.get_next_date()".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_table_is_reported_once() {
+        let (mut columns, functions) = full_schema();
+        columns.retain(|(t, _)| t != "all_scheduler_job_run_details");
+        assert_eq!(
+            missing_objects("dbms_job", &columns, &functions),
+            vec!["table dbms_job.all_scheduler_job_run_details".to_string()]
+        );
+    }
+
+    #[test]
+    fn message_names_installed_version() {
+        let missing = vec!["column dbms_job.all_async_jobs.on_success".to_string()];
+        let msg = incompatibility_message(&missing, Some("3.0.2"));
+        assert!(msg.contains("all_async_jobs.on_success"));
+        assert!(msg.contains("3.0.2"));
+        let msg = incompatibility_message(&missing, None);
+        assert!(msg.contains("not installed"));
+    }
+
+    #[test]
+    fn versions_are_compared_as_numbers() {
+        assert_eq!(parse_version("3.1.0"), Some((3, 1, 0)));
+        assert_eq!(parse_version("3.10"), Some((3, 10, 0)));
+        assert_eq!(parse_version("3.x"), None);
+        assert_eq!(parse_version("3.1.0.1"), None);
+        assert!(parse_version("3.10.0") > parse_version("3.9.2"));
+    }
+
+    #[test]
+    fn outdated_or_later_major_schema_is_refused() {
+        assert!(check_version(MIN_SCHEMA_VERSION).is_ok());
+        assert!(check_version(VERSION).is_ok());
+        let err = check_version("3.0.2").unwrap_err();
+        assert!(err.contains("3.0.2") && err.contains("--upgrade-schema"));
+        let (major, ..) = parse_version(VERSION).unwrap();
+        let err = check_version(&format!("{}.0.0", major + 1)).unwrap_err();
+        assert!(err.contains("upgrade the scheduler"));
+        // Left to the layout check.
+        assert!(check_version("devel").is_ok());
+    }
+}
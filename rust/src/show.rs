@@ -0,0 +1,86 @@
+//! `--show-config`: the settings the daemon would run with.
+//!
+//! The configuration file is read as the daemon reads it, includes and
+//! environment overrides included, with the `--debug` and `--no-debug`
+//! overrides of the command line applied. Every setting is printed in the
+//! syntax of the configuration file, followed by where its value comes
+//! from. Secret values are masked.
+
+use crate::config::{SECRET_SETTINGS, config_settings, read_config, setting_sources};
+use crate::logging::capture_log;
+use crate::model::{Config, DbInfo};
+use std::env;
+
+/// Lines of `--show-config` for `config_file`, and the ERROR and WARNING
+/// lines logged while reading it.
+pub fn show_config(
+    config_file: &str,
+    config: &mut Config,
+    dbinfo: &mut DbInfo,
+    debug_override: Option<bool>,
+) -> (Vec<String>, Vec<String>) {
+    let ((), lines) = capture_log(|| read_config(config_file, config, dbinfo, true));
+    let problems = lines
+        .into_iter()
+        .filter(|(level, _)| matches!(level.as_str(), "WARNING" | "ERROR" | "FATAL"))
+        .map(|(level, msg)| format!("{level}: {msg}"))
+        .collect();
+    if let Some(debug) = debug_override {
+        config.debug = debug;
+    }
+    let (sources, _) = capture_log(|| setting_sources(config_file, config, dbinfo, env::vars()));
+    let settings = config_settings(config, dbinfo)
+        .into_iter()
+        .zip(sources)
+        .map(|((name, value), (_, source))| {
+            let value = if SECRET_SETTINGS.contains(&name) && !value.is_empty() {
+                "********".to_string()
+            } else {
+                value
+            };
+            let source = match (name, debug_override) {
+                ("debug", Some(_)) => "command line".to_string(),
+                _ => source,
+            };
+            format!("{:<40} # {source}", format!("{name}={value}"))
+        })
+        .collect();
+    (settings, problems)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::show_config;
+    use crate::{default_config, default_dbinfo};
+    use std::env;
+    use std::fs;
+
+    #[test]
+    fn settings_show_their_source_and_mask_secrets() {
+        let path = env::temp_dir()
+            .join(format!("pg_dbms_job_show_{}.conf", std::process::id()))
+            .to_string_lossy()
+            .into_owned();
+        fs::write(&path, "nap_time=2\npasswd=s3cret\nnap_tme=2\n").unwrap();
+        let (mut config, mut dbinfo) = (default_config(), default_dbinfo());
+        let (settings, problems) = show_config(&path, &mut config, &mut dbinfo, Some(true));
+        fs::remove_file(&path).unwrap();
+        let line = |name: &str| {
+            settings
+                .iter()
+                .find(|line| line.starts_with(&format!("{name}=")))
+                .unwrap()
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        assert_eq!(line("nap_time"), "nap_time=2 # file");
+        assert_eq!(line("passwd"), "passwd=******** # file");
+        assert_eq!(line("debug"), "debug=1 # command line");
+        assert_eq!(line("passwd_file"), "passwd_file= # default");
+        assert_eq!(
+            problems,
+            ["WARNING: Unknown setting nap_tme in configuration file, ignored"]
+        );
+    }
+}
@@ -0,0 +1,444 @@
+//! Projection of a job's firing times on a virtual clock.
+//!
+//! The scheduler computes a job's next execution date by evaluating its
+//! `interval` expression (`dbms_job.get_next_date`) when the job is claimed,
+//! i.e. at the previous execution date. [`project`] replays that rule from a
+//! virtual start date without waiting for real time to pass, which lets a
+//! schedule change be checked before it is applied. The evaluation itself is
+//! behind the [`NextDate`] trait so the projection logic can be driven by any
+//! schedule source; [`SqlNextDate`] evaluates a `dbms_job` interval expression
+//! in the database with the clock functions pinned to the virtual time.
+
+use crate::db::{connect_tool, in_schema};
+use crate::dst::{crosses_offset_change, wall_clock_next_date};
+use crate::model::{DbInfo, DstPolicy};
+use chrono::{DateTime, Local, Utc};
+use postgres::Client;
+
+/// Number of firing times printed by `--simulate` when `--runs` is not given.
+pub const DEFAULT_RUNS: usize = 10;
+
+/// Something that knows when a job fires next.
+pub trait NextDate {
+    /// Next execution date of a job run at `now`, or `None` when the job does
+    /// not run again.
+    fn next_date(&mut self, now: DateTime<Utc>) -> Result<Option<DateTime<Utc>>, String>;
+}
+
+/// Firing times of a job whose first execution is at `first`, stopping after
+/// `max_runs` runs or past `until`.
+///
+/// A schedule whose next date does not move past the current one would make
+/// the scheduler run the job in a loop; it is reported as an error.
+pub fn project<N: NextDate>(
+    schedule: &mut N,
+    first: DateTime<Utc>,
+    until: Option<DateTime<Utc>>,
+    max_runs: usize,
+) -> Result<Vec<DateTime<Utc>>, String> {
+    let mut runs = Vec::new();
+    let mut at = first;
+    while runs.len() < max_runs && until.is_none_or(|u| at <= u) {
+        runs.push(at);
+        match schedule.next_date(at)? {
+            None => break,
+            Some(next) if next <= at => {
+                return Err(format!(
+                    "interval does not advance: run at {} is followed by {}",
+                    at.to_rfc3339(),
+                    next.to_rfc3339()
+                ));
+            }
+            Some(next) => at = next,
+        }
+    }
+    Ok(runs)
+}
+
+/// SQL clock functions replaced by the virtual time. Entries ending in `()`
+/// only match when called with an empty argument list.
+const CLOCK_FUNCTIONS: &[&str] = &[
+    "current_timestamp",
+    "localtimestamp",
+    "sysdate",
+    "now()",
+    "clock_timestamp()",
+    "statement_timestamp()",
+    "transaction_timestamp()",
+];
+
+/// Whether `interval` is a cron expression, five fields of digits, names
+/// and the `* , - /` signs or a `@` macro such as `@daily`, rather than a
+/// SQL expression. Mirrors `dbms_job.is_cron()`.
+pub fn is_cron(interval: &str) -> bool {
+    let interval = interval.trim();
+    if let Some(macro_name) = interval.strip_prefix('@') {
+        return !macro_name.is_empty() && macro_name.chars().all(|c| c.is_ascii_alphabetic());
+    }
+    let fields: Vec<&str> = interval.split_whitespace().collect();
+    fields.len() == 5
+        && fields
+            .iter()
+            .flat_map(|field| field.chars())
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '*' | ',' | '/' | '-'))
+}
+
+/// Whether `interval` is an Oracle calendaring expression, such as
+/// `FREQ=DAILY;BYHOUR=2`: `FREQ=` then letters, digits, white space and the
+/// `= ; , + -` signs. Mirrors `dbms_job.is_calendar()`.
+pub fn is_calendar(interval: &str) -> bool {
+    let interval = interval.trim_start();
+    let Some(freq) = interval.get(..4) else {
+        return false;
+    };
+    let rest = interval[4..].trim_start();
+    freq.eq_ignore_ascii_case("freq")
+        && rest.starts_with('=')
+        && rest.chars().all(|c| {
+            c.is_ascii_alphanumeric()
+                || c.is_whitespace()
+                || matches!(c, '=' | ';' | ',' | '+' | '-')
+        })
+}
+
+/// SQL expression of the next date of `interval` evaluated at `at`: a cron
+/// or calendaring expression is evaluated by `cron_next_date()` or
+/// `calendar_next_date()` of the job queue schema `schema` from `at`, a SQL
+/// expression with its clock functions returning `at`.
+pub fn next_date_expr(schema: &str, interval: &str, at: DateTime<Utc>) -> String {
+    let function = if is_cron(interval) {
+        "cron_next_date"
+    } else if is_calendar(interval) {
+        "calendar_next_date"
+    } else {
+        return with_virtual_clock(interval, at);
+    };
+    // No quote can be part of a cron or calendaring expression.
+    let expr = format!(
+        "dbms_job.{function}('{}', '{}'::timestamptz)",
+        interval.trim(),
+        at.to_rfc3339()
+    );
+    in_schema(schema, &expr).into_owned()
+}
+
+/// Rewrite `expr` so that every clock function returns `at`.
+///
+/// String literals and quoted identifiers are copied untouched.
+/// `current_date` becomes the date part of `at`.
+pub fn with_virtual_clock(expr: &str, at: DateTime<Utc>) -> String {
+    let literal = format!("'{}'::timestamptz", at.to_rfc3339());
+    let chars: Vec<char> = expr.chars().collect();
+    let mut out = String::with_capacity(expr.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '\'' || c == '"' {
+            let start = i;
+            i += 1;
+            while i < chars.len() && chars[i] != c {
+                i += 1;
+            }
+            i = (i + 1).min(chars.len());
+            out.extend(&chars[start..i]);
+            continue;
+        }
+        if c.is_ascii_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            let word: String = chars[start..i].iter().collect::<String>().to_lowercase();
+            if word == "current_date" {
+                out.push_str(&format!("({literal})::date"));
+                continue;
+            }
+            if CLOCK_FUNCTIONS.contains(&word.as_str()) {
+                out.push_str(&format!("({literal})"));
+                continue;
+            }
+            if CLOCK_FUNCTIONS.contains(&format!("{word}()").as_str()) {
+                let mut j = i;
+                while j < chars.len() && chars[j].is_whitespace() {
+                    j += 1;
+                }
+                if chars.get(j) == Some(&'(') {
+                    let mut k = j + 1;
+                    while k < chars.len() && chars[k].is_whitespace() {
+                        k += 1;
+                    }
+                    if chars.get(k) == Some(&')') {
+                        out.push_str(&format!("({literal})"));
+                        i = k + 1;
+                        continue;
+                    }
+                }
+            }
+            out.extend(&chars[start..i]);
+            continue;
+        }
+        out.push(c);
+        i += 1;
+    }
+    out
+}
+
+/// Evaluates a `dbms_job` interval expression in the database at a virtual
+/// time, the way `dbms_job.get_next_date()` does at claim time, then the
+/// scheduler's `dst_policy` when the date crosses a change of UTC offset.
+pub struct SqlNextDate<'a> {
+    client: &'a mut Client,
+    schema: String,
+    interval: String,
+    dst_policy: DstPolicy,
+}
+
+impl<'a> SqlNextDate<'a> {
+    pub fn new(
+        client: &'a mut Client,
+        schema: &str,
+        interval: &str,
+        dst_policy: DstPolicy,
+    ) -> Self {
+        SqlNextDate {
+            client,
+            schema: schema.to_string(),
+            interval: interval.to_string(),
+            dst_policy,
+        }
+    }
+}
+
+impl NextDate for SqlNextDate<'_> {
+    fn next_date(&mut self, now: DateTime<Utc>) -> Result<Option<DateTime<Utc>>, String> {
+        let expr = next_date_expr(&self.schema, &self.interval, now);
+        let row = self
+            .client
+            .query_one(
+                &format!("SELECT extract(epoch FROM ({expr})::timestamptz)::float8"),
+                &[],
+            )
+            .map_err(|e| format!("can not evaluate interval \"{}\": {e}", self.interval))?;
+        let next = row.get::<_, Option<f64>>(0).and_then(from_epoch);
+        match next {
+            Some(next)
+                if self.dst_policy != DstPolicy::Server
+                    && crosses_offset_change(self.client, now, next)? =>
+            {
+                wall_clock_next_date(
+                    self.client,
+                    &self.schema,
+                    &self.interval,
+                    now,
+                    self.dst_policy,
+                )
+            }
+            _ => Ok(next),
+        }
+    }
+}
+
+/// Convert epoch seconds returned by the database into a UTC date.
+pub fn from_epoch(secs: f64) -> Option<DateTime<Utc>> {
+    DateTime::from_timestamp_micros((secs * 1_000_000.0).round() as i64)
+}
+
+/// Parse a timestamp given on the command line with the database's own
+/// `timestamptz` input rules, so the same formats as in SQL are accepted.
+fn parse_timestamp(client: &mut Client, value: &str) -> Result<DateTime<Utc>, String> {
+    let row = client
+        .query_one(
+            "SELECT extract(epoch FROM $1::text::timestamptz)::float8",
+            &[&value],
+        )
+        .map_err(|e| format!("invalid timestamp \"{value}\": {e}"))?;
+    from_epoch(row.get(0)).ok_or_else(|| format!("timestamp \"{value}\" is out of range"))
+}
+
+/// Options of the `--simulate` command.
+pub struct SimulateOptions<'a> {
+    /// Job queue schema of the job.
+    pub schema: &'a str,
+    /// Job whose schedule is projected.
+    pub job: i64,
+    /// Interval expression replacing the job's own, to test a change.
+    pub interval: Option<&'a str>,
+    /// First execution date; defaults to the job's `next_date`.
+    pub from: Option<&'a str>,
+    /// Last date of the projection.
+    pub until: Option<&'a str>,
+    /// Maximum number of firing times.
+    pub runs: usize,
+    /// Placement of the dates crossing a daylight saving time change.
+    pub dst_policy: DstPolicy,
+}
+
+/// Print the projected firing times of a job on stdout.
+pub fn run_simulation(dbinfo: &DbInfo, opts: &SimulateOptions) -> Result<(), String> {
+    let mut client = connect_tool(dbinfo, "pg_dbms_job:simulate")?;
+    let row = client
+        .query_opt(
+            &*in_schema(
+                opts.schema,
+                "SELECT interval, extract(epoch FROM next_date)::float8 \
+                 FROM dbms_job.all_scheduled_jobs WHERE job = $1",
+            ),
+            &[&opts.job],
+        )
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("job {} is not a job in the job queue", opts.job))?;
+    let interval = opts
+        .interval
+        .map(str::to_string)
+        .or_else(|| row.get::<_, Option<String>>(0));
+    let first = match opts.from {
+        Some(from) => parse_timestamp(&mut client, from)?,
+        None => from_epoch(row.get(1)).ok_or("next_date is out of range")?,
+    };
+    let until = opts
+        .until
+        .map(|u| parse_timestamp(&mut client, u))
+        .transpose()?;
+
+    let runs = match &interval {
+        Some(interval) => {
+            println!("job {}: interval {}", opts.job, interval);
+            let mut schedule =
+                SqlNextDate::new(&mut client, opts.schema, interval, opts.dst_policy);
+            project(&mut schedule, first, until, opts.runs)?
+        }
+        None => {
+            println!("job {}: no interval, runs once", opts.job);
+            project(&mut OneShot, first, until, opts.runs)?
+        }
+    };
+    for run in runs {
+        println!(
+            "{}",
+            run.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S%:z")
+        );
+    }
+    Ok(())
+}
+
+/// Schedule of a job without interval: it runs once.
+struct OneShot;
+
+impl NextDate for OneShot {
+    fn next_date(&mut self, _now: DateTime<Utc>) -> Result<Option<DateTime<Utc>>, String> {
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        NextDate, OneShot, is_calendar, is_cron, next_date_expr, project, with_virtual_clock,
+    };
+    use chrono::{DateTime, Duration, TimeZone, Utc};
+
+    /// A fixed-step schedule standing in for an interval expression.
+    struct Every(Duration);
+
+    impl NextDate for Every {
+        fn next_date(&mut self, now: DateTime<Utc>) -> Result<Option<DateTime<Utc>>, String> {
+            Ok(Some(now + self.0))
+        }
+    }
+
+    fn t0() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 3, 1, 2, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn project_stops_at_max_runs() {
+        let runs = project(&mut Every(Duration::hours(1)), t0(), None, 3).unwrap();
+        assert_eq!(
+            runs,
+            vec![t0(), t0() + Duration::hours(1), t0() + Duration::hours(2)]
+        );
+    }
+
+    #[test]
+    fn project_stops_past_until() {
+        let until = t0() + Duration::minutes(150);
+        let runs = project(&mut Every(Duration::hours(1)), t0(), Some(until), 100).unwrap();
+        assert_eq!(runs.len(), 3);
+        assert!(runs.iter().all(|r| *r <= until));
+    }
+
+    #[test]
+    fn project_one_shot_runs_once() {
+        assert_eq!(project(&mut OneShot, t0(), None, 10).unwrap(), vec![t0()]);
+    }
+
+    #[test]
+    fn project_rejects_non_advancing_interval() {
+        let err = project(&mut Every(Duration::zero()), t0(), None, 10).unwrap_err();
+        assert!(err.contains("does not advance"));
+    }
+
+    #[test]
+    fn virtual_clock_replaces_clock_functions() {
+        let lit = "('2026-03-01T02:00:00+00:00'::timestamptz)";
+        assert_eq!(
+            with_virtual_clock("current_timestamp + '1 day'::interval", t0()),
+            format!("{lit} + '1 day'::interval")
+        );
+        assert_eq!(
+            with_virtual_clock("date_trunc('hour', NOW ( )) + interval '1 hour'", t0()),
+            format!("date_trunc('hour', {lit}) + interval '1 hour'")
+        );
+        assert_eq!(
+            with_virtual_clock("current_date + 1", t0()),
+            format!("{lit}::date + 1")
+        );
+    }
+
+    #[test]
+    fn cron_expressions_are_told_from_sql_ones() {
+        assert!(is_cron("0 3 * * 1-5"));
+        assert!(is_cron(" */15 8-18 * jan,jul MON-fri "));
+        assert!(is_cron("@daily"));
+        assert!(!is_cron("current_timestamp + '1 day'::interval"));
+        assert!(!is_cron("sysdate + 1 / 24"));
+        assert!(!is_cron("0 3 * *"));
+        assert!(!is_cron("@"));
+        assert_eq!(
+            next_date_expr("dbms_job", " 0 3 * * 1-5", t0()),
+            "dbms_job.cron_next_date('0 3 * * 1-5', '2026-03-01T02:00:00+00:00'::timestamptz)"
+        );
+        assert_eq!(
+            next_date_expr("dbms_job", "now() + interval '1 hour'", t0()),
+            "('2026-03-01T02:00:00+00:00'::timestamptz) + interval '1 hour'"
+        );
+    }
+
+    #[test]
+    fn calendaring_expressions_are_told_from_sql_ones() {
+        assert!(is_calendar("FREQ=DAILY;BYHOUR=2;BYMINUTE=30"));
+        assert!(is_calendar(" freq = monthly; byday = -1FRI "));
+        assert!(!is_calendar("FREQ=DAILY;BYHOUR=2'"));
+        assert!(!is_calendar("frequency + interval '1 day'"));
+        assert!(!is_calendar("0 3 * * 1-5"));
+        assert!(!is_cron("FREQ=DAILY;BYHOUR=2"));
+        assert_eq!(
+            next_date_expr("dbms_job", "FREQ=WEEKLY;BYDAY=MON ", t0()),
+            "dbms_job.calendar_next_date('FREQ=WEEKLY;BYDAY=MON', '2026-03-01T02:00:00+00:00'::timestamptz)"
+        );
+    }
+
+    #[test]
+    fn virtual_clock_leaves_literals_and_other_names_alone() {
+        assert_eq!(
+            with_virtual_clock("'now'::timestamptz + \"now\".x", t0()),
+            "'now'::timestamptz + \"now\".x"
+        );
+        // A user function merely named like a clock function with arguments.
+        assert_eq!(with_virtual_clock("now(1)", t0()), "now(1)");
+        assert_eq!(
+            with_virtual_clock("my_current_timestamp()", t0()),
+            "my_current_timestamp()"
+        );
+    }
+}
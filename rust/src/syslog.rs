@@ -0,0 +1,129 @@
+//! Syslog output of the log writer.
+//!
+//! With `log_destination = syslog` each log line is sent as one datagram to
+//! the local syslog socket, in the BSD format (RFC 3164) every local daemon
+//! accepts: `<PRI>Mmm dd hh:mm:ss ident[pid]: LEVEL: message`. The socket is
+//! connected lazily and again after a send error, so a restart of the
+//! syslog daemon only loses the lines sent while it was down.
+
+use chrono::{DateTime, Local};
+use std::os::unix::net::UnixDatagram;
+
+/// Facility of the lines when `syslog_facility` is unknown, `local0`.
+pub const DEFAULT_FACILITY: u8 = 16;
+
+/// Facility code of a `syslog_facility` value, `None` when unknown.
+pub fn facility_code(name: &str) -> Option<u8> {
+    let code = match name.trim().to_ascii_lowercase().as_str() {
+        "user" => 1,
+        "daemon" => 3,
+        "local0" => 16,
+        "local1" => 17,
+        "local2" => 18,
+        "local3" => 19,
+        "local4" => 20,
+        "local5" => 21,
+        "local6" => 22,
+        "local7" => 23,
+        _ => return None,
+    };
+    Some(code)
+}
+
+/// Syslog severity of a log level, as PostgreSQL maps its own.
+pub fn severity(level: &str) -> u8 {
+    match level.to_ascii_uppercase().as_str() {
+        "PANIC" => 2,
+        "FATAL" | "ERROR" => 3,
+        "WARNING" => 4,
+        "NOTICE" => 5,
+        "DEBUG" => 7,
+        _ => 6,
+    }
+}
+
+/// Datagram of one log line.
+pub fn datagram(
+    facility: u8,
+    severity: u8,
+    ident: &str,
+    pid: u32,
+    msg: &str,
+    at: DateTime<Local>,
+) -> String {
+    let pri = u16::from(facility) * 8 + u16::from(severity);
+    format!(
+        "<{pri}>{} {ident}[{pid}]: {msg}",
+        at.format("%b %e %H:%M:%S")
+    )
+}
+
+/// Connection of the log writer to a local datagram socket, the syslog
+/// one or the journald one.
+pub struct DatagramSocket {
+    /// Paths tried in turn.
+    paths: &'static [&'static str],
+    socket: Option<UnixDatagram>,
+}
+
+impl DatagramSocket {
+    pub fn new(paths: &'static [&'static str]) -> Self {
+        DatagramSocket {
+            paths,
+            socket: None,
+        }
+    }
+
+    /// Send a datagram, connecting first if needed. Returns false when it
+    /// could not be delivered.
+    pub fn send(&mut self, datagram: &[u8]) -> bool {
+        for _ in 0..2 {
+            if self.socket.is_none() {
+                self.socket = self.connect();
+            }
+            let Some(socket) = &self.socket else {
+                return false;
+            };
+            if socket.send(datagram).is_ok() {
+                return true;
+            }
+            // The daemon may have been restarted: reconnect once.
+            self.socket = None;
+        }
+        false
+    }
+
+    fn connect(&self) -> Option<UnixDatagram> {
+        let socket = UnixDatagram::unbound().ok()?;
+        self.paths
+            .iter()
+            .any(|path| socket.connect(path).is_ok())
+            .then_some(socket)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{datagram, facility_code, severity};
+    use chrono::{Local, TimeZone};
+
+    #[test]
+    fn facility_names() {
+        assert_eq!(facility_code("LOCAL0"), Some(16));
+        assert_eq!(facility_code(" local7"), Some(23));
+        assert_eq!(facility_code("daemon"), Some(3));
+        assert_eq!(facility_code("kern"), None);
+    }
+
+    #[test]
+    fn datagram_format() {
+        let at = Local.with_ymd_and_hms(2026, 3, 5, 9, 7, 2).unwrap();
+        assert_eq!(
+            datagram(16, severity("ERROR"), "pg_dbms_job", 42, "ERROR: boom", at),
+            "<131>Mar  5 09:07:02 pg_dbms_job[42]: ERROR: boom"
+        );
+        assert_eq!(severity("LOG"), 6);
+        assert_eq!(severity("WARNING"), 4);
+        assert_eq!(severity("DEBUG"), 7);
+    }
+}
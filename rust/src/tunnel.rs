@@ -0,0 +1,307 @@
+//! SSH tunnel to a database only reachable through a bastion host.
+//!
+//! With `ssh_host` set, the scheduler runs `ssh -N -L` itself, forwarding a
+//! free port of the loopback interface to `host:port` of the database as
+//! seen from the bastion, and every connection, the main one and those of
+//! the job pool, goes through it. The `ssh` process is supervised from the
+//! main loop: when it exits, the connections are dropped and the tunnel is
+//! started again, with `startup_delay` between failed attempts. A reload
+//! changing the tunnel settings restarts it the same way.
+
+use crate::constants::SSH_TUNNEL_TIMEOUT_SECS;
+use crate::dlog;
+use crate::logging::dprint;
+use crate::model::{Config, DbInfo};
+use std::io::{BufRead, BufReader};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Where a tunnel goes, from the configuration.
+#[derive(Clone, Debug, PartialEq)]
+struct TunnelTarget {
+    ssh_host: String,
+    ssh_port: u16,
+    ssh_user: String,
+    ssh_key: String,
+    /// Database host as seen from the bastion, or its socket directory.
+    db_host: String,
+    db_port: u16,
+}
+
+impl TunnelTarget {
+    /// Target of the configuration, `None` when `ssh_host` is not set.
+    fn of(config: &Config, dbinfo: &DbInfo) -> Option<Self> {
+        (!config.ssh_host.is_empty()).then(|| TunnelTarget {
+            ssh_host: config.ssh_host.clone(),
+            ssh_port: config.ssh_port,
+            ssh_user: config.ssh_user.clone(),
+            ssh_key: config.ssh_key.clone(),
+            db_host: dbinfo.host.clone(),
+            db_port: dbinfo.port,
+        })
+    }
+
+    /// Arguments of the `ssh` command forwarding `local_port`.
+    fn ssh_args(&self, local_port: u16) -> Vec<String> {
+        // A database reached through a Unix socket on the bastion is
+        // forwarded to the socket itself.
+        let remote = if self.db_host.starts_with('/') {
+            format!("{}/.s.PGSQL.{}", self.db_host, self.db_port)
+        } else if self.db_host.is_empty() {
+            format!("localhost:{}", self.db_port)
+        } else {
+            format!("{}:{}", self.db_host, self.db_port)
+        };
+        let mut args: Vec<String> = [
+            "-N",
+            "-o",
+            "BatchMode=yes",
+            "-o",
+            "ExitOnForwardFailure=yes",
+            "-o",
+            "ServerAliveInterval=15",
+            "-o",
+            "ServerAliveCountMax=3",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+        args.extend(["-p".to_string(), self.ssh_port.to_string()]);
+        if !self.ssh_user.is_empty() {
+            args.extend(["-l".to_string(), self.ssh_user.clone()]);
+        }
+        if !self.ssh_key.is_empty() {
+            args.extend([
+                "-i".to_string(),
+                self.ssh_key.clone(),
+                "-o".to_string(),
+                "IdentitiesOnly=yes".to_string(),
+            ]);
+        }
+        args.extend([
+            "-L".to_string(),
+            format!("{}:{local_port}:{remote}", Ipv4Addr::LOCALHOST),
+            "--".to_string(),
+            self.ssh_host.clone(),
+        ]);
+        args
+    }
+}
+
+/// A running `ssh` process forwarding `local_port`.
+struct SshProcess {
+    child: Child,
+    target: TunnelTarget,
+    local_port: u16,
+    /// Last line written by `ssh` on its standard error.
+    last_error: Arc<Mutex<String>>,
+}
+
+impl SshProcess {
+    /// Start `ssh` and wait until the forwarded port accepts connections.
+    fn start(target: TunnelTarget) -> Result<Self, String> {
+        let local_port = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+            .and_then(|listener| listener.local_addr())
+            .map_err(|e| format!("can not find a free local port, {e}"))?
+            .port();
+        let mut command = Command::new("ssh");
+        command
+            .args(target.ssh_args(local_port))
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped());
+        #[cfg(target_os = "linux")]
+        {
+            use std::os::unix::process::CommandExt;
+            // Never leave the tunnel behind, whatever way the daemon exits.
+            // SAFETY: prctl() is async-signal-safe.
+            unsafe {
+                command.pre_exec(|| {
+                    libc::prctl(libc::PR_SET_PDEATHSIG, libc::SIGTERM);
+                    Ok(())
+                });
+            }
+        }
+        let mut child = command
+            .spawn()
+            .map_err(|e| format!("can not run ssh, {e}"))?;
+
+        let last_error = Arc::new(Mutex::new(String::new()));
+        if let Some(stderr) = child.stderr.take() {
+            let last_error = Arc::clone(&last_error);
+            thread::spawn(move || {
+                for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+                    if !line.trim().is_empty() {
+                        *last_error.lock().unwrap_or_else(|e| e.into_inner()) = line;
+                    }
+                }
+            });
+        }
+
+        let mut process = SshProcess {
+            child,
+            target,
+            local_port,
+            last_error,
+        };
+        let deadline = Instant::now() + Duration::from_secs_f64(SSH_TUNNEL_TIMEOUT_SECS);
+        loop {
+            if let Some(status) = process.exit_status() {
+                return Err(format!("ssh exited with {status}{}", process.reason()));
+            }
+            if TcpStream::connect((Ipv4Addr::LOCALHOST, local_port)).is_ok() {
+                return Ok(process);
+            }
+            if Instant::now() >= deadline {
+                return Err(format!(
+                    "ssh did not open the tunnel within {SSH_TUNNEL_TIMEOUT_SECS} seconds{}",
+                    process.reason()
+                ));
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+    }
+
+    /// Exit status of `ssh`, `None` while it runs.
+    fn exit_status(&mut self) -> Option<String> {
+        match self.child.try_wait() {
+            Ok(None) => None,
+            Ok(Some(status)) => Some(status.to_string()),
+            Err(err) => Some(err.to_string()),
+        }
+    }
+
+    /// `: <last error line>` of `ssh`, empty when it wrote none.
+    fn reason(&self) -> String {
+        // Give the reader thread a moment to catch the last line.
+        thread::sleep(Duration::from_millis(50));
+        let line = self.last_error.lock().unwrap_or_else(|e| e.into_inner());
+        if line.is_empty() {
+            String::new()
+        } else {
+            format!(": {line}")
+        }
+    }
+}
+
+impl Drop for SshProcess {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// The SSH tunnel of the daemon, if the configuration asks for one.
+#[derive(Default)]
+pub struct Tunnel {
+    process: Option<SshProcess>,
+}
+
+impl Tunnel {
+    /// Make sure the tunnel asked by the configuration is up: start it,
+    /// restart it when `ssh` has exited or its settings changed, stop it
+    /// when `ssh_host` was cleared. Returns true when the route to the
+    /// database changed, so existing connections must be dropped.
+    pub fn ensure(&mut self, config: &Config, dbinfo: &DbInfo) -> Result<bool, String> {
+        let target = TunnelTarget::of(config, dbinfo);
+        if let Some(process) = self.process.as_mut() {
+            if Some(&process.target) == target.as_ref() {
+                match process.exit_status() {
+                    None => return Ok(false),
+                    Some(status) => dlog!(
+                        config,
+                        "WARNING",
+                        "ssh tunnel through {} exited with {}{}, restarting it",
+                        process.target.ssh_host,
+                        status,
+                        process.reason()
+                    ),
+                }
+            } else {
+                dprint(
+                    config,
+                    "LOG",
+                    "ssh tunnel settings changed, closing the tunnel",
+                );
+            }
+            self.process = None;
+            if target.is_none() {
+                return Ok(true);
+            }
+        }
+        let Some(target) = target else {
+            return Ok(false);
+        };
+        let process = SshProcess::start(target).map_err(|e| {
+            format!(
+                "can not open the ssh tunnel through {}, {e}",
+                config.ssh_host
+            )
+        })?;
+        dlog!(
+            config,
+            "LOG",
+            "ssh tunnel through {} listening on {}:{}",
+            process.target.ssh_host,
+            Ipv4Addr::LOCALHOST,
+            process.local_port
+        );
+        self.process = Some(process);
+        Ok(true)
+    }
+
+    /// Connection settings of the database, through the tunnel when it is
+    /// up.
+    pub fn route(&self, dbinfo: &DbInfo) -> DbInfo {
+        match &self.process {
+            Some(process) => DbInfo {
+                host: Ipv4Addr::LOCALHOST.to_string(),
+                port: process.local_port,
+                ..dbinfo.clone()
+            },
+            None => dbinfo.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TunnelTarget;
+
+    fn target(db_host: &str) -> TunnelTarget {
+        TunnelTarget {
+            ssh_host: "bastion.example.com".to_string(),
+            ssh_port: 2222,
+            ssh_user: "tunnel".to_string(),
+            ssh_key: "/etc/pg_dbms_job/id_ed25519".to_string(),
+            db_host: db_host.to_string(),
+            db_port: 5433,
+        }
+    }
+
+    #[test]
+    fn ssh_args_forward_the_database_port() {
+        let args = target("db.internal").ssh_args(40000);
+        let joined = args.join(" ");
+        assert!(joined.starts_with("-N -o BatchMode=yes -o ExitOnForwardFailure=yes"));
+        assert!(joined.contains("-p 2222 -l tunnel -i /etc/pg_dbms_job/id_ed25519"));
+        assert!(joined.ends_with("-L 127.0.0.1:40000:db.internal:5433 -- bastion.example.com"));
+    }
+
+    #[test]
+    fn ssh_args_default_to_the_bastion_itself_or_its_socket() {
+        let args = target("").ssh_args(40000);
+        assert!(args.contains(&"127.0.0.1:40000:localhost:5433".to_string()));
+        let args = target("/var/run/postgresql").ssh_args(40000);
+        assert!(args.contains(&"127.0.0.1:40000:/var/run/postgresql/.s.PGSQL.5433".to_string()));
+
+        let mut bare = target("db");
+        bare.ssh_user.clear();
+        bare.ssh_key.clear();
+        let args = bare.ssh_args(1);
+        assert!(!args.contains(&"-l".to_string()) && !args.contains(&"-i".to_string()));
+    }
+}
@@ -1,16 +1,79 @@
 //! Small utilities used across the scheduler.
 
+use std::hash::{BuildHasher, Hasher, RandomState};
+
 /// Print a fatal message and terminate the process.
 pub fn die(msg: &str) -> ! {
     eprintln!("{msg}");
     std::process::exit(1);
 }
 
+/// Host name of the machine running the scheduler, `unknown` if it can not
+/// be determined.
+pub fn host_name() -> String {
+    nix::unistd::gethostname()
+        .ok()
+        .and_then(|name| name.into_string().ok())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Random number in `[0, 1)`.
+pub fn random_fraction() -> f64 {
+    // See new_run_id: 53 bits of an empty hash fill the mantissa.
+    (RandomState::new().build_hasher().finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Random (version 4) UUID identifying one job run, in its text form.
+pub fn new_run_id() -> String {
+    // Every `RandomState` has its own keys, drawn from the OS random source,
+    // so an empty hash is 64 unpredictable bits without another dependency.
+    let half = || u128::from(RandomState::new().build_hasher().finish());
+    let mut bits = (half() << 64) | half();
+    // Version 4, variant RFC 4122.
+    bits = (bits & !(0xf << 76) & !(0x3 << 62)) | (0x4 << 76) | (0x2 << 62);
+    let hex = format!("{bits:032x}");
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
 #[cfg(test)]
 mod tests {
-    use super::die;
+    use super::{die, host_name, new_run_id, random_fraction};
     use std::process::Command;
 
+    #[test]
+    fn host_name_is_never_empty() {
+        assert!(!host_name().is_empty());
+    }
+
+    #[test]
+    fn random_fractions_are_in_unit_interval() {
+        let draws: Vec<f64> = (0..100).map(|_| random_fraction()).collect();
+        assert!(draws.iter().all(|x| (0.0..1.0).contains(x)));
+        assert!(draws.iter().any(|x| *x != draws[0]));
+    }
+
+    #[test]
+    fn run_ids_are_distinct_version_4_uuids() {
+        let id = new_run_id();
+        assert_eq!(id.len(), 36);
+        let groups: Vec<&str> = id.split('-').collect();
+        assert_eq!(
+            groups.iter().map(|g| g.len()).collect::<Vec<_>>(),
+            [8, 4, 4, 4, 12]
+        );
+        assert!(groups[2].starts_with('4'));
+        assert!(groups[3].starts_with(['8', '9', 'a', 'b']));
+        assert_ne!(id, new_run_id());
+    }
+
     #[test]
     fn die_exits_child() {
         if std::env::var("PG_DBMS_JOB_DIE_TEST").is_ok() {
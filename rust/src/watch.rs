@@ -0,0 +1,114 @@
+//! `auto_reload`: reload of the configuration when its file changes.
+//!
+//! A thread watches the directory of the configuration file with inotify,
+//! so a file replaced by a rename, as deployment tools and Kubernetes
+//! ConfigMaps do, is seen like one written in place. Once the directory
+//! has been quiet for `AUTO_RELOAD_SETTLE_SECS`, the content of the file is
+//! compared with the one last seen and, when it differs, the daemon sends
+//! itself SIGHUP: the reload is the one of `pg_dbms_job -r`.
+
+use crate::constants::AUTO_RELOAD_SETTLE_SECS;
+use crate::dlog;
+use crate::model::Config;
+use nix::errno::Errno;
+use nix::sys::inotify::{AddWatchFlags, InitFlags, Inotify};
+use nix::sys::signal::{Signal, kill};
+use nix::unistd::getpid;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+/// Watcher of the configuration file, started the first time
+/// `auto_reload` is set and then only paused while it is not.
+#[derive(Default)]
+pub struct ConfigWatcher {
+    enabled: Option<Arc<AtomicBool>>,
+}
+
+impl ConfigWatcher {
+    /// Follow the `auto_reload` setting of `config`, starting the watch of
+    /// `config_file` when it is first enabled.
+    pub fn ensure(&mut self, config: &Config, config_file: &str) {
+        if let Some(enabled) = &self.enabled {
+            enabled.store(config.auto_reload, Ordering::Relaxed);
+            return;
+        }
+        if !config.auto_reload {
+            return;
+        }
+        match watch(config_file) {
+            Ok(enabled) => {
+                dlog!(
+                    config,
+                    "LOG",
+                    "watching configuration file {} for changes",
+                    config_file
+                );
+                self.enabled = Some(enabled);
+            }
+            Err(err) => dlog!(
+                config,
+                "ERROR",
+                "can not watch configuration file {} for changes, {}",
+                config_file,
+                err
+            ),
+        }
+    }
+}
+
+/// Start the thread watching `config_file`, returning the flag pausing it.
+fn watch(config_file: &str) -> Result<Arc<AtomicBool>, String> {
+    let path = Path::new(config_file);
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let inotify =
+        Inotify::init(InitFlags::IN_NONBLOCK | InitFlags::IN_CLOEXEC).map_err(|e| e.to_string())?;
+    inotify
+        .add_watch(
+            dir,
+            AddWatchFlags::IN_CLOSE_WRITE
+                | AddWatchFlags::IN_MOVED_TO
+                | AddWatchFlags::IN_CREATE
+                | AddWatchFlags::IN_DELETE,
+        )
+        .map_err(|e| format!("{}, {e}", dir.display()))?;
+    let enabled = Arc::new(AtomicBool::new(true));
+    let flag = Arc::clone(&enabled);
+    let path = path.to_path_buf();
+    thread::Builder::new()
+        .name("config-watch".to_string())
+        .spawn(move || {
+            let mut seen = fs::read(&path).ok();
+            let mut pending = false;
+            loop {
+                thread::sleep(Duration::from_secs_f64(AUTO_RELOAD_SETTLE_SECS));
+                let changed = match inotify.read_events() {
+                    Ok(events) => !events.is_empty(),
+                    Err(Errno::EAGAIN) => false,
+                    Err(_) => return,
+                };
+                if changed {
+                    // Wait for the writes in progress to settle.
+                    pending = true;
+                    continue;
+                }
+                if !pending || !flag.load(Ordering::Relaxed) {
+                    continue;
+                }
+                pending = false;
+                let content = fs::read(&path).ok();
+                if content.is_some() && content != seen {
+                    seen = content;
+                    let _ = kill(getpid(), Signal::SIGHUP);
+                }
+            }
+        })
+        .map_err(|e| e.to_string())?;
+    Ok(enabled)
+}
@@ -0,0 +1,1316 @@
+----
+-- Script to create the base objects of the pg_dbms_job extension
+----
+CREATE SEQUENCE dbms_job.jobseq;
+
+-- Table used to store the jobs to run by the scheduler
+CREATE TABLE dbms_job.all_scheduled_jobs (
+        job bigint DEFAULT nextval('dbms_job.jobseq') PRIMARY KEY, -- identifier of job
+        log_user name DEFAULT current_user, -- user that submit the job
+        priv_user name DEFAULT current_user, -- user whose default privileges apply to this job (not used)
+        schema_user text DEFAULT current_setting('search_path'), -- default schema used to parse the job
+        last_date timestamp with time zone, -- date on which this job last successfully executed
+        last_sec text, -- same as last_date (not used)
+        this_date timestamp with time zone, -- date that this job started executing, null when the job is not running
+        this_sec text, -- same as this_date (not used)
+        next_date timestamp with time zone NOT NULL, -- date that this job will next be executed
+        next_sec timestamp with time zone, -- same as next_date (not used)
+        total_time interval, -- total wall clock time spent by the system on this job, in seconds
+        broken boolean DEFAULT false, -- true: no attempt is made to run this job, false: an attempt is made to run this job
+        interval text, -- a date function, evaluated at the start of execution, becomes next next_date
+        failures bigint, -- number of times the job has started and failed since its last success
+        what text  NOT NULL, -- body of the anonymous pl/sql block that the job executes
+        nls_env text, -- session parameters describing the nls environment of the job (not used)
+	misc_env bytea, -- Other session parameters that apply to this job (not used)
+	instance integer DEFAULT 0, -- ID of the instance that can execute or is executing the job (not used)
+	on_success bigint, -- job to enqueue asynchronously after a successful run
+	on_failure bigint, -- job to enqueue asynchronously after a failed run
+	last_error text, -- error message of the last failed run
+	last_sqlstate text, -- SQLSTATE of the last failed run
+	last_failure_date timestamp with time zone, -- date on which this job last failed
+	consecutive_overruns integer NOT NULL DEFAULT 0, -- consecutive runs longer than the scheduler's hard_max_run_duration
+	continue_on_error boolean NOT NULL DEFAULT false, -- run each statement under a savepoint and continue after a failing one
+	retry_delay interval, -- delay after a failed run before the job is retried, NULL to wait for the next scheduled date
+	resource_key text, -- jobs sharing a key never run concurrently, NULL for no serialization
+	max_run_duration interval, -- run time after which the scheduler cancels the run, NULL for no limit
+	max_failures integer, -- consecutive failed runs after which the job is marked broken, NULL for the scheduler's max_failures, 0 for no limit
+	priority integer NOT NULL DEFAULT 3 CHECK (priority BETWEEN 1 AND 5), -- from 1 (highest) to 5 (lowest), due jobs are claimed and started in priority order
+	job_class text, -- class limiting the jobs running at the same time to its job_class.<name> setting, NULL for no class
+	job_type text NOT NULL DEFAULT 'PLSQL_BLOCK' CHECK (job_type IN ('PLSQL_BLOCK', 'EXECUTABLE')), -- job type: PLSQL_BLOCK runs what as a PL/pgSQL block, EXECUTABLE as a shell command
+	os_user text, -- operating system user an EXECUTABLE job runs as, NULL for the scheduler's own
+	run_count bigint NOT NULL DEFAULT 0, -- number of completed runs, successful or not
+	failure_count bigint NOT NULL DEFAULT 0, -- number of failed runs
+	last_success_date timestamp with time zone, -- date on which this job last completed successfully
+	last_status text -- status of the last run: SUCCEEDED, FAILED, PARTIAL, TIMEOUT, TIMED_OUT, CANCELLED or BROKEN
+);
+COMMENT ON TABLE dbms_job.all_scheduled_jobs
+    IS 'Table used to store the periodical jobs to run by the scheduler.';
+REVOKE ALL ON dbms_job.all_scheduled_jobs FROM PUBLIC;
+
+-- The user can only see the job that he has created
+ALTER TABLE dbms_job.all_scheduled_jobs ENABLE ROW LEVEL SECURITY;
+CREATE POLICY dbms_job_policy ON dbms_job.all_scheduled_jobs USING (log_user = current_user);
+
+-- Create the asynchronous jobs queue, for immediat execution
+CREATE TABLE dbms_job.all_async_jobs (
+        job bigint DEFAULT nextval('dbms_job.jobseq') PRIMARY KEY, -- identifier of job
+        log_user name DEFAULT current_user, -- user that submit the job
+        schema_user text DEFAULT current_setting('search_path'), -- default search_path used to execute the job
+        create_date timestamp with time zone DEFAULT current_timestamp, -- date on which this job has been created.
+        what text NOT NULL, -- body of the anonymous pl/sql block that the job executes
+        this_date timestamp with time zone, -- date that this job started executing, null when the job is not running
+        on_success bigint, -- job to enqueue asynchronously after a successful run
+        on_failure bigint, -- job to enqueue asynchronously after a failed run
+        continue_on_error boolean NOT NULL DEFAULT false, -- run each statement under a savepoint and continue after a failing one
+        resource_key text, -- jobs sharing a key never run concurrently, NULL for no serialization
+        max_run_duration interval, -- run time after which the scheduler cancels the run, NULL for no limit
+        priority integer NOT NULL DEFAULT 3 CHECK (priority BETWEEN 1 AND 5), -- from 1 (highest) to 5 (lowest), queued jobs are claimed and started in priority order
+        job_class text, -- class limiting the jobs running at the same time to its job_class.<name> setting, NULL for no class
+        source_job bigint, -- scheduled job this job is a copy of, enqueued by a chain or a dependency
+        job_type text NOT NULL DEFAULT 'PLSQL_BLOCK' CHECK (job_type IN ('PLSQL_BLOCK', 'EXECUTABLE')), -- job type: PLSQL_BLOCK runs what as a PL/pgSQL block, EXECUTABLE as a shell command
+        os_user text -- operating system user an EXECUTABLE job runs as, NULL for the scheduler's own
+);
+COMMENT ON TABLE dbms_job.all_async_jobs
+    IS 'Table used to store the jobs to be run asynchronously by the scheduler.';
+REVOKE ALL ON dbms_job.all_async_jobs FROM PUBLIC;
+
+-- The user can only see the job that he has created
+ALTER TABLE dbms_job.all_async_jobs ENABLE ROW LEVEL SECURITY;
+CREATE POLICY dbms_job_policy ON dbms_job.all_async_jobs USING (log_user = current_user);
+
+-- Dependencies between jobs: the scheduler enqueues a job asynchronously
+-- once all its SUCCEEDED prerequisites have succeeded, or one of its FAILED
+-- prerequisites has failed, since it was last enqueued.
+CREATE TABLE dbms_job.all_job_dependencies (
+        job bigint NOT NULL, -- job enqueued once its prerequisites completed
+        prerequisite bigint NOT NULL, -- job whose runs are waited for
+        condition text NOT NULL DEFAULT 'SUCCEEDED' CHECK (condition IN ('SUCCEEDED', 'FAILED')), -- outcome of the prerequisite's run waited for
+        satisfied_date timestamp with time zone, -- run of the prerequisite with that outcome since the job was last enqueued, NULL for none
+        log_user name DEFAULT current_user, -- user that declared the dependency
+        PRIMARY KEY (job, prerequisite, condition)
+);
+CREATE INDEX all_job_dependencies_prerequisite_idx ON dbms_job.all_job_dependencies (prerequisite);
+COMMENT ON TABLE dbms_job.all_job_dependencies
+    IS 'Table used to store the dependencies between jobs.';
+REVOKE ALL ON dbms_job.all_job_dependencies FROM PUBLIC;
+
+-- The user can only see the dependencies that he has declared
+ALTER TABLE dbms_job.all_job_dependencies ENABLE ROW LEVEL SECURITY;
+CREATE POLICY dbms_job_policy ON dbms_job.all_job_dependencies USING (log_user = current_user);
+
+-- Create a view similar to DMBS_JOB.ALL_JOBS. The column list is spelled out
+-- so that scheduler-specific columns added to all_scheduled_jobs do not have
+-- to be mirrored in the asynchronous branch of the UNION.
+CREATE VIEW dbms_job.all_jobs AS
+    SELECT job, log_user, priv_user, schema_user, last_date, last_sec,
+           this_date, this_sec, next_date, next_sec, total_time,
+           broken, interval, failures, what, nls_env, misc_env,
+           instance FROM dbms_job.all_scheduled_jobs
+    UNION
+    SELECT job, log_user, NULL priv_user, schema_user, NULL last_date, NULL last_sec,
+           NULL this_date, NULL this_sec, create_date next_date, NULL next_sec, NULL total_time,
+	   'f' broken, NULL "interval", NULL failures, what, NULL nls_env, NULL misc_env,
+	   0 instance FROM dbms_job.all_async_jobs;
+COMMENT ON VIEW dbms_job.all_jobs
+    IS 'View registering all jobs to be run asynchronously or scheduled.';
+REVOKE ALL ON dbms_job.all_jobs FROM PUBLIC;
+
+-- Schedulers serving this database, one row per host, upserted by the
+-- scheduler daemon each time it connects. A scheduler whose heartbeat is
+-- recent owns the database: another one refuses to start.
+CREATE TABLE dbms_job.scheduler_registry (
+	host text PRIMARY KEY, -- host name of the machine running the scheduler
+	pid integer NOT NULL, -- process identifier of the scheduler
+	version text NOT NULL, -- version of the scheduler
+	features text[] NOT NULL DEFAULT '{}', -- optional capabilities of this scheduler build
+	start_time timestamp with time zone NOT NULL, -- date on which the scheduler was started
+	heartbeat timestamp with time zone, -- last sign of life of the scheduler, NULL once stopped
+	pending_scheduled bigint, -- due scheduled jobs not yet claimed, counted with the heartbeat
+	pending_async bigint -- queued asynchronous jobs not yet claimed, counted with the heartbeat
+);
+COMMENT ON TABLE dbms_job.scheduler_registry
+    IS 'Table used to register the scheduler daemons serving this database.';
+REVOKE ALL ON dbms_job.scheduler_registry FROM PUBLIC;
+
+-- Maintenance switch: while enabled, the scheduler claims no new job so that
+-- job execution can be frozen from SQL (restores, upgrades, VACUUM FULL
+-- windows). Jobs already running are not interrupted.
+CREATE TABLE dbms_job.maintenance_mode (
+	id boolean PRIMARY KEY DEFAULT true CHECK (id), -- the table has a single row
+	enabled boolean NOT NULL DEFAULT false, -- true: the scheduler starts no new job
+	reason text, -- why job execution is paused, reported in the scheduler log
+	changed_by name NOT NULL DEFAULT current_user, -- user who last changed the flag
+	changed_at timestamp with time zone NOT NULL DEFAULT current_timestamp -- date of the last change
+);
+COMMENT ON TABLE dbms_job.maintenance_mode
+    IS 'Single-row switch pausing job dispatch by the scheduler.';
+INSERT INTO dbms_job.maintenance_mode DEFAULT VALUES;
+REVOKE ALL ON dbms_job.maintenance_mode FROM PUBLIC;
+
+CREATE PROCEDURE dbms_job.set_maintenance_mode(
+		enabled     IN  boolean,
+		reason      IN  text DEFAULT NULL)
+    LANGUAGE PLPGSQL
+    AS $$
+BEGIN
+    UPDATE dbms_job.maintenance_mode
+        SET enabled = $1, reason = $2, changed_by = current_user, changed_at = current_timestamp;
+END;
+$$;
+
+COMMENT ON PROCEDURE dbms_job.set_maintenance_mode(boolean,text)
+    IS 'Pauses or resumes the dispatch of jobs by the scheduler';
+REVOKE ALL ON PROCEDURE dbms_job.set_maintenance_mode FROM PUBLIC;
+
+-- Configuration reloads of the scheduler, one row per reload, written by the
+-- scheduler daemon once it has reconnected with the new settings.
+CREATE TABLE dbms_job.config_audit (
+	id bigserial PRIMARY KEY, -- identifier of the reload event
+	audit_date timestamp with time zone NOT NULL DEFAULT current_timestamp, -- date of the reload
+	host text NOT NULL, -- host name of the machine running the scheduler
+	pid integer NOT NULL, -- process identifier of the scheduler
+	config_file text NOT NULL, -- path of the configuration file reloaded
+	config_hash text, -- SHA-256 of the configuration file, NULL if it could not be read
+	changes jsonb NOT NULL DEFAULT '{}' -- changed settings: {"name": {"old": ..., "new": ...}}
+);
+COMMENT ON TABLE dbms_job.config_audit
+    IS 'Table used to record the configuration reloads of the scheduler.';
+REVOKE ALL ON dbms_job.config_audit FROM PUBLIC;
+
+-- Lifecycle events of the scheduler daemons (start, stop, reload, lost
+-- connection, saturated worker pool), written by the daemon when
+-- scheduler_event_log is enabled, for DBAs without access to its log.
+CREATE TABLE dbms_job.scheduler_event_log (
+	id bigserial PRIMARY KEY, -- identifier of the event
+	event_date timestamp with time zone NOT NULL DEFAULT current_timestamp, -- date of the event, as seen by the scheduler
+	host text NOT NULL, -- host name of the machine running the scheduler
+	pid integer NOT NULL, -- process identifier of the scheduler
+	event text NOT NULL, -- start, stop, reload, connection_lost or queue_saturated
+	detail text -- description of the event
+);
+COMMENT ON TABLE dbms_job.scheduler_event_log
+    IS 'Table used to record the lifecycle events of the scheduler.';
+REVOKE ALL ON dbms_job.scheduler_event_log FROM PUBLIC;
+
+-- Create a table to store the result of the job execution.
+--
+-- Range-partitioned by log_date (monthly) so the unbounded growth of this
+-- write-only history table is managed by creating/dropping whole partitions
+-- rather than DELETE+VACUUM. log_date is part of the primary key because a
+-- partitioned table's PK must include the partition key. Requires PostgreSQL 11+
+-- (declarative partitioning with a DEFAULT partition).
+CREATE TABLE dbms_job.all_scheduler_job_run_details (
+	log_id bigserial, -- unique identifier of the log entry
+	log_date timestamp with time zone NOT NULL DEFAULT current_timestamp, -- date of the log entry (partition key)
+	owner name, -- owner of the scheduler job
+	job_name varchar(261), -- name of the scheduler job
+	job_subname varchar(261), -- Subname of the Scheduler job (for a chain step job)
+	status text, -- status of the job run
+	error char(5), -- error code in the case of an error
+	req_start_date timestamp with time zone, -- requested start date of the job run
+	actual_start_date timestamp with time zone, -- actual date on which the job was run
+	run_duration bigint, -- duration of the job run in seconds
+	instance_id integer, -- identifier of the instance on which the job was run
+	session_id integer, -- session identifier of the job run
+	slave_pid integer, -- process identifier of the PostgreSQL backend that ran the job
+	cpu_used integer, -- amount of cpu used for the job run
+	additional_info	text, -- additional information on the job run, error message, etc.
+	result text, -- value published by the job through the pg_dbms_job.result setting
+	error_message text, -- message of the error, without detail, hint or context
+	error_detail text, -- DETAIL of the error
+	error_hint text, -- HINT of the error
+	error_context text, -- CONTEXT of the error: PL/pgSQL line and call stack
+	run_id uuid, -- identifier of the run, also in the scheduler log lines of the run
+	scheduler_host text, -- host name of the scheduler that ran the job
+	job_schema text, -- job queue schema of the job
+	PRIMARY KEY (log_id, log_date)
+) PARTITION BY RANGE (log_date);
+COMMENT ON TABLE dbms_job.all_scheduler_job_run_details
+    IS 'Table used to store the information about the jobs executed.';
+REVOKE ALL ON dbms_job.all_scheduler_job_run_details FROM PUBLIC;
+
+-- The user can only see the job that he has created
+ALTER TABLE dbms_job.all_scheduler_job_run_details ENABLE ROW LEVEL SECURITY;
+CREATE POLICY dbms_job_policy ON dbms_job.all_scheduler_job_run_details USING (owner = current_user);
+
+----
+-- Partition maintenance for all_scheduler_job_run_details
+--
+-- Ensures a monthly partition exists for the current month and the next
+-- `months_ahead` months (so routine inserts never fall through to the DEFAULT
+-- partition), then drops monthly partitions entirely older than
+-- `retention_months`. Safe to call repeatedly; a missed call only leaves rows
+-- in the DEFAULT partition and pauses pruning — it never breaks inserts.
+--
+-- Schedule it (as a privileged role, since it issues CREATE/DROP TABLE), e.g.
+-- once a day via cron, or by submitting it as a recurring dbms_job:
+--   SELECT dbms_job.submit(
+--       'PERFORM dbms_job.maintain_run_details_partitions();',
+--       current_timestamp,
+--       'current_timestamp + interval ''1 day''');
+----
+CREATE OR REPLACE FUNCTION dbms_job.maintain_run_details_partitions(
+    months_ahead integer DEFAULT 1,
+    retention_months integer DEFAULT 3
+) RETURNS void
+    LANGUAGE PLPGSQL
+    AS $$
+DECLARE
+    start_d date;
+    end_d   date;
+    part    text;
+    cutoff  date;
+    r       record;
+BEGIN
+    FOR i IN 0..GREATEST(months_ahead, 0) LOOP
+        start_d := (date_trunc('month', current_date) + make_interval(months => i))::date;
+        end_d   := (start_d + interval '1 month')::date;
+        part    := 'all_scheduler_job_run_details_' || to_char(start_d, 'YYYYMM');
+        IF NOT EXISTS (
+            SELECT 1 FROM pg_class c
+            JOIN pg_namespace n ON n.oid = c.relnamespace
+            WHERE n.nspname = 'dbms_job' AND c.relname = part
+        ) THEN
+            EXECUTE format(
+                'CREATE TABLE dbms_job.%I PARTITION OF dbms_job.all_scheduler_job_run_details '
+                'FOR VALUES FROM (%L) TO (%L)', part, start_d, end_d);
+        END IF;
+    END LOOP;
+
+    IF retention_months IS NOT NULL AND retention_months > 0 THEN
+        cutoff := (date_trunc('month', current_date)
+                   - make_interval(months => retention_months))::date;
+        FOR r IN
+            SELECT c.relname
+            FROM pg_inherits inh
+            JOIN pg_class c ON c.oid = inh.inhrelid
+            JOIN pg_class p ON p.oid = inh.inhparent
+            JOIN pg_namespace n ON n.oid = p.relnamespace
+            WHERE n.nspname = 'dbms_job'
+              AND p.relname = 'all_scheduler_job_run_details'
+              AND c.relname ~ '^all_scheduler_job_run_details_[0-9]{6}$'
+        LOOP
+            IF to_date(right(r.relname, 6), 'YYYYMM') < cutoff THEN
+                EXECUTE format('DROP TABLE IF EXISTS dbms_job.%I', r.relname);
+            END IF;
+        END LOOP;
+    END IF;
+END;
+$$;
+REVOKE ALL ON FUNCTION dbms_job.maintain_run_details_partitions(integer, integer) FROM PUBLIC;
+
+-- Catch-all partition so inserts never fail even if maintenance lapses, plus
+-- the current and next month's partitions to start with.
+CREATE TABLE dbms_job.all_scheduler_job_run_details_default
+    PARTITION OF dbms_job.all_scheduler_job_run_details DEFAULT;
+SELECT dbms_job.maintain_run_details_partitions(1, 3);
+
+----
+-- Indexes supporting the scheduler dispatch scans
+--
+-- The daemon refetches ready jobs on every cycle. Without these partial
+-- indexes both fetches degrade to sequential scans over the whole table,
+-- which dominates dispatch latency once the tables accumulate rows/bloat.
+-- The indexes are partial on `this_date IS NULL` so they only cover the
+-- small set of not-yet-running jobs, matching the WHERE clauses in
+-- jobs.rs::get_async_jobs / get_scheduled_jobs.
+----
+CREATE INDEX IF NOT EXISTS all_async_jobs_pending_idx
+    ON dbms_job.all_async_jobs (job)
+    WHERE this_date IS NULL;
+CREATE INDEX IF NOT EXISTS all_scheduled_jobs_pending_idx
+    ON dbms_job.all_scheduled_jobs (next_date)
+    WHERE this_date IS NULL;
+
+----
+-- Per-table autovacuum tuning for the queue tables
+--
+-- Each dispatch+completion performs an UPDATE per job, so these tables churn
+-- heavily and bloat fast under the default 20% scale factor. Vacuum/analyze
+-- them aggressively (after ~100 dead tuples, no cost-delay throttling) so the
+-- partial indexes above and the dispatch scans stay on a compact heap.
+----
+ALTER TABLE dbms_job.all_async_jobs SET (
+    autovacuum_vacuum_scale_factor = 0.0,
+    autovacuum_vacuum_threshold = 100,
+    autovacuum_analyze_scale_factor = 0.0,
+    autovacuum_analyze_threshold = 100,
+    autovacuum_vacuum_cost_delay = 0
+);
+ALTER TABLE dbms_job.all_scheduled_jobs SET (
+    autovacuum_vacuum_scale_factor = 0.0,
+    autovacuum_vacuum_threshold = 100,
+    autovacuum_analyze_scale_factor = 0.0,
+    autovacuum_analyze_threshold = 100,
+    autovacuum_vacuum_cost_delay = 0
+);
+
+----
+-- Stored procedures
+----
+CREATE PROCEDURE dbms_job.broken(
+		jobid     IN  bigint,
+		broken    IN  boolean,
+		next_date IN  timestamp with time zone DEFAULT current_timestamp)
+    LANGUAGE PLPGSQL
+    AS $$
+BEGIN
+    -- interval must be in the future
+    IF next_date < current_timestamp THEN
+        RAISE EXCEPTION 'next_date must be a time in the future: %', next_date USING ERRCODE = '23420';
+    END IF;
+    -- a job enabled again starts over its counts of failures and overruns
+    UPDATE dbms_job.all_scheduled_jobs SET broken=$2,next_date=$3,
+        failures = CASE WHEN $2 THEN failures ELSE 0 END,
+        consecutive_overruns = CASE WHEN $2 THEN consecutive_overruns ELSE 0 END
+        WHERE job=$1;
+    IF NOT FOUND THEN
+        RAISE EXCEPTION 'null_value_not_allowed' USING detail = 'job number is not a job in the job queue';
+    END IF;
+END;
+$$;
+
+COMMENT ON PROCEDURE dbms_job.broken(bigint,boolean,timestamp with time zone)
+    IS 'Disables job execution. Broken jobs are never run.';
+REVOKE ALL ON PROCEDURE dbms_job.broken FROM PUBLIC;
+
+CREATE PROCEDURE dbms_job.change(
+		job          IN  bigint,
+		what         IN  text,
+		next_date    IN  timestamp with time zone,
+		job_interval IN  text,
+		instance     IN  bigint DEFAULT 0,
+		force        IN  boolean DEFAULT false)
+    LANGUAGE PLPGSQL 
+    AS $$
+DECLARE
+    cols_modified text;
+    future_date timestamp with time zone;
+    v_ret bigint;
+BEGIN
+    -- If what, next_date or job_interval are NULL they are kept unchanged
+    IF what IS NOT NULL THEN
+	cols_modified := coalesce(cols_modified, '') || 'what=' || quote_literal(what) || ','; 
+    END IF;
+    IF next_date IS NOT NULL THEN
+	cols_modified := coalesce(cols_modified, '') || 'next_date=' || quote_literal(next_date) || ','; 
+    END IF;
+    IF job_interval IS NOT NULL THEN
+        -- interval must be in the future
+        future_date := dbms_job.get_next_date(job_interval);
+        IF future_date < current_timestamp THEN
+    	    RAISE EXCEPTION 'Interval must evaluate to a time in the future: %', future_date USING ERRCODE = '23420';
+        END IF;
+	cols_modified := coalesce(cols_modified, '') || 'interval=' || quote_literal(job_interval) || ','; 
+    END IF;
+    IF cols_modified IS NOT NULL THEN
+        EXECUTE 'UPDATE dbms_job.all_scheduled_jobs SET ' || rtrim(cols_modified, ',') || ' WHERE job=$1 RETURNING job'INTO v_ret USING job;
+        IF v_ret IS NULL THEN
+            RAISE EXCEPTION 'null_value_not_allowed' USING detail = 'job number is not a job in the job queue';
+        END IF;
+    END IF;
+END;
+$$;
+COMMENT ON PROCEDURE dbms_job.change(bigint,text,timestamp with time zone,text,bigint,boolean)
+    IS 'Alters any of the user-definable parameters associated with a job';
+REVOKE ALL ON PROCEDURE dbms_job.change FROM PUBLIC;
+
+CREATE PROCEDURE dbms_job.interval(
+		jobid           IN  bigint,
+		job_interval  IN  text)
+    LANGUAGE PLPGSQL 
+    AS $$
+DECLARE
+    next_date timestamp with time zone;
+    v_interval text;
+    v_retval bigint;
+BEGIN
+    IF job_interval IS NULL THEN
+        UPDATE dbms_job.all_scheduled_jobs SET interval = NULL WHERE job = jobid;
+    ELSE
+        -- interval must be in the future
+        next_date := dbms_job.get_next_date(job_interval);
+        IF next_date < current_timestamp THEN
+    	    RAISE EXCEPTION 'Interval must evaluate to a time in the future: %', next_date USING ERRCODE = '23420';
+        END IF;
+        v_interval := 'UPDATE dbms_job.all_scheduled_jobs SET interval = ' || quote_literal(job_interval) || ' WHERE job = ' || jobid || ' RETURNING job';
+        EXECUTE v_interval INTO v_retval;
+        IF v_retval IS NULL THEN
+            RAISE EXCEPTION 'null_value_not_allowed' USING detail = 'job number is not a job in the job queue';
+        END IF;
+    END IF;
+END;
+$$;
+
+COMMENT ON PROCEDURE dbms_job.interval(bigint,text)
+    IS 'Alters the interval between executions for a specified job';
+REVOKE ALL ON PROCEDURE dbms_job.interval FROM PUBLIC;
+
+CREATE PROCEDURE dbms_job.next_date(
+		jobid        IN  bigint,
+		next_date  IN  timestamp with time zone)
+    LANGUAGE PLPGSQL
+    AS $$
+BEGIN
+    IF next_date IS NULL THEN
+        RAISE EXCEPTION 'Next date can not be NULL';
+    END IF;
+    UPDATE dbms_job.all_scheduled_jobs SET next_date = $2 WHERE job = jobid;
+    IF NOT FOUND THEN
+        RAISE EXCEPTION 'null_value_not_allowed' USING detail = 'job number is not a job in the job queue';
+    END IF;
+END;
+$$;
+
+COMMENT ON PROCEDURE dbms_job.next_date(bigint,timestamp with time zone)
+    IS 'Alters the next execution time for a specified job';
+REVOKE ALL ON PROCEDURE dbms_job.next_date FROM PUBLIC;
+
+CREATE PROCEDURE dbms_job.on_completion(
+		jobid       IN  bigint,
+		on_success  IN  bigint,
+		on_failure  IN  bigint DEFAULT NULL)
+    LANGUAGE PLPGSQL
+    AS $$
+BEGIN
+    UPDATE dbms_job.all_scheduled_jobs SET on_success = $2, on_failure = $3 WHERE job = jobid;
+    IF NOT FOUND THEN
+        RAISE EXCEPTION 'null_value_not_allowed' USING detail = 'job number is not a job in the job queue';
+    END IF;
+END;
+$$;
+
+COMMENT ON PROCEDURE dbms_job.on_completion(bigint,bigint,bigint)
+    IS 'Sets the jobs enqueued asynchronously after a successful or failed run';
+REVOKE ALL ON PROCEDURE dbms_job.on_completion FROM PUBLIC;
+
+CREATE PROCEDURE dbms_job.continue_on_error(
+		jobid       IN  bigint,
+		enabled     IN  boolean DEFAULT true)
+    LANGUAGE PLPGSQL
+    AS $$
+BEGIN
+    UPDATE dbms_job.all_scheduled_jobs SET continue_on_error = enabled WHERE job = jobid;
+    IF NOT FOUND THEN
+        UPDATE dbms_job.all_async_jobs SET continue_on_error = enabled WHERE job = jobid;
+    END IF;
+    IF NOT FOUND THEN
+        RAISE EXCEPTION 'null_value_not_allowed' USING detail = 'job number is not a job in the job queue';
+    END IF;
+END;
+$$;
+
+COMMENT ON PROCEDURE dbms_job.continue_on_error(bigint,boolean)
+    IS 'Runs the statements of a job independently, continuing after a failing one';
+REVOKE ALL ON PROCEDURE dbms_job.continue_on_error FROM PUBLIC;
+
+CREATE PROCEDURE dbms_job.resource_key(
+		jobid       IN  bigint,
+		key         IN  text)
+    LANGUAGE PLPGSQL
+    AS $$
+BEGIN
+    UPDATE dbms_job.all_scheduled_jobs SET resource_key = key WHERE job = jobid;
+    IF NOT FOUND THEN
+        UPDATE dbms_job.all_async_jobs SET resource_key = key WHERE job = jobid;
+    END IF;
+    IF NOT FOUND THEN
+        RAISE EXCEPTION 'null_value_not_allowed' USING detail = 'job number is not a job in the job queue';
+    END IF;
+END;
+$$;
+
+COMMENT ON PROCEDURE dbms_job.resource_key(bigint,text)
+    IS 'Sets the key serializing the runs of jobs that share a resource';
+REVOKE ALL ON PROCEDURE dbms_job.resource_key FROM PUBLIC;
+
+CREATE PROCEDURE dbms_job.job_class(
+		jobid       IN  bigint,
+		class       IN  text)
+    LANGUAGE PLPGSQL
+    AS $$
+BEGIN
+    UPDATE dbms_job.all_scheduled_jobs SET job_class = class WHERE job = jobid;
+    IF NOT FOUND THEN
+        UPDATE dbms_job.all_async_jobs SET job_class = class WHERE job = jobid;
+    END IF;
+    IF NOT FOUND THEN
+        RAISE EXCEPTION 'null_value_not_allowed' USING detail = 'job number is not a job in the job queue';
+    END IF;
+END;
+$$;
+
+COMMENT ON PROCEDURE dbms_job.job_class(bigint,text)
+    IS 'Sets the class of a job, whose setting limits the jobs of the class running at the same time';
+REVOKE ALL ON PROCEDURE dbms_job.job_class FROM PUBLIC;
+
+CREATE PROCEDURE dbms_job.job_type(
+		jobid       IN  bigint,
+		type        IN  text,
+		osuser      IN  text DEFAULT NULL)
+    LANGUAGE PLPGSQL
+    AS $$
+BEGIN
+    IF upper(type) NOT IN ('PLSQL_BLOCK', 'EXECUTABLE') THEN
+        RAISE EXCEPTION 'invalid job type "%"', type USING ERRCODE = '22023', hint = 'job type must be PLSQL_BLOCK or EXECUTABLE';
+    END IF;
+    UPDATE dbms_job.all_scheduled_jobs SET job_type = upper(type), os_user = osuser WHERE job = jobid;
+    IF NOT FOUND THEN
+        UPDATE dbms_job.all_async_jobs SET job_type = upper(type), os_user = osuser WHERE job = jobid;
+    END IF;
+    IF NOT FOUND THEN
+        RAISE EXCEPTION 'null_value_not_allowed' USING detail = 'job number is not a job in the job queue';
+    END IF;
+END;
+$$;
+
+COMMENT ON PROCEDURE dbms_job.job_type(bigint,text,text)
+    IS 'Sets the type of a job, a PL/pgSQL block or an operating system command, and the operating system user of a command';
+REVOKE ALL ON PROCEDURE dbms_job.job_type FROM PUBLIC;
+
+CREATE PROCEDURE dbms_job.add_dependency(
+		jobid         IN  bigint,
+		prerequisite  IN  bigint,
+		condition     IN  text DEFAULT 'SUCCEEDED')
+    LANGUAGE PLPGSQL
+    AS $$
+BEGIN
+    IF upper($3) NOT IN ('SUCCEEDED', 'FAILED') THEN
+        RAISE EXCEPTION 'invalid dependency condition "%": SUCCEEDED or FAILED expected', $3 USING ERRCODE = '22023';
+    END IF;
+    IF $1 = $2 THEN
+        RAISE EXCEPTION 'job % can not depend on itself', $1 USING ERRCODE = '22023';
+    END IF;
+    IF NOT EXISTS (SELECT FROM dbms_job.all_scheduled_jobs WHERE job = $1)
+       OR NOT EXISTS (SELECT FROM dbms_job.all_scheduled_jobs WHERE job = $2) THEN
+        RAISE EXCEPTION 'null_value_not_allowed' USING detail = 'job number is not a job in the job queue';
+    END IF;
+    INSERT INTO dbms_job.all_job_dependencies (job, prerequisite, condition)
+        VALUES ($1, $2, upper($3)) ON CONFLICT DO NOTHING;
+END;
+$$;
+
+COMMENT ON PROCEDURE dbms_job.add_dependency(bigint,bigint,text)
+    IS 'Makes a job run after a successful or failed run of another job';
+REVOKE ALL ON PROCEDURE dbms_job.add_dependency FROM PUBLIC;
+
+CREATE PROCEDURE dbms_job.remove_dependency(
+		jobid         IN  bigint,
+		prerequisite  IN  bigint)
+    LANGUAGE PLPGSQL
+    AS $$
+BEGIN
+    DELETE FROM dbms_job.all_job_dependencies d WHERE d.job = $1 AND d.prerequisite = $2;
+    IF NOT FOUND THEN
+        RAISE EXCEPTION 'null_value_not_allowed' USING detail = 'job does not depend on this job';
+    END IF;
+END;
+$$;
+
+COMMENT ON PROCEDURE dbms_job.remove_dependency(bigint,bigint)
+    IS 'Removes the dependencies of a job on another job';
+REVOKE ALL ON PROCEDURE dbms_job.remove_dependency FROM PUBLIC;
+
+CREATE PROCEDURE dbms_job.max_failures(
+		jobid       IN  bigint,
+		failures    IN  integer)
+    LANGUAGE PLPGSQL
+    AS $$
+BEGIN
+    UPDATE dbms_job.all_scheduled_jobs SET max_failures = $2 WHERE job = jobid;
+    IF NOT FOUND THEN
+        RAISE EXCEPTION 'null_value_not_allowed' USING detail = 'job number is not a job in the job queue';
+    END IF;
+END;
+$$;
+
+COMMENT ON PROCEDURE dbms_job.max_failures(bigint,integer)
+    IS 'Sets the number of consecutive failed runs after which the scheduler marks a job broken';
+REVOKE ALL ON PROCEDURE dbms_job.max_failures FROM PUBLIC;
+
+CREATE PROCEDURE dbms_job.max_run_duration(
+		jobid       IN  bigint,
+		duration    IN  interval)
+    LANGUAGE PLPGSQL
+    AS $$
+BEGIN
+    UPDATE dbms_job.all_scheduled_jobs SET max_run_duration = duration WHERE job = jobid;
+    IF NOT FOUND THEN
+        UPDATE dbms_job.all_async_jobs SET max_run_duration = duration WHERE job = jobid;
+    END IF;
+    IF NOT FOUND THEN
+        RAISE EXCEPTION 'null_value_not_allowed' USING detail = 'job number is not a job in the job queue';
+    END IF;
+END;
+$$;
+
+COMMENT ON PROCEDURE dbms_job.max_run_duration(bigint,interval)
+    IS 'Sets the run time after which the scheduler cancels a run of the job';
+REVOKE ALL ON PROCEDURE dbms_job.max_run_duration FROM PUBLIC;
+
+CREATE PROCEDURE dbms_job.priority(
+		jobid       IN  bigint,
+		priority    IN  integer)
+    LANGUAGE PLPGSQL
+    AS $$
+BEGIN
+    IF $2 IS NULL OR $2 NOT BETWEEN 1 AND 5 THEN
+        RAISE EXCEPTION 'priority must be between 1 and 5: %', $2 USING ERRCODE = '22023';
+    END IF;
+    UPDATE dbms_job.all_scheduled_jobs SET priority = $2 WHERE job = jobid;
+    IF NOT FOUND THEN
+        UPDATE dbms_job.all_async_jobs SET priority = $2 WHERE job = jobid;
+    END IF;
+    IF NOT FOUND THEN
+        RAISE EXCEPTION 'null_value_not_allowed' USING detail = 'job number is not a job in the job queue';
+    END IF;
+END;
+$$;
+
+COMMENT ON PROCEDURE dbms_job.priority(bigint,integer)
+    IS 'Sets the priority of a job, from 1 (highest) to 5 (lowest)';
+REVOKE ALL ON PROCEDURE dbms_job.priority FROM PUBLIC;
+
+CREATE PROCEDURE dbms_job.retry_delay(
+		jobid       IN  bigint,
+		delay       IN  interval)
+    LANGUAGE PLPGSQL
+    AS $$
+BEGIN
+    UPDATE dbms_job.all_scheduled_jobs SET retry_delay = delay WHERE job = jobid;
+    IF NOT FOUND THEN
+        RAISE EXCEPTION 'null_value_not_allowed' USING detail = 'job number is not a job in the job queue';
+    END IF;
+END;
+$$;
+
+COMMENT ON PROCEDURE dbms_job.retry_delay(bigint,interval)
+    IS 'Sets how long after a failed run a job is retried';
+REVOKE ALL ON PROCEDURE dbms_job.retry_delay FROM PUBLIC;
+
+CREATE PROCEDURE dbms_job.remove(
+		jobid        IN  bigint)
+    LANGUAGE PLPGSQL
+    AS $$
+BEGIN
+    DELETE FROM dbms_job.all_scheduled_jobs WHERE job = jobid;
+    IF NOT FOUND THEN
+        RAISE EXCEPTION 'null_value_not_allowed' USING detail = 'job number is not a job in the job queue';
+    END IF;
+    DELETE FROM dbms_job.all_job_dependencies WHERE job = jobid OR prerequisite = jobid;
+END;
+$$;
+
+COMMENT ON PROCEDURE dbms_job.remove(bigint)
+    IS 'Removes specified job from the job queue';
+REVOKE ALL ON PROCEDURE dbms_job.remove FROM PUBLIC;
+
+CREATE PROCEDURE dbms_job.run(
+		jobid   IN bigint,
+		force   IN boolean DEFAULT false)
+    LANGUAGE PLPGSQL
+    AS $$
+DECLARE
+    v_what    text;
+    tmp_what  text;
+    start_t   timestamp with time zone;
+    end_t     timestamp with time zone;
+    v_state   text;
+    v_msg     text;
+    v_detail  text;
+    v_hint    text;
+    v_context text;
+BEGIN
+    IF jobid IS NULL THEN
+	RETURN;
+    END IF;
+    -- Get the job definition
+    SELECT what INTO v_what FROM dbms_job.all_scheduled_jobs WHERE job = jobid;
+    IF v_what IS NULL THEN
+        RAISE EXCEPTION 'null_value_not_allowed' USING detail = 'job number is not a job in the job queue';
+    END IF;
+    -- When force is false execute the job immediatly in foreground
+    IF NOT force THEN
+        start_t :=  clock_timestamp();
+	-- Remove BEGIN/END from the code
+	SELECT regexp_replace(v_what, 'BEGIN\s+(.*)\s*END;', '\1', 'i') INTO tmp_what;
+        UPDATE dbms_job.all_scheduled_jobs SET this_date = start_t WHERE job = jobid;
+        BEGIN
+    	EXECUTE tmp_what;
+        EXCEPTION
+    	WHEN others THEN
+    	    -- Increase the failure count
+    	    UPDATE dbms_job.all_scheduled_jobs SET
+	        failures = failures + 1
+	    WHERE job = jobid;
+	    -- Rethrow the exception
+	    RAISE;
+        END;
+        end_t :=  clock_timestamp();
+        -- Update job's statistics
+        UPDATE dbms_job.all_scheduled_jobs SET 
+            last_date = end_t,
+            this_date = NULL,
+            total_time = total_time + ((EXTRACT(EPOCH FROM end_t) - EXTRACT(EPOCH FROM start_t)) || ' seconds')::interval,
+            failures = 0,
+            instance = instance+1,
+            broken = false,
+	    next_date = dbms_job.get_next_date(interval)
+        WHERE job = jobid;
+	-- No write to history table in foreground mode
+    ELSE
+        -- Execute the job in background by submitting an asynchronous job
+	SELECT dbms_job.submit(v_what) INTO jobid;
+    END IF;
+END;
+$$;
+COMMENT ON PROCEDURE dbms_job.run(bigint, boolean)
+    IS 'Forces a specified job to run immediatly. It runs even if it is broken';
+REVOKE ALL ON PROCEDURE dbms_job.run FROM PUBLIC;
+
+CREATE FUNCTION dbms_job.submit(
+		jobid         OUT   bigint,
+		what          IN    text,
+		next_date     IN    timestamp with time zone DEFAULT current_timestamp,
+		job_interval  IN    text DEFAULT NULL,
+		no_parse      IN    boolean DEFAULT false)
+    RETURNS bigint
+    LANGUAGE PLPGSQL
+    AS $$
+BEGIN
+    -- interval must be in the future
+    IF next_date < current_timestamp THEN
+        RAISE EXCEPTION 'next_date must be a time in the future: %', next_date USING ERRCODE = '23420';
+    END IF;
+    -- A cron or calendaring expression is checked now rather than when
+    -- the job is claimed
+    IF dbms_job.is_cron(job_interval) THEN
+        PERFORM dbms_job.cron_next_date(job_interval);
+    ELSIF dbms_job.is_calendar(job_interval) THEN
+        PERFORM dbms_job.calendar_next_date(job_interval);
+    END IF;
+    -- When an interval is defined this is a job to be scheduled
+    IF job_interval IS NOT NULL THEN
+        INSERT INTO dbms_job.all_scheduled_jobs (what,next_date,interval) VALUES ($2,$3,$4) RETURNING job INTO jobid;
+    ELSE
+	-- With no interval verify if the job is planned in
+	-- the future or that it must be executed immediatly
+        IF next_date > current_timestamp THEN
+            INSERT INTO dbms_job.all_scheduled_jobs (what,next_date,interval) VALUES ($2,$3,$4) RETURNING job INTO jobid;
+        ELSE
+            -- This is an immediate asynchronous execution, use the special queue
+            INSERT INTO dbms_job.all_async_jobs (what) VALUES ($2) RETURNING job INTO jobid;
+        END IF;
+    END IF;
+END;
+$$;
+COMMENT ON FUNCTION dbms_job.submit(text,timestamp with time zone,text,boolean)
+    IS 'Submits a new job to the job queue.';
+REVOKE ALL ON FUNCTION dbms_job.submit FROM PUBLIC;
+
+CREATE PROCEDURE dbms_job.what(
+		job       IN  bigint,
+		what      IN  text)
+    LANGUAGE SQL
+    AS 'UPDATE dbms_job.all_scheduled_jobs SET what=$2 WHERE job=$1';
+COMMENT ON PROCEDURE dbms_job.what(bigint,text)
+    IS 'Alters the job description for a specified job';
+REVOKE ALL ON PROCEDURE dbms_job.what FROM PUBLIC;
+
+CREATE FUNCTION dbms_job.job_scheduled_notify()
+    RETURNS trigger
+    LANGUAGE PLPGSQL
+    AS $$
+BEGIN
+    -- When a change occurs in the all_scheduled_jobs table, notify the scheduler
+    -- with a payload of the form {"op":"submit|change|remove","job":<id>} so
+    -- that it can update its schedule cache for this job only.
+    IF TG_OP = 'UPDATE' THEN
+	-- We do not notify the scheduler if it is at the origine of the UPDATE.
+        -- We increment the value of the instance column when this is an internal
+	-- update after an execution.
+        IF NEW.instance = OLD.instance THEN
+	    PERFORM pg_notify('dbms_job_scheduled_notify', json_build_object('op', 'change', 'job', NEW.job)::text);
+        END IF;
+	RETURN NEW;
+    END IF;
+    IF TG_OP = 'INSERT' THEN
+	PERFORM pg_notify('dbms_job_scheduled_notify', json_build_object('op', 'submit', 'job', NEW.job)::text);
+	RETURN NEW;
+    END IF;
+    IF TG_OP = 'DELETE' THEN
+	PERFORM pg_notify('dbms_job_scheduled_notify', json_build_object('op', 'remove', 'job', OLD.job)::text);
+	RETURN OLD;
+    END IF;
+    -- TRUNCATE, the whole cache must be invalidated
+    PERFORM pg_notify('dbms_job_scheduled_notify', json_build_object('op', 'truncate')::text);
+    RETURN NULL;
+END;
+$$;
+COMMENT ON FUNCTION dbms_job.job_scheduled_notify()
+    IS 'Notify the scheduler that the job cache must be updated';
+
+-- When there is a modification in the JOB table, tell the scheduler which job
+-- changed so it can refresh its cached schedule for that job
+CREATE TRIGGER dbms_job_scheduled_notify_trg
+    AFTER INSERT OR UPDATE OR DELETE
+    ON dbms_job.all_scheduled_jobs
+    FOR EACH ROW EXECUTE FUNCTION dbms_job.job_scheduled_notify();
+
+CREATE TRIGGER dbms_job_scheduled_truncate_notify_trg
+    AFTER TRUNCATE
+    ON dbms_job.all_scheduled_jobs
+    FOR STATEMENT EXECUTE FUNCTION dbms_job.job_scheduled_notify();
+
+CREATE FUNCTION dbms_job.job_async_notify()
+    RETURNS trigger
+    LANGUAGE PLPGSQL
+    AS $$
+DECLARE
+    jobs bigint[];
+BEGIN
+    -- When new asynchronous jobs are submitted, notify the scheduler with
+    -- their ids so that it only claims them. A larger batch is notified
+    -- with an empty payload, the scheduler then reads the whole queue.
+    SELECT array_agg(job ORDER BY job) INTO jobs FROM (SELECT job FROM new_jobs LIMIT 101) AS j;
+    IF jobs IS NULL THEN
+        RETURN NULL;
+    END IF;
+    IF cardinality(jobs) <= 100 THEN
+        PERFORM pg_notify('dbms_job_async_notify', json_build_object('jobs', jobs)::text);
+    ELSE
+        PERFORM pg_notify('dbms_job_async_notify', '');
+    END IF;
+    RETURN NULL;
+END;
+$$;
+COMMENT ON FUNCTION dbms_job.job_async_notify()
+    IS 'Notify the scheduler of the asynchronous jobs submitted';
+
+-- When there is a new asynchronous job submited
+-- to inform the daemon of the jobs to claim
+CREATE TRIGGER dbms_job_async_notify_trg
+    AFTER INSERT
+    ON dbms_job.all_async_jobs
+    REFERENCING NEW TABLE AS new_jobs
+    FOR STATEMENT EXECUTE FUNCTION dbms_job.job_async_notify();
+
+-- Values of a field of a cron expression: a list of *, numbers or names,
+-- ranges and steps, each between low and high. names, when given, are the
+-- names of the values from low.
+CREATE FUNCTION dbms_job.cron_field(field text, low integer, high integer, names text[] DEFAULT NULL)
+    RETURNS integer[]
+    LANGUAGE PLPGSQL
+    IMMUTABLE
+    AS $$
+DECLARE
+    item text;
+    range text;
+    step integer;
+    first integer;
+    last integer;
+    bounds integer[];
+    i integer;
+    result integer[] := '{}';
+BEGIN
+    FOREACH item IN ARRAY string_to_array(lower(field), ',') LOOP
+        range := split_part(item, '/', 1);
+        step := 1;
+        IF item LIKE '%/%' THEN
+            IF split_part(item, '/', 2) !~ '^[0-9]+$' OR split_part(item, '/', 2)::integer = 0 THEN
+                RAISE EXCEPTION 'invalid step in cron field "%"', field USING ERRCODE = '22023';
+            END IF;
+            step := split_part(item, '/', 2)::integer;
+        END IF;
+        IF range = '*' THEN
+            first := low;
+            last := high;
+        ELSE
+            bounds := '{}';
+            FOREACH range IN ARRAY string_to_array(range, '-') LOOP
+                IF range ~ '^[0-9]+$' THEN
+                    bounds := bounds || range::integer;
+                ELSIF array_position(names, range) IS NOT NULL THEN
+                    bounds := bounds || (low + array_position(names, range) - 1);
+                ELSE
+                    RAISE EXCEPTION 'invalid value "%" in cron field "%"', range, field USING ERRCODE = '22023';
+                END IF;
+            END LOOP;
+            IF cardinality(bounds) NOT IN (1, 2) THEN
+                RAISE EXCEPTION 'invalid range in cron field "%"', field USING ERRCODE = '22023';
+            END IF;
+            first := bounds[1];
+            -- a single value with a step runs up to the end: 5/15 is 5-59/15
+            last := CASE WHEN cardinality(bounds) = 2 THEN bounds[2]
+                         WHEN item LIKE '%/%' THEN high
+                         ELSE bounds[1] END;
+        END IF;
+        IF first < low OR last > high OR first > last THEN
+            RAISE EXCEPTION 'cron field "%" is out of range %-%', field, low, high USING ERRCODE = '22023';
+        END IF;
+        FOR i IN SELECT generate_series(first, last, step) LOOP
+            result := result || i;
+        END LOOP;
+    END LOOP;
+    RETURN result;
+END;
+$$;
+COMMENT ON FUNCTION dbms_job.cron_field(text, integer, integer, text[])
+    IS 'Values of a field of a cron expression';
+
+-- First date after a given one matching a cron expression, on the wall
+-- clock of the session time zone.
+CREATE FUNCTION dbms_job.cron_next_date(expr text, after timestamp with time zone DEFAULT current_timestamp)
+    RETURNS timestamp with time zone
+    LANGUAGE PLPGSQL
+    STABLE
+    AS $$
+DECLARE
+    fields text[];
+    minutes integer[];
+    hours integer[];
+    days integer[];
+    months integer[];
+    weekdays integer[];
+    -- with a day of month and a day of week both restricted, either matches
+    either_day boolean;
+    day_ok boolean;
+    t timestamp;
+    latest timestamp;
+BEGIN
+    fields := regexp_split_to_array(trim(CASE lower(trim(expr))
+        WHEN '@yearly' THEN '0 0 1 1 *'
+        WHEN '@annually' THEN '0 0 1 1 *'
+        WHEN '@monthly' THEN '0 0 1 * *'
+        WHEN '@weekly' THEN '0 0 * * 0'
+        WHEN '@daily' THEN '0 0 * * *'
+        WHEN '@midnight' THEN '0 0 * * *'
+        WHEN '@hourly' THEN '0 * * * *'
+        ELSE expr END), '\s+');
+    IF cardinality(fields) <> 5 THEN
+        RAISE EXCEPTION 'invalid cron expression "%": minute hour day month weekday expected', expr USING ERRCODE = '22023';
+    END IF;
+    minutes := dbms_job.cron_field(fields[1], 0, 59);
+    hours := dbms_job.cron_field(fields[2], 0, 23);
+    days := dbms_job.cron_field(fields[3], 1, 31);
+    months := dbms_job.cron_field(fields[4], 1, 12,
+        '{jan,feb,mar,apr,may,jun,jul,aug,sep,oct,nov,dec}');
+    weekdays := dbms_job.cron_field(fields[5], 0, 7, '{sun,mon,tue,wed,thu,fri,sat}');
+    -- 7 is Sunday too
+    IF 7 = ANY(weekdays) THEN
+        weekdays := weekdays || 0;
+    END IF;
+    either_day := fields[3] NOT LIKE '*%' AND fields[5] NOT LIKE '*%';
+
+    t := date_trunc('minute', after::timestamp) + interval '1 minute';
+    latest := t + interval '30 years';
+    WHILE t <= latest LOOP
+        IF NOT extract(month FROM t)::integer = ANY(months) THEN
+            t := date_trunc('month', t) + interval '1 month';
+            CONTINUE;
+        END IF;
+        day_ok := CASE WHEN either_day
+            THEN extract(day FROM t)::integer = ANY(days) OR extract(dow FROM t)::integer = ANY(weekdays)
+            ELSE extract(day FROM t)::integer = ANY(days) AND extract(dow FROM t)::integer = ANY(weekdays) END;
+        IF NOT day_ok THEN
+            t := date_trunc('day', t) + interval '1 day';
+            CONTINUE;
+        END IF;
+        IF NOT extract(hour FROM t)::integer = ANY(hours) THEN
+            t := date_trunc('hour', t) + interval '1 hour';
+            CONTINUE;
+        END IF;
+        IF NOT extract(minute FROM t)::integer = ANY(minutes) THEN
+            t := t + interval '1 minute';
+            CONTINUE;
+        END IF;
+        -- a wall-clock time repeated when the clocks go back may map
+        -- before the date given
+        IF t::timestamp with time zone > after THEN
+            RETURN t::timestamp with time zone;
+        END IF;
+        t := t + interval '1 minute';
+    END LOOP;
+    RAISE EXCEPTION 'cron expression "%" matches no date', expr USING ERRCODE = '22023';
+END;
+$$;
+COMMENT ON FUNCTION dbms_job.cron_next_date(text, timestamp with time zone)
+    IS 'First date after the given one matching a cron expression';
+
+-- Whether a job interval is a cron expression rather than a SQL expression:
+-- five fields of digits, names and the * , - / signs, or a @ macro.
+CREATE FUNCTION dbms_job.is_cron(text)
+    RETURNS boolean
+    LANGUAGE SQL
+    IMMUTABLE
+    AS $$ SELECT $1 ~ '^\s*(@[a-zA-Z]+|[0-9a-zA-Z*,/-]+(\s+[0-9a-zA-Z*,/-]+){4})\s*$' $$;
+COMMENT ON FUNCTION dbms_job.is_cron(text)
+    IS 'Whether a job interval is a cron expression';
+
+-- Values of a clause of a calendaring expression: a list of numbers or
+-- names, each between low and high; 0 is refused when low is negative,
+-- a negative value counting from the end. names, when given, are the names
+-- of the values from low.
+CREATE FUNCTION dbms_job.calendar_values(clause text, list text, low integer, high integer, names text[] DEFAULT NULL)
+    RETURNS integer[]
+    LANGUAGE PLPGSQL
+    IMMUTABLE
+    AS $$
+DECLARE
+    item text;
+    v integer;
+    result integer[] := '{}';
+BEGIN
+    FOREACH item IN ARRAY string_to_array(upper(list), ',') LOOP
+        IF item ~ '^[+-]?[0-9]{1,4}$' THEN
+            v := item::integer;
+        ELSIF array_position(names, item) IS NOT NULL THEN
+            v := low + array_position(names, item) - 1;
+        ELSE
+            RAISE EXCEPTION 'invalid value "%" in calendaring clause %', item, clause USING ERRCODE = '22023';
+        END IF;
+        IF v < low OR v > high OR (v = 0 AND low < 0) THEN
+            RAISE EXCEPTION 'calendaring clause % is out of range % to %', clause, low, high USING ERRCODE = '22023';
+        END IF;
+        result := result || v;
+    END LOOP;
+    RETURN result;
+END;
+$$;
+COMMENT ON FUNCTION dbms_job.calendar_values(text, text, integer, integer, text[])
+    IS 'Values of a clause of a calendaring expression';
+
+-- Whether a day matches the BYMONTH, BYMONTHDAY and BYDAY clauses of a
+-- calendaring expression. weekdays are ISO days of week, 1 for Monday,
+-- and nths their week in the month, or in the year when in_year, 0 for
+-- every week, negative from the end.
+CREATE FUNCTION dbms_job.calendar_day(d date, months integer[], monthdays integer[], weekdays integer[], nths integer[], in_year boolean)
+    RETURNS boolean
+    LANGUAGE SQL
+    IMMUTABLE
+    AS $$
+SELECT (months IS NULL OR extract(month FROM d)::integer = ANY(months))
+   AND (monthdays IS NULL
+        OR extract(day FROM d)::integer = ANY(monthdays)
+        OR extract(day FROM d)::integer - extract(day FROM date_trunc('month', d) + interval '1 month - 1 day')::integer - 1 = ANY(monthdays))
+   AND (weekdays IS NULL OR EXISTS (
+        SELECT FROM unnest(weekdays, nths) AS w(dow, nth),
+             LATERAL (SELECT CASE WHEN in_year THEN extract(doy FROM d) ELSE extract(day FROM d) END::integer AS pos,
+                             CASE WHEN in_year THEN extract(doy FROM date_trunc('year', d) + interval '1 year - 1 day')
+                                  ELSE extract(day FROM date_trunc('month', d) + interval '1 month - 1 day') END::integer AS len) AS p
+        WHERE w.dow = extract(isodow FROM d)
+          AND (w.nth = 0
+               OR w.nth > 0 AND (p.pos - 1) / 7 + 1 = w.nth
+               OR w.nth < 0 AND (p.len - p.pos) / 7 + 1 = -w.nth)))
+$$;
+COMMENT ON FUNCTION dbms_job.calendar_day(date, integer[], integer[], integer[], integer[], boolean)
+    IS 'Whether a day matches the day clauses of a calendaring expression';
+
+-- First date after a given one matching an Oracle calendaring expression,
+-- FREQ=DAILY;BYHOUR=2;BYMINUTE=30, on the wall clock of the session time
+-- zone. The periods of INTERVAL are counted from the one holding the date
+-- given, and the parts of the date no clause sets are the first of their
+-- period: midnight, the first day of the month, Monday.
+CREATE FUNCTION dbms_job.calendar_next_date(expr text, after timestamp with time zone DEFAULT current_timestamp)
+    RETURNS timestamp with time zone
+    LANGUAGE PLPGSQL
+    STABLE
+    AS $$
+DECLARE
+    clause text;
+    name text;
+    list text;
+    item text;
+    parts text[];
+    freq text;
+    step integer := 1;
+    months integer[];
+    monthdays integer[];
+    weekdays integer[];
+    nths integer[];
+    hours integer[];
+    minutes integer[];
+    seconds integer[];
+    in_year boolean;
+    unit interval;
+    period timestamp;
+    skip_to timestamp;
+    latest timestamp;
+    candidate timestamp;
+BEGIN
+    FOREACH clause IN ARRAY string_to_array(upper(regexp_replace(expr, '\s', '', 'g')), ';') LOOP
+        CONTINUE WHEN clause = '';
+        name := split_part(clause, '=', 1);
+        list := substr(clause, length(name) + 2);
+        IF clause NOT LIKE '%=%' OR list = '' THEN
+            RAISE EXCEPTION 'invalid calendaring clause "%"', clause USING ERRCODE = '22023';
+        END IF;
+        CASE name
+        WHEN 'FREQ' THEN
+            freq := list;
+            unit := CASE freq
+                WHEN 'YEARLY' THEN interval '1 year'
+                WHEN 'MONTHLY' THEN interval '1 month'
+                WHEN 'WEEKLY' THEN interval '1 week'
+                WHEN 'DAILY' THEN interval '1 day'
+                WHEN 'HOURLY' THEN interval '1 hour'
+                WHEN 'MINUTELY' THEN interval '1 minute'
+                WHEN 'SECONDLY' THEN interval '1 second' END;
+            IF unit IS NULL THEN
+                RAISE EXCEPTION 'invalid calendaring frequency "%"', freq USING ERRCODE = '22023';
+            END IF;
+        WHEN 'INTERVAL' THEN
+            step := (dbms_job.calendar_values(name, list, 1, 999))[1];
+        WHEN 'BYMONTH' THEN
+            months := dbms_job.calendar_values(name, list, 1, 12,
+                '{JAN,FEB,MAR,APR,MAY,JUN,JUL,AUG,SEP,OCT,NOV,DEC}');
+        WHEN 'BYMONTHDAY' THEN
+            monthdays := dbms_job.calendar_values(name, list, -31, 31);
+        WHEN 'BYDAY' THEN
+            weekdays := '{}';
+            nths := '{}';
+            FOREACH item IN ARRAY string_to_array(list, ',') LOOP
+                parts := regexp_match(item, '^([+-]?[0-9]{1,2})?(MON|TUE|WED|THU|FRI|SAT|SUN)$');
+                IF parts IS NULL OR coalesce(parts[1]::integer, 1) = 0 THEN
+                    RAISE EXCEPTION 'invalid value "%" in calendaring clause BYDAY', item USING ERRCODE = '22023';
+                END IF;
+                weekdays := weekdays || array_position('{MON,TUE,WED,THU,FRI,SAT,SUN}'::text[], parts[2]);
+                nths := nths || coalesce(parts[1]::integer, 0);
+            END LOOP;
+        WHEN 'BYHOUR' THEN
+            hours := dbms_job.calendar_values(name, list, 0, 23);
+        WHEN 'BYMINUTE' THEN
+            minutes := dbms_job.calendar_values(name, list, 0, 59);
+        WHEN 'BYSECOND' THEN
+            seconds := dbms_job.calendar_values(name, list, 0, 59);
+        ELSE
+            RAISE EXCEPTION 'unsupported calendaring clause "%"', name USING ERRCODE = '22023';
+        END CASE;
+    END LOOP;
+    IF freq IS NULL THEN
+        RAISE EXCEPTION 'invalid calendaring expression "%": FREQ expected', expr USING ERRCODE = '22023';
+    END IF;
+    IF freq NOT IN ('YEARLY', 'MONTHLY') AND 0 <> ANY(nths) THEN
+        RAISE EXCEPTION 'a week number in BYDAY needs FREQ=MONTHLY or FREQ=YEARLY' USING ERRCODE = '22023';
+    END IF;
+    -- without a day clause, the first day of the year, month or week
+    IF monthdays IS NULL AND weekdays IS NULL THEN
+        IF freq = 'YEARLY' AND months IS NULL THEN
+            months := '{1}';
+        END IF;
+        IF freq IN ('YEARLY', 'MONTHLY') THEN
+            monthdays := '{1}';
+        ELSIF freq = 'WEEKLY' THEN
+            weekdays := '{1}';
+            nths := '{0}';
+        END IF;
+    END IF;
+    in_year := freq = 'YEARLY' AND months IS NULL;
+
+    period := date_trunc(CASE freq WHEN 'YEARLY' THEN 'year' WHEN 'MONTHLY' THEN 'month' WHEN 'WEEKLY' THEN 'week'
+        WHEN 'DAILY' THEN 'day' WHEN 'HOURLY' THEN 'hour' WHEN 'MINUTELY' THEN 'minute' ELSE 'second' END,
+        after::timestamp);
+    latest := period + interval '30 years';
+    WHILE period <= latest LOOP
+        IF freq = 'DAILY' AND NOT dbms_job.calendar_day(period::date, months, NULL, NULL, NULL, false) THEN
+            -- a month out of BYMONTH is skipped whole
+            skip_to := date_trunc('month', period) + interval '1 month';
+        ELSIF freq IN ('YEARLY', 'MONTHLY', 'WEEKLY', 'DAILY') THEN
+            SELECT min(c) INTO candidate
+            FROM (SELECT d + make_interval(hours => h, mins => m, secs => s) AS c
+                  FROM generate_series(period, period + unit - interval '1 day', interval '1 day') AS d,
+                       unnest(coalesce(hours, '{0}')) AS h,
+                       unnest(coalesce(minutes, '{0}')) AS m,
+                       unnest(coalesce(seconds, '{0}')) AS s
+                  WHERE dbms_job.calendar_day(d::date, months, monthdays, weekdays, nths, in_year)) AS candidates
+            -- a wall-clock time repeated when the clocks go back may map
+            -- before the date given
+            WHERE c::timestamp with time zone > after;
+            IF candidate IS NOT NULL THEN
+                RETURN candidate::timestamp with time zone;
+            END IF;
+            period := period + step * unit;
+            CONTINUE;
+        -- below a day, the period sets the hour, minute or second and the
+        -- clauses of these only filter the periods
+        ELSIF NOT dbms_job.calendar_day(period::date, months, monthdays, weekdays, nths, false) THEN
+            skip_to := date_trunc('day', period) + interval '1 day';
+        ELSIF hours IS NOT NULL AND NOT extract(hour FROM period)::integer = ANY(hours) THEN
+            skip_to := date_trunc('hour', period) + interval '1 hour';
+        ELSIF freq = 'HOURLY' THEN
+            SELECT min(c) INTO candidate
+            FROM (SELECT period + make_interval(mins => m, secs => s) AS c
+                  FROM unnest(coalesce(minutes, '{0}')) AS m, unnest(coalesce(seconds, '{0}')) AS s) AS candidates
+            WHERE c::timestamp with time zone > after;
+            IF candidate IS NOT NULL THEN
+                RETURN candidate::timestamp with time zone;
+            END IF;
+            skip_to := period + unit;
+        ELSIF minutes IS NOT NULL AND NOT extract(minute FROM period)::integer = ANY(minutes) THEN
+            skip_to := date_trunc('minute', period) + interval '1 minute';
+        ELSIF freq = 'MINUTELY' THEN
+            SELECT min(c) INTO candidate
+            FROM (SELECT period + make_interval(secs => s) AS c
+                  FROM unnest(coalesce(seconds, '{0}')) AS s) AS candidates
+            WHERE c::timestamp with time zone > after;
+            IF candidate IS NOT NULL THEN
+                RETURN candidate::timestamp with time zone;
+            END IF;
+            skip_to := period + unit;
+        ELSIF (seconds IS NULL OR extract(second FROM period)::integer = ANY(seconds))
+              AND period::timestamp with time zone > after THEN
+            RETURN period::timestamp with time zone;
+        ELSE
+            skip_to := period + unit;
+        END IF;
+        -- first period of the INTERVAL steps from here at or after skip_to
+        period := period + step * unit
+            * ceil(extract(epoch FROM skip_to - period) / extract(epoch FROM step * unit));
+    END LOOP;
+    RAISE EXCEPTION 'calendaring expression "%" matches no date', expr USING ERRCODE = '22023';
+END;
+$$;
+COMMENT ON FUNCTION dbms_job.calendar_next_date(text, timestamp with time zone)
+    IS 'First date after the given one matching an Oracle calendaring expression';
+
+-- Whether a job interval is an Oracle calendaring expression rather than a
+-- SQL expression: FREQ= then letters, digits and the = ; , + - signs.
+CREATE FUNCTION dbms_job.is_calendar(text)
+    RETURNS boolean
+    LANGUAGE SQL
+    IMMUTABLE
+    AS $$ SELECT $1 ~* '^\s*freq\s*=[a-z0-9=;,+\s-]*$' $$;
+COMMENT ON FUNCTION dbms_job.is_calendar(text)
+    IS 'Whether a job interval is an Oracle calendaring expression';
+
+CREATE FUNCTION dbms_job.get_next_date(text)
+    RETURNS timestamp with time zone
+    LANGUAGE PLPGSQL
+    AS $$
+DECLARE
+    next_date timestamp with time zone;
+BEGIN
+	IF dbms_job.is_cron($1) THEN
+		RETURN dbms_job.cron_next_date($1);
+	END IF;
+	IF dbms_job.is_calendar($1) THEN
+		RETURN dbms_job.calendar_next_date($1);
+	END IF;
+	EXECUTE 'SELECT '||$1 INTO next_date;
+	RETURN next_date;
+END;
+$$;
+COMMENT ON FUNCTION dbms_job.get_next_date(text)
+    IS 'Used to get the next date returned by the interval code';
+
+
+-- Version of the dbms_job objects, read by the scheduler to tell which
+-- migrations of --upgrade-schema apply when the schema was installed by
+-- --install-schema rather than as an extension.
+CREATE OR REPLACE FUNCTION dbms_job.schema_version()
+    RETURNS text
+    LANGUAGE SQL
+    IMMUTABLE
+    AS $$ SELECT '3.1.0'::text $$;
+COMMENT ON FUNCTION dbms_job.schema_version()
+    IS 'Version of the pg_dbms_job schema';
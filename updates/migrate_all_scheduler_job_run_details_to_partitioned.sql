@@ -129,6 +129,7 @@ CREATE TABLE dbms_job.all_scheduler_job_run_details (
     slave_pid integer,
     cpu_used integer,
     additional_info text,
+    result text,
     PRIMARY KEY (log_id, log_date)
 ) PARTITION BY RANGE (log_date);
 
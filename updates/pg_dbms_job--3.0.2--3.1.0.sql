@@ -0,0 +1,1052 @@
+----
+-- Upgrade pg_dbms_job from 3.0.2 to 3.1.0.
+--
+-- Run with: ALTER EXTENSION pg_dbms_job UPDATE TO '3.1.0';
+----
+
+-- Job-defined result value. A job publishes it with
+--   PERFORM set_config('pg_dbms_job.result', <text>, true);
+-- and the scheduler stores it with the run details after a successful run.
+-- On a partitioned history table the column is propagated to every partition.
+ALTER TABLE dbms_job.all_scheduler_job_run_details
+    ADD COLUMN IF NOT EXISTS result text;
+
+-- Fields of the error of a failed run, kept apart from additional_info so
+-- that failures can be grouped by message.
+ALTER TABLE dbms_job.all_scheduler_job_run_details
+    ADD COLUMN IF NOT EXISTS error_message text,
+    ADD COLUMN IF NOT EXISTS error_detail text,
+    ADD COLUMN IF NOT EXISTS error_hint text,
+    ADD COLUMN IF NOT EXISTS error_context text;
+
+-- Identifier of the run, the [run ...] tag of the scheduler log lines it
+-- produced and the pg_dbms_job.run_id setting seen by the job body.
+ALTER TABLE dbms_job.all_scheduler_job_run_details
+    ADD COLUMN IF NOT EXISTS run_id uuid;
+
+-- Host name of the scheduler that ran the job. slave_pid now holds the pid
+-- of the PostgreSQL backend of the run, to be found in pg_stat_activity and
+-- the server log, rather than the one of the scheduler.
+ALTER TABLE dbms_job.all_scheduler_job_run_details
+    ADD COLUMN IF NOT EXISTS scheduler_host text;
+
+-- Job queue schema of the job, for the schedulers serving several job queue
+-- schemas of a database with the schemas setting.
+ALTER TABLE dbms_job.all_scheduler_job_run_details
+    ADD COLUMN IF NOT EXISTS job_schema text;
+
+-- Completion chaining: the job named in on_success / on_failure is enqueued as
+-- an asynchronous job as soon as a run finishes with that outcome. The async
+-- queue carries the columns too, so a chained run can chain further.
+ALTER TABLE dbms_job.all_scheduled_jobs
+    ADD COLUMN IF NOT EXISTS on_success bigint,
+    ADD COLUMN IF NOT EXISTS on_failure bigint;
+ALTER TABLE dbms_job.all_async_jobs
+    ADD COLUMN IF NOT EXISTS on_success bigint,
+    ADD COLUMN IF NOT EXISTS on_failure bigint;
+
+-- Outcome of the last failed run, kept on the job row so that a job that keeps
+-- failing can be diagnosed without searching the run history.
+ALTER TABLE dbms_job.all_scheduled_jobs
+    ADD COLUMN IF NOT EXISTS last_error text,
+    ADD COLUMN IF NOT EXISTS last_sqlstate text,
+    ADD COLUMN IF NOT EXISTS last_failure_date timestamp with time zone;
+
+-- Consecutive runs longer than the scheduler's hard_max_run_duration; the job
+-- is marked broken when it reaches hard_max_run_overruns.
+ALTER TABLE dbms_job.all_scheduled_jobs
+    ADD COLUMN IF NOT EXISTS consecutive_overruns integer NOT NULL DEFAULT 0;
+
+-- Continue-on-error execution: the statements of the job body are run one by
+-- one under a savepoint and a failing statement does not abort the others.
+ALTER TABLE dbms_job.all_scheduled_jobs
+    ADD COLUMN IF NOT EXISTS continue_on_error boolean NOT NULL DEFAULT false;
+ALTER TABLE dbms_job.all_async_jobs
+    ADD COLUMN IF NOT EXISTS continue_on_error boolean NOT NULL DEFAULT false;
+
+-- Retry delay: after a failed run the job's next_date is brought forward to
+-- the failure date plus retry_delay when that is earlier.
+ALTER TABLE dbms_job.all_scheduled_jobs
+    ADD COLUMN IF NOT EXISTS retry_delay interval;
+
+-- Resource key: runs of jobs sharing a key are serialized with a
+-- transaction-level advisory lock taken by the scheduler.
+ALTER TABLE dbms_job.all_scheduled_jobs
+    ADD COLUMN IF NOT EXISTS resource_key text;
+ALTER TABLE dbms_job.all_async_jobs
+    ADD COLUMN IF NOT EXISTS resource_key text;
+
+-- Maximum run duration: a run longer than max_run_duration is canceled by
+-- the scheduler, then its backend terminated, and recorded as TIMED_OUT.
+ALTER TABLE dbms_job.all_scheduled_jobs
+    ADD COLUMN IF NOT EXISTS max_run_duration interval;
+ALTER TABLE dbms_job.all_async_jobs
+    ADD COLUMN IF NOT EXISTS max_run_duration interval;
+
+-- Maximum failures: the scheduler marks a job broken once it has failed this
+-- many times in a row, NULL using the scheduler's max_failures setting.
+ALTER TABLE dbms_job.all_scheduled_jobs
+    ADD COLUMN IF NOT EXISTS max_failures integer;
+
+-- Job priority: due jobs are claimed and started from priority 1 to 5.
+ALTER TABLE dbms_job.all_scheduled_jobs
+    ADD COLUMN IF NOT EXISTS priority integer NOT NULL DEFAULT 3 CHECK (priority BETWEEN 1 AND 5);
+ALTER TABLE dbms_job.all_async_jobs
+    ADD COLUMN IF NOT EXISTS priority integer NOT NULL DEFAULT 3 CHECK (priority BETWEEN 1 AND 5);
+
+-- Job classes: at most job_class.<name> jobs of a class run at the same time.
+ALTER TABLE dbms_job.all_scheduled_jobs
+    ADD COLUMN IF NOT EXISTS job_class text;
+ALTER TABLE dbms_job.all_async_jobs
+    ADD COLUMN IF NOT EXISTS job_class text;
+
+-- Job dependencies: an asynchronous copy of a scheduled job, enqueued by a
+-- chain or a dependency, keeps the number of the job it was copied from so
+-- that its own dependents are enqueued after it.
+ALTER TABLE dbms_job.all_async_jobs
+    ADD COLUMN IF NOT EXISTS source_job bigint;
+
+-- External jobs: an EXECUTABLE job runs its what as an operating system
+-- command, as os_user when set.
+ALTER TABLE dbms_job.all_scheduled_jobs
+    ADD COLUMN IF NOT EXISTS job_type text NOT NULL DEFAULT 'PLSQL_BLOCK' CHECK (job_type IN ('PLSQL_BLOCK', 'EXECUTABLE')),
+    ADD COLUMN IF NOT EXISTS os_user text;
+ALTER TABLE dbms_job.all_async_jobs
+    ADD COLUMN IF NOT EXISTS job_type text NOT NULL DEFAULT 'PLSQL_BLOCK' CHECK (job_type IN ('PLSQL_BLOCK', 'EXECUTABLE')),
+    ADD COLUMN IF NOT EXISTS os_user text;
+
+-- Dependencies between jobs: the scheduler enqueues a job asynchronously
+-- once all its SUCCEEDED prerequisites have succeeded, or one of its FAILED
+-- prerequisites has failed, since it was last enqueued.
+CREATE TABLE IF NOT EXISTS dbms_job.all_job_dependencies (
+        job bigint NOT NULL, -- job enqueued once its prerequisites completed
+        prerequisite bigint NOT NULL, -- job whose runs are waited for
+        condition text NOT NULL DEFAULT 'SUCCEEDED' CHECK (condition IN ('SUCCEEDED', 'FAILED')), -- outcome of the prerequisite's run waited for
+        satisfied_date timestamp with time zone, -- run of the prerequisite with that outcome since the job was last enqueued, NULL for none
+        log_user name DEFAULT current_user, -- user that declared the dependency
+        PRIMARY KEY (job, prerequisite, condition)
+);
+CREATE INDEX IF NOT EXISTS all_job_dependencies_prerequisite_idx ON dbms_job.all_job_dependencies (prerequisite);
+COMMENT ON TABLE dbms_job.all_job_dependencies
+    IS 'Table used to store the dependencies between jobs.';
+REVOKE ALL ON dbms_job.all_job_dependencies FROM PUBLIC;
+
+-- The user can only see the dependencies that he has declared
+ALTER TABLE dbms_job.all_job_dependencies ENABLE ROW LEVEL SECURITY;
+CREATE POLICY dbms_job_policy ON dbms_job.all_job_dependencies USING (log_user = current_user);
+
+-- Cumulative run counters maintained by the scheduler after each run.
+ALTER TABLE dbms_job.all_scheduled_jobs
+    ADD COLUMN IF NOT EXISTS run_count bigint NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS failure_count bigint NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS last_success_date timestamp with time zone;
+
+-- Status of the last run, with the run-details vocabulary.
+ALTER TABLE dbms_job.all_scheduled_jobs
+    ADD COLUMN IF NOT EXISTS last_status text;
+
+-- all_jobs keeps the Oracle column list; spell it out so the new
+-- all_scheduled_jobs columns stay out of the UNION.
+CREATE OR REPLACE VIEW dbms_job.all_jobs AS
+    SELECT job, log_user, priv_user, schema_user, last_date, last_sec,
+           this_date, this_sec, next_date, next_sec, total_time,
+           broken, interval, failures, what, nls_env, misc_env,
+           instance FROM dbms_job.all_scheduled_jobs
+    UNION
+    SELECT job, log_user, NULL priv_user, schema_user, NULL last_date, NULL last_sec,
+           NULL this_date, NULL this_sec, create_date next_date, NULL next_sec, NULL total_time,
+	   'f' broken, NULL "interval", NULL failures, what, NULL nls_env, NULL misc_env,
+	   0 instance FROM dbms_job.all_async_jobs;
+
+CREATE PROCEDURE dbms_job.on_completion(
+		jobid       IN  bigint,
+		on_success  IN  bigint,
+		on_failure  IN  bigint DEFAULT NULL)
+    LANGUAGE PLPGSQL
+    AS $$
+BEGIN
+    UPDATE dbms_job.all_scheduled_jobs SET on_success = $2, on_failure = $3 WHERE job = jobid;
+    IF NOT FOUND THEN
+        RAISE EXCEPTION 'null_value_not_allowed' USING detail = 'job number is not a job in the job queue';
+    END IF;
+END;
+$$;
+
+COMMENT ON PROCEDURE dbms_job.on_completion(bigint,bigint,bigint)
+    IS 'Sets the jobs enqueued asynchronously after a successful or failed run';
+REVOKE ALL ON PROCEDURE dbms_job.on_completion FROM PUBLIC;
+
+CREATE PROCEDURE dbms_job.continue_on_error(
+		jobid       IN  bigint,
+		enabled     IN  boolean DEFAULT true)
+    LANGUAGE PLPGSQL
+    AS $$
+BEGIN
+    UPDATE dbms_job.all_scheduled_jobs SET continue_on_error = enabled WHERE job = jobid;
+    IF NOT FOUND THEN
+        UPDATE dbms_job.all_async_jobs SET continue_on_error = enabled WHERE job = jobid;
+    END IF;
+    IF NOT FOUND THEN
+        RAISE EXCEPTION 'null_value_not_allowed' USING detail = 'job number is not a job in the job queue';
+    END IF;
+END;
+$$;
+
+COMMENT ON PROCEDURE dbms_job.continue_on_error(bigint,boolean)
+    IS 'Runs the statements of a job independently, continuing after a failing one';
+REVOKE ALL ON PROCEDURE dbms_job.continue_on_error FROM PUBLIC;
+
+CREATE PROCEDURE dbms_job.resource_key(
+		jobid       IN  bigint,
+		key         IN  text)
+    LANGUAGE PLPGSQL
+    AS $$
+BEGIN
+    UPDATE dbms_job.all_scheduled_jobs SET resource_key = key WHERE job = jobid;
+    IF NOT FOUND THEN
+        UPDATE dbms_job.all_async_jobs SET resource_key = key WHERE job = jobid;
+    END IF;
+    IF NOT FOUND THEN
+        RAISE EXCEPTION 'null_value_not_allowed' USING detail = 'job number is not a job in the job queue';
+    END IF;
+END;
+$$;
+
+COMMENT ON PROCEDURE dbms_job.resource_key(bigint,text)
+    IS 'Sets the key serializing the runs of jobs that share a resource';
+REVOKE ALL ON PROCEDURE dbms_job.resource_key FROM PUBLIC;
+
+CREATE PROCEDURE dbms_job.job_class(
+		jobid       IN  bigint,
+		class       IN  text)
+    LANGUAGE PLPGSQL
+    AS $$
+BEGIN
+    UPDATE dbms_job.all_scheduled_jobs SET job_class = class WHERE job = jobid;
+    IF NOT FOUND THEN
+        UPDATE dbms_job.all_async_jobs SET job_class = class WHERE job = jobid;
+    END IF;
+    IF NOT FOUND THEN
+        RAISE EXCEPTION 'null_value_not_allowed' USING detail = 'job number is not a job in the job queue';
+    END IF;
+END;
+$$;
+
+COMMENT ON PROCEDURE dbms_job.job_class(bigint,text)
+    IS 'Sets the class of a job, whose setting limits the jobs of the class running at the same time';
+REVOKE ALL ON PROCEDURE dbms_job.job_class FROM PUBLIC;
+
+CREATE PROCEDURE dbms_job.job_type(
+		jobid       IN  bigint,
+		type        IN  text,
+		osuser      IN  text DEFAULT NULL)
+    LANGUAGE PLPGSQL
+    AS $$
+BEGIN
+    IF upper(type) NOT IN ('PLSQL_BLOCK', 'EXECUTABLE') THEN
+        RAISE EXCEPTION 'invalid job type "%"', type USING ERRCODE = '22023', hint = 'job type must be PLSQL_BLOCK or EXECUTABLE';
+    END IF;
+    UPDATE dbms_job.all_scheduled_jobs SET job_type = upper(type), os_user = osuser WHERE job = jobid;
+    IF NOT FOUND THEN
+        UPDATE dbms_job.all_async_jobs SET job_type = upper(type), os_user = osuser WHERE job = jobid;
+    END IF;
+    IF NOT FOUND THEN
+        RAISE EXCEPTION 'null_value_not_allowed' USING detail = 'job number is not a job in the job queue';
+    END IF;
+END;
+$$;
+
+COMMENT ON PROCEDURE dbms_job.job_type(bigint,text,text)
+    IS 'Sets the type of a job, a PL/pgSQL block or an operating system command, and the operating system user of a command';
+REVOKE ALL ON PROCEDURE dbms_job.job_type FROM PUBLIC;
+
+CREATE PROCEDURE dbms_job.add_dependency(
+		jobid         IN  bigint,
+		prerequisite  IN  bigint,
+		condition     IN  text DEFAULT 'SUCCEEDED')
+    LANGUAGE PLPGSQL
+    AS $$
+BEGIN
+    IF upper($3) NOT IN ('SUCCEEDED', 'FAILED') THEN
+        RAISE EXCEPTION 'invalid dependency condition "%": SUCCEEDED or FAILED expected', $3 USING ERRCODE = '22023';
+    END IF;
+    IF $1 = $2 THEN
+        RAISE EXCEPTION 'job % can not depend on itself', $1 USING ERRCODE = '22023';
+    END IF;
+    IF NOT EXISTS (SELECT FROM dbms_job.all_scheduled_jobs WHERE job = $1)
+       OR NOT EXISTS (SELECT FROM dbms_job.all_scheduled_jobs WHERE job = $2) THEN
+        RAISE EXCEPTION 'null_value_not_allowed' USING detail = 'job number is not a job in the job queue';
+    END IF;
+    INSERT INTO dbms_job.all_job_dependencies (job, prerequisite, condition)
+        VALUES ($1, $2, upper($3)) ON CONFLICT DO NOTHING;
+END;
+$$;
+
+COMMENT ON PROCEDURE dbms_job.add_dependency(bigint,bigint,text)
+    IS 'Makes a job run after a successful or failed run of another job';
+REVOKE ALL ON PROCEDURE dbms_job.add_dependency FROM PUBLIC;
+
+CREATE PROCEDURE dbms_job.remove_dependency(
+		jobid         IN  bigint,
+		prerequisite  IN  bigint)
+    LANGUAGE PLPGSQL
+    AS $$
+BEGIN
+    DELETE FROM dbms_job.all_job_dependencies d WHERE d.job = $1 AND d.prerequisite = $2;
+    IF NOT FOUND THEN
+        RAISE EXCEPTION 'null_value_not_allowed' USING detail = 'job does not depend on this job';
+    END IF;
+END;
+$$;
+
+COMMENT ON PROCEDURE dbms_job.remove_dependency(bigint,bigint)
+    IS 'Removes the dependencies of a job on another job';
+REVOKE ALL ON PROCEDURE dbms_job.remove_dependency FROM PUBLIC;
+
+-- dbms_job.remove() drops the dependencies of the job and on the job.
+CREATE OR REPLACE PROCEDURE dbms_job.remove(
+		jobid        IN  bigint)
+    LANGUAGE PLPGSQL
+    AS $$
+BEGIN
+    DELETE FROM dbms_job.all_scheduled_jobs WHERE job = jobid;
+    IF NOT FOUND THEN
+        RAISE EXCEPTION 'null_value_not_allowed' USING detail = 'job number is not a job in the job queue';
+    END IF;
+    DELETE FROM dbms_job.all_job_dependencies WHERE job = jobid OR prerequisite = jobid;
+END;
+$$;
+
+COMMENT ON PROCEDURE dbms_job.remove(bigint)
+    IS 'Removes specified job from the job queue';
+REVOKE ALL ON PROCEDURE dbms_job.remove FROM PUBLIC;
+
+-- dbms_job.broken() resets the failures and overruns of a job enabled again,
+-- as it would otherwise be broken again by max_failures on its next failed
+-- run, or by hard_max_run_duration on its next overrun.
+CREATE OR REPLACE PROCEDURE dbms_job.broken(
+		jobid     IN  bigint,
+		broken    IN  boolean,
+		next_date IN  timestamp with time zone DEFAULT current_timestamp)
+    LANGUAGE PLPGSQL
+    AS $$
+BEGIN
+    -- interval must be in the future
+    IF next_date < current_timestamp THEN
+        RAISE EXCEPTION 'next_date must be a time in the future: %', next_date USING ERRCODE = '23420';
+    END IF;
+    -- a job enabled again starts over its counts of failures and overruns
+    UPDATE dbms_job.all_scheduled_jobs SET broken=$2,next_date=$3,
+        failures = CASE WHEN $2 THEN failures ELSE 0 END,
+        consecutive_overruns = CASE WHEN $2 THEN consecutive_overruns ELSE 0 END
+        WHERE job=$1;
+    IF NOT FOUND THEN
+        RAISE EXCEPTION 'null_value_not_allowed' USING detail = 'job number is not a job in the job queue';
+    END IF;
+END;
+$$;
+
+COMMENT ON PROCEDURE dbms_job.broken(bigint,boolean,timestamp with time zone)
+    IS 'Disables job execution. Broken jobs are never run.';
+
+CREATE PROCEDURE dbms_job.max_failures(
+		jobid       IN  bigint,
+		failures    IN  integer)
+    LANGUAGE PLPGSQL
+    AS $$
+BEGIN
+    UPDATE dbms_job.all_scheduled_jobs SET max_failures = $2 WHERE job = jobid;
+    IF NOT FOUND THEN
+        RAISE EXCEPTION 'null_value_not_allowed' USING detail = 'job number is not a job in the job queue';
+    END IF;
+END;
+$$;
+
+COMMENT ON PROCEDURE dbms_job.max_failures(bigint,integer)
+    IS 'Sets the number of consecutive failed runs after which the scheduler marks a job broken';
+REVOKE ALL ON PROCEDURE dbms_job.max_failures FROM PUBLIC;
+
+CREATE PROCEDURE dbms_job.max_run_duration(
+		jobid       IN  bigint,
+		duration    IN  interval)
+    LANGUAGE PLPGSQL
+    AS $$
+BEGIN
+    UPDATE dbms_job.all_scheduled_jobs SET max_run_duration = duration WHERE job = jobid;
+    IF NOT FOUND THEN
+        UPDATE dbms_job.all_async_jobs SET max_run_duration = duration WHERE job = jobid;
+    END IF;
+    IF NOT FOUND THEN
+        RAISE EXCEPTION 'null_value_not_allowed' USING detail = 'job number is not a job in the job queue';
+    END IF;
+END;
+$$;
+
+COMMENT ON PROCEDURE dbms_job.max_run_duration(bigint,interval)
+    IS 'Sets the run time after which the scheduler cancels a run of the job';
+REVOKE ALL ON PROCEDURE dbms_job.max_run_duration FROM PUBLIC;
+
+CREATE PROCEDURE dbms_job.priority(
+		jobid       IN  bigint,
+		priority    IN  integer)
+    LANGUAGE PLPGSQL
+    AS $$
+BEGIN
+    IF $2 IS NULL OR $2 NOT BETWEEN 1 AND 5 THEN
+        RAISE EXCEPTION 'priority must be between 1 and 5: %', $2 USING ERRCODE = '22023';
+    END IF;
+    UPDATE dbms_job.all_scheduled_jobs SET priority = $2 WHERE job = jobid;
+    IF NOT FOUND THEN
+        UPDATE dbms_job.all_async_jobs SET priority = $2 WHERE job = jobid;
+    END IF;
+    IF NOT FOUND THEN
+        RAISE EXCEPTION 'null_value_not_allowed' USING detail = 'job number is not a job in the job queue';
+    END IF;
+END;
+$$;
+
+COMMENT ON PROCEDURE dbms_job.priority(bigint,integer)
+    IS 'Sets the priority of a job, from 1 (highest) to 5 (lowest)';
+REVOKE ALL ON PROCEDURE dbms_job.priority FROM PUBLIC;
+
+CREATE PROCEDURE dbms_job.retry_delay(
+		jobid       IN  bigint,
+		delay       IN  interval)
+    LANGUAGE PLPGSQL
+    AS $$
+BEGIN
+    UPDATE dbms_job.all_scheduled_jobs SET retry_delay = delay WHERE job = jobid;
+    IF NOT FOUND THEN
+        RAISE EXCEPTION 'null_value_not_allowed' USING detail = 'job number is not a job in the job queue';
+    END IF;
+END;
+$$;
+
+COMMENT ON PROCEDURE dbms_job.retry_delay(bigint,interval)
+    IS 'Sets how long after a failed run a job is retried';
+REVOKE ALL ON PROCEDURE dbms_job.retry_delay FROM PUBLIC;
+
+-- Structured notification payloads: one {"op":...,"job":...} NOTIFY per
+-- changed row instead of one opaque NOTIFY per statement.
+CREATE OR REPLACE FUNCTION dbms_job.job_scheduled_notify()
+    RETURNS trigger
+    LANGUAGE PLPGSQL
+    AS $$
+BEGIN
+    -- When a change occurs in the all_scheduled_jobs table, notify the scheduler
+    -- with a payload of the form {"op":"submit|change|remove","job":<id>} so
+    -- that it can update its schedule cache for this job only.
+    IF TG_OP = 'UPDATE' THEN
+	-- We do not notify the scheduler if it is at the origine of the UPDATE.
+        -- We increment the value of the instance column when this is an internal
+	-- update after an execution.
+        IF NEW.instance = OLD.instance THEN
+	    PERFORM pg_notify('dbms_job_scheduled_notify', json_build_object('op', 'change', 'job', NEW.job)::text);
+        END IF;
+	RETURN NEW;
+    END IF;
+    IF TG_OP = 'INSERT' THEN
+	PERFORM pg_notify('dbms_job_scheduled_notify', json_build_object('op', 'submit', 'job', NEW.job)::text);
+	RETURN NEW;
+    END IF;
+    IF TG_OP = 'DELETE' THEN
+	PERFORM pg_notify('dbms_job_scheduled_notify', json_build_object('op', 'remove', 'job', OLD.job)::text);
+	RETURN OLD;
+    END IF;
+    -- TRUNCATE, the whole cache must be invalidated
+    PERFORM pg_notify('dbms_job_scheduled_notify', json_build_object('op', 'truncate')::text);
+    RETURN NULL;
+END;
+$$;
+COMMENT ON FUNCTION dbms_job.job_scheduled_notify()
+    IS 'Notify the scheduler that the job cache must be updated';
+
+DROP TRIGGER IF EXISTS dbms_job_scheduled_notify_trg ON dbms_job.all_scheduled_jobs;
+-- When there is a modification in the JOB table, tell the scheduler which job
+-- changed so it can refresh its cached schedule for that job
+CREATE TRIGGER dbms_job_scheduled_notify_trg
+    AFTER INSERT OR UPDATE OR DELETE
+    ON dbms_job.all_scheduled_jobs
+    FOR EACH ROW EXECUTE FUNCTION dbms_job.job_scheduled_notify();
+
+CREATE TRIGGER dbms_job_scheduled_truncate_notify_trg
+    AFTER TRUNCATE
+    ON dbms_job.all_scheduled_jobs
+    FOR STATEMENT EXECUTE FUNCTION dbms_job.job_scheduled_notify();
+
+-- Schedulers serving this database, one row per host, upserted by the
+-- scheduler daemon each time it connects. A scheduler whose heartbeat is
+-- recent owns the database: another one refuses to start.
+CREATE TABLE IF NOT EXISTS dbms_job.scheduler_registry (
+	host text PRIMARY KEY, -- host name of the machine running the scheduler
+	pid integer NOT NULL, -- process identifier of the scheduler
+	version text NOT NULL, -- version of the scheduler
+	features text[] NOT NULL DEFAULT '{}', -- optional capabilities of this scheduler build
+	start_time timestamp with time zone NOT NULL, -- date on which the scheduler was started
+	heartbeat timestamp with time zone, -- last sign of life of the scheduler, NULL once stopped
+	pending_scheduled bigint, -- due scheduled jobs not yet claimed, counted with the heartbeat
+	pending_async bigint -- queued asynchronous jobs not yet claimed, counted with the heartbeat
+);
+ALTER TABLE dbms_job.scheduler_registry
+	ADD COLUMN IF NOT EXISTS heartbeat timestamp with time zone,
+	ADD COLUMN IF NOT EXISTS pending_scheduled bigint,
+	ADD COLUMN IF NOT EXISTS pending_async bigint;
+COMMENT ON TABLE dbms_job.scheduler_registry
+    IS 'Table used to register the scheduler daemons serving this database.';
+REVOKE ALL ON dbms_job.scheduler_registry FROM PUBLIC;
+
+-- Maintenance switch: while enabled, the scheduler claims no new job so that
+-- job execution can be frozen from SQL (restores, upgrades, VACUUM FULL
+-- windows). Jobs already running are not interrupted.
+CREATE TABLE IF NOT EXISTS dbms_job.maintenance_mode (
+	id boolean PRIMARY KEY DEFAULT true CHECK (id), -- the table has a single row
+	enabled boolean NOT NULL DEFAULT false, -- true: the scheduler starts no new job
+	reason text, -- why job execution is paused, reported in the scheduler log
+	changed_by name NOT NULL DEFAULT current_user, -- user who last changed the flag
+	changed_at timestamp with time zone NOT NULL DEFAULT current_timestamp -- date of the last change
+);
+COMMENT ON TABLE dbms_job.maintenance_mode
+    IS 'Single-row switch pausing job dispatch by the scheduler.';
+INSERT INTO dbms_job.maintenance_mode DEFAULT VALUES ON CONFLICT DO NOTHING;
+REVOKE ALL ON dbms_job.maintenance_mode FROM PUBLIC;
+
+CREATE PROCEDURE dbms_job.set_maintenance_mode(
+		enabled     IN  boolean,
+		reason      IN  text DEFAULT NULL)
+    LANGUAGE PLPGSQL
+    AS $$
+BEGIN
+    UPDATE dbms_job.maintenance_mode
+        SET enabled = $1, reason = $2, changed_by = current_user, changed_at = current_timestamp;
+END;
+$$;
+
+COMMENT ON PROCEDURE dbms_job.set_maintenance_mode(boolean,text)
+    IS 'Pauses or resumes the dispatch of jobs by the scheduler';
+REVOKE ALL ON PROCEDURE dbms_job.set_maintenance_mode FROM PUBLIC;
+
+-- Configuration reloads of the scheduler, one row per reload, written by the
+-- scheduler daemon once it has reconnected with the new settings.
+CREATE TABLE IF NOT EXISTS dbms_job.config_audit (
+	id bigserial PRIMARY KEY, -- identifier of the reload event
+	audit_date timestamp with time zone NOT NULL DEFAULT current_timestamp, -- date of the reload
+	host text NOT NULL, -- host name of the machine running the scheduler
+	pid integer NOT NULL, -- process identifier of the scheduler
+	config_file text NOT NULL, -- path of the configuration file reloaded
+	config_hash text, -- SHA-256 of the configuration file, NULL if it could not be read
+	changes jsonb NOT NULL DEFAULT '{}' -- changed settings: {"name": {"old": ..., "new": ...}}
+);
+COMMENT ON TABLE dbms_job.config_audit
+    IS 'Table used to record the configuration reloads of the scheduler.';
+REVOKE ALL ON dbms_job.config_audit FROM PUBLIC;
+
+-- Lifecycle events of the scheduler daemons (start, stop, reload, lost
+-- connection, saturated worker pool), written by the daemon when
+-- scheduler_event_log is enabled, for DBAs without access to its log.
+CREATE TABLE IF NOT EXISTS dbms_job.scheduler_event_log (
+	id bigserial PRIMARY KEY, -- identifier of the event
+	event_date timestamp with time zone NOT NULL DEFAULT current_timestamp, -- date of the event, as seen by the scheduler
+	host text NOT NULL, -- host name of the machine running the scheduler
+	pid integer NOT NULL, -- process identifier of the scheduler
+	event text NOT NULL, -- start, stop, reload, connection_lost or queue_saturated
+	detail text -- description of the event
+);
+COMMENT ON TABLE dbms_job.scheduler_event_log
+    IS 'Table used to record the lifecycle events of the scheduler.';
+REVOKE ALL ON dbms_job.scheduler_event_log FROM PUBLIC;
+
+-- Cron expressions as job intervals, e.g. '0 3 * * 1-5', evaluated by
+-- dbms_job.get_next_date() with dbms_job.cron_next_date().
+-- Values of a field of a cron expression: a list of *, numbers or names,
+-- ranges and steps, each between low and high. names, when given, are the
+-- names of the values from low.
+CREATE FUNCTION dbms_job.cron_field(field text, low integer, high integer, names text[] DEFAULT NULL)
+    RETURNS integer[]
+    LANGUAGE PLPGSQL
+    IMMUTABLE
+    AS $$
+DECLARE
+    item text;
+    range text;
+    step integer;
+    first integer;
+    last integer;
+    bounds integer[];
+    i integer;
+    result integer[] := '{}';
+BEGIN
+    FOREACH item IN ARRAY string_to_array(lower(field), ',') LOOP
+        range := split_part(item, '/', 1);
+        step := 1;
+        IF item LIKE '%/%' THEN
+            IF split_part(item, '/', 2) !~ '^[0-9]+$' OR split_part(item, '/', 2)::integer = 0 THEN
+                RAISE EXCEPTION 'invalid step in cron field "%"', field USING ERRCODE = '22023';
+            END IF;
+            step := split_part(item, '/', 2)::integer;
+        END IF;
+        IF range = '*' THEN
+            first := low;
+            last := high;
+        ELSE
+            bounds := '{}';
+            FOREACH range IN ARRAY string_to_array(range, '-') LOOP
+                IF range ~ '^[0-9]+$' THEN
+                    bounds := bounds || range::integer;
+                ELSIF array_position(names, range) IS NOT NULL THEN
+                    bounds := bounds || (low + array_position(names, range) - 1);
+                ELSE
+                    RAISE EXCEPTION 'invalid value "%" in cron field "%"', range, field USING ERRCODE = '22023';
+                END IF;
+            END LOOP;
+            IF cardinality(bounds) NOT IN (1, 2) THEN
+                RAISE EXCEPTION 'invalid range in cron field "%"', field USING ERRCODE = '22023';
+            END IF;
+            first := bounds[1];
+            -- a single value with a step runs up to the end: 5/15 is 5-59/15
+            last := CASE WHEN cardinality(bounds) = 2 THEN bounds[2]
+                         WHEN item LIKE '%/%' THEN high
+                         ELSE bounds[1] END;
+        END IF;
+        IF first < low OR last > high OR first > last THEN
+            RAISE EXCEPTION 'cron field "%" is out of range %-%', field, low, high USING ERRCODE = '22023';
+        END IF;
+        FOR i IN SELECT generate_series(first, last, step) LOOP
+            result := result || i;
+        END LOOP;
+    END LOOP;
+    RETURN result;
+END;
+$$;
+COMMENT ON FUNCTION dbms_job.cron_field(text, integer, integer, text[])
+    IS 'Values of a field of a cron expression';
+
+-- First date after a given one matching a cron expression, on the wall
+-- clock of the session time zone.
+CREATE FUNCTION dbms_job.cron_next_date(expr text, after timestamp with time zone DEFAULT current_timestamp)
+    RETURNS timestamp with time zone
+    LANGUAGE PLPGSQL
+    STABLE
+    AS $$
+DECLARE
+    fields text[];
+    minutes integer[];
+    hours integer[];
+    days integer[];
+    months integer[];
+    weekdays integer[];
+    -- with a day of month and a day of week both restricted, either matches
+    either_day boolean;
+    day_ok boolean;
+    t timestamp;
+    latest timestamp;
+BEGIN
+    fields := regexp_split_to_array(trim(CASE lower(trim(expr))
+        WHEN '@yearly' THEN '0 0 1 1 *'
+        WHEN '@annually' THEN '0 0 1 1 *'
+        WHEN '@monthly' THEN '0 0 1 * *'
+        WHEN '@weekly' THEN '0 0 * * 0'
+        WHEN '@daily' THEN '0 0 * * *'
+        WHEN '@midnight' THEN '0 0 * * *'
+        WHEN '@hourly' THEN '0 * * * *'
+        ELSE expr END), '\s+');
+    IF cardinality(fields) <> 5 THEN
+        RAISE EXCEPTION 'invalid cron expression "%": minute hour day month weekday expected', expr USING ERRCODE = '22023';
+    END IF;
+    minutes := dbms_job.cron_field(fields[1], 0, 59);
+    hours := dbms_job.cron_field(fields[2], 0, 23);
+    days := dbms_job.cron_field(fields[3], 1, 31);
+    months := dbms_job.cron_field(fields[4], 1, 12,
+        '{jan,feb,mar,apr,may,jun,jul,aug,sep,oct,nov,dec}');
+    weekdays := dbms_job.cron_field(fields[5], 0, 7, '{sun,mon,tue,wed,thu,fri,sat}');
+    -- 7 is Sunday too
+    IF 7 = ANY(weekdays) THEN
+        weekdays := weekdays || 0;
+    END IF;
+    either_day := fields[3] NOT LIKE '*%' AND fields[5] NOT LIKE '*%';
+
+    t := date_trunc('minute', after::timestamp) + interval '1 minute';
+    latest := t + interval '30 years';
+    WHILE t <= latest LOOP
+        IF NOT extract(month FROM t)::integer = ANY(months) THEN
+            t := date_trunc('month', t) + interval '1 month';
+            CONTINUE;
+        END IF;
+        day_ok := CASE WHEN either_day
+            THEN extract(day FROM t)::integer = ANY(days) OR extract(dow FROM t)::integer = ANY(weekdays)
+            ELSE extract(day FROM t)::integer = ANY(days) AND extract(dow FROM t)::integer = ANY(weekdays) END;
+        IF NOT day_ok THEN
+            t := date_trunc('day', t) + interval '1 day';
+            CONTINUE;
+        END IF;
+        IF NOT extract(hour FROM t)::integer = ANY(hours) THEN
+            t := date_trunc('hour', t) + interval '1 hour';
+            CONTINUE;
+        END IF;
+        IF NOT extract(minute FROM t)::integer = ANY(minutes) THEN
+            t := t + interval '1 minute';
+            CONTINUE;
+        END IF;
+        -- a wall-clock time repeated when the clocks go back may map
+        -- before the date given
+        IF t::timestamp with time zone > after THEN
+            RETURN t::timestamp with time zone;
+        END IF;
+        t := t + interval '1 minute';
+    END LOOP;
+    RAISE EXCEPTION 'cron expression "%" matches no date', expr USING ERRCODE = '22023';
+END;
+$$;
+COMMENT ON FUNCTION dbms_job.cron_next_date(text, timestamp with time zone)
+    IS 'First date after the given one matching a cron expression';
+
+-- Whether a job interval is a cron expression rather than a SQL expression:
+-- five fields of digits, names and the * , - / signs, or a @ macro.
+CREATE FUNCTION dbms_job.is_cron(text)
+    RETURNS boolean
+    LANGUAGE SQL
+    IMMUTABLE
+    AS $$ SELECT $1 ~ '^\s*(@[a-zA-Z]+|[0-9a-zA-Z*,/-]+(\s+[0-9a-zA-Z*,/-]+){4})\s*$' $$;
+COMMENT ON FUNCTION dbms_job.is_cron(text)
+    IS 'Whether a job interval is a cron expression';
+
+-- Oracle calendaring expressions as job intervals, e.g.
+-- 'FREQ=DAILY;BYHOUR=2;BYMINUTE=30', evaluated by dbms_job.get_next_date()
+-- with dbms_job.calendar_next_date().
+-- Values of a clause of a calendaring expression: a list of numbers or
+-- names, each between low and high; 0 is refused when low is negative,
+-- a negative value counting from the end. names, when given, are the names
+-- of the values from low.
+CREATE FUNCTION dbms_job.calendar_values(clause text, list text, low integer, high integer, names text[] DEFAULT NULL)
+    RETURNS integer[]
+    LANGUAGE PLPGSQL
+    IMMUTABLE
+    AS $$
+DECLARE
+    item text;
+    v integer;
+    result integer[] := '{}';
+BEGIN
+    FOREACH item IN ARRAY string_to_array(upper(list), ',') LOOP
+        IF item ~ '^[+-]?[0-9]{1,4}$' THEN
+            v := item::integer;
+        ELSIF array_position(names, item) IS NOT NULL THEN
+            v := low + array_position(names, item) - 1;
+        ELSE
+            RAISE EXCEPTION 'invalid value "%" in calendaring clause %', item, clause USING ERRCODE = '22023';
+        END IF;
+        IF v < low OR v > high OR (v = 0 AND low < 0) THEN
+            RAISE EXCEPTION 'calendaring clause % is out of range % to %', clause, low, high USING ERRCODE = '22023';
+        END IF;
+        result := result || v;
+    END LOOP;
+    RETURN result;
+END;
+$$;
+COMMENT ON FUNCTION dbms_job.calendar_values(text, text, integer, integer, text[])
+    IS 'Values of a clause of a calendaring expression';
+
+-- Whether a day matches the BYMONTH, BYMONTHDAY and BYDAY clauses of a
+-- calendaring expression. weekdays are ISO days of week, 1 for Monday,
+-- and nths their week in the month, or in the year when in_year, 0 for
+-- every week, negative from the end.
+CREATE FUNCTION dbms_job.calendar_day(d date, months integer[], monthdays integer[], weekdays integer[], nths integer[], in_year boolean)
+    RETURNS boolean
+    LANGUAGE SQL
+    IMMUTABLE
+    AS $$
+SELECT (months IS NULL OR extract(month FROM d)::integer = ANY(months))
+   AND (monthdays IS NULL
+        OR extract(day FROM d)::integer = ANY(monthdays)
+        OR extract(day FROM d)::integer - extract(day FROM date_trunc('month', d) + interval '1 month - 1 day')::integer - 1 = ANY(monthdays))
+   AND (weekdays IS NULL OR EXISTS (
+        SELECT FROM unnest(weekdays, nths) AS w(dow, nth),
+             LATERAL (SELECT CASE WHEN in_year THEN extract(doy FROM d) ELSE extract(day FROM d) END::integer AS pos,
+                             CASE WHEN in_year THEN extract(doy FROM date_trunc('year', d) + interval '1 year - 1 day')
+                                  ELSE extract(day FROM date_trunc('month', d) + interval '1 month - 1 day') END::integer AS len) AS p
+        WHERE w.dow = extract(isodow FROM d)
+          AND (w.nth = 0
+               OR w.nth > 0 AND (p.pos - 1) / 7 + 1 = w.nth
+               OR w.nth < 0 AND (p.len - p.pos) / 7 + 1 = -w.nth)))
+$$;
+COMMENT ON FUNCTION dbms_job.calendar_day(date, integer[], integer[], integer[], integer[], boolean)
+    IS 'Whether a day matches the day clauses of a calendaring expression';
+
+-- First date after a given one matching an Oracle calendaring expression,
+-- FREQ=DAILY;BYHOUR=2;BYMINUTE=30, on the wall clock of the session time
+-- zone. The periods of INTERVAL are counted from the one holding the date
+-- given, and the parts of the date no clause sets are the first of their
+-- period: midnight, the first day of the month, Monday.
+CREATE FUNCTION dbms_job.calendar_next_date(expr text, after timestamp with time zone DEFAULT current_timestamp)
+    RETURNS timestamp with time zone
+    LANGUAGE PLPGSQL
+    STABLE
+    AS $$
+DECLARE
+    clause text;
+    name text;
+    list text;
+    item text;
+    parts text[];
+    freq text;
+    step integer := 1;
+    months integer[];
+    monthdays integer[];
+    weekdays integer[];
+    nths integer[];
+    hours integer[];
+    minutes integer[];
+    seconds integer[];
+    in_year boolean;
+    unit interval;
+    period timestamp;
+    skip_to timestamp;
+    latest timestamp;
+    candidate timestamp;
+BEGIN
+    FOREACH clause IN ARRAY string_to_array(upper(regexp_replace(expr, '\s', '', 'g')), ';') LOOP
+        CONTINUE WHEN clause = '';
+        name := split_part(clause, '=', 1);
+        list := substr(clause, length(name) + 2);
+        IF clause NOT LIKE '%=%' OR list = '' THEN
+            RAISE EXCEPTION 'invalid calendaring clause "%"', clause USING ERRCODE = '22023';
+        END IF;
+        CASE name
+        WHEN 'FREQ' THEN
+            freq := list;
+            unit := CASE freq
+                WHEN 'YEARLY' THEN interval '1 year'
+                WHEN 'MONTHLY' THEN interval '1 month'
+                WHEN 'WEEKLY' THEN interval '1 week'
+                WHEN 'DAILY' THEN interval '1 day'
+                WHEN 'HOURLY' THEN interval '1 hour'
+                WHEN 'MINUTELY' THEN interval '1 minute'
+                WHEN 'SECONDLY' THEN interval '1 second' END;
+            IF unit IS NULL THEN
+                RAISE EXCEPTION 'invalid calendaring frequency "%"', freq USING ERRCODE = '22023';
+            END IF;
+        WHEN 'INTERVAL' THEN
+            step := (dbms_job.calendar_values(name, list, 1, 999))[1];
+        WHEN 'BYMONTH' THEN
+            months := dbms_job.calendar_values(name, list, 1, 12,
+                '{JAN,FEB,MAR,APR,MAY,JUN,JUL,AUG,SEP,OCT,NOV,DEC}');
+        WHEN 'BYMONTHDAY' THEN
+            monthdays := dbms_job.calendar_values(name, list, -31, 31);
+        WHEN 'BYDAY' THEN
+            weekdays := '{}';
+            nths := '{}';
+            FOREACH item IN ARRAY string_to_array(list, ',') LOOP
+                parts := regexp_match(item, '^([+-]?[0-9]{1,2})?(MON|TUE|WED|THU|FRI|SAT|SUN)$');
+                IF parts IS NULL OR coalesce(parts[1]::integer, 1) = 0 THEN
+                    RAISE EXCEPTION 'invalid value "%" in calendaring clause BYDAY', item USING ERRCODE = '22023';
+                END IF;
+                weekdays := weekdays || array_position('{MON,TUE,WED,THU,FRI,SAT,SUN}'::text[], parts[2]);
+                nths := nths || coalesce(parts[1]::integer, 0);
+            END LOOP;
+        WHEN 'BYHOUR' THEN
+            hours := dbms_job.calendar_values(name, list, 0, 23);
+        WHEN 'BYMINUTE' THEN
+            minutes := dbms_job.calendar_values(name, list, 0, 59);
+        WHEN 'BYSECOND' THEN
+            seconds := dbms_job.calendar_values(name, list, 0, 59);
+        ELSE
+            RAISE EXCEPTION 'unsupported calendaring clause "%"', name USING ERRCODE = '22023';
+        END CASE;
+    END LOOP;
+    IF freq IS NULL THEN
+        RAISE EXCEPTION 'invalid calendaring expression "%": FREQ expected', expr USING ERRCODE = '22023';
+    END IF;
+    IF freq NOT IN ('YEARLY', 'MONTHLY') AND 0 <> ANY(nths) THEN
+        RAISE EXCEPTION 'a week number in BYDAY needs FREQ=MONTHLY or FREQ=YEARLY' USING ERRCODE = '22023';
+    END IF;
+    -- without a day clause, the first day of the year, month or week
+    IF monthdays IS NULL AND weekdays IS NULL THEN
+        IF freq = 'YEARLY' AND months IS NULL THEN
+            months := '{1}';
+        END IF;
+        IF freq IN ('YEARLY', 'MONTHLY') THEN
+            monthdays := '{1}';
+        ELSIF freq = 'WEEKLY' THEN
+            weekdays := '{1}';
+            nths := '{0}';
+        END IF;
+    END IF;
+    in_year := freq = 'YEARLY' AND months IS NULL;
+
+    period := date_trunc(CASE freq WHEN 'YEARLY' THEN 'year' WHEN 'MONTHLY' THEN 'month' WHEN 'WEEKLY' THEN 'week'
+        WHEN 'DAILY' THEN 'day' WHEN 'HOURLY' THEN 'hour' WHEN 'MINUTELY' THEN 'minute' ELSE 'second' END,
+        after::timestamp);
+    latest := period + interval '30 years';
+    WHILE period <= latest LOOP
+        IF freq = 'DAILY' AND NOT dbms_job.calendar_day(period::date, months, NULL, NULL, NULL, false) THEN
+            -- a month out of BYMONTH is skipped whole
+            skip_to := date_trunc('month', period) + interval '1 month';
+        ELSIF freq IN ('YEARLY', 'MONTHLY', 'WEEKLY', 'DAILY') THEN
+            SELECT min(c) INTO candidate
+            FROM (SELECT d + make_interval(hours => h, mins => m, secs => s) AS c
+                  FROM generate_series(period, period + unit - interval '1 day', interval '1 day') AS d,
+                       unnest(coalesce(hours, '{0}')) AS h,
+                       unnest(coalesce(minutes, '{0}')) AS m,
+                       unnest(coalesce(seconds, '{0}')) AS s
+                  WHERE dbms_job.calendar_day(d::date, months, monthdays, weekdays, nths, in_year)) AS candidates
+            -- a wall-clock time repeated when the clocks go back may map
+            -- before the date given
+            WHERE c::timestamp with time zone > after;
+            IF candidate IS NOT NULL THEN
+                RETURN candidate::timestamp with time zone;
+            END IF;
+            period := period + step * unit;
+            CONTINUE;
+        -- below a day, the period sets the hour, minute or second and the
+        -- clauses of these only filter the periods
+        ELSIF NOT dbms_job.calendar_day(period::date, months, monthdays, weekdays, nths, false) THEN
+            skip_to := date_trunc('day', period) + interval '1 day';
+        ELSIF hours IS NOT NULL AND NOT extract(hour FROM period)::integer = ANY(hours) THEN
+            skip_to := date_trunc('hour', period) + interval '1 hour';
+        ELSIF freq = 'HOURLY' THEN
+            SELECT min(c) INTO candidate
+            FROM (SELECT period + make_interval(mins => m, secs => s) AS c
+                  FROM unnest(coalesce(minutes, '{0}')) AS m, unnest(coalesce(seconds, '{0}')) AS s) AS candidates
+            WHERE c::timestamp with time zone > after;
+            IF candidate IS NOT NULL THEN
+                RETURN candidate::timestamp with time zone;
+            END IF;
+            skip_to := period + unit;
+        ELSIF minutes IS NOT NULL AND NOT extract(minute FROM period)::integer = ANY(minutes) THEN
+            skip_to := date_trunc('minute', period) + interval '1 minute';
+        ELSIF freq = 'MINUTELY' THEN
+            SELECT min(c) INTO candidate
+            FROM (SELECT period + make_interval(secs => s) AS c
+                  FROM unnest(coalesce(seconds, '{0}')) AS s) AS candidates
+            WHERE c::timestamp with time zone > after;
+            IF candidate IS NOT NULL THEN
+                RETURN candidate::timestamp with time zone;
+            END IF;
+            skip_to := period + unit;
+        ELSIF (seconds IS NULL OR extract(second FROM period)::integer = ANY(seconds))
+              AND period::timestamp with time zone > after THEN
+            RETURN period::timestamp with time zone;
+        ELSE
+            skip_to := period + unit;
+        END IF;
+        -- first period of the INTERVAL steps from here at or after skip_to
+        period := period + step * unit
+            * ceil(extract(epoch FROM skip_to - period) / extract(epoch FROM step * unit));
+    END LOOP;
+    RAISE EXCEPTION 'calendaring expression "%" matches no date', expr USING ERRCODE = '22023';
+END;
+$$;
+COMMENT ON FUNCTION dbms_job.calendar_next_date(text, timestamp with time zone)
+    IS 'First date after the given one matching an Oracle calendaring expression';
+
+-- Whether a job interval is an Oracle calendaring expression rather than a
+-- SQL expression: FREQ= then letters, digits and the = ; , + - signs.
+CREATE FUNCTION dbms_job.is_calendar(text)
+    RETURNS boolean
+    LANGUAGE SQL
+    IMMUTABLE
+    AS $$ SELECT $1 ~* '^\s*freq\s*=[a-z0-9=;,+\s-]*$' $$;
+COMMENT ON FUNCTION dbms_job.is_calendar(text)
+    IS 'Whether a job interval is an Oracle calendaring expression';
+
+CREATE OR REPLACE FUNCTION dbms_job.get_next_date(text)
+    RETURNS timestamp with time zone
+    LANGUAGE PLPGSQL
+    AS $$
+DECLARE
+    next_date timestamp with time zone;
+BEGIN
+	IF dbms_job.is_cron($1) THEN
+		RETURN dbms_job.cron_next_date($1);
+	END IF;
+	IF dbms_job.is_calendar($1) THEN
+		RETURN dbms_job.calendar_next_date($1);
+	END IF;
+	EXECUTE 'SELECT '||$1 INTO next_date;
+	RETURN next_date;
+END;
+$$;
+COMMENT ON FUNCTION dbms_job.get_next_date(text)
+    IS 'Used to get the next date returned by the interval code';
+
+CREATE OR REPLACE FUNCTION dbms_job.submit(
+		jobid         OUT   bigint,
+		what          IN    text,
+		next_date     IN    timestamp with time zone DEFAULT current_timestamp,
+		job_interval  IN    text DEFAULT NULL,
+		no_parse      IN    boolean DEFAULT false)
+    RETURNS bigint
+    LANGUAGE PLPGSQL
+    AS $$
+BEGIN
+    -- interval must be in the future
+    IF next_date < current_timestamp THEN
+        RAISE EXCEPTION 'next_date must be a time in the future: %', next_date USING ERRCODE = '23420';
+    END IF;
+    -- A cron or calendaring expression is checked now rather than when
+    -- the job is claimed
+    IF dbms_job.is_cron(job_interval) THEN
+        PERFORM dbms_job.cron_next_date(job_interval);
+    ELSIF dbms_job.is_calendar(job_interval) THEN
+        PERFORM dbms_job.calendar_next_date(job_interval);
+    END IF;
+    -- When an interval is defined this is a job to be scheduled
+    IF job_interval IS NOT NULL THEN
+        INSERT INTO dbms_job.all_scheduled_jobs (what,next_date,interval) VALUES ($2,$3,$4) RETURNING job INTO jobid;
+    ELSE
+	-- With no interval verify if the job is planned in
+	-- the future or that it must be executed immediatly
+        IF next_date > current_timestamp THEN
+            INSERT INTO dbms_job.all_scheduled_jobs (what,next_date,interval) VALUES ($2,$3,$4) RETURNING job INTO jobid;
+        ELSE
+            -- This is an immediate asynchronous execution, use the special queue
+            INSERT INTO dbms_job.all_async_jobs (what) VALUES ($2) RETURNING job INTO jobid;
+        END IF;
+    END IF;
+END;
+$$;
+COMMENT ON FUNCTION dbms_job.submit(text,timestamp with time zone,text,boolean)
+    IS 'Submits a new job to the job queue.';
+
+-- Version of the dbms_job objects, read by the scheduler to tell which
+-- migrations of --upgrade-schema apply when the schema was installed by
+-- --install-schema rather than as an extension.
+CREATE OR REPLACE FUNCTION dbms_job.schema_version()
+    RETURNS text
+    LANGUAGE SQL
+    IMMUTABLE
+    AS $$ SELECT '3.1.0'::text $$;
+COMMENT ON FUNCTION dbms_job.schema_version()
+    IS 'Version of the pg_dbms_job schema';
+
+-- The asynchronous job notification carries the ids of the submitted jobs.
+DROP TRIGGER dbms_job_async_notify_trg ON dbms_job.all_async_jobs;
+CREATE OR REPLACE FUNCTION dbms_job.job_async_notify()
+    RETURNS trigger
+    LANGUAGE PLPGSQL
+    AS $$
+DECLARE
+    jobs bigint[];
+BEGIN
+    -- When new asynchronous jobs are submitted, notify the scheduler with
+    -- their ids so that it only claims them. A larger batch is notified
+    -- with an empty payload, the scheduler then reads the whole queue.
+    SELECT array_agg(job ORDER BY job) INTO jobs FROM (SELECT job FROM new_jobs LIMIT 101) AS j;
+    IF jobs IS NULL THEN
+        RETURN NULL;
+    END IF;
+    IF cardinality(jobs) <= 100 THEN
+        PERFORM pg_notify('dbms_job_async_notify', json_build_object('jobs', jobs)::text);
+    ELSE
+        PERFORM pg_notify('dbms_job_async_notify', '');
+    END IF;
+    RETURN NULL;
+END;
+$$;
+COMMENT ON FUNCTION dbms_job.job_async_notify()
+    IS 'Notify the scheduler of the asynchronous jobs submitted';
+
+CREATE TRIGGER dbms_job_async_notify_trg
+    AFTER INSERT
+    ON dbms_job.all_async_jobs
+    REFERENCING NEW TABLE AS new_jobs
+    FOR STATEMENT EXECUTE FUNCTION dbms_job.job_async_notify();